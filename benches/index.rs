@@ -0,0 +1,99 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares consignment index backends to choose
+//! [`rgbstd::containers::HASH_INDEX_THRESHOLD`].
+//!
+//! Run with `cargo bench --bench index`.
+
+use amplify::confinement::Confined;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rgbstd::containers::{
+    IndexBackend, IndexedConsignment, PubWitness, Transfer, WitnessBundle, XPubWitness,
+};
+use rgbstd::validation::ConsignmentApi;
+use rgbstd::{BundleId, InputMap, OpId, Operation, Transition, Txid, Vin};
+use strict_encoding::StrictDumb;
+
+const SIZES: [usize; 8] = [16, 64, 128, 256, 512, 1024, 4096, 16384];
+
+/// Constructs transfer consignment with the given number of bundles, each
+/// containing a single state transition.
+fn consignment(bundles: usize) -> (Transfer, Vec<OpId>, Vec<BundleId>) {
+    let mut transfer = Transfer::strict_dumb();
+    let mut opids = Vec::with_capacity(bundles);
+    let mut bundle_ids = Vec::with_capacity(bundles);
+    for no in 0..bundles as u32 {
+        let mut transition = Transition::strict_dumb();
+        transition.nonce = no as u64;
+        let opid = transition.id();
+        let mut witness_bundle: WitnessBundle = WitnessBundle::strict_dumb();
+        let mut txid = [0u8; 32];
+        txid[..4].copy_from_slice(&no.to_le_bytes());
+        witness_bundle.pub_witness = XPubWitness::Bitcoin(PubWitness::new(Txid::from(txid)));
+        witness_bundle.bundle.input_map = InputMap::with(Vin::from_u32(no), opid);
+        witness_bundle.bundle.known_transitions = Confined::with((opid, transition));
+        opids.push(opid);
+        bundle_ids.push(witness_bundle.bundle.bundle_id());
+        transfer.bundles.push(witness_bundle).unwrap();
+    }
+    (transfer, opids, bundle_ids)
+}
+
+fn index_lookups(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lookups");
+    for size in SIZES {
+        let (transfer, opids, bundle_ids) = consignment(size);
+        for backend in [IndexBackend::BTree, IndexBackend::Hash] {
+            let index = IndexedConsignment::with_backend(&transfer, backend);
+            let id = BenchmarkId::new(format!("{backend:?}"), size);
+            group.bench_function(id, |b| {
+                b.iter(|| {
+                    for opid in &opids {
+                        black_box(index.op_witness_id(*opid));
+                    }
+                    for bundle_id in &bundle_ids {
+                        black_box(index.bundle(*bundle_id));
+                        black_box(index.anchor(*bundle_id));
+                    }
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn index_construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("construction");
+    for size in SIZES {
+        let (transfer, ..) = consignment(size);
+        for backend in [IndexBackend::BTree, IndexBackend::Hash] {
+            let id = BenchmarkId::new(format!("{backend:?}"), size);
+            group.bench_function(id, |b| {
+                b.iter(|| black_box(IndexedConsignment::with_backend(&transfer, backend)))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, index_lookups, index_construction);
+criterion_main!(benches);
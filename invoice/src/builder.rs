@@ -21,6 +21,7 @@
 
 use std::str::FromStr;
 
+use bp::seals::txout::CloseMethod;
 use rgb::ContractId;
 use strict_encoding::{FieldName, TypeName};
 
@@ -42,6 +43,7 @@ impl RgbInvoiceBuilder {
             beneficiary: beneficiary.into(),
             owned_state: InvoiceState::Void,
             expiry: None,
+            close_methods: none!(),
             unknown_query: none!(),
         })
     }
@@ -126,6 +128,21 @@ impl RgbInvoiceBuilder {
         self
     }
 
+    /// Adds seal close method to the list of methods accepted by the
+    /// beneficiary.
+    pub fn add_close_method(mut self, method: CloseMethod) -> Self {
+        if !self.0.close_methods.contains(&method) {
+            self.0.close_methods.push(method);
+        }
+        self
+    }
+
+    pub fn add_close_methods(self, methods: impl IntoIterator<Item = CloseMethod>) -> Self {
+        methods
+            .into_iter()
+            .fold(self, |builder, method| builder.add_close_method(method))
+    }
+
     fn drop_unspecified_transport(&mut self) {
         if self.0.transports.len() == 1 && self.0.transports[0] == RgbTransport::UnspecifiedMeans {
             self.0.transports = vec![];
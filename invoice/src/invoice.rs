@@ -254,6 +254,9 @@ pub struct RgbInvoice {
     pub owned_state: InvoiceState,
     /// UTC unix timestamp
     pub expiry: Option<i64>,
+    /// Seal close methods accepted by the beneficiary; any method is accepted
+    /// if empty.
+    pub close_methods: Vec<CloseMethod>,
     pub unknown_query: IndexMap<String, String>,
 }
 
@@ -262,4 +265,10 @@ impl RgbInvoice {
     pub fn address_network(&self) -> AddressNetwork { self.beneficiary.address_network() }
     pub fn layer1(&self) -> Layer1 { self.beneficiary.layer1() }
     pub fn is_prod(&self) -> bool { self.beneficiary.is_prod() }
+
+    /// Detects whether the beneficiary accepts commitments made with the
+    /// given seal close method.
+    pub fn accepts_close_method(&self, method: CloseMethod) -> bool {
+        self.close_methods.is_empty() || self.close_methods.contains(&method)
+    }
 }
@@ -25,6 +25,7 @@ use std::num::ParseIntError;
 use std::str::FromStr;
 
 use baid64::{Baid64ParseError, DisplayBaid64, FromBaid64Str};
+use bp::seals::txout::CloseMethod;
 use fluent_uri::enc::EStr;
 use fluent_uri::Uri;
 use indexmap::IndexMap;
@@ -40,6 +41,7 @@ use crate::invoice::{
 const OMITTED: &str = "~";
 const EXPIRY: &str = "expiry";
 const ENDPOINTS: &str = "endpoints";
+const METHODS: &str = "methods";
 const TRANSPORT_SEP: char = ',';
 const TRANSPORT_HOST_SEP: &str = "://";
 const QUERY_ENCODE: &AsciiSet = &CONTROLS
@@ -138,6 +140,7 @@ impl RgbInvoice {
     fn has_params(&self) -> bool {
         self.expiry.is_some()
             || self.transports != vec![RgbTransport::UnspecifiedMeans]
+            || !self.close_methods.is_empty()
             || !self.unknown_query.is_empty()
     }
 
@@ -153,6 +156,14 @@ impl RgbInvoice {
             }
             query_params.insert(ENDPOINTS.to_string(), transports.join(&TRANSPORT_SEP.to_string()));
         }
+        if !self.close_methods.is_empty() {
+            let methods = self
+                .close_methods
+                .iter()
+                .map(CloseMethod::to_string)
+                .collect::<Vec<_>>();
+            query_params.insert(METHODS.to_string(), methods.join(&TRANSPORT_SEP.to_string()));
+        }
         query_params.extend(self.unknown_query.clone());
         query_params
     }
@@ -424,6 +435,15 @@ impl FromStr for RgbInvoice {
             vec![RgbTransport::UnspecifiedMeans]
         };
 
+        let close_methods = match query_params.shift_remove(METHODS) {
+            Some(methods) => methods
+                .split(TRANSPORT_SEP)
+                .map(CloseMethod::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| InvoiceParseError::InvalidQueryParam(e.to_string()))?,
+            None => vec![],
+        };
+
         let mut expiry = None;
         if let Some(exp) = query_params.shift_remove(EXPIRY) {
             let timestamp = exp
@@ -441,6 +461,7 @@ impl FromStr for RgbInvoice {
             beneficiary,
             owned_state: value,
             expiry,
+            close_methods,
             unknown_query: query_params,
         })
     }
@@ -543,6 +564,21 @@ mod test {
         let result = RgbInvoice::from_str(invoice_str);
         assert!(matches!(result, Err(InvoiceParseError::InvalidExpiration(_))));
 
+        // with close methods
+        let invoice_str = "rgb:11Fa!$Dk-rUWXhy8-7H35qXm-pLGGLOo-txBWUgj-tbOaSbI/RGB20/BF+bc:utxob:\
+                           zlVS28Rb-amM5lih-ONXGACC-IUWD0Y$-0JXcnWZ-MQn8VEI-B39!F?\
+                           methods=tapret1st,opret1st";
+        let invoice = RgbInvoice::from_str(invoice_str).unwrap();
+        assert_eq!(invoice.close_methods, vec![CloseMethod::TapretFirst, CloseMethod::OpretFirst]);
+        assert!(invoice.accepts_close_method(CloseMethod::OpretFirst));
+        assert_eq!(invoice.to_string(), invoice_str);
+
+        // bad close method
+        let invoice_str = "rgb:11Fa!$Dk-rUWXhy8-7H35qXm-pLGGLOo-txBWUgj-tbOaSbI/RGB20/BF+bc:utxob:\
+                           zlVS28Rb-amM5lih-ONXGACC-IUWD0Y$-0JXcnWZ-MQn8VEI-B39!F?methods=tap";
+        let result = RgbInvoice::from_str(invoice_str);
+        assert!(matches!(result, Err(InvoiceParseError::InvalidQueryParam(_))));
+
         // with bad query parameter
         let invoice_str = "rgb:11Fa!$Dk-rUWXhy8-7H35qXm-pLGGLOo-txBWUgj-tbOaSbI/RGB20/BF+bc:utxob:\
                            zlVS28Rb-amM5lih-ONXGACC-IUWD0Y$-0JXcnWZ-MQn8VEI-B39!F?expiry";
@@ -0,0 +1,200 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use bp::{Sats, Txid};
+use rgb::vm::XWitnessTx;
+use rgb::{XChain, XWitnessId};
+use serde_json::Value;
+
+use super::{bitcoin_tx, BroadcastError, Broadcaster, JsonRpcClient, MempoolInfo};
+use crate::transport::{network_config, NetworkConfig};
+
+/// Bitcoin Core RPC error code for transactions absent from the mempool.
+const RPC_INVALID_ADDRESS_OR_KEY: i64 = -5;
+
+/// Broadcaster using Bitcoin Core JSON-RPC API.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct BitcoindBroadcaster<C: JsonRpcClient> {
+    client: C,
+}
+
+impl<C: JsonRpcClient> BitcoindBroadcaster<C> {
+    /// Constructs broadcaster configuring the client with the process-wide
+    /// network configuration.
+    pub fn new(client: C) -> Self { Self::with_network_config(client, &network_config()) }
+
+    /// Constructs broadcaster configuring the client with the provided
+    /// network configuration.
+    pub fn with_network_config(mut client: C, config: &NetworkConfig) -> Self {
+        client.set_network_config(config);
+        BitcoindBroadcaster { client }
+    }
+
+    pub fn client(&self) -> &C { &self.client }
+
+    pub fn into_client(self) -> C { self.client }
+}
+
+fn btc_to_sats<E: std::error::Error>(value: &Value) -> Result<Sats, BroadcastError<E>> {
+    value
+        .as_f64()
+        .filter(|btc| *btc >= 0.0)
+        .map(|btc| Sats::from_sats((btc * 100_000_000.0).round() as u64))
+        .ok_or_else(|| BroadcastError::InvalidResponse(value.to_string()))
+}
+
+impl<C: JsonRpcClient> Broadcaster for BitcoindBroadcaster<C> {
+    type Error = BroadcastError<C::Error>;
+
+    fn broadcast(&self, tx: &XWitnessTx) -> Result<XWitnessId, Self::Error> {
+        let tx = bitcoin_tx(tx)?;
+        let txid = self
+            .client
+            .call("sendrawtransaction", vec![Value::String(tx.to_string())])
+            .map_err(BroadcastError::Client)?
+            .map_err(|err| BroadcastError::Rejected(err.message))?;
+        match txid.as_str().map(Txid::from_str) {
+            Some(Ok(txid)) if txid == tx.txid() => Ok(XChain::Bitcoin(txid)),
+            _ => Err(BroadcastError::InvalidResponse(txid.to_string())),
+        }
+    }
+
+    fn mempool_info(&self, witness_id: XWitnessId) -> Result<Option<MempoolInfo>, Self::Error> {
+        let XChain::Bitcoin(txid) = witness_id else {
+            return Err(BroadcastError::UnsupportedLayer(witness_id.layer1()));
+        };
+        let txid = Value::String(txid.to_string());
+
+        let entry = match self
+            .client
+            .call("getmempoolentry", vec![txid.clone()])
+            .map_err(BroadcastError::Client)?
+        {
+            Ok(entry) => entry,
+            Err(err) if err.code == RPC_INVALID_ADDRESS_OR_KEY => return Ok(None),
+            Err(err) => return Err(BroadcastError::InvalidResponse(err.to_string())),
+        };
+        let size = |field: &str| {
+            entry[field]
+                .as_u64()
+                .and_then(|size| u32::try_from(size).ok())
+                .ok_or_else(|| BroadcastError::InvalidResponse(entry.to_string()))
+        };
+        let vsize = size("vsize")?;
+        let ancestor_vsize = size("ancestorsize")?;
+        let fee = btc_to_sats(&entry["fees"]["base"])?;
+        let ancestor_fee = btc_to_sats(&entry["fees"]["ancestor"])?;
+
+        let ancestors = self
+            .client
+            .call("getmempoolancestors", vec![txid])
+            .map_err(BroadcastError::Client)?
+            .map_err(|err| BroadcastError::InvalidResponse(err.to_string()))?;
+        let ancestors = ancestors
+            .as_array()
+            .and_then(|list| {
+                list.iter()
+                    .map(|txid| txid.as_str().and_then(|s| Txid::from_str(s).ok()))
+                    .collect::<Option<BTreeSet<_>>>()
+            })
+            .ok_or_else(|| BroadcastError::InvalidResponse(ancestors.to_string()))?;
+
+        Ok(Some(MempoolInfo {
+            fee,
+            vsize,
+            ancestors,
+            ancestor_fee,
+            ancestor_vsize,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+
+    use bp::Outpoint;
+    use rgb::Layer1;
+    use serde_json::json;
+
+    use super::*;
+    use crate::broadcast::test::tx;
+    use crate::broadcast::RpcError;
+    use crate::transport::NetworkAware;
+
+    struct Node;
+
+    impl NetworkAware for Node {
+        fn set_network_config(&mut self, _: &NetworkConfig) {}
+    }
+
+    impl JsonRpcClient for Node {
+        type Error = Infallible;
+
+        fn call(
+            &self,
+            method: &str,
+            params: Vec<Value>,
+        ) -> Result<Result<Value, RpcError>, Self::Error> {
+            let txid = params[0].as_str().unwrap();
+            Ok(match method {
+                "sendrawtransaction" => Ok(json!(bp::Tx::from_str(txid).unwrap().txid())),
+                "getmempoolentry" if txid.starts_with("00") => Err(RpcError {
+                    code: RPC_INVALID_ADDRESS_OR_KEY,
+                    message: s!("Transaction not in mempool"),
+                }),
+                "getmempoolentry" => Ok(json!({
+                    "vsize": 110,
+                    "ancestorsize": 250,
+                    "fees": { "base": 0.00001100, "ancestor": 0.00002000 }
+                })),
+                "getmempoolancestors" => Ok(json!([Txid::from([1u8; 32])])),
+                _ => unreachable!(),
+            })
+        }
+    }
+
+    #[test]
+    fn bitcoind() {
+        let broadcaster = BitcoindBroadcaster::new(Node);
+        let tx = tx(&[Outpoint::new(Txid::from([1u8; 32]), 0)], &[1000]);
+        let txid = tx.txid();
+        assert_eq!(broadcaster.broadcast(&XChain::Bitcoin(tx.clone())), Ok(XChain::Bitcoin(txid)));
+        assert_eq!(
+            broadcaster.broadcast(&XChain::Liquid(tx)),
+            Err(BroadcastError::UnsupportedLayer(Layer1::Liquid))
+        );
+
+        let info = broadcaster
+            .mempool_info(XChain::Bitcoin(Txid::from([0xFFu8; 32])))
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.fee, Sats::from_sats(1100u64));
+        assert_eq!(info.ancestor_fee, Sats::from_sats(2000u64));
+        assert_eq!(info.fee_rate(), 10.0);
+        assert_eq!(info.ancestor_fee_rate(), 8.0);
+        assert_eq!(info.ancestors, bset![Txid::from([1u8; 32])]);
+        assert_eq!(broadcaster.mempool_info(XChain::Bitcoin(Txid::from([0u8; 32]))), Ok(None));
+    }
+}
@@ -0,0 +1,272 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use amplify::hex::ToHex;
+use bp::{Tx, Txid};
+use commit_verify::{DigestExt, Sha256};
+use rgb::vm::XWitnessTx;
+use rgb::{Layer1, XChain, XWitnessId};
+use serde_json::Value;
+
+use super::{BroadcastError, Broadcaster, JsonRpcClient, MempoolInfo, RpcError};
+use crate::transport::{network_config, NetworkConfig};
+
+/// Fragments of error messages used by Electrum servers (or the nodes backing
+/// them) to report transactions which are not known.
+const NOT_FOUND_MESSAGES: [&str; 3] =
+    ["no such mempool or blockchain transaction", "transaction not found", "missing transaction"];
+
+fn is_not_found(err: &RpcError) -> bool {
+    let message = err.message.to_lowercase();
+    NOT_FOUND_MESSAGES.iter().any(|m| message.contains(m))
+}
+
+/// Broadcaster using Electrum server protocol.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ElectrumBroadcaster<C: JsonRpcClient> {
+    client: C,
+    layer1: Layer1,
+}
+
+impl<C: JsonRpcClient> ElectrumBroadcaster<C> {
+    /// Constructs broadcaster configuring the client with the process-wide
+    /// network configuration.
+    pub fn new(client: C, layer1: Layer1) -> Self {
+        Self::with_network_config(client, layer1, &network_config())
+    }
+
+    /// Constructs broadcaster configuring the client with the provided
+    /// network configuration.
+    pub fn with_network_config(mut client: C, layer1: Layer1, config: &NetworkConfig) -> Self {
+        client.set_network_config(config);
+        ElectrumBroadcaster { client, layer1 }
+    }
+
+    pub fn client(&self) -> &C { &self.client }
+
+    pub fn into_client(self) -> C { self.client }
+
+    fn fetch(&self, txid: Txid) -> Result<Option<(Tx, bool)>, BroadcastError<C::Error>> {
+        let res = self
+            .client
+            .call("blockchain.transaction.get", vec![txid.to_string().into(), true.into()])
+            .map_err(BroadcastError::Client)?;
+        let info = match res {
+            Ok(info) => info,
+            // Electrum servers report unknown transactions with an error
+            Err(err) if is_not_found(&err) => return Ok(None),
+            // Some servers (like electrs) don't support verbose transaction
+            // information
+            Err(_) => return self.fetch_raw(txid),
+        };
+        let tx = info["hex"]
+            .as_str()
+            .and_then(|hex| Tx::from_str(hex).ok())
+            .ok_or_else(|| BroadcastError::InvalidResponse(info.to_string()))?;
+        let confirmed = info["confirmations"].as_u64().unwrap_or_default() > 0;
+        Ok(Some((tx, confirmed)))
+    }
+
+    /// Fetches transaction without the verbose information, detecting whether
+    /// it is confirmed from the history of its first output script.
+    fn fetch_raw(&self, txid: Txid) -> Result<Option<(Tx, bool)>, BroadcastError<C::Error>> {
+        let hex = match self
+            .client
+            .call("blockchain.transaction.get", vec![txid.to_string().into(), false.into()])
+            .map_err(BroadcastError::Client)?
+        {
+            Ok(hex) => hex,
+            Err(err) if is_not_found(&err) => return Ok(None),
+            Err(err) => return Err(BroadcastError::InvalidResponse(err.to_string())),
+        };
+        let tx = hex
+            .as_str()
+            .and_then(|hex| Tx::from_str(hex).ok())
+            .ok_or_else(|| BroadcastError::InvalidResponse(hex.to_string()))?;
+        let script = &tx
+            .outputs
+            .first()
+            .ok_or_else(|| {
+                BroadcastError::InvalidResponse(format!("transaction {txid} has no outputs"))
+            })?
+            .script_pubkey;
+
+        let mut hasher = Sha256::default();
+        hasher.input_raw(script.as_slice());
+        let mut script_hash = hasher.finish();
+        script_hash.reverse();
+        let history = self
+            .client
+            .call("blockchain.scripthash.get_history", vec![script_hash.to_hex().into()])
+            .map_err(BroadcastError::Client)?
+            .map_err(|err| BroadcastError::InvalidResponse(err.to_string()))?;
+        let txid = txid.to_string();
+        let height = history
+            .as_array()
+            .and_then(|list| {
+                list.iter()
+                    .find(|entry| entry["tx_hash"].as_str() == Some(txid.as_str()))
+            })
+            .and_then(|entry| entry["height"].as_i64())
+            .ok_or_else(|| BroadcastError::InvalidResponse(history.to_string()))?;
+        // Mempool transactions have zero or negative height
+        Ok(Some((tx, height > 0)))
+    }
+}
+
+impl<C: JsonRpcClient> Broadcaster for ElectrumBroadcaster<C> {
+    type Error = BroadcastError<C::Error>;
+
+    fn broadcast(&self, tx: &XWitnessTx) -> Result<XWitnessId, Self::Error> {
+        if tx.layer1() != self.layer1 {
+            return Err(BroadcastError::UnsupportedLayer(tx.layer1()));
+        }
+        let tx = tx.as_reduced_unsafe();
+        let txid = self
+            .client
+            .call("blockchain.transaction.broadcast", vec![Value::String(tx.to_string())])
+            .map_err(BroadcastError::Client)?
+            .map_err(|err| BroadcastError::Rejected(err.message))?;
+        match txid.as_str().map(Txid::from_str) {
+            Some(Ok(txid)) if txid == tx.txid() => Ok(XChain::with(self.layer1, txid)),
+            _ => Err(BroadcastError::InvalidResponse(txid.to_string())),
+        }
+    }
+
+    fn mempool_info(&self, witness_id: XWitnessId) -> Result<Option<MempoolInfo>, Self::Error> {
+        if witness_id.layer1() != self.layer1 {
+            return Err(BroadcastError::UnsupportedLayer(witness_id.layer1()));
+        }
+        MempoolInfo::collect(*witness_id.as_reduced_unsafe(), |txid| self.fetch(txid))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::convert::Infallible;
+
+    use bp::{Outpoint, Sats};
+    use serde_json::json;
+
+    use super::*;
+    use crate::broadcast::test::tx;
+    use crate::transport::NetworkAware;
+
+    struct Server {
+        verbose: bool,
+        txs: BTreeMap<String, (Tx, i64)>,
+    }
+
+    impl NetworkAware for Server {
+        fn set_network_config(&mut self, _: &NetworkConfig) {}
+    }
+
+    impl JsonRpcClient for Server {
+        type Error = Infallible;
+
+        fn call(
+            &self,
+            method: &str,
+            params: Vec<Value>,
+        ) -> Result<Result<Value, RpcError>, Self::Error> {
+            let arg = params[0].as_str().unwrap();
+            let not_found = RpcError {
+                code: 2,
+                message: s!("daemon error: No such mempool or blockchain transaction."),
+            };
+            Ok(match method {
+                "blockchain.transaction.get" if params[1] == json!(true) && !self.verbose => {
+                    Err(RpcError {
+                        code: 1,
+                        message: s!("verbose transactions are currently unsupported"),
+                    })
+                }
+                "blockchain.transaction.get" => match self.txs.get(arg) {
+                    None => Err(not_found),
+                    Some((tx, _)) if params[1] == json!(false) => Ok(json!(tx.to_string())),
+                    Some((tx, height)) => Ok(json!({
+                        "hex": tx.to_string(),
+                        "confirmations": if *height > 0 { 1 } else { 0 },
+                    })),
+                },
+                "blockchain.scripthash.get_history" => Ok(Value::Array(
+                    self.txs
+                        .iter()
+                        .map(|(txid, (_, height))| json!({ "tx_hash": txid, "height": height }))
+                        .collect(),
+                )),
+                _ => unreachable!(),
+            })
+        }
+    }
+
+    #[test]
+    fn electrum() {
+        let funding = tx(&[Outpoint::new(Txid::from([9u8; 32]), 0)], &[10_000]);
+        let child = tx(&[Outpoint::new(funding.txid(), 0)], &[9_000]);
+        for verbose in [true, false] {
+            let server = Server {
+                verbose,
+                txs: bmap! {
+                    funding.txid().to_string() => (funding.clone(), 100),
+                    child.txid().to_string() => (child.clone(), 0),
+                },
+            };
+            let broadcaster = ElectrumBroadcaster::new(server, Layer1::Bitcoin);
+            let info = broadcaster
+                .mempool_info(XChain::Bitcoin(child.txid()))
+                .unwrap()
+                .unwrap();
+            assert_eq!(info.fee, Sats::from_sats(1_000u64));
+            assert!(info.ancestors.is_empty());
+            assert_eq!(broadcaster.mempool_info(XChain::Bitcoin(funding.txid())), Ok(None));
+            assert_eq!(broadcaster.mempool_info(XChain::Bitcoin(Txid::from([1u8; 32]))), Ok(None));
+        }
+    }
+
+    #[test]
+    fn electrum_errors() {
+        struct Failing;
+        impl NetworkAware for Failing {
+            fn set_network_config(&mut self, _: &NetworkConfig) {}
+        }
+        impl JsonRpcClient for Failing {
+            type Error = Infallible;
+            fn call(&self, _: &str, _: Vec<Value>) -> Result<Result<Value, RpcError>, Infallible> {
+                Ok(Err(RpcError {
+                    code: -32603,
+                    message: s!("internal error"),
+                }))
+            }
+        }
+
+        // Errors other than unknown transaction must not be reported as an
+        // absent transaction
+        let broadcaster = ElectrumBroadcaster::new(Failing, Layer1::Bitcoin);
+        assert!(matches!(
+            broadcaster.mempool_info(XChain::Bitcoin(Txid::from([1u8; 32]))),
+            Err(BroadcastError::InvalidResponse(_))
+        ));
+    }
+}
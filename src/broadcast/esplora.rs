@@ -0,0 +1,126 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use bp::{Tx, Txid};
+use rgb::vm::XWitnessTx;
+use rgb::{Layer1, XChain, XWitnessId};
+use serde_json::Value;
+
+use super::{BroadcastError, Broadcaster, HttpClient, MempoolInfo};
+use crate::transport::{network_config, NetworkConfig};
+
+/// Broadcaster using Esplora HTTP API.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct EsploraBroadcaster<C: HttpClient> {
+    client: C,
+    url: String,
+    layer1: Layer1,
+}
+
+impl<C: HttpClient> EsploraBroadcaster<C> {
+    /// Constructs broadcaster for the Esplora server at the given base URL
+    /// (like `https://blockstream.info/api`), configuring the client with the
+    /// process-wide network configuration.
+    pub fn new(client: C, url: impl ToString, layer1: Layer1) -> Self {
+        Self::with_network_config(client, url, layer1, &network_config())
+    }
+
+    /// Constructs broadcaster configuring the client with the provided
+    /// network configuration.
+    pub fn with_network_config(
+        mut client: C,
+        url: impl ToString,
+        layer1: Layer1,
+        config: &NetworkConfig,
+    ) -> Self {
+        client.set_network_config(config);
+        EsploraBroadcaster {
+            client,
+            url: url.to_string().trim_end_matches('/').to_owned(),
+            layer1,
+        }
+    }
+
+    pub fn client(&self) -> &C { &self.client }
+
+    pub fn into_client(self) -> C { self.client }
+
+    fn get(&self, path: &str) -> Result<Option<String>, BroadcastError<C::Error>> {
+        let (status, body) = self
+            .client
+            .get(&format!("{}{path}", self.url))
+            .map_err(BroadcastError::Client)?;
+        match status {
+            200 => Ok(Some(body)),
+            404 => Ok(None),
+            _ => Err(BroadcastError::InvalidResponse(format!("HTTP status {status}: {body}"))),
+        }
+    }
+
+    fn fetch(&self, txid: Txid) -> Result<Option<(Tx, bool)>, BroadcastError<C::Error>> {
+        let Some(hex) = self.get(&format!("/tx/{txid}/hex"))? else {
+            return Ok(None);
+        };
+        let tx = Tx::from_str(hex.trim())
+            .map_err(|err| BroadcastError::InvalidResponse(err.to_string()))?;
+        let status = self
+            .get(&format!("/tx/{txid}/status"))?
+            .ok_or_else(|| BroadcastError::InvalidResponse(s!("missed transaction status")))?;
+        let confirmed = serde_json::from_str::<Value>(&status)
+            .ok()
+            .and_then(|status| status["confirmed"].as_bool())
+            .ok_or_else(|| BroadcastError::InvalidResponse(status))?;
+        Ok(Some((tx, confirmed)))
+    }
+}
+
+impl<C: HttpClient> Broadcaster for EsploraBroadcaster<C> {
+    type Error = BroadcastError<C::Error>;
+
+    fn broadcast(&self, tx: &XWitnessTx) -> Result<XWitnessId, Self::Error> {
+        if tx.layer1() != self.layer1 {
+            return Err(BroadcastError::UnsupportedLayer(tx.layer1()));
+        }
+        let tx = tx.as_reduced_unsafe();
+        let (status, body) = self
+            .client
+            .post(&format!("{}/tx", self.url), tx.to_string())
+            .map_err(BroadcastError::Client)?;
+        if status != 200 {
+            return Err(BroadcastError::Rejected(body));
+        }
+        let txid = Txid::from_str(body.trim())
+            .map_err(|_| BroadcastError::InvalidResponse(body.clone()))?;
+        if txid != tx.txid() {
+            return Err(BroadcastError::InvalidResponse(body));
+        }
+        Ok(XChain::with(self.layer1, txid))
+    }
+
+    fn mempool_info(&self, witness_id: XWitnessId) -> Result<Option<MempoolInfo>, Self::Error> {
+        if witness_id.layer1() != self.layer1 {
+            return Err(BroadcastError::UnsupportedLayer(witness_id.layer1()));
+        }
+        MempoolInfo::collect(*witness_id.as_reduced_unsafe(), |txid| self.fetch(txid))
+    }
+}
@@ -0,0 +1,370 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Broadcasting of witness transactions.
+//!
+//! The library doesn't perform network I/O itself: [`Broadcaster`]
+//! implementations for Electrum, Esplora and Bitcoin Core RPC servers operate
+//! on top of a client provided by the wallet, which owns network connections
+//! and must respect the [`NetworkConfig`] it is configured with.
+//!
+//! [`NetworkConfig`]: crate::transport::NetworkConfig
+
+#[cfg(feature = "serde")]
+mod electrum;
+#[cfg(feature = "serde")]
+mod esplora;
+#[cfg(feature = "serde")]
+mod bitcoind;
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::error::Error;
+
+use bp::{Sats, Tx, Txid, Weight};
+use rgb::vm::XWitnessTx;
+use rgb::{Layer1, XWitnessId};
+
+#[cfg(feature = "serde")]
+pub use bitcoind::BitcoindBroadcaster;
+#[cfg(feature = "serde")]
+pub use electrum::ElectrumBroadcaster;
+#[cfg(feature = "serde")]
+pub use esplora::EsploraBroadcaster;
+
+use crate::transport::NetworkAware;
+
+/// Maximal number of unconfirmed ancestors analyzed for a transaction, matching
+/// the default Bitcoin Core mempool policy.
+pub const MAX_MEMPOOL_ANCESTORS: usize = 25;
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum BroadcastError<E: Error> {
+    /// broadcaster client error: {0}
+    Client(E),
+
+    /// transaction was rejected by the server: {0}
+    Rejected(String),
+
+    /// server returned invalid response: {0}
+    InvalidResponse(String),
+
+    /// broadcaster doesn't support {0} transactions.
+    UnsupportedLayer(Layer1),
+}
+
+/// Information about a transaction present in the mempool.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct MempoolInfo {
+    pub fee: Sats,
+    pub vsize: u32,
+    /// Unconfirmed transactions which must be mined before or together with
+    /// the transaction.
+    pub ancestors: BTreeSet<Txid>,
+    /// Fees of the transaction and all of its unconfirmed ancestors.
+    pub ancestor_fee: Sats,
+    /// Size of the transaction and all of its unconfirmed ancestors.
+    pub ancestor_vsize: u32,
+}
+
+impl MempoolInfo {
+    /// Fee rate of the transaction, in sats per vbyte.
+    pub fn fee_rate(&self) -> f64 { self.fee.sats() as f64 / self.vsize.max(1) as f64 }
+
+    /// Fee rate of the transaction package including all of its unconfirmed
+    /// ancestors, in sats per vbyte. This is the fee rate the transaction is
+    /// effectively mined with.
+    pub fn ancestor_fee_rate(&self) -> f64 {
+        self.ancestor_fee.sats() as f64 / self.ancestor_vsize.max(1) as f64
+    }
+
+    /// Computes mempool information using a data source which provides only
+    /// raw transactions and their confirmation status, like Electrum or
+    /// Esplora servers.
+    ///
+    /// The `fetch` function returns transaction with the given id and whether
+    /// it is confirmed, or `None` if the transaction is not known.
+    pub fn collect<E: Error>(
+        txid: Txid,
+        mut fetch: impl FnMut(Txid) -> Result<Option<(Tx, bool)>, BroadcastError<E>>,
+    ) -> Result<Option<Self>, BroadcastError<E>> {
+        let Some((tx, confirmed)) = fetch(txid)? else {
+            return Ok(None);
+        };
+        if confirmed {
+            return Ok(None);
+        }
+
+        let mut cache = BTreeMap::<Txid, (Tx, bool)>::new();
+        let mut fetch_cached = |txid: Txid| -> Result<(Tx, bool), BroadcastError<E>> {
+            if let Some(entry) = cache.get(&txid) {
+                return Ok(entry.clone());
+            }
+            let entry = fetch(txid)?.ok_or_else(|| {
+                BroadcastError::InvalidResponse(format!("unknown transaction {txid}"))
+            })?;
+            cache.insert(txid, entry.clone());
+            Ok(entry)
+        };
+
+        let mut ancestors = BTreeSet::new();
+        let mut queue = VecDeque::from([tx.clone()]);
+        let mut fee = None;
+        let mut ancestor_fee = Sats::ZERO;
+        let mut ancestor_vsize = 0u32;
+        while let Some(tx) = queue.pop_front() {
+            let mut inputs = Sats::ZERO;
+            for txin in tx.inputs() {
+                let prevout = txin.prev_output;
+                let (prev_tx, prev_confirmed) = fetch_cached(prevout.txid)?;
+                let value = prev_tx
+                    .outputs
+                    .get(prevout.vout_usize())
+                    .ok_or_else(|| {
+                        BroadcastError::InvalidResponse(format!("missed previous output {prevout}"))
+                    })?
+                    .value;
+                inputs.saturating_add_assign(value);
+                if !prev_confirmed
+                    && ancestors.len() < MAX_MEMPOOL_ANCESTORS
+                    && ancestors.insert(prevout.txid)
+                {
+                    queue.push_back(prev_tx);
+                }
+            }
+            let outputs = tx
+                .outputs()
+                .fold(Sats::ZERO, |sum, txout| sum.saturating_add(txout.value));
+            let tx_fee = inputs.saturating_sub(outputs);
+            fee.get_or_insert(tx_fee);
+            ancestor_fee.saturating_add_assign(tx_fee);
+            ancestor_vsize += tx.vbytes().into_u32();
+        }
+
+        Ok(Some(Self {
+            fee: fee.unwrap_or_default(),
+            vsize: tx.vbytes().into_u32(),
+            ancestors,
+            ancestor_fee,
+            ancestor_vsize,
+        }))
+    }
+}
+
+/// Fee and ancestry analysis of a witness transaction, allowing receivers to
+/// judge the likelihood of the unconfirmed witness being mined.
+#[derive(Clone, PartialEq, Debug)]
+pub struct WitnessAnalysis {
+    pub witness_id: XWitnessId,
+    /// Mempool information, or `None` if the witness is already mined or
+    /// unknown to the broadcaster.
+    pub mempool: Option<MempoolInfo>,
+    /// Error returned by the broadcaster, if the analysis has failed.
+    pub error: Option<String>,
+}
+
+impl WitnessAnalysis {
+    /// Analyzes witness using the mempool information from the broadcaster.
+    ///
+    /// Broadcaster errors don't fail the analysis and are reported in
+    /// [`WitnessAnalysis::error`].
+    pub fn analyze(broadcaster: &impl Broadcaster, witness_id: XWitnessId) -> Self {
+        let (mempool, error) = match broadcaster.mempool_info(witness_id) {
+            Ok(mempool) => (mempool, None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        Self {
+            witness_id,
+            mempool,
+            error,
+        }
+    }
+
+    /// Detects whether the witness is known to be unconfirmed.
+    pub fn is_unconfirmed(&self) -> bool { self.mempool.is_some() }
+
+    /// Effective fee rate of the unconfirmed witness, in sats per vbyte,
+    /// which accounts for its unconfirmed ancestors.
+    pub fn fee_rate(&self) -> Option<f64> {
+        self.mempool.as_ref().map(MempoolInfo::ancestor_fee_rate)
+    }
+
+    /// Number of unconfirmed ancestors of the witness, which bounds the
+    /// depth of the unconfirmed transaction chain.
+    pub fn ancestor_count(&self) -> usize {
+        self.mempool
+            .as_ref()
+            .map(|info| info.ancestors.len())
+            .unwrap_or_default()
+    }
+}
+
+/// Service broadcasting witness transactions and providing mempool
+/// information about them.
+pub trait Broadcaster {
+    type Error: Error;
+
+    /// Broadcasts transaction to the network, returning its id.
+    fn broadcast(&self, tx: &XWitnessTx) -> Result<XWitnessId, Self::Error>;
+
+    /// Returns mempool information for the transaction, or `None` if the
+    /// transaction is either already mined or unknown.
+    fn mempool_info(&self, witness_id: XWitnessId) -> Result<Option<MempoolInfo>, Self::Error>;
+}
+
+impl<B: Broadcaster> Broadcaster for &B {
+    type Error = B::Error;
+
+    fn broadcast(&self, tx: &XWitnessTx) -> Result<XWitnessId, Self::Error> {
+        (*self).broadcast(tx)
+    }
+
+    fn mempool_info(&self, witness_id: XWitnessId) -> Result<Option<MempoolInfo>, Self::Error> {
+        (*self).mempool_info(witness_id)
+    }
+}
+
+/// Client performing HTTP requests, used by [`EsploraBroadcaster`].
+pub trait HttpClient: NetworkAware {
+    type Error: Error;
+
+    /// Performs GET request, returning HTTP status code and response body.
+    fn get(&self, url: &str) -> Result<(u16, String), Self::Error>;
+
+    /// Performs POST request, returning HTTP status code and response body.
+    fn post(&self, url: &str, body: String) -> Result<(u16, String), Self::Error>;
+}
+
+/// Error object returned by a JSON-RPC server.
+#[derive(Clone, Eq, PartialEq, Debug, Display)]
+#[display("RPC error {code}: {message}")]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// Client performing JSON-RPC calls, used by [`ElectrumBroadcaster`] and
+/// [`BitcoindBroadcaster`].
+#[cfg(feature = "serde")]
+pub trait JsonRpcClient: NetworkAware {
+    type Error: Error;
+
+    /// Performs the call, returning either its result or an error object
+    /// returned by the server.
+    fn call(
+        &self,
+        method: &str,
+        params: Vec<serde_json::Value>,
+    ) -> Result<Result<serde_json::Value, RpcError>, Self::Error>;
+}
+
+#[cfg(feature = "serde")]
+fn bitcoin_tx<E: Error>(tx: &XWitnessTx) -> Result<&Tx, BroadcastError<E>> {
+    match tx {
+        XWitnessTx::Bitcoin(tx) => Ok(tx),
+        other => Err(BroadcastError::UnsupportedLayer(other.layer1())),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::convert::Infallible;
+
+    use bp::{LockTime, Outpoint, SeqNo, TxIn, TxOut, TxVer, VarIntArray, Witness};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    pub fn tx(inputs: &[Outpoint], values: &[u64]) -> Tx {
+        Tx {
+            version: TxVer::V2,
+            inputs: VarIntArray::from_iter_checked(inputs.iter().map(|prevout| TxIn {
+                prev_output: *prevout,
+                sig_script: none!(),
+                sequence: SeqNo::ZERO,
+                witness: Witness::new(),
+            })),
+            outputs: VarIntArray::from_iter_checked(
+                values
+                    .iter()
+                    .map(|value| TxOut::new(bp::ScriptPubkey::strict_dumb(), *value)),
+            ),
+            lock_time: LockTime::ZERO,
+        }
+    }
+
+    #[test]
+    fn mempool_info() {
+        let funding = tx(&[], &[10_000, 5_000]);
+        let parent = tx(&[Outpoint::new(funding.txid(), 0)], &[9_000]);
+        let child =
+            tx(&[Outpoint::new(parent.txid(), 0), Outpoint::new(funding.txid(), 1)], &[12_000]);
+        let txs = [(funding.clone(), true), (parent.clone(), false), (child.clone(), false)]
+            .into_iter()
+            .map(|(tx, confirmed)| (tx.txid(), (tx, confirmed)))
+            .collect::<BTreeMap<_, _>>();
+        let fetch = |txid| Ok::<_, BroadcastError<Infallible>>(txs.get(&txid).cloned());
+
+        let info = MempoolInfo::collect(child.txid(), fetch).unwrap().unwrap();
+        assert_eq!(info.fee, Sats::from_sats(2_000u64));
+        assert_eq!(info.ancestors, bset![parent.txid()]);
+        assert_eq!(info.ancestor_fee, Sats::from_sats(3_000u64));
+        assert_eq!(info.ancestor_vsize, child.vbytes().into_u32() + parent.vbytes().into_u32());
+        assert!(info.ancestor_fee_rate() < info.fee_rate());
+
+        assert_eq!(MempoolInfo::collect(funding.txid(), fetch).unwrap(), None);
+        assert_eq!(MempoolInfo::collect(Txid::strict_dumb(), fetch).unwrap(), None);
+    }
+
+    #[test]
+    fn witness_analysis() {
+        struct MockBroadcaster(Option<MempoolInfo>);
+        impl Broadcaster for MockBroadcaster {
+            type Error = BroadcastError<Infallible>;
+            fn broadcast(&self, _: &XWitnessTx) -> Result<XWitnessId, Self::Error> {
+                Err(BroadcastError::Rejected(s!("mock")))
+            }
+            fn mempool_info(&self, _: XWitnessId) -> Result<Option<MempoolInfo>, Self::Error> {
+                self.0
+                    .clone()
+                    .ok_or(BroadcastError::InvalidResponse(s!("mock")))
+                    .map(Some)
+            }
+        }
+
+        let witness_id = XWitnessId::Bitcoin(Txid::strict_dumb());
+        let info = MempoolInfo {
+            fee: Sats::from_sats(1_000u64),
+            vsize: 100,
+            ancestors: bset![Txid::from([1u8; 32]), Txid::from([2u8; 32])],
+            ancestor_fee: Sats::from_sats(1_500u64),
+            ancestor_vsize: 300,
+        };
+        let analysis = WitnessAnalysis::analyze(&MockBroadcaster(Some(info)), witness_id);
+        assert!(analysis.is_unconfirmed());
+        assert_eq!(analysis.fee_rate(), Some(5.0));
+        assert_eq!(analysis.ancestor_count(), 2);
+
+        let analysis = WitnessAnalysis::analyze(&MockBroadcaster(None), witness_id);
+        assert!(!analysis.is_unconfirmed());
+        assert!(analysis.error.is_some());
+    }
+}
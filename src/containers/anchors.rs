@@ -20,19 +20,85 @@
 // limitations under the License.
 
 use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
 
 use amplify::ByteArray;
 use bp::dbc::opret::OpretProof;
 use bp::dbc::tapret::TapretProof;
-use bp::dbc::{anchor, Anchor};
-use bp::{Tx, Txid};
+use bp::dbc::{anchor, Anchor, DbcMethod};
+use bp::{dbc, Tx, Txid};
 use commit_verify::mpc;
 use rgb::validation::DbcProof;
-use rgb::{BundleId, DiscloseHash, TransitionBundle, XChain, XWitnessId};
+use rgb::{BundleId, ContractId, DiscloseHash, Layer1, TransitionBundle, XChain, XWitnessId};
 use strict_encoding::StrictDumb;
 
 use crate::{MergeReveal, MergeRevealError, LIB_NAME_RGB_STD};
 
+/// Errors verifying an anchor against a witness transaction.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum AnchorError {
+    /// anchor contains invalid multi-protocol commitment proof for contract
+    /// {0}.
+    InvalidProof(ContractId),
+
+    /// anchor doesn't contain a commitment for contract {0}.
+    UnknownContract(ContractId),
+
+    /// witness transaction doesn't contain the anchor commitment: {0}
+    Dbc(String),
+}
+
+/// Computes multi-protocol commitment produced by the anchor for the bundle
+/// of the contract.
+///
+/// The anchor commits to the bundle only if the produced commitment is the one
+/// contained in the witness transaction, which is checked by
+/// [`verify_anchor`].
+pub fn mpc_commitment<D: dbc::Proof<M>, M: DbcMethod>(
+    anchor: &Anchor<mpc::MerkleProof, D, M>,
+    contract_id: ContractId,
+    bundle_id: BundleId,
+) -> Result<mpc::Commitment, AnchorError> {
+    anchor
+        .convolve(contract_id, mpc::Message::from(bundle_id))
+        .map_err(|_| AnchorError::InvalidProof(contract_id))
+}
+
+/// Verifies that the raw witness transaction contains the anchor commitment
+/// (as a tapret proof or an opret output, depending on the anchor method), and
+/// that the anchor commits to the bundle under the contract.
+///
+/// Returns the verified multi-protocol commitment. The function doesn't
+/// validate the bundle itself: this is done by the consignment validator.
+pub fn verify_anchor<D: dbc::Proof<M>, M: DbcMethod>(
+    anchor: &Anchor<mpc::MerkleProof, D, M>,
+    contract_id: ContractId,
+    bundle_id: BundleId,
+    tx: &Tx,
+) -> Result<mpc::Commitment, AnchorError> {
+    let commitment = mpc_commitment(anchor, contract_id, bundle_id)?;
+    anchor
+        .dbc_proof
+        .verify(&commitment, tx)
+        .map_err(|err| AnchorError::Dbc(err.to_string()))?;
+    Ok(commitment)
+}
+
+/// Returns bundle revealed for the contract in the anchor with a full
+/// multi-protocol commitment block.
+pub fn anchored_bundle_id<D: dbc::Proof<M>, M: DbcMethod>(
+    anchor: &Anchor<mpc::MerkleBlock, D, M>,
+    contract_id: ContractId,
+) -> Option<BundleId> {
+    anchor
+        .mpc_proof
+        .to_known_message_map()
+        .get(&mpc::ProtocolId::from(contract_id))
+        .map(|msg| BundleId::from_byte_array(msg.to_byte_array()))
+}
+
 #[derive(Clone, Eq, PartialEq, Debug)]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
 #[strict_type(lib = LIB_NAME_RGB_STD)]
@@ -59,30 +125,43 @@ impl SealWitness {
 
 pub type XPubWitness = XChain<PubWitness>;
 
+/// Typed id of a witness transaction.
+///
+/// Consignment indexes and contract state refer to witness transactions via
+/// this trait, such that support for a new layer 1 with a different format of
+/// transaction ids requires only implementing the trait for its witness id,
+/// without adding new matches over [`XChain`] variants.
+pub trait WitnessId: Copy + Ord + Hash + Debug + Display {
+    /// Layer 1 where the witness transaction is mined.
+    fn layer1(&self) -> Layer1;
+
+    /// Detects whether both witness transactions belong to the same layer 1.
+    fn is_same_layer(&self, other: &Self) -> bool { self.layer1() == other.layer1() }
+}
+
+impl WitnessId for XWitnessId {
+    fn layer1(&self) -> Layer1 { XChain::layer1(self) }
+}
+
 pub trait ToWitnessId {
-    fn to_witness_id(&self) -> XWitnessId;
+    type WitnessId: WitnessId;
+
+    fn to_witness_id(&self) -> Self::WitnessId;
 }
 
 impl ToWitnessId for XPubWitness {
+    type WitnessId = XWitnessId;
+
     fn to_witness_id(&self) -> XWitnessId { self.map_ref(|w| w.txid()) }
 }
 
 impl MergeReveal for XPubWitness {
     fn merge_reveal(self, other: Self) -> Result<Self, MergeRevealError> {
-        match (self, other) {
-            (XChain::Bitcoin(one), XChain::Bitcoin(two)) => {
-                one.merge_reveal(two).map(XChain::Bitcoin)
-            }
-            (XChain::Liquid(one), XChain::Liquid(two)) => one.merge_reveal(two).map(XChain::Liquid),
-            (XChain::Bitcoin(bitcoin), XChain::Liquid(liquid))
-            | (XChain::Liquid(liquid), XChain::Bitcoin(bitcoin)) => {
-                Err(MergeRevealError::ChainMismatch {
-                    bitcoin: bitcoin.txid(),
-                    liquid: liquid.txid(),
-                })
-            }
-            _ => unreachable!(),
+        let (id1, id2) = (self.to_witness_id(), other.to_witness_id());
+        if !id1.is_same_layer(&id2) {
+            return Err(MergeRevealError::LayerMismatch(id1, id2));
         }
+        self.try_map(|one| one.merge_reveal(other.as_reduced_unsafe().clone()))
     }
 }
 
@@ -231,6 +310,37 @@ impl AnchorSet {
             .map(|msg| BundleId::from_byte_array(msg.to_byte_array()))
     }
 
+    /// Verifies that the raw witness transaction contains the commitment of an
+    /// anchor from the set committing to a bundle of the contract.
+    ///
+    /// Returns the id of the bundle and the verified multi-protocol commitment.
+    pub fn verify(
+        &self,
+        contract_id: ContractId,
+        tx: &Tx,
+    ) -> Result<(BundleId, mpc::Commitment), AnchorError> {
+        fn verify<D: dbc::Proof<M>, M: DbcMethod>(
+            anchor: &Anchor<mpc::MerkleBlock, D, M>,
+            contract_id: ContractId,
+            tx: &Tx,
+        ) -> Result<(BundleId, mpc::Commitment), AnchorError> {
+            let bundle_id = anchored_bundle_id(anchor, contract_id)
+                .ok_or(AnchorError::UnknownContract(contract_id))?;
+            let anchor = anchor
+                .to_merkle_proof(contract_id)
+                .map_err(|_| AnchorError::UnknownContract(contract_id))?;
+            Ok((bundle_id, verify_anchor(&anchor, contract_id, bundle_id, tx)?))
+        }
+        match self {
+            AnchorSet::Tapret(tapret) => verify(tapret, contract_id, tx),
+            AnchorSet::Opret(opret) => verify(opret, contract_id, tx),
+            AnchorSet::Double { tapret, opret } => match verify(tapret, contract_id, tx) {
+                Err(AnchorError::UnknownContract(_)) => verify(opret, contract_id, tx),
+                res => res,
+            },
+        }
+    }
+
     pub fn has_tapret(&self) -> bool { matches!(self, Self::Tapret(_) | Self::Double { .. }) }
 
     pub fn has_opret(&self) -> bool { matches!(self, Self::Opret(_) | Self::Double { .. }) }
@@ -266,3 +376,85 @@ impl AnchorSet {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use bp::{LockTime, Sats, ScriptPubkey, SeqNo, TxIn, TxOut, TxVer, Witness};
+    use commit_verify::{CommitId, TryCommitVerify};
+
+    use super::*;
+
+    #[test]
+    fn witness_layers() {
+        let txid = Txid::from_byte_array([1u8; 32]);
+        let bitcoin = XChain::Bitcoin(PubWitness::new(txid));
+        let liquid = XChain::Liquid(PubWitness::new(txid));
+        assert_eq!(bitcoin.to_witness_id().layer1(), Layer1::Bitcoin);
+        assert!(
+            !bitcoin
+                .to_witness_id()
+                .is_same_layer(&liquid.to_witness_id())
+        );
+        assert_eq!(bitcoin.clone().merge_reveal(bitcoin.clone()), Ok(bitcoin.clone()));
+        assert_eq!(
+            bitcoin.clone().merge_reveal(liquid.clone()),
+            Err(MergeRevealError::LayerMismatch(bitcoin.to_witness_id(), liquid.to_witness_id()))
+        );
+    }
+
+    #[test]
+    fn opret_anchor() {
+        let contract_id = ContractId::from_byte_array([1u8; 32]);
+        let other_id = ContractId::from_byte_array([2u8; 32]);
+        let bundle_id = BundleId::from_byte_array([3u8; 32]);
+        let source = mpc::MultiSource {
+            min_depth: mpc::MPC_MINIMAL_DEPTH,
+            messages: Confined::from_checked(bmap! {
+                mpc::ProtocolId::from(contract_id) => mpc::Message::from(bundle_id)
+            }),
+            static_entropy: Some(1),
+        };
+        let block = mpc::MerkleBlock::from(mpc::MerkleTree::try_commit(&source).unwrap());
+        let commitment = block.commit_id();
+
+        let txout = |script_pubkey| TxOut {
+            value: Sats::ZERO,
+            script_pubkey,
+        };
+        let mut tx = Tx {
+            version: TxVer::V2,
+            inputs: Confined::from_checked(vec![TxIn {
+                prev_output: strict_dumb!(),
+                sig_script: none!(),
+                sequence: SeqNo::ZERO,
+                witness: Witness::new(),
+            }]),
+            outputs: Confined::from_checked(vec![txout(ScriptPubkey::op_return(
+                commitment.as_slice(),
+            ))]),
+            lock_time: LockTime::ZERO,
+        };
+        let anchors = AnchorSet::Opret(Anchor::new(block.clone(), OpretProof::default()));
+
+        assert_eq!(anchors.verify(contract_id, &tx), Ok((bundle_id, commitment)));
+        assert_eq!(anchors.verify(other_id, &tx), Err(AnchorError::UnknownContract(other_id)));
+
+        let proof =
+            Anchor::new(block.to_merkle_proof(contract_id.into()).unwrap(), OpretProof::default());
+        assert_eq!(mpc_commitment(&proof, contract_id, bundle_id), Ok(commitment));
+        let other_bundle = BundleId::from_byte_array([4u8; 32]);
+        assert_ne!(mpc_commitment(&proof, contract_id, other_bundle), Ok(commitment));
+        assert!(matches!(
+            verify_anchor(&proof, contract_id, other_bundle, &tx),
+            Err(AnchorError::Dbc(_))
+        ));
+        assert_eq!(verify_anchor(&proof, contract_id, bundle_id, &tx), Ok(commitment));
+
+        tx.outputs = Confined::from_checked(vec![txout(ScriptPubkey::op_return(&[0u8; 32]))]);
+        assert!(matches!(
+            verify_anchor(&proof, contract_id, bundle_id, &tx),
+            Err(AnchorError::Dbc(_))
+        ));
+    }
+}
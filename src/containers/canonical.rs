@@ -0,0 +1,240 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Canonical JSON serialization of container metadata.
+//!
+//! Signatures over supplements, transfer receipts and content endorsements
+//! made by wallets written in other languages are frequently produced over
+//! JSON representation of the data. To make such signatures reproducible, the
+//! data are serialized into a canonical form following the JSON
+//! Canonicalization Scheme (JCS, RFC 8785) for the integer-only data:
+//! - object keys are sorted by their UTF-16 code units, as required by JCS;
+//! - no insignificant whitespace is present;
+//! - numbers must be integers and are written in decimal form without leading zeros, exponent or
+//!   fraction; floating-point values are rejected. Integers outside of the range exactly
+//!   representable by IEEE 754 double-precision numbers (±2^53-1) can't be represented by JCS and
+//!   are rejected as well: data containing such values must serialize them as strings;
+//! - strings use the minimal JSON escaping: only `"`, `\` and control characters are escaped, with
+//!   `\b`, `\f`, `\n`, `\r`, `\t` short forms and lowercase `\u00xx` for other control characters.
+//!
+//! The signed message is a tagged SHA256 hash of the canonical JSON string.
+
+use amplify::Bytes32;
+use commit_verify::{DigestExt, Sha256};
+use rgb::Identity;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::SigBlob;
+
+/// Tag used for hashing canonical JSON into the signed message.
+pub const CANONICAL_JSON_TAG: &str = "urn:lnp-bp:rgb:canonical-json#2024-10-14";
+
+/// Maximal absolute value of an integer which can be canonically serialized.
+pub const CANONICAL_MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum CanonicalError {
+    /// data can't be represented as JSON: {0}
+    Json(String),
+
+    /// floating-point number {0} can't be canonically serialized.
+    Float(String),
+
+    /// integer {0} exceeds the range of integers which can be canonically
+    /// serialized.
+    UnsafeInteger(String),
+}
+
+/// Serializes the data into a canonical JSON string.
+pub fn to_canonical_json(data: &impl Serialize) -> Result<String, CanonicalError> {
+    let value = serde_json::to_value(data).map_err(|err| CanonicalError::Json(err.to_string()))?;
+    let mut s = String::new();
+    write_value(&value, &mut s)?;
+    Ok(s)
+}
+
+/// Computes message for signing the data, which is a tagged hash of the
+/// canonical JSON serialization of the data.
+pub fn canonical_digest(data: &impl Serialize) -> Result<Bytes32, CanonicalError> {
+    let json = to_canonical_json(data)?;
+    let mut hasher = Sha256::from_tag(CANONICAL_JSON_TAG);
+    hasher.input_raw(json.as_bytes());
+    Ok(hasher.finish().into())
+}
+
+/// Signer producing signatures over the canonical JSON of the metadata.
+pub trait CanonicalSigner {
+    fn sign_digest(&self, digest: Bytes32) -> SigBlob;
+}
+
+/// Verifier for the signatures over the canonical JSON of the metadata.
+pub trait CanonicalVerifier {
+    fn verify_digest(&self, identity: &Identity, digest: Bytes32, sig: &SigBlob) -> bool;
+}
+
+/// Signs canonical JSON serialization of the data.
+pub fn sign_canonical(
+    data: &impl Serialize,
+    signer: &impl CanonicalSigner,
+) -> Result<SigBlob, CanonicalError> {
+    canonical_digest(data).map(|digest| signer.sign_digest(digest))
+}
+
+/// Verifies signature of an identity over canonical JSON serialization of the
+/// data.
+pub fn verify_canonical(
+    data: &impl Serialize,
+    identity: &Identity,
+    sig: &SigBlob,
+    verifier: &impl CanonicalVerifier,
+) -> Result<bool, CanonicalError> {
+    canonical_digest(data).map(|digest| verifier.verify_digest(identity, digest, sig))
+}
+
+fn write_value(value: &Value, s: &mut String) -> Result<(), CanonicalError> {
+    match value {
+        Value::Null => s.push_str("null"),
+        Value::Bool(true) => s.push_str("true"),
+        Value::Bool(false) => s.push_str("false"),
+        Value::Number(n) if n.is_f64() => return Err(CanonicalError::Float(n.to_string())),
+        Value::Number(n) => {
+            let safe = n
+                .as_u64()
+                .map(|v| v <= CANONICAL_MAX_SAFE_INTEGER)
+                .or_else(|| {
+                    n.as_i64()
+                        .map(|v| v.unsigned_abs() <= CANONICAL_MAX_SAFE_INTEGER)
+                })
+                .unwrap_or_default();
+            if !safe {
+                return Err(CanonicalError::UnsafeInteger(n.to_string()));
+            }
+            s.push_str(&n.to_string());
+        }
+        Value::String(v) => write_str(v, s),
+        Value::Array(items) => {
+            s.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    s.push(',');
+                }
+                write_value(item, s)?;
+            }
+            s.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            s.push('{');
+            for (index, (key, item)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    s.push(',');
+                }
+                write_str(key, s);
+                s.push(':');
+                write_value(item, s)?;
+            }
+            s.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_str(v: &str, s: &mut String) {
+    s.push('"');
+    for c in v.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\u{08}' => s.push_str("\\b"),
+            '\u{0c}' => s.push_str("\\f"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            c if c < ' ' => s.push_str(&format!("\\u{:04x}", c as u32)),
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::containers::{Transfer, TransferReceipt};
+
+    #[test]
+    fn canonical_json() {
+        let mut data = HashMap::new();
+        data.insert("zeta", serde_json::json!({ "b": [1, -2, null], "a": "x\"\n\u{1}ü" }));
+        data.insert("alpha", serde_json::json!(true));
+        assert_eq!(
+            to_canonical_json(&data).unwrap(),
+            r#"{"alpha":true,"zeta":{"a":"x\"\n\u0001ü","b":[1,-2,null]}}"#
+        );
+        assert!(matches!(to_canonical_json(&1.5f64), Err(CanonicalError::Float(_))));
+
+        // Keys are ordered by UTF-16 code units, where surrogate pairs precede
+        // characters from the upper part of the basic multilingual plane
+        let data = serde_json::json!({ "\u{e000}": 1, "\u{1f600}": 2, "a": 3 });
+        assert_eq!(to_canonical_json(&data).unwrap(), "{\"a\":3,\"\u{1f600}\":2,\"\u{e000}\":1}");
+
+        let data =
+            serde_json::json!([CANONICAL_MAX_SAFE_INTEGER, -(CANONICAL_MAX_SAFE_INTEGER as i64)]);
+        assert_eq!(to_canonical_json(&data).unwrap(), "[9007199254740991,-9007199254740991]");
+        for unsafe_int in [
+            serde_json::json!(CANONICAL_MAX_SAFE_INTEGER + 1),
+            serde_json::json!(-(CANONICAL_MAX_SAFE_INTEGER as i64) - 1),
+            serde_json::json!({ "amount": u64::MAX }),
+        ] {
+            assert!(matches!(
+                to_canonical_json(&unsafe_int),
+                Err(CanonicalError::UnsafeInteger(_))
+            ));
+        }
+
+        struct EchoSigner;
+        impl CanonicalSigner for EchoSigner {
+            fn sign_digest(&self, digest: Bytes32) -> SigBlob {
+                SigBlob::from(amplify::confinement::NonEmptyBlob::from_slice_checked(&digest[..]))
+            }
+        }
+        impl CanonicalVerifier for EchoSigner {
+            fn verify_digest(&self, _: &Identity, digest: Bytes32, sig: &SigBlob) -> bool {
+                sig.as_slice() == &digest[..]
+            }
+        }
+
+        let receipt =
+            TransferReceipt::new(&Transfer::strict_dumb(), Identity::from("ssi:receiver"), 0);
+        let sig = sign_canonical(&receipt, &EchoSigner).unwrap();
+        assert!(verify_canonical(&receipt, &receipt.receiver, &sig, &EchoSigner).unwrap());
+        let mut other = receipt.clone();
+        other.timestamp = 1;
+        assert!(!verify_canonical(&other, &receipt.receiver, &sig, &EchoSigner).unwrap());
+    }
+}
@@ -19,37 +19,50 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::io;
+use std::iter;
 use std::ops::Deref;
 use std::str::FromStr;
 
-use aluvm::library::Lib;
+use aluvm::library::{Lib, LibId};
 use amplify::confinement::{
-    Confined, LargeOrdSet, MediumBlob, SmallOrdMap, SmallOrdSet, TinyOrdMap, TinyOrdSet,
+    self, Confined, LargeOrdSet, MediumBlob, SmallOrdMap, SmallOrdSet, SmallVec, TinyOrdMap,
+    TinyOrdSet, U32,
 };
 use amplify::{ByteArray, Bytes32};
 use armor::{ArmorHeader, AsciiArmor, StrictArmor, StrictArmorError};
 use baid64::{Baid64ParseError, DisplayBaid64, FromBaid64Str};
-use commit_verify::{CommitEncode, CommitEngine, CommitId, CommitmentId, DigestExt, Sha256};
+use chrono::Utc;
+use commit_verify::{
+    CommitEncode, CommitEngine, CommitId, CommitStep, CommitmentId, Conceal, DigestExt, Sha256,
+};
+use invoice::{Amount, Beneficiary, InvoiceState, NonFungible, RgbInvoice};
 use rgb::validation::{ResolveWitness, Validator, Validity, Warning, CONSIGNMENT_MAX_LIBS};
 use rgb::{
-    impl_serde_baid64, validation, AttachId, BundleId, ContractId, Extension, Genesis, GraphSeal,
-    Operation, Schema, SchemaId, XChain,
+    impl_serde_baid64, validation, Assign, AssignmentType, AttachId, BundleId, ContractId,
+    DataState, DiscloseHash, ExposedState, Extension, Genesis, GraphSeal, OpId, Operation, Schema,
+    SchemaId, TypedAssigns, XChain,
 };
 use rgbcore::validation::ConsignmentApi;
-use strict_encoding::{StrictDeserialize, StrictDumb, StrictSerialize};
-use strict_types::TypeSystem;
+use strict_encoding::{
+    DecodeError, FieldName, StreamReader, StrictDecode, StrictDeserialize, StrictDumb, StrictEncode,
+    StrictReader, StrictSerialize, StrictWriter, TypeName, TypedRead, TypedWrite,
+    WriteStruct,
+};
+use strict_types::{TypeSysId, TypeSystem};
 
 use super::{
-    ContainerVer, ContentId, ContentSigs, IndexedConsignment, Supplement, WitnessBundle,
+    ContainerVer, ContentId, ContentRef, ContentSigs, IndexedConsignment, ResourceExhausted,
+    ScriptLimits, SupplId, Supplement, ValidationReport, WitnessBundle,
     ASCII_ARMOR_CONSIGNMENT_TYPE, ASCII_ARMOR_CONTRACT, ASCII_ARMOR_IFACE, ASCII_ARMOR_SCHEMA,
     ASCII_ARMOR_TERMINAL, ASCII_ARMOR_VERSION,
 };
-use crate::interface::{Iface, IfaceImpl};
+use crate::interface::{AllocatedState, Iface, IfaceImpl, ImplId};
 use crate::persistence::{MemContract, MemContractState};
-use crate::resolvers::ConsignmentResolver;
+use crate::resolvers::{ConsignmentResolver, Progress, ProgressResolver, ProgressSink};
 use crate::{BundleExt, SecretSeal, LIB_NAME_RGB_STD};
 
 pub type Transfer = Consignment<true>;
@@ -128,6 +141,34 @@ impl_serde_baid64!(ConsignmentId);
 
 impl ConsignmentId {
     pub const fn from_array(id: [u8; 32]) -> Self { Self(Bytes32::from_array(id)) }
+
+    /// Computes id of a strict-serialized consignment read from a stream,
+    /// without materializing the consignment in memory. Useful for relays and
+    /// proxies which route and deduplicate consignments by their ids and
+    /// never validate them.
+    ///
+    /// NB: the data are not validated beyond what is required for their
+    /// decoding.
+    pub fn from_strict_reader(reader: impl io::Read) -> Result<Self, DecodeError> {
+        let mut reader = StrictReader::with(StreamReader::new::<U32>(reader));
+        Ok(ConsignmentCommitment::strict_read(&mut reader)?.commit_id())
+    }
+}
+
+/// Errors of consignment validation performed under additional constraints.
+#[derive(Clone, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ValidationError {
+    /// {0}
+    #[from]
+    Invalid(validation::Status),
+
+    /// validation scripts exceed resource limits: {0}
+    #[from]
+    ResourceExhausted(ResourceExhausted),
+
+    /// validation is cancelled.
+    Cancelled,
 }
 
 pub type ValidContract = ValidConsignment<false>;
@@ -144,6 +185,10 @@ pub struct ValidConsignment<const TRANSFER: bool> {
 impl<const TRANSFER: bool> ValidConsignment<TRANSFER> {
     pub fn validation_status(&self) -> &validation::Status { &self.validation_status }
 
+    /// Returns deterministic report on the consignment validation, which can
+    /// be persisted or sent over RPC.
+    pub fn validation_report(&self) -> ValidationReport { (&self.validation_status).into() }
+
     pub fn into_consignment(self) -> Consignment<TRANSFER> { self.consignment }
 
     pub fn into_validation_status(self) -> validation::Status { self.validation_status }
@@ -151,6 +196,15 @@ impl<const TRANSFER: bool> ValidConsignment<TRANSFER> {
     pub fn split(self) -> (Consignment<TRANSFER>, validation::Status) {
         (self.consignment, self.validation_status)
     }
+    /// Wraps consignment without validating it, for testing components which
+    /// accept validated consignments only.
+    #[cfg(test)]
+    pub(crate) fn assume_valid(consignment: Consignment<TRANSFER>) -> Self {
+        Self {
+            validation_status: validation::Status::new(),
+            consignment,
+        }
+    }
 }
 
 impl<const TRANSFER: bool> Deref for ValidConsignment<TRANSFER> {
@@ -169,7 +223,7 @@ impl<const TRANSFER: bool> Deref for ValidConsignment<TRANSFER> {
 /// with `endpoints` and process up to the genesis.
 #[derive(Clone, Debug, Display)]
 #[display(AsciiArmor::to_ascii_armored_string)]
-#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode, PartialEq)]
+#[derive(StrictType, StrictDumb, PartialEq)]
 #[strict_type(lib = LIB_NAME_RGB_STD)]
 #[cfg_attr(
     feature = "serde",
@@ -177,7 +231,9 @@ impl<const TRANSFER: bool> Deref for ValidConsignment<TRANSFER> {
     serde(crate = "serde_crate", rename_all = "camelCase")
 )]
 pub struct Consignment<const TRANSFER: bool> {
-    /// Version.
+    /// Version. Determines the layout of the encoded consignment, see
+    /// [`Consignment::records`].
+    #[strict_type(dumb = ContainerVer::V3)]
     pub version: ContainerVer,
 
     /// Specifies whether the consignment contains information about state
@@ -222,6 +278,78 @@ pub struct Consignment<const TRANSFER: bool> {
     /// Signatures on the pieces of content which are the part of the
     /// consignment.
     pub signatures: TinyOrdMap<ContentId, ContentSigs>,
+
+    /// Optional extension records, keyed by the record name. The records are
+    /// not committed to by the consignment id and are not kept in the stash.
+    ///
+    /// The records are encoded only by consignments of [`ContainerVer::V3`]
+    /// and above; consignments of the earlier versions end right after the
+    /// signatures and always have no records.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub records: TinyOrdMap<TypeName, MediumBlob>,
+}
+
+impl<const TRANSFER: bool> StrictEncode for Consignment<TRANSFER> {
+    fn strict_encode<W: TypedWrite>(&self, writer: W) -> io::Result<W> {
+        writer.write_struct::<Self>(|w| {
+            let w = w
+                .write_field(fname!("version"), &self.version)?
+                .write_field(fname!("transfer"), &self.transfer)?
+                .write_field(fname!("terminals"), &self.terminals)?
+                .write_field(fname!("genesis"), &self.genesis)?
+                .write_field(fname!("extensions"), &self.extensions)?
+                .write_field(fname!("bundles"), &self.bundles)?
+                .write_field(fname!("schema"), &self.schema)?
+                .write_field(fname!("ifaces"), &self.ifaces)?
+                .write_field(fname!("supplements"), &self.supplements)?
+                .write_field(fname!("types"), &self.types)?
+                .write_field(fname!("scripts"), &self.scripts)?
+                .write_field(fname!("attachments"), &self.attachments)?
+                .write_field(fname!("signatures"), &self.signatures)?;
+            if !self.version.has_records() {
+                return Ok(w.complete());
+            }
+            Ok(w.write_field(fname!("records"), &self.records)?.complete())
+        })
+    }
+}
+
+impl<const TRANSFER: bool> StrictDecode for Consignment<TRANSFER> {
+    fn strict_decode(reader: &mut impl TypedRead) -> Result<Self, DecodeError> {
+        // The fields are read sequentially since the presence of the records
+        // depends on the version.
+        let version = ContainerVer::strict_decode(reader)?;
+        let transfer = StrictDecode::strict_decode(reader)?;
+        let terminals = StrictDecode::strict_decode(reader)?;
+        let genesis = StrictDecode::strict_decode(reader)?;
+        let extensions = StrictDecode::strict_decode(reader)?;
+        let bundles = StrictDecode::strict_decode(reader)?;
+        let schema = StrictDecode::strict_decode(reader)?;
+        let ifaces = StrictDecode::strict_decode(reader)?;
+        let supplements = StrictDecode::strict_decode(reader)?;
+        let types = StrictDecode::strict_decode(reader)?;
+        let scripts = StrictDecode::strict_decode(reader)?;
+        let attachments = StrictDecode::strict_decode(reader)?;
+        let signatures = StrictDecode::strict_decode(reader)?;
+        let records =
+            if version.has_records() { StrictDecode::strict_decode(reader)? } else { none!() };
+        Ok(Self {
+            version,
+            transfer,
+            terminals,
+            genesis,
+            extensions,
+            bundles,
+            schema,
+            ifaces,
+            supplements,
+            types,
+            scripts,
+            attachments,
+            signatures,
+            records,
+        })
+    }
 }
 
 impl<const TRANSFER: bool> StrictSerialize for Consignment<TRANSFER> {}
@@ -230,36 +358,251 @@ impl<const TRANSFER: bool> StrictDeserialize for Consignment<TRANSFER> {}
 impl<const TRANSFER: bool> CommitEncode for Consignment<TRANSFER> {
     type CommitmentId = ConsignmentId;
 
+    fn commit_encode(&self, e: &mut CommitEngine) { self.commitment().commit_encode(e) }
+}
+
+/// Data of a consignment which are committed to by its id.
+struct ConsignmentCommitment {
+    version: ContainerVer,
+    transfer: bool,
+    contract_id: ContractId,
+    genesis: DiscloseHash,
+    iimpls: TinyOrdSet<ImplId>,
+    bundles: LargeOrdSet<DiscloseHash>,
+    extensions: LargeOrdSet<DiscloseHash>,
+    terminals: SmallOrdMap<BundleId, XChain<SecretSeal>>,
+    attachments: SmallOrdSet<AttachId>,
+    supplements: TinyOrdSet<SupplId>,
+    types: TypeSysId,
+    scripts: SmallOrdSet<LibId>,
+    signatures: TinyOrdMap<ContentId, ContentSigs>,
+}
+
+impl CommitEncode for ConsignmentCommitment {
+    type CommitmentId = ConsignmentId;
+
     fn commit_encode(&self, e: &mut CommitEngine) {
         e.commit_to_serialized(&self.version);
         e.commit_to_serialized(&self.transfer);
 
-        e.commit_to_serialized(&self.contract_id());
-        e.commit_to_serialized(&self.genesis.disclose_hash());
-        e.commit_to_set(&TinyOrdSet::from_iter_checked(
-            self.ifaces.values().map(|iimpl| iimpl.impl_id()),
-        ));
+        e.commit_to_serialized(&self.contract_id);
+        e.commit_to_serialized(&self.genesis);
+        e.commit_to_set(&self.iimpls);
 
-        e.commit_to_set(&LargeOrdSet::from_iter_checked(
-            self.bundles.iter().map(WitnessBundle::commit_id),
-        ));
-        e.commit_to_set(&LargeOrdSet::from_iter_checked(
-            self.extensions.iter().map(Extension::disclose_hash),
-        ));
+        e.commit_to_set(&self.bundles);
+        e.commit_to_set(&self.extensions);
         e.commit_to_map(&self.terminals);
 
-        e.commit_to_set(&SmallOrdSet::from_iter_checked(self.attachments.keys().copied()));
-        e.commit_to_set(&TinyOrdSet::from_iter_checked(
-            self.supplements.iter().map(|suppl| suppl.suppl_id()),
-        ));
+        e.commit_to_set(&self.attachments);
+        e.commit_to_set(&self.supplements);
 
-        e.commit_to_serialized(&self.types.id());
-        e.commit_to_set(&SmallOrdSet::from_iter_checked(self.scripts.iter().map(|lib| lib.id())));
+        e.commit_to_serialized(&self.types);
+        e.commit_to_set(&self.scripts);
 
         e.commit_to_map(&self.signatures);
     }
 }
 
+impl ConsignmentCommitment {
+    /// Reads strict-serialized consignment, keeping only the data committed
+    /// to by its id. Collections which may be large are decoded item by item,
+    /// such that no more than a single item is held in memory at a time.
+    fn strict_read(reader: &mut impl TypedRead) -> Result<Self, DecodeError> {
+        let version = ContainerVer::strict_decode(reader)?;
+        let transfer = bool::strict_decode(reader)?;
+        let terminals = StrictDecode::strict_decode(reader)?;
+        let genesis = Genesis::strict_decode(reader)?;
+
+        let len = u32::strict_decode(reader)? as usize;
+        let extensions = stream_set(reader, len, Extension::disclose_hash)?;
+        let len = u32::strict_decode(reader)? as usize;
+        let bundles = stream_set(reader, len, |bundle: &WitnessBundle| bundle.commit_id())?;
+        Schema::strict_decode(reader)?;
+        let len = u8::strict_decode(reader)? as usize;
+        let iimpls = stream_map(reader, len, |_: &Iface, iimpl: &IfaceImpl| iimpl.impl_id())?;
+        let len = u8::strict_decode(reader)? as usize;
+        let supplements = stream_set(reader, len, Supplement::suppl_id)?;
+        let types = TypeSystem::strict_decode(reader)?.id();
+        let len = u16::strict_decode(reader)? as usize;
+        if len > CONSIGNMENT_MAX_LIBS {
+            return Err(confinement::Error::Oversize {
+                len,
+                max_len: CONSIGNMENT_MAX_LIBS,
+            }
+            .into());
+        }
+        let scripts = stream_set(reader, len, Lib::id)?;
+        let len = u16::strict_decode(reader)? as usize;
+        let attachments = stream_map(reader, len, |id: &AttachId, _: &MediumBlob| *id)?;
+        let signatures = StrictDecode::strict_decode(reader)?;
+        if version.has_records() {
+            TinyOrdMap::<TypeName, MediumBlob>::strict_decode(reader)?;
+        }
+
+        Ok(ConsignmentCommitment {
+            version,
+            transfer,
+            contract_id: genesis.contract_id(),
+            genesis: genesis.disclose_hash(),
+            iimpls: Confined::try_from(iimpls)?,
+            bundles: Confined::try_from(bundles)?,
+            extensions: Confined::try_from(extensions)?,
+            terminals,
+            attachments: Confined::try_from(attachments)?,
+            supplements: Confined::try_from(supplements)?,
+            types,
+            scripts: Confined::try_from(scripts)?,
+            signatures,
+        })
+    }
+}
+
+/// Component of a consignment committed to by the consignment id.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CommitmentComponent {
+    /// Name of the consignment field the component is computed from.
+    pub name: &'static str,
+    /// Type of the component and the way it is committed to.
+    pub step: CommitStep,
+    /// Strict serialization of the component as it enters the commitment.
+    pub data: Vec<u8>,
+    /// SHA256 digest of the serialized component.
+    pub digest: Bytes32,
+}
+
+/// Breakdown of the consignment id commitment into its components.
+///
+/// The consignment id is a SHA256 hash tagged with [`Self::tag`] of the
+/// serialized components concatenated in the order of the layout.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ConsignmentLayout {
+    pub tag: &'static str,
+    pub components: Vec<CommitmentComponent>,
+    pub consignment_id: ConsignmentId,
+}
+
+impl ConsignmentLayout {
+    /// Recomputes consignment id from the components of the layout.
+    pub fn compute_id(&self) -> ConsignmentId {
+        let mut hasher = Sha256::from_tag(self.tag);
+        for component in &self.components {
+            hasher.input_raw(&component.data);
+        }
+        ConsignmentId::from(hasher)
+    }
+
+    /// Verifies that the components of the layout produce the consignment id.
+    pub fn verify(&self) -> bool { self.compute_id() == self.consignment_id }
+}
+
+impl ConsignmentCommitment {
+    fn layout(&self) -> ConsignmentLayout {
+        fn component<T: StrictEncode>(
+            name: &'static str,
+            value: &T,
+            commit: impl FnOnce(&mut CommitEngine),
+        ) -> CommitmentComponent {
+            let mut engine = CommitEngine::new(ConsignmentId::TAG);
+            commit(&mut engine);
+            let step = engine.as_layout()[0].clone();
+            let writer = StrictWriter::in_memory::<{ usize::MAX }>();
+            let writer = value.strict_encode(writer).expect("in-memory encoding");
+            let data = writer.unbox().unconfine();
+            let mut hasher = Sha256::default();
+            hasher.input_raw(&data);
+            let digest = hasher.finish().into();
+            CommitmentComponent {
+                name,
+                step,
+                data,
+                digest,
+            }
+        }
+
+        let components = vec![
+            component("version", &self.version, |e| e.commit_to_serialized(&self.version)),
+            component("transfer", &self.transfer, |e| e.commit_to_serialized(&self.transfer)),
+            component("contractId", &self.contract_id, |e| {
+                e.commit_to_serialized(&self.contract_id)
+            }),
+            component("genesis", &self.genesis, |e| e.commit_to_serialized(&self.genesis)),
+            component("ifaces", &self.iimpls, |e| e.commit_to_set(&self.iimpls)),
+            component("bundles", &self.bundles, |e| e.commit_to_set(&self.bundles)),
+            component("extensions", &self.extensions, |e| e.commit_to_set(&self.extensions)),
+            component("terminals", &self.terminals, |e| e.commit_to_map(&self.terminals)),
+            component("attachments", &self.attachments, |e| e.commit_to_set(&self.attachments)),
+            component("supplements", &self.supplements, |e| e.commit_to_set(&self.supplements)),
+            component("types", &self.types, |e| e.commit_to_serialized(&self.types)),
+            component("scripts", &self.scripts, |e| e.commit_to_set(&self.scripts)),
+            component("signatures", &self.signatures, |e| e.commit_to_map(&self.signatures)),
+        ];
+        ConsignmentLayout {
+            tag: ConsignmentId::TAG,
+            components,
+            consignment_id: self.commit_id(),
+        }
+    }
+}
+
+/// Detects assignment type for which the operation uses more than a single
+/// asset tag.
+fn mixed_asset_tags(op: &dyn Operation) -> Option<AssignmentType> {
+    op.assignments().flat().iter().find_map(|(ty, assigns)| {
+        let tags = assigns
+            .as_fungible()
+            .iter()
+            .filter_map(|assign| assign.as_revealed_state())
+            .map(|state| state.tag)
+            .collect::<BTreeSet<_>>();
+        (tags.len() > 1).then_some(*ty)
+    })
+}
+
+/// Decodes `len` items of a strict-encoded set one by one, checking their
+/// order and uniqueness and collecting only their ids.
+fn stream_set<T: StrictDecode + Ord, Id: Ord>(
+    reader: &mut impl TypedRead,
+    len: usize,
+    id: impl Fn(&T) -> Id,
+) -> Result<BTreeSet<Id>, DecodeError> {
+    let mut ids = BTreeSet::new();
+    let mut last = None::<T>;
+    for _ in 0..len {
+        let item = T::strict_decode(reader)?;
+        match &last {
+            Some(last) if last > &item => return Err(DecodeError::BrokenSetOrder),
+            Some(last) if last == &item => return Err(DecodeError::RepeatedSetValue),
+            _ => {}
+        }
+        ids.insert(id(&item));
+        last = Some(item);
+    }
+    Ok(ids)
+}
+
+/// Decodes `len` entries of a strict-encoded map one by one, checking their
+/// order and uniqueness and collecting only their ids.
+fn stream_map<K: StrictDecode + Ord, V: StrictDecode, Id: Ord>(
+    reader: &mut impl TypedRead,
+    len: usize,
+    id: impl Fn(&K, &V) -> Id,
+) -> Result<BTreeSet<Id>, DecodeError> {
+    let mut ids = BTreeSet::new();
+    let mut last = None::<K>;
+    for _ in 0..len {
+        let key = K::strict_decode(reader)?;
+        let val = V::strict_decode(reader)?;
+        match &last {
+            Some(last) if last > &key => return Err(DecodeError::BrokenMapOrder),
+            Some(last) if last == &key => return Err(DecodeError::RepeatedMapValue),
+            _ => {}
+        }
+        ids.insert(id(&key, &val));
+        last = Some(key);
+    }
+    Ok(ids)
+}
+
 impl<const TRANSFER: bool> ConsignmentExt for Consignment<TRANSFER> {
     #[inline]
     fn contract_id(&self) -> ContractId { self.genesis.contract_id() }
@@ -284,6 +627,58 @@ impl<const TRANSFER: bool> Consignment<TRANSFER> {
     #[inline]
     pub fn consignment_id(&self) -> ConsignmentId { self.commit_id() }
 
+    /// Explains what the consignment id commits to, providing digests of all
+    /// the committed components such that the commitment can be
+    /// independently reproduced.
+    pub fn commitment_layout(&self) -> ConsignmentLayout { self.commitment().layout() }
+
+    fn commitment(&self) -> ConsignmentCommitment {
+        ConsignmentCommitment {
+            version: self.version,
+            transfer: self.transfer,
+            contract_id: self.contract_id(),
+            genesis: self.genesis.disclose_hash(),
+            iimpls: TinyOrdSet::from_iter_checked(
+                self.ifaces.values().map(|iimpl| iimpl.impl_id()),
+            ),
+            bundles: LargeOrdSet::from_iter_checked(
+                self.bundles.iter().map(WitnessBundle::commit_id),
+            ),
+            extensions: LargeOrdSet::from_iter_checked(
+                self.extensions.iter().map(Extension::disclose_hash),
+            ),
+            terminals: self.terminals.clone(),
+            attachments: SmallOrdSet::from_iter_checked(self.attachments.keys().copied()),
+            supplements: TinyOrdSet::from_iter_checked(
+                self.supplements.iter().map(|suppl| suppl.suppl_id()),
+            ),
+            types: self.types.id(),
+            scripts: SmallOrdSet::from_iter_checked(self.scripts.iter().map(|lib| lib.id())),
+            signatures: self.signatures.clone(),
+        }
+    }
+
+    /// Changes the type of the consignment without changing the value of its
+    /// `transfer` flag, thus keeping the consignment id intact.
+    pub(crate) fn retype<const KIND: bool>(self) -> Consignment<KIND> {
+        Consignment {
+            version: self.version,
+            transfer: self.transfer,
+            terminals: self.terminals,
+            genesis: self.genesis,
+            extensions: self.extensions,
+            bundles: self.bundles,
+            schema: self.schema,
+            ifaces: self.ifaces,
+            supplements: self.supplements,
+            types: self.types,
+            scripts: self.scripts,
+            attachments: self.attachments,
+            signatures: self.signatures,
+            records: self.records,
+        }
+    }
+
     #[inline]
     pub fn schema_id(&self) -> SchemaId { self.schema.schema_id() }
 
@@ -323,6 +718,60 @@ impl<const TRANSFER: bool> Consignment<TRANSFER> {
             attachments: self.attachments,
             signatures: self.signatures,
             scripts: self.scripts,
+            records: self.records,
+        }
+    }
+
+    /// Validates the consignment after checking that its validation scripts
+    /// fit the provided resource limits.
+    ///
+    /// First, the scripts are checked against the static limits; next the
+    /// worst-case execution of each of them is checked against the execution
+    /// limits, see [`ScriptLimits::check_execution`]. Only if none of the
+    /// limits are exceeded the consensus validation is performed, which is the
+    /// only run of the scripts. Otherwise, the validation fails with
+    /// [`ValidationError::ResourceExhausted`].
+    #[allow(clippy::result_large_err)]
+    pub fn validate_with_limits(
+        self,
+        resolver: &impl ResolveWitness,
+        testnet: bool,
+        limits: &ScriptLimits,
+    ) -> Result<ValidConsignment<TRANSFER>, (ValidationError, Consignment<TRANSFER>)> {
+        if let Err(err) = limits
+            .check(&self.scripts)
+            .and_then(|_| limits.check_execution(&self))
+        {
+            return Err((err.into(), self));
+        }
+        self.validate(resolver, testnet)
+            .map_err(|(status, consignment)| (status.into(), consignment))
+    }
+
+    /// Validates consignment like [`Consignment::validate`], reporting
+    /// progress of the bundle processing to the sink.
+    ///
+    /// Once the sink reports cancellation, validation stops querying the
+    /// resolver and fails with [`ValidationError::Cancelled`].
+    #[allow(clippy::result_large_err)]
+    pub fn validate_with_progress(
+        self,
+        resolver: &impl ResolveWitness,
+        testnet: bool,
+        sink: &impl ProgressSink,
+    ) -> Result<ValidConsignment<TRANSFER>, (ValidationError, Consignment<TRANSFER>)> {
+        sink.report(Progress::Validating(self.contract_id()));
+        let witnesses = self
+            .bundles
+            .iter()
+            .map(WitnessBundle::witness_id)
+            .collect::<Vec<_>>();
+        let resolver = ProgressResolver::new(resolver, sink, witnesses);
+        match self.validate(&resolver, testnet) {
+            Err((_, consignment)) if sink.is_cancelled() => {
+                Err((ValidationError::Cancelled, consignment))
+            }
+            res => res.map_err(|(status, consignment)| (status.into(), consignment)),
         }
     }
 
@@ -345,6 +794,25 @@ impl<const TRANSFER: bool> Consignment<TRANSFER> {
             (&self.schema, self.contract_id()),
         );
 
+        // asset tags may be rotated by the operations, but each operation must use a single
+        // tag for all its fungible state of the same type
+        let ops = iter::once(&self.genesis as &dyn Operation)
+            .chain(self.extensions.iter().map(|ext| ext as &dyn Operation))
+            .chain(
+                self.bundles
+                    .iter()
+                    .flat_map(|wb| wb.bundle.known_transitions.values())
+                    .map(|transition| transition as &dyn Operation),
+            );
+        for op in ops {
+            if let Some(ty) = mixed_asset_tags(op) {
+                status.add_failure(validation::Failure::Custom(format!(
+                    "operation {} uses different asset tags for state {ty}",
+                    op.id()
+                )));
+            }
+        }
+
         let validity = status.validity();
 
         if self.transfer != TRANSFER {
@@ -362,12 +830,36 @@ impl<const TRANSFER: bool> Consignment<TRANSFER> {
             }
         }
 
-        // check bundle ids listed in terminals are present in the consignment
-        for bundle_id in self.terminals.keys() {
-            if !index.bundle_ids().any(|id| id == *bundle_id) {
+        // check bundle ids listed in terminals are present in the consignment and assign
+        // state to the terminal seals, which may be defined as witness transaction outputs
+        for (bundle_id, seal) in &self.terminals {
+            let Some(bundle) = index.bundle(*bundle_id) else {
                 status.add_warning(Warning::Custom(format!(
                     "terminal bundle id {bundle_id} is not present in the consignment"
                 )));
+                continue;
+            };
+            if !bundle.known_transitions.values().any(|transition| {
+                transition
+                    .assignments
+                    .values()
+                    .any(|assigns| assigns.to_confidential_seals().contains(seal))
+            }) {
+                status.add_warning(Warning::Custom(format!(
+                    "terminal seal {seal} is not assigned by the bundle {bundle_id}"
+                )));
+            }
+        }
+        // check valencies redeemed by extensions are declared by the redeemed operations
+        for extension in &self.extensions {
+            for (ty, prev_id) in extension.redeemed() {
+                if !index.declares_valency(*prev_id, *ty) {
+                    status.add_warning(Warning::Custom(format!(
+                        "extension {} redeems valency {ty} which is not declared by operation \
+                         {prev_id}",
+                        extension.id()
+                    )));
+                }
             }
         }
         // TODO: check attach ids from data containers are present in operations
@@ -386,6 +878,366 @@ impl<const TRANSFER: bool> Consignment<TRANSFER> {
     }
 }
 
+impl Transfer {
+    /// Extracts a minimal transfer consignment for the recipient of the state
+    /// assigned to the provided terminal seal.
+    ///
+    /// The extracted consignment contains only the state transitions from the
+    /// history of the terminal transition, with all the assignments of the
+    /// terminal transition not belonging to the recipient being concealed. This
+    /// allows to send each of the recipients of a batched transfer only the
+    /// data they need, without leaking amounts and seals of the other
+    /// recipients.
+    ///
+    /// Extension records are not carried over, since they may be addressed to
+    /// the other recipients; supplements and signatures are kept only for the
+    /// content present in the extracted consignment.
+    ///
+    /// Returns `None` if none of the known state transitions assigns state to
+    /// the seal.
+    pub fn extract_for_terminal(&self, seal: XChain<SecretSeal>) -> Option<Transfer> {
+        let transitions = self
+            .bundles
+            .iter()
+            .flat_map(|witness_bundle| {
+                let bundle_id = witness_bundle.bundle.bundle_id();
+                witness_bundle
+                    .bundle
+                    .known_transitions
+                    .iter()
+                    .map(move |(opid, transition)| (*opid, (bundle_id, transition)))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let (terminal_id, (bundle_id, _)) = transitions.iter().find(|(_, (_, transition))| {
+            transition
+                .assignments
+                .values()
+                .any(|assigns| assigns.to_confidential_seals().contains(&seal))
+        })?;
+        let (terminal_id, bundle_id) = (*terminal_id, *bundle_id);
+
+        // Collect the history of the terminal transition
+        let mut history = BTreeSet::new();
+        let mut ids = vec![terminal_id];
+        while let Some(id) = ids.pop() {
+            if !history.insert(id) {
+                continue;
+            }
+            if let Some((_, transition)) = transitions.get(&id) {
+                ids.extend(transition.inputs().iter().map(|input| input.prev_out.op));
+            }
+        }
+
+        let mut bundles = LargeOrdSet::with_capacity(self.bundles.len());
+        for witness_bundle in &self.bundles {
+            let mut known_transitions = BTreeMap::new();
+            for (opid, transition) in &witness_bundle.bundle.known_transitions {
+                if !history.contains(opid) {
+                    continue;
+                }
+                let mut transition = transition.clone();
+                if *opid == terminal_id {
+                    for assigns in transition.assignments.values_mut() {
+                        conceal_except(assigns, seal);
+                    }
+                }
+                known_transitions.insert(*opid, transition);
+            }
+            // Bundles without the transitions from the history are not needed
+            let Ok(known_transitions) = Confined::try_from(known_transitions) else {
+                continue;
+            };
+            let mut witness_bundle = witness_bundle.clone();
+            witness_bundle.bundle.known_transitions = known_transitions;
+            bundles.push(witness_bundle).ok();
+        }
+
+        // Supplements and signatures are kept only for the content present in
+        // the extracted consignment
+        let content = iter::once(ContentRef::Schema(self.schema.schema_id()))
+            .chain(iter::once(ContentRef::Genesis(self.contract_id())))
+            .chain(
+                self.ifaces
+                    .keys()
+                    .map(|iface| ContentRef::Iface(iface.iface_id())),
+            )
+            .chain(
+                self.ifaces
+                    .values()
+                    .map(|iimpl| ContentRef::IfaceImpl(iimpl.impl_id())),
+            )
+            .collect::<BTreeSet<_>>();
+        let supplements = self
+            .supplements
+            .iter()
+            .filter(|suppl| content.contains(&suppl.content_id))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        let suppl_ids = supplements
+            .iter()
+            .map(Supplement::suppl_id)
+            .collect::<BTreeSet<_>>();
+        let signatures = self
+            .signatures
+            .iter()
+            .filter(|(content_id, _)| match content_id {
+                ContentId::Schema(id) => content.contains(&ContentRef::Schema(*id)),
+                ContentId::Genesis(id) => content.contains(&ContentRef::Genesis(*id)),
+                ContentId::Iface(id) => content.contains(&ContentRef::Iface(*id)),
+                ContentId::IfaceImpl(id) => content.contains(&ContentRef::IfaceImpl(*id)),
+                ContentId::Suppl(id) => suppl_ids.contains(id),
+            })
+            .map(|(content_id, sigs)| (*content_id, sigs.clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        Some(Transfer {
+            version: self.version,
+            transfer: self.transfer,
+            terminals: Confined::from_checked(bmap! { bundle_id => seal }),
+            genesis: self.genesis.clone(),
+            extensions: self.extensions.clone(),
+            bundles,
+            schema: self.schema.clone(),
+            ifaces: self.ifaces.clone(),
+            supplements: Confined::from_checked(supplements),
+            types: self.types.clone(),
+            scripts: self.scripts.clone(),
+            attachments: self.attachments.clone(),
+            signatures: Confined::from_checked(signatures),
+            // Records may be addressed to the other recipients of the transfer
+            records: none!(),
+        })
+    }
+}
+
+/// Business-level mismatch between a transfer and the invoice it is supposed
+/// to pay, detected by [`Transfer::satisfies_invoice`].
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum InvoiceMismatch {
+    /// the transfer is made for contract {actual}, while the invoice requires
+    /// contract {expected}.
+    Contract {
+        expected: ContractId,
+        actual: ContractId,
+    },
+
+    /// the transfer doesn't provide an implementation of interface {0}
+    /// required by the invoice.
+    Iface(TypeName),
+
+    /// operation {0} required by the invoice is not implemented by the
+    /// contract.
+    UnknownOperation(FieldName),
+
+    /// assignment {0} required by the invoice is not implemented by the
+    /// contract.
+    UnknownAssignment(FieldName),
+
+    /// none of the transfer terminals matches the invoice beneficiary.
+    NoBeneficiary,
+
+    /// state is assigned to the beneficiary by an operation which is not {0}
+    /// operation required by the invoice.
+    Operation(FieldName),
+
+    /// the transfer assigns {actual} to the beneficiary, while the invoice
+    /// requires {expected}.
+    Amount { expected: Amount, actual: Amount },
+
+    /// the transfer doesn't assign to the beneficiary the data required by the
+    /// invoice.
+    Data,
+
+    /// the invoice has expired at {0}.
+    Expired(i64),
+}
+
+/// Information about the state assigned by a transfer to the invoice
+/// beneficiary, returned by [`Transfer::satisfies_invoice`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Satisfaction {
+    pub contract_id: ContractId,
+    /// Terminal seals of the transfer matching the invoice beneficiary.
+    pub terminals: BTreeSet<XChain<SecretSeal>>,
+    /// Operations assigning state to the beneficiary.
+    pub opids: BTreeSet<OpId>,
+    /// State assigned to the beneficiary, if revealed.
+    pub state: Vec<AllocatedState>,
+}
+
+impl Satisfaction {
+    /// Total amount of fungible state assigned to the beneficiary.
+    pub fn amount(&self) -> Amount {
+        self.state
+            .iter()
+            .filter_map(|state| match state {
+                AllocatedState::Amount(amount) => Some(*amount),
+                _ => None,
+            })
+            .sum()
+    }
+}
+
+impl Transfer {
+    /// Checks whether the transfer pays the invoice, verifying its contract,
+    /// interface, operation, amount, expiry and the presence of the
+    /// beneficiary seal among the terminals.
+    ///
+    /// The check is a business-level one and is performed independently of
+    /// the consensus-level validation of the consignment. Transfers assigning
+    /// amounts exceeding the one requested by the invoice are accepted.
+    ///
+    /// For witness output invoices the beneficiary seal is not known before
+    /// the witness transaction is created, thus all the transfer terminals are
+    /// considered to belong to the beneficiary.
+    pub fn satisfies_invoice(&self, invoice: &RgbInvoice) -> Result<Satisfaction, InvoiceMismatch> {
+        if let Some(expiry) = invoice.expiry {
+            if expiry < Utc::now().timestamp() {
+                return Err(InvoiceMismatch::Expired(expiry));
+            }
+        }
+
+        let contract_id = self.contract_id();
+        if let Some(expected) = invoice.contract {
+            if expected != contract_id {
+                return Err(InvoiceMismatch::Contract {
+                    expected,
+                    actual: contract_id,
+                });
+            }
+        }
+
+        let iimpl = match &invoice.iface {
+            Some(name) => Some(
+                self.ifaces
+                    .iter()
+                    .find(|(iface, _)| &iface.name == name)
+                    .map(|(_, iimpl)| iimpl)
+                    .ok_or_else(|| InvoiceMismatch::Iface(name.clone()))?,
+            ),
+            None => None,
+        };
+        let transition_type = match (&invoice.operation, iimpl) {
+            (Some(name), Some(iimpl)) => Some(
+                iimpl
+                    .transition_type(name)
+                    .ok_or_else(|| InvoiceMismatch::UnknownOperation(name.clone()))?,
+            ),
+            _ => None,
+        };
+        let assignment_type = match (&invoice.assignment, iimpl) {
+            (Some(name), Some(iimpl)) => Some(
+                iimpl
+                    .assignments_type(name)
+                    .ok_or_else(|| InvoiceMismatch::UnknownAssignment(name.clone()))?,
+            ),
+            _ => None,
+        };
+
+        let terminals = match invoice.beneficiary.into_inner() {
+            Beneficiary::BlindedSeal(secret) => {
+                let secret = XChain::with(invoice.layer1(), secret);
+                self.terminals
+                    .values()
+                    .filter(|seal| **seal == secret)
+                    .copied()
+                    .collect::<BTreeSet<_>>()
+            }
+            Beneficiary::WitnessVout(_) => self.terminals.values().copied().collect(),
+        };
+        if terminals.is_empty() {
+            return Err(InvoiceMismatch::NoBeneficiary);
+        }
+
+        let mut opids = BTreeSet::new();
+        let mut state = vec![];
+        for witness_bundle in &self.bundles {
+            let bundle_id = witness_bundle.bundle.bundle_id();
+            if !self
+                .terminals
+                .get(&bundle_id)
+                .map(|seal| terminals.contains(seal))
+                .unwrap_or_default()
+            {
+                continue;
+            }
+            for view in witness_bundle.bundle.inspect(None) {
+                let assignments = view
+                    .assignments
+                    .iter()
+                    .filter(|a| terminals.contains(&a.secret_seal))
+                    .filter(|a| assignment_type.map(|ty| a.opout.ty == ty).unwrap_or(true))
+                    .collect::<Vec<_>>();
+                if assignments.is_empty() {
+                    continue;
+                }
+                if let (Some(ty), Some(name)) = (transition_type, &invoice.operation) {
+                    if view.transition_type != ty {
+                        return Err(InvoiceMismatch::Operation(name.clone()));
+                    }
+                }
+                opids.insert(view.opid);
+                state.extend(assignments.into_iter().filter_map(|a| a.state.clone()));
+            }
+        }
+        if opids.is_empty() {
+            return Err(InvoiceMismatch::NoBeneficiary);
+        }
+
+        let satisfaction = Satisfaction {
+            contract_id,
+            terminals,
+            opids,
+            state,
+        };
+        match &invoice.owned_state {
+            InvoiceState::Void | InvoiceState::Attach(_) => {}
+            InvoiceState::Amount(expected) => {
+                let actual = satisfaction.amount();
+                if actual < *expected {
+                    return Err(InvoiceMismatch::Amount {
+                        expected: *expected,
+                        actual,
+                    });
+                }
+            }
+            InvoiceState::Data(NonFungible::RGB21(allocation)) => {
+                let data = DataState::from(*allocation);
+                if !satisfaction
+                    .state
+                    .iter()
+                    .any(|state| matches!(state, AllocatedState::Data(d) if *d == data))
+                {
+                    return Err(InvoiceMismatch::Data);
+                }
+            }
+        }
+        Ok(satisfaction)
+    }
+}
+
+fn conceal_except(assigns: &mut TypedAssigns<GraphSeal>, seal: XChain<SecretSeal>) {
+    fn conceal<State: ExposedState>(
+        list: &mut SmallVec<Assign<State, GraphSeal>>,
+        seal: XChain<SecretSeal>,
+    ) where
+        Assign<State, GraphSeal>: Clone,
+    {
+        for assign in list.iter_mut() {
+            if assign.to_confidential_seal() != seal {
+                *assign = assign.conceal();
+            }
+        }
+    }
+    match assigns {
+        TypedAssigns::Declarative(list) => conceal(list, seal),
+        TypedAssigns::Fungible(list) => conceal(list, seal),
+        TypedAssigns::Structured(list) => conceal(list, seal),
+        TypedAssigns::Attachment(list) => conceal(list, seal),
+    }
+}
+
 impl<const TRANSFER: bool> StrictArmor for Consignment<TRANSFER> {
     type Id = ConsignmentId;
     const PLATE_TITLE: &'static str = "RGB CONSIGNMENT";
@@ -456,8 +1308,142 @@ impl<const TRANSFER: bool> FromStr for Consignment<TRANSFER> {
 
 #[cfg(test)]
 mod test {
+    use bp::seals::txout::CloseMethod;
+    use bp::{Txid, Vout};
+    use rgb::{AssetTag, AssignmentType, Assignments, RevealedValue, Transition};
+
     use super::*;
 
+    #[test]
+    fn extract_for_terminal() {
+        let seal = |no: u32| {
+            XChain::Bitcoin(GraphSeal::new_random(
+                CloseMethod::OpretFirst,
+                Txid::from([1u8; 32]),
+                Vout::from_u32(no),
+            ))
+        };
+        let (seal1, seal2) = (seal(0), seal(1));
+        let assign = |seal: XChain<GraphSeal>, value: u64| {
+            Assign::revealed(
+                seal,
+                RevealedValue::new_random_blinding(value, AssetTag::from([2u8; 32])),
+            )
+        };
+        let mut transition = Transition::strict_dumb();
+        transition.assignments = Assignments::from(Confined::from_checked(bmap! {
+            AssignmentType::with(4000) => TypedAssigns::Fungible(
+                Confined::from_checked(vec![assign(seal1, 10), assign(seal2, 20)])
+            )
+        }));
+        let opid = transition.id();
+        let mut witness_bundle = WitnessBundle::strict_dumb();
+        witness_bundle.bundle.known_transitions =
+            Confined::from_checked(bmap! { opid => transition });
+        let mut transfer = Transfer::strict_dumb();
+        transfer.bundles = Confined::from_checked(bset! { witness_bundle });
+        // Memo addressed to the other recipient
+        transfer
+            .records
+            .insert(tn!("EncryptedMemo"), MediumBlob::from_slice_checked(b"memo for seal2"))
+            .unwrap();
+        let schema_suppl = Supplement::new(transfer.schema_id(), "ssi:issuer");
+        let foreign_suppl = Supplement::new(ContractId::from([3u8; 32]), "ssi:issuer");
+        transfer.supplements =
+            Confined::from_checked(bset! { schema_suppl.clone(), foreign_suppl.clone() });
+        transfer.signatures = Confined::from_checked(bmap! {
+            ContentId::Schema(transfer.schema_id()) => ContentSigs::strict_dumb(),
+            ContentId::Suppl(schema_suppl.suppl_id()) => ContentSigs::strict_dumb(),
+            ContentId::Suppl(foreign_suppl.suppl_id()) => ContentSigs::strict_dumb(),
+        });
+
+        let secret1 = seal1.conceal();
+        let extracted = transfer.extract_for_terminal(secret1).unwrap();
+        assert!(extracted.records.is_empty());
+        assert_eq!(extracted.supplements.iter().collect::<Vec<_>>(), vec![&schema_suppl]);
+        assert_eq!(
+            extracted
+                .signatures
+                .keys()
+                .copied()
+                .collect::<BTreeSet<_>>(),
+            bset! {
+                ContentId::Schema(transfer.schema_id()),
+                ContentId::Suppl(schema_suppl.suppl_id()),
+            }
+        );
+        assert_eq!(extracted.terminals.len(), 1);
+        let witness_bundle = extracted.bundles.first().unwrap();
+        assert_eq!(extracted.terminals.get(&witness_bundle.bundle.bundle_id()), Some(&secret1));
+        let transition = &witness_bundle.bundle.known_transitions[&opid];
+        assert_eq!(transition.id(), opid);
+        let assigns = transition.assignments[&AssignmentType::with(4000)].as_fungible();
+        assert!(matches!(assigns[0], Assign::Revealed { .. }));
+        assert!(matches!(assigns[1], Assign::Confidential { .. }));
+
+        assert!(transfer.extract_for_terminal(seal(2).conceal()).is_none());
+    }
+
+    #[test]
+    fn satisfies_invoice() {
+        use invoice::{RgbInvoiceBuilder, XChainNet};
+
+        let seal = XChain::Bitcoin(GraphSeal::new_random(
+            CloseMethod::OpretFirst,
+            Txid::from([1u8; 32]),
+            Vout::from_u32(0),
+        ));
+        let mut transition = Transition::strict_dumb();
+        transition.assignments = Assignments::from(Confined::from_checked(bmap! {
+            AssignmentType::with(4000) => TypedAssigns::Fungible(Confined::from_checked(vec![
+                Assign::revealed(
+                    seal,
+                    RevealedValue::new_random_blinding(10, AssetTag::from([2u8; 32])),
+                )
+            ]))
+        }));
+        let opid = transition.id();
+        let mut witness_bundle = WitnessBundle::strict_dumb();
+        witness_bundle.bundle.known_transitions =
+            Confined::from_checked(bmap! { opid => transition });
+        let bundle_id = witness_bundle.bundle.bundle_id();
+        let mut transfer = Transfer::strict_dumb();
+        transfer.bundles = Confined::from_checked(bset! { witness_bundle });
+        transfer.terminals = Confined::from_checked(bmap! { bundle_id => seal.conceal() });
+
+        let invoice = |secret: SecretSeal, amount: u64| {
+            let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+            RgbInvoiceBuilder::with(transfer.contract_id(), beneficiary)
+                .set_amount_raw(amount)
+                .finish()
+        };
+        let secret = seal.as_reduced_unsafe().conceal();
+        let satisfaction = transfer.satisfies_invoice(&invoice(secret, 10)).unwrap();
+        assert_eq!(satisfaction.opids, bset![opid]);
+        assert_eq!(satisfaction.amount(), Amount::from(10u64));
+
+        assert_eq!(
+            transfer.satisfies_invoice(&invoice(secret, 11)),
+            Err(InvoiceMismatch::Amount {
+                expected: Amount::from(11u64),
+                actual: Amount::from(10u64)
+            })
+        );
+        assert_eq!(
+            transfer.satisfies_invoice(&invoice(SecretSeal::strict_dumb(), 10)),
+            Err(InvoiceMismatch::NoBeneficiary)
+        );
+        let mut expired = invoice(secret, 10);
+        expired.expiry = Some(1);
+        assert_eq!(transfer.satisfies_invoice(&expired), Err(InvoiceMismatch::Expired(1)));
+        let mut other = invoice(secret, 10);
+        other.contract = Some(ContractId::from([1u8; 32]));
+        assert!(matches!(
+            transfer.satisfies_invoice(&other),
+            Err(InvoiceMismatch::Contract { .. })
+        ));
+    }
+
     #[test]
     fn contract_str_round_trip() {
         let s = include_str!("../../asset/armored_contract.default");
@@ -515,6 +1501,59 @@ Check-SHA256: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa
         assert_eq!(transfer.to_string(), s.replace('\r', ""), "transfer string round trip fails");
     }
 
+    #[test]
+    fn streamed_consignment_id() {
+        let s = include_str!("../../asset/armored_transfer.default");
+        let mut transfer = Transfer::from_str(s).unwrap();
+        assert_eq!(transfer.version, ContainerVer::V2);
+        for version in [ContainerVer::V2, ContainerVer::V3] {
+            transfer.version = version;
+            let data = transfer.to_strict_serialized::<U32>().unwrap();
+            assert_eq!(
+                ConsignmentId::from_strict_reader(data.as_slice()).unwrap(),
+                transfer.consignment_id()
+            );
+            assert!(ConsignmentId::from_strict_reader(&data[..data.len() - 1]).is_err());
+        }
+    }
+
+    #[test]
+    fn asset_tags_consistency() {
+        let seal = XChain::Bitcoin(GraphSeal::new_random(
+            CloseMethod::OpretFirst,
+            Txid::from([1u8; 32]),
+            Vout::from_u32(0),
+        ));
+        let transition = |tags: [u8; 2]| {
+            let mut transition = Transition::strict_dumb();
+            transition.assignments = Assignments::from(Confined::from_checked(bmap! {
+                AssignmentType::with(4000) => TypedAssigns::Fungible(Confined::from_checked(
+                    tags.iter()
+                        .map(|tag| Assign::revealed(
+                            seal,
+                            RevealedValue::new_random_blinding(10, AssetTag::from([*tag; 32])),
+                        ))
+                        .collect()
+                ))
+            }));
+            transition
+        };
+        assert_eq!(mixed_asset_tags(&transition([2, 2])), None);
+        assert_eq!(mixed_asset_tags(&transition([2, 3])), Some(AssignmentType::with(4000)));
+    }
+
+    #[test]
+    fn commitment_layout() {
+        let s = include_str!("../../asset/armored_transfer.default");
+        let transfer = Transfer::from_str(s).unwrap();
+        let mut layout = transfer.commitment_layout();
+        assert_eq!(layout.consignment_id, transfer.consignment_id());
+        assert_eq!(layout.components.len(), 13);
+        assert!(layout.verify());
+        layout.components.swap(0, 1);
+        assert!(!layout.verify());
+    }
+
     #[test]
     fn error_transfer_strs() {
         let s = include_str!("../../asset/armored_transfer.default");
@@ -582,4 +1621,31 @@ Check-SHA256: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa
             Err(ConsignmentParseError::Type)
         ));
     }
+
+    #[test]
+    fn decode_records() {
+        let mut transfer = Transfer::strict_dumb();
+        assert_eq!(transfer.version, ContainerVer::V3);
+        let data = transfer.to_strict_serialized::<U32>().unwrap().release();
+        // Truncated records map is rejected
+        let truncated = Confined::try_from(data[..data.len() - 1].to_vec()).unwrap();
+        assert!(Transfer::from_strict_serialized::<U32>(truncated).is_err());
+
+        transfer
+            .records
+            .insert(tn!("Memo"), MediumBlob::from_checked(vec![1, 2, 3]))
+            .unwrap();
+        let data = transfer.to_strict_serialized::<U32>().unwrap();
+        assert_eq!(Transfer::from_strict_serialized::<U32>(data.clone()).unwrap(), transfer);
+        let data = data.release();
+        let truncated = Confined::try_from(data[..data.len() - 2].to_vec()).unwrap();
+        assert!(Transfer::from_strict_serialized::<U32>(truncated).is_err());
+
+        // Consignments of the earlier version end right after the signatures
+        transfer.version = ContainerVer::V2;
+        let legacy = transfer.to_strict_serialized::<U32>().unwrap();
+        assert_eq!(legacy.len(), data.len() - 12);
+        transfer.records = none!();
+        assert_eq!(Transfer::from_strict_serialized::<U32>(legacy).unwrap(), transfer);
+    }
 }
@@ -303,6 +303,7 @@ mod test {
             scripts: Default::default(),
             attachments: Default::default(),
             signatures: Default::default(),
+            records: Default::default(),
         }
     }
 
@@ -401,6 +402,7 @@ mod test {
             scripts: Default::default(),
             attachments: Default::default(),
             signatures: Default::default(),
+            records: Default::default(),
         }
     }
 
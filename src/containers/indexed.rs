@@ -19,61 +19,175 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::Hash;
 use std::ops::Deref;
+use std::sync::Arc;
 
 use rgb::validation::{ConsignmentApi, EAnchor, OpRef, Scripts};
 use rgb::{
-    BundleId, Extension, Genesis, OpId, Operation, Schema, Transition, TransitionBundle, XWitnessId,
+    BundleId, Extension, Genesis, OpId, Operation, Schema, Transition, TransitionBundle,
+    ValencyType, XWitnessId,
 };
 use strict_types::TypeSystem;
 
-use super::{Consignment, XPubWitness};
-use crate::containers::anchors::ToWitnessId;
+use super::{Consignment, ToWitnessId, WitnessId, XPubWitness};
 
+/// Number of operations in a consignment starting from which the index uses
+/// hash maps for the lookups.
+///
+/// The value is chosen with the `index` benchmark (`cargo bench --bench
+/// index`): B-tree lookups of 32-byte ids are faster for consignments of a few
+/// dozens operations, hash lookups overtake them starting from ~64 operations
+/// and are several times faster for thousands of operations. The cost of the
+/// index construction is dominated by computing operation and bundle ids and
+/// does not depend on the backend.
+pub const HASH_INDEX_THRESHOLD: usize = 64;
+
+/// Backend of the maps used by [`IndexedConsignment`] for the lookups where
+/// the ordering is irrelevant.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum IndexBackend {
+    #[default]
+    BTree,
+    Hash,
+}
+
+impl IndexBackend {
+    /// Selects backend for indexing a consignment with the given number of
+    /// operations, using [`HASH_INDEX_THRESHOLD`].
+    pub fn select(operations: usize) -> Self {
+        if operations >= HASH_INDEX_THRESHOLD { IndexBackend::Hash } else { IndexBackend::BTree }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum IdxMap<K, V> {
+    BTree(BTreeMap<K, V>),
+    Hash(HashMap<K, V>),
+}
+
+impl<K: Ord + Hash, V> IdxMap<K, V> {
+    fn new(backend: IndexBackend) -> Self {
+        match backend {
+            IndexBackend::BTree => IdxMap::BTree(empty!()),
+            IndexBackend::Hash => IdxMap::Hash(empty!()),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        match self {
+            IdxMap::BTree(map) => map.get(key),
+            IdxMap::Hash(map) => map.get(key),
+        }
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        match self {
+            IdxMap::BTree(map) => map.insert(key, value),
+            IdxMap::Hash(map) => map.insert(key, value),
+        };
+    }
+
+    fn get_or_default(&mut self, key: K) -> &mut V
+    where V: Default {
+        match self {
+            IdxMap::BTree(map) => map.entry(key).or_default(),
+            IdxMap::Hash(map) => map.entry(key).or_default(),
+        }
+    }
+}
+
+/// Index over consignment operations, bundles and their witnesses.
+///
+/// The index refers to witness transactions with the [`WitnessId`] produced
+/// by the public witness of the consignment bundles via [`ToWitnessId`].
+/// Validation API of RGB consensus requires [`XWitnessId`], thus
+/// [`ConsignmentApi`] is implemented for the index with this witness id only.
 // TODO: Transform consignment into this type instead of composing over it
 #[derive(Clone, Debug)]
-pub struct IndexedConsignment<'c, const TRANSFER: bool> {
+pub struct IndexedConsignment<'c, const TRANSFER: bool, W: WitnessId = XWitnessId> {
     consignment: &'c Consignment<TRANSFER>,
+    backend: IndexBackend,
     scripts: Scripts,
-    anchor_idx: BTreeMap<BundleId, (XWitnessId, &'c EAnchor)>,
-    bundle_idx: BTreeMap<BundleId, &'c TransitionBundle>,
-    op_witness_idx: BTreeMap<OpId, XWitnessId>,
-    op_bundle_idx: BTreeMap<OpId, BundleId>,
-    extension_idx: BTreeMap<OpId, &'c Extension>,
-    witness_idx: BTreeMap<XWitnessId, &'c XPubWitness>,
+    anchor_idx: IdxMap<BundleId, (W, &'c EAnchor)>,
+    bundle_idx: IdxMap<BundleId, &'c TransitionBundle>,
+    /// Ids of all bundles in a deterministic order, independent from the
+    /// backend, shared with the iterators returned by `bundle_ids`.
+    bundle_ids: Arc<[BundleId]>,
+    op_witness_idx: IdxMap<OpId, W>,
+    op_bundle_idx: IdxMap<OpId, BundleId>,
+    extension_idx: IdxMap<OpId, &'c Extension>,
+    witness_idx: IdxMap<W, &'c XPubWitness>,
+    /// Operations declaring valencies of a given type.
+    valency_idx: IdxMap<ValencyType, BTreeSet<OpId>>,
+    /// Extensions redeeming a valency declared by an operation.
+    redeem_idx: BTreeMap<(OpId, ValencyType), BTreeSet<OpId>>,
 }
 
-impl<'c, const TRANSFER: bool> Deref for IndexedConsignment<'c, TRANSFER> {
+impl<'c, const TRANSFER: bool, W: WitnessId> Deref for IndexedConsignment<'c, TRANSFER, W> {
     type Target = Consignment<TRANSFER>;
 
     fn deref(&self) -> &Self::Target { self.consignment }
 }
 
-impl<'c, const TRANSFER: bool> IndexedConsignment<'c, TRANSFER> {
+impl<'c, const TRANSFER: bool, W: WitnessId> IndexedConsignment<'c, TRANSFER, W>
+where XPubWitness: ToWitnessId<WitnessId = W>
+{
+    /// Indexes consignment, selecting the index backend basing on the number
+    /// of the operations in the consignment with [`IndexBackend::select`].
     pub fn new(consignment: &'c Consignment<TRANSFER>) -> Self {
-        let mut anchor_idx = BTreeMap::new();
-        let mut bundle_idx = BTreeMap::new();
-        let mut op_witness_idx = BTreeMap::new();
-        let mut op_bundle_idx = BTreeMap::new();
-        let mut extension_idx = BTreeMap::new();
-        let mut witness_idx = BTreeMap::new();
+        let operations = consignment
+            .bundles
+            .iter()
+            .map(|wb| wb.bundle.known_transitions.len())
+            .sum::<usize>()
+            + consignment.extensions.len()
+            + 1;
+        Self::with_backend(consignment, IndexBackend::select(operations))
+    }
+
+    /// Indexes consignment using the provided index backend.
+    pub fn with_backend(consignment: &'c Consignment<TRANSFER>, backend: IndexBackend) -> Self {
+        let mut anchor_idx = IdxMap::new(backend);
+        let mut bundle_idx = IdxMap::new(backend);
+        let mut bundle_ids = BTreeSet::new();
+        let mut op_witness_idx = IdxMap::new(backend);
+        let mut op_bundle_idx = IdxMap::new(backend);
+        let mut extension_idx = IdxMap::new(backend);
+        let mut witness_idx = IdxMap::new(backend);
+        let mut valency_idx = IdxMap::<_, BTreeSet<_>>::new(backend);
+        // Iterated over ranges of the keys, thus must remain ordered
+        let mut redeem_idx = BTreeMap::<_, BTreeSet<_>>::new();
+        let mut index_valencies = |opid: OpId, op: &dyn Operation| {
+            for ty in op.valencies() {
+                valency_idx.get_or_default(ty).insert(opid);
+            }
+        };
+        index_valencies(consignment.genesis.id(), &consignment.genesis);
         for witness_bundle in &consignment.bundles {
-            witness_idx
-                .insert(witness_bundle.pub_witness.to_witness_id(), &witness_bundle.pub_witness);
+            let witness_id = witness_bundle.pub_witness.to_witness_id();
+            witness_idx.insert(witness_id, &witness_bundle.pub_witness);
             let bundle = &witness_bundle.bundle;
             let bundle_id = bundle.bundle_id();
-            let witness_id = witness_bundle.pub_witness.to_witness_id();
             bundle_idx.insert(bundle_id, bundle);
+            bundle_ids.insert(bundle_id);
             anchor_idx.insert(bundle_id, (witness_id, &witness_bundle.anchor));
-            for opid in witness_bundle.bundle.known_transitions.keys() {
+            for (opid, transition) in &witness_bundle.bundle.known_transitions {
                 op_witness_idx.insert(*opid, witness_id);
                 op_bundle_idx.insert(*opid, bundle_id);
+                index_valencies(*opid, transition);
             }
         }
         for extension in &consignment.extensions {
-            extension_idx.insert(extension.id(), extension);
+            let opid = extension.id();
+            extension_idx.insert(opid, extension);
+            index_valencies(opid, extension);
+            for (ty, prev_id) in extension.redeemed() {
+                redeem_idx.entry((*prev_id, *ty)).or_default().insert(opid);
+            }
         }
+        let bundle_ids = bundle_ids.into_iter().collect();
         let scripts = Scripts::from_iter_checked(
             consignment
                 .scripts
@@ -82,16 +196,23 @@ impl<'c, const TRANSFER: bool> IndexedConsignment<'c, TRANSFER> {
         );
         Self {
             consignment,
+            backend,
             scripts,
             anchor_idx,
             bundle_idx,
+            bundle_ids,
             op_witness_idx,
             op_bundle_idx,
             extension_idx,
             witness_idx,
+            valency_idx,
+            redeem_idx,
         }
     }
 
+    /// Returns backend used by the index.
+    pub fn backend(&self) -> IndexBackend { self.backend }
+
     fn extension(&self, opid: OpId) -> Option<&Extension> { self.extension_idx.get(&opid).copied() }
 
     fn transition(&self, opid: OpId) -> Option<&Transition> {
@@ -101,12 +222,59 @@ impl<'c, const TRANSFER: bool> IndexedConsignment<'c, TRANSFER> {
             .and_then(|bundle| bundle.known_transitions.get(&opid))
     }
 
-    pub fn pub_witness(&self, id: XWitnessId) -> Option<&XPubWitness> {
-        self.witness_idx.get(&id).copied()
+    pub fn pub_witness(&self, id: W) -> Option<&XPubWitness> { self.witness_idx.get(&id).copied() }
+
+    /// Returns id of the witness of the bundle.
+    pub fn bundle_witness_id(&self, bundle_id: BundleId) -> Option<W> {
+        self.anchor_idx.get(&bundle_id).map(|(id, _)| *id)
+    }
+
+    /// Returns id of the witness of the bundle containing the operation.
+    pub fn op_witness(&self, opid: OpId) -> Option<W> { self.op_witness_idx.get(&opid).copied() }
+
+    /// Returns ids of operations declaring valencies of the given type.
+    pub fn valency_declarations(&self, ty: ValencyType) -> impl Iterator<Item = OpId> + '_ {
+        self.valency_idx.get(&ty).into_iter().flatten().copied()
+    }
+
+    /// Detects whether the operation declares valency of the given type.
+    pub fn declares_valency(&self, opid: OpId, ty: ValencyType) -> bool {
+        self.valency_idx
+            .get(&ty)
+            .map(|ops| ops.contains(&opid))
+            .unwrap_or_default()
+    }
+
+    /// Returns extensions redeeming valency of the given type declared by the
+    /// operation.
+    pub fn redeeming_extensions(
+        &self,
+        opid: OpId,
+        ty: ValencyType,
+    ) -> impl Iterator<Item = &Extension> + '_ {
+        self.redeem_idx
+            .get(&(opid, ty))
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.extension(*id))
+    }
+
+    /// Returns all valencies of the operation which were redeemed, together
+    /// with the extensions redeeming them.
+    pub fn redeemed_valencies(
+        &self,
+        opid: OpId,
+    ) -> impl Iterator<Item = (ValencyType, &Extension)> + '_ {
+        self.redeem_idx
+            .range((opid, ValencyType::with(u16::MIN))..=(opid, ValencyType::with(u16::MAX)))
+            .flat_map(move |((_, ty), ids)| {
+                ids.iter()
+                    .filter_map(move |id| self.extension(*id).map(|ext| (*ty, ext)))
+            })
     }
 }
 
-impl<'c, const TRANSFER: bool> ConsignmentApi for IndexedConsignment<'c, TRANSFER> {
+impl<'c, const TRANSFER: bool> ConsignmentApi for IndexedConsignment<'c, TRANSFER, XWitnessId> {
     fn schema(&self) -> &Schema { &self.schema }
 
     fn types(&self) -> &TypeSystem { &self.types }
@@ -125,11 +293,8 @@ impl<'c, const TRANSFER: bool> ConsignmentApi for IndexedConsignment<'c, TRANSFE
     fn genesis(&self) -> &Genesis { &self.genesis }
 
     fn bundle_ids<'iter>(&self) -> impl Iterator<Item = BundleId> + 'iter {
-        self.bundle_idx
-            .keys()
-            .copied()
-            .collect::<BTreeSet<_>>()
-            .into_iter()
+        let ids = self.bundle_ids.clone();
+        (0..ids.len()).map(move |no| ids[no])
     }
 
     fn bundle(&self, bundle_id: BundleId) -> Option<&TransitionBundle> {
@@ -140,7 +305,81 @@ impl<'c, const TRANSFER: bool> ConsignmentApi for IndexedConsignment<'c, TRANSFE
         self.anchor_idx.get(&bundle_id).map(|(id, set)| (*id, *set))
     }
 
-    fn op_witness_id(&self, opid: OpId) -> Option<XWitnessId> {
-        self.op_witness_idx.get(&opid).copied()
+    fn op_witness_id(&self, opid: OpId) -> Option<XWitnessId> { self.op_witness(opid) }
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::containers::{Transfer, WitnessBundle};
+
+    #[test]
+    fn valency_index() {
+        let ty = ValencyType::with(1);
+        let mut transfer = Transfer::strict_dumb();
+        transfer.genesis.valencies.push(ty).unwrap();
+        let genesis_id = transfer.genesis.id();
+        let mut extension = Extension::strict_dumb();
+        extension.redeemed.insert(ty, genesis_id).unwrap();
+        let ext_id = extension.id();
+        transfer.extensions.push(extension).unwrap();
+
+        let index = IndexedConsignment::new(&transfer);
+        assert_eq!(index.valency_declarations(ty).collect::<Vec<_>>(), vec![genesis_id]);
+        assert!(index.declares_valency(genesis_id, ty));
+        assert!(!index.declares_valency(ext_id, ty));
+        assert_eq!(
+            index
+                .redeeming_extensions(genesis_id, ty)
+                .map(Extension::id)
+                .collect::<Vec<_>>(),
+            vec![ext_id]
+        );
+        assert_eq!(index.redeemed_valencies(genesis_id).count(), 1);
+        assert_eq!(index.redeemed_valencies(OpId::strict_dumb()).count(), 0);
+    }
+
+    #[test]
+    fn index_backends() {
+        let mut transfer = Transfer::strict_dumb();
+        let extension = Extension::strict_dumb();
+        let ext_id = extension.id();
+        transfer.extensions.push(extension).unwrap();
+
+        assert_eq!(IndexedConsignment::new(&transfer).backend(), IndexBackend::BTree);
+        assert_eq!(IndexBackend::select(HASH_INDEX_THRESHOLD - 1), IndexBackend::BTree);
+        assert_eq!(IndexBackend::select(HASH_INDEX_THRESHOLD), IndexBackend::Hash);
+        for backend in [IndexBackend::BTree, IndexBackend::Hash] {
+            let index = IndexedConsignment::with_backend(&transfer, backend);
+            assert_eq!(index.backend(), backend);
+            assert!(matches!(index.operation(ext_id), Some(OpRef::Extension(_))));
+            assert!(index.operation(OpId::strict_dumb()).is_none());
+            assert_eq!(index.bundle_ids().count(), 0);
+        }
+    }
+
+    #[test]
+    fn witness_index() {
+        let mut transfer = Transfer::strict_dumb();
+        let witness_bundle = WitnessBundle::strict_dumb();
+        let bundle_id = witness_bundle.bundle.bundle_id();
+        let pub_witness = witness_bundle.pub_witness.clone();
+        let witness_id = pub_witness.to_witness_id();
+        transfer.bundles.push(witness_bundle).unwrap();
+
+        let index = IndexedConsignment::new(&transfer);
+        assert_eq!(index.bundle_witness_id(bundle_id), Some(witness_id));
+        assert_eq!(index.pub_witness(witness_id), Some(&pub_witness));
+        assert_eq!(index.bundle_witness_id(BundleId::strict_dumb()), None);
+        for opid in transfer
+            .bundles
+            .iter()
+            .flat_map(|wb| wb.bundle.known_transitions.keys())
+        {
+            assert_eq!(index.op_witness(*opid), Some(witness_id));
+        }
+        assert_eq!(index.op_witness(Extension::strict_dumb().id()), None);
     }
 }
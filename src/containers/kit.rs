@@ -180,12 +180,69 @@ impl Kit {
         // TODO: Add sig validator
         //_: &impl SigValidator,
     ) -> Result<ValidKit, (validation::Status, Kit)> {
-        let status = validation::Status::new();
+        let mut status = validation::Status::new();
+
+        for iface in &self.ifaces {
+            if let Err(errors) = iface.check() {
+                for err in errors {
+                    status.add_failure(validation::Failure::Custom(format!(
+                        "interface {} is inconsistent: {err}",
+                        iface.name
+                    )));
+                }
+            }
+        }
+
+        for iimpl in &self.iimpls {
+            let iface = self.ifaces.iter().find(|i| i.iface_id() == iimpl.iface_id);
+            let schema = self
+                .schemata
+                .iter()
+                .find(|s| s.schema_id() == iimpl.schema_id);
+            let (Some(iface), Some(schema)) = (iface, schema) else {
+                status.add_warning(validation::Warning::Custom(format!(
+                    "implementation {} references interface {} or schema {} which are not part of \
+                     the kit",
+                    iimpl.impl_id(),
+                    iimpl.iface_id,
+                    iimpl.schema_id
+                )));
+                continue;
+            };
+            if let Err(errors) = iimpl.check(iface, schema) {
+                for err in errors {
+                    status.add_failure(validation::Failure::Custom(format!(
+                        "implementation {} is inconsistent: {err}",
+                        iimpl.impl_id()
+                    )));
+                }
+            }
+        }
+
+        for schema in &self.schemata {
+            let validators = schema
+                .genesis
+                .validator
+                .into_iter()
+                .chain(schema.transitions.values().filter_map(|t| t.validator))
+                .chain(schema.extensions.values().filter_map(|e| e.validator));
+            for site in validators {
+                if !self.scripts.iter().any(|lib| lib.id() == site.lib) {
+                    status.add_warning(validation::Warning::Custom(format!(
+                        "schema {} uses validation script {} which is not part of the kit",
+                        schema.schema_id(),
+                        site.lib
+                    )));
+                }
+            }
+        }
+
         // TODO:
-        //  - Verify integrity for each interface
-        //  - Verify implementations against interfaces
         //  - Check schema integrity
         //  - Validate content sigs and remove untrusted ones
+        if status.validity() == validation::Validity::Invalid {
+            return Err((status, self));
+        }
         Ok(ValidKit {
             validation_status: status,
             kit: self,
@@ -271,6 +328,8 @@ impl FromStr for Kit {
 
 #[cfg(test)]
 mod test {
+    use strict_encoding::StrictDumb;
+
     use super::*;
     #[test]
     fn kit_str_round_trip() {
@@ -287,6 +346,38 @@ mod test {
         );
     }
 
+    #[test]
+    fn kit_validation() {
+        let mut kit = Kit::default();
+        kit.iimpls.push(strict_dumb!()).unwrap();
+        let status = kit.validate().unwrap().into_validation_status();
+        assert!(status.failures.is_empty());
+        assert_eq!(status.warnings.len(), 1);
+    }
+
+    #[test]
+    fn kit_validation_failure() {
+        let iface = Iface::strict_dumb();
+        let schema = Schema::strict_dumb();
+        let mut iimpl = IfaceImpl::strict_dumb();
+        iimpl.iface_id = iface.iface_id();
+        iimpl.schema_id = schema.schema_id();
+        iimpl.timestamp = chrono::Utc::now().timestamp() + 3600;
+
+        let mut kit = Kit::default();
+        kit.ifaces.push(iface).unwrap();
+        kit.schemata.push(schema).unwrap();
+        kit.iimpls.push(iimpl).unwrap();
+        let (status, _) = kit.validate().unwrap_err();
+        assert_eq!(status.validity(), validation::Validity::Invalid);
+        assert!(
+            status
+                .failures
+                .iter()
+                .any(|f| f.to_string().contains("is inconsistent"))
+        );
+    }
+
     #[test]
     fn error_kit_strs() {
         assert!(
@@ -0,0 +1,457 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter;
+
+use aluvm::isa::{ControlFlowOp, Instr, InstructionSet};
+use aluvm::library::{Lib, LibId};
+use aluvm::reg::CALL_STACK_SIZE;
+use rgb::validation::CONSIGNMENT_MAX_LIBS;
+use rgb::vm::RgbIsa;
+use rgb::{OpId, Operation};
+
+use super::Consignment;
+use crate::persistence::MemContract;
+
+/// Maximal number of straight-line code segments executed by a single
+/// validation script.
+///
+/// Each of the segments ends with a jump, a call or a return. AluVM fails the
+/// script once it performs more than [`CALL_STACK_SIZE`] jumps and calls, and
+/// each of the returns matches one of the calls.
+const MAX_EXEC_SEGMENTS: u64 = 2 * CALL_STACK_SIZE as u64 + 1;
+
+/// Validation script resources exceeding the limits of [`ScriptLimits`].
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum ResourceExhausted {
+    /// consignment contains {0} script libraries, exceeding the limit of {1}.
+    Libs(usize, usize),
+
+    /// script library {0} has code segment of {1} bytes, exceeding the limit
+    /// of {2} bytes.
+    CodeSize(LibId, usize, usize),
+
+    /// script library {0} has data segment of {1} bytes, exceeding the limit
+    /// of {2} bytes.
+    DataSize(LibId, usize, usize),
+
+    /// script libraries require {0} bytes of memory, exceeding the limit of {1}
+    /// bytes.
+    Memory(usize, usize),
+
+    /// script library {0} contains {1} instructions, exceeding the limit of
+    /// {2}.
+    Instructions(LibId, usize, usize),
+
+    /// script library {0} has complexity {1}, exceeding the limit of {2}.
+    Complexity(LibId, u64, u64),
+
+    /// script library {0} contains invalid bytecode.
+    InvalidCode(LibId),
+
+    /// validation script of operation {0} may execute up to {1} instructions,
+    /// exceeding the limit of {2}.
+    ExecSteps(OpId, u64, u64),
+
+    /// validation script of operation {0} may accumulate complexity up to {1}
+    /// during the execution, exceeding the limit of {2}.
+    ExecComplexity(OpId, u64, u64),
+}
+
+/// Resources used by a validation script library.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct ScriptMeter {
+    /// Size of the code segment, in bytes.
+    pub code_len: usize,
+    /// Size of the data segment, in bytes.
+    pub data_len: usize,
+    /// Number of instructions in the code segment.
+    pub instructions: usize,
+    /// Sum of the complexity of all instructions in the code segment.
+    pub complexity: u64,
+    /// Number of instructions transferring control: jumps, calls and returns.
+    pub transfers: usize,
+}
+
+impl ScriptMeter {
+    /// Meters resources used by the script library.
+    pub fn with(lib: &Lib) -> Result<Self, ResourceExhausted> {
+        let code = lib
+            .disassemble::<Instr<RgbIsa<MemContract>>>()
+            .map_err(|_| ResourceExhausted::InvalidCode(lib.id()))?;
+        Ok(ScriptMeter {
+            code_len: lib.code.len(),
+            data_len: lib.data.len(),
+            instructions: code.len(),
+            complexity: code
+                .iter()
+                .map(InstructionSet::complexity)
+                .fold(0u64, u64::saturating_add),
+            transfers: code
+                .iter()
+                .filter(|instr| {
+                    matches!(
+                        instr,
+                        Instr::ControlFlow(
+                            ControlFlowOp::Jmp(_)
+                                | ControlFlowOp::Jif(_)
+                                | ControlFlowOp::Routine(_)
+                                | ControlFlowOp::Call(_)
+                                | ControlFlowOp::Exec(_)
+                                | ControlFlowOp::Ret
+                        )
+                    )
+                })
+                .count(),
+        })
+    }
+
+    /// Memory required to load the library into the VM, in bytes.
+    pub fn memory(&self) -> usize { self.code_len + self.data_len }
+}
+
+/// Limits on the resources which can be used by the validation scripts
+/// provided in a consignment.
+///
+/// All limits are checked before any of the scripts are executed. Execution
+/// limits are checked against the worst-case execution of the scripts, which
+/// is bounded by the AluVM limit on the number of jumps, such that the
+/// consensus validation run can't exceed them. This protects services
+/// accepting consignments from untrusted parties from maliciously expensive
+/// scripts.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct ScriptLimits {
+    /// Maximum number of script libraries.
+    pub max_libs: usize,
+    /// Maximum size of the code segment of a single library, in bytes.
+    pub max_code_len: usize,
+    /// Maximum size of the data segment of a single library, in bytes.
+    pub max_data_len: usize,
+    /// Maximum memory occupied by all libraries, in bytes.
+    pub max_memory: usize,
+    /// Maximum number of instructions in a single library.
+    pub max_instructions: usize,
+    /// Maximum complexity of a single library.
+    pub max_complexity: u64,
+    /// Maximum number of instructions which may be executed by the
+    /// validation script of a single operation.
+    pub max_exec_steps: u64,
+    /// Maximum complexity which may be accumulated during the execution of
+    /// the validation script of a single operation.
+    pub max_exec_complexity: u64,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self { Self::STANDARD }
+}
+
+impl ScriptLimits {
+    /// Limits matching the consensus limits on the script libraries.
+    pub const UNLIMITED: Self = ScriptLimits {
+        max_libs: CONSIGNMENT_MAX_LIBS,
+        max_code_len: u16::MAX as usize,
+        max_data_len: u16::MAX as usize,
+        max_memory: usize::MAX,
+        max_instructions: usize::MAX,
+        max_complexity: u64::MAX,
+        max_exec_steps: u64::MAX,
+        max_exec_complexity: u64::MAX,
+    };
+
+    /// Limits suitable for most of the wallets.
+    pub const STANDARD: Self = ScriptLimits {
+        max_libs: 256,
+        max_code_len: u16::MAX as usize,
+        max_data_len: u16::MAX as usize,
+        max_memory: 4 * 1024 * 1024,
+        max_instructions: 0x10000,
+        max_complexity: 100_000_000,
+        max_exec_steps: 1 << 32,
+        max_exec_complexity: 1 << 40,
+    };
+
+    /// Limits for public services accepting consignments from untrusted
+    /// parties.
+    pub const STRICT: Self = ScriptLimits {
+        max_libs: 32,
+        max_code_len: 0x4000,
+        max_data_len: 0x4000,
+        max_memory: 256 * 1024,
+        max_instructions: 0x1000,
+        max_complexity: 10_000_000,
+        max_exec_steps: 1 << 24,
+        max_exec_complexity: 1 << 32,
+    };
+
+    /// Checks that the script libraries fit the limits, returning the first
+    /// exceeded limit otherwise.
+    pub fn check<'lib>(
+        &self,
+        scripts: impl IntoIterator<Item = &'lib Lib>,
+    ) -> Result<(), ResourceExhausted> {
+        let mut libs = 0usize;
+        let mut memory = 0usize;
+        for lib in scripts {
+            libs += 1;
+            if libs > self.max_libs {
+                return Err(ResourceExhausted::Libs(libs, self.max_libs));
+            }
+            let id = lib.id();
+            let meter = ScriptMeter::with(lib)?;
+            if meter.code_len > self.max_code_len {
+                return Err(ResourceExhausted::CodeSize(id, meter.code_len, self.max_code_len));
+            }
+            if meter.data_len > self.max_data_len {
+                return Err(ResourceExhausted::DataSize(id, meter.data_len, self.max_data_len));
+            }
+            if meter.instructions > self.max_instructions {
+                return Err(ResourceExhausted::Instructions(
+                    id,
+                    meter.instructions,
+                    self.max_instructions,
+                ));
+            }
+            if meter.complexity > self.max_complexity {
+                return Err(ResourceExhausted::Complexity(
+                    id,
+                    meter.complexity,
+                    self.max_complexity,
+                ));
+            }
+            memory = memory.saturating_add(meter.memory());
+            if memory > self.max_memory {
+                return Err(ResourceExhausted::Memory(memory, self.max_memory));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that validation scripts of all consignment operations fit the
+    /// execution limits, returning the first exceeded limit otherwise.
+    ///
+    /// The scripts are not executed: the limits are checked against the
+    /// worst-case execution of each script, which can't perform more than
+    /// [`CALL_STACK_SIZE`] jumps and calls, executing between them no more
+    /// instructions than present in one of the libraries reachable from its
+    /// entry point. Thus, the limits hold for the consensus validation run.
+    /// Libraries absent from the consignment are left to the consensus
+    /// validation.
+    pub fn check_execution<const TRANSFER: bool>(
+        &self,
+        consignment: &Consignment<TRANSFER>,
+    ) -> Result<(), ResourceExhausted> {
+        let schema = &consignment.schema;
+        let libs = consignment
+            .scripts
+            .iter()
+            .map(|lib| (lib.id(), lib))
+            .collect::<BTreeMap<_, _>>();
+
+        let genesis = iter::once((consignment.genesis.id(), schema.genesis.validator));
+        let extensions = consignment.extensions.iter().map(|extension| {
+            let validator = schema
+                .extensions
+                .get(&extension.extension_type)
+                .and_then(|s| s.validator);
+            (extension.id(), validator)
+        });
+        let transitions = consignment
+            .bundles
+            .iter()
+            .flat_map(|wb| wb.bundle.known_transitions.values())
+            .map(|transition| {
+                let validator = schema
+                    .transitions
+                    .get(&transition.transition_type)
+                    .and_then(|s| s.validator);
+                (transition.id(), validator)
+            });
+
+        let mut bounds = BTreeMap::<LibId, (u64, u64)>::new();
+        for (opid, validator) in genesis.chain(extensions).chain(transitions) {
+            let Some(validator) = validator else {
+                continue;
+            };
+            let (steps, complexity) = match bounds.get(&validator.lib) {
+                Some(bound) => *bound,
+                None => {
+                    let bound = Self::exec_bound(validator.lib, &libs)?;
+                    bounds.insert(validator.lib, bound);
+                    bound
+                }
+            };
+            if steps > self.max_exec_steps {
+                return Err(ResourceExhausted::ExecSteps(opid, steps, self.max_exec_steps));
+            }
+            if complexity > self.max_exec_complexity {
+                return Err(ResourceExhausted::ExecComplexity(
+                    opid,
+                    complexity,
+                    self.max_exec_complexity,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Computes the maximal number of instructions and the maximal complexity
+    /// of a script execution starting in the library with the provided id.
+    fn exec_bound(
+        entry: LibId,
+        libs: &BTreeMap<LibId, &Lib>,
+    ) -> Result<(u64, u64), ResourceExhausted> {
+        let mut reachable = BTreeSet::new();
+        let mut queue = vec![entry];
+        let mut instructions = 0usize;
+        let mut complexity = 0u64;
+        let mut transfers = 0usize;
+        while let Some(id) = queue.pop() {
+            if !reachable.insert(id) {
+                continue;
+            }
+            let Some(lib) = libs.get(&id) else {
+                continue;
+            };
+            let meter = ScriptMeter::with(lib)?;
+            instructions = instructions.max(meter.instructions);
+            complexity = complexity.max(meter.complexity);
+            transfers = transfers.saturating_add(meter.transfers);
+            queue.extend(lib.libs.iter().copied());
+        }
+        let segments = if transfers == 0 { 1 } else { MAX_EXEC_SEGMENTS };
+        Ok((segments.saturating_mul(instructions as u64), segments.saturating_mul(complexity)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use aluvm::library::LibSite;
+    use amplify::confinement::Confined;
+
+    use super::*;
+    use crate::containers::{Contract, ValidationError};
+    use crate::interface::resolver::DumbResolver;
+
+    #[test]
+    fn script_limits() {
+        let code = (0..16)
+            .map(|_| Instr::<RgbIsa<MemContract>>::ControlFlow(ControlFlowOp::Test))
+            .collect::<Vec<_>>();
+        let lib = Lib::assemble(&code).unwrap();
+        let meter = ScriptMeter::with(&lib).unwrap();
+        assert_eq!(meter.instructions, 16);
+        assert_eq!(meter.code_len, 16);
+        assert_eq!(meter.data_len, 0);
+
+        assert_eq!(ScriptLimits::STRICT.check([&lib]), Ok(()));
+        assert_eq!(ScriptLimits::UNLIMITED.check([&lib, &lib]), Ok(()));
+
+        let limits = ScriptLimits {
+            max_instructions: 8,
+            ..ScriptLimits::STRICT
+        };
+        assert_eq!(limits.check([&lib]), Err(ResourceExhausted::Instructions(lib.id(), 16, 8)));
+
+        let limits = ScriptLimits {
+            max_libs: 1,
+            ..ScriptLimits::STRICT
+        };
+        assert_eq!(limits.check([&lib, &lib]), Err(ResourceExhausted::Libs(2, 1)));
+
+        let limits = ScriptLimits {
+            max_complexity: meter.complexity - 1,
+            ..ScriptLimits::STRICT
+        };
+        assert_eq!(
+            limits.check([&lib]),
+            Err(ResourceExhausted::Complexity(lib.id(), meter.complexity, meter.complexity - 1))
+        );
+    }
+
+    #[test]
+    fn execution_limits() {
+        let mut contract =
+            Contract::from_str(include_str!("../../asset/armored_contract.default")).unwrap();
+        let lib =
+            Lib::assemble(&[Instr::<RgbIsa<MemContract>>::ControlFlow(ControlFlowOp::Jmp(0))])
+                .unwrap();
+        contract.schema.genesis.validator = Some(LibSite::with(0, lib.id()));
+        contract.scripts = Confined::from_checked(bset! { lib });
+        let opid = contract.genesis.id();
+
+        assert_eq!(ScriptLimits::STRICT.check(&contract.scripts), Ok(()));
+        assert_eq!(ScriptLimits::STRICT.check_execution(&contract), Ok(()));
+        let limits = ScriptLimits {
+            max_exec_steps: 1000,
+            ..ScriptLimits::UNLIMITED
+        };
+        assert_eq!(
+            limits.check_execution(&contract),
+            Err(ResourceExhausted::ExecSteps(opid, MAX_EXEC_SEGMENTS, 1000))
+        );
+        let limits = ScriptLimits {
+            max_exec_complexity: 100,
+            ..ScriptLimits::UNLIMITED
+        };
+        assert_eq!(
+            limits.check_execution(&contract),
+            Err(ResourceExhausted::ExecComplexity(opid, 2 * MAX_EXEC_SEGMENTS, 100))
+        );
+        assert_eq!(ScriptLimits::UNLIMITED.check_execution(&contract), Ok(()));
+
+        let limits = ScriptLimits {
+            max_exec_steps: 1000,
+            ..ScriptLimits::STRICT
+        };
+        let (err, mut contract) = contract
+            .validate_with_limits(&DumbResolver, true, &limits)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ValidationError::ResourceExhausted(ResourceExhausted::ExecSteps(id, _, 1000)) if id == opid
+        ));
+
+        // Scripts without jumps execute each instruction at most once
+        let code = (0..16)
+            .map(|_| Instr::<RgbIsa<MemContract>>::ControlFlow(ControlFlowOp::Test))
+            .collect::<Vec<_>>();
+        let lib = Lib::assemble(&code).unwrap();
+        contract.schema.genesis.validator = Some(LibSite::with(0, lib.id()));
+        contract.scripts = Confined::from_checked(bset! { lib });
+        let limits = ScriptLimits {
+            max_exec_steps: 16,
+            ..ScriptLimits::STRICT
+        };
+        assert_eq!(limits.check_execution(&contract), Ok(()));
+        let limits = ScriptLimits {
+            max_exec_steps: 15,
+            ..ScriptLimits::STRICT
+        };
+        assert_eq!(
+            limits.check_execution(&contract),
+            Err(ResourceExhausted::ExecSteps(opid, 16, 15))
+        );
+    }
+}
@@ -0,0 +1,249 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Payment memos encrypted to the invoice beneficiary.
+//!
+//! The beneficiary publishes memo public key in the invoice
+//! [`INVOICE_MEMO_KEY`] query parameter. The sender attaches memo to the
+//! transfer as [`EncryptedMemo`] extension record, encrypted with ECIES
+//! scheme:
+//! - the shared secret is the ECDH of an ephemeral key and the memo key;
+//! - the encryption key is a tagged hash of the shared secret and the invoice beneficiary, binding
+//!   the memo to the invoice;
+//! - the memo is encrypted with ChaCha20-Poly1305 under a random nonce, authenticating the
+//!   ephemeral key as associated data.
+//!
+//! Memos are not a part of any contract, consignment or witness commitments
+//! and are not kept in the stash.
+
+use amplify::confinement::SmallBlob;
+use amplify::{Bytes, Bytes32};
+use bp::secp256k1::ecdh::SharedSecret;
+use bp::secp256k1::rand::thread_rng;
+use bp::secp256k1::SecretKey;
+use bp::CompressedPk;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use commit_verify::{DigestExt, Sha256};
+use invoice::RgbInvoice;
+use strict_encoding::{StrictDeserialize, StrictSerialize};
+
+use super::{ExtensionRecord, Transfer};
+use crate::LIB_NAME_RGB_STD;
+
+/// Invoice query parameter containing hex-encoded compressed public key to
+/// which payment memos must be encrypted.
+pub const INVOICE_MEMO_KEY: &str = "memo";
+
+const MEMO_KEY_TAG: &str = "urn:lnp-bp:rgb:memo-key#2024-10-14";
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum MemoError {
+    /// invoice doesn't provide a key for encrypting payment memos.
+    NoMemoKey,
+
+    /// invoice memo key '{0}' is not a valid compressed public key.
+    InvalidMemoKey(String),
+
+    /// memo of {0} bytes is too large.
+    TooLarge(usize),
+
+    /// memo is not encrypted for the invoice or the key, or is corrupted.
+    Unauthenticated,
+
+    /// memo is not a valid UTF-8 string.
+    NotUtf8,
+
+    /// transfer memo record is invalid: {0}
+    InvalidRecord(String),
+}
+
+/// Payment memo encrypted to the invoice beneficiary.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct EncryptedMemo {
+    pub ephemeral: CompressedPk,
+    pub nonce: Bytes<12>,
+    /// Encrypted memo followed by the Poly1305 authentication tag.
+    pub ciphertext: SmallBlob,
+}
+
+impl StrictSerialize for EncryptedMemo {}
+impl StrictDeserialize for EncryptedMemo {}
+
+impl ExtensionRecord for EncryptedMemo {
+    const RECORD_NAME: &'static str = "EncryptedMemo";
+}
+
+/// Returns memo key published in the invoice, if any.
+pub fn invoice_memo_key(invoice: &RgbInvoice) -> Result<Option<CompressedPk>, MemoError> {
+    invoice
+        .unknown_query
+        .get(INVOICE_MEMO_KEY)
+        .map(|key| {
+            key.parse()
+                .map_err(|_| MemoError::InvalidMemoKey(key.clone()))
+        })
+        .transpose()
+}
+
+/// Publishes memo key in the invoice.
+pub fn set_invoice_memo_key(invoice: &mut RgbInvoice, key: CompressedPk) {
+    invoice
+        .unknown_query
+        .insert(INVOICE_MEMO_KEY.to_owned(), key.to_string());
+}
+
+impl EncryptedMemo {
+    fn key(shared: SharedSecret, invoice: &RgbInvoice) -> Bytes32 {
+        let mut hasher = Sha256::from_tag(MEMO_KEY_TAG);
+        hasher.input_raw(&shared.secret_bytes());
+        hasher.input_raw(invoice.beneficiary.to_string().as_bytes());
+        hasher.finish().into()
+    }
+
+    fn cipher(key: Bytes32) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(Key::from_slice(key.as_slice()))
+    }
+
+    /// Encrypts memo to the key published in the invoice.
+    pub fn encrypt(invoice: &RgbInvoice, memo: &str) -> Result<Self, MemoError> {
+        let memo_key = invoice_memo_key(invoice)?.ok_or(MemoError::NoMemoKey)?;
+        let secret = SecretKey::new(&mut thread_rng());
+        let ephemeral = CompressedPk::from(secret.public_key(bp::secp256k1::SECP256K1));
+        let key = Self::key(SharedSecret::new(&memo_key, &secret), invoice);
+
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut thread_rng());
+        let payload = Payload {
+            msg: memo.as_bytes(),
+            aad: &ephemeral.to_byte_array(),
+        };
+        let data = Self::cipher(key)
+            .encrypt(&nonce, payload)
+            .map_err(|_| MemoError::TooLarge(memo.len()))?;
+        let ciphertext = SmallBlob::try_from(data).map_err(|_| MemoError::TooLarge(memo.len()))?;
+        Ok(EncryptedMemo {
+            ephemeral,
+            nonce: Bytes::from_slice_unsafe(nonce),
+            ciphertext,
+        })
+    }
+
+    /// Decrypts memo with the secret key corresponding to the memo key of the
+    /// invoice.
+    pub fn decrypt(
+        &self,
+        invoice: &RgbInvoice,
+        secret_key: &SecretKey,
+    ) -> Result<String, MemoError> {
+        let key = Self::key(SharedSecret::new(&self.ephemeral, secret_key), invoice);
+        let payload = Payload {
+            msg: self.ciphertext.as_slice(),
+            aad: &self.ephemeral.to_byte_array(),
+        };
+        let data = Self::cipher(key)
+            .decrypt(Nonce::from_slice(self.nonce.as_slice()), payload)
+            .map_err(|_| MemoError::Unauthenticated)?;
+        String::from_utf8(data).map_err(|_| MemoError::NotUtf8)
+    }
+}
+
+impl Transfer {
+    /// Attaches payment memo encrypted to the beneficiary of the invoice,
+    /// replacing previously attached memo.
+    pub fn attach_memo(&mut self, invoice: &RgbInvoice, memo: &str) -> Result<(), MemoError> {
+        let record = EncryptedMemo::encrypt(invoice, memo)?;
+        self.set_extension_record(&record)
+            .map_err(|err| MemoError::InvalidRecord(err.to_string()))?;
+        Ok(())
+    }
+
+    /// Reads payment memo attached to the transfer paying the invoice.
+    ///
+    /// Returns `Ok(None)` if the transfer has no memo.
+    pub fn read_memo(
+        &self,
+        invoice: &RgbInvoice,
+        secret_key: &SecretKey,
+    ) -> Result<Option<String>, MemoError> {
+        self.extension_record::<EncryptedMemo>()
+            .map(|record| {
+                record
+                    .map_err(|err| MemoError::InvalidRecord(err.to_string()))?
+                    .decrypt(invoice, secret_key)
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use invoice::{Beneficiary, RgbInvoiceBuilder, XChainNet};
+    use rgb::{ContractId, SecretSeal};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn encrypted_memo() {
+        let beneficiary =
+            XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(SecretSeal::strict_dumb()));
+        let mut invoice = RgbInvoiceBuilder::with(ContractId::strict_dumb(), beneficiary).finish();
+        let mut transfer = Transfer::strict_dumb();
+        assert_eq!(transfer.attach_memo(&invoice, "order #1"), Err(MemoError::NoMemoKey));
+
+        let secret = SecretKey::new(&mut thread_rng());
+        set_invoice_memo_key(&mut invoice, secret.public_key(bp::secp256k1::SECP256K1).into());
+        assert_eq!(transfer.read_memo(&invoice, &secret), Ok(None));
+        transfer.attach_memo(&invoice, "order #1").unwrap();
+        assert_eq!(transfer.read_memo(&invoice, &secret).unwrap().unwrap(), "order #1");
+        let first = transfer
+            .extension_record::<EncryptedMemo>()
+            .unwrap()
+            .unwrap();
+        transfer.attach_memo(&invoice, "order #1").unwrap();
+        let second = transfer
+            .extension_record::<EncryptedMemo>()
+            .unwrap()
+            .unwrap();
+        assert_ne!(first.nonce, second.nonce);
+        assert_ne!(first.ciphertext, second.ciphertext);
+
+        let other = SecretKey::new(&mut thread_rng());
+        assert_eq!(transfer.read_memo(&invoice, &other), Err(MemoError::Unauthenticated));
+        let mut record = transfer
+            .extension_record::<EncryptedMemo>()
+            .unwrap()
+            .unwrap();
+        record.ciphertext = SmallBlob::try_from(b"order #2".to_vec()).unwrap();
+        assert_eq!(record.decrypt(&invoice, &secret), Err(MemoError::Unauthenticated));
+        let mut record = second;
+        record.nonce = first.nonce;
+        assert_eq!(record.decrypt(&invoice, &secret), Err(MemoError::Unauthenticated));
+    }
+}
@@ -28,6 +28,8 @@
 //!    parties, and also for performing "change" operations on inventory during state transfers.
 
 mod seal;
+#[cfg(feature = "serde")]
+mod canonical;
 mod anchors;
 mod consignment;
 mod disclosure;
@@ -36,26 +38,55 @@ mod partials;
 mod indexed;
 mod file;
 mod kit;
+mod limits;
+mod memo;
+mod receipt;
+mod report;
+mod records;
+mod signing;
 mod suppl;
 
-pub use anchors::{AnchorSet, PubWitness, SealWitness, ToWitnessId, WitnessBundle, XPubWitness};
+pub use anchors::{
+    anchored_bundle_id, mpc_commitment, verify_anchor, AnchorError, AnchorSet, PubWitness,
+    SealWitness, ToWitnessId, WitnessBundle, WitnessId, XPubWitness,
+};
+#[cfg(feature = "serde")]
+pub use canonical::{
+    canonical_digest, sign_canonical, to_canonical_json, verify_canonical, CanonicalError,
+    CanonicalSigner, CanonicalVerifier, CANONICAL_JSON_TAG, CANONICAL_MAX_SAFE_INTEGER,
+};
 pub use consignment::{
-    Consignment, ConsignmentExt, ConsignmentId, ConsignmentParseError, Contract, Transfer,
-    ValidConsignment, ValidContract, ValidTransfer,
+    CommitmentComponent, Consignment, ConsignmentExt, ConsignmentId, ConsignmentLayout,
+    ConsignmentParseError, Contract, InvoiceMismatch, Satisfaction, Transfer, ValidConsignment,
+    ValidContract, ValidTransfer, ValidationError,
 };
 pub use disclosure::Disclosure;
 pub use file::{FileContent, LoadError, UniversalFile};
-pub use indexed::IndexedConsignment;
+pub use indexed::{IndexBackend, IndexedConsignment, HASH_INDEX_THRESHOLD};
 pub use kit::{Kit, KitId, ValidKit};
+pub use limits::{ResourceExhausted, ScriptLimits, ScriptMeter};
+pub use memo::{
+    invoice_memo_key, set_invoice_memo_key, EncryptedMemo, MemoError, INVOICE_MEMO_KEY,
+};
 pub use partials::{
     Batch, BundleDichotomy, CloseMethodSet, Dichotomy, Fascia, TransitionDichotomy, TransitionInfo,
     TransitionInfoError,
 };
-pub use seal::{BuilderSeal, VoutSeal};
+pub use receipt::{
+    ReceiptError, ReceiptId, ReceiptSigner, ReceiptVerifier, SignedReceipt, TransferReceipt,
+};
+pub use records::ExtensionRecord;
+pub use report::{ReportEntry, ReportValidity, ValidationReport};
+pub use seal::{BuilderSeal, ChainSecretSeal, VoutSeal};
+pub use signing::{SealDestination, SigningAssignment, SigningContext, SigningTransition};
 pub use suppl::{
-    AnnotationName, Annotations, ContentRef, SupplId, SupplItem, SupplMap, SupplSub, Supplement,
-    TickerSuppl, VelocityHint, SUPPL_ANNOT_IFACE_CLASS, SUPPL_ANNOT_IFACE_FEATURES,
-    SUPPL_ANNOT_VELOCITY,
+    AmountDisplay, AnnotationName, Annotations, Attestation, AttestationPolicy, Capabilities,
+    ContentRef, ContractDependencies, ContractDependency, DefaultOps, DigitGrouping, DisplayRules,
+    OpClass, SupplId, SupplItem, SupplMap, SupplSub, Supplement, SymbolPosition, TickerSuppl,
+    Verdict, VelocityHint, CAPABILITY_BURNABLE, CAPABILITY_CONFIDENTIAL_AMOUNTS,
+    CAPABILITY_ENGRAVABLE, CAPABILITY_INFLATABLE, SUPPL_ANNOT_ATTESTATION, SUPPL_ANNOT_CAPABILITIES,
+    SUPPL_ANNOT_DEFAULT_OPS, SUPPL_ANNOT_DEPENDENCIES, SUPPL_ANNOT_DISPLAY, SUPPL_ANNOT_DOC,
+    SUPPL_ANNOT_IFACE_CLASS, SUPPL_ANNOT_IFACE_FEATURES, SUPPL_ANNOT_VELOCITY,
 };
 pub use util::{
     ContainerVer, ContentId, ContentSigs, DumbValidator, SigBlob, SigValidator, TrustLevel,
@@ -33,7 +33,7 @@ use rgb::{
 };
 use strict_encoding::{StrictDecode, StrictDeserialize, StrictDumb, StrictEncode, StrictSerialize};
 
-use crate::containers::{AnchorSet, XPubWitness};
+use crate::containers::{AnchorSet, ToWitnessId, XPubWitness};
 use crate::LIB_NAME_RGB_STD;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
@@ -109,6 +109,20 @@ impl From<CloseMethod> for CloseMethodSet {
 impl CloseMethodSet {
     pub fn has_tapret_first(self) -> bool { matches!(self, Self::TapretFirst | Self::Both) }
     pub fn has_opret_first(self) -> bool { matches!(self, Self::OpretFirst | Self::Both) }
+
+    pub fn contains(self, method: CloseMethod) -> bool {
+        match method {
+            CloseMethod::TapretFirst => self.has_tapret_first(),
+            CloseMethod::OpretFirst => self.has_opret_first(),
+        }
+    }
+
+    /// Lists close methods in the set, with tapret-first method going first.
+    pub fn methods(self) -> impl Iterator<Item = CloseMethod> {
+        [CloseMethod::TapretFirst, CloseMethod::OpretFirst]
+            .into_iter()
+            .filter(move |method| self.contains(*method))
+    }
 }
 
 #[derive(Clone, Eq, Debug)]
@@ -327,7 +341,7 @@ impl StrictSerialize for Fascia {}
 impl StrictDeserialize for Fascia {}
 
 impl Fascia {
-    pub fn witness_id(&self) -> XWitnessId { self.witness.map_ref(|w| w.txid()) }
+    pub fn witness_id(&self) -> XWitnessId { self.witness.to_witness_id() }
 
     pub fn into_bundles(self) -> impl IntoIterator<Item = (ContractId, TransitionBundle)> {
         self.bundles
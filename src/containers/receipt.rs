@@ -0,0 +1,246 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use amplify::confinement::SmallOrdMap;
+use amplify::{ByteArray, Bytes32};
+use baid64::{Baid64ParseError, DisplayBaid64, FromBaid64Str};
+use commit_verify::{CommitId, CommitmentId, DigestExt, Sha256};
+use rgb::{BundleId, ContractId, Identity, XChain};
+use strict_encoding::{StrictDeserialize, StrictSerialize};
+
+use super::{ConsignmentExt, ConsignmentId, ContainerVer, SigBlob, Transfer};
+use crate::{LIB_NAME_RGB_STD, SecretSeal};
+
+/// Transfer receipt identifier.
+///
+/// Receipt identifier commits to all receipt data and is the message signed by
+/// the receiver.
+#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, From)]
+#[wrapper(Deref, BorrowSlice, Hex, Index, RangeOps)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+pub struct ReceiptId(
+    #[from]
+    #[from([u8; 32])]
+    Bytes32,
+);
+
+impl From<Sha256> for ReceiptId {
+    fn from(hasher: Sha256) -> Self { hasher.finish().into() }
+}
+
+impl CommitmentId for ReceiptId {
+    const TAG: &'static str = "urn:lnp-bp:rgb:receipt#2024-10-14";
+}
+
+impl DisplayBaid64 for ReceiptId {
+    const HRI: &'static str = "rgb:rcpt";
+    const CHUNKING: bool = true;
+    const PREFIX: bool = true;
+    const EMBED_CHECKSUM: bool = false;
+    const MNEMONIC: bool = false;
+    fn to_baid64_payload(&self) -> [u8; 32] { self.to_byte_array() }
+}
+impl FromBaid64Str for ReceiptId {}
+impl FromStr for ReceiptId {
+    type Err = Baid64ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Self::from_baid64_str(s) }
+}
+impl Display for ReceiptId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { self.fmt_baid64(f) }
+}
+
+impl_serde_baid64!(ReceiptId);
+
+/// Acknowledgment of a transfer acceptance produced by the receiver.
+///
+/// The receipt is independent of the transport used to deliver the transfer
+/// and, once signed by the receiver, provides the sender with a proof that the
+/// transfer was received and accepted.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[derive(CommitEncode)]
+#[commit_encode(strategy = strict, id = ReceiptId)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct TransferReceipt {
+    /// Version.
+    pub version: ContainerVer,
+    /// Id of the accepted transfer consignment.
+    pub transfer_id: ConsignmentId,
+    pub contract_id: ContractId,
+    /// Transfer terminals accepted by the receiver.
+    pub terminals: SmallOrdMap<BundleId, XChain<SecretSeal>>,
+    /// Unix timestamp of the transfer acceptance.
+    pub timestamp: i64,
+    /// Identity of the receiver signing the receipt.
+    pub receiver: Identity,
+}
+
+impl StrictSerialize for TransferReceipt {}
+impl StrictDeserialize for TransferReceipt {}
+
+impl TransferReceipt {
+    /// Constructs receipt for all terminals of an accepted transfer.
+    pub fn new(transfer: &Transfer, receiver: Identity, timestamp: i64) -> Self {
+        TransferReceipt {
+            version: ContainerVer::V2,
+            transfer_id: transfer.consignment_id(),
+            contract_id: transfer.contract_id(),
+            terminals: transfer.terminals.clone(),
+            timestamp,
+            receiver,
+        }
+    }
+
+    #[inline]
+    pub fn receipt_id(&self) -> ReceiptId { self.commit_id() }
+
+    /// Signs the receipt with the provided signer.
+    pub fn sign(self, signer: &impl ReceiptSigner) -> SignedReceipt {
+        let sig = signer.sign_receipt(self.receipt_id());
+        SignedReceipt { receipt: self, sig }
+    }
+}
+
+/// Transfer receipt signed by the receiver.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SignedReceipt {
+    pub receipt: TransferReceipt,
+    pub sig: SigBlob,
+}
+
+impl StrictSerialize for SignedReceipt {}
+impl StrictDeserialize for SignedReceipt {}
+
+impl SignedReceipt {
+    #[inline]
+    pub fn receipt_id(&self) -> ReceiptId { self.receipt.receipt_id() }
+
+    /// Verifies the receipt signature and that the receipt matches the
+    /// transfer sent.
+    pub fn verify(
+        &self,
+        transfer: &Transfer,
+        verifier: &impl ReceiptVerifier,
+    ) -> Result<(), ReceiptError> {
+        let receipt = &self.receipt;
+        let transfer_id = transfer.consignment_id();
+        if receipt.transfer_id != transfer_id {
+            return Err(ReceiptError::TransferMismatch(receipt.transfer_id, transfer_id));
+        }
+        if let Some(bundle_id) = receipt
+            .terminals
+            .iter()
+            .find(|(id, seal)| transfer.terminals.get(*id) != Some(seal))
+            .map(|(id, _)| *id)
+        {
+            return Err(ReceiptError::UnknownTerminal(bundle_id));
+        }
+        if !verifier.verify_receipt(&receipt.receiver, self.receipt_id(), &self.sig) {
+            return Err(ReceiptError::InvalidSig(self.receipt_id()));
+        }
+        Ok(())
+    }
+}
+
+/// Signer producing receiver signatures for the transfer receipts.
+pub trait ReceiptSigner {
+    fn sign_receipt(&self, receipt_id: ReceiptId) -> SigBlob;
+}
+
+/// Verifier for the receiver signatures on the transfer receipts.
+pub trait ReceiptVerifier {
+    fn verify_receipt(&self, receiver: &Identity, receipt_id: ReceiptId, sig: &SigBlob) -> bool;
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum ReceiptError {
+    /// receipt is issued for transfer {0} and not for {1}.
+    TransferMismatch(ConsignmentId, ConsignmentId),
+
+    /// receipt acknowledges terminal {0} which is not a part of the transfer.
+    UnknownTerminal(BundleId),
+
+    /// invalid receiver signature on receipt {0}.
+    InvalidSig(ReceiptId),
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::NonEmptyBlob;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    struct EchoSigner;
+    impl ReceiptSigner for EchoSigner {
+        fn sign_receipt(&self, receipt_id: ReceiptId) -> SigBlob {
+            SigBlob::from(NonEmptyBlob::from_slice_checked(receipt_id.as_slice()))
+        }
+    }
+    impl ReceiptVerifier for EchoSigner {
+        fn verify_receipt(&self, _: &Identity, receipt_id: ReceiptId, sig: &SigBlob) -> bool {
+            sig.as_slice() == receipt_id.as_slice()
+        }
+    }
+
+    #[test]
+    fn receipt_verification() {
+        let transfer = Transfer::strict_dumb();
+        let receipt = TransferReceipt::new(&transfer, Identity::from("ssi:receiver"), 1231006505);
+        assert_eq!(receipt.transfer_id, transfer.consignment_id());
+        let id = receipt.receipt_id();
+        assert_eq!(ReceiptId::from_str(&id.to_string()).unwrap(), id);
+
+        let signed = receipt.sign(&EchoSigner);
+        assert_eq!(signed.verify(&transfer, &EchoSigner), Ok(()));
+
+        let mut forged = signed.clone();
+        forged.receipt.timestamp += 1;
+        assert_eq!(
+            forged.verify(&transfer, &EchoSigner),
+            Err(ReceiptError::InvalidSig(forged.receipt_id()))
+        );
+
+        let mut other = transfer.clone();
+        other.transfer = true;
+        assert_eq!(
+            signed.verify(&other, &EchoSigner),
+            Err(ReceiptError::TransferMismatch(transfer.consignment_id(), other.consignment_id()))
+        );
+    }
+}
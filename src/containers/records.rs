@@ -0,0 +1,142 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Optional extension records carried by consignments.
+//!
+//! Extension records allow ecosystem experiments (like payment memos) to
+//! attach typed data to transfers and contracts without further changes of
+//! the container format. The records are kept in [`Consignment::records`],
+//! keyed by the record name, which is encoded by consignments starting from
+//! [`ContainerVer::V3`]. The records are not committed to by the consignment
+//! id and are not kept in the stash; implementations not aware of a record
+//! just ignore it.
+
+use std::collections::BTreeSet;
+
+use amplify::confinement::U24;
+use strict_encoding::{
+    DeserializeError, SerializeError, StrictDeserialize, StrictSerialize, TypeName,
+};
+
+use super::{Consignment, ContainerVer};
+
+/// Typed extension record which may be attached to a consignment.
+pub trait ExtensionRecord: StrictSerialize + StrictDeserialize {
+    /// Unique name of the record, which must be a valid type name.
+    const RECORD_NAME: &'static str;
+
+    fn record_name() -> TypeName { TypeName::from(Self::RECORD_NAME) }
+}
+
+impl<const TRANSFER: bool> Consignment<TRANSFER> {
+    /// Returns names of all extension records present in the consignment,
+    /// including unknown ones.
+    pub fn extension_record_names(&self) -> BTreeSet<TypeName> {
+        self.records.keys().cloned().collect()
+    }
+
+    /// Reads extension record of a specific type, if present.
+    pub fn extension_record<X: ExtensionRecord>(&self) -> Option<Result<X, DeserializeError>> {
+        let blob = self.records.get(&X::record_name())?;
+        Some(X::from_strict_serialized::<U24>(blob.clone()))
+    }
+
+    /// Adds extension record to the consignment, replacing the previous
+    /// record of the same type.
+    ///
+    /// Consignments of the versions not carrying the records are upgraded to
+    /// [`ContainerVer::V3`], which changes their id.
+    ///
+    /// Returns whether the previous record was replaced.
+    pub fn set_extension_record<X: ExtensionRecord>(
+        &mut self,
+        record: &X,
+    ) -> Result<bool, SerializeError> {
+        let blob = record.to_strict_serialized::<U24>()?;
+        let prev = self.records.insert(X::record_name(), blob)?;
+        if !self.version.has_records() {
+            self.version = ContainerVer::V3;
+        }
+        Ok(prev.is_some())
+    }
+
+    /// Removes extension record with the given name from the consignment.
+    ///
+    /// Returns whether the record was present.
+    pub fn remove_extension_record(&mut self, name: impl Into<TypeName>) -> bool {
+        self.records
+            .remove(&name.into())
+            .expect("zero items allowed")
+            .is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::containers::Transfer;
+    use crate::LIB_NAME_RGB_STD;
+
+    #[derive(Clone, Eq, PartialEq, Debug)]
+    #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+    #[strict_type(lib = LIB_NAME_RGB_STD)]
+    struct Memo {
+        invoice_no: u32,
+    }
+    impl StrictSerialize for Memo {}
+    impl StrictDeserialize for Memo {}
+    impl ExtensionRecord for Memo {
+        const RECORD_NAME: &'static str = "Memo";
+    }
+
+    #[test]
+    fn extension_records() {
+        let mut transfer = Transfer::strict_dumb();
+        let id = transfer.consignment_id();
+        assert!(transfer.extension_record::<Memo>().is_none());
+        assert!(
+            !transfer
+                .set_extension_record(&Memo { invoice_no: 1 })
+                .unwrap()
+        );
+        assert!(
+            transfer
+                .set_extension_record(&Memo { invoice_no: 2 })
+                .unwrap()
+        );
+        assert!(transfer.supplements.is_empty());
+        assert_eq!(transfer.consignment_id(), id);
+        assert_eq!(transfer.extension_record::<Memo>().unwrap().unwrap(), Memo { invoice_no: 2 });
+        assert_eq!(transfer.extension_record_names(), bset![tn!("Memo")]);
+
+        assert!(transfer.remove_extension_record("Memo"));
+        assert!(!transfer.remove_extension_record("Memo"));
+        assert!(transfer.records.is_empty());
+
+        transfer.version = ContainerVer::V2;
+        transfer
+            .set_extension_record(&Memo { invoice_no: 3 })
+            .unwrap();
+        assert_eq!(transfer.version, ContainerVer::V3);
+    }
+}
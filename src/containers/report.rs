@@ -0,0 +1,222 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serializable reports on consignment validation.
+//!
+//! [`validation::Status`] produced by the validation is an in-memory value,
+//! which order of entries depends on the order the consignment was validated
+//! in. [`ValidationReport`] is its deterministic form: entries are sorted and
+//! deduplicated, thus the same status always produces the same strict and
+//! serde serialization. Reports can be persisted alongside the accepted
+//! consignments and returned over RPC, instead of re-validating consignments
+//! for display.
+
+use std::fmt::{self, Display, Formatter};
+
+use amplify::confinement::{SmallOrdSet, SmallString, TinyString};
+use rgb::validation::{self, Validity};
+use strict_encoding::{StrictDeserialize, StrictSerialize};
+
+use crate::LIB_NAME_RGB_STD;
+
+/// Validity of a consignment reported by [`ValidationReport`].
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, Default)]
+#[display(lowercase)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD, tags = repr, into_u8, try_from_u8)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[repr(u8)]
+pub enum ReportValidity {
+    #[default]
+    Valid = 0,
+    Warnings = 1,
+    Invalid = 2,
+}
+
+impl From<Validity> for ReportValidity {
+    fn from(validity: Validity) -> Self {
+        match validity {
+            Validity::Valid => ReportValidity::Valid,
+            Validity::Warnings => ReportValidity::Warnings,
+            Validity::Invalid => ReportValidity::Invalid,
+        }
+    }
+}
+
+/// Single failure, warning or information message of a validation report.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display("{code}: {message}")]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ReportEntry {
+    /// Name of the status variant, like `SchemaMismatch`.
+    pub code: TinyString,
+    /// Human-readable message. Messages which don't fit the size limit are
+    /// truncated.
+    pub message: SmallString,
+}
+
+impl ReportEntry {
+    fn with(entry: &(impl Display + fmt::Debug)) -> Self {
+        let debug = format!("{entry:?}");
+        let code = debug
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .next()
+            .unwrap_or_default();
+        ReportEntry {
+            code: TinyString::from_checked(truncate(code, u8::MAX as usize)),
+            message: SmallString::from_checked(truncate(&entry.to_string(), u16::MAX as usize)),
+        }
+    }
+}
+
+/// Deterministic report on a consignment validation.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ValidationReport {
+    pub validity: ReportValidity,
+    pub failures: SmallOrdSet<ReportEntry>,
+    pub warnings: SmallOrdSet<ReportEntry>,
+    pub info: SmallOrdSet<ReportEntry>,
+}
+
+impl StrictSerialize for ValidationReport {}
+impl StrictDeserialize for ValidationReport {}
+
+impl From<&validation::Status> for ValidationReport {
+    fn from(status: &validation::Status) -> Self {
+        fn entries<T: Display + fmt::Debug>(list: &[T]) -> SmallOrdSet<ReportEntry> {
+            SmallOrdSet::from_iter_checked(
+                list.iter().take(u16::MAX as usize).map(ReportEntry::with),
+            )
+        }
+        ValidationReport {
+            validity: status.validity().into(),
+            failures: entries(&status.failures),
+            warnings: entries(&status.warnings),
+            info: entries(&status.info),
+        }
+    }
+}
+
+impl From<validation::Status> for ValidationReport {
+    fn from(status: validation::Status) -> Self { ValidationReport::from(&status) }
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool { self.validity != ReportValidity::Invalid }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Consignment validity: {}", self.validity)?;
+        for (title, entries) in
+            [("Failures", &self.failures), ("Warnings", &self.warnings), ("Info", &self.info)]
+        {
+            if entries.is_empty() {
+                continue;
+            }
+            writeln!(f, "{title}:")?;
+            for entry in entries {
+                writeln!(f, "- {entry}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    let mut len = s.len().min(max);
+    while !s.is_char_boundary(len) {
+        len -= 1;
+    }
+    s[..len].to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use rgb::validation::{Failure, Warning};
+    use rgb::SchemaId;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn deterministic_report() {
+        let failure = Failure::SchemaMismatch {
+            expected: SchemaId::strict_dumb(),
+            actual: SchemaId::from([1u8; 32]),
+        };
+        let mut status = validation::Status::new();
+        status.add_warning(Warning::Custom(s!("second")));
+        status.add_warning(Warning::Custom(s!("first")));
+        status.add_failure(failure.clone());
+        status.add_failure(failure.clone());
+
+        let report = ValidationReport::from(&status);
+        assert_eq!(report.validity, ReportValidity::Invalid);
+        assert!(!report.is_valid());
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures.first().unwrap().code.as_str(), "SchemaMismatch");
+        assert_eq!(
+            report
+                .warnings
+                .iter()
+                .map(|entry| entry.code.as_str())
+                .collect::<Vec<_>>(),
+            ["Custom", "Custom"]
+        );
+
+        let mut reordered = validation::Status::new();
+        reordered.add_failure(failure);
+        reordered.add_warning(Warning::Custom(s!("first")));
+        reordered.add_warning(Warning::Custom(s!("second")));
+        let other = ValidationReport::from(reordered);
+        assert_eq!(other, report);
+
+        let data = report
+            .to_strict_serialized::<{ u16::MAX as usize }>()
+            .unwrap();
+        assert_eq!(
+            other
+                .to_strict_serialized::<{ u16::MAX as usize }>()
+                .unwrap(),
+            data
+        );
+        assert_eq!(ValidationReport::from_strict_serialized(data).unwrap(), report);
+        assert_eq!(truncate("aü", 2), "a");
+    }
+}
@@ -23,7 +23,8 @@
 
 use bp::seals::txout::{BlindSeal, CloseMethod, SealTxid};
 use bp::secp256k1::rand::{thread_rng, RngCore};
-use bp::Vout;
+use bp::{Outpoint, Vout};
+use commit_verify::Conceal;
 use rgb::{GraphSeal, Layer1, SecretSeal, TxoSeal, XChain};
 
 use crate::LIB_NAME_RGB_STD;
@@ -119,6 +120,10 @@ impl<Id: SealTxid> From<XChain<BlindSeal<Id>>> for BuilderSeal<BlindSeal<Id>> {
     fn from(seal: XChain<BlindSeal<Id>>) -> Self { BuilderSeal::Revealed(seal) }
 }
 
+impl From<XChain<VoutSeal>> for BuilderSeal<GraphSeal> {
+    fn from(seal: XChain<VoutSeal>) -> Self { BuilderSeal::Revealed(seal.map(GraphSeal::from)) }
+}
+
 impl<Seal: TxoSeal + Ord> BuilderSeal<Seal> {
     pub fn layer1(&self) -> Layer1 {
         match self {
@@ -127,3 +132,104 @@ impl<Seal: TxoSeal + Ord> BuilderSeal<Seal> {
         }
     }
 }
+
+/// Construction of chain-aware secret seals for invoices.
+///
+/// The helpers hide the conceal procedure, such that the secret seal always
+/// commits to the seal definition on the same chain the seal is used on.
+pub trait ChainSecretSeal: Sized {
+    /// Constructs secret seal for the outpoint on the given chain using the
+    /// provided close method and blinding factor.
+    fn with_blinding(
+        layer1: Layer1,
+        method: CloseMethod,
+        outpoint: Outpoint,
+        blinding: u64,
+    ) -> Self;
+
+    /// Constructs secret seal for the outpoint on the given chain using the
+    /// provided close method and a random blinding factor.
+    ///
+    /// Returns the secret seal together with the revealed seal definition,
+    /// which must be kept by the wallet to accept the incoming state.
+    fn new_random(
+        layer1: Layer1,
+        method: CloseMethod,
+        outpoint: Outpoint,
+    ) -> (Self, XChain<GraphSeal>);
+
+    /// Verifies that the revealed seal definition corresponds to the secret
+    /// seal, including the chain it is defined on.
+    fn is_revealed_by(&self, seal: &XChain<GraphSeal>) -> bool;
+}
+
+impl ChainSecretSeal for XChain<SecretSeal> {
+    fn with_blinding(
+        layer1: Layer1,
+        method: CloseMethod,
+        outpoint: Outpoint,
+        blinding: u64,
+    ) -> Self {
+        let seal = GraphSeal::with_blinding(method, outpoint.txid, outpoint.vout, blinding);
+        XChain::<GraphSeal>::with(layer1, seal).conceal()
+    }
+
+    fn new_random(
+        layer1: Layer1,
+        method: CloseMethod,
+        outpoint: Outpoint,
+    ) -> (Self, XChain<GraphSeal>) {
+        let seal =
+            XChain::with(layer1, GraphSeal::new_random(method, outpoint.txid, outpoint.vout));
+        (seal.conceal(), seal)
+    }
+
+    fn is_revealed_by(&self, seal: &XChain<GraphSeal>) -> bool { seal.conceal() == *self }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::seals::txout::TxPtr;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn vout_builder_seal() {
+        let seal = VoutSeal::with_tapret(2u32, 0xdead);
+        let BuilderSeal::Revealed(revealed) = BuilderSeal::from(XChain::Bitcoin(seal)) else {
+            panic!("vout seal must be revealed");
+        };
+        assert_eq!(revealed.as_reduced_unsafe().txid, TxPtr::WitnessTx);
+        assert_eq!(revealed.as_reduced_unsafe().vout, Vout::from_u32(2));
+        assert_eq!(revealed.as_reduced_unsafe().blinding, 0xdead);
+    }
+
+    #[test]
+    fn chain_secret_seal() {
+        let outpoint = Outpoint::strict_dumb();
+        let (secret, seal) =
+            XChain::<SecretSeal>::new_random(Layer1::Bitcoin, CloseMethod::TapretFirst, outpoint);
+        assert_eq!(secret.layer1(), Layer1::Bitcoin);
+        assert!(secret.is_revealed_by(&seal));
+
+        let blinding = seal.as_reduced_unsafe().blinding;
+        assert_eq!(
+            XChain::with_blinding(Layer1::Bitcoin, CloseMethod::TapretFirst, outpoint, blinding),
+            secret
+        );
+        let liquid =
+            XChain::with_blinding(Layer1::Liquid, CloseMethod::TapretFirst, outpoint, blinding);
+        assert_ne!(liquid, secret);
+        assert!(!liquid.is_revealed_by(&seal));
+        assert!(
+            !XChain::<SecretSeal>::with_blinding(
+                Layer1::Bitcoin,
+                CloseMethod::OpretFirst,
+                outpoint,
+                blinding
+            )
+            .is_revealed_by(&seal)
+        );
+    }
+}
@@ -0,0 +1,262 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing context for hardware and air-gapped signers.
+//!
+//! A signer of a witness transaction sees only the bitcoin part of the PSBT,
+//! while the transaction also commits to RGB state transitions. Signing
+//! context is a compact description of the state transitions from a composed
+//! [`Batch`], which is passed to the signer together with the PSBT, such that
+//! the signer can display the RGB state spent and assigned by the transaction
+//! before the user consents to sign it.
+//!
+//! Once the PSBT is signed and the [`Fascia`] is extracted from it, the
+//! wallet completes the transfer with [`Stock::complete_signed`], which
+//! checks that the witness commits exactly to the state transitions presented
+//! to the signer.
+//!
+//! [`Fascia`]: super::Fascia
+//! [`Stock::complete_signed`]: crate::persistence::Stock::complete_signed
+
+use std::fmt::{self, Display, Formatter};
+
+use amplify::confinement::{Confined, SmallOrdSet, SmallVec};
+use bp::seals::txout::{CloseMethod, TxPtr};
+use bp::{Outpoint, Vout};
+use rgb::{AssignmentType, ContractId, OpId, TransitionType, XChain, XOutpoint};
+use strict_encoding::{FieldName, StrictDeserialize, StrictSerialize};
+
+use super::{Batch, TransitionInfo};
+use crate::contract::TypedAssignsExt;
+use crate::interface::{AllocatedState, IfaceImpl};
+use crate::{SecretSeal, LIB_NAME_RGB_STD};
+
+/// Destination of the state assigned by a state transition.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD, tags = order, dumb = Self::WitnessOutput(strict_dumb!()))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum SealDestination {
+    /// Output of the witness transaction being signed.
+    WitnessOutput(Vout),
+    /// Output of some other existing transaction.
+    Outpoint(XOutpoint),
+    /// Blinded seal provided by a beneficiary.
+    Concealed(XChain<SecretSeal>),
+}
+
+impl Display for SealDestination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SealDestination::WitnessOutput(vout) => write!(f, "witness output #{vout}"),
+            SealDestination::Outpoint(outpoint) => write!(f, "output {outpoint}"),
+            SealDestination::Concealed(seal) => write!(f, "blinded seal {seal}"),
+        }
+    }
+}
+
+/// State assigned by a state transition, as presented to the signer.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SigningAssignment {
+    pub ty: AssignmentType,
+    /// Name of the assignment type, if known from the interface implementation.
+    pub name: Option<FieldName>,
+    pub seal: SealDestination,
+    /// Assigned state, if it is not concealed.
+    pub state: Option<AllocatedState>,
+}
+
+/// State transition committed to by the witness transaction, as presented to
+/// the signer.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SigningTransition {
+    pub contract_id: ContractId,
+    pub opid: OpId,
+    pub ty: TransitionType,
+    /// Name of the transition type, if known from the interface
+    /// implementation.
+    pub name: Option<FieldName>,
+    pub method: CloseMethod,
+    /// Outputs spent by the transition.
+    pub inputs: SmallOrdSet<XOutpoint>,
+    pub assignments: SmallVec<SigningAssignment>,
+}
+
+impl SigningTransition {
+    /// Constructs description of the transition, using the interface
+    /// implementation for naming the transition and assignment types.
+    pub fn with(info: &TransitionInfo, iimpl: Option<&IfaceImpl>) -> Self {
+        let transition = &info.transition;
+        let mut assignments = vec![];
+        for (ty, assigns) in transition.assignments.iter() {
+            let name = iimpl.and_then(|iimpl| iimpl.assignment_name(*ty)).cloned();
+            for index in 0..assigns.len_u16() {
+                let seal = match assigns.revealed_seal_at(index).ok().flatten() {
+                    Some(seal) => {
+                        let layer1 = seal.layer1();
+                        let seal = seal.as_reduced_unsafe();
+                        match seal.txid {
+                            TxPtr::WitnessTx => SealDestination::WitnessOutput(seal.vout),
+                            TxPtr::Txid(txid) => SealDestination::Outpoint(
+                                XChain::<Outpoint>::with(layer1, Outpoint::new(txid, seal.vout))
+                                    .into(),
+                            ),
+                        }
+                    }
+                    None => {
+                        SealDestination::Concealed(assigns.to_confidential_seals()[index as usize])
+                    }
+                };
+                let state = assigns.revealed_state_at(index);
+                assignments.push(SigningAssignment {
+                    ty: *ty,
+                    name: name.clone(),
+                    seal,
+                    state,
+                });
+            }
+        }
+        SigningTransition {
+            contract_id: transition.contract_id,
+            opid: info.id,
+            ty: transition.transition_type,
+            name: iimpl
+                .and_then(|iimpl| iimpl.transition_name(transition.transition_type))
+                .cloned(),
+            method: info.method,
+            inputs: Confined::from_iter_checked(info.inputs.iter().copied()),
+            assignments: Confined::from_iter_checked(assignments),
+        }
+    }
+}
+
+/// Compact description of the RGB state transitions committed to by a
+/// witness transaction, which is presented to the signer of the transaction.
+///
+/// The context can be serialized with strict encoding and passed to an
+/// air-gapped signer together with the PSBT; its [`Display`] implementation
+/// provides human-readable text for the signer screen.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SigningContext {
+    pub transitions: SmallVec<SigningTransition>,
+}
+
+impl StrictSerialize for SigningContext {}
+impl StrictDeserialize for SigningContext {}
+
+impl SigningContext {
+    /// Constructs signing context for the batch, using the provided function
+    /// to look up an interface implementation for naming the types used by
+    /// contracts.
+    pub fn with<'iimpl>(
+        batch: &Batch,
+        iimpl: impl Fn(ContractId) -> Option<&'iimpl IfaceImpl>,
+    ) -> Self {
+        let transitions = batch
+            .clone()
+            .into_iter()
+            .map(|info| SigningTransition::with(&info, iimpl(info.transition.contract_id)));
+        SigningContext {
+            transitions: Confined::from_iter_checked(transitions),
+        }
+    }
+
+    /// Returns ids of all state transitions presented to the signer.
+    pub fn opids(&self) -> impl Iterator<Item = OpId> + '_ {
+        self.transitions.iter().map(|transition| transition.opid)
+    }
+}
+
+impl Display for SigningContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for transition in &self.transitions {
+            write!(f, "contract {}, ", transition.contract_id)?;
+            match &transition.name {
+                Some(name) => write!(f, "{name}")?,
+                None => write!(f, "transition type {}", transition.ty)?,
+            }
+            writeln!(f, " {} closing {}:", transition.opid, transition.method)?;
+            for input in &transition.inputs {
+                writeln!(f, "  spends {input}")?;
+            }
+            for assignment in &transition.assignments {
+                match &assignment.name {
+                    Some(name) => write!(f, "  {name}: ")?,
+                    None => write!(f, "  assignment type {}: ", assignment.ty)?,
+                }
+                match &assignment.state {
+                    Some(state) => write!(f, "{state}")?,
+                    None => f.write_str("concealed state")?,
+                }
+                writeln!(f, " to {}", assignment.seal)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn signing_context() {
+        let batch = Batch::strict_dumb();
+        let context = SigningContext::with(&batch, |_| None);
+        assert_eq!(context.transitions.len(), batch.clone().into_iter().count());
+        assert_eq!(context.opids().collect::<Vec<_>>(), vec![batch.main.first.id]);
+
+        let transition = &context.transitions[0];
+        assert_eq!(transition.inputs.len(), 1);
+        let text = context.to_string();
+        assert!(text.contains(&format!("spends {}", transition.inputs.first().unwrap())));
+
+        let data = context.to_strict_serialized::<{ usize::MAX }>().unwrap();
+        assert_eq!(SigningContext::from_strict_serialized(data).unwrap(), context);
+    }
+}
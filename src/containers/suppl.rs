@@ -19,16 +19,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeSet;
 use std::fmt;
-use std::fmt::{Display, Formatter};
+use std::fmt::{Display, Formatter, Write};
 use std::str::FromStr;
 
-use amplify::confinement::{SmallBlob, TinyOrdMap};
+use amplify::confinement::{SmallBlob, TinyOrdMap, TinyOrdSet, TinyString};
 use amplify::{ByteArray, Bytes32};
 use baid64::{Baid64ParseError, DisplayBaid64, FromBaid64Str};
 use chrono::Utc;
 use commit_verify::{CommitId, CommitmentId, DigestExt, Sha256};
-use rgb::{AssignmentType, ContractId, GlobalStateType, Identity, SchemaId};
+use invoice::{Amount, Precision};
+use rgb::{AssignmentType, ContractId, GlobalStateType, Identity, Opout, SchemaId, XOutpoint};
 use strict_encoding::stl::{AlphaCaps, AlphaNumDash};
 use strict_encoding::{
     DeserializeError, FieldName, RString, SerializeError, StrictDeserialize, StrictSerialize,
@@ -37,11 +39,23 @@ use strict_encoding::{
 use strict_types::value;
 
 use crate::interface::{IfaceId, ImplId};
+use crate::stl::AssetSpec;
 use crate::LIB_NAME_RGB_STD;
 
 pub const SUPPL_ANNOT_VELOCITY: &str = "Velocity";
 pub const SUPPL_ANNOT_IFACE_CLASS: &str = "Standard";
 pub const SUPPL_ANNOT_IFACE_FEATURES: &str = "Features";
+pub const SUPPL_ANNOT_DEPENDENCIES: &str = "Dependencies";
+pub const SUPPL_ANNOT_DISPLAY: &str = "Display";
+pub const SUPPL_ANNOT_DOC: &str = "Doc";
+pub const SUPPL_ANNOT_ATTESTATION: &str = "Attestation";
+pub const SUPPL_ANNOT_CAPABILITIES: &str = "Capabilities";
+pub const SUPPL_ANNOT_DEFAULT_OPS: &str = "DefaultOps";
+
+pub const CAPABILITY_CONFIDENTIAL_AMOUNTS: &str = "confidentialAmounts";
+pub const CAPABILITY_BURNABLE: &str = "burnable";
+pub const CAPABILITY_INFLATABLE: &str = "inflatable";
+pub const CAPABILITY_ENGRAVABLE: &str = "engravable";
 
 /// Contract supplement identifier.
 ///
@@ -353,3 +367,526 @@ impl VelocityHint {
         }
     }
 }
+
+/// Allocation of another contract which the contract state depends on, like
+/// an asset backing a basket or a wrapped asset.
+///
+/// The dependency is declared in the contract genesis supplement under
+/// [`SUPPL_ANNOT_DEPENDENCIES`] annotation. The referenced allocation must
+/// remain locked at the specified outpoint for the contract state to be
+/// backed.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[display("{opout} of contract {contract_id} locked at {lock}")]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ContractDependency {
+    pub contract_id: ContractId,
+    pub opout: Opout,
+    /// Outpoint at which the allocation is locked.
+    pub lock: XOutpoint,
+}
+
+#[derive(Wrapper, WrapperMut, Clone, PartialEq, Eq, Hash, Debug, Default, From)]
+#[wrapper(Deref)]
+#[wrapper_mut(DerefMut)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct ContractDependencies(TinyOrdSet<ContractDependency>);
+
+impl StrictSerialize for ContractDependencies {}
+impl StrictDeserialize for ContractDependencies {}
+
+impl Supplement {
+    /// Returns dependencies of the contract declared in its genesis
+    /// supplement.
+    pub fn dependencies(&self) -> ContractDependencies {
+        match self.content_id {
+            ContentRef::Genesis(_) => self
+                .get_default_opt(SupplSub::Itself, SUPPL_ANNOT_DEPENDENCIES)
+                .unwrap_or_default(),
+            _ => none!(),
+        }
+    }
+
+    /// Declares dependencies of the contract in its genesis supplement.
+    pub fn declare_dependencies(
+        &mut self,
+        dependencies: &ContractDependencies,
+    ) -> Result<bool, SerializeError> {
+        self.annotate_itself(SUPPL_ANNOT_DEPENDENCIES, dependencies)
+    }
+}
+
+/// Named capability flags of the contracts created with a schema, like
+/// [`CAPABILITY_BURNABLE`] or [`CAPABILITY_INFLATABLE`], which allow wallets
+/// to enable actions on the contracts without knowing specific schema ids.
+///
+/// The capabilities are declared in the schema supplement under
+/// [`SUPPL_ANNOT_CAPABILITIES`] annotation.
+#[derive(Wrapper, WrapperMut, Clone, PartialEq, Eq, Hash, Debug, Default, From)]
+#[wrapper(Deref)]
+#[wrapper_mut(DerefMut)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct Capabilities(TinyOrdSet<FieldName>);
+
+impl StrictSerialize for Capabilities {}
+impl StrictDeserialize for Capabilities {}
+
+impl Capabilities {
+    /// Detects presence of a capability with the given name.
+    pub fn has(&self, name: &str) -> bool { self.iter().any(|cap| cap.as_str() == name) }
+
+    pub fn has_confidential_amounts(&self) -> bool { self.has(CAPABILITY_CONFIDENTIAL_AMOUNTS) }
+
+    pub fn is_burnable(&self) -> bool { self.has(CAPABILITY_BURNABLE) }
+
+    pub fn is_inflatable(&self) -> bool { self.has(CAPABILITY_INFLATABLE) }
+
+    pub fn is_engravable(&self) -> bool { self.has(CAPABILITY_ENGRAVABLE) }
+}
+
+impl Supplement {
+    /// Returns capabilities declared by a schema supplement.
+    pub fn capabilities(&self) -> Capabilities {
+        match self.content_id {
+            ContentRef::Schema(_) => self
+                .get_default_opt(SupplSub::Itself, SUPPL_ANNOT_CAPABILITIES)
+                .unwrap_or_default(),
+            _ => none!(),
+        }
+    }
+
+    /// Declares capabilities of the contracts in their schema supplement.
+    pub fn declare_capabilities(
+        &mut self,
+        capabilities: &Capabilities,
+    ) -> Result<bool, SerializeError> {
+        self.annotate_itself(SUPPL_ANNOT_CAPABILITIES, capabilities)
+    }
+}
+
+/// Class of wallet actions, which an interface implementation may map to
+/// one of its operations.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD, tags = repr, into_u8, try_from_u8)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+#[repr(u8)]
+pub enum OpClass {
+    /// Primary transfer of the contract state, used by "send" actions.
+    #[default]
+    Transfer = 0,
+    /// Secondary issuance of the contract state.
+    Issue = 1,
+    /// Burning of the contract state.
+    Burn = 2,
+    /// Replacement of the contract state, re-issuing burned state.
+    Replace = 3,
+}
+
+/// Names of the operations implementing wallet actions, which let generic
+/// wallets wire their actions to any contract without per-schema
+/// configuration.
+///
+/// The operations are declared in the interface implementation supplement
+/// under [`SUPPL_ANNOT_DEFAULT_OPS`] annotation.
+#[derive(Wrapper, WrapperMut, Clone, PartialEq, Eq, Hash, Debug, Default, From)]
+#[wrapper(Deref)]
+#[wrapper_mut(DerefMut)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct DefaultOps(TinyOrdMap<OpClass, FieldName>);
+
+impl StrictSerialize for DefaultOps {}
+impl StrictDeserialize for DefaultOps {}
+
+impl Supplement {
+    /// Returns operations implementing wallet actions declared by an interface
+    /// implementation supplement.
+    pub fn default_ops(&self) -> DefaultOps {
+        match self.content_id {
+            ContentRef::IfaceImpl(_) => self
+                .get_default_opt(SupplSub::Itself, SUPPL_ANNOT_DEFAULT_OPS)
+                .unwrap_or_default(),
+            _ => none!(),
+        }
+    }
+
+    /// Declares operations implementing wallet actions in the interface
+    /// implementation supplement.
+    pub fn declare_default_ops(&mut self, ops: &DefaultOps) -> Result<bool, SerializeError> {
+        self.annotate_itself(SUPPL_ANNOT_DEFAULT_OPS, ops)
+    }
+}
+
+/// Position of the unit symbol relative to the amount.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD, tags = repr, into_u8, try_from_u8)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[repr(u8)]
+pub enum SymbolPosition {
+    /// Symbol precedes the amount without a separator, like in `$10.5`.
+    Prefix = 0,
+    /// Symbol follows the amount after a space, like in `10.5 USDT`.
+    #[default]
+    Suffix = 1,
+}
+
+/// Separator used for grouping digits of the integer part of an amount in
+/// thousands.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD, tags = repr, into_u8, try_from_u8)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[repr(u8)]
+pub enum DigitGrouping {
+    #[default]
+    None = 0,
+    Space = 1,
+    Underscore = 2,
+    Comma = 3,
+}
+
+impl DigitGrouping {
+    pub fn separator(self) -> Option<char> {
+        match self {
+            DigitGrouping::None => None,
+            DigitGrouping::Space => Some(' '),
+            DigitGrouping::Underscore => Some('_'),
+            DigitGrouping::Comma => Some(','),
+        }
+    }
+}
+
+/// Rules for displaying amounts of a fungible contract state, declared by
+/// interface implementation or contract genesis supplements under
+/// [`SUPPL_ANNOT_DISPLAY`] annotation.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct DisplayRules {
+    /// Number of decimal digits in the amount.
+    pub precision: Precision,
+    /// Minimal number of fractional digits to display; trailing zeros after
+    /// them are trimmed.
+    pub min_decimals: u8,
+    pub symbol: Option<TinyString>,
+    pub symbol_position: SymbolPosition,
+    pub grouping: DigitGrouping,
+}
+
+impl StrictSerialize for DisplayRules {}
+impl StrictDeserialize for DisplayRules {}
+
+impl Default for DisplayRules {
+    fn default() -> Self {
+        DisplayRules {
+            precision: Precision::Indivisible,
+            min_decimals: 0,
+            symbol: None,
+            symbol_position: default!(),
+            grouping: default!(),
+        }
+    }
+}
+
+impl DisplayRules {
+    /// # Panics
+    ///
+    /// If the symbol is longer than 255 bytes.
+    pub fn with(precision: Precision, symbol: Option<&str>) -> Self {
+        DisplayRules {
+            precision,
+            symbol: symbol.map(|s| TinyString::from_checked(s.to_owned())),
+            ..default!()
+        }
+    }
+
+    /// Constructs rules matching the precision and ticker of the asset
+    /// specification, used when no display rules are declared by the contract
+    /// supplements.
+    pub fn with_spec(spec: &AssetSpec) -> Self { Self::with(spec.precision, Some(spec.ticker())) }
+
+    /// Constructs displayable representation of the amount following the
+    /// rules.
+    pub fn display(&self, amount: Amount) -> AmountDisplay<'_> {
+        AmountDisplay {
+            amount,
+            rules: self,
+        }
+    }
+}
+
+/// Amount formatted according to [`DisplayRules`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct AmountDisplay<'rules> {
+    pub amount: Amount,
+    pub rules: &'rules DisplayRules,
+}
+
+impl Display for AmountDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let rules = self.rules;
+        let (int, fract) = self.amount.split(rules.precision);
+
+        let mut s = String::new();
+        if let (Some(symbol), SymbolPosition::Prefix) = (&rules.symbol, rules.symbol_position) {
+            s.push_str(symbol);
+        }
+        let int = int.to_string();
+        for (pos, c) in int.chars().enumerate() {
+            if pos > 0 && (int.len() - pos) % 3 == 0 {
+                if let Some(sep) = rules.grouping.separator() {
+                    s.push(sep);
+                }
+            }
+            s.push(c);
+        }
+        let decimals = rules.precision.decimals() as usize;
+        let fract = format!("{fract:0>decimals$}");
+        let min = (rules.min_decimals as usize).min(decimals);
+        let fract = fract.trim_end_matches('0');
+        if fract.len().max(min) > 0 {
+            write!(s, ".{fract:0<min$}")?;
+        }
+        if let (Some(symbol), SymbolPosition::Suffix) = (&rules.symbol, rules.symbol_position) {
+            write!(s, " {symbol}")?;
+        }
+        f.pad(&s)
+    }
+}
+
+impl Supplement {
+    /// Returns rules for displaying amounts declared by the supplement.
+    pub fn display_rules(&self) -> Option<DisplayRules> {
+        self.get_default_opt(SupplSub::Itself, SUPPL_ANNOT_DISPLAY)
+    }
+}
+
+/// Verdict of a third party on a contract.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD, tags = repr, into_u8, try_from_u8)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+#[repr(u8)]
+pub enum Verdict {
+    /// Identity of the contract issuer is verified by the attester.
+    #[strict_type(dumb)]
+    VerifiedIssuer = 0,
+    /// Contract is endorsed by the attester.
+    Endorsed = 1,
+    /// Contract is suspected to be fraudulent.
+    Suspicious = 0x80,
+    /// Contract is known to be a scam.
+    Scam = 0x81,
+}
+
+impl Verdict {
+    /// Detects whether the verdict warns against the contract.
+    pub fn is_negative(self) -> bool { self as u8 >= 0x80 }
+}
+
+/// Attestation of a third party about a contract, used for maintaining
+/// community-curated lists of assets.
+///
+/// The attestation is put into a contract genesis supplement created by the
+/// attester under [`SUPPL_ANNOT_ATTESTATION`] annotation.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct Attestation {
+    pub verdict: Verdict,
+    pub comment: Option<TinyString>,
+}
+
+impl StrictSerialize for Attestation {}
+impl StrictDeserialize for Attestation {}
+
+impl Attestation {
+    pub fn new(verdict: Verdict) -> Self {
+        Attestation {
+            verdict,
+            comment: None,
+        }
+    }
+
+    /// Creates supplement of the attester carrying the attestation about the
+    /// contract.
+    pub fn to_supplement(
+        &self,
+        contract_id: ContractId,
+        attester: impl Into<Identity>,
+    ) -> Result<Supplement, SerializeError> {
+        let mut suppl = Supplement::new(contract_id, attester);
+        suppl.annotate_itself(SUPPL_ANNOT_ATTESTATION, self)?;
+        Ok(suppl)
+    }
+}
+
+/// Policy defining whose attestations must be taken into account by a
+/// wallet.
+pub trait AttestationPolicy {
+    /// Detects whether the identity belongs to a curator trusted by the
+    /// wallet.
+    fn is_curator(&self, identity: &Identity) -> bool;
+}
+
+impl AttestationPolicy for BTreeSet<Identity> {
+    fn is_curator(&self, identity: &Identity) -> bool { self.contains(identity) }
+}
+
+impl Supplement {
+    /// Returns third-party attestation about the contract carried by its
+    /// genesis supplement.
+    pub fn attestation(&self) -> Option<Attestation> {
+        match self.content_id {
+            ContentRef::Genesis(_) => {
+                self.get_default_opt(SupplSub::Itself, SUPPL_ANNOT_ATTESTATION)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn dependencies() {
+        let contract_id = ContractId::strict_dumb();
+        let mut suppl = Supplement::new(contract_id, "ssi:issuer");
+        assert!(suppl.dependencies().is_empty());
+
+        let dependency = ContractDependency::strict_dumb();
+        let dependencies = ContractDependencies::from(tiny_bset![dependency.clone()]);
+        assert!(!suppl.declare_dependencies(&dependencies).unwrap());
+        assert_eq!(suppl.dependencies(), dependencies);
+
+        let mut suppl = Supplement::new(SchemaId::strict_dumb(), "ssi:issuer");
+        suppl.declare_dependencies(&dependencies).unwrap();
+        assert!(suppl.dependencies().is_empty());
+    }
+
+    #[test]
+    fn display_rules() {
+        let mut rules = DisplayRules::with(Precision::CentiMicro, Some("USDT"));
+        rules.grouping = DigitGrouping::Space;
+        assert_eq!(rules.display(Amount::from(123_456_780_000u64)).to_string(), "1 234.5678 USDT");
+        assert_eq!(rules.display(Amount::from(100_000_000u64)).to_string(), "1 USDT");
+
+        rules.min_decimals = 2;
+        rules.symbol = Some(TinyString::from_checked(s!("$")));
+        rules.symbol_position = SymbolPosition::Prefix;
+        rules.grouping = DigitGrouping::Comma;
+        assert_eq!(rules.display(Amount::from(100_000_000_000u64)).to_string(), "$1,000.00");
+        assert_eq!(rules.display(Amount::from(1u64)).to_string(), "$0.00000001");
+
+        let spec = AssetSpec::with("TCKR", "Test asset", Precision::Centi, None).unwrap();
+        let spec_rules = DisplayRules::with_spec(&spec);
+        assert_eq!(spec_rules.display(Amount::from(123_450u64)).to_string(), "1234.5 TCKR");
+
+        let rules = DisplayRules::default();
+        assert_eq!(rules.display(Amount::from(1000u64)).to_string(), "1000");
+
+        let mut suppl = Supplement::new(ContractId::strict_dumb(), "ssi:issuer");
+        assert_eq!(suppl.display_rules(), None);
+        suppl.annotate_itself(SUPPL_ANNOT_DISPLAY, &rules).unwrap();
+        assert_eq!(suppl.display_rules(), Some(rules));
+    }
+
+    #[test]
+    fn attestation() {
+        let contract_id = ContractId::strict_dumb();
+        let curators = bset![Identity::from("ssi:curator")];
+        assert!(
+            Supplement::new(contract_id, "ssi:issuer")
+                .attestation()
+                .is_none()
+        );
+
+        let attestation = Attestation::new(Verdict::Scam);
+        let suppl = attestation
+            .to_supplement(contract_id, "ssi:curator")
+            .unwrap();
+        assert_eq!(suppl.attestation(), Some(attestation));
+        assert!(suppl.attestation().unwrap().verdict.is_negative());
+        assert!(curators.is_curator(&suppl.creator));
+        assert!(!Verdict::VerifiedIssuer.is_negative());
+    }
+
+    #[test]
+    fn capabilities() {
+        let capabilities =
+            Capabilities::from(tiny_bset![fname!(CAPABILITY_BURNABLE), fname!("custom")]);
+        assert!(capabilities.is_burnable());
+        assert!(capabilities.has("custom"));
+        assert!(!capabilities.is_inflatable());
+
+        let mut suppl = Supplement::new(SchemaId::strict_dumb(), "ssi:issuer");
+        assert!(suppl.capabilities().is_empty());
+        suppl.declare_capabilities(&capabilities).unwrap();
+        assert_eq!(suppl.capabilities(), capabilities);
+
+        let mut suppl = Supplement::new(ContractId::strict_dumb(), "ssi:issuer");
+        suppl.declare_capabilities(&capabilities).unwrap();
+        assert!(suppl.capabilities().is_empty());
+    }
+
+    #[test]
+    fn default_ops() {
+        let ops = DefaultOps::from(tiny_bmap! {
+            OpClass::Transfer => fname!("transfer"),
+            OpClass::Burn => fname!("burn"),
+        });
+        let mut suppl = Supplement::new(ImplId::strict_dumb(), "ssi:developer");
+        assert!(suppl.default_ops().is_empty());
+        suppl.declare_default_ops(&ops).unwrap();
+        assert_eq!(suppl.default_ops(), ops);
+        assert_eq!(suppl.default_ops().get(&OpClass::Burn), Some(&fname!("burn")));
+
+        let mut suppl = Supplement::new(SchemaId::strict_dumb(), "ssi:developer");
+        suppl.declare_default_ops(&ops).unwrap();
+        assert!(suppl.default_ops().is_empty());
+    }
+}
@@ -24,7 +24,7 @@ use std::collections::btree_map;
 use amplify::confinement::{NonEmptyBlob, NonEmptyOrdMap};
 use commit_verify::StrictHash;
 use rgb::{ContractId, Identity, SchemaId};
-use strict_encoding::StrictDumb;
+use strict_encoding::{StrictDeserialize, StrictDumb, StrictSerialize};
 
 use super::SupplId;
 use crate::interface::{IfaceId, ImplId};
@@ -45,15 +45,29 @@ pub enum ContainerVer {
     #[default]
     #[display("v2", alt = "2")]
     V2 = 2,
+
+    /// Consignments carrying extension records.
+    #[display("v3", alt = "3")]
+    V3 = 3,
+}
+
+impl ContainerVer {
+    /// Detects whether consignments of this version carry extension records.
+    pub fn has_records(self) -> bool { self >= ContainerVer::V3 }
 }
 
+/// Validator of the signatures over the consignment content.
 pub trait SigValidator {
-    fn validate_sig(&self, identity: &Identity, sig: SigBlob) -> bool;
+    /// Checks that the signature is produced by the identity over the content
+    /// with the provided id. Validators must check the signed message against
+    /// the content id, such that the signatures can't be copied to other
+    /// contracts or other content of the same contract.
+    fn validate_sig(&self, identity: &Identity, content_id: ContentId, sig: SigBlob) -> bool;
 }
 
 pub struct DumbValidator;
 impl SigValidator for DumbValidator {
-    fn validate_sig(&self, _: &Identity, _: SigBlob) -> bool { false }
+    fn validate_sig(&self, _: &Identity, _: ContentId, _: SigBlob) -> bool { false }
 }
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, Default)]
@@ -97,6 +111,9 @@ pub enum ContentId {
     Suppl(SupplId),
 }
 
+impl StrictSerialize for ContentId {}
+impl StrictDeserialize for ContentId {}
+
 #[derive(Wrapper, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, From, Display)]
 #[wrapper(Deref, AsSlice, BorrowSlice, Hex)]
 #[display(LowerHex)]
@@ -35,6 +35,7 @@ use rgb::{
 };
 use strict_encoding::{StrictDecode, StrictDumb, StrictEncode};
 
+use crate::interface::AllocatedState;
 use crate::LIB_NAME_RGB_STD;
 
 /// Trait used by contract state. Unlike [`ExposedState`] it doesn't allow
@@ -199,6 +200,10 @@ pub trait TypedAssignsExt<Seal: ExposedSeal> {
     fn reveal_seal(&mut self, seal: XChain<Seal>);
 
     fn filter_revealed_seals(&self) -> Vec<XChain<Seal>>;
+
+    /// Returns state of the assignment with the given index, if the state is
+    /// revealed.
+    fn revealed_state_at(&self, index: u16) -> Option<AllocatedState>;
 }
 
 impl<Seal: ExposedSeal> TypedAssignsExt<Seal> for TypedAssigns<Seal> {
@@ -252,4 +257,20 @@ impl<Seal: ExposedSeal> TypedAssignsExt<Seal> for TypedAssigns<Seal> {
             }
         }
     }
+
+    fn revealed_state_at(&self, index: u16) -> Option<AllocatedState> {
+        let index = index as usize;
+        match self {
+            TypedAssigns::Declarative(s) => {
+                s.get(index)?.as_revealed_state().copied().map(From::from)
+            }
+            TypedAssigns::Fungible(s) => s.get(index)?.as_revealed_state().copied().map(From::from),
+            TypedAssigns::Structured(s) => {
+                s.get(index)?.as_revealed_state().cloned().map(From::from)
+            }
+            TypedAssigns::Attachment(s) => {
+                s.get(index)?.as_revealed_state().cloned().map(From::from)
+            }
+        }
+    }
 }
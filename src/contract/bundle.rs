@@ -19,9 +19,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use rgb::{GraphSeal, OpId, Operation, Transition, TransitionBundle, XChain};
+use bp::{Outpoint, Tx};
+use rgb::{
+    AssignmentType, GraphSeal, OpId, Operation, Opout, Transition, TransitionBundle,
+    TransitionType, Vin, XChain,
+};
 
 use crate::contract::TypedAssignsExt;
+use crate::interface::AllocatedState;
+use crate::SecretSeal;
 
 #[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
 #[display(doc_comments)]
@@ -30,6 +36,65 @@ pub enum RevealError {
     UnrelatedTransition(OpId, Transition),
 }
 
+/// State assigned by a known state transition of a bundle.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct AssignmentView {
+    pub opout: Opout,
+    /// Concealed form of the seal, which is always known.
+    pub secret_seal: XChain<SecretSeal>,
+    /// Seal definition, if it is revealed.
+    pub seal: Option<XChain<GraphSeal>>,
+    /// Assigned state, if it is revealed.
+    pub state: Option<AllocatedState>,
+}
+
+/// Input of the witness transaction closing seals spent by a state
+/// transition.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct WitnessInput {
+    pub vin: Vin,
+    /// Outpoint spent by the input, if the witness transaction is known.
+    pub outpoint: Option<Outpoint>,
+}
+
+/// Structured representation of a known state transition of a bundle.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct TransitionView {
+    pub opid: OpId,
+    pub transition_type: TransitionType,
+    /// Outputs of the previous operations spent by the transition.
+    pub inputs: Vec<Opout>,
+    /// Inputs of the witness transaction closing the seals spent by the
+    /// transition.
+    pub witness_inputs: Vec<WitnessInput>,
+    /// State assigned by the transition.
+    pub assignments: Vec<AssignmentView>,
+}
+
+impl TransitionView {
+    /// Returns assignments of the given type.
+    pub fn assignments_of(&self, ty: AssignmentType) -> impl Iterator<Item = &AssignmentView> {
+        self.assignments
+            .iter()
+            .filter(move |assignment| assignment.opout.ty == ty)
+    }
+}
+
 pub trait BundleExt {
     /// Ensures that the seal is revealed inside the bundle.
     fn reveal_seal(&mut self, seal: XChain<GraphSeal>);
@@ -41,6 +106,15 @@ pub trait BundleExt {
     /// `true` if the transition was previously concealed; `false` if it was
     /// already revealed; error if the transition is unrelated to the bundle.
     fn reveal_transition(&mut self, transition: Transition) -> Result<bool, RevealError>;
+
+    /// Enumerates known state transitions of the bundle together with their
+    /// inputs, assignments and the witness transaction inputs closing the
+    /// spent seals. If the witness transaction is provided, the outpoints
+    /// spent by the witness inputs are reported as well.
+    ///
+    /// Concealed transitions, which are present only in the input map, are
+    /// not reported.
+    fn inspect(&self, witness: Option<&Tx>) -> Vec<TransitionView>;
 }
 
 impl BundleExt for TransitionBundle {
@@ -65,4 +139,106 @@ impl BundleExt for TransitionBundle {
             .expect("same size as input map");
         Ok(true)
     }
+
+    fn inspect(&self, witness: Option<&Tx>) -> Vec<TransitionView> {
+        self.known_transitions
+            .iter()
+            .map(|(opid, transition)| {
+                let witness_inputs = self
+                    .input_map
+                    .iter()
+                    .filter(|(_, id)| *id == opid)
+                    .map(|(vin, _)| WitnessInput {
+                        vin: *vin,
+                        outpoint: witness
+                            .and_then(|tx| tx.inputs.get(vin.to_usize()))
+                            .map(|input| input.prev_output),
+                    })
+                    .collect();
+                let mut assignments = vec![];
+                for (ty, assigns) in transition.assignments.iter() {
+                    let secret_seals = assigns.to_confidential_seals();
+                    for no in 0..assigns.len_u16() {
+                        assignments.push(AssignmentView {
+                            opout: Opout::new(*opid, *ty, no),
+                            secret_seal: secret_seals[no as usize],
+                            seal: assigns.revealed_seal_at(no).ok().flatten(),
+                            state: assigns.revealed_state_at(no),
+                        });
+                    }
+                }
+                TransitionView {
+                    opid: *opid,
+                    transition_type: transition.transition_type,
+                    inputs: transition
+                        .inputs()
+                        .iter()
+                        .map(|input| input.prev_out)
+                        .collect(),
+                    witness_inputs,
+                    assignments,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use bp::seals::txout::CloseMethod;
+    use bp::{Txid, Vout};
+    use commit_verify::Conceal;
+    use rgb::{
+        Assign, AssetTag, Assignments, InputMap, RevealedValue, TypedAssigns, XChain,
+    };
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn inspect() {
+        let seal = XChain::Bitcoin(GraphSeal::new_random(
+            CloseMethod::OpretFirst,
+            Txid::from([1u8; 32]),
+            Vout::from_u32(0),
+        ));
+        let ty = AssignmentType::with(4000);
+        let mut transition = Transition::strict_dumb();
+        transition.assignments = Assignments::from(Confined::from_checked(bmap! {
+            ty => TypedAssigns::Fungible(Confined::from_checked(vec![
+                Assign::revealed(
+                    seal,
+                    RevealedValue::new_random_blinding(10, AssetTag::from([2u8; 32])),
+                ),
+                Assign::revealed(
+                    seal,
+                    RevealedValue::new_random_blinding(20, AssetTag::from([2u8; 32])),
+                )
+                .conceal(),
+            ]))
+        }));
+        let opid = transition.id();
+        let mut bundle = TransitionBundle::strict_dumb();
+        bundle.input_map = InputMap::with(Vin::from_u32(1), opid);
+        bundle.known_transitions = Confined::from_checked(bmap! { opid => transition });
+
+        let views = bundle.inspect(None);
+        assert_eq!(views.len(), 1);
+        let view = &views[0];
+        assert_eq!(view.opid, opid);
+        assert_eq!(view.witness_inputs, vec![WitnessInput {
+            vin: Vin::from_u32(1),
+            outpoint: None
+        }]);
+        let assignments = view.assignments_of(ty).collect::<Vec<_>>();
+        assert_eq!(assignments.len(), 2);
+        assert_eq!(assignments[0].opout, Opout::new(opid, ty, 0));
+        assert_eq!(assignments[0].seal, Some(seal));
+        assert_eq!(assignments[0].secret_seal, seal.conceal());
+        assert!(assignments[0].state.is_some());
+        assert_eq!(assignments[1].seal, None);
+        assert_eq!(assignments[1].state, None);
+        assert_eq!(assignments[1].secret_seal, seal.conceal());
+    }
 }
@@ -20,6 +20,7 @@
 // limitations under the License.
 
 use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
 
 use amplify::confinement::Confined;
 use amplify::Wrapper;
@@ -27,11 +28,10 @@ use bp::Txid;
 use commit_verify::{mpc, Conceal};
 use rgb::{
     Assign, Assignments, BundleId, ExposedSeal, ExposedState, Extension, Genesis, OpId, Operation,
-    Transition, TransitionBundle, TypedAssigns,
+    Transition, TransitionBundle, TypedAssigns, XWitnessId,
 };
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Display, Error, From)]
-#[display(doc_comments)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Error, From)]
 pub enum MergeRevealError {
     /// operations {0} and {1} has different commitment ids and can't be
     /// merge-revealed. This usually means internal application business logic
@@ -41,8 +41,13 @@ pub enum MergeRevealError {
     /// mismatch in anchor chains: one grip references bitcoin transaction
     /// {bitcoin} and the other merged part references liquid transaction
     /// {liquid}.
+    #[deprecated(since = "0.11.0-beta.9", note = "use `LayerMismatch` instead")]
     ChainMismatch { bitcoin: Txid, liquid: Txid },
 
+    /// mismatch in anchor chains: witness transactions {0} and {1} belong to
+    /// different layer 1.
+    LayerMismatch(XWitnessId, XWitnessId),
+
     /// mismatching transaction id for merge-revealed: {0} and {1}.
     TxidMismatch(Txid, Txid),
 
@@ -59,6 +64,45 @@ pub enum MergeRevealError {
     ContractMismatch,
 }
 
+// Implemented manually since the derived implementation would use the
+// deprecated variant without allowing it.
+impl Display for MergeRevealError {
+    #[allow(deprecated)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeRevealError::OperationMismatch(id1, id2) => write!(
+                f,
+                "operations {id1} and {id2} has different commitment ids and can't be \
+                 merge-revealed. This usually means internal application business logic error \
+                 which should be reported to the software vendor."
+            ),
+            MergeRevealError::ChainMismatch { bitcoin, liquid } => write!(
+                f,
+                "mismatch in anchor chains: one grip references bitcoin transaction {bitcoin} and \
+                 the other merged part references liquid transaction {liquid}."
+            ),
+            MergeRevealError::LayerMismatch(id1, id2) => write!(
+                f,
+                "mismatch in anchor chains: witness transactions {id1} and {id2} belong to \
+                 different layer 1."
+            ),
+            MergeRevealError::TxidMismatch(txid1, txid2) => {
+                write!(f, "mismatching transaction id for merge-revealed: {txid1} and {txid2}.")
+            }
+            MergeRevealError::AnchorsNonEqual(bundle_id) => {
+                write!(f, "anchors in anchored bundle are not equal for bundle {bundle_id}.")
+            }
+            MergeRevealError::InsufficientInputs => {
+                f.write_str("the merged bundles contain more transitions than inputs.")
+            }
+            MergeRevealError::ContractMismatch => f.write_str(
+                "contract id provided for the merge-reveal operation doesn't match multiprotocol \
+                 commitment.",
+            ),
+        }
+    }
+}
+
 /// A trait to merge two structures modifying the revealed status
 /// of the first one. The merge operation will **consume** both the structures
 /// and return a new structure with revealed states.
@@ -24,7 +24,7 @@ mod bundle;
 mod merge_reveal;
 
 pub use assignments::{KnownState, OutputAssignment, TypedAssignsExt, WitnessInfo};
-pub use bundle::{BundleExt, RevealError};
+pub use bundle::{AssignmentView, BundleExt, RevealError, TransitionView, WitnessInput};
 pub use merge_reveal::{MergeReveal, MergeRevealError};
 use rgb::vm::OrdOpRef;
 use rgb::{ExtensionType, OpId, TransitionType, XWitnessId};
@@ -0,0 +1,317 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test asset faucet for contract and wallet developers.
+//!
+//! [`Faucet`] issues throwaway RGB20 and RGB21 assets on the outputs of a
+//! funded signet or testnet wallet and sends them to the provided invoices,
+//! returning the consignments for the recipients. This allows downstream
+//! applications to obtain test assets programmatically, without running a
+//! full RGB wallet.
+//!
+//! The faucet keeps the issued contracts in an in-memory [`Stock`]. The
+//! wallet, implementing [`FaucetWallet`], provides the unspent outputs for
+//! the issue and constructs, signs and broadcasts the witness transactions.
+
+#![allow(clippy::result_large_err)]
+
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use bp::seals::txout::{CloseMethod, ExplicitSeal};
+use bp::{Outpoint, Vout};
+use invoice::{Allocation, Amount, Beneficiary, Precision, RgbInvoice, TokenIndex};
+use rgb::validation::ResolveWitness;
+use rgb::{ContractId, GenesisSeal, Identity, SchemaId, XChain, XOutputSeal};
+use strict_encoding::StrictSerialize;
+
+use crate::containers::{
+    Batch, BuilderSeal, ConsignmentExt, Fascia, Transfer, ValidContract, ValidKit,
+};
+use crate::interface::{ContractBuilder, IfaceRef, Rgb20Template, Rgb21Template, TemplateError};
+use crate::persistence::Stock;
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum FaucetError<E: Error> {
+    /// wallet error: {0}
+    Wallet(E),
+
+    #[from]
+    #[display(inner)]
+    Template(TemplateError),
+
+    /// stock error: {0}
+    Stock(String),
+
+    /// invoice doesn't specify a contract.
+    NoContract,
+
+    /// contract {0} was not issued by the faucet or has its state spent.
+    UnknownContract(ContractId),
+}
+
+/// Funded signet or testnet wallet used by the [`Faucet`].
+pub trait FaucetWallet {
+    type Error: Error;
+
+    /// Seal closing method used by the wallet.
+    fn close_method(&self) -> CloseMethod;
+
+    /// Returns an unspent output of the wallet which will hold a newly
+    /// issued asset.
+    fn issue_outpoint(&mut self) -> Result<Outpoint, Self::Error>;
+
+    /// Number of the witness transaction output receiving the change.
+    fn change_vout(&self) -> Vout;
+
+    /// Number of the witness transaction output paying to the beneficiary of
+    /// a witness vout invoice.
+    fn beneficiary_vout(&self) -> Vout;
+
+    /// Constructs witness transaction spending the previous outputs of the
+    /// batch and committing to its state transitions, then signs and
+    /// broadcasts it, returning the fascia of the transaction.
+    ///
+    /// The change must be paid to [`FaucetWallet::change_vout`] output.
+    fn pay(&mut self, batch: Batch) -> Result<Fascia, Self::Error>;
+}
+
+/// Faucet issuing test assets and sending them to invoices.
+#[derive(Debug)]
+pub struct Faucet<W: FaucetWallet> {
+    wallet: W,
+    stock: Stock,
+    outputs: BTreeMap<ContractId, XOutputSeal>,
+}
+
+impl<W: FaucetWallet> Faucet<W> {
+    /// Constructs faucet using the kit which must provide the schemata,
+    /// interfaces and implementations of the issued assets.
+    pub fn new(wallet: W, kit: ValidKit) -> Result<Self, FaucetError<W::Error>> {
+        let mut stock = Stock::in_memory();
+        stock
+            .import_kit(kit)
+            .map_err(|err| FaucetError::Stock(err.to_string()))?;
+        Ok(Faucet {
+            wallet,
+            stock,
+            outputs: none!(),
+        })
+    }
+
+    pub fn wallet(&self) -> &W { &self.wallet }
+
+    pub fn stock(&self) -> &Stock { &self.stock }
+
+    /// Returns output currently holding the state of the contract.
+    pub fn output(&self, contract_id: ContractId) -> Option<XOutputSeal> {
+        self.outputs.get(&contract_id).copied()
+    }
+
+    /// Issues fungible asset under RGB20 interface, allocating the whole
+    /// supply to a single output of the wallet.
+    #[allow(clippy::too_many_arguments)]
+    pub fn issue_rgb20(
+        &mut self,
+        schema_id: SchemaId,
+        iface: impl Into<IfaceRef>,
+        ticker: &str,
+        name: &str,
+        precision: Precision,
+        supply: impl Into<Amount>,
+        resolver: impl ResolveWitness,
+    ) -> Result<ContractId, FaucetError<W::Error>> {
+        let supply = supply.into();
+        let (seal, output) = self.issue_seal()?;
+        let template =
+            Rgb20Template::testnet_simple(ticker, name, precision, supply, [(seal, supply)])?;
+        let contract = template.issue(self.builder(schema_id, iface)?)?;
+        self.import(contract, output, resolver)
+    }
+
+    /// Issues collection with a single non-fungible token under RGB21
+    /// interface, allocating the token to a single output of the wallet.
+    ///
+    /// The type of the token data is defined by the interface implementation.
+    #[allow(clippy::too_many_arguments)]
+    pub fn issue_rgb21<T: StrictSerialize>(
+        &mut self,
+        schema_id: SchemaId,
+        iface: impl Into<IfaceRef>,
+        ticker: &str,
+        name: &str,
+        index: impl Into<TokenIndex>,
+        token: T,
+        resolver: impl ResolveWitness,
+    ) -> Result<ContractId, FaucetError<W::Error>> {
+        let (seal, output) = self.issue_seal()?;
+        let allocation = Allocation::with(index, 1u64);
+        let template = Rgb21Template::testnet_simple(ticker, name, [token], [(seal, allocation)])?;
+        let contract = template.issue(self.builder(schema_id, iface)?)?;
+        self.import(contract, output, resolver)
+    }
+
+    /// Sends asset to the invoice, returning the consignment for the
+    /// recipient.
+    ///
+    /// The witness transaction is constructed, signed and broadcast by the
+    /// wallet; the consignment can be sent to the recipient right away.
+    pub fn send(
+        &mut self,
+        invoice: &RgbInvoice,
+        resolver: impl ResolveWitness,
+    ) -> Result<Transfer, FaucetError<W::Error>> {
+        let contract_id = invoice.contract.ok_or(FaucetError::NoContract)?;
+        let prev_output = self
+            .output(contract_id)
+            .ok_or(FaucetError::UnknownContract(contract_id))?;
+        let beneficiary_vout = match invoice.beneficiary.into_inner() {
+            Beneficiary::BlindedSeal(_) => None,
+            Beneficiary::WitnessVout(_) => Some(self.wallet.beneficiary_vout()),
+        };
+        let change_vout = self.wallet.change_vout();
+        let batch = self
+            .stock
+            .compose(
+                invoice,
+                [prev_output],
+                self.wallet.close_method(),
+                beneficiary_vout,
+                |_, _, _| Some(change_vout),
+            )
+            .map_err(|err| FaucetError::Stock(err.to_string()))?;
+
+        let fascia = self.wallet.pay(batch).map_err(FaucetError::Wallet)?;
+        let witness_id = fascia.witness_id();
+        self.stock
+            .consume_fascia(fascia, resolver)
+            .map_err(|err| FaucetError::Stock(err.to_string()))?;
+        let transfer = self
+            .stock
+            .transfers(witness_id, [(invoice, beneficiary_vout)])
+            .map_err(|err| FaucetError::Stock(err.to_string()))?
+            .pop()
+            .expect("single transfer per invoice");
+
+        let change = witness_id
+            .map(|txid| ExplicitSeal::with(self.wallet.close_method(), txid, change_vout));
+        self.outputs.insert(contract_id, change);
+        Ok(transfer)
+    }
+
+    fn issue_seal(
+        &mut self,
+    ) -> Result<(BuilderSeal<GenesisSeal>, XOutputSeal), FaucetError<W::Error>> {
+        let method = self.wallet.close_method();
+        let outpoint = self.wallet.issue_outpoint().map_err(FaucetError::Wallet)?;
+        let seal = GenesisSeal::new_random(method, outpoint.txid, outpoint.vout);
+        let output = ExplicitSeal::with(method, outpoint.txid, outpoint.vout);
+        Ok((BuilderSeal::from(XChain::Bitcoin(seal)), XChain::Bitcoin(output)))
+    }
+
+    fn builder(
+        &self,
+        schema_id: SchemaId,
+        iface: impl Into<IfaceRef>,
+    ) -> Result<ContractBuilder, FaucetError<W::Error>> {
+        self.stock
+            .contract_builder(Identity::default(), schema_id, iface)
+            .map_err(|err| FaucetError::Stock(err.to_string()))
+    }
+
+    fn import(
+        &mut self,
+        contract: ValidContract,
+        output: XOutputSeal,
+        resolver: impl ResolveWitness,
+    ) -> Result<ContractId, FaucetError<W::Error>> {
+        let contract_id = contract.contract_id();
+        self.stock
+            .import_contract(contract, resolver)
+            .map_err(|err| FaucetError::Stock(err.to_string()))?;
+        self.outputs.insert(contract_id, output);
+        Ok(contract_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+
+    use bp::Txid;
+    use invoice::{RgbInvoiceBuilder, XChainNet};
+    use rgb::SecretSeal;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::interface::resolver::DumbResolver;
+
+    struct Wallet;
+
+    impl FaucetWallet for Wallet {
+        type Error = Infallible;
+
+        fn close_method(&self) -> CloseMethod { CloseMethod::OpretFirst }
+
+        fn issue_outpoint(&mut self) -> Result<Outpoint, Self::Error> {
+            Ok(Outpoint::new(Txid::from([1u8; 32]), 0))
+        }
+
+        fn change_vout(&self) -> Vout { Vout::from_u32(1) }
+
+        fn beneficiary_vout(&self) -> Vout { Vout::from_u32(0) }
+
+        fn pay(&mut self, _batch: Batch) -> Result<Fascia, Self::Error> {
+            Ok(Fascia::strict_dumb())
+        }
+    }
+
+    #[test]
+    fn faucet_errors() {
+        let mut faucet = Faucet {
+            wallet: Wallet,
+            stock: Stock::in_memory(),
+            outputs: none!(),
+        };
+        assert!(matches!(
+            faucet.issue_rgb20(
+                SchemaId::strict_dumb(),
+                "RGB20",
+                "TCKR",
+                "Test asset",
+                Precision::Indivisible,
+                100u64,
+                DumbResolver
+            ),
+            Err(FaucetError::Stock(_))
+        ));
+        assert!(faucet.outputs.is_empty());
+
+        let beneficiary =
+            XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(SecretSeal::strict_dumb()));
+        let invoice = RgbInvoiceBuilder::with(ContractId::strict_dumb(), beneficiary).finish();
+        assert!(matches!(
+            faucet.send(&invoice, DumbResolver),
+            Err(FaucetError::UnknownContract(id)) if id == ContractId::strict_dumb()
+        ));
+    }
+}
@@ -0,0 +1,754 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! gRPC server over the [`Service`] facade.
+//!
+//! Server and client code is generated with tonic from `proto/service.proto`
+//! into the [`proto`] module. [`GrpcServer`] implements the generated
+//! [`RgbService`] trait and is served with any tonic transport after wrapping
+//! it into [`RgbServiceServer`](proto::rgb_service_server::RgbServiceServer).
+//!
+//! Requests are processed by the service on a blocking thread of the tokio
+//! runtime; consignment imports stream validation progress to the client and
+//! are cancelled once the client drops the stream.
+//!
+//! If an authentication callback is set with
+//! [`GrpcServer::with_authentication`], it maps request metadata (like bearer
+//! token) to an account, on behalf of which the request is processed with
+//! [`Service::process_as`].
+
+#![allow(clippy::result_large_err)]
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use amplify::confinement::{Confined, U32 as U32MAX};
+use bp::seals::txout::CloseMethod;
+use rgb::validation::{self, ResolveWitness};
+use strict_encoding::{StrictDeserialize, StrictSerialize};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::metadata::MetadataMap;
+use tonic::Status;
+
+use self::proto::rgb_service_server::RgbService;
+use self::proto::{import_event, label_ref, progress};
+use crate::info::{ContractInfo, IfaceInfo, ImplInfo, SchemaInfo};
+use crate::persistence::{
+    IndexProvider, LabelRef, MemIndex, MemStash, MemState, StashProvider, StateProvider,
+};
+use crate::resolvers::{Progress, ProgressSink};
+use crate::service::{HistoryEntry, Request, Response, Service};
+use crate::Amount;
+
+/// Types and traits generated from `proto/service.proto`.
+#[allow(clippy::all, missing_docs)]
+pub mod proto {
+    tonic::include_proto!("rgb.service.v1");
+}
+
+/// Callback authenticating gRPC requests by their metadata, returning the
+/// account on behalf of which the request is processed.
+pub type Authenticate = dyn Fn(&MetadataMap) -> Result<String, Status> + Send + Sync;
+
+/// Stream of events produced by consignment imports.
+pub type ImportStream = UnboundedReceiverStream<Result<proto::ImportEvent, Status>>;
+
+/// gRPC server processing requests with a [`Service`].
+pub struct GrpcServer<
+    R: ResolveWitness,
+    S: StashProvider = MemStash,
+    H: StateProvider = MemState,
+    P: IndexProvider = MemIndex,
+> {
+    service: Arc<Mutex<Service<R, S, H, P>>>,
+    auth: Option<Arc<Authenticate>>,
+}
+
+impl<R: ResolveWitness, S: StashProvider, H: StateProvider, P: IndexProvider> Clone
+    for GrpcServer<R, S, H, P>
+{
+    fn clone(&self) -> Self {
+        GrpcServer {
+            service: self.service.clone(),
+            auth: self.auth.clone(),
+        }
+    }
+}
+
+impl<R: ResolveWitness, S: StashProvider, H: StateProvider, P: IndexProvider>
+    GrpcServer<R, S, H, P>
+{
+    pub fn new(service: Service<R, S, H, P>) -> Self {
+        GrpcServer {
+            service: Arc::new(Mutex::new(service)),
+            auth: None,
+        }
+    }
+
+    /// Sets callback authenticating requests; authenticated requests are
+    /// processed on behalf of the returned account, subject to the service
+    /// access control lists.
+    pub fn with_authentication(
+        mut self,
+        auth: impl Fn(&MetadataMap) -> Result<String, Status> + Send + Sync + 'static,
+    ) -> Self {
+        self.auth = Some(Arc::new(auth));
+        self
+    }
+
+    /// Returns service shared by all clones of the server.
+    pub fn service(&self) -> &Arc<Mutex<Service<R, S, H, P>>> { &self.service }
+
+    fn account(&self, metadata: &MetadataMap) -> Result<Option<String>, Status> {
+        self.auth.as_ref().map(|auth| auth(metadata)).transpose()
+    }
+}
+
+impl<R, S, H, P> GrpcServer<R, S, H, P>
+where
+    R: ResolveWitness + Send + 'static,
+    S: StashProvider + Send + 'static,
+    H: StateProvider + Send + 'static,
+    P: IndexProvider + Send + 'static,
+{
+    async fn call<T>(
+        &self,
+        metadata: &MetadataMap,
+        request: Request,
+        reply: impl FnOnce(Response) -> Result<T, Status>,
+    ) -> Result<tonic::Response<T>, Status> {
+        let account = self.account(metadata)?;
+        let service = self.service.clone();
+        let response =
+            tokio::task::spawn_blocking(move || Self::process(&service, account, request, &()))
+                .await
+                .map_err(|err| Status::internal(err.to_string()))?;
+        reply(response).map(tonic::Response::new)
+    }
+
+    fn import(
+        &self,
+        metadata: &MetadataMap,
+        request: Request,
+    ) -> Result<tonic::Response<ImportStream>, Status> {
+        let account = self.account(metadata)?;
+        let service = self.service.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::task::spawn_blocking(move || {
+            let sink = EventSink(tx);
+            let event = match Self::process(&service, account, request, &sink) {
+                Response::Imported(status) => Ok(validation_reply(true, status)),
+                Response::Invalid(status) => Ok(validation_reply(false, status)),
+                other => Err(failure(other)),
+            };
+            // The client may have already dropped the stream
+            let _ = sink.0.send(event.map(|reply| proto::ImportEvent {
+                event: Some(import_event::Event::Result(reply)),
+            }));
+        });
+        Ok(tonic::Response::new(UnboundedReceiverStream::new(rx)))
+    }
+
+    fn process(
+        service: &Mutex<Service<R, S, H, P>>,
+        account: Option<String>,
+        request: Request,
+        sink: &impl ProgressSink,
+    ) -> Response {
+        let Ok(mut service) = service.lock() else {
+            return Response::Failure(s!("service is unavailable after a panic"));
+        };
+        match account {
+            Some(account) => service.process_as(&account, request, sink),
+            None => service.process(request, sink),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<R, S, H, P> RgbService for GrpcServer<R, S, H, P>
+where
+    R: ResolveWitness + Send + 'static,
+    S: StashProvider + Send + 'static,
+    H: StateProvider + Send + 'static,
+    P: IndexProvider + Send + 'static,
+{
+    async fn list_ifaces(
+        &self,
+        request: tonic::Request<proto::Empty>,
+    ) -> Result<tonic::Response<proto::IfacesReply>, Status> {
+        self.call(request.metadata(), Request::Ifaces, |response| match response {
+            Response::Ifaces(ifaces) => Ok(proto::IfacesReply {
+                ifaces: ifaces.into_iter().map(proto::IfaceInfo::from).collect(),
+            }),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+
+    async fn list_schemata(
+        &self,
+        request: tonic::Request<proto::Empty>,
+    ) -> Result<tonic::Response<proto::SchemataReply>, Status> {
+        self.call(request.metadata(), Request::Schemata, |response| match response {
+            Response::Schemata(schemata) => Ok(proto::SchemataReply {
+                schemata: schemata.into_iter().map(proto::SchemaInfo::from).collect(),
+            }),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+
+    async fn list_contracts(
+        &self,
+        request: tonic::Request<proto::Empty>,
+    ) -> Result<tonic::Response<proto::ContractsReply>, Status> {
+        self.call(request.metadata(), Request::Contracts, |response| match response {
+            Response::Contracts(contracts) => Ok(proto::ContractsReply {
+                contracts: contracts
+                    .into_iter()
+                    .map(proto::ContractInfo::from)
+                    .collect(),
+            }),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+
+    async fn get_contract_info(
+        &self,
+        request: tonic::Request<proto::ContractRequest>,
+    ) -> Result<tonic::Response<proto::ContractInfo>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        let request = Request::ContractInfo(parse(&req.contract_id, "contract id")?);
+        self.call(&metadata, request, |response| match response {
+            Response::ContractInfo(info) => Ok(info.into()),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+
+    async fn get_balance(
+        &self,
+        request: tonic::Request<proto::BalanceRequest>,
+    ) -> Result<tonic::Response<proto::BalanceReply>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        let request = Request::Balance {
+            contract_id: parse(&req.contract_id, "contract id")?,
+            outpoints: parse_all(&req.outpoints, "outpoint")?,
+        };
+        self.call(&metadata, request, |response| match response {
+            Response::Balance(amount) => Ok(proto::BalanceReply {
+                amount: amount.value(),
+            }),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+
+    async fn get_history(
+        &self,
+        request: tonic::Request<proto::ContractRequest>,
+    ) -> Result<tonic::Response<proto::HistoryReply>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        let request = Request::History(parse(&req.contract_id, "contract id")?);
+        self.call(&metadata, request, |response| match response {
+            Response::History(entries) => Ok(proto::HistoryReply {
+                entries: entries.into_iter().map(proto::HistoryEntry::from).collect(),
+            }),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+
+    async fn new_invoice(
+        &self,
+        request: tonic::Request<proto::InvoiceRequest>,
+    ) -> Result<tonic::Response<proto::InvoiceReply>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        let close_method = match proto::CloseMethod::try_from(req.close_method) {
+            Ok(proto::CloseMethod::OpretFirst) => CloseMethod::OpretFirst,
+            Ok(proto::CloseMethod::TapretFirst) => CloseMethod::TapretFirst,
+            Err(_) => return Err(Status::invalid_argument("invalid close method")),
+        };
+        let request = Request::NewInvoice {
+            contract_id: parse(&req.contract_id, "contract id")?,
+            outpoint: parse(&req.outpoint, "outpoint")?,
+            amount: Amount::from(req.amount),
+            close_method,
+        };
+        self.call(&metadata, request, |response| match response {
+            Response::Invoice(invoice) => Ok(proto::InvoiceReply { invoice }),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+
+    async fn import_kit(
+        &self,
+        request: tonic::Request<proto::Consignment>,
+    ) -> Result<tonic::Response<proto::ValidationReply>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        let request = Request::ImportKit(decode(req.data)?);
+        self.call(&metadata, request, |response| match response {
+            Response::Imported(status) => Ok(validation_reply(true, status)),
+            Response::Invalid(status) => Ok(validation_reply(false, status)),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+
+    type ImportContractStream = ImportStream;
+
+    async fn import_contract(
+        &self,
+        request: tonic::Request<proto::Consignment>,
+    ) -> Result<tonic::Response<ImportStream>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        self.import(&metadata, Request::ImportContract(decode(req.data)?))
+    }
+
+    type AcceptTransferStream = ImportStream;
+
+    async fn accept_transfer(
+        &self,
+        request: tonic::Request<proto::Consignment>,
+    ) -> Result<tonic::Response<ImportStream>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        self.import(&metadata, Request::AcceptTransfer(decode(req.data)?))
+    }
+
+    async fn export_contract(
+        &self,
+        request: tonic::Request<proto::ContractRequest>,
+    ) -> Result<tonic::Response<proto::Consignment>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        let request = Request::ExportContract(parse(&req.contract_id, "contract id")?);
+        self.call(&metadata, request, |response| match response {
+            Response::Contract(contract) => encode(&contract),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+
+    async fn transfer(
+        &self,
+        request: tonic::Request<proto::TransferRequest>,
+    ) -> Result<tonic::Response<proto::Consignment>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        let request = Request::Transfer {
+            contract_id: parse(&req.contract_id, "contract id")?,
+            outputs: parse_all(&req.outputs, "output seal")?,
+            secret_seal: req
+                .secret_seal
+                .as_deref()
+                .map(|seal| parse(seal, "secret seal"))
+                .transpose()?,
+        };
+        self.call(&metadata, request, |response| match response {
+            Response::Transfer(transfer) => encode(&transfer),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+
+    async fn store_secret_seal(
+        &self,
+        request: tonic::Request<proto::SealRequest>,
+    ) -> Result<tonic::Response<proto::StoredReply>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        let request = Request::StoreSecretSeal(parse(&req.seal, "seal")?);
+        self.call(&metadata, request, stored).await
+    }
+
+    async fn list_labels(
+        &self,
+        request: tonic::Request<proto::Empty>,
+    ) -> Result<tonic::Response<proto::LabelsReply>, Status> {
+        self.call(request.metadata(), Request::Labels, |response| match response {
+            Response::Labels(labels) => Ok(proto::LabelsReply {
+                labels: labels
+                    .into_iter()
+                    .map(|(label_ref, label)| proto::Label {
+                        label_ref: Some(label_ref.into()),
+                        label,
+                    })
+                    .collect(),
+            }),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+
+    async fn set_label(
+        &self,
+        request: tonic::Request<proto::SetLabelRequest>,
+    ) -> Result<tonic::Response<proto::StoredReply>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        let label_ref = match req.label_ref.and_then(|label_ref| label_ref.target) {
+            Some(label_ref::Target::Contract(id)) => LabelRef::Contract(parse(&id, "contract id")?),
+            Some(label_ref::Target::Transfer(id)) => {
+                LabelRef::Transfer(parse(&id, "consignment id")?)
+            }
+            Some(label_ref::Target::Allocation(opout)) => {
+                LabelRef::Allocation(parse(&opout, "opout")?)
+            }
+            None => return Err(Status::invalid_argument("missed label reference")),
+        };
+        let request = Request::SetLabel {
+            label_ref,
+            label: req.label,
+        };
+        self.call(&metadata, request, stored).await
+    }
+
+    async fn update_witnesses(
+        &self,
+        request: tonic::Request<proto::UpdateWitnessesRequest>,
+    ) -> Result<tonic::Response<proto::UpdateWitnessesReply>, Status> {
+        let (metadata, _, req) = request.into_parts();
+        let request = Request::UpdateWitnesses {
+            after_height: req.after_height,
+        };
+        self.call(&metadata, request, |response| match response {
+            Response::Updated { succeeded, failed } => Ok(proto::UpdateWitnessesReply {
+                succeeded: succeeded as u64,
+                failed: failed
+                    .into_iter()
+                    .map(|(id, err)| (id.to_string(), err))
+                    .collect::<HashMap<_, _>>(),
+            }),
+            other => Err(failure(other)),
+        })
+        .await
+    }
+}
+
+/// Progress sink forwarding progress to the import stream, which cancels
+/// the import once the client drops the stream.
+struct EventSink(UnboundedSender<Result<proto::ImportEvent, Status>>);
+
+impl ProgressSink for EventSink {
+    fn report(&self, progress: Progress) {
+        let stage = match progress {
+            Progress::Validating(contract_id) => {
+                progress::Stage::Validating(contract_id.to_string())
+            }
+            Progress::Bundles { processed, total } => {
+                progress::Stage::Bundles(proto::BundlesProgress {
+                    processed: processed as u64,
+                    total: total as u64,
+                })
+            }
+            Progress::Storing => progress::Stage::Storing(proto::Empty {}),
+        };
+        let event = import_event::Event::Progress(proto::Progress { stage: Some(stage) });
+        let _ = self.0.send(Ok(proto::ImportEvent { event: Some(event) }));
+    }
+
+    fn is_cancelled(&self) -> bool { self.0.is_closed() }
+}
+
+fn parse<T: FromStr>(s: &str, what: &str) -> Result<T, Status>
+where T::Err: Display {
+    T::from_str(s).map_err(|err| Status::invalid_argument(format!("invalid {what} '{s}': {err}")))
+}
+
+fn parse_all<T: FromStr>(items: &[String], what: &str) -> Result<Vec<T>, Status>
+where T::Err: Display {
+    items.iter().map(|s| parse(s, what)).collect()
+}
+
+fn decode<T: StrictDeserialize>(data: Vec<u8>) -> Result<T, Status> {
+    let data = Confined::try_from(data).map_err(|err| Status::invalid_argument(err.to_string()))?;
+    T::from_strict_serialized::<U32MAX>(data)
+        .map_err(|err| Status::invalid_argument(format!("invalid consignment data: {err}")))
+}
+
+fn encode(data: &impl StrictSerialize) -> Result<proto::Consignment, Status> {
+    let data = data
+        .to_strict_serialized::<U32MAX>()
+        .map_err(|err| Status::internal(err.to_string()))?;
+    Ok(proto::Consignment {
+        data: data.release(),
+    })
+}
+
+fn stored(response: Response) -> Result<proto::StoredReply, Status> {
+    match response {
+        Response::Stored(new) => Ok(proto::StoredReply { new }),
+        other => Err(failure(other)),
+    }
+}
+
+fn validation_reply(imported: bool, status: validation::Status) -> proto::ValidationReply {
+    proto::ValidationReply {
+        imported,
+        status: Some(proto::ValidationStatus {
+            failures: status.failures.iter().map(ToString::to_string).collect(),
+            warnings: status.warnings.iter().map(ToString::to_string).collect(),
+            info: status.info.iter().map(ToString::to_string).collect(),
+        }),
+    }
+}
+
+/// Converts service response which doesn't match the request into a gRPC
+/// status.
+fn failure(response: Response) -> Status {
+    match response {
+        Response::Cancelled => Status::cancelled("request is cancelled"),
+        Response::Denied { permission, .. } => {
+            Status::permission_denied(format!("{permission} permission is required"))
+        }
+        Response::Invalid(status) => Status::failed_precondition(status.to_string()),
+        Response::Failure(err) => Status::internal(err),
+        other => Status::internal(format!("unexpected service response {other:?}")),
+    }
+}
+
+impl From<IfaceInfo> for proto::IfaceInfo {
+    fn from(info: IfaceInfo) -> Self {
+        proto::IfaceInfo {
+            id: info.id.to_string(),
+            version: info.version as u32,
+            name: info.name.to_string(),
+            standard: info.standard.map(|standard| standard.to_string()),
+            features: info.features.iter().map(ToString::to_string).collect(),
+            developer: info.developer.to_string(),
+            created_at: info.created_at.timestamp(),
+            inherits: info.inherits.iter().map(ToString::to_string).collect(),
+            default_op: info.default_op.map(|op| op.to_string()),
+        }
+    }
+}
+
+impl From<ImplInfo> for proto::ImplInfo {
+    fn from(info: ImplInfo) -> Self {
+        proto::ImplInfo {
+            id: info.id.to_string(),
+            iface_id: info.iface_id.to_string(),
+            iface_name: info.iface_name.to_string(),
+            developer: info.developer.to_string(),
+            created_at: info.created_at.timestamp(),
+        }
+    }
+}
+
+impl From<SchemaInfo> for proto::SchemaInfo {
+    fn from(info: SchemaInfo) -> Self {
+        proto::SchemaInfo {
+            id: info.id.to_string(),
+            name: info.name.to_string(),
+            developer: info.developer.to_string(),
+            created_at: info.created_at.timestamp(),
+            implements: info
+                .implements
+                .into_iter()
+                .map(proto::ImplInfo::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<ContractInfo> for proto::ContractInfo {
+    fn from(info: ContractInfo) -> Self {
+        proto::ContractInfo {
+            id: info.id.to_string(),
+            schema_id: info.schema_id.to_string(),
+            issuer: info.issuer.to_string(),
+            issued_at: info.issued_at.timestamp(),
+            testnet: info.testnet,
+            alt_layers1: info.alt_layers1.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+impl From<HistoryEntry> for proto::HistoryEntry {
+    fn from(entry: HistoryEntry) -> Self {
+        proto::HistoryEntry {
+            opout: entry.opout.to_string(),
+            seal: entry.seal.to_string(),
+            amount: entry.amount.value(),
+            witness: entry.witness.map(|id| id.to_string()),
+        }
+    }
+}
+
+impl From<LabelRef> for proto::LabelRef {
+    fn from(label_ref: LabelRef) -> Self {
+        let target = match label_ref {
+            LabelRef::Contract(id) => label_ref::Target::Contract(id.to_string()),
+            LabelRef::Transfer(id) => label_ref::Target::Transfer(id.to_string()),
+            LabelRef::Allocation(opout) => label_ref::Target::Allocation(opout.to_string()),
+        };
+        proto::LabelRef {
+            target: Some(target),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::Future;
+
+    use rgb::ContractId;
+    use strict_encoding::StrictDumb;
+    use tokio_stream::StreamExt;
+    use tonic::Code;
+
+    use super::*;
+    use crate::containers::{ConsignmentExt, Contract};
+    use crate::interface::resolver::DumbResolver;
+    use crate::persistence::Stock;
+    use crate::service::{AccessControl, Permission};
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn service() -> Service<DumbResolver> { Service::new(Stock::in_memory(), DumbResolver, true) }
+
+    #[test]
+    fn grpc() {
+        let server = GrpcServer::new(service());
+        let contract_id = ContractId::strict_dumb();
+        let label_ref = proto::LabelRef::from(LabelRef::Contract(contract_id));
+        block_on(async {
+            let reply = server
+                .list_contracts(tonic::Request::new(proto::Empty {}))
+                .await
+                .unwrap();
+            assert!(reply.into_inner().contracts.is_empty());
+
+            let request = proto::ContractRequest {
+                contract_id: s!("invalid"),
+            };
+            let err = server
+                .get_contract_info(tonic::Request::new(request))
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), Code::InvalidArgument);
+            let request = proto::ContractRequest {
+                contract_id: contract_id.to_string(),
+            };
+            let err = server
+                .get_contract_info(tonic::Request::new(request))
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), Code::Internal);
+
+            let request = proto::SetLabelRequest {
+                label_ref: Some(label_ref.clone()),
+                label: Some(s!("savings")),
+            };
+            let reply = server
+                .set_label(tonic::Request::new(request))
+                .await
+                .unwrap();
+            assert!(reply.into_inner().new);
+            let reply = server
+                .list_labels(tonic::Request::new(proto::Empty {}))
+                .await
+                .unwrap();
+            assert_eq!(reply.into_inner().labels, vec![proto::Label {
+                label_ref: Some(label_ref),
+                label: s!("savings"),
+            }]);
+        });
+    }
+
+    #[test]
+    fn import_stream() {
+        let server = GrpcServer::new(service());
+        let contract = Contract::strict_dumb();
+        let data = contract.to_strict_serialized::<U32MAX>().unwrap().release();
+        block_on(async {
+            let stream = server
+                .import_contract(tonic::Request::new(proto::Consignment { data }))
+                .await
+                .unwrap()
+                .into_inner();
+            let events = stream
+                .map(|event| event.unwrap().event.unwrap())
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(events.len(), 2);
+            assert_eq!(
+                events[0],
+                import_event::Event::Progress(proto::Progress {
+                    stage: Some(progress::Stage::Validating(contract.contract_id().to_string())),
+                })
+            );
+            assert!(matches!(&events[1], import_event::Event::Result(reply) if !reply.imported));
+
+            let request = proto::Consignment { data: vec![0xFF] };
+            let err = server
+                .import_contract(tonic::Request::new(request))
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), Code::InvalidArgument);
+        });
+    }
+
+    #[test]
+    fn authentication() {
+        let mut acl = AccessControl::new();
+        acl.grant("auditor", Permission::View, None);
+        let server =
+            GrpcServer::new(service().with_access_control(acl)).with_authentication(|metadata| {
+                match metadata.get("authorization").map(|token| token.to_str()) {
+                    Some(Ok("Bearer auditor")) => Ok(s!("auditor")),
+                    Some(Ok("Bearer cashier")) => Ok(s!("cashier")),
+                    _ => Err(Status::unauthenticated("invalid token")),
+                }
+            });
+        let request = |token: Option<&str>| {
+            let mut request = tonic::Request::new(proto::Empty {});
+            if let Some(token) = token {
+                request
+                    .metadata_mut()
+                    .insert("authorization", token.parse().unwrap());
+            }
+            request
+        };
+        block_on(async {
+            assert!(
+                server
+                    .list_labels(request(Some("Bearer auditor")))
+                    .await
+                    .is_ok()
+            );
+            let err = server.list_labels(request(None)).await.unwrap_err();
+            assert_eq!(err.code(), Code::Unauthenticated);
+            let err = server
+                .list_labels(request(Some("Bearer cashier")))
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), Code::PermissionDenied);
+        });
+    }
+}
@@ -25,7 +25,7 @@ use std::collections::{BTreeMap, HashSet};
 
 use amplify::confinement::{Confined, SmallOrdSet, TinyOrdMap, U16};
 use amplify::{confinement, Wrapper};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use invoice::{Allocation, Amount};
 use rgb::validation::Scripts;
 use rgb::{
@@ -36,13 +36,17 @@ use rgb::{
     TransitionType, TypedAssigns, XChain, XOutpoint,
 };
 use rgbcore::{GlobalStateSchema, GlobalStateType, MetaType, Metadata, ValencyType};
-use strict_encoding::{FieldName, SerializeError, StrictSerialize};
+use strict_encoding::{FieldName, SerializeError, StrictDeserialize, StrictSerialize};
 use strict_types::{decode, SemId, TypeSystem};
 
 use crate::containers::{BuilderSeal, ContainerVer, Contract, ValidConsignment};
 use crate::interface::resolver::DumbResolver;
-use crate::interface::{Iface, IfaceImpl, TransitionIface};
+use crate::interface::{
+    Iface, IfaceImpl, PreflightCheck, PreflightReport, PreflightStatus, TransitionIface,
+    TEMPLATE_ASSIGNMENT_OWNER, TEMPLATE_GLOBAL_ISSUED_SUPPLY, TEMPLATE_GLOBAL_TERMS,
+};
 use crate::persistence::PersistedState;
+use crate::stl::ContractTerms;
 use crate::Outpoint;
 
 #[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
@@ -85,6 +89,10 @@ pub enum BuilderError {
     /// tag defined by the contract.
     AssetTagInvalid(AssignmentType),
 
+    /// asset tag for state `{0}` can't be rotated after fungible state of the
+    /// same type was added to the operation.
+    AssetTagRotation(AssignmentType),
+
     /// interface doesn't specifies default operation name, thus an explicit
     /// operation type must be provided with `set_operation_type` method.
     NoOperationSubtype,
@@ -362,6 +370,185 @@ impl ContractBuilder {
         Ok(self)
     }
 
+    /// Performs a dry run of the contract issuance, checking the genesis
+    /// against common issuer mistakes. The returned checklist must be free of
+    /// failures for the contract to be issued as intended.
+    pub fn preflight(&self) -> PreflightReport {
+        let builder = &self.builder;
+        let mut report = PreflightReport::default();
+
+        let mut totals = BTreeMap::new();
+        for (type_id, values) in &builder.fungible {
+            let name = builder.iimpl.assignment_name(*type_id);
+            let name = name
+                .map(FieldName::to_string)
+                .unwrap_or_else(|| type_id.to_string());
+            let total = values
+                .values()
+                .try_fold(0u64, |sum, value| sum.checked_add(value.value.as_u64()));
+            match total {
+                Some(total) => {
+                    report.push(
+                        PreflightCheck::SupplyOverflow,
+                        PreflightStatus::Passed,
+                        format!("total of `{name}` is {total}"),
+                    );
+                    totals.insert(*type_id, Amount::from(total));
+                }
+                None => report.push(
+                    PreflightCheck::SupplyOverflow,
+                    PreflightStatus::Failed,
+                    format!("total of `{name}` exceeds the maximal amount"),
+                ),
+            }
+        }
+
+        let supply_type = builder.global_type(&fname!(TEMPLATE_GLOBAL_ISSUED_SUPPLY));
+        let owner_type = builder.assignments_type(&fname!(TEMPLATE_ASSIGNMENT_OWNER));
+        if let Some((supply_type, owner_type)) = supply_type.zip(owner_type) {
+            let declared = builder
+                .global
+                .get(&supply_type)
+                .into_iter()
+                .flat_map(|values| values.iter())
+                .map(|data| Amount::from_strict_serialized::<U16>(data.as_inner().clone()))
+                .try_fold(Amount::ZERO, |sum, amount| sum.checked_add(amount.ok()?));
+            let allocated = totals.get(&owner_type).copied().unwrap_or_default();
+            match declared {
+                Some(declared) if declared == allocated => report.push(
+                    PreflightCheck::IssuedSupply,
+                    PreflightStatus::Passed,
+                    format!("allocations match the issued supply of {declared}"),
+                ),
+                Some(declared) => report.push(
+                    PreflightCheck::IssuedSupply,
+                    PreflightStatus::Failed,
+                    format!(
+                        "allocations sum up to {allocated} while the issued supply is {declared}"
+                    ),
+                ),
+                None => report.push(
+                    PreflightCheck::IssuedSupply,
+                    PreflightStatus::Failed,
+                    "issued supply can't be decoded or overflows",
+                ),
+            }
+        }
+
+        for (name, occurrences) in &builder.iface.genesis.globals {
+            let Some(type_id) = builder.global_type(name) else {
+                report.push(
+                    PreflightCheck::GlobalState,
+                    PreflightStatus::Failed,
+                    format!("`{name}` is not implemented by the interface implementation"),
+                );
+                continue;
+            };
+            let count = builder
+                .global
+                .get(&type_id)
+                .map(|values| values.len())
+                .unwrap_or(0);
+            match occurrences.check(count as u16) {
+                Ok(()) => report.push(
+                    PreflightCheck::GlobalState,
+                    PreflightStatus::Passed,
+                    format!("`{name}` is provided"),
+                ),
+                Err(err) if count == 0 => report.push(
+                    PreflightCheck::GlobalState,
+                    PreflightStatus::Failed,
+                    format!("required `{name}` is missing ({err})"),
+                ),
+                Err(err) => report.push(
+                    PreflightCheck::GlobalState,
+                    PreflightStatus::Failed,
+                    format!("`{name}`: {err}"),
+                ),
+            }
+        }
+
+        for (name, occurrences) in &builder.iface.genesis.assignments {
+            let Some(type_id) = builder.assignments_type(name) else {
+                report.push(
+                    PreflightCheck::IfaceConformance,
+                    PreflightStatus::Failed,
+                    format!("`{name}` is not implemented by the interface implementation"),
+                );
+                continue;
+            };
+            let count = builder
+                .rights
+                .get(&type_id)
+                .map(|set| set.len())
+                .unwrap_or(0)
+                + builder
+                    .fungible
+                    .get(&type_id)
+                    .map(|map| map.len())
+                    .unwrap_or(0)
+                + builder.data.get(&type_id).map(|map| map.len()).unwrap_or(0)
+                + builder
+                    .attachments
+                    .get(&type_id)
+                    .map(|map| map.len())
+                    .unwrap_or(0);
+            match occurrences.check(count as u16) {
+                Ok(()) => report.push(
+                    PreflightCheck::IfaceConformance,
+                    PreflightStatus::Passed,
+                    format!("`{name}` has {count} assignment(s)"),
+                ),
+                Err(err) => report.push(
+                    PreflightCheck::IfaceConformance,
+                    PreflightStatus::Failed,
+                    format!("`{name}`: {err}"),
+                ),
+            }
+        }
+
+        let media = builder
+            .global_type(&fname!(TEMPLATE_GLOBAL_TERMS))
+            .and_then(|type_id| builder.global.get(&type_id))
+            .into_iter()
+            .flat_map(|values| values.iter())
+            .filter_map(|data| {
+                ContractTerms::from_strict_serialized::<U16>(data.as_inner().clone()).ok()
+            })
+            .filter_map(|terms| terms.media)
+            .collect::<Vec<_>>();
+        for attachment in &media {
+            report.push(
+                PreflightCheck::Media,
+                PreflightStatus::Warning,
+                format!(
+                    "contract terms reference {} media {} which must be distributed separately",
+                    attachment.ty, attachment.digest
+                ),
+            );
+        }
+        let attachments = builder
+            .attachments
+            .values()
+            .map(|map| map.len())
+            .sum::<usize>();
+        if attachments > 0 {
+            report.push(
+                PreflightCheck::Media,
+                PreflightStatus::Warning,
+                format!(
+                    "{attachments} attachment(s) are assigned, which files must be distributed \
+                     separately"
+                ),
+            );
+        }
+        if media.is_empty() && attachments == 0 {
+            report.push(PreflightCheck::Media, PreflightStatus::Passed, "no media are referenced");
+        }
+
+        report
+    }
+
     pub fn issue_contract(self) -> Result<ValidConsignment<false>, BuilderError> {
         debug_assert!(
             !self.builder.deterministic,
@@ -405,7 +592,7 @@ impl ContractBuilder {
         let scripts = Confined::from_iter_checked(self.scripts.into_values());
 
         let contract = Contract {
-            version: ContainerVer::V2,
+            version: ContainerVer::V3,
             transfer: false,
             terminals: none!(),
             genesis,
@@ -420,6 +607,7 @@ impl ContractBuilder {
 
             supplements: none!(), // TODO: Add supplements
             signatures: none!(),  // TODO: Add signatures
+            records: none!(),
         };
 
         let valid_contract = contract
@@ -588,6 +776,33 @@ impl TransitionBuilder {
         Ok(self)
     }
 
+    /// Replaces asset tag of the fungible state with a newly derived one, such
+    /// that the state issued by the transition (like at secondary issuance)
+    /// can't be linked with the previously issued tranches by its asset tag.
+    ///
+    /// Must be called before any fungible state of the same type is added to
+    /// the transition.
+    pub fn rotate_asset_tag(self, name: impl Into<FieldName>) -> Result<Self, BuilderError> {
+        self.rotate_asset_tag_det(name, Utc::now(), rand::random())
+    }
+
+    /// Replaces asset tag of the fungible state with the one deterministically
+    /// derived from the contract id, timestamp and salt.
+    ///
+    /// See [`TransitionBuilder::rotate_asset_tag`] for details.
+    pub fn rotate_asset_tag_det(
+        mut self,
+        name: impl Into<FieldName>,
+        timestamp: DateTime<Utc>,
+        salt: u64,
+    ) -> Result<Self, BuilderError> {
+        let domain = self.contract_id.to_string();
+        self.builder = self
+            .builder
+            .rotate_asset_tag(name, domain, timestamp, salt)?;
+        Ok(self)
+    }
+
     #[inline]
     pub fn add_metadata(
         mut self,
@@ -982,6 +1197,26 @@ impl<Seal: ExposedSeal> OperationBuilder<Seal> {
         Ok(self)
     }
 
+    fn rotate_asset_tag(
+        mut self,
+        name: impl Into<FieldName>,
+        domain: impl AsRef<str>,
+        timestamp: DateTime<Utc>,
+        salt: u64,
+    ) -> Result<Self, BuilderError> {
+        let name = name.into();
+        let type_id = self
+            .assignments_type(&name)
+            .ok_or(BuilderError::AssignmentNotFound(name))?;
+        if self.fungible.contains_key(&type_id) {
+            return Err(BuilderError::AssetTagRotation(type_id));
+        }
+
+        let asset_tag = AssetTag::new_deterministic(domain, type_id, timestamp, salt);
+        self.asset_tags.insert(type_id, asset_tag)?;
+        Ok(self)
+    }
+
     pub fn init_asset_tag(&mut self, name: impl Into<FieldName>) -> Result<AssetTag, BuilderError> {
         let name = name.into();
         let type_id = self
@@ -22,18 +22,23 @@
 use std::borrow::Borrow;
 use std::collections::{BTreeSet, HashMap, HashSet};
 
+use amplify::confinement::{SmallBlob, U16};
 use invoice::{Allocation, Amount};
 use rgb::{
-    AssignmentType, AttachState, ContractId, DataState, OpId, RevealedAttach, RevealedData,
-    RevealedValue, Schema, VoidState, XOutpoint, XOutputSeal, XWitnessId,
+    AssignmentType, AttachState, ContractId, DataState, OpId, OwnedStateSchema, RevealedAttach,
+    RevealedData, RevealedValue, Schema, VoidState, XOutpoint, XOutputSeal, XWitnessId,
 };
-use strict_encoding::{FieldName, StrictDecode, StrictDumb, StrictEncode};
+use strict_encoding::{FieldName, StrictDecode, StrictDeserialize, StrictDumb, StrictEncode};
 use strict_types::{StrictVal, TypeSystem};
 
+use crate::containers::{
+    AmountDisplay, Capabilities, DefaultOps, DisplayRules, OpClass, CAPABILITY_CONFIDENTIAL_AMOUNTS,
+};
 use crate::contract::{KnownState, OutputAssignment, WitnessInfo};
 use crate::info::ContractInfo;
-use crate::interface::{AssignmentsFilter, IfaceImpl};
+use crate::interface::{AssignmentsFilter, IfaceImpl, TEMPLATE_GLOBAL_SPEC};
 use crate::persistence::ContractStateRead;
+use crate::stl::AssetSpec;
 use crate::LIB_NAME_RGB_STD;
 
 #[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
@@ -237,11 +242,71 @@ pub struct ContractIface<S: ContractStateRead> {
     pub iface: IfaceImpl,
     pub types: TypeSystem,
     pub info: ContractInfo,
+    /// Rules for displaying amounts of the contract fungible state.
+    pub display: DisplayRules,
+    /// Capabilities declared by the schema supplement.
+    pub schema_capabilities: Capabilities,
+    /// Operations implementing wallet actions, declared by the interface
+    /// implementation supplement.
+    pub default_ops: DefaultOps,
+}
+
+/// Formatting of amounts according to the contract display rules.
+pub trait AmountDisplayExt {
+    fn display_for<'c, S: ContractStateRead>(
+        &self,
+        contract: &'c ContractIface<S>,
+    ) -> AmountDisplay<'c>;
+}
+
+impl AmountDisplayExt for Amount {
+    fn display_for<'c, S: ContractStateRead>(
+        &self,
+        contract: &'c ContractIface<S>,
+    ) -> AmountDisplay<'c> {
+        contract.display.display(*self)
+    }
 }
 
 impl<S: ContractStateRead> ContractIface<S> {
     pub fn contract_id(&self) -> ContractId { self.state.contract_id() }
 
+    /// Returns asset specification from the [`TEMPLATE_GLOBAL_SPEC`] global
+    /// state, if the interface defines it with [`AssetSpec`] type.
+    pub fn asset_spec(&self) -> Option<AssetSpec> {
+        let type_id = self.iface.global_type(&fname!(TEMPLATE_GLOBAL_SPEC))?;
+        let data = self.state.global(type_id).ok()?.next()?;
+        let data = SmallBlob::try_from(data.borrow().as_slice().to_vec()).ok()?;
+        AssetSpec::from_strict_serialized::<U16>(data).ok()
+    }
+
+    /// Returns capabilities of the contract declared by its schema, which
+    /// always include [`CAPABILITY_CONFIDENTIAL_AMOUNTS`] when the schema
+    /// defines fungible state.
+    pub fn capabilities(&self) -> Capabilities {
+        let mut capabilities = self.schema_capabilities.clone();
+        if self
+            .schema
+            .owned_types
+            .values()
+            .any(|schema| matches!(schema, OwnedStateSchema::Fungible(_)))
+        {
+            // the set may be full only for a maliciously crafted supplement
+            capabilities
+                .push(fname!(CAPABILITY_CONFIDENTIAL_AMOUNTS))
+                .ok();
+        }
+        capabilities
+    }
+
+    /// Returns name of the operation implementing the wallet action of the
+    /// given class, if it is declared and implemented by the contract.
+    pub fn default_op(&self, class: OpClass) -> Option<&FieldName> {
+        self.default_ops.get(&class).filter(|name| {
+            self.iface.transition_type(name).is_some() || self.iface.extension_type(name).is_some()
+        })
+    }
+
     /// # Panics
     ///
     /// If data are corrupted and contract schema doesn't match interface
@@ -0,0 +1,437 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Documentation of interface operations.
+//!
+//! Interfaces don't carry documentation themselves, since any edit of a doc
+//! string would change the interface id. Instead, the docs are provided as
+//! [`OpDoc`] annotations of supplements: either the supplement of the
+//! interface, or the supplement of a specific contract, which allows issuers
+//! to describe what each operation does for their contract. The docs are
+//! rendered into [`IfaceDocs`], which can be presented as markdown or
+//! serialized into JSON.
+
+use std::fmt::{self, Display, Formatter};
+
+use amplify::confinement::{SmallString, TinyOrdMap};
+use rgb::{OpType, Occurrences};
+use strict_encoding::{
+    FieldName, SerializeError, StrictDeserialize, StrictSerialize, TypeName, VariantName,
+};
+
+use super::{ArgMap, Iface, OpName};
+use crate::containers::{SupplItem, SupplSub, Supplement, SUPPL_ANNOT_DOC};
+use crate::LIB_NAME_RGB_STD;
+
+/// Documentation of an interface operation provided by a supplement.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct OpDoc {
+    /// Description of what the operation does.
+    pub summary: SmallString,
+    /// Descriptions of the operation arguments.
+    pub args: TinyOrdMap<FieldName, SmallString>,
+}
+
+impl StrictSerialize for OpDoc {}
+impl StrictDeserialize for OpDoc {}
+
+impl OpDoc {
+    pub fn with(summary: &str) -> Self {
+        OpDoc {
+            summary: SmallString::from_checked(summary.to_owned()),
+            args: none!(),
+        }
+    }
+
+    /// Adds description of an argument, replacing the previous one.
+    pub fn describe_arg(&mut self, name: FieldName, doc: &str) {
+        self.args
+            .insert(name, SmallString::from_checked(doc.to_owned()))
+            .expect("too many arguments in the operation doc");
+    }
+}
+
+fn suppl_key(op: &OpName) -> (SupplSub, SupplItem) {
+    match op {
+        OpName::Genesis => (SupplSub::Genesis, SupplItem::Default),
+        OpName::Transition(name) => (SupplSub::Transition, SupplItem::FieldName(name.clone())),
+        OpName::Extension(name) => (SupplSub::Extension, SupplItem::FieldName(name.clone())),
+    }
+}
+
+fn arg_items(args: &ArgMap) -> impl Iterator<Item = (&FieldName, Occurrences)> {
+    args.iter().map(|(name, occ)| (name, occ.clone()))
+}
+
+impl Supplement {
+    /// Returns documentation for the operation, if provided by the
+    /// supplement.
+    pub fn op_doc(&self, op: &OpName) -> Option<OpDoc> {
+        let (sub, item) = suppl_key(op);
+        self.get(sub, item, SUPPL_ANNOT_DOC)
+            .transpose()
+            .ok()
+            .flatten()
+    }
+
+    /// Adds documentation for the operation, returning whether it has
+    /// replaced a previous one.
+    pub fn set_op_doc(&mut self, op: &OpName, doc: &OpDoc) -> Result<bool, SerializeError> {
+        let (sub, item) = suppl_key(op);
+        self.annotate(sub, item, SUPPL_ANNOT_DOC, doc)
+    }
+}
+
+/// Role of an argument in an operation.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+#[display(lowercase)]
+pub enum ArgRole {
+    Metadata,
+    Global,
+    Input,
+    Assignment,
+    Redeems,
+    Valency,
+}
+
+/// Rendered documentation of an operation argument.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ArgDocs {
+    pub name: FieldName,
+    pub role: ArgRole,
+    /// Minimal number of values which must be provided for the argument.
+    pub min: u16,
+    /// Maximal number of values which can be provided for the argument.
+    pub max: u16,
+    pub doc: Option<String>,
+}
+
+/// Rendered documentation of an error which may be returned by an operation.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ErrorDocs {
+    pub name: VariantName,
+    pub message: Option<String>,
+}
+
+/// Rendered documentation of an operation.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct OpDocs {
+    pub op_type: OpType,
+    /// Operation name; `None` for the genesis.
+    pub name: Option<FieldName>,
+    /// Whether the operation is the default operation of the interface.
+    pub is_default: bool,
+    pub summary: Option<String>,
+    pub args: Vec<ArgDocs>,
+    pub errors: Vec<ErrorDocs>,
+}
+
+impl OpDocs {
+    pub fn op_name(&self) -> OpName {
+        match (self.op_type, &self.name) {
+            (OpType::StateTransition, Some(name)) => OpName::Transition(name.clone()),
+            (OpType::StateExtension, Some(name)) => OpName::Extension(name.clone()),
+            _ => OpName::Genesis,
+        }
+    }
+}
+
+/// Rendered documentation of all interface operations.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct IfaceDocs {
+    pub iface: TypeName,
+    pub operations: Vec<OpDocs>,
+}
+
+impl IfaceDocs {
+    /// Returns documentation for a specific operation.
+    pub fn operation(&self, op: &OpName) -> Option<&OpDocs> {
+        self.operations.iter().find(|docs| &docs.op_name() == op)
+    }
+
+    /// Renders documentation as markdown.
+    pub fn to_markdown(&self) -> String { self.to_string() }
+}
+
+impl Display for IfaceDocs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# {}", self.iface)?;
+        for op in &self.operations {
+            writeln!(f)?;
+            match &op.name {
+                Some(name) => write!(f, "## {} `{name}`", op.op_type)?,
+                None => write!(f, "## {}", op.op_type)?,
+            }
+            if op.is_default {
+                f.write_str(" (default)")?;
+            }
+            writeln!(f)?;
+            if let Some(summary) = &op.summary {
+                writeln!(f, "\n{summary}")?;
+            }
+            if !op.args.is_empty() {
+                writeln!(f, "\n| Argument | Role | Occurrences | Description |")?;
+                writeln!(f, "|---|---|---|---|")?;
+                for arg in &op.args {
+                    let max = if arg.max == u16::MAX { s!("*") } else { arg.max.to_string() };
+                    writeln!(
+                        f,
+                        "| `{}` | {} | {}..{max} | {} |",
+                        arg.name,
+                        arg.role,
+                        arg.min,
+                        arg.doc.as_deref().unwrap_or_default()
+                    )?;
+                }
+            }
+            if !op.errors.is_empty() {
+                writeln!(f, "\nErrors:")?;
+                for err in &op.errors {
+                    match &err.message {
+                        Some(message) => writeln!(f, "- `{}`: {message}", err.name)?,
+                        None => writeln!(f, "- `{}`", err.name)?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iface {
+    /// Renders documentation of all interface operations using docs provided
+    /// by the supplements.
+    ///
+    /// Supplements are applied in order, such that docs from later ones take
+    /// precedence; thus an interface supplement should go first, followed by
+    /// the supplement of a specific contract.
+    pub fn render_docs<'a>(&self, suppls: impl IntoIterator<Item = &'a Supplement>) -> IfaceDocs {
+        let mut ops = vec![OpName::Genesis];
+        ops.extend(self.transitions.keys().cloned().map(OpName::Transition));
+        ops.extend(self.extensions.keys().cloned().map(OpName::Extension));
+
+        let suppls = suppls.into_iter().collect::<Vec<_>>();
+        let operations = ops
+            .into_iter()
+            .map(|op| {
+                let mut summary = None;
+                let mut arg_docs = TinyOrdMap::<FieldName, SmallString>::new();
+                for doc in suppls.iter().filter_map(|suppl| suppl.op_doc(&op)) {
+                    if !doc.summary.is_empty() {
+                        summary = Some(doc.summary.to_string());
+                    }
+                    arg_docs.extend(doc.args).expect("same size confinement");
+                }
+                self.op_docs(op, summary, &arg_docs)
+            })
+            .collect();
+
+        IfaceDocs {
+            iface: self.name.clone(),
+            operations,
+        }
+    }
+
+    fn op_docs(
+        &self,
+        op: OpName,
+        summary: Option<String>,
+        arg_docs: &TinyOrdMap<FieldName, SmallString>,
+    ) -> OpDocs {
+        let mut args = vec![];
+        let mut add =
+            |role: ArgRole, items: &mut dyn Iterator<Item = (&FieldName, Occurrences)>| {
+                args.extend(items.map(|(name, occ)| ArgDocs {
+                    name: name.clone(),
+                    role,
+                    min: occ.min_value(),
+                    max: occ.max_value(),
+                    doc: arg_docs.get(name).map(SmallString::to_string),
+                }))
+            };
+        let once = |name| (name, Occurrences::Once);
+        let (op_type, name, is_default, errors) = match &op {
+            OpName::Genesis => {
+                let g = &self.genesis;
+                add(ArgRole::Metadata, &mut g.metadata.iter().map(once));
+                add(ArgRole::Global, &mut arg_items(&g.globals));
+                add(ArgRole::Assignment, &mut arg_items(&g.assignments));
+                add(ArgRole::Valency, &mut g.valencies.iter().map(once));
+                (OpType::Genesis, None, false, &g.errors)
+            }
+            OpName::Transition(name) => {
+                let t = &self.transitions[name];
+                add(ArgRole::Metadata, &mut t.metadata.iter().map(once));
+                add(ArgRole::Global, &mut arg_items(&t.globals));
+                add(ArgRole::Input, &mut arg_items(&t.inputs));
+                add(ArgRole::Assignment, &mut arg_items(&t.assignments));
+                add(ArgRole::Valency, &mut t.valencies.iter().map(once));
+                let is_default = self.default_operation.as_ref() == Some(name);
+                (OpType::StateTransition, Some(name.clone()), is_default, &t.errors)
+            }
+            OpName::Extension(name) => {
+                let e = &self.extensions[name];
+                add(ArgRole::Metadata, &mut e.metadata.iter().map(once));
+                add(ArgRole::Global, &mut arg_items(&e.globals));
+                add(ArgRole::Redeems, &mut e.redeems.iter().map(once));
+                add(ArgRole::Assignment, &mut arg_items(&e.assignments));
+                add(ArgRole::Valency, &mut e.valencies.iter().map(once));
+                (OpType::StateExtension, Some(name.clone()), false, &e.errors)
+            }
+        };
+        let errors = errors
+            .iter()
+            .map(|name| ErrorDocs {
+                name: name.clone(),
+                message: self.errors.get(name).map(|msg| msg.to_string()),
+            })
+            .collect();
+
+        OpDocs {
+            op_type,
+            name,
+            is_default,
+            summary,
+            args,
+            errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rgb::{ContractId, Identity};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::interface::{
+        AssignIface, GenesisIface, Modifier, OwnedIface, Req, TransitionIface, VerNo,
+    };
+
+    fn iface() -> Iface {
+        Iface {
+            version: VerNo::V1,
+            name: tn!("Test"),
+            inherits: none!(),
+            timestamp: 1711405444,
+            metadata: none!(),
+            global_state: none!(),
+            assignments: tiny_bmap! {
+                fname!("assetOwner") => AssignIface::private(OwnedIface::Amount, Req::NoneOrMore),
+            },
+            valencies: none!(),
+            genesis: GenesisIface {
+                modifier: Modifier::Final,
+                metadata: none!(),
+                globals: none!(),
+                assignments: tiny_bmap! { fname!("assetOwner") => Occurrences::NoneOrMore },
+                valencies: none!(),
+                errors: none!(),
+            },
+            transitions: tiny_bmap! {
+                fname!("transfer") => TransitionIface {
+                    modifier: Modifier::Final,
+                    optional: false,
+                    metadata: none!(),
+                    globals: none!(),
+                    inputs: tiny_bmap! { fname!("assetOwner") => Occurrences::OnceOrMore },
+                    assignments: tiny_bmap! { fname!("assetOwner") => Occurrences::OnceOrMore },
+                    valencies: none!(),
+                    errors: tiny_bset![vname!("nonEqualAmounts")],
+                    default_assignment: Some(fname!("assetOwner")),
+                },
+            },
+            extensions: none!(),
+            default_operation: Some(fname!("transfer")),
+            errors: tiny_bmap! {
+                vname!("nonEqualAmounts") => tiny_s!("input and output amounts differ"),
+            },
+            developer: Identity::default(),
+        }
+    }
+
+    #[test]
+    fn render_docs() {
+        let iface = iface();
+        let transfer = OpName::Transition(fname!("transfer"));
+
+        let mut iface_suppl = Supplement::new(iface.iface_id(), "ssi:developer");
+        let mut doc = OpDoc::with("Transfers tokens");
+        doc.describe_arg(fname!("assetOwner"), "tokens being transferred");
+        assert!(!iface_suppl.set_op_doc(&transfer, &doc).unwrap());
+        assert_eq!(iface_suppl.op_doc(&transfer), Some(doc));
+        assert_eq!(iface_suppl.op_doc(&OpName::Genesis), None);
+
+        let mut contract_suppl = Supplement::new(ContractId::strict_dumb(), "ssi:issuer");
+        contract_suppl
+            .set_op_doc(&transfer, &OpDoc::with("Transfers shares of the company"))
+            .unwrap();
+
+        let docs = iface.render_docs([&iface_suppl, &contract_suppl]);
+        assert_eq!(docs.operations.len(), 2);
+        let op = docs.operation(&transfer).unwrap();
+        assert!(op.is_default);
+        assert_eq!(op.summary.as_deref(), Some("Transfers shares of the company"));
+        assert_eq!(op.args.len(), 2);
+        assert_eq!(op.args[0].role, ArgRole::Input);
+        assert_eq!(op.args[0].doc.as_deref(), Some("tokens being transferred"));
+        assert_eq!(op.errors[0].message.as_deref(), Some("input and output amounts differ"));
+        assert_eq!(docs.operation(&OpName::Genesis).unwrap().summary, None);
+
+        let md = docs.to_markdown();
+        assert!(md.starts_with("# Test\n"));
+        assert!(md.contains("## transition `transfer` (default)\n"));
+        assert!(md.contains("| `assetOwner` | input | 1..* | tokens being transferred |\n"));
+        assert!(md.contains("- `nonEqualAmounts`: input and output amounts differ\n"));
+    }
+}
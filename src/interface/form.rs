@@ -0,0 +1,632 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Form descriptors for contract operations.
+//!
+//! Using the strict types referenced by an interface, the module produces a
+//! JSON-schema-like description of the arguments required by a contract
+//! operation. Wallets can use such description to render input forms for any
+//! operation of any interface without hardcoding per-asset user screens.
+
+use std::fmt::{self, Display, Formatter};
+
+use rgb::Occurrences;
+use strict_encoding::{FieldName, NumCls, Primitive, VariantName};
+use strict_types::{SemId, SymbolicSys, Ty};
+
+use super::{ArgMap, Iface, OpName, OwnedIface};
+
+/// Errors happening during construction of an operation form.
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum FormError {
+    /// {0} is not defined by the interface.
+    UnknownOperation(OpName),
+
+    /// {0} references field '{1}' which is not defined by the interface.
+    UnknownField(OpName, FieldName),
+
+    /// type {0} is absent from the provided type system.
+    UnknownType(SemId),
+}
+
+/// Argument value type as it must be represented in a form.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase", tag = "type")
+)]
+pub enum FormType {
+    /// Argument has no value (unit type or declarative rights).
+    Unit,
+
+    /// Any value is accepted: the interface doesn't restrict the type.
+    Any,
+
+    /// Fungible amount.
+    Amount,
+
+    /// Attachment to the contract: file hash and media type.
+    Attachment,
+
+    /// Integer number.
+    Integer {
+        signed: bool,
+        non_zero: bool,
+        bits: u16,
+    },
+
+    /// Floating-point number.
+    Float { bits: u16 },
+
+    /// Unicode or ASCII text with length constraints (in characters).
+    Text { min_len: u64, max_len: u64 },
+
+    /// Binary data with length constraints (in bytes).
+    Bytes { min_len: u64, max_len: u64 },
+
+    /// One of the listed variants without associated data.
+    Enum { variants: Vec<VariantName> },
+
+    /// One of the listed variants, each with associated data.
+    Union { variants: Vec<FormVariant> },
+
+    /// Value which may be omitted.
+    Optional { inner: Box<FormType> },
+
+    /// Ordered list of unnamed values.
+    Tuple { items: Vec<FormType> },
+
+    /// Structure with named fields.
+    Struct { fields: Vec<FormField> },
+
+    /// Fixed-size array of values.
+    Array { item: Box<FormType>, len: u16 },
+
+    /// Variable-size list (`unique = false`) or set (`unique = true`) of values.
+    List {
+        item: Box<FormType>,
+        unique: bool,
+        min_len: u64,
+        max_len: u64,
+    },
+
+    /// Key-value map.
+    Map {
+        key: Box<FormType>,
+        value: Box<FormType>,
+        min_len: u64,
+        max_len: u64,
+    },
+}
+
+/// A named field of a structure type.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct FormField {
+    pub name: FieldName,
+    pub ty: FormType,
+}
+
+/// A variant of a union type.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct FormVariant {
+    pub name: VariantName,
+    pub tag: u8,
+    pub ty: FormType,
+}
+
+/// Operation argument described by the form.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct FormArg {
+    pub name: FieldName,
+    /// Fully qualified name of the argument type, if known to the type
+    /// system.
+    pub type_name: Option<String>,
+    /// Minimal number of values which must be provided for the argument.
+    pub min: u16,
+    /// Maximal number of values which can be provided for the argument.
+    pub max: u16,
+    pub ty: FormType,
+}
+
+impl FormArg {
+    pub fn is_required(&self) -> bool { self.min > 0 }
+    pub fn is_multiple(&self) -> bool { self.max > 1 }
+}
+
+/// Description of all arguments of a contract operation, which is sufficient
+/// for rendering an input form for it.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct OpForm {
+    /// Operation name; `None` for the genesis.
+    pub name: Option<FieldName>,
+    pub metadata: Vec<FormArg>,
+    pub globals: Vec<FormArg>,
+    pub assignments: Vec<FormArg>,
+    /// Assignment which should be used by default for the operation.
+    pub default_assignment: Option<FieldName>,
+}
+
+impl Display for FormType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FormType::Unit => f.write_str("()"),
+            FormType::Any => f.write_str("Any"),
+            FormType::Amount => f.write_str("Amount"),
+            FormType::Attachment => f.write_str("Attachment"),
+            FormType::Integer {
+                signed,
+                non_zero,
+                bits,
+            } => {
+                let cls = match (signed, non_zero) {
+                    (_, true) => 'N',
+                    (true, false) => 'I',
+                    (false, false) => 'U',
+                };
+                write!(f, "{cls}{bits}")
+            }
+            FormType::Float { bits } => write!(f, "F{bits}"),
+            FormType::Text { min_len, max_len } => write!(f, "Text({min_len}..{max_len})"),
+            FormType::Bytes { min_len, max_len } if min_len == max_len => {
+                write!(f, "Bytes({min_len})")
+            }
+            FormType::Bytes { min_len, max_len } => write!(f, "Bytes({min_len}..{max_len})"),
+            FormType::Enum { variants } => {
+                let variants = variants
+                    .iter()
+                    .map(VariantName::to_string)
+                    .collect::<Vec<_>>();
+                write!(f, "{}", variants.join(" | "))
+            }
+            FormType::Union { variants } => {
+                for (i, v) in variants.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "{}({})", v.name, v.ty)?;
+                }
+                Ok(())
+            }
+            FormType::Optional { inner } => write!(f, "{inner}?"),
+            FormType::Tuple { items } => {
+                f.write_str("(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str(")")
+            }
+            FormType::Struct { fields } => {
+                f.write_str("{")?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", field.name, field.ty)?;
+                }
+                f.write_str("}")
+            }
+            FormType::Array { item, len } => write!(f, "[{item}; {len}]"),
+            FormType::List {
+                item,
+                unique,
+                min_len,
+                max_len,
+            } => {
+                let (open, close) = if *unique { ("{", "}") } else { ("[", "]") };
+                write!(f, "{open}{item} ^ {min_len}..{max_len}{close}")
+            }
+            FormType::Map {
+                key,
+                value,
+                min_len,
+                max_len,
+            } => write!(f, "{{{key} -> {value} ^ {min_len}..{max_len}}}"),
+        }
+    }
+}
+
+impl FormType {
+    /// Constructs form type description for a strict type with a given
+    /// semantic id, as it is defined in the type system.
+    pub fn with(sem_id: SemId, sys: &SymbolicSys) -> Result<Self, FormError> {
+        let ty = sys.get(sem_id).ok_or(FormError::UnknownType(sem_id))?;
+        Ok(match ty {
+            Ty::Primitive(prim) => Self::primitive(*prim),
+            Ty::UnicodeChar => FormType::Text {
+                min_len: 1,
+                max_len: 1,
+            },
+            Ty::Enum(variants) => FormType::Enum {
+                variants: variants.iter().map(|v| v.name.clone()).collect(),
+            },
+            Ty::Union(_) if ty.as_some().is_some() => {
+                let inner = *ty.as_some().expect("checked above");
+                FormType::Optional {
+                    inner: Box::new(Self::with(inner, sys)?),
+                }
+            }
+            Ty::Union(variants) => FormType::Union {
+                variants: variants
+                    .iter()
+                    .map(|(v, id)| {
+                        Ok(FormVariant {
+                            name: v.name.clone(),
+                            tag: v.tag,
+                            ty: Self::with(*id, sys)?,
+                        })
+                    })
+                    .collect::<Result<_, FormError>>()?,
+            },
+            // Newtypes are transparent for the forms
+            Ty::Tuple(fields) if fields.len() == 1 => {
+                Self::with(*fields.ty_by_pos(0).expect("single item"), sys)?
+            }
+            Ty::Tuple(fields) => FormType::Tuple {
+                items: fields
+                    .iter()
+                    .map(|id| Self::with(*id, sys))
+                    .collect::<Result<_, _>>()?,
+            },
+            Ty::Struct(fields) => FormType::Struct {
+                fields: fields
+                    .iter()
+                    .map(|field| {
+                        Ok(FormField {
+                            name: field.name.clone(),
+                            ty: Self::with(field.ty, sys)?,
+                        })
+                    })
+                    .collect::<Result<_, FormError>>()?,
+            },
+            Ty::Array(id, len) if Self::is_byte(*id, sys) => FormType::Bytes {
+                min_len: *len as u64,
+                max_len: *len as u64,
+            },
+            Ty::Array(id, len) => FormType::Array {
+                item: Box::new(Self::with(*id, sys)?),
+                len: *len,
+            },
+            Ty::List(id, sizing) if Self::is_byte(*id, sys) => FormType::Bytes {
+                min_len: sizing.min,
+                max_len: sizing.max,
+            },
+            Ty::List(id, sizing) if Self::is_char(*id, sys) => FormType::Text {
+                min_len: sizing.min,
+                max_len: sizing.max,
+            },
+            Ty::List(id, sizing) | Ty::Set(id, sizing) => FormType::List {
+                item: Box::new(Self::with(*id, sys)?),
+                unique: matches!(ty, Ty::Set(..)),
+                min_len: sizing.min,
+                max_len: sizing.max,
+            },
+            Ty::Map(key, value, sizing) => FormType::Map {
+                key: Box::new(Self::with(*key, sys)?),
+                value: Box::new(Self::with(*value, sys)?),
+                min_len: sizing.min,
+                max_len: sizing.max,
+            },
+        })
+    }
+
+    fn primitive(prim: Primitive) -> Self {
+        if prim == Primitive::UNIT {
+            return FormType::Unit;
+        }
+        if prim == Primitive::BYTE {
+            return FormType::Bytes {
+                min_len: 1,
+                max_len: 1,
+            };
+        }
+        let info = prim.info();
+        let bits = info.byte_size() * 8;
+        match info.ty {
+            NumCls::Float => FormType::Float { bits },
+            NumCls::Unsigned => FormType::Integer {
+                signed: false,
+                non_zero: false,
+                bits,
+            },
+            NumCls::Signed => FormType::Integer {
+                signed: true,
+                non_zero: false,
+                bits,
+            },
+            NumCls::NonZero => FormType::Integer {
+                signed: false,
+                non_zero: true,
+                bits,
+            },
+        }
+    }
+
+    fn is_byte(sem_id: SemId, sys: &SymbolicSys) -> bool {
+        sys.get(sem_id).map(Ty::is_byte).unwrap_or_default()
+    }
+
+    fn is_char(sem_id: SemId, sys: &SymbolicSys) -> bool {
+        sys.get(sem_id)
+            .map(|ty| ty.is_unicode_char() || ty.is_char_enum())
+            .unwrap_or_default()
+    }
+}
+
+impl FormArg {
+    fn with(
+        name: FieldName,
+        occ: &Occurrences,
+        sem_id: Option<SemId>,
+        ty: FormType,
+        sys: &SymbolicSys,
+    ) -> Self {
+        FormArg {
+            name,
+            type_name: sem_id
+                .and_then(|id| sys.lookup(id))
+                .map(|fqn| fqn.to_string()),
+            min: occ.min_value(),
+            max: occ.max_value(),
+            ty,
+        }
+    }
+}
+
+impl Iface {
+    /// Constructs description of the arguments for a given operation, which
+    /// can be used to render an input form for it.
+    ///
+    /// The provided type system must contain all types referenced by the
+    /// interface.
+    pub fn op_form(&self, op: &OpName, sys: &SymbolicSys) -> Result<OpForm, FormError> {
+        let (name, metadata, globals, assignments, default_assignment) = match op {
+            OpName::Genesis => (
+                None,
+                &self.genesis.metadata,
+                &self.genesis.globals,
+                &self.genesis.assignments,
+                None,
+            ),
+            OpName::Transition(name) => {
+                let t = self
+                    .transitions
+                    .get(name)
+                    .ok_or_else(|| FormError::UnknownOperation(op.clone()))?;
+                (Some(name), &t.metadata, &t.globals, &t.assignments, t.default_assignment.as_ref())
+            }
+            OpName::Extension(name) => {
+                let e = self
+                    .extensions
+                    .get(name)
+                    .ok_or_else(|| FormError::UnknownOperation(op.clone()))?;
+                (Some(name), &e.metadata, &e.globals, &e.assignments, e.default_assignment.as_ref())
+            }
+        };
+
+        let metadata = metadata
+            .iter()
+            .map(|name| {
+                let sem_id = *self
+                    .metadata
+                    .get(name)
+                    .ok_or_else(|| FormError::UnknownField(op.clone(), name.clone()))?;
+                let ty = FormType::with(sem_id, sys)?;
+                Ok(FormArg::with(name.clone(), &Occurrences::Once, Some(sem_id), ty, sys))
+            })
+            .collect::<Result<_, FormError>>()?;
+
+        let globals = self.form_globals(op, globals, sys)?;
+        let assignments = self.form_assignments(op, assignments, sys)?;
+
+        Ok(OpForm {
+            name: name.cloned(),
+            metadata,
+            globals,
+            assignments,
+            default_assignment: default_assignment.cloned(),
+        })
+    }
+
+    fn form_globals(
+        &self,
+        op: &OpName,
+        args: &ArgMap,
+        sys: &SymbolicSys,
+    ) -> Result<Vec<FormArg>, FormError> {
+        args.iter()
+            .map(|(name, occ)| {
+                let sem_id = self
+                    .global_state
+                    .get(name)
+                    .ok_or_else(|| FormError::UnknownField(op.clone(), name.clone()))?
+                    .sem_id;
+                let ty = match sem_id {
+                    Some(id) => FormType::with(id, sys)?,
+                    None => FormType::Any,
+                };
+                Ok(FormArg::with(name.clone(), occ, sem_id, ty, sys))
+            })
+            .collect()
+    }
+
+    fn form_assignments(
+        &self,
+        op: &OpName,
+        args: &ArgMap,
+        sys: &SymbolicSys,
+    ) -> Result<Vec<FormArg>, FormError> {
+        args.iter()
+            .map(|(name, occ)| {
+                let owned_state = self
+                    .assignments
+                    .get(name)
+                    .ok_or_else(|| FormError::UnknownField(op.clone(), name.clone()))?
+                    .owned_state;
+                let ty = match owned_state {
+                    OwnedIface::Any | OwnedIface::AnyData => FormType::Any,
+                    OwnedIface::Rights => FormType::Unit,
+                    OwnedIface::Amount => FormType::Amount,
+                    OwnedIface::AnyAttach => FormType::Attachment,
+                    OwnedIface::Data(id) => FormType::with(id, sys)?,
+                };
+                Ok(FormArg::with(name.clone(), occ, owned_state.sem_id(), ty, sys))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strict_types::stl::std_stl;
+    use strict_types::typesys::SystemBuilder;
+
+    use super::*;
+    use crate::interface::{
+        AssignIface, GenesisIface, GlobalIface, Modifier, Req, TransitionIface, VerNo,
+    };
+    use crate::stl::{bp_tx_stl, rgb_contract_stl};
+
+    fn sys() -> SymbolicSys {
+        SystemBuilder::new()
+            .import(std_stl())
+            .unwrap()
+            .import(bp_tx_stl())
+            .unwrap()
+            .import(rgb_contract_stl())
+            .unwrap()
+            .finalize()
+            .unwrap()
+    }
+
+    fn iface(sys: &SymbolicSys) -> Iface {
+        let spec = *sys.resolve("RGBContract.AssetSpec").unwrap();
+        Iface {
+            version: VerNo::V1,
+            name: tn!("Test"),
+            inherits: none!(),
+            timestamp: 1711405444,
+            metadata: none!(),
+            global_state: tiny_bmap! {
+                fname!("spec") => GlobalIface::required(spec),
+            },
+            assignments: tiny_bmap! {
+                fname!("assetOwner") => AssignIface::private(OwnedIface::Amount, Req::NoneOrMore),
+            },
+            valencies: none!(),
+            genesis: GenesisIface {
+                modifier: Modifier::Final,
+                metadata: none!(),
+                globals: tiny_bmap! { fname!("spec") => Occurrences::Once },
+                assignments: tiny_bmap! { fname!("assetOwner") => Occurrences::NoneOrMore },
+                valencies: none!(),
+                errors: none!(),
+            },
+            transitions: tiny_bmap! {
+                fname!("transfer") => TransitionIface {
+                    modifier: Modifier::Final,
+                    optional: false,
+                    metadata: none!(),
+                    globals: none!(),
+                    inputs: tiny_bmap! { fname!("assetOwner") => Occurrences::OnceOrMore },
+                    assignments: tiny_bmap! { fname!("assetOwner") => Occurrences::OnceOrMore },
+                    valencies: none!(),
+                    errors: none!(),
+                    default_assignment: Some(fname!("assetOwner")),
+                },
+            },
+            extensions: none!(),
+            default_operation: Some(fname!("transfer")),
+            errors: none!(),
+            developer: none!(),
+        }
+    }
+
+    #[test]
+    fn genesis_form() {
+        let sys = sys();
+        let form = iface(&sys).op_form(&OpName::Genesis, &sys).unwrap();
+        assert_eq!(form.name, None);
+        assert_eq!(form.globals.len(), 1);
+
+        let spec = &form.globals[0];
+        assert_eq!(spec.type_name.as_deref(), Some("RGBContract.AssetSpec"));
+        assert!(spec.is_required());
+        assert!(!spec.is_multiple());
+        let FormType::Struct { fields } = &spec.ty else {
+            panic!("asset spec must be a structure")
+        };
+        let names = fields
+            .iter()
+            .map(|f| f.name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["ticker", "name", "details", "precision"]);
+        assert!(matches!(fields[2].ty, FormType::Optional { .. }));
+        assert!(matches!(fields[3].ty, FormType::Enum { .. }));
+
+        let owner = &form.assignments[0];
+        assert_eq!(owner.ty, FormType::Amount);
+        assert!(!owner.is_required());
+        assert!(owner.is_multiple());
+    }
+
+    #[test]
+    fn transition_form() {
+        let sys = sys();
+        let iface = iface(&sys);
+        let form = iface
+            .op_form(&OpName::Transition(fname!("transfer")), &sys)
+            .unwrap();
+        assert_eq!(form.name, Some(fname!("transfer")));
+        assert_eq!(form.default_assignment, Some(fname!("assetOwner")));
+        assert!(form.globals.is_empty());
+        assert!(form.assignments[0].is_required());
+
+        assert_eq!(
+            iface.op_form(&OpName::Transition(fname!("burn")), &sys),
+            Err(FormError::UnknownOperation(OpName::Transition(fname!("burn"))))
+        );
+    }
+}
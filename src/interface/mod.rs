@@ -31,13 +31,19 @@ mod filter;
 pub(crate) mod resolver;
 mod contractum;
 mod inheritance;
+mod form;
+mod preflight;
+mod template;
+mod docs;
 
 pub use builder::{BuilderError, ContractBuilder, TransitionBuilder, TxOutpoint};
 pub use contract::{
-    AllocatedState, AttachAllocation, ContractError, ContractIface, ContractOp, DataAllocation,
-    FungibleAllocation, OpDirection, OwnedAllocation, RightsAllocation,
+    AllocatedState, AmountDisplayExt, AttachAllocation, ContractError, ContractIface, ContractOp,
+    DataAllocation, FungibleAllocation, OpDirection, OwnedAllocation, RightsAllocation,
 };
 pub use contractum::IfaceDisplay;
+pub use docs::{ArgDocs, ArgRole, ErrorDocs, IfaceDocs, OpDoc, OpDocs};
+pub use form::{FormArg, FormError, FormField, FormType, FormVariant, OpForm};
 pub use filter::{AssignmentsFilter, FilterExclude, FilterIncludeAll};
 pub use iface::{
     ArgMap, AssignIface, ExtensionIface, GenesisIface, GlobalIface, Iface, IfaceClass, IfaceId,
@@ -46,6 +52,12 @@ pub use iface::{
 };
 pub use iimpl::{IfaceImpl, ImplId, NamedField, NamedType, NamedVariant, SchemaTypeIndex};
 pub use inheritance::{CheckInheritance, ExtensionError, InheritanceFailure};
+pub use preflight::{PreflightCheck, PreflightItem, PreflightReport, PreflightStatus};
+pub use template::{
+    Rgb20Template, Rgb21Template, TemplateError, TEMPLATE_ASSIGNMENT_OWNER,
+    TEMPLATE_GLOBAL_ISSUED_SUPPLY, TEMPLATE_GLOBAL_SPEC, TEMPLATE_GLOBAL_TERMS,
+    TEMPLATE_GLOBAL_TOKENS,
+};
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, Default)]
 #[derive(StrictType, StrictEncode, StrictDecode)]
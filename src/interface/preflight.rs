@@ -0,0 +1,166 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{self, Display, Formatter};
+
+/// Check performed by [`crate::interface::ContractBuilder::preflight`].
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum PreflightCheck {
+    /// Total amount of a fungible state fits into the 64-bit range.
+    #[display("supply overflow")]
+    SupplyOverflow,
+
+    /// Fungible allocations sum up to the declared issued supply.
+    #[display("issued supply")]
+    IssuedSupply,
+
+    /// Global state matches the requirements of the interface genesis.
+    #[display("global state")]
+    GlobalState,
+
+    /// Owned state matches the requirements of the interface genesis.
+    #[display("interface conformance")]
+    IfaceConformance,
+
+    /// Media referenced by the contract must be distributed separately.
+    #[display("media")]
+    Media,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum PreflightStatus {
+    #[display("[x]")]
+    Passed,
+
+    #[display("[!]")]
+    Warning,
+
+    #[display("[ ]")]
+    Failed,
+}
+
+/// Item of the issuance checklist.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct PreflightItem {
+    pub check: PreflightCheck,
+    pub status: PreflightStatus,
+    pub details: String,
+}
+
+/// Checklist-style report on a contract genesis produced before its issuance.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct PreflightReport {
+    pub items: Vec<PreflightItem>,
+}
+
+impl PreflightReport {
+    pub(super) fn push(
+        &mut self,
+        check: PreflightCheck,
+        status: PreflightStatus,
+        details: impl ToString,
+    ) {
+        self.items.push(PreflightItem {
+            check,
+            status,
+            details: details.to_string(),
+        });
+    }
+
+    /// Detects whether none of the checks has failed. Warnings do not prevent
+    /// the issuance.
+    pub fn is_ok(&self) -> bool { self.failures().next().is_none() }
+
+    pub fn failures(&self) -> impl Iterator<Item = &PreflightItem> {
+        self.items
+            .iter()
+            .filter(|item| item.status == PreflightStatus::Failed)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &PreflightItem> {
+        self.items
+            .iter()
+            .filter(|item| item.status == PreflightStatus::Warning)
+    }
+}
+
+impl Display for PreflightReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for item in &self.items {
+            writeln!(f, "{} {}: {}", item.status, item.check, item.details)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rgb::{Identity, Occurrences, Schema};
+    use strict_encoding::StrictDumb;
+    use strict_types::TypeSystem;
+
+    use crate::interface::{ContractBuilder, Iface, IfaceImpl};
+
+    #[test]
+    fn preflight() {
+        let mut iface = Iface::strict_dumb();
+        iface.genesis.globals = tiny_bmap! { fname!("spec") => Occurrences::Once };
+        iface.genesis.assignments = tiny_bmap! { fname!("assetOwner") => Occurrences::NoneOrMore };
+        let builder = ContractBuilder::with(
+            Identity::default(),
+            iface,
+            Schema::strict_dumb(),
+            IfaceImpl::strict_dumb(),
+            TypeSystem::default(),
+            none!(),
+        );
+        let report = builder.preflight();
+        assert!(!report.is_ok());
+        assert_eq!(report.failures().count(), 2);
+        assert_eq!(report.warnings().count(), 0);
+        assert_eq!(
+            report.to_string(),
+            "[ ] global state: `spec` is not implemented by the interface implementation\n[ ] \
+             interface conformance: `assetOwner` is not implemented by the interface \
+             implementation\n[x] media: no media are referenced\n"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Genesis templates for common asset launches.
+//!
+//! Templates fill in the global state and allocations of a genesis following
+//! the field names of the RGB20 and RGB21 interface standards, leaving to the
+//! issuer only the asset-specific data. Interface, schema and implementation
+//! for the contract are provided with the [`ContractBuilder`], which is
+//! testnet by default; the builder also creates asset tags and timestamps for
+//! the genesis.
+
+#![allow(clippy::result_large_err)]
+
+use std::str::FromStr;
+
+use invoice::{Allocation, Amount, Precision};
+use rgb::GenesisSeal;
+use strict_encoding::{InvalidRString, StrictSerialize};
+
+use crate::containers::{BuilderSeal, ValidContract};
+use crate::interface::{BuilderError, ContractBuilder};
+use crate::stl::{AssetSpec, ContractTerms, RicardianContract};
+
+/// Name of the global state with asset specification.
+pub const TEMPLATE_GLOBAL_SPEC: &str = "spec";
+/// Name of the global state with contract terms.
+pub const TEMPLATE_GLOBAL_TERMS: &str = "terms";
+/// Name of the global state with the issued supply of a fungible asset.
+pub const TEMPLATE_GLOBAL_ISSUED_SUPPLY: &str = "issuedSupply";
+/// Name of the global state with the data of collection tokens.
+pub const TEMPLATE_GLOBAL_TOKENS: &str = "tokens";
+/// Name of the owned state with the asset allocations.
+pub const TEMPLATE_ASSIGNMENT_OWNER: &str = "assetOwner";
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum TemplateError {
+    /// invalid asset specification: {0}
+    #[from]
+    InvalidSpec(InvalidRString),
+
+    /// asset allocations sum up to {allocated} while the issued supply is
+    /// {supply}.
+    SupplyMismatch { supply: Amount, allocated: Amount },
+
+    /// asset allocations overflow the maximal supply.
+    SupplyOverflow,
+
+    /// collection must contain at least a single token.
+    NoTokens,
+
+    #[from]
+    #[display(inner)]
+    Builder(BuilderError),
+}
+
+/// Template for a genesis of a fungible asset under RGB20 interface.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Rgb20Template {
+    pub spec: AssetSpec,
+    pub terms: ContractTerms,
+    pub issued_supply: Amount,
+    pub allocations: Vec<(BuilderSeal<GenesisSeal>, Amount)>,
+}
+
+impl Rgb20Template {
+    /// Constructs template for a fungible asset with no contract terms, which
+    /// issues the whole `supply` to the provided `allocations`.
+    ///
+    /// Errors if the allocations don't sum up to the issued supply.
+    pub fn testnet_simple(
+        ticker: &str,
+        name: &str,
+        precision: Precision,
+        supply: impl Into<Amount>,
+        allocations: impl IntoIterator<Item = (impl Into<BuilderSeal<GenesisSeal>>, Amount)>,
+    ) -> Result<Self, TemplateError> {
+        let template = Rgb20Template {
+            spec: AssetSpec::with(ticker, name, precision, None)?,
+            terms: empty_terms(),
+            issued_supply: supply.into(),
+            allocations: allocations
+                .into_iter()
+                .map(|(seal, amount)| (seal.into(), amount))
+                .collect(),
+        };
+        template.check()?;
+        Ok(template)
+    }
+
+    /// Checks that the allocations sum up to the issued supply.
+    pub fn check(&self) -> Result<(), TemplateError> {
+        let mut allocated = Amount::ZERO;
+        for (_, amount) in &self.allocations {
+            allocated
+                .checked_add_assign(*amount)
+                .ok_or(TemplateError::SupplyOverflow)?;
+        }
+        if allocated != self.issued_supply {
+            return Err(TemplateError::SupplyMismatch {
+                supply: self.issued_supply,
+                allocated,
+            });
+        }
+        Ok(())
+    }
+
+    /// Adds global state and allocations of the template to the contract
+    /// builder.
+    pub fn apply(self, builder: ContractBuilder) -> Result<ContractBuilder, TemplateError> {
+        self.check()?;
+        let mut builder = builder
+            .add_global_state(TEMPLATE_GLOBAL_SPEC, self.spec)?
+            .add_global_state(TEMPLATE_GLOBAL_TERMS, self.terms)?
+            .add_global_state(TEMPLATE_GLOBAL_ISSUED_SUPPLY, self.issued_supply)?;
+        for (seal, amount) in self.allocations {
+            builder = builder.add_fungible_state(TEMPLATE_ASSIGNMENT_OWNER, seal, amount)?;
+        }
+        Ok(builder)
+    }
+
+    /// Issues contract from the template using the provided builder.
+    pub fn issue(self, builder: ContractBuilder) -> Result<ValidContract, TemplateError> {
+        Ok(self.apply(builder)?.issue_contract()?)
+    }
+}
+
+/// Template for a genesis of a collection of unique or fractional tokens
+/// under RGB21 interface.
+///
+/// The type of the token data is defined by the interface implementation,
+/// thus it is a generic parameter of the template.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Rgb21Template<T: StrictSerialize> {
+    pub spec: AssetSpec,
+    pub terms: ContractTerms,
+    pub tokens: Vec<T>,
+    pub allocations: Vec<(BuilderSeal<GenesisSeal>, Allocation)>,
+}
+
+impl<T: StrictSerialize> Rgb21Template<T> {
+    /// Constructs template for a collection with no contract terms, allocating
+    /// the tokens to the provided `allocations`.
+    pub fn testnet_simple(
+        ticker: &str,
+        name: &str,
+        tokens: impl IntoIterator<Item = T>,
+        allocations: impl IntoIterator<Item = (impl Into<BuilderSeal<GenesisSeal>>, Allocation)>,
+    ) -> Result<Self, TemplateError> {
+        let template = Rgb21Template {
+            spec: AssetSpec::with(ticker, name, Precision::Indivisible, None)?,
+            terms: empty_terms(),
+            tokens: tokens.into_iter().collect(),
+            allocations: allocations
+                .into_iter()
+                .map(|(seal, allocation)| (seal.into(), allocation))
+                .collect(),
+        };
+        if template.tokens.is_empty() {
+            return Err(TemplateError::NoTokens);
+        }
+        Ok(template)
+    }
+
+    /// Adds global state and allocations of the template to the contract
+    /// builder.
+    pub fn apply(self, builder: ContractBuilder) -> Result<ContractBuilder, TemplateError> {
+        if self.tokens.is_empty() {
+            return Err(TemplateError::NoTokens);
+        }
+        let mut builder = builder
+            .add_global_state(TEMPLATE_GLOBAL_SPEC, self.spec)?
+            .add_global_state(TEMPLATE_GLOBAL_TERMS, self.terms)?;
+        for token in self.tokens {
+            builder = builder.add_global_state(TEMPLATE_GLOBAL_TOKENS, token)?;
+        }
+        for (seal, allocation) in self.allocations {
+            builder = builder.add_data(TEMPLATE_ASSIGNMENT_OWNER, seal, allocation)?;
+        }
+        Ok(builder)
+    }
+
+    /// Issues contract from the template using the provided builder.
+    pub fn issue(self, builder: ContractBuilder) -> Result<ValidContract, TemplateError> {
+        Ok(self.apply(builder)?.issue_contract()?)
+    }
+}
+
+fn empty_terms() -> ContractTerms {
+    ContractTerms {
+        text: RicardianContract::from_str("").expect("empty string"),
+        media: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rgb::XChain;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn rgb20_supply() {
+        let seal = BuilderSeal::from(XChain::Bitcoin(GenesisSeal::strict_dumb()));
+        let template = Rgb20Template::testnet_simple(
+            "TCKR",
+            "Test asset",
+            Precision::CentiMicro,
+            Amount::from(100u64),
+            [(seal, Amount::from(60u64)), (seal, Amount::from(40u64))],
+        )
+        .unwrap();
+        assert_eq!(template.spec.ticker(), "TCKR");
+        assert_eq!(template.allocations.len(), 2);
+
+        assert_eq!(
+            Rgb20Template::testnet_simple(
+                "TCKR",
+                "Test asset",
+                Precision::CentiMicro,
+                Amount::from(100u64),
+                [(seal, Amount::from(60u64))],
+            ),
+            Err(TemplateError::SupplyMismatch {
+                supply: Amount::from(100u64),
+                allocated: Amount::from(60u64)
+            })
+        );
+        assert!(matches!(
+            Rgb20Template::testnet_simple(
+                "TCKR",
+                "Test asset",
+                Precision::CentiMicro,
+                Amount::from(100u64),
+                [(seal, Amount::from(u64::MAX)), (seal, Amount::from(1u64))],
+            ),
+            Err(TemplateError::SupplyOverflow)
+        ));
+        assert!(matches!(
+            Rgb20Template::testnet_simple(
+                "TICKERTOOLONG",
+                "Test asset",
+                Precision::CentiMicro,
+                Amount::ZERO,
+                Vec::<(BuilderSeal<GenesisSeal>, Amount)>::new(),
+            ),
+            Err(TemplateError::InvalidSpec(_))
+        ));
+    }
+}
@@ -42,13 +42,25 @@ pub mod interface;
 pub mod containers;
 pub mod persistence;
 pub mod resolvers;
+pub mod broadcast;
+pub mod transport;
+pub mod service;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 mod contract;
 pub mod info;
+pub mod simulator;
+pub mod faucet;
+pub mod vectors;
+pub mod migrations;
+pub mod prelude;
 
 pub use bp::{Outpoint, Txid};
 pub use contract::{
-    BundleExt, KnownState, MergeReveal, MergeRevealError, OutputAssignment, RevealError,
-    TypedAssignsExt, WitnessInfo,
+    AssignmentView, BundleExt, KnownState, MergeReveal, MergeRevealError, OutputAssignment,
+    RevealError, TransitionView, TypedAssignsExt, WitnessInfo, WitnessInput,
 };
 pub use invoice::{Allocation, Amount, CoinAmount, OwnedFraction, Precision, TokenIndex};
 pub use rgb::prelude::*;
@@ -0,0 +1,756 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection and migration of data produced by other versions of the library.
+//!
+//! The module recognizes binary and ASCII-armored containers and persisted
+//! stock files (stash, state, index and archive), detects the version of
+//! their encoding and upgrades them into the current encoding. Migration may
+//! be run in a dry-run mode, producing only the report on the required
+//! actions.
+//!
+//! Containers produced before v0.11 (container versions 0 and 1) are detected
+//! and reported, but can't be upgraded: their data model is not compatible
+//! with the current one and they must be re-exported from the latest v0.10
+//! release as v0.11 containers.
+//!
+//! Stock files start with a header carrying the file kind and the encoding
+//! version ([`StockVer`]). Files without the header were written before the
+//! header was introduced and are upgraded by adding the data which appeared
+//! in the stock since then.
+
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, BufRead};
+
+use amplify::confinement::U32 as U32MAX;
+use armor::AsciiArmor;
+use strict_encoding::{
+    DecodeError, DeserializeError, SerializeError, StreamReader, StrictDecode, StrictReader,
+    StrictSerialize,
+};
+
+use crate::containers::{
+    ContainerVer, Contract, FileContent, Kit, LoadError, Transfer, UniversalFile,
+};
+#[cfg(feature = "fs")]
+use crate::persistence::fs::FsBinStore;
+use crate::persistence::{MemArchive, MemIndex, MemStash, MemState};
+
+const RGB_PREFIX: &[u8; 4] = b"RGB\x00";
+const ARMOR_BEGIN: &str = "-----BEGIN ";
+const STOCK_HEADER_LEN: usize = 8;
+
+/// Version of the encoding of the stock files.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[repr(u8)]
+pub enum StockVer {
+    /// Files written before the version header was introduced.
+    V0 = 0,
+    /// Files with the version header. Adds labels, provenance, reservations,
+    /// provisional witnesses, conflict resolutions, pending transfers and
+    /// close method policy to the stash, the set of archived contracts to the state, the index of
+    /// spent assignments to the index and introduces the archive file.
+    #[default]
+    V1 = 1,
+}
+
+impl TryFrom<u8> for StockVer {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::V0),
+            1 => Ok(Self::V1),
+            unknown => Err(unknown),
+        }
+    }
+}
+
+/// Type of data subject to migration.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display(lowercase)]
+pub enum DataKind {
+    Kit,
+    Contract,
+    Transfer,
+    /// Consignment of unknown type (legacy consignments don't provide the
+    /// type information).
+    Consignment,
+    Stash,
+    State,
+    Index,
+    Archive,
+}
+
+impl DataKind {
+    /// Detects whether the data are one of the stock files.
+    pub fn is_stock(self) -> bool {
+        matches!(self, DataKind::Stash | DataKind::State | DataKind::Index | DataKind::Archive)
+    }
+
+    const fn stock_magic(self) -> Option<[u8; 3]> {
+        match self {
+            DataKind::Stash => Some(*b"STH"),
+            DataKind::State => Some(*b"STT"),
+            DataKind::Index => Some(*b"IDX"),
+            DataKind::Archive => Some(*b"ARC"),
+            _ => None,
+        }
+    }
+
+    /// Returns header which starts stock files of this kind written with the
+    /// current version of the encoding.
+    pub fn stock_header(self) -> Option<[u8; STOCK_HEADER_LEN]> {
+        let magic = self.stock_magic()?;
+        let mut header = [0u8; STOCK_HEADER_LEN];
+        header[..4].copy_from_slice(RGB_PREFIX);
+        header[4..7].copy_from_slice(&magic);
+        header[7] = StockVer::default() as u8;
+        Some(header)
+    }
+}
+
+/// Format of the data detected by [`detect`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct DataFormat {
+    pub kind: DataKind,
+    /// Whether the data are ASCII-armored.
+    pub armored: bool,
+    /// Container version or, for the stock files, [`StockVer`] of the
+    /// encoding.
+    pub version: Option<u8>,
+}
+
+impl Display for DataFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.armored {
+            f.write_str("armored ")?;
+        }
+        Display::fmt(&self.kind, f)?;
+        if let Some(version) = self.version {
+            write!(f, " v{version}")?;
+        }
+        Ok(())
+    }
+}
+
+impl DataFormat {
+    /// Detects whether the data are encoded with the current version of the
+    /// encoding.
+    pub fn is_current(&self) -> bool {
+        match self.version {
+            Some(v) if self.kind.is_stock() => v == StockVer::default() as u8,
+            Some(v) => ContainerVer::try_from(v).is_ok(),
+            None => true,
+        }
+    }
+
+    /// Detects whether the data were produced before v0.11.
+    pub fn is_legacy(&self) -> bool {
+        match self.version {
+            Some(v) if !self.kind.is_stock() => v < ContainerVer::V2 as u8,
+            _ => false,
+        }
+    }
+
+    /// Detects whether the data can be upgraded into the current encoding.
+    pub fn is_upgradable(&self) -> bool {
+        match self.version {
+            Some(v) if self.kind.is_stock() => StockVer::try_from(v).is_ok(),
+            _ => self.is_current(),
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum MigrationError {
+    /// data format is not recognized.
+    Unrecognized,
+
+    /// {0} was produced by rgb-std before v0.11 and can't be upgraded; please
+    /// re-export it with the latest v0.10 release into v0.11 format.
+    Legacy(DataFormat),
+
+    /// {0} uses a version of the encoding which is not known to this release.
+    UnknownVersion(DataFormat),
+
+    /// unable to decode {0}: {1}
+    Decode(DataFormat, String),
+
+    /// unable to access stock files: {0}
+    Io(String),
+}
+
+/// Report produced by the migration procedure.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct MigrationReport {
+    /// Detected data format.
+    pub format: DataFormat,
+    /// Whether the migration was run in a dry-run mode, without producing
+    /// the migrated data.
+    pub dry_run: bool,
+    /// Actions performed during the migration (or required to be performed,
+    /// for the dry-run mode).
+    pub actions: Vec<String>,
+}
+
+impl MigrationReport {
+    /// Detects whether the data didn't require any changes.
+    pub fn is_up_to_date(&self) -> bool { self.actions.is_empty() }
+}
+
+impl Display for MigrationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format)?;
+        if self.dry_run {
+            f.write_str(" (dry run)")?;
+        }
+        if self.is_up_to_date() {
+            return writeln!(f, ": up to date");
+        }
+        writeln!(f, ":")?;
+        for action in &self.actions {
+            writeln!(f, "- {action}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Detects format of a binary or ASCII-armored container, or of a stock file
+/// with the version header.
+///
+/// Stock files written without the version header are detected with
+/// [`detect_stock`].
+pub fn detect(data: &[u8]) -> Result<DataFormat, MigrationError> {
+    if let Some(rest) = data.strip_prefix(RGB_PREFIX) {
+        let kind = match rest.get(..3) {
+            Some(magic) if magic == Kit::MAGIC => DataKind::Kit,
+            Some(magic) if magic == Contract::MAGIC => DataKind::Contract,
+            Some(magic) if magic == Transfer::MAGIC => DataKind::Transfer,
+            Some(magic) => [DataKind::Stash, DataKind::State, DataKind::Index, DataKind::Archive]
+                .into_iter()
+                .find(|kind| kind.stock_magic().is_some_and(|m| m.as_slice() == magic))
+                .ok_or(MigrationError::Unrecognized)?,
+            None => return Err(MigrationError::Unrecognized),
+        };
+        let version = *rest.get(3).ok_or(MigrationError::Unrecognized)?;
+        return Ok(DataFormat {
+            kind,
+            armored: false,
+            version: Some(version),
+        });
+    }
+
+    let text = std::str::from_utf8(data).map_err(|_| MigrationError::Unrecognized)?;
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty());
+    let title = lines
+        .next()
+        .and_then(|line| line.strip_prefix(ARMOR_BEGIN))
+        .and_then(|line| line.strip_suffix("-----"))
+        .ok_or(MigrationError::Unrecognized)?;
+    let mut kind = match title {
+        t if t == <Kit as AsciiArmor>::PLATE_TITLE => DataKind::Kit,
+        t if t == <Contract as AsciiArmor>::PLATE_TITLE => DataKind::Consignment,
+        _ => return Err(MigrationError::Unrecognized),
+    };
+    // Legacy containers without version header are of version 0
+    let mut version = 0u8;
+    for line in lines.take_while(|line| !line.is_empty()) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        match (name.trim(), value.trim()) {
+            ("Version", value) => {
+                version = value.parse().map_err(|_| MigrationError::Unrecognized)?
+            }
+            ("Type", "contract") if kind == DataKind::Consignment => kind = DataKind::Contract,
+            ("Type", "transfer") if kind == DataKind::Consignment => kind = DataKind::Transfer,
+            _ => {}
+        }
+    }
+    Ok(DataFormat {
+        kind,
+        armored: true,
+        version: Some(version),
+    })
+}
+
+/// Migrates container into the current binary encoding.
+///
+/// In the dry-run mode returns only the report, without the migrated
+/// container.
+pub fn migrate(
+    data: &[u8],
+    dry_run: bool,
+) -> Result<(MigrationReport, Option<UniversalFile>), MigrationError> {
+    let format = detect(data)?;
+    if format.is_legacy() {
+        return Err(MigrationError::Legacy(format));
+    }
+    if !format.is_current() {
+        return Err(MigrationError::UnknownVersion(format));
+    }
+
+    if format.kind.is_stock() {
+        return Err(MigrationError::Unrecognized);
+    }
+
+    let mut actions = vec![];
+    if format.armored {
+        actions.push(s!("convert ASCII armor into binary encoding"));
+    }
+    let file = if format.armored {
+        let text = std::str::from_utf8(data).expect("checked during detection");
+        match format.kind {
+            DataKind::Kit => Kit::from_ascii_armored_str(text).map(UniversalFile::from),
+            DataKind::Contract => Contract::from_ascii_armored_str(text).map(UniversalFile::from),
+            DataKind::Transfer => Transfer::from_ascii_armored_str(text).map(UniversalFile::from),
+            _ => return Err(MigrationError::Unrecognized),
+        }
+        .map_err(|err| MigrationError::Decode(format, err.to_string()))?
+    } else {
+        UniversalFile::load(data).map_err(|err| match err {
+            LoadError::InvalidMagic => MigrationError::Unrecognized,
+            err => MigrationError::Decode(format, err.to_string()),
+        })?
+    };
+    let report = MigrationReport {
+        format,
+        dry_run,
+        actions,
+    };
+    Ok((report, if dry_run { None } else { Some(file) }))
+}
+
+/// Detects format of a stock file of the given kind, which may lack the
+/// version header if it was written with [`StockVer::V0`] encoding.
+pub fn detect_stock(kind: DataKind, data: &[u8]) -> Result<DataFormat, MigrationError> {
+    split_stock(kind, data).map(|(format, _)| format)
+}
+
+fn split_stock(kind: DataKind, data: &[u8]) -> Result<(DataFormat, &[u8]), MigrationError> {
+    if !kind.is_stock() {
+        return Err(MigrationError::Unrecognized);
+    }
+    let (version, payload) = if data.starts_with(RGB_PREFIX) {
+        let format = detect(data)?;
+        if format.kind != kind {
+            return Err(MigrationError::Unrecognized);
+        }
+        (format.version.expect("stock files are versioned"), &data[STOCK_HEADER_LEN..])
+    } else if kind == DataKind::Archive {
+        // Archive was introduced together with the version header
+        return Err(MigrationError::Unrecognized);
+    } else {
+        (StockVer::V0 as u8, data)
+    };
+    let format = DataFormat {
+        kind,
+        armored: false,
+        version: Some(version),
+    };
+    if !format.is_upgradable() {
+        return Err(MigrationError::UnknownVersion(format));
+    }
+    Ok((format, payload))
+}
+
+fn decode_stock<'data, T>(
+    format: DataFormat,
+    data: &'data [u8],
+    decode: impl FnOnce(
+        &mut StrictReader<StreamReader<io::Cursor<&'data [u8]>>>,
+    ) -> Result<T, DecodeError>,
+) -> Result<T, MigrationError> {
+    let mut reader = StrictReader::in_memory::<U32MAX>(data);
+    let res = decode(&mut reader).map_err(DeserializeError::from);
+    let res = res.and_then(|object| {
+        if !reader.into_cursor().fill_buf()?.is_empty() {
+            return Err(DeserializeError::DataNotEntirelyConsumed);
+        }
+        Ok(object)
+    });
+    res.map_err(|err| MigrationError::Decode(format, err.to_string()))
+}
+
+fn stock_actions(format: DataFormat) -> Vec<String> {
+    if format.is_current() {
+        return vec![];
+    }
+    let upgrade = match format.kind {
+        DataKind::Stash => {
+            "add empty labels, provenance, reservations, provisional witnesses, conflict \
+             resolutions, pending transfers and default close method policy"
+        }
+        DataKind::State => "add empty set of archived contracts",
+        DataKind::Index => "rebuild index of spent assignments from the stash",
+        _ => unreachable!("archive has a single version"),
+    };
+    vec![upgrade.to_owned(), s!("add version header")]
+}
+
+fn stock_report(format: DataFormat, dry_run: bool) -> MigrationReport {
+    MigrationReport {
+        format,
+        dry_run,
+        actions: stock_actions(format),
+    }
+}
+
+/// Loads stash file, upgrading it into the current encoding.
+pub fn load_stash(data: &[u8]) -> Result<(MigrationReport, MemStash), MigrationError> {
+    let (format, data) = split_stock(DataKind::Stash, data)?;
+    let stash = decode_stock(format, data, |reader| {
+        if format.is_current() {
+            MemStash::strict_decode(reader)
+        } else {
+            MemStash::strict_decode_v0(reader)
+        }
+    })?;
+    Ok((stock_report(format, false), stash))
+}
+
+/// Loads state file, upgrading it into the current encoding.
+pub fn load_state(data: &[u8]) -> Result<(MigrationReport, MemState), MigrationError> {
+    let (format, data) = split_stock(DataKind::State, data)?;
+    let state = decode_stock(format, data, |reader| {
+        if format.is_current() {
+            MemState::strict_decode(reader)
+        } else {
+            MemState::strict_decode_v0(reader)
+        }
+    })?;
+    Ok((stock_report(format, false), state))
+}
+
+/// Loads index file, upgrading it into the current encoding. The stash is
+/// used to rebuild the parts of the index missed in the previous versions of
+/// the encoding.
+pub fn load_index(
+    data: &[u8],
+    stash: &MemStash,
+) -> Result<(MigrationReport, MemIndex), MigrationError> {
+    let (format, data) = split_stock(DataKind::Index, data)?;
+    let index = if format.is_current() {
+        decode_stock(format, data, MemIndex::strict_decode)?
+    } else {
+        let mut index = decode_stock(format, data, MemIndex::strict_decode_v0)?;
+        index
+            .index_spendings(stash)
+            .map_err(|err| MigrationError::Decode(format, err.to_string()))?;
+        index
+    };
+    Ok((stock_report(format, false), index))
+}
+
+/// Loads archive file.
+pub fn load_archive(data: &[u8]) -> Result<MemArchive, MigrationError> {
+    let (format, data) = split_stock(DataKind::Archive, data)?;
+    decode_stock(format, data, MemArchive::strict_decode)
+}
+
+/// Serializes stock file of the given kind in the current encoding, prefixed
+/// with the version header.
+pub fn serialize_stock(
+    kind: DataKind,
+    object: &impl StrictSerialize,
+) -> Result<Vec<u8>, SerializeError> {
+    let mut data = kind
+        .stock_header()
+        .expect("only stock files are serialized")
+        .to_vec();
+    data.extend(object.to_strict_serialized::<U32MAX>()?.release());
+    Ok(data)
+}
+
+/// Checks whether stock file (`stash.dat`, `state.dat`, `index.dat` or
+/// `archive.dat` written by `FsBinStore`) can be read by the current version
+/// of the library, reporting the actions required to upgrade it.
+///
+/// Files which can't be decoded must be recreated by importing the
+/// consignments (migrated with [`migrate`]) into a new stock.
+pub fn check_stock(kind: DataKind, data: &[u8]) -> Result<MigrationReport, MigrationError> {
+    let format = match kind {
+        DataKind::Stash => load_stash(data)?.0.format,
+        DataKind::State => load_state(data)?.0.format,
+        // Without the stash only decoding of the index is checked
+        DataKind::Index => load_index(data, &MemStash::in_memory())?.0.format,
+        DataKind::Archive => {
+            load_archive(data)?;
+            detect_stock(kind, data)?
+        }
+        _ => return Err(MigrationError::Unrecognized),
+    };
+    Ok(stock_report(format, true))
+}
+
+/// Upgrades stock files kept by the file store into the current encoding.
+///
+/// In the dry-run mode returns only the reports, without rewriting the files.
+#[cfg(feature = "fs")]
+pub fn migrate_store(
+    store: &FsBinStore,
+    dry_run: bool,
+) -> Result<Vec<MigrationReport>, MigrationError> {
+    use nonasync::persistence::PersistenceProvider;
+
+    let read = |path: &std::path::Path| {
+        std::fs::read(path).map_err(|err| MigrationError::Io(err.to_string()))
+    };
+    let write = |res: Result<(), nonasync::persistence::PersistenceError>| {
+        res.map_err(|err| MigrationError::Io(err.to_string()))
+    };
+
+    let stash_data = read(&store.stash)?;
+    let state_data = read(&store.state)?;
+    let index_data = read(&store.index)?;
+    if dry_run {
+        let mut reports = vec![
+            check_stock(DataKind::Stash, &stash_data)?,
+            check_stock(DataKind::State, &state_data)?,
+            check_stock(DataKind::Index, &index_data)?,
+        ];
+        if store.archive.exists() {
+            reports.push(check_stock(DataKind::Archive, &read(&store.archive)?)?);
+        }
+        return Ok(reports);
+    }
+
+    let (stash_report, stash) = load_stash(&stash_data)?;
+    let (state_report, state) = load_state(&state_data)?;
+    let (index_report, index) = load_index(&index_data, &stash)?;
+    if !stash_report.is_up_to_date() {
+        write(store.store(&stash))?;
+    }
+    if !state_report.is_up_to_date() {
+        write(store.store(&state))?;
+    }
+    if !index_report.is_up_to_date() {
+        write(store.store(&index))?;
+    }
+    Ok(vec![stash_report, state_report, index_report])
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+    use std::str::FromStr;
+
+    use rgb::{ContractId, OpId, Operation};
+
+    use super::*;
+    use crate::persistence::{
+        ContractStateRead, IndexReadProvider, StashReadProvider, StateReadProvider,
+    };
+
+    #[test]
+    fn detect_containers() {
+        let format = detect(include_bytes!("../asset/contract.default")).unwrap();
+        assert_eq!(format, DataFormat {
+            kind: DataKind::Contract,
+            armored: false,
+            version: Some(2)
+        });
+        assert!(format.is_current());
+
+        let format = detect(include_bytes!("../asset/armored_transfer.default")).unwrap();
+        assert_eq!(format, DataFormat {
+            kind: DataKind::Transfer,
+            armored: true,
+            version: Some(2)
+        });
+
+        let format = detect(include_bytes!("../asset/armored_kit.default")).unwrap();
+        assert_eq!(format.kind, DataKind::Kit);
+
+        let legacy =
+            "-----BEGIN RGB CONSIGNMENT-----\nVersion: 1\n\n00\n-----END RGB CONSIGNMENT-----\n";
+        let format = detect(legacy.as_bytes()).unwrap();
+        assert_eq!(format, DataFormat {
+            kind: DataKind::Consignment,
+            armored: true,
+            version: Some(1)
+        });
+        assert!(format.is_legacy());
+        assert!(matches!(migrate(legacy.as_bytes(), true), Err(MigrationError::Legacy(_))));
+
+        assert!(matches!(detect(b"garbage"), Err(MigrationError::Unrecognized)));
+    }
+
+    #[test]
+    fn migrate_containers() {
+        let (report, file) = migrate(include_bytes!("../asset/kit.default"), false).unwrap();
+        assert!(report.is_up_to_date());
+        assert!(matches!(file, Some(UniversalFile::Kit(_))));
+
+        let (report, file) =
+            migrate(include_bytes!("../asset/armored_contract.default"), true).unwrap();
+        assert!(!report.is_up_to_date());
+        assert!(report.dry_run);
+        assert!(file.is_none());
+
+        let mut data = include_bytes!("../asset/transfer.default").to_vec();
+        data[7] = 4;
+        assert!(matches!(migrate(&data, true), Err(MigrationError::UnknownVersion(_))));
+    }
+
+    #[test]
+    fn migrate_records() {
+        // Consignments of the version preceding the extension records don't
+        // have the records map and are kept in their version
+        let data = include_bytes!("../asset/transfer.default");
+        let (report, file) = migrate(data, false).unwrap();
+        assert_eq!(report.format.version, Some(ContainerVer::V2 as u8));
+        assert!(report.is_up_to_date());
+        let mut migrated = vec![];
+        file.unwrap().save(&mut migrated).unwrap();
+        assert_eq!(migrated, data);
+
+        let mut transfer = Transfer::load(&data[..]).unwrap();
+        transfer.version = ContainerVer::V3;
+        let mut data = vec![];
+        transfer.save(&mut data).unwrap();
+        let (report, _) = migrate(&data, true).unwrap();
+        assert_eq!(report.format.version, Some(ContainerVer::V3 as u8));
+        assert!(report.is_up_to_date());
+        assert!(matches!(migrate(&data[..data.len() - 1], true), Err(MigrationError::Decode(..))));
+    }
+
+    #[test]
+    fn check_stock_files() {
+        let data = serialize_stock(DataKind::Stash, &MemStash::in_memory()).unwrap();
+        assert_eq!(detect(&data).unwrap(), DataFormat {
+            kind: DataKind::Stash,
+            armored: false,
+            version: Some(1)
+        });
+        assert!(check_stock(DataKind::Stash, &data).unwrap().is_up_to_date());
+        assert!(matches!(check_stock(DataKind::Index, &data), Err(MigrationError::Unrecognized)));
+
+        let mut data = serialize_stock(DataKind::State, &MemState::in_memory()).unwrap();
+        data[7] = 2;
+        assert!(matches!(
+            check_stock(DataKind::State, &data),
+            Err(MigrationError::UnknownVersion(_))
+        ));
+
+        let report =
+            check_stock(DataKind::Stash, include_bytes!("../asset/stock_v0/stash.dat")).unwrap();
+        assert_eq!(report.format.version, Some(StockVer::V0 as u8));
+        assert!(!report.format.is_legacy());
+        assert!(!report.is_up_to_date());
+        assert!(report.dry_run);
+        let report =
+            check_stock(DataKind::Index, include_bytes!("../asset/stock_v0/index.dat")).unwrap();
+        assert!(!report.is_up_to_date());
+        // Headerless files of another kind are rejected by decoding
+        assert!(matches!(
+            check_stock(DataKind::Stash, include_bytes!("../asset/stock_v0/state.dat")),
+            Err(MigrationError::Decode(..))
+        ));
+    }
+
+    #[test]
+    fn load_stock_v0() {
+        let contract_id =
+            ContractId::from_str("rgb:FOh0FEIR-6lWY2Rm-5LUK!hV-Ij3foia-u!xvjOy-pqSTxbA").unwrap();
+        let opid =
+            OpId::from_str("38d99ad8600d1f8586751a680ba14508f2cadca220317b3f33e9c0bbd402cf64")
+                .unwrap();
+
+        let (report, stash) = load_stash(include_bytes!("../asset/stock_v0/stash.dat")).unwrap();
+        assert!(!report.dry_run);
+        assert_eq!(report.actions.len(), 2);
+        assert!(
+            stash
+                .geneses()
+                .unwrap()
+                .any(|genesis| genesis.contract_id() == contract_id)
+        );
+        assert!(stash.debug_labels().is_empty());
+        assert!(stash.debug_resolutions().is_empty());
+
+        let (report, state) = load_state(include_bytes!("../asset/stock_v0/state.dat")).unwrap();
+        assert!(!report.is_up_to_date());
+        let contract = state.contract_state(contract_id).unwrap();
+        let amounts = contract
+            .fungible_all()
+            .map(|assignment| assignment.state.value.as_u64())
+            .collect::<BTreeSet<_>>();
+        // Issued amount and the change; the payment is assigned to a blinded
+        // seal, which is not tracked by the state
+        assert_eq!(amounts, bset![600, 1000]);
+
+        let (report, index) =
+            load_index(include_bytes!("../asset/stock_v0/index.dat"), &stash).unwrap();
+        assert!(!report.is_up_to_date());
+        let transition = stash
+            .debug_bundles()
+            .values()
+            .find_map(|bundle| bundle.known_transitions.get(&opid))
+            .unwrap();
+        assert!(!transition.inputs.is_empty());
+        for input in &transition.inputs {
+            assert_eq!(index.spenders(input.prev_out).unwrap(), bset![opid]);
+        }
+        let spent = transition.inputs.iter().next().unwrap().prev_out;
+
+        // Upgraded files are read back in the current encoding
+        let data = serialize_stock(DataKind::Index, &index).unwrap();
+        let (report, reloaded) = load_index(&data, &MemStash::in_memory()).unwrap();
+        assert!(report.is_up_to_date());
+        assert_eq!(reloaded.spenders(spent).unwrap(), bset![opid]);
+        let data = serialize_stock(DataKind::Stash, &stash).unwrap();
+        assert!(load_stash(&data).unwrap().0.is_up_to_date());
+        let data = serialize_stock(DataKind::State, &state).unwrap();
+        assert!(load_state(&data).unwrap().0.is_up_to_date());
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn migrate_fs_store() {
+        let dir = std::env::temp_dir().join(format!("rgb-std-migrate-{}", std::process::id()));
+        let store = FsBinStore::new(dir.clone()).unwrap();
+        std::fs::write(&store.stash, include_bytes!("../asset/stock_v0/stash.dat")).unwrap();
+        std::fs::write(&store.state, include_bytes!("../asset/stock_v0/state.dat")).unwrap();
+        std::fs::write(&store.index, include_bytes!("../asset/stock_v0/index.dat")).unwrap();
+
+        let reports = migrate_store(&store, true).unwrap();
+        assert!(reports.iter().all(|report| !report.is_up_to_date()));
+        assert_eq!(
+            detect_stock(DataKind::Index, &std::fs::read(&store.index).unwrap())
+                .unwrap()
+                .version,
+            Some(StockVer::V0 as u8)
+        );
+
+        let reports = migrate_store(&store, false).unwrap();
+        assert_eq!(reports.len(), 3);
+        let reports = migrate_store(&store, true).unwrap();
+        assert!(reports.iter().all(MigrationReport::is_up_to_date));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Columnar export of contract data for analytical stores.
+//!
+//! Contract allocations, operations and witnesses are exported as Arrow
+//! record batches, which can be passed to any Arrow-based tool directly or
+//! written as Parquet files with [`ContractExport::write_parquet`].
+
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::builder::{
+    ArrayBuilder, StringBuilder, UInt16Builder, UInt32Builder, UInt64Builder,
+};
+use arrow_array::RecordBatch;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use rgb::ContractId;
+
+/// Returns schema of the allocations record batch.
+pub fn allocation_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("contract_id", DataType::Utf8, false),
+        Field::new("opid", DataType::Utf8, false),
+        Field::new("assignment_type", DataType::UInt16, false),
+        Field::new("output_no", DataType::UInt16, false),
+        Field::new("layer1", DataType::Utf8, false),
+        Field::new("txid", DataType::Utf8, false),
+        Field::new("vout", DataType::UInt32, false),
+        Field::new("state", DataType::Utf8, false),
+        Field::new("amount", DataType::UInt64, true),
+        Field::new("data", DataType::Utf8, true),
+        Field::new("witness_id", DataType::Utf8, true),
+    ]))
+}
+
+/// Returns schema of the operations record batch.
+pub fn operation_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("contract_id", DataType::Utf8, false),
+        Field::new("opid", DataType::Utf8, false),
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("op_type", DataType::UInt16, true),
+        Field::new("bundle_id", DataType::Utf8, true),
+        Field::new("inputs", DataType::UInt32, false),
+        Field::new("assignments", DataType::UInt32, false),
+        Field::new("witness_id", DataType::Utf8, true),
+    ]))
+}
+
+/// Returns schema of the witnesses record batch.
+pub fn witness_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("contract_id", DataType::Utf8, false),
+        Field::new("witness_id", DataType::Utf8, false),
+        Field::new("layer1", DataType::Utf8, false),
+        Field::new("status", DataType::Utf8, true),
+        Field::new("height", DataType::UInt32, true),
+        Field::new("bundles", DataType::UInt32, false),
+    ]))
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, From)]
+pub(super) enum Value {
+    #[from]
+    Utf8(Option<String>),
+    #[from]
+    UInt16(Option<u16>),
+    #[from]
+    UInt32(Option<u32>),
+    #[from]
+    UInt64(Option<u64>),
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self { Value::Utf8(Some(v)) }
+}
+impl From<u16> for Value {
+    fn from(v: u16) -> Self { Value::UInt16(Some(v)) }
+}
+impl From<u32> for Value {
+    fn from(v: u32) -> Self { Value::UInt32(Some(v)) }
+}
+
+enum ColumnBuilder {
+    Utf8(StringBuilder),
+    UInt16(UInt16Builder),
+    UInt32(UInt32Builder),
+    UInt64(UInt64Builder),
+}
+
+impl ColumnBuilder {
+    fn with(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Utf8 => ColumnBuilder::Utf8(StringBuilder::new()),
+            DataType::UInt16 => ColumnBuilder::UInt16(UInt16Builder::new()),
+            DataType::UInt32 => ColumnBuilder::UInt32(UInt32Builder::new()),
+            DataType::UInt64 => ColumnBuilder::UInt64(UInt64Builder::new()),
+            _ => unreachable!("unsupported column type {data_type}"),
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        match (self, value) {
+            (ColumnBuilder::Utf8(b), Value::Utf8(val)) => b.append_option(val),
+            (ColumnBuilder::UInt16(b), Value::UInt16(val)) => b.append_option(val),
+            (ColumnBuilder::UInt32(b), Value::UInt32(val)) => b.append_option(val),
+            (ColumnBuilder::UInt64(b), Value::UInt64(val)) => b.append_option(val),
+            (_, value) => unreachable!("value {value:?} doesn't match column type"),
+        }
+    }
+
+    fn builder(&mut self) -> &mut dyn ArrayBuilder {
+        match self {
+            ColumnBuilder::Utf8(b) => b,
+            ColumnBuilder::UInt16(b) => b,
+            ColumnBuilder::UInt32(b) => b,
+            ColumnBuilder::UInt64(b) => b,
+        }
+    }
+}
+
+/// Builder accumulating rows of a record batch with a fixed schema.
+pub(super) struct BatchBuilder {
+    schema: SchemaRef,
+    columns: Vec<ColumnBuilder>,
+}
+
+impl BatchBuilder {
+    fn with(schema: SchemaRef) -> Self {
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| ColumnBuilder::with(field.data_type()))
+            .collect();
+        BatchBuilder { schema, columns }
+    }
+
+    pub(super) fn push_row(&mut self, row: impl IntoIterator<Item = Value>) {
+        let mut len = 0;
+        for (column, value) in self.columns.iter_mut().zip(row) {
+            column.push(value);
+            len += 1;
+        }
+        assert_eq!(len, self.columns.len(), "row doesn't match batch schema");
+    }
+
+    fn finish(mut self) -> RecordBatch {
+        let columns = self
+            .columns
+            .iter_mut()
+            .map(|column| column.builder().finish())
+            .collect();
+        RecordBatch::try_new(self.schema, columns).expect("columns match the schema")
+    }
+}
+
+/// Builder of [`ContractExport`], filled by the stock.
+pub(super) struct ContractExportBuilder {
+    contract_id: ContractId,
+    pub(super) allocations: BatchBuilder,
+    pub(super) operations: BatchBuilder,
+    pub(super) witnesses: BatchBuilder,
+}
+
+impl ContractExportBuilder {
+    pub(super) fn new(contract_id: ContractId) -> Self {
+        ContractExportBuilder {
+            contract_id,
+            allocations: BatchBuilder::with(allocation_schema()),
+            operations: BatchBuilder::with(operation_schema()),
+            witnesses: BatchBuilder::with(witness_schema()),
+        }
+    }
+
+    pub(super) fn finish(self) -> ContractExport {
+        ContractExport {
+            contract_id: self.contract_id,
+            allocations: self.allocations.finish(),
+            operations: self.operations.finish(),
+            witnesses: self.witnesses.finish(),
+        }
+    }
+}
+
+/// Arrow record batches with the data of a single contract.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ContractExport {
+    pub contract_id: ContractId,
+    pub allocations: RecordBatch,
+    pub operations: RecordBatch,
+    pub witnesses: RecordBatch,
+}
+
+impl ContractExport {
+    /// Returns all record batches of the export together with their names.
+    pub fn batches(&self) -> [(&'static str, &RecordBatch); 3] {
+        [
+            ("allocations", &self.allocations),
+            ("operations", &self.operations),
+            ("witnesses", &self.witnesses),
+        ]
+    }
+
+    /// Writes record batch as a Parquet file into the provided writer.
+    pub fn write_parquet<W: Write + Send>(
+        batch: &RecordBatch,
+        writer: W,
+    ) -> Result<W, ParquetError> {
+        let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+        writer.write(batch)?;
+        writer.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use arrow_array::{Array, UInt32Array};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn record_batches() {
+        let mut builder = ContractExportBuilder::new(ContractId::strict_dumb());
+        builder.witnesses.push_row([
+            Value::from(s!("contract")),
+            Value::from(s!("witness")),
+            Value::from(s!("bitcoin")),
+            Value::from(None::<String>),
+            Value::from(None::<u32>),
+            Value::from(2u32),
+        ]);
+        let export = builder.finish();
+        assert_eq!(export.witnesses.num_rows(), 1);
+        assert_eq!(export.allocations.num_rows(), 0);
+        assert_eq!(export.allocations.schema(), allocation_schema());
+        let height = export.witnesses.column_by_name("height").unwrap();
+        assert_eq!(height.null_count(), 1);
+
+        let data = ContractExport::write_parquet(&export.witnesses, vec![]).unwrap();
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(data))
+            .unwrap()
+            .build()
+            .unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert_eq!(batch, export.witnesses);
+        let bundles = batch.column_by_name("bundles").unwrap();
+        let bundles = bundles.as_any().downcast_ref::<UInt32Array>().unwrap();
+        assert_eq!(bundles.value(0), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn schema_mismatch() {
+        let mut builder = ContractExportBuilder::new(ContractId::strict_dumb());
+        builder.witnesses.push_row([Value::from(2u32)]);
+    }
+}
@@ -0,0 +1,172 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Probabilistic membership filters over operations and bundles known to the
+//! stash.
+//!
+//! Contracts with long histories make the checks whether an operation or a
+//! bundle is already known, which are performed for each operation during
+//! consignment acceptance, as slow as the lookups in the stash storage.
+//! Bloom filters kept in memory answer most of these checks for the new
+//! operations without touching the storage, keeping acceptance latency flat
+//! as the history grows.
+//!
+//! A filter never gives false negatives, thus an object absent from the filter
+//! is definitely absent from the stash. Objects present in the filter are
+//! looked up in the stash. Filters are not persisted and are rebuilt from the
+//! stash once enabled with [`Stock::enable_membership_filters`]. Rolled back
+//! writes may leave extra items in the filter, which only increases the rate
+//! of false positives.
+//!
+//! [`Stock::enable_membership_filters`]: super::Stock::enable_membership_filters
+
+use amplify::ByteArray;
+use rgb::{BundleId, OpId};
+
+/// Rate of false positives of the membership filters when the number of items
+/// doesn't exceed their capacity.
+pub const BLOOM_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Bloom filter over 32-byte identifiers.
+///
+/// The identifiers are tagged hashes, thus their bytes are used as the hash
+/// values directly, deriving filter positions with double hashing.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u32,
+    items: usize,
+}
+
+impl BloomFilter {
+    /// Constructs filter which has [`BLOOM_FALSE_POSITIVE_RATE`] for the given
+    /// number of items.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-capacity * BLOOM_FALSE_POSITIVE_RATE.ln() / (ln2 * ln2)).ceil() as usize;
+        let hashes = ((bits as f64 / capacity) * ln2).round().max(1.0) as u32;
+        BloomFilter {
+            bits: vec![0; bits.div_ceil(64)],
+            hashes,
+            items: 0,
+        }
+    }
+
+    /// Number of insertions into the filter, including the repeated ones.
+    pub fn len(&self) -> usize { self.items }
+
+    /// Detects whether no items were added to the filter.
+    pub fn is_empty(&self) -> bool { self.items == 0 }
+
+    /// Adds an identifier to the filter.
+    pub fn insert(&mut self, id: [u8; 32]) {
+        for pos in self.positions(id).collect::<Vec<_>>() {
+            self.bits[pos / 64] |= 1 << (pos % 64);
+        }
+        self.items += 1;
+    }
+
+    /// Detects whether the identifier may be present in the filter. Returns
+    /// `false` only if the identifier was never added.
+    pub fn may_contain(&self, id: [u8; 32]) -> bool {
+        self.positions(id)
+            .all(|pos| self.bits[pos / 64] & (1 << (pos % 64)) != 0)
+    }
+
+    fn positions(&self, id: [u8; 32]) -> impl Iterator<Item = usize> {
+        let width = self.bits.len() as u64 * 64;
+        let h1 = u64::from_le_bytes(id[..8].try_into().expect("fixed size"));
+        let h2 = u64::from_le_bytes(id[8..16].try_into().expect("fixed size")) | 1;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % width) as usize)
+    }
+}
+
+/// Membership filters over ids of the operations and bundles known to the
+/// stash.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct MembershipFilter {
+    operations: BloomFilter,
+    bundles: BloomFilter,
+}
+
+impl MembershipFilter {
+    /// Constructs empty filters for the expected number of operations and
+    /// bundles.
+    pub fn with_capacity(operations: usize, bundles: usize) -> Self {
+        MembershipFilter {
+            operations: BloomFilter::with_capacity(operations),
+            bundles: BloomFilter::with_capacity(bundles),
+        }
+    }
+
+    /// Adds an operation to the filter.
+    pub fn insert_operation(&mut self, opid: OpId) { self.operations.insert(opid.to_byte_array()) }
+
+    /// Adds a bundle to the filter.
+    pub fn insert_bundle(&mut self, bundle_id: BundleId) {
+        self.bundles.insert(bundle_id.to_byte_array())
+    }
+
+    /// Detects whether the operation may be known to the stash.
+    pub fn may_contain_operation(&self, opid: OpId) -> bool {
+        self.operations.may_contain(opid.to_byte_array())
+    }
+
+    /// Detects whether the bundle may be known to the stash.
+    pub fn may_contain_bundle(&self, bundle_id: BundleId) -> bool {
+        self.bundles.may_contain(bundle_id.to_byte_array())
+    }
+
+    /// Filter over operation ids.
+    pub fn operations(&self) -> &BloomFilter { &self.operations }
+
+    /// Filter over bundle ids.
+    pub fn bundles(&self) -> &BloomFilter { &self.bundles }
+}
+
+#[cfg(test)]
+mod test {
+    use commit_verify::{DigestExt, Sha256};
+
+    use super::*;
+
+    fn id(no: u32) -> [u8; 32] {
+        let mut hasher = Sha256::default();
+        hasher.input_raw(&no.to_le_bytes());
+        hasher.finish()
+    }
+
+    #[test]
+    fn bloom_filter() {
+        let mut filter = BloomFilter::with_capacity(1000);
+        assert!(filter.is_empty());
+        for no in 0..1000 {
+            filter.insert(id(no));
+        }
+        assert_eq!(filter.len(), 1000);
+        assert!((0..1000).all(|no| filter.may_contain(id(no))));
+        let false_positives = (1000..11000)
+            .filter(|no| filter.may_contain(id(*no)))
+            .count();
+        assert!(false_positives < 200, "{false_positives} false positives");
+    }
+}
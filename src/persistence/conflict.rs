@@ -0,0 +1,188 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conflicts between state transitions.
+//!
+//! State transitions spending the same allocation may reach the stock from
+//! different devices sharing the same wallet, or from a chain reorganization.
+//! Only one of them may get into the blockchain; the stock keeps all branches,
+//! but includes into the contract state only the one chosen for the conflict,
+//! and reports the conflict such that the caller may select another branch.
+
+use std::fmt::{self, Display, Formatter};
+
+use rgb::vm::WitnessOrd;
+use rgb::{BundleId, ContractId, OpId, Opout, XWitnessId};
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum ConflictError {
+    /// state transition {1} is not a branch of the conflict over {0}.
+    UnknownBranch(Opout, OpId),
+
+    /// state transition {0} can't be kept since it has no valid witness.
+    ArchivedBranch(OpId),
+}
+
+/// State transition spending a contested allocation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ConflictBranch {
+    pub opid: OpId,
+    pub bundle_id: BundleId,
+    /// Witness transactions of the transition together with their status, if
+    /// known to the contract state.
+    pub witnesses: Vec<(XWitnessId, Option<WitnessOrd>)>,
+}
+
+impl ConflictBranch {
+    /// Detects whether the branch has a witness which is not archived.
+    pub fn is_valid(&self) -> bool {
+        self.witnesses
+            .iter()
+            .any(|(_, ord)| ord.map(|ord| ord.is_valid()).unwrap_or(true))
+    }
+
+    /// Detects whether the branch has a mined witness.
+    pub fn is_mined(&self) -> bool {
+        self.witnesses
+            .iter()
+            .any(|(_, ord)| matches!(ord, Some(WitnessOrd::Mined(_))))
+    }
+}
+
+/// Allocation spent by multiple state transitions.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct Conflict {
+    pub contract_id: ContractId,
+    pub opout: Opout,
+    pub branches: Vec<ConflictBranch>,
+}
+
+impl Conflict {
+    /// Detects whether at most a single branch of the conflict remains valid.
+    pub fn is_resolved(&self) -> bool {
+        self.branches
+            .iter()
+            .filter(|branch| branch.is_valid())
+            .count()
+            <= 1
+    }
+
+    /// Returns the branch which must be kept: the only mined one, if any.
+    pub fn winner(&self) -> Option<&ConflictBranch> {
+        let mut mined = self.branches.iter().filter(|branch| branch.is_mined());
+        match (mined.next(), mined.next()) {
+            (Some(branch), None) => Some(branch),
+            _ => None,
+        }
+    }
+
+    pub fn branch(&self, opid: OpId) -> Option<&ConflictBranch> {
+        self.branches.iter().find(|branch| branch.opid == opid)
+    }
+}
+
+/// Report on conflicting state transitions known to the stock.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ConflictReport {
+    pub conflicts: Vec<Conflict>,
+}
+
+impl ConflictReport {
+    pub fn is_empty(&self) -> bool { self.conflicts.is_empty() }
+
+    /// Returns conflicts where multiple branches remain valid.
+    pub fn unresolved(&self) -> impl Iterator<Item = &Conflict> {
+        self.conflicts
+            .iter()
+            .filter(|conflict| !conflict.is_resolved())
+    }
+
+    pub fn conflict(&self, opout: Opout) -> Option<&Conflict> {
+        self.conflicts
+            .iter()
+            .find(|conflict| conflict.opout == opout)
+    }
+}
+
+impl Display for ConflictReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for conflict in &self.conflicts {
+            let status = if conflict.is_resolved() { "resolved" } else { "unresolved" };
+            writeln!(f, "{} of {} ({status}):", conflict.opout, conflict.contract_id)?;
+            for branch in &conflict.branches {
+                let state = if branch.is_valid() { "valid" } else { "archived" };
+                writeln!(f, "  {} in bundle {} ({state})", branch.opid, branch.bundle_id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rgb::vm::WitnessPos;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn conflict_resolution() {
+        let branch = |no: u8, ord: Option<WitnessOrd>| ConflictBranch {
+            opid: OpId::from([no; 32]),
+            bundle_id: BundleId::from([no; 32]),
+            witnesses: vec![(XWitnessId::strict_dumb(), ord)],
+        };
+        let mined = WitnessOrd::Mined(WitnessPos::new(100, 1231006505).unwrap());
+        let mut conflict = Conflict {
+            contract_id: ContractId::strict_dumb(),
+            opout: Opout::strict_dumb(),
+            branches: vec![branch(1, Some(WitnessOrd::Tentative)), branch(2, None)],
+        };
+        assert!(!conflict.is_resolved());
+        assert_eq!(conflict.winner(), None);
+
+        conflict.branches[1] = branch(2, Some(mined));
+        assert_eq!(conflict.winner().unwrap().opid, OpId::from([2u8; 32]));
+
+        conflict.branches[0] = branch(1, Some(WitnessOrd::Archived));
+        assert!(conflict.is_resolved());
+        let report = ConflictReport {
+            conflicts: vec![conflict],
+        };
+        assert_eq!(report.unresolved().count(), 0);
+    }
+}
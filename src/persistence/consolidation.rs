@@ -0,0 +1,135 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consolidation of fragmented allocations.
+//!
+//! Wallets receiving many small payments end up with the state fragmented
+//! over many outputs, each requiring a separate witness transaction input
+//! when spent. Consolidation merges such allocations into a single output
+//! with a self-transfer. Since the history of the consolidated allocation is
+//! a union of the histories of the merged ones, allocations are merged only
+//! while their histories overlap enough for the future consignments not to
+//! grow beyond the policy limit.
+
+use bp::seals::txout::CloseMethod;
+use bp::Vout;
+use invoice::Amount;
+use rgb::Opout;
+use strict_encoding::FieldName;
+
+/// Policy for selecting allocations to consolidate.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ConsolidationPolicy {
+    pub method: CloseMethod,
+    /// Witness transaction output receiving the consolidated state.
+    pub vout: Vout,
+    /// Name of the consolidated assignment; if not provided, the default
+    /// assignment of the interface is used.
+    pub assignment: Option<FieldName>,
+    /// Minimal number of allocations worth consolidating.
+    pub min_allocations: usize,
+    /// Maximal number of allocations consolidated with a single operation.
+    pub max_allocations: usize,
+    /// Allocations above this amount are left untouched.
+    pub max_amount: Option<Amount>,
+    /// Maximal size (in bytes) of the history which future consignments of
+    /// the consolidated allocation will carry in excess to the largest
+    /// history of the merged allocations.
+    pub max_overhead: usize,
+}
+
+impl ConsolidationPolicy {
+    /// Constructs policy merging from 2 and up to 32 allocations of any amount
+    /// while the history overhead stays below 64 kiB.
+    pub fn with(method: CloseMethod, vout: impl Into<Vout>) -> Self {
+        ConsolidationPolicy {
+            method,
+            vout: vout.into(),
+            assignment: None,
+            min_allocations: 2,
+            max_allocations: 32,
+            max_amount: None,
+            max_overhead: u16::MAX as usize,
+        }
+    }
+}
+
+/// Report on a composed consolidation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ConsolidationReport {
+    /// Merged allocations.
+    pub merged: Vec<Opout>,
+    /// Number of the merged outputs.
+    pub outputs: usize,
+    /// Total amount of the consolidated allocation.
+    pub amount: Amount,
+    /// Number of witness bundles in the history of the consolidated
+    /// allocation.
+    pub history_bundles: usize,
+    /// Size (in bytes) of the history of the consolidated allocation,
+    /// excluding the consolidation operation itself.
+    pub history_size: usize,
+    /// Size (in bytes) of the largest history of the merged allocations.
+    pub max_history_size: usize,
+    /// Total size (in bytes) of the histories of the merged allocations, as
+    /// they would be consigned by sending each of them separately.
+    pub separate_history_size: usize,
+}
+
+impl ConsolidationReport {
+    /// Number of witness transaction inputs saved in the future sends.
+    pub fn inputs_saved(&self) -> usize { self.outputs.saturating_sub(1) }
+
+    /// Size of the history which consignments of the consolidated allocation
+    /// carry in excess to the largest history of the merged allocations.
+    pub fn overhead(&self) -> usize { self.history_size - self.max_history_size }
+
+    /// Size of the consignment data saved by sending the consolidated
+    /// allocation at once instead of sending the merged allocations
+    /// separately.
+    pub fn size_savings(&self) -> usize { self.separate_history_size - self.history_size }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn report_economics() {
+        let report = ConsolidationReport {
+            merged: vec![],
+            outputs: 3,
+            amount: Amount::from(100u64),
+            history_bundles: 4,
+            history_size: 1000,
+            max_history_size: 800,
+            separate_history_size: 2200,
+        };
+        assert_eq!(report.inputs_saved(), 2);
+        assert_eq!(report.overhead(), 200);
+        assert_eq!(report.size_savings(), 1200);
+    }
+}
@@ -0,0 +1,170 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fee bumping of stuck witness transactions with CPFP.
+//!
+//! A receiver of a transfer which witness transaction is stuck in the mempool
+//! with a low fee may accelerate it by spending its own output of the witness
+//! transaction with a high-fee child transaction (child pays for parent). The
+//! output carries the received RGB state, thus the child transaction must be
+//! a witness transaction itself, re-assigning the state to one of its own
+//! outputs; otherwise the state would be lost.
+//!
+//! [`Stock::compose_cpfp`] composes the batch of state transitions for the
+//! child, together with the [`CpfpPackage`] describing the fees required to
+//! reach the target fee rate. The wallet (UTXO provider) adds funding inputs
+//! and the change, constructs and signs the child transaction and passes its
+//! fascia to [`Stock::finalize_cpfp`]. Once the package is mined,
+//! [`Stock::cpfp_status`] updates the status of both witness transactions in
+//! the contract state.
+//!
+//! [`Stock::compose_cpfp`]: super::Stock::compose_cpfp
+//! [`Stock::finalize_cpfp`]: super::Stock::finalize_cpfp
+//! [`Stock::cpfp_status`]: super::Stock::cpfp_status
+
+use bp::{Sats, Vout};
+use rgb::{XOutpoint, XOutputSeal, XWitnessId};
+
+use super::FasciaError;
+use crate::containers::TransitionInfoError;
+use crate::interface::BuilderError;
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum CpfpError {
+    /// witness transaction {0} is not present in the mempool.
+    NotInMempool(XWitnessId),
+
+    /// witness transaction {0} already has a fee rate reaching the target.
+    NotStuck(XWitnessId),
+
+    /// witness transaction {0} has no outputs with the state owned by the
+    /// wallet.
+    NoOutputs(XWitnessId),
+
+    /// unable to retrieve mempool information for {0}: {1}
+    Broadcaster(XWitnessId, String),
+
+    /// child transaction {0} doesn't spend output {1} of the parent witness
+    /// transaction.
+    NotSpent(XWitnessId, XOutpoint),
+
+    #[from]
+    #[display(inner)]
+    Transition(TransitionInfoError),
+
+    #[from]
+    #[display(inner)]
+    Builder(BuilderError),
+
+    #[from]
+    #[display(inner)]
+    Fascia(FasciaError),
+}
+
+/// Parent witness transaction and fee requirements for the CPFP child
+/// transaction.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct CpfpPackage {
+    /// Stuck witness transaction.
+    pub parent: XWitnessId,
+    /// Outputs of the parent transaction which must be spent by the child.
+    pub outputs: Vec<XOutputSeal>,
+    /// Output of the child transaction receiving the state.
+    pub vout: Vout,
+    /// Fees of the parent transaction and all of its unconfirmed ancestors.
+    pub ancestor_fee: Sats,
+    /// Size of the parent transaction and all of its unconfirmed ancestors.
+    pub ancestor_vsize: u32,
+    /// Target fee rate of the package, in sats per vbyte.
+    pub fee_rate: f64,
+}
+
+impl CpfpPackage {
+    /// Computes fee which must be paid by the child transaction of the given
+    /// size for the whole package to reach the target fee rate.
+    pub fn child_fee(&self, child_vsize: u32) -> Sats {
+        let vsize = self.ancestor_vsize.saturating_add(child_vsize);
+        let fee = (self.fee_rate * vsize as f64).ceil() as u64;
+        Sats::from_sats(fee).saturating_sub(self.ancestor_fee)
+    }
+
+    /// Computes fee rate of the package with the child transaction, in sats
+    /// per vbyte.
+    pub fn package_fee_rate(&self, child_fee: Sats, child_vsize: u32) -> f64 {
+        let fee = self.ancestor_fee.saturating_add(child_fee);
+        let vsize = self.ancestor_vsize.saturating_add(child_vsize);
+        fee.sats() as f64 / vsize.max(1) as f64
+    }
+}
+
+/// Status of a CPFP package.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display(lowercase)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum CpfpStatus {
+    /// Parent or child transaction is not mined yet.
+    Pending,
+    /// Both parent and child transactions are mined.
+    Confirmed,
+    /// Parent or child transaction was excluded from the mempool or the
+    /// blockchain.
+    Dropped,
+}
+
+#[cfg(test)]
+mod test {
+    use rgb::XChain;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn child_fee() {
+        let package = CpfpPackage {
+            parent: XChain::Bitcoin(strict_dumb!()),
+            outputs: vec![XOutputSeal::strict_dumb()],
+            vout: Vout::from_u32(0),
+            ancestor_fee: Sats::from_sats(200u64),
+            ancestor_vsize: 200,
+            fee_rate: 10.0,
+        };
+        let fee = package.child_fee(100);
+        assert_eq!(fee, Sats::from_sats(2800u64));
+        assert_eq!(package.package_fee_rate(fee, 100), 10.0);
+        assert!(package.package_fee_rate(Sats::ZERO, 100) < 1.0);
+
+        let package = CpfpPackage {
+            fee_rate: 0.5,
+            ..package
+        };
+        assert_eq!(package.child_fee(100), Sats::ZERO);
+    }
+}
@@ -19,20 +19,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 
-use amplify::confinement::U32 as U32MAX;
 use nonasync::persistence::{PersistenceError, PersistenceProvider};
-use strict_encoding::{StrictDeserialize, StrictSerialize};
+use strict_encoding::StrictSerialize;
 
-use crate::persistence::{MemIndex, MemStash, MemState};
+use crate::migrations::{self, DataKind};
+use crate::persistence::{ArchiveProvider, MemArchive, MemIndex, MemStash, MemState};
 
+/// File store of the stock. Each file starts with the version header; files
+/// written without the header by the previous versions are upgraded on load
+/// and are rewritten with the header once the stock is stored.
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct FsBinStore {
     pub stash: PathBuf,
     pub state: PathBuf,
     pub index: PathBuf,
+    /// State of the archived contracts, which is read only once the archive
+    /// is accessed.
+    pub archive: PathBuf,
 }
 
 impl FsBinStore {
@@ -45,49 +51,80 @@ impl FsBinStore {
         state.push("state.dat");
         let mut index = path.clone();
         index.push("index.dat");
+        let mut archive = path.clone();
+        archive.push("archive.dat");
 
         Ok(Self {
             stash,
             state,
             index,
+            archive,
         })
     }
 }
+
+fn write(
+    path: &Path,
+    kind: DataKind,
+    object: &impl StrictSerialize,
+) -> Result<(), PersistenceError> {
+    let data = migrations::serialize_stock(kind, object).map_err(PersistenceError::with)?;
+    fs::write(path, data).map_err(PersistenceError::with)
+}
+
 impl PersistenceProvider<MemStash> for FsBinStore {
     fn load(&self) -> Result<MemStash, PersistenceError> {
-        MemStash::strict_deserialize_from_file::<U32MAX>(&self.stash)
-            .map_err(PersistenceError::with)
+        let data = fs::read(&self.stash).map_err(PersistenceError::with)?;
+        let (_, stash) = migrations::load_stash(&data).map_err(PersistenceError::with)?;
+        Ok(stash)
     }
 
     fn store(&self, object: &MemStash) -> Result<(), PersistenceError> {
-        object
-            .strict_serialize_to_file::<U32MAX>(&self.stash)
-            .map_err(PersistenceError::with)
+        write(&self.stash, DataKind::Stash, object)
     }
 }
 
 impl PersistenceProvider<MemState> for FsBinStore {
     fn load(&self) -> Result<MemState, PersistenceError> {
-        MemState::strict_deserialize_from_file::<U32MAX>(&self.state)
-            .map_err(PersistenceError::with)
+        let data = fs::read(&self.state).map_err(PersistenceError::with)?;
+        let (_, state) = migrations::load_state(&data).map_err(PersistenceError::with)?;
+        Ok(state.with_archive_provider(self.clone()))
     }
 
     fn store(&self, object: &MemState) -> Result<(), PersistenceError> {
-        object
-            .strict_serialize_to_file::<U32MAX>(&self.state)
-            .map_err(PersistenceError::with)
+        // The archive is written first, such that the state never references
+        // contracts absent in the archive
+        if let Some(archive) = object.dirty_archive() {
+            write(&self.archive, DataKind::Archive, archive)?;
+            object.mark_archive_stored();
+        }
+        write(&self.state, DataKind::State, object)
+    }
+}
+
+impl ArchiveProvider for FsBinStore {
+    fn load_archive(&self) -> Result<MemArchive, PersistenceError> {
+        if !self.archive.exists() {
+            return Ok(MemArchive::default());
+        }
+        let data = fs::read(&self.archive).map_err(PersistenceError::with)?;
+        migrations::load_archive(&data).map_err(PersistenceError::with)
     }
 }
 
 impl PersistenceProvider<MemIndex> for FsBinStore {
     fn load(&self) -> Result<MemIndex, PersistenceError> {
-        MemIndex::strict_deserialize_from_file::<U32MAX>(&self.index)
-            .map_err(PersistenceError::with)
+        let data = fs::read(&self.index).map_err(PersistenceError::with)?;
+        let format =
+            migrations::detect_stock(DataKind::Index, &data).map_err(PersistenceError::with)?;
+        // Previous versions of the index are completed with the data from the
+        // stash
+        let stash = if format.is_current() { MemStash::in_memory() } else { self.load()? };
+        let (_, index) = migrations::load_index(&data, &stash).map_err(PersistenceError::with)?;
+        Ok(index)
     }
 
     fn store(&self, object: &MemIndex) -> Result<(), PersistenceError> {
-        object
-            .strict_serialize_to_file::<U32MAX>(&self.index)
-            .map_err(PersistenceError::with)
+        write(&self.index, DataKind::Index, object)
     }
 }
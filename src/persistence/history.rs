@@ -0,0 +1,300 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Operation graph of a contract history.
+//!
+//! Contract history forms a directed acyclic graph of the contract
+//! operations, where edges connect operations assigning state (or declaring
+//! valencies) with operations spending (or redeeming) them. The graph can be
+//! rendered in DOT and GraphML formats for visualizing multi-branch histories
+//! with the standard graph tools.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use amplify::{ByteArray, Wrapper};
+use baid64::DisplayBaid64;
+use invoice::Amount;
+use rgb::{
+    ContractId, Extension, Genesis, OpFullType, OpId, Operation, Opout, Transition, ValencyType,
+    XWitnessId,
+};
+
+/// Operation of the contract history.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct HistoryNode {
+    pub opid: OpId,
+    pub ty: OpFullType,
+    /// Witness transaction of a state transition.
+    pub witness_id: Option<XWitnessId>,
+    /// Revealed amounts of the fungible state assigned by the operation.
+    pub amounts: Vec<Amount>,
+}
+
+impl HistoryNode {
+    /// Returns mnemonic of the operation id, used to distinguish operations
+    /// visually.
+    pub fn mnemonic(&self) -> String { OpMnemonic(self.opid).to_baid64_mnemonic() }
+
+    fn label(&self) -> String {
+        let amounts = self
+            .amounts
+            .iter()
+            .map(Amount::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{}\\n{}\\n{amounts}", self.mnemonic(), self.ty)
+    }
+}
+
+struct OpMnemonic(OpId);
+
+impl DisplayBaid64 for OpMnemonic {
+    const HRI: &'static str = "rgb:op";
+    const CHUNKING: bool = false;
+    const PREFIX: bool = false;
+    const EMBED_CHECKSUM: bool = false;
+    const MNEMONIC: bool = true;
+    fn to_baid64_payload(&self) -> [u8; 32] { self.0.to_byte_array() }
+}
+
+/// Relation between two operations of the contract history.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum HistoryLink {
+    /// State assigned by the operation is spent.
+    Spend(Opout),
+    /// Valency declared by the operation is redeemed.
+    Redeem(ValencyType),
+}
+
+/// Edge of the contract history graph.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct HistoryEdge {
+    pub from: OpId,
+    pub to: OpId,
+    pub link: HistoryLink,
+    /// Witness transaction of the spending state transition.
+    pub witness_id: Option<XWitnessId>,
+}
+
+impl HistoryEdge {
+    fn label(&self) -> String {
+        let mut label = match self.link {
+            HistoryLink::Spend(opout) => format!("{}/{}", opout.ty.to_inner(), opout.no),
+            HistoryLink::Redeem(ty) => format!("valency {}", ty.to_inner()),
+        };
+        if let Some(witness_id) = self.witness_id {
+            label.push_str(&format!("\\n{witness_id}"));
+        }
+        label
+    }
+}
+
+/// Graph of the contract operations known to the stock.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ContractHistory {
+    pub contract_id: ContractId,
+    pub nodes: BTreeMap<OpId, HistoryNode>,
+    pub edges: Vec<HistoryEdge>,
+}
+
+impl ContractHistory {
+    pub fn new(contract_id: ContractId) -> Self {
+        ContractHistory {
+            contract_id,
+            nodes: empty!(),
+            edges: empty!(),
+        }
+    }
+
+    pub fn add_genesis(&mut self, genesis: &Genesis) { self.add_node(genesis, None); }
+
+    pub fn add_transition(&mut self, transition: &Transition, witness_id: XWitnessId) {
+        let to = transition.id();
+        for input in transition.inputs().iter() {
+            self.edges.push(HistoryEdge {
+                from: input.prev_out.op,
+                to,
+                link: HistoryLink::Spend(input.prev_out),
+                witness_id: Some(witness_id),
+            });
+        }
+        self.add_node(transition, Some(witness_id));
+    }
+
+    pub fn add_extension(&mut self, extension: &Extension) {
+        let to = extension.id();
+        for (ty, from) in extension.redeemed() {
+            self.edges.push(HistoryEdge {
+                from: *from,
+                to,
+                link: HistoryLink::Redeem(*ty),
+                witness_id: None,
+            });
+        }
+        self.add_node(extension, None);
+    }
+
+    fn add_node(&mut self, op: &dyn Operation, witness_id: Option<XWitnessId>) {
+        let amounts = op
+            .assignments()
+            .flat()
+            .values()
+            .flat_map(|assigns| assigns.as_fungible().iter())
+            .filter_map(|assign| assign.as_revealed_state())
+            .map(|state| Amount::from(state.value.as_u64()))
+            .collect();
+        let opid = op.id();
+        self.nodes.insert(opid, HistoryNode {
+            opid,
+            ty: op.full_type(),
+            witness_id,
+            amounts,
+        });
+    }
+
+    /// Returns operations referenced by the history, which are not known to
+    /// the stock (for instance, concealed ones).
+    pub fn unknown_operations(&self) -> BTreeSet<OpId> {
+        self.edges
+            .iter()
+            .map(|edge| edge.from)
+            .filter(|opid| !self.nodes.contains_key(opid))
+            .collect()
+    }
+
+    /// Renders the graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!("digraph \"{}\" {{\n", self.contract_id);
+        for (opid, node) in &self.nodes {
+            let shape = match node.ty {
+                OpFullType::Genesis => "doubleoctagon",
+                OpFullType::StateTransition(_) => "box",
+                OpFullType::StateExtension(_) => "ellipse",
+            };
+            writeln!(dot, "  \"{opid}\" [label=\"{}\", shape={shape}];", node.label())
+                .expect("writing to string");
+        }
+        for opid in self.unknown_operations() {
+            writeln!(dot, "  \"{opid}\" [label=\"unknown\", style=dashed];")
+                .expect("writing to string");
+        }
+        for edge in &self.edges {
+            writeln!(dot, "  \"{}\" -> \"{}\" [label=\"{}\"];", edge.from, edge.to, edge.label())
+                .expect("writing to string");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph in GraphML format.
+    pub fn to_graphml(&self) -> String {
+        let mut xml = s!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        for (id, domain) in [
+            ("mnemonic", "node"),
+            ("type", "node"),
+            ("amounts", "node"),
+            ("link", "edge"),
+            ("witness", "all"),
+        ] {
+            writeln!(
+                xml,
+                "  <key id=\"{id}\" for=\"{domain}\" attr.name=\"{id}\" attr.type=\"string\"/>"
+            )
+            .expect("writing to string");
+        }
+        writeln!(xml, "  <graph id=\"{}\" edgedefault=\"directed\">", self.contract_id)
+            .expect("writing to string");
+        let data = |xml: &mut String, key: &str, value: &str| {
+            writeln!(xml, "      <data key=\"{key}\">{value}</data>").expect("writing to string");
+        };
+        for (opid, node) in &self.nodes {
+            writeln!(xml, "    <node id=\"{opid}\">").expect("writing to string");
+            data(&mut xml, "mnemonic", &node.mnemonic());
+            data(&mut xml, "type", &node.ty.to_string());
+            let amounts = node
+                .amounts
+                .iter()
+                .map(Amount::to_string)
+                .collect::<Vec<_>>();
+            data(&mut xml, "amounts", &amounts.join(","));
+            if let Some(witness_id) = node.witness_id {
+                data(&mut xml, "witness", &witness_id.to_string());
+            }
+            xml.push_str("    </node>\n");
+        }
+        for opid in self.unknown_operations() {
+            writeln!(xml, "    <node id=\"{opid}\"/>").expect("writing to string");
+        }
+        for edge in &self.edges {
+            writeln!(xml, "    <edge source=\"{}\" target=\"{}\">", edge.from, edge.to)
+                .expect("writing to string");
+            let link = match edge.link {
+                HistoryLink::Spend(opout) => opout.to_string(),
+                HistoryLink::Redeem(ty) => format!("valency {}", ty.to_inner()),
+            };
+            data(&mut xml, "link", &link);
+            if let Some(witness_id) = edge.witness_id {
+                data(&mut xml, "witness", &witness_id.to_string());
+            }
+            xml.push_str("    </edge>\n");
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rgb::{AssignmentType, Input};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn history_graph() {
+        let genesis = Genesis::strict_dumb();
+        let genesis_id = genesis.id();
+        let mut transition = Transition::strict_dumb();
+        let prev_out = Opout::new(genesis_id, AssignmentType::with(1), 0);
+        transition.inputs.push(Input::with(prev_out)).unwrap();
+        let witness_id = XWitnessId::strict_dumb();
+
+        let mut history = ContractHistory::new(genesis.contract_id());
+        history.add_transition(&transition, witness_id);
+        assert_eq!(history.unknown_operations(), bset![genesis_id]);
+        history.add_genesis(&genesis);
+        assert!(history.unknown_operations().is_empty());
+        assert_eq!(history.edges.len(), 1);
+        assert_eq!(history.edges[0].link, HistoryLink::Spend(prev_out));
+
+        let dot = history.to_dot();
+        assert!(dot.starts_with("digraph"));
+        assert!(dot.contains(&format!("\"{genesis_id}\" -> \"{}\"", transition.id())));
+        assert!(dot.contains(&history.nodes[&genesis_id].mnemonic()));
+
+        let graphml = history.to_graphml();
+        assert_eq!(graphml.matches("<node ").count(), 2);
+        assert!(graphml.contains(&format!("<edge source=\"{genesis_id}\"")));
+        assert!(graphml.contains(&witness_id.to_string()));
+    }
+}
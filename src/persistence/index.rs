@@ -19,7 +19,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fmt::Debug;
 
@@ -32,7 +32,7 @@ use rgb::{
 };
 
 use crate::containers::{ConsignmentExt, ToWitnessId, WitnessBundle};
-use crate::persistence::{MemError, StoreTransaction};
+use crate::persistence::{MemError, MemIndex, StoreTransaction};
 use crate::SecretSeal;
 
 #[derive(Debug, Display, Error, From)]
@@ -160,22 +160,37 @@ impl<P: IndexProvider> Index<P> {
         &mut self,
         consignment: impl ConsignmentExt,
     ) -> Result<(), IndexError<P>> {
-        let contract_id = consignment.contract_id();
+        self.index_parts(
+            consignment.contract_id(),
+            consignment.genesis(),
+            consignment.extensions(),
+            consignment.bundled_witnesses().map(
+                |WitnessBundle {
+                     pub_witness,
+                     bundle,
+                     anchor: _,
+                 }| (pub_witness.to_witness_id(), bundle),
+            ),
+        )
+    }
 
+    /// Indexes contract operations, each transition bundle being accompanied
+    /// by the id of its witness.
+    pub(super) fn index_parts<'op>(
+        &mut self,
+        contract_id: ContractId,
+        genesis: &Genesis,
+        extensions: impl IntoIterator<Item = &'op Extension>,
+        bundles: impl IntoIterator<Item = (XWitnessId, &'op TransitionBundle)>,
+    ) -> Result<(), IndexError<P>> {
         self.provider
             .register_contract(contract_id)
             .map_err(IndexError::WriteProvider)?;
-        self.index_genesis(contract_id, consignment.genesis())?;
-        for extension in consignment.extensions() {
+        self.index_genesis(contract_id, genesis)?;
+        for extension in extensions {
             self.index_extension(contract_id, extension)?;
         }
-        for WitnessBundle {
-            pub_witness,
-            bundle,
-            anchor: _,
-        } in consignment.bundled_witnesses()
-        {
-            let witness_id = pub_witness.to_witness_id();
+        for (witness_id, bundle) in bundles {
             self.index_bundle(contract_id, bundle, witness_id)?;
         }
 
@@ -249,6 +264,9 @@ impl<P: IndexProvider> Index<P> {
 
         for (opid, transition) in &bundle.known_transitions {
             self.provider.register_operation(*opid, bundle_id)?;
+            for input in &transition.inputs {
+                self.provider.register_spending(input.prev_out, *opid)?;
+            }
             for (type_id, assign) in transition.assignments.iter() {
                 match assign {
                     TypedAssigns::Declarative(vec) => {
@@ -337,6 +355,39 @@ impl<P: IndexProvider> Index<P> {
     ) -> Result<(impl Iterator<Item = XWitnessId> + '_, ContractId), IndexError<P>> {
         Ok(self.provider.bundle_info(bundle_id)?)
     }
+
+    pub(super) fn contract_bundle_ids(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<BTreeSet<BundleId>, IndexError<P>> {
+        self.provider
+            .contract_bundle_ids(contract_id)
+            .map_err(IndexError::ReadProvider)
+    }
+
+    pub(super) fn spenders(&self, opout: Opout) -> Result<BTreeSet<OpId>, IndexError<P>> {
+        self.provider
+            .spenders(opout)
+            .map_err(IndexError::ReadProvider)
+    }
+
+    pub(super) fn contested_opouts(
+        &self,
+    ) -> Result<BTreeMap<Opout, BTreeSet<OpId>>, IndexError<P>> {
+        self.provider
+            .contested_opouts()
+            .map_err(IndexError::ReadProvider)
+    }
+
+    pub(super) fn replace_contract(
+        &mut self,
+        contract_id: ContractId,
+        rebuilt: &MemIndex,
+    ) -> Result<(), IndexError<P>> {
+        self.provider
+            .replace_contract(contract_id, rebuilt)
+            .map_err(IndexError::WriteProvider)
+    }
 }
 
 impl<P: IndexProvider> StoreTransaction for Index<P> {
@@ -392,6 +443,19 @@ pub trait IndexReadProvider {
         &self,
         bundle_id: BundleId,
     ) -> Result<(impl Iterator<Item = XWitnessId>, ContractId), IndexReadError<Self::Error>>;
+
+    /// Returns ids of all bundles indexed for the contract.
+    fn contract_bundle_ids(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<BTreeSet<BundleId>, Self::Error>;
+
+    /// Returns state transitions spending the allocation.
+    fn spenders(&self, opout: Opout) -> Result<BTreeSet<OpId>, Self::Error>;
+
+    /// Returns allocations spent by more than a single state transition,
+    /// together with the transitions spending them.
+    fn contested_opouts(&self) -> Result<BTreeMap<Opout, BTreeSet<OpId>>, Self::Error>;
 }
 
 pub trait IndexWriteProvider: StoreTransaction<TransactionErr = Self::Error> {
@@ -412,6 +476,14 @@ pub trait IndexWriteProvider: StoreTransaction<TransactionErr = Self::Error> {
         bundle_id: BundleId,
     ) -> Result<bool, IndexWriteError<Self::Error>>;
 
+    /// Registers state transition spending the allocation, returning whether
+    /// the spending was not known before.
+    fn register_spending(
+        &mut self,
+        opout: Opout,
+        opid: OpId,
+    ) -> Result<bool, IndexWriteError<Self::Error>>;
+
     fn index_genesis_assignments<State: ExposedState>(
         &mut self,
         contract_id: ContractId,
@@ -428,4 +500,12 @@ pub trait IndexWriteProvider: StoreTransaction<TransactionErr = Self::Error> {
         type_id: AssignmentType,
         witness_id: XWitnessId,
     ) -> Result<(), IndexWriteError<Self::Error>>;
+
+    /// Replaces all index entries of the contract with the ones rebuilt from
+    /// the contract operations.
+    fn replace_contract(
+        &mut self,
+        contract_id: ContractId,
+        rebuilt: &MemIndex,
+    ) -> Result<(), Self::Error>;
 }
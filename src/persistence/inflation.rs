@@ -0,0 +1,409 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of the monetary policy of RGB20 assets.
+//!
+//! Inflatable RGB20 assets issue additional supply by spending inflation
+//! allowance, which is a fungible right created in the genesis. The report
+//! reconstructs the tree of the inflation rights from the contract operations
+//! known to the stock: who holds them, how much can still be issued, and what
+//! was issued when, flagging operations which break the policy.
+
+use std::collections::BTreeMap;
+
+use amplify::confinement::U16;
+use amplify::Wrapper;
+use invoice::Amount;
+use rgb::vm::WitnessOrd;
+use rgb::{
+    AssignmentType, ContractId, GlobalStateType, OpId, OpType, Operation, Opout, XOutputSeal,
+    XWitnessId,
+};
+use strict_encoding::StrictDeserialize;
+
+/// Name of the owned state with the inflation allowance of an RGB20 asset.
+pub const RGB20_ASSIGNMENT_INFLATION: &str = "inflationAllowance";
+
+/// Violations of the asset monetary policy.
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display(doc_comments)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum InflationViolation {
+    /// operation {opid} issues and re-assigns {used} of the inflation allowance
+    /// while spending only {allowance}.
+    AllowanceExceeded {
+        opid: OpId,
+        allowance: Amount,
+        used: Amount,
+    },
+
+    /// issued supply {issued} exceeds the maximal supply {max}.
+    SupplyExceeded { issued: Amount, max: Amount },
+
+    /// amount of the inflation allowance {0} is concealed and can't be verified.
+    ConcealedAllowance(Opout),
+
+    /// operation {0} spends inflation allowance {1} unknown to the stock.
+    UnknownAllowance(OpId, Opout),
+
+    /// operation {0} declares invalid issued supply.
+    InvalidSupply(OpId),
+}
+
+/// Inflation right created by a contract operation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct InflationRight {
+    pub opout: Opout,
+    /// Amount of the allowance, unless it is concealed.
+    pub amount: Option<Amount>,
+    /// Holder of the right, if the seal is known to the stock.
+    pub seal: Option<XOutputSeal>,
+    /// Operation which has spent the right.
+    pub spent_by: Option<OpId>,
+}
+
+impl InflationRight {
+    pub fn is_spent(&self) -> bool { self.spent_by.is_some() }
+}
+
+/// Supply issued by a contract operation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct Issuance {
+    pub opid: OpId,
+    pub amount: Amount,
+    /// Witness of the issuance; `None` for the genesis.
+    pub witness_id: Option<XWitnessId>,
+    pub witness_ord: Option<WitnessOrd>,
+}
+
+/// Report on the inflation of an RGB20 asset.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct InflationReport {
+    pub contract_id: ContractId,
+    /// Supply issued in the genesis together with the inflation allowance
+    /// created by it.
+    pub max_supply: Amount,
+    /// Supply issued by the genesis and all known issuances.
+    pub issued_supply: Amount,
+    /// Inflation allowance which is not spent yet.
+    pub remaining_allowance: Amount,
+    /// Issuances ordered by their witnesses, starting with the genesis.
+    pub issuances: Vec<Issuance>,
+    pub rights: Vec<InflationRight>,
+    pub violations: Vec<InflationViolation>,
+}
+
+impl InflationReport {
+    /// Detects whether the known contract operations follow the monetary
+    /// policy of the asset.
+    pub fn is_intact(&self) -> bool { self.violations.is_empty() }
+
+    /// Returns rights which are not spent yet.
+    pub fn unspent_rights(&self) -> impl Iterator<Item = &InflationRight> {
+        self.rights.iter().filter(|right| !right.is_spent())
+    }
+}
+
+struct AuditOp {
+    opid: OpId,
+    is_genesis: bool,
+    issued: Option<Amount>,
+    inputs: Vec<Opout>,
+    witness_id: Option<XWitnessId>,
+    witness_ord: Option<WitnessOrd>,
+}
+
+/// Collects contract operations and produces an [`InflationReport`].
+pub(super) struct InflationAudit {
+    contract_id: ContractId,
+    supply_type: GlobalStateType,
+    allowance_type: AssignmentType,
+    ops: Vec<AuditOp>,
+    rights: BTreeMap<Opout, InflationRight>,
+}
+
+impl InflationAudit {
+    pub fn new(
+        contract_id: ContractId,
+        supply_type: GlobalStateType,
+        allowance_type: AssignmentType,
+    ) -> Self {
+        InflationAudit {
+            contract_id,
+            supply_type,
+            allowance_type,
+            ops: vec![],
+            rights: none!(),
+        }
+    }
+
+    pub fn add_operation(
+        &mut self,
+        op: &dyn Operation,
+        witness_id: Option<XWitnessId>,
+        witness_ord: Option<WitnessOrd>,
+    ) {
+        let opid = op.id();
+        let issued = op
+            .globals()
+            .get(&self.supply_type)
+            .into_iter()
+            .flat_map(|values| values.iter())
+            .map(|data| Amount::from_strict_serialized::<U16>(data.to_inner()))
+            .try_fold(Amount::ZERO, |sum, amount| sum.checked_add(amount.ok()?));
+        let inputs = op
+            .inputs()
+            .iter()
+            .map(|input| input.prev_out)
+            .filter(|opout| opout.ty == self.allowance_type)
+            .collect();
+        if let Some(assigns) = op.assignments_by_type(self.allowance_type) {
+            for (no, assign) in assigns.as_fungible().iter().enumerate() {
+                let opout = Opout::new(opid, self.allowance_type, no as u16);
+                let amount = assign
+                    .as_revealed_state()
+                    .map(|state| Amount::from(state.value.as_u64()));
+                self.rights.insert(opout, InflationRight {
+                    opout,
+                    amount,
+                    seal: None,
+                    spent_by: None,
+                });
+            }
+        }
+        self.ops.push(AuditOp {
+            opid,
+            is_genesis: op.op_type() == OpType::Genesis,
+            issued,
+            inputs,
+            witness_id,
+            witness_ord,
+        });
+    }
+
+    /// Registers holder of an inflation right known to the contract state.
+    pub fn add_holder(&mut self, opout: Opout, seal: XOutputSeal) {
+        if let Some(right) = self.rights.get_mut(&opout) {
+            right.seal = Some(seal);
+        }
+    }
+
+    pub fn finish(mut self) -> InflationReport {
+        let mut violations = vec![];
+        let mut max_supply = Amount::ZERO;
+        let mut issued_supply = Amount::ZERO;
+        let mut issuances = vec![];
+
+        self.ops
+            .sort_by_key(|op| (!op.is_genesis, op.witness_ord.is_none(), op.witness_ord));
+        for op in &self.ops {
+            let Some(issued) = op.issued else {
+                violations.push(InflationViolation::InvalidSupply(op.opid));
+                continue;
+            };
+            let created = self
+                .rights
+                .values()
+                .filter(|right| right.opout.op == op.opid)
+                .try_fold(Amount::ZERO, |sum, right| sum.checked_add(right.amount?));
+            for right in self.rights.values() {
+                if right.opout.op == op.opid && right.amount.is_none() {
+                    violations.push(InflationViolation::ConcealedAllowance(right.opout));
+                }
+            }
+
+            let mut spent = Some(Amount::ZERO);
+            for opout in &op.inputs {
+                let Some(right) = self.rights.get_mut(opout) else {
+                    violations.push(InflationViolation::UnknownAllowance(op.opid, *opout));
+                    spent = None;
+                    continue;
+                };
+                right.spent_by = Some(op.opid);
+                spent = spent.zip(right.amount).and_then(|(a, b)| a.checked_add(b));
+            }
+
+            if op.is_genesis {
+                max_supply = created
+                    .unwrap_or(Amount::from(u64::MAX))
+                    .saturating_add(issued);
+            } else if let (Some(spent), Some(created)) = (spent, created) {
+                let used = created.saturating_add(issued);
+                if used > spent {
+                    violations.push(InflationViolation::AllowanceExceeded {
+                        opid: op.opid,
+                        allowance: spent,
+                        used,
+                    });
+                }
+            }
+
+            if op.is_genesis || issued > Amount::ZERO {
+                issued_supply.saturating_add_assign(issued);
+                issuances.push(Issuance {
+                    opid: op.opid,
+                    amount: issued,
+                    witness_id: op.witness_id,
+                    witness_ord: op.witness_ord,
+                });
+            }
+        }
+        if issued_supply > max_supply {
+            violations.push(InflationViolation::SupplyExceeded {
+                issued: issued_supply,
+                max: max_supply,
+            });
+        }
+
+        let remaining_allowance = self
+            .rights
+            .values()
+            .filter(|right| !right.is_spent())
+            .filter_map(|right| right.amount)
+            .fold(Amount::ZERO, |sum, amount| sum.saturating_add(amount));
+
+        InflationReport {
+            contract_id: self.contract_id,
+            max_supply,
+            issued_supply,
+            remaining_allowance,
+            issuances,
+            rights: self.rights.into_values().collect(),
+            violations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use bp::seals::txout::CloseMethod;
+    use bp::{Txid, Vout};
+    use rgb::{
+        Assign, AssetTag, Assignments, DataState, ExposedSeal, Genesis, GenesisSeal, GlobalState,
+        GraphSeal, Input, Inputs, RevealedValue, Transition, TypedAssigns, XChain,
+    };
+    use strict_encoding::{StrictDumb, StrictSerialize};
+
+    use super::*;
+
+    const SUPPLY: GlobalStateType = GlobalStateType::with(2010);
+    const ALLOWANCE: AssignmentType = AssignmentType::with(4010);
+
+    fn supply(amount: u64) -> GlobalState {
+        let mut globals = GlobalState::default();
+        let data = Amount::from(amount).to_strict_serialized::<U16>().unwrap();
+        globals.add_state(SUPPLY, DataState::from(data)).unwrap();
+        globals
+    }
+
+    fn allowance<Seal: ExposedSeal>(seal: XChain<Seal>, amount: u64) -> Assignments<Seal> {
+        let assign = Assign::revealed(
+            seal,
+            RevealedValue::new_random_blinding(amount, AssetTag::from([2u8; 32])),
+        );
+        Assignments::from(Confined::from_checked(bmap! {
+            ALLOWANCE => TypedAssigns::Fungible(Confined::from_checked(vec![assign]))
+        }))
+    }
+
+    fn issue(prev_out: Opout, issued: u64, remaining: u64) -> Transition {
+        let seal = GraphSeal::new_random(
+            CloseMethod::OpretFirst,
+            Txid::from([1u8; 32]),
+            Vout::from_u32(1),
+        );
+        let mut transition = Transition::strict_dumb();
+        transition.inputs = Inputs::from(Confined::from_checked(bset![Input::with(prev_out)]));
+        transition.globals = supply(issued);
+        transition.assignments = allowance(XChain::Bitcoin(seal), remaining);
+        transition
+    }
+
+    #[test]
+    fn inflation_audit() {
+        let mut genesis = Genesis::strict_dumb();
+        genesis.globals = supply(100);
+        let seal = GenesisSeal::new_random(
+            CloseMethod::OpretFirst,
+            Txid::from([1u8; 32]),
+            Vout::from_u32(0),
+        );
+        genesis.assignments = allowance(XChain::Bitcoin(seal), 50);
+        let genesis_right = Opout::new(genesis.id(), ALLOWANCE, 0);
+
+        let issue1 = issue(genesis_right, 30, 20);
+        let issue1_right = Opout::new(issue1.id(), ALLOWANCE, 0);
+        let ord = Some(WitnessOrd::Tentative);
+
+        let mut audit = InflationAudit::new(ContractId::strict_dumb(), SUPPLY, ALLOWANCE);
+        audit.add_operation(&issue1, Some(XWitnessId::strict_dumb()), ord);
+        audit.add_operation(&genesis, None, None);
+        let report = audit.finish();
+        assert!(report.is_intact());
+        assert_eq!(report.max_supply, Amount::from(150u64));
+        assert_eq!(report.issued_supply, Amount::from(130u64));
+        assert_eq!(report.remaining_allowance, Amount::from(20u64));
+        assert_eq!(report.issuances.len(), 2);
+        assert_eq!(report.issuances[0].opid, genesis.id());
+        assert_eq!(report.unspent_rights().map(|r| r.opout).collect::<Vec<_>>(), vec![
+            issue1_right
+        ]);
+
+        let issue2 = issue(issue1_right, 40, 0);
+        let mut audit = InflationAudit::new(ContractId::strict_dumb(), SUPPLY, ALLOWANCE);
+        audit.add_operation(&genesis, None, None);
+        audit.add_operation(&issue1, Some(XWitnessId::strict_dumb()), ord);
+        audit.add_operation(&issue2, Some(XWitnessId::strict_dumb()), ord);
+        let report = audit.finish();
+        assert!(!report.is_intact());
+        assert_eq!(report.violations, vec![
+            InflationViolation::AllowanceExceeded {
+                opid: issue2.id(),
+                allowance: Amount::from(20u64),
+                used: Amount::from(40u64),
+            },
+            InflationViolation::SupplyExceeded {
+                issued: Amount::from(170u64),
+                max: Amount::from(150u64),
+            },
+        ]);
+    }
+}
@@ -0,0 +1,290 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bulk generation of invoices.
+//!
+//! Payout collection campaigns require generating many invoices at once,
+//! distributing them to the payers with mail-merge style tools and matching
+//! the incoming transfers against the invoices afterwards. Generated invoices
+//! can be exported to CSV or JSON, and imported back for matching.
+
+use std::str::FromStr;
+
+use bp::seals::txout::CloseMethod;
+use invoice::{Amount, Pay2Vout, RgbInvoice};
+use rgb::{ContractId, XOutpoint};
+use strict_encoding::TypeName;
+
+use crate::containers::{InvoiceMismatch, Satisfaction, Transfer};
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum BatchInvoiceError {
+    /// no UTXOs are provided for deriving blinded seals.
+    NoUtxos,
+
+    /// {0} witness output addresses are provided for {1} invoices.
+    NotEnoughAddresses(usize, usize),
+
+    /// line {0} of the CSV data is invalid.
+    InvalidCsv(usize),
+
+    /// invalid invoice for '{0}': {1}
+    InvalidInvoice(String, String),
+}
+
+/// Request for generating an invoice within a batch.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct InvoiceRequest {
+    /// Label identifying the payer or the purpose of the payment.
+    pub label: String,
+    pub contract_id: ContractId,
+    pub iface: Option<TypeName>,
+    pub amount: Amount,
+    /// UTC unix timestamp.
+    pub expiry: Option<i64>,
+}
+
+impl InvoiceRequest {
+    pub fn new(label: impl ToString, contract_id: ContractId, amount: impl Into<Amount>) -> Self {
+        Self {
+            label: label.to_string(),
+            contract_id,
+            iface: None,
+            amount: amount.into(),
+            expiry: None,
+        }
+    }
+}
+
+/// Source of the beneficiary seals for the invoices of a batch.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum BatchSeals {
+    /// Blinded seals derived over the available UTXOs, which are used in
+    /// round-robin fashion.
+    Blinded {
+        method: CloseMethod,
+        utxos: Vec<XOutpoint>,
+    },
+    /// Witness output seals, with an address for each of the invoices.
+    WitnessVout(Vec<Pay2Vout>),
+}
+
+/// Invoice generated within a batch.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct InvoiceRecord {
+    pub label: String,
+    /// Invoice string.
+    pub invoice: String,
+}
+
+impl InvoiceRecord {
+    pub fn with(label: impl ToString, invoice: &RgbInvoice) -> Self {
+        Self {
+            label: label.to_string(),
+            invoice: invoice.to_string(),
+        }
+    }
+
+    pub fn invoice(&self) -> Result<RgbInvoice, BatchInvoiceError> {
+        RgbInvoice::from_str(&self.invoice)
+            .map_err(|err| BatchInvoiceError::InvalidInvoice(self.label.clone(), err.to_string()))
+    }
+}
+
+/// Batch of generated invoices.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct InvoiceBatch {
+    pub records: Vec<InvoiceRecord>,
+}
+
+impl InvoiceBatch {
+    pub const CSV_HEADER: &'static str = "label,invoice";
+
+    pub fn len(&self) -> usize { self.records.len() }
+
+    pub fn is_empty(&self) -> bool { self.records.is_empty() }
+
+    /// Exports the batch as CSV with `label` and `invoice` columns.
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", Self::CSV_HEADER);
+        for record in &self.records {
+            csv.push_str(&csv_field(&record.label));
+            csv.push(',');
+            csv.push_str(&csv_field(&record.invoice));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Imports the batch from CSV produced by [`InvoiceBatch::to_csv`],
+    /// checking that the invoices are valid.
+    pub fn from_csv(csv: &str) -> Result<Self, BatchInvoiceError> {
+        let mut records = vec![];
+        for (no, line) in csv.lines().enumerate() {
+            if (no == 0 && line == Self::CSV_HEADER) || line.trim().is_empty() {
+                continue;
+            }
+            let fields = parse_csv_line(line).ok_or(BatchInvoiceError::InvalidCsv(no + 1))?;
+            let [label, invoice] = <[String; 2]>::try_from(fields)
+                .map_err(|_| BatchInvoiceError::InvalidCsv(no + 1))?;
+            let record = InvoiceRecord { label, invoice };
+            record.invoice()?;
+            records.push(record);
+        }
+        Ok(Self { records })
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("in-memory serialization")
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> { serde_json::from_str(json) }
+
+    /// Matches transfers against the invoices of the batch.
+    ///
+    /// Returns for each of the invoices either the satisfaction information
+    /// from the first transfer paying it, or the mismatch reported by the
+    /// last checked transfer; invoices with no transfers are reported with
+    /// `None`.
+    pub fn match_transfers<'t>(
+        &self,
+        transfers: impl IntoIterator<Item = &'t Transfer> + Clone,
+    ) -> Result<Vec<InvoiceMatch>, BatchInvoiceError> {
+        let mut matches = Vec::with_capacity(self.records.len());
+        for record in &self.records {
+            let invoice = record.invoice()?;
+            let mut status = None;
+            for transfer in transfers.clone() {
+                match transfer.satisfies_invoice(&invoice) {
+                    Ok(satisfaction) => {
+                        status = Some(Ok(satisfaction));
+                        break;
+                    }
+                    Err(mismatch) => status = Some(Err(mismatch)),
+                }
+            }
+            matches.push(InvoiceMatch {
+                label: record.label.clone(),
+                status,
+            });
+        }
+        Ok(matches)
+    }
+}
+
+/// Result of matching transfers against an invoice of a batch.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct InvoiceMatch {
+    pub label: String,
+    pub status: Option<Result<Satisfaction, InvoiceMismatch>>,
+}
+
+impl InvoiceMatch {
+    pub fn is_paid(&self) -> bool { matches!(self.status, Some(Ok(_))) }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn parse_csv_line(line: &str) -> Option<Vec<String>> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+#[cfg(test)]
+mod test {
+    use invoice::{Beneficiary, RgbInvoiceBuilder, XChainNet};
+    use rgb::SecretSeal;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn csv_round_trip() {
+        let beneficiary =
+            XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(SecretSeal::strict_dumb()));
+        let invoice = RgbInvoiceBuilder::with(ContractId::strict_dumb(), beneficiary)
+            .set_interface(tn!("RGB20Fixed"))
+            .set_amount_raw(100u64)
+            .add_close_methods([CloseMethod::TapretFirst, CloseMethod::OpretFirst])
+            .finish();
+        let batch = InvoiceBatch {
+            records: vec![
+                InvoiceRecord::with("alice", &invoice),
+                InvoiceRecord::with("bob, \"the payer\"", &invoice),
+            ],
+        };
+        let csv = batch.to_csv();
+        assert_eq!(InvoiceBatch::from_csv(&csv).unwrap(), batch);
+        assert_eq!(batch.records[1].invoice().unwrap(), invoice);
+
+        assert_eq!(
+            InvoiceBatch::from_csv("label,invoice\n\"alice,rgb:"),
+            Err(BatchInvoiceError::InvalidCsv(2))
+        );
+        assert!(matches!(
+            InvoiceBatch::from_csv("alice,rgb:invalid"),
+            Err(BatchInvoiceError::InvalidInvoice(..))
+        ));
+    }
+}
@@ -25,14 +25,17 @@ use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::convert::Infallible;
 use std::fmt::{Debug, Formatter};
 use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Arc;
 use std::{iter, mem};
 
 use aluvm::library::{Lib, LibId};
 use amplify::confinement::{
     self, Confined, LargeOrdMap, LargeOrdSet, MediumBlob, MediumOrdMap, MediumOrdSet, SmallOrdMap,
-    TinyOrdMap, TinyOrdSet,
+    SmallOrdSet, SmallString, TinyOrdMap, TinyOrdSet,
 };
 use amplify::num::u24;
+use amplify::Wrapper;
 use bp::dbc::tapret::TapretCommitment;
 use commit_verify::{CommitId, Conceal};
 use nonasync::persistence::{CloneNoPersistence, Persistence, PersistenceError, Persisting};
@@ -48,18 +51,22 @@ use rgb::{
     RevealedData, RevealedValue, Schema, SchemaId, SecretSeal, Transition, TransitionBundle,
     TypedAssigns, VoidState, XChain, XOutpoint, XOutputSeal, XWitnessId,
 };
-use strict_encoding::{StrictDeserialize, StrictSerialize};
+use strict_encoding::{
+    DecodeError, StrictDecode, StrictDeserialize, StrictSerialize, TypedRead,
+};
 use strict_types::TypeSystem;
 
 use super::{
-    ContractIfaceError, ContractStateRead, ContractStateWrite, IndexInconsistency, IndexProvider,
-    IndexReadError, IndexReadProvider, IndexWriteError, IndexWriteProvider, SchemaIfaces,
+    CloseMethodPolicy, ContractIfaceError, ContractStateRead, ContractStateWrite,
+    IndexInconsistency, IndexProvider, IndexReadError, IndexReadProvider, IndexWriteError,
+    IndexWriteProvider, LabelRef, Provenance, ProvenanceRef, Reservation, SchemaIfaces,
     StashInconsistency, StashProvider, StashProviderError, StashReadProvider, StashWriteProvider,
     StateInconsistency, StateProvider, StateReadProvider, StateWriteProvider, StoreTransaction,
     UpdateRes,
 };
 use crate::containers::{
-    AnchorSet, ContentId, ContentRef, ContentSigs, SealWitness, SigBlob, Supplement, TrustLevel,
+    AnchorSet, ConsignmentId, ContentId, ContentRef, ContentSigs, SealWitness, SigBlob, Supplement,
+    Transfer, TrustLevel,
 };
 use crate::contract::{GlobalOut, KnownState, OpWitness, OutputAssignment};
 use crate::interface::{Iface, IfaceClass, IfaceId, IfaceImpl, IfaceRef};
@@ -73,6 +80,9 @@ pub enum MemError {
 
     #[from]
     Confinement(confinement::Error),
+
+    #[from]
+    Inconsistency(StateInconsistency),
 }
 
 //////////
@@ -102,6 +112,13 @@ pub struct MemStash {
     identities: SmallOrdMap<Identity, TrustLevel>,
     libs: SmallOrdMap<LibId, Lib>,
     sigs: SmallOrdMap<ContentId, ContentSigs>,
+    labels: MediumOrdMap<LabelRef, SmallString>,
+    provenance: MediumOrdMap<ProvenanceRef, Provenance>,
+    reservations: MediumOrdMap<OpId, Reservation>,
+    provisional: MediumOrdMap<XWitnessId, u32>,
+    resolutions: MediumOrdMap<Opout, OpId>,
+    pending: TinyOrdMap<ConsignmentId, Transfer>,
+    close_methods: CloseMethodPolicy,
 }
 
 impl StrictSerialize for MemStash {}
@@ -124,8 +141,36 @@ impl MemStash {
             identities: empty!(),
             libs: empty!(),
             sigs: empty!(),
+            labels: empty!(),
+            provenance: empty!(),
+            reservations: empty!(),
+            provisional: empty!(),
+            resolutions: empty!(),
+            pending: empty!(),
+            close_methods: default!(),
         }
     }
+
+    /// Decodes stash written before the stock files were versioned, which
+    /// lacks labels, provenance, reservations, provisional witnesses,
+    /// conflict resolutions, pending transfers and close method policy.
+    pub(crate) fn strict_decode_v0(reader: &mut impl TypedRead) -> Result<Self, DecodeError> {
+        let mut stash = Self::in_memory();
+        stash.schemata = StrictDecode::strict_decode(reader)?;
+        stash.ifaces = StrictDecode::strict_decode(reader)?;
+        stash.geneses = StrictDecode::strict_decode(reader)?;
+        stash.suppl = StrictDecode::strict_decode(reader)?;
+        stash.bundles = StrictDecode::strict_decode(reader)?;
+        stash.extensions = StrictDecode::strict_decode(reader)?;
+        stash.witnesses = StrictDecode::strict_decode(reader)?;
+        stash.attachments = StrictDecode::strict_decode(reader)?;
+        stash.secret_seals = StrictDecode::strict_decode(reader)?;
+        stash.type_system = StrictDecode::strict_decode(reader)?;
+        stash.identities = StrictDecode::strict_decode(reader)?;
+        stash.libs = StrictDecode::strict_decode(reader)?;
+        stash.sigs = StrictDecode::strict_decode(reader)?;
+        Ok(stash)
+    }
 }
 
 impl CloneNoPersistence for MemStash {
@@ -145,6 +190,13 @@ impl CloneNoPersistence for MemStash {
             identities: self.identities.clone(),
             libs: self.libs.clone(),
             sigs: self.sigs.clone(),
+            labels: self.labels.clone(),
+            provenance: self.provenance.clone(),
+            reservations: self.reservations.clone(),
+            provisional: self.provisional.clone(),
+            resolutions: self.resolutions.clone(),
+            pending: self.pending.clone(),
+            close_methods: self.close_methods,
         }
     }
 }
@@ -167,8 +219,10 @@ impl StoreTransaction for MemStash {
     }
     #[inline]
     fn commit_transaction(&mut self) -> Result<(), Self::TransactionErr> { Ok(self.store()?) }
+    // In-memory data can't be reverted; the changes are not persisted until the
+    // next transaction is committed
     #[inline]
-    fn rollback_transaction(&mut self) { unreachable!() }
+    fn rollback_transaction(&mut self) {}
 }
 
 impl StashProvider for MemStash {}
@@ -247,7 +301,10 @@ impl StashReadProvider for MemStash {
     }
 
     fn supplement(&self, content_ref: ContentRef) -> Result<Option<&Supplement>, Self::Error> {
-        Ok(self.suppl.get(&content_ref).and_then(|s| s.first()))
+        Ok(self
+            .suppl
+            .get(&content_ref)
+            .and_then(|s| s.iter().find(|suppl| suppl.attestation().is_none())))
     }
 
     fn supplements(
@@ -354,6 +411,55 @@ impl StashReadProvider for MemStash {
     fn secret_seals(&self) -> Result<impl Iterator<Item = XChain<GraphSeal>>, Self::Error> {
         Ok(self.secret_seals.iter().copied())
     }
+
+    fn label(&self, label_ref: LabelRef) -> Result<Option<&str>, Self::Error> {
+        Ok(self.labels.get(&label_ref).map(|label| label.as_str()))
+    }
+
+    fn labels(&self) -> Result<impl Iterator<Item = (LabelRef, &str)>, Self::Error> {
+        Ok(self
+            .labels
+            .iter()
+            .map(|(label_ref, label)| (*label_ref, label.as_str())))
+    }
+
+    fn provenance(&self, obj: ProvenanceRef) -> Result<Option<&Provenance>, Self::Error> {
+        Ok(self.provenance.get(&obj))
+    }
+
+    fn provenances(
+        &self,
+    ) -> Result<impl Iterator<Item = (ProvenanceRef, &Provenance)>, Self::Error> {
+        Ok(self
+            .provenance
+            .iter()
+            .map(|(obj, provenance)| (*obj, provenance)))
+    }
+
+    fn reservations(&self) -> Result<impl Iterator<Item = (OpId, &Reservation)>, Self::Error> {
+        Ok(self
+            .reservations
+            .iter()
+            .map(|(id, reservation)| (*id, reservation)))
+    }
+
+    fn provisional_witnesses(
+        &self,
+    ) -> Result<impl Iterator<Item = (XWitnessId, u32)>, Self::Error> {
+        Ok(self.provisional.iter().map(|(id, min)| (*id, *min)))
+    }
+
+    fn conflict_resolutions(&self) -> Result<impl Iterator<Item = (Opout, OpId)>, Self::Error> {
+        Ok(self.resolutions.iter().map(|(opout, keep)| (*opout, *keep)))
+    }
+
+    fn pending_consignments(&self) -> Result<impl Iterator<Item = &Transfer>, Self::Error> {
+        Ok(self.pending.values())
+    }
+
+    fn close_method_policy(&self) -> Result<CloseMethodPolicy, Self::Error> {
+        Ok(self.close_methods)
+    }
 }
 
 impl StashWriteProvider for MemStash {
@@ -478,12 +584,101 @@ impl StashWriteProvider for MemStash {
         self.secret_seals.push(seal)?;
         Ok(!present)
     }
+
+    fn replace_label(&mut self, label_ref: LabelRef, label: String) -> Result<bool, Self::Error> {
+        let label = SmallString::try_from(label)?;
+        Ok(self.labels.insert(label_ref, label)?.is_none())
+    }
+
+    fn remove_label(&mut self, label_ref: LabelRef) -> Result<bool, Self::Error> {
+        Ok(self.labels.remove(&label_ref)?.is_some())
+    }
+
+    fn add_provenance(
+        &mut self,
+        obj: ProvenanceRef,
+        provenance: Provenance,
+    ) -> Result<bool, Self::Error> {
+        if self.provenance.contains_key(&obj) {
+            return Ok(false);
+        }
+        self.provenance.insert(obj, provenance)?;
+        Ok(true)
+    }
+
+    fn replace_reservation(
+        &mut self,
+        id: OpId,
+        reservation: Reservation,
+    ) -> Result<bool, Self::Error> {
+        Ok(self.reservations.insert(id, reservation)?.is_none())
+    }
+
+    fn remove_reservation(&mut self, id: OpId) -> Result<bool, Self::Error> {
+        Ok(self.reservations.remove(&id)?.is_some())
+    }
+
+    fn replace_provisional_witness(
+        &mut self,
+        witness_id: XWitnessId,
+        min_confirmations: u32,
+    ) -> Result<bool, Self::Error> {
+        Ok(self
+            .provisional
+            .insert(witness_id, min_confirmations)?
+            .is_none())
+    }
+
+    fn remove_provisional_witness(&mut self, witness_id: XWitnessId) -> Result<bool, Self::Error> {
+        Ok(self.provisional.remove(&witness_id)?.is_some())
+    }
+
+    fn replace_conflict_resolution(
+        &mut self,
+        opout: Opout,
+        keep: OpId,
+    ) -> Result<bool, Self::Error> {
+        Ok(self.resolutions.insert(opout, keep)? != Some(keep))
+    }
+
+    fn add_pending_consignment(&mut self, consignment: Transfer) -> Result<bool, Self::Error> {
+        let id = consignment.consignment_id();
+        Ok(self.pending.insert(id, consignment)?.is_none())
+    }
+
+    fn remove_pending_consignment(&mut self, id: ConsignmentId) -> Result<bool, Self::Error> {
+        Ok(self.pending.remove(&id)?.is_some())
+    }
+
+    fn set_close_method_policy(&mut self, policy: CloseMethodPolicy) -> Result<(), Self::Error> {
+        self.close_methods = policy;
+        Ok(())
+    }
 }
 
 //////////
 // STATE
 //////////
 
+/// Provider of the cold storage for the state of archived contracts, which is
+/// kept separately from the main state and is loaded only when the archive is
+/// accessed.
+pub trait ArchiveProvider: Send + Sync + Debug {
+    fn load_archive(&self) -> Result<MemArchive, PersistenceError>;
+}
+
+/// State of the archived contracts.
+#[derive(Getters, Clone, Debug, Default)]
+#[getter(prefix = "debug_")]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STORAGE)]
+pub struct MemArchive {
+    contracts: SmallOrdMap<ContractId, MemContractState>,
+}
+
+impl StrictSerialize for MemArchive {}
+impl StrictDeserialize for MemArchive {}
+
 #[derive(Getters, Debug)]
 #[getter(prefix = "debug_")]
 #[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
@@ -495,6 +690,21 @@ pub struct MemState {
 
     witnesses: LargeOrdMap<XWitnessId, WitnessOrd>,
     contracts: TinyOrdMap<ContractId, MemContractState>,
+    /// Contracts moved to the archive, which are excluded from the state
+    /// queries. The state of these contracts is kept in [`MemArchive`].
+    archived: SmallOrdSet<ContractId>,
+
+    /// Archive, loaded on the first access.
+    #[getter(skip)]
+    #[strict_type(skip)]
+    archive: Option<MemArchive>,
+    /// Whether the archive was changed since it was loaded or stored.
+    #[getter(skip)]
+    #[strict_type(skip)]
+    archive_dirty: AtomicBool,
+    #[getter(skip)]
+    #[strict_type(skip)]
+    archive_provider: Option<Arc<dyn ArchiveProvider>>,
 }
 
 impl StrictSerialize for MemState {}
@@ -506,7 +716,99 @@ impl MemState {
             persistence: none!(),
             witnesses: empty!(),
             contracts: empty!(),
+            archived: empty!(),
+            archive: None,
+            archive_dirty: AtomicBool::new(false),
+            archive_provider: None,
+        }
+    }
+
+    /// Decodes state written before the stock files were versioned, which
+    /// lacks the set of archived contracts.
+    pub(crate) fn strict_decode_v0(reader: &mut impl TypedRead) -> Result<Self, DecodeError> {
+        let mut state = Self::in_memory();
+        state.witnesses = StrictDecode::strict_decode(reader)?;
+        state.contracts = StrictDecode::strict_decode(reader)?;
+        Ok(state)
+    }
+
+    /// Sets provider from which the archive is loaded on the first access.
+    pub fn with_archive_provider(mut self, provider: impl ArchiveProvider + 'static) -> Self {
+        self.archive_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Returns archive if it was loaded and has changed since it was stored
+    /// last time.
+    pub fn dirty_archive(&self) -> Option<&MemArchive> {
+        if self.archive_dirty.load(AtomicOrdering::SeqCst) { self.archive.as_ref() } else { None }
+    }
+
+    /// Resets the archive change flag once the persistence provider has
+    /// stored the archive.
+    pub fn mark_archive_stored(&self) { self.archive_dirty.store(false, AtomicOrdering::SeqCst); }
+
+    fn archive_mut(&mut self) -> Result<&mut MemArchive, PersistenceError> {
+        if self.archive.is_none() {
+            let archive = match &self.archive_provider {
+                Some(provider) => provider.load_archive()?,
+                None => MemArchive::default(),
+            };
+            self.archive = Some(archive);
+        }
+        Ok(self.archive.as_mut().expect("just loaded"))
+    }
+
+    fn restore_archived(&mut self, contract_id: ContractId) -> Result<bool, MemError> {
+        if !self.archived.contains(&contract_id) {
+            return Ok(false);
+        }
+        let Some(contract) = self.archive_mut()?.contracts.get(&contract_id).cloned() else {
+            return Err(StateInconsistency::ArchivedContract(contract_id).into());
+        };
+        self.contracts.insert(contract_id, contract)?;
+        self.archived.remove(&contract_id)?;
+        self.archive_mut()?.contracts.remove(&contract_id)?;
+        self.archive_dirty.store(true, AtomicOrdering::SeqCst);
+        Ok(true)
+    }
+
+    fn move_to_archive(
+        &mut self,
+        contract_id: ContractId,
+        contract: MemContractState,
+    ) -> Result<(), MemError> {
+        self.archive_mut()?
+            .contracts
+            .insert(contract_id, contract)?;
+        self.archive_dirty.store(true, AtomicOrdering::SeqCst);
+        self.archived.push(contract_id)?;
+        self.contracts.remove(&contract_id)?;
+        Ok(())
+    }
+
+    /// Restores the contract state and the archive after a failed archival
+    /// or restoration.
+    fn revert_archival(
+        &mut self,
+        contract_id: ContractId,
+        contract: MemContractState,
+        archived: bool,
+    ) {
+        if archived {
+            self.contracts.remove(&contract_id).ok();
+            self.archived.push(contract_id).ok();
+            if let Some(archive) = self.archive.as_mut() {
+                archive.contracts.insert(contract_id, contract).ok();
+            }
+        } else {
+            self.archived.remove(&contract_id).ok();
+            if let Some(archive) = self.archive.as_mut() {
+                archive.contracts.remove(&contract_id).ok();
+            }
+            self.contracts.insert(contract_id, contract).ok();
         }
+        self.archive_dirty.store(true, AtomicOrdering::SeqCst);
     }
 }
 
@@ -516,6 +818,10 @@ impl CloneNoPersistence for MemState {
             persistence: None,
             witnesses: self.witnesses.clone(),
             contracts: self.contracts.clone(),
+            archived: self.archived.clone(),
+            archive: self.archive.clone(),
+            archive_dirty: AtomicBool::new(self.archive_dirty.load(AtomicOrdering::SeqCst)),
+            archive_provider: self.archive_provider.clone(),
         }
     }
 }
@@ -538,8 +844,10 @@ impl StoreTransaction for MemState {
     }
     #[inline]
     fn commit_transaction(&mut self) -> Result<(), Self::TransactionErr> { Ok(self.store()?) }
+    // In-memory data can't be reverted; the changes are not persisted until the
+    // next transaction is committed
     #[inline]
-    fn rollback_transaction(&mut self) { unreachable!() }
+    fn rollback_transaction(&mut self) {}
 }
 
 impl StateProvider for MemState {}
@@ -552,6 +860,9 @@ impl StateReadProvider for MemState {
         &self,
         contract_id: ContractId,
     ) -> Result<Self::ContractRead<'_>, Self::Error> {
+        if self.archived.contains(&contract_id) {
+            return Err(StateInconsistency::ArchivedContract(contract_id));
+        }
         let unfiltered = self
             .contracts
             .get(&contract_id)
@@ -583,6 +894,10 @@ impl StateReadProvider for MemState {
             .ok_or(StateInconsistency::AbsentValidWitness)?;
         Ok(ord.is_valid())
     }
+
+    fn archived_contracts(&self) -> Result<impl Iterator<Item = ContractId> + '_, Self::Error> {
+        Ok(self.archived.iter().copied())
+    }
 }
 
 impl StateWriteProvider for MemState {
@@ -596,6 +911,8 @@ impl StateWriteProvider for MemState {
     ) -> Result<Self::ContractWrite<'_>, Self::Error> {
         // TODO: Add begin/commit transaction
         let contract_id = genesis.contract_id();
+        // Importing new data for an archived contract brings it back
+        self.restore_archived(contract_id)?;
         // This crazy construction is caused by a stupidity of rust borrow checker
         let contract = if self.contracts.contains_key(&contract_id) {
             if let Some(contract) = self.contracts.get_mut(&contract_id) {
@@ -628,6 +945,7 @@ impl StateWriteProvider for MemState {
         contract_id: ContractId,
     ) -> Result<Option<Self::ContractWrite<'_>>, Self::Error> {
         // TODO: Add begin/commit transaction
+        self.restore_archived(contract_id)?;
         Ok(self
             .contracts
             .get_mut(&contract_id)
@@ -677,6 +995,62 @@ impl StateWriteProvider for MemState {
         self.commit_transaction()?;
         Ok(UpdateRes { succeeded, failed })
     }
+
+    fn archive_contract(&mut self, contract_id: ContractId) -> Result<bool, Self::Error> {
+        let Some(contract) = self.contracts.get(&contract_id).cloned() else {
+            return Ok(false);
+        };
+        self.begin_transaction()?;
+        let res = self
+            .move_to_archive(contract_id, contract.clone())
+            .and_then(|_| self.commit_transaction());
+        if let Err(err) = res {
+            self.revert_archival(contract_id, contract, false);
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    fn unarchive_contract(&mut self, contract_id: ContractId) -> Result<bool, Self::Error> {
+        if !self.archived.contains(&contract_id) {
+            return Ok(false);
+        }
+        self.begin_transaction()?;
+        let res = self
+            .restore_archived(contract_id)
+            .and_then(|_| self.commit_transaction());
+        if let Err(err) = res {
+            if let Some(contract) = self.contracts.get(&contract_id).cloned() {
+                self.revert_archival(contract_id, contract, true);
+            }
+            return Err(err);
+        }
+        Ok(true)
+    }
+
+    fn replace_contract(
+        &mut self,
+        contract_id: ContractId,
+        rebuilt: &MemState,
+    ) -> Result<(), Self::Error> {
+        let Some(contract) = rebuilt.contracts.get(&contract_id).cloned() else {
+            return Err(StateInconsistency::UnknownContract(contract_id).into());
+        };
+        self.begin_transaction()?;
+        // Archived contract remains archived with the repaired state
+        if self.archived.contains(&contract_id) {
+            self.archive_mut()?
+                .contracts
+                .insert(contract_id, contract)?;
+            self.archive_dirty.store(true, AtomicOrdering::SeqCst);
+        } else {
+            self.contracts.insert(contract_id, contract)?;
+        }
+        for (witness_id, ord) in &rebuilt.witnesses {
+            self.witnesses.insert(*witness_id, *ord)?;
+        }
+        self.commit_transaction()
+    }
 }
 
 #[derive(Getters, Clone, Eq, PartialEq, Debug)]
@@ -1067,7 +1441,9 @@ impl ContractStateEvolve for MemContract<MemContractState> {
         .map_err(|err| {
             // TODO: remove once evolve_state would accept arbitrary errors
             match err {
-                MemError::Persistence(_) => unreachable!("only confinement errors are possible"),
+                MemError::Persistence(_) | MemError::Inconsistency(_) => {
+                    unreachable!("only confinement errors are possible")
+                }
                 MemError::Confinement(e) => e,
             }
         })?;
@@ -1213,6 +1589,7 @@ pub struct MemIndex {
     bundle_witness_index: MediumOrdMap<BundleId, TinyOrdSet<XWitnessId>>,
     contract_index: TinyOrdMap<ContractId, ContractIndex>,
     terminal_index: MediumOrdMap<XChain<SecretSeal>, TinyOrdSet<Opout>>,
+    spent_index: MediumOrdMap<Opout, TinyOrdSet<OpId>>,
 }
 
 impl StrictSerialize for MemIndex {}
@@ -1227,8 +1604,37 @@ impl MemIndex {
             bundle_witness_index: empty!(),
             contract_index: empty!(),
             terminal_index: empty!(),
+            spent_index: empty!(),
         }
     }
+
+    /// Decodes index written before the stock files were versioned, which
+    /// lacks the index of spent assignments. The index must be completed with
+    /// [`Self::index_spendings`].
+    pub(crate) fn strict_decode_v0(reader: &mut impl TypedRead) -> Result<Self, DecodeError> {
+        let mut index = Self::in_memory();
+        index.op_bundle_index = StrictDecode::strict_decode(reader)?;
+        index.bundle_contract_index = StrictDecode::strict_decode(reader)?;
+        index.bundle_witness_index = StrictDecode::strict_decode(reader)?;
+        index.contract_index = StrictDecode::strict_decode(reader)?;
+        index.terminal_index = StrictDecode::strict_decode(reader)?;
+        Ok(index)
+    }
+
+    /// Registers assignments spent by the transitions known to the stash.
+    pub(crate) fn index_spendings(
+        &mut self,
+        stash: &MemStash,
+    ) -> Result<(), IndexWriteError<MemError>> {
+        for bundle in stash.bundles.values() {
+            for (opid, transition) in &bundle.known_transitions {
+                for input in &transition.inputs {
+                    self.register_spending(input.prev_out, *opid)?;
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl CloneNoPersistence for MemIndex {
@@ -1240,6 +1646,7 @@ impl CloneNoPersistence for MemIndex {
             bundle_witness_index: self.bundle_witness_index.clone(),
             contract_index: self.contract_index.clone(),
             terminal_index: self.terminal_index.clone(),
+            spent_index: self.spent_index.clone(),
         }
     }
 }
@@ -1262,8 +1669,10 @@ impl StoreTransaction for MemIndex {
     }
     #[inline]
     fn commit_transaction(&mut self) -> Result<(), Self::TransactionErr> { Ok(self.store()?) }
+    // In-memory data can't be reverted; the changes are not persisted until the
+    // next transaction is committed
     #[inline]
-    fn rollback_transaction(&mut self) { unreachable!() }
+    fn rollback_transaction(&mut self) {}
 }
 
 impl IndexProvider for MemIndex {}
@@ -1355,6 +1764,35 @@ impl IndexReadProvider for MemIndex {
             .ok_or(IndexInconsistency::BundleContractUnknown(bundle_id))?;
         Ok((witness_ids.iter().copied(), *contract_id))
     }
+
+    fn contract_bundle_ids(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<BTreeSet<BundleId>, Self::Error> {
+        Ok(self
+            .bundle_contract_index
+            .iter()
+            .filter(|(_, id)| **id == contract_id)
+            .map(|(bundle_id, _)| *bundle_id)
+            .collect())
+    }
+
+    fn spenders(&self, opout: Opout) -> Result<BTreeSet<OpId>, Self::Error> {
+        Ok(self
+            .spent_index
+            .get(&opout)
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default())
+    }
+
+    fn contested_opouts(&self) -> Result<BTreeMap<Opout, BTreeSet<OpId>>, Self::Error> {
+        Ok(self
+            .spent_index
+            .iter()
+            .filter(|(_, ids)| ids.len() > 1)
+            .map(|(opout, ids)| (*opout, ids.iter().copied().collect()))
+            .collect())
+    }
 }
 
 impl IndexWriteProvider for MemIndex {
@@ -1421,6 +1859,24 @@ impl IndexWriteProvider for MemIndex {
         Ok(!present)
     }
 
+    fn register_spending(
+        &mut self,
+        opout: Opout,
+        opid: OpId,
+    ) -> Result<bool, IndexWriteError<Self::Error>> {
+        match self.spent_index.get_mut(&opout) {
+            Some(ids) if ids.contains(&opid) => Ok(false),
+            Some(ids) => {
+                ids.push(opid)?;
+                Ok(true)
+            }
+            None => {
+                self.spent_index.insert(opout, tiny_bset!(opid))?;
+                Ok(true)
+            }
+        }
+    }
+
     fn index_genesis_assignments<State: ExposedState>(
         &mut self,
         contract_id: ContractId,
@@ -1491,6 +1947,95 @@ impl IndexWriteProvider for MemIndex {
         // We need two cycles due to the borrow checker
         self.extend_terminals(vec, opid, type_id)
     }
+
+    fn replace_contract(
+        &mut self,
+        contract_id: ContractId,
+        rebuilt: &MemIndex,
+    ) -> Result<(), Self::Error> {
+        // Operations which entries are dropped: the ones indexed for the contract
+        // before and the ones of the rebuilt index
+        let stale = self
+            .bundle_contract_index
+            .iter()
+            .filter(|(_, id)| **id == contract_id)
+            .map(|(bundle_id, _)| *bundle_id)
+            .collect::<BTreeSet<_>>();
+        let mut opids = self
+            .op_bundle_index
+            .iter()
+            .filter(|(_, bundle_id)| stale.contains(*bundle_id))
+            .map(|(opid, _)| *opid)
+            .chain(rebuilt.op_bundle_index.keys().copied())
+            .collect::<BTreeSet<_>>();
+        opids.insert(OpId::from_inner(contract_id.into_inner()));
+        for index in self
+            .contract_index
+            .get(&contract_id)
+            .into_iter()
+            .chain(rebuilt.contract_index.get(&contract_id))
+        {
+            opids.extend(
+                index
+                    .public_opouts
+                    .iter()
+                    .chain(index.outpoint_opouts.values().flatten())
+                    .map(|opout| opout.op),
+            );
+        }
+
+        self.begin_transaction()?;
+        for bundle_id in &stale {
+            self.bundle_contract_index.remove(bundle_id)?;
+            self.bundle_witness_index.remove(bundle_id)?;
+        }
+        for opid in &opids {
+            self.op_bundle_index.remove(opid)?;
+        }
+        self.contract_index.remove(&contract_id)?;
+        let terminals = mem::take(&mut self.terminal_index)
+            .release()
+            .into_iter()
+            .filter_map(|(seal, opouts)| {
+                let opouts = opouts
+                    .into_iter()
+                    .filter(|opout| !opids.contains(&opout.op))
+                    .collect::<BTreeSet<_>>();
+                (!opouts.is_empty()).then(|| (seal, Confined::from_checked(opouts)))
+            });
+        self.terminal_index = Confined::from_checked(terminals.collect());
+        let spent = mem::take(&mut self.spent_index)
+            .release()
+            .into_iter()
+            .filter(|(opout, _)| !opids.contains(&opout.op));
+        self.spent_index = Confined::from_checked(spent.collect());
+
+        for (opid, bundle_id) in &rebuilt.op_bundle_index {
+            self.op_bundle_index.insert(*opid, *bundle_id)?;
+        }
+        for (bundle_id, id) in &rebuilt.bundle_contract_index {
+            self.bundle_contract_index.insert(*bundle_id, *id)?;
+        }
+        for (bundle_id, witness_ids) in &rebuilt.bundle_witness_index {
+            self.bundle_witness_index
+                .insert(*bundle_id, witness_ids.clone())?;
+        }
+        for (id, index) in &rebuilt.contract_index {
+            self.contract_index.insert(*id, index.clone())?;
+        }
+        for (seal, opouts) in &rebuilt.terminal_index {
+            match self.terminal_index.get_mut(seal) {
+                Some(present) => present.extend(opouts.iter().copied())?,
+                None => {
+                    self.terminal_index.insert(*seal, opouts.clone())?;
+                }
+            }
+        }
+        for (opout, opids) in &rebuilt.spent_index {
+            self.spent_index.insert(*opout, opids.clone())?;
+        }
+        self.commit_transaction()
+    }
 }
 
 impl MemIndex {
@@ -34,30 +34,69 @@ mod stock;
 mod stash;
 mod state;
 mod index;
+mod proof;
+mod history;
+mod bloom;
+mod payment;
+mod consolidation;
+mod upgrade;
+mod conflict;
+mod cpfp;
+mod invoices;
+mod inflation;
+mod replacement;
+mod spend;
+mod onboard;
+mod sync;
+#[cfg(feature = "analytics")]
+mod analytics;
 
 mod memory;
 #[cfg(feature = "fs")]
 pub mod fs;
 
+#[cfg(feature = "analytics")]
+pub use analytics::{allocation_schema, operation_schema, witness_schema, ContractExport};
+pub use bloom::{BloomFilter, MembershipFilter, BLOOM_FALSE_POSITIVE_RATE};
+pub use conflict::{Conflict, ConflictBranch, ConflictError, ConflictReport};
+pub use consolidation::{ConsolidationPolicy, ConsolidationReport};
+pub use cpfp::{CpfpError, CpfpPackage, CpfpStatus};
+pub use history::{ContractHistory, HistoryEdge, HistoryLink, HistoryNode};
 pub use index::{
     Index, IndexError, IndexInconsistency, IndexProvider, IndexReadError, IndexReadProvider,
     IndexWriteError, IndexWriteProvider,
 };
-pub use memory::{
-    MemContract, MemContractState, MemError, MemGlobalState, MemIndex, MemStash, MemState,
+pub use memory::{ArchiveProvider, MemArchive, MemError, MemIndex, MemStash, MemState};
+#[doc(hidden)]
+pub use memory::{MemContract, MemContractState, MemGlobalState};
+pub use inflation::{
+    InflationReport, InflationRight, InflationViolation, Issuance, RGB20_ASSIGNMENT_INFLATION,
 };
+pub use invoices::{
+    BatchInvoiceError, BatchSeals, InvoiceBatch, InvoiceMatch, InvoiceRecord, InvoiceRequest,
+};
+pub use onboard::{IssuerVerdict, OnboardReport, SigCheck, TrustPolicy};
+pub use payment::{PaymentProof, PaymentProofError};
+pub use proof::{AllocationCommitment, AllocationLeaf, AllocationProof, ALLOCATION_LEAF_TAG};
+pub use replacement::{ReplacementKind, WitnessReplacement};
+pub use spend::{AuthorizeAll, SpendAuthorizer, SpendRequest};
 pub use stash::{
-    ProviderError as StashProviderError, SchemaIfaces, Stash, StashDataError, StashError,
-    StashInconsistency, StashProvider, StashReadProvider, StashWriteProvider,
+    CloseMethodPolicy, LabelRef, Provenance, ProvenanceRef, ProviderError as StashProviderError,
+    Reservation, SchemaIfaces, Stash, StashDataError, StashError, StashInconsistency,
+    StashProvider, StashReadProvider, StashWriteProvider,
 };
 pub use state::{
     ContractStateRead, ContractStateWrite, PersistedState, State, StateError, StateInconsistency,
     StateProvider, StateReadProvider, StateWriteProvider,
 };
 pub use stock::{
-    ComposeError, ConsignError, ContractIfaceError, FasciaError, InputError as StockInputError,
-    Stock, StockError, StockErrorAll, StockErrorMem, UpdateRes,
+    AcceptOptions, AcceptReport, ComposeError, ConfirmationError, ConsignError,
+    ContractIfaceError, DependencyError, Discrepancy, FasciaError, InputError as StockInputError,
+    RecomputeError, ReindexReport, ResumeReport, Stock, StockError, StockErrorAll, StockErrorMem,
+    UpdateRes, WatchItem, RESERVATION_TIMEOUT,
 };
+pub use sync::{KnownOps, SyncReport, SyncRequest, SyncResponse};
+pub use upgrade::{ContractUpgrade, UpgradeEntry, UpgradeError, UpgradeStatus};
 
 pub trait StoreTransaction {
     type TransactionErr: std::error::Error;
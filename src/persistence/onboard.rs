@@ -0,0 +1,287 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verified onboarding of contracts.
+//!
+//! Contracts received from untrusted sources may be imported with
+//! [`Stock::import_contract_verified`], which in addition to the consignment
+//! validation checks signatures of the contract issuer over the genesis and
+//! the supplements against a [`TrustPolicy`], reporting the verdict in an
+//! auditable [`OnboardReport`].
+//!
+//! [`Stock::import_contract_verified`]: super::Stock::import_contract_verified
+
+use std::collections::BTreeMap;
+
+use rgb::validation::{self, Validity};
+use rgb::{ContractId, Identity};
+
+use crate::containers::{Consignment, ConsignmentExt, ContentId, SigValidator, SupplId, TrustLevel};
+
+/// Policy for accepting contracts depending on the signatures of their
+/// issuers.
+#[derive(Clone, Debug)]
+pub struct TrustPolicy<V: SigValidator> {
+    /// Validator of the signatures carried by the consignments.
+    pub validator: V,
+    /// Trust levels of the known identities. Identities not listed here have
+    /// [`TrustLevel::Unknown`] level.
+    pub identities: BTreeMap<Identity, TrustLevel>,
+    /// Minimal trust level of the issuer required to accept a contract.
+    pub min_trust: TrustLevel,
+    /// Whether contracts without a valid issuer signature over the genesis
+    /// and over all supplements created by the issuer must be rejected.
+    pub require_signature: bool,
+}
+
+impl<V: SigValidator> TrustPolicy<V> {
+    /// Constructs policy accepting contracts of all issuers not known to be
+    /// malicious, including unsigned contracts.
+    pub fn with(validator: V) -> Self {
+        TrustPolicy {
+            validator,
+            identities: none!(),
+            min_trust: TrustLevel::Unknown,
+            require_signature: false,
+        }
+    }
+
+    /// Returns trust level of the identity under this policy.
+    pub fn trust_level(&self, identity: &Identity) -> TrustLevel {
+        self.identities.get(identity).copied().unwrap_or_default()
+    }
+
+    /// Detects whether a contract with the verdict must be imported.
+    pub fn accepts(&self, verdict: &IssuerVerdict) -> bool {
+        match verdict {
+            IssuerVerdict::Verified => true,
+            IssuerVerdict::Unsigned | IssuerVerdict::UnsignedSupplement(_) => {
+                !self.require_signature
+            }
+            IssuerVerdict::Untrusted(_) | IssuerVerdict::BadSignature(_) => false,
+        }
+    }
+
+    /// Checks all signatures carried by the consignment and produces verdict
+    /// on its issuer.
+    pub fn check<const TRANSFER: bool>(
+        &self,
+        consignment: &Consignment<TRANSFER>,
+    ) -> (Vec<SigCheck>, IssuerVerdict) {
+        let issuer = &consignment.genesis.issuer;
+        let checks = consignment
+            .signatures
+            .iter()
+            .flat_map(|(content_id, sigs)| {
+                sigs.iter().map(|(identity, sig)| SigCheck {
+                    content_id: *content_id,
+                    signer: identity.clone(),
+                    trust: self.trust_level(identity),
+                    valid: self
+                        .validator
+                        .validate_sig(identity, *content_id, sig.clone()),
+                })
+            })
+            .collect::<Vec<_>>();
+        let signed = |content_id: ContentId| {
+            checks.iter().any(|check| {
+                check.content_id == content_id && &check.signer == issuer && check.valid
+            })
+        };
+
+        let trust = self.trust_level(issuer);
+        let verdict =
+            if trust < self.min_trust {
+                IssuerVerdict::Untrusted(trust)
+            } else if let Some(check) = checks
+                .iter()
+                .find(|check| &check.signer == issuer && !check.valid)
+            {
+                IssuerVerdict::BadSignature(check.content_id)
+            } else if !signed(ContentId::Genesis(consignment.contract_id())) {
+                IssuerVerdict::Unsigned
+            } else if let Some(suppl) = consignment.supplements.iter().find(|suppl| {
+                &suppl.creator == issuer && !signed(ContentId::Suppl(suppl.suppl_id()))
+            }) {
+                IssuerVerdict::UnsignedSupplement(suppl.suppl_id())
+            } else {
+                IssuerVerdict::Verified
+            };
+        (checks, verdict)
+    }
+}
+
+/// Result of checking a single signature carried by a consignment.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SigCheck {
+    pub content_id: ContentId,
+    pub signer: Identity,
+    pub trust: TrustLevel,
+    pub valid: bool,
+}
+
+/// Verdict on the contract issuer.
+#[derive(Clone, Eq, PartialEq, Debug, Display)]
+#[display(doc_comments)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum IssuerVerdict {
+    /// genesis and all the supplements created by the issuer are signed by
+    /// the issuer.
+    Verified,
+
+    /// genesis is not signed by the issuer.
+    Unsigned,
+
+    /// supplement {0} claims to be created by the issuer, but is not signed by
+    /// the issuer.
+    UnsignedSupplement(SupplId),
+
+    /// issuer has trust level {0}, which is below the policy requirements.
+    Untrusted(TrustLevel),
+
+    /// signature of the issuer over {0:?} is invalid.
+    BadSignature(ContentId),
+}
+
+/// Auditable report on contract onboarding.
+#[derive(Clone, Debug)]
+pub struct OnboardReport {
+    pub contract_id: ContractId,
+    pub issuer: Identity,
+    /// Status of the consignment validation.
+    pub status: validation::Status,
+    /// Results of checking each of the signatures carried by the consignment.
+    pub signatures: Vec<SigCheck>,
+    pub verdict: IssuerVerdict,
+    /// Whether the contract was imported into the stock.
+    pub imported: bool,
+}
+
+impl OnboardReport {
+    /// Detects whether the consignment has passed the validation.
+    pub fn is_valid(&self) -> bool { self.status.validity() == Validity::Valid }
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::{Confined, NonEmptyBlob, U8};
+    use strict_encoding::{StrictDumb, StrictSerialize};
+
+    use super::*;
+    use crate::containers::{ContentSigs, Contract, SigBlob, Supplement};
+
+    struct Validator;
+    impl SigValidator for Validator {
+        fn validate_sig(&self, _: &Identity, content_id: ContentId, sig: SigBlob) -> bool {
+            sig == signature(content_id)
+        }
+    }
+
+    fn signature(content_id: ContentId) -> SigBlob {
+        let data = content_id.to_strict_serialized::<U8>().unwrap();
+        SigBlob::from(NonEmptyBlob::from_slice_checked(data.as_slice()))
+    }
+
+    #[test]
+    fn issuer_verdict() {
+        let mut policy = TrustPolicy::with(Validator);
+        let mut contract = Contract::strict_dumb();
+        let issuer = contract.genesis.issuer.clone();
+        let contract_id = contract.contract_id();
+        let sign = |contract: &mut Contract, content_id, sig: SigBlob| {
+            let sigs = ContentSigs::from(Confined::with((issuer.clone(), sig)));
+            contract.signatures.insert(content_id, sigs).unwrap();
+        };
+
+        assert_eq!(policy.check(&contract).1, IssuerVerdict::Unsigned);
+        assert!(policy.accepts(&IssuerVerdict::Unsigned));
+
+        sign(
+            &mut contract,
+            ContentId::Genesis(contract_id),
+            signature(ContentId::Genesis(contract_id)),
+        );
+        let (checks, verdict) = policy.check(&contract);
+        assert_eq!(verdict, IssuerVerdict::Verified);
+        assert!(checks[0].valid);
+
+        let suppl = Supplement::new(contract_id, issuer.clone());
+        let suppl_id = suppl.suppl_id();
+        contract.supplements.push(suppl).unwrap();
+        assert_eq!(policy.check(&contract).1, IssuerVerdict::UnsignedSupplement(suppl_id));
+        policy.require_signature = true;
+        assert!(!policy.accepts(&IssuerVerdict::UnsignedSupplement(suppl_id)));
+
+        sign(&mut contract, ContentId::Suppl(suppl_id), SigBlob::default());
+        assert_eq!(
+            policy.check(&contract).1,
+            IssuerVerdict::BadSignature(ContentId::Suppl(suppl_id))
+        );
+        sign(&mut contract, ContentId::Suppl(suppl_id), signature(ContentId::Suppl(suppl_id)));
+        assert_eq!(policy.check(&contract).1, IssuerVerdict::Verified);
+
+        policy.identities.insert(issuer, TrustLevel::Malicious);
+        assert_eq!(policy.check(&contract).1, IssuerVerdict::Untrusted(TrustLevel::Malicious));
+    }
+
+    #[test]
+    fn copied_signature() {
+        let policy = TrustPolicy::with(Validator);
+        let mut contract = Contract::strict_dumb();
+        let issuer = contract.genesis.issuer.clone();
+        let contract_id = contract.contract_id();
+        let sign = |contract: &mut Contract, content_id, sig: SigBlob| {
+            let sigs = ContentSigs::from(Confined::with((issuer.clone(), sig)));
+            contract.signatures.insert(content_id, sigs).unwrap();
+        };
+
+        let other_id = ContractId::from([0xA5u8; 32]);
+        assert_ne!(other_id, contract_id);
+        sign(
+            &mut contract,
+            ContentId::Genesis(contract_id),
+            signature(ContentId::Genesis(other_id)),
+        );
+        assert_eq!(
+            policy.check(&contract).1,
+            IssuerVerdict::BadSignature(ContentId::Genesis(contract_id))
+        );
+
+        let genesis_sig = signature(ContentId::Genesis(contract_id));
+        sign(&mut contract, ContentId::Genesis(contract_id), genesis_sig.clone());
+        let suppl = Supplement::new(contract_id, issuer.clone());
+        let suppl_id = suppl.suppl_id();
+        contract.supplements.push(suppl).unwrap();
+        sign(&mut contract, ContentId::Suppl(suppl_id), genesis_sig);
+        let (checks, verdict) = policy.check(&contract);
+        assert_eq!(verdict, IssuerVerdict::BadSignature(ContentId::Suppl(suppl_id)));
+        assert_eq!(checks.iter().filter(|check| check.valid).count(), 1);
+    }
+}
@@ -0,0 +1,211 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proofs of invoice payments.
+//!
+//! A payee holding an accepted transfer can prove that an invoice was paid by
+//! revealing the blinded seal from the invoice and disclosing the state
+//! transition bundle assigning the state to that seal, together with its
+//! anchor to a witness transaction. The proof is verified against the
+//! invoice and an independent witness resolver, which confirms that the
+//! witness transaction is mined and contains the anchor commitment.
+
+use commit_verify::Conceal;
+use invoice::{Amount, Beneficiary, InvoiceState, RgbInvoice};
+use rgb::validation::ResolveWitness;
+use rgb::vm::WitnessOrd;
+use rgb::{ContractId, GraphSeal, Opout, XChain, XWitnessId};
+
+use crate::containers::{verify_anchor, WitnessBundle};
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum PaymentProofError {
+    /// invoice doesn't specify a contract.
+    NoContract,
+
+    /// invoice beneficiary is not a blinded seal; payments to witness outputs
+    /// can't be proven.
+    NotBlinded,
+
+    /// blinded seal of the invoice is not known to the stash.
+    UnknownSeal,
+
+    /// proof is made for contract {0} which doesn't match the invoice.
+    ContractMismatch(ContractId),
+
+    /// proof seal doesn't match blinded seal of the invoice.
+    SealMismatch,
+
+    /// operation {0} doesn't assign state to the invoice seal.
+    NoAssignment(Opout),
+
+    /// assigned amount {0} is less than the invoiced amount {1}.
+    InsufficientAmount(Amount, Amount),
+
+    /// witness transaction {0} can't be resolved: {1}
+    Unresolved(XWitnessId, String),
+
+    /// witness transaction {0} is not mined.
+    Unconfirmed(XWitnessId),
+
+    /// witness transaction {0} doesn't commit to the transition bundle: {1}
+    InvalidAnchor(XWitnessId, String),
+}
+
+/// Proof binding a paid invoice to the settling state transition and its
+/// witness transaction.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct PaymentProof {
+    pub contract_id: ContractId,
+    /// Revealed invoice beneficiary seal.
+    pub seal: XChain<GraphSeal>,
+    /// Assignment of the payment to the seal.
+    pub opout: Opout,
+    /// Bundle containing the transition with the assignment, anchored to its
+    /// witness transaction.
+    pub witness_bundle: WitnessBundle,
+}
+
+impl PaymentProof {
+    pub fn witness_id(&self) -> XWitnessId { self.witness_bundle.witness_id() }
+
+    /// Verifies that the proof settles the invoice with a mined witness
+    /// transaction, returning the witness mining status.
+    pub fn verify(
+        &self,
+        invoice: &RgbInvoice,
+        resolver: &impl ResolveWitness,
+    ) -> Result<WitnessOrd, PaymentProofError> {
+        let contract_id = invoice.contract.ok_or(PaymentProofError::NoContract)?;
+        if contract_id != self.contract_id {
+            return Err(PaymentProofError::ContractMismatch(self.contract_id));
+        }
+        let Beneficiary::BlindedSeal(secret) = invoice.beneficiary.into_inner() else {
+            return Err(PaymentProofError::NotBlinded);
+        };
+        if self.seal.layer1() != invoice.layer1()
+            || self.seal.as_reduced_unsafe().conceal() != secret
+        {
+            return Err(PaymentProofError::SealMismatch);
+        }
+
+        let bundle = &self.witness_bundle.bundle;
+        let assignments = bundle
+            .known_transitions
+            .get(&self.opout.op)
+            .and_then(|transition| transition.assignments.get(&self.opout.ty))
+            .ok_or(PaymentProofError::NoAssignment(self.opout))?;
+        let assigned = assignments.to_confidential_seals();
+        if assigned.get(self.opout.no as usize) != Some(&self.seal.map_ref(GraphSeal::conceal)) {
+            return Err(PaymentProofError::NoAssignment(self.opout));
+        }
+        if let InvoiceState::Amount(invoiced) = invoice.owned_state {
+            let paid = assignments
+                .as_fungible_state_at(self.opout.no)
+                .ok()
+                .flatten()
+                .map(|state| Amount::from(state.value))
+                .unwrap_or_default();
+            if paid < invoiced {
+                return Err(PaymentProofError::InsufficientAmount(paid, invoiced));
+            }
+        }
+
+        let witness_id = self.witness_id();
+        let ord = resolver
+            .resolve_pub_witness_ord(witness_id)
+            .map_err(|err| PaymentProofError::Unresolved(witness_id, err.to_string()))?;
+        if !matches!(ord, WitnessOrd::Mined(_)) {
+            return Err(PaymentProofError::Unconfirmed(witness_id));
+        }
+        let tx = resolver
+            .resolve_pub_witness(witness_id)
+            .map_err(|err| PaymentProofError::Unresolved(witness_id, err.to_string()))?;
+        verify_anchor(
+            &self.witness_bundle.anchor,
+            contract_id,
+            bundle.bundle_id(),
+            tx.as_reduced_unsafe(),
+        )
+        .map_err(|err| PaymentProofError::InvalidAnchor(witness_id, err.to_string()))?;
+
+        Ok(ord)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::seals::txout::CloseMethod;
+    use bp::{Txid, Vout};
+    use invoice::{RgbInvoiceBuilder, XChainNet};
+    use rgb::{AssignmentType, OpId};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::interface::resolver::DumbResolver;
+
+    #[test]
+    fn verify_rejects() {
+        let seal = XChain::Bitcoin(GraphSeal::new_random(
+            CloseMethod::OpretFirst,
+            Txid::from([1u8; 32]),
+            Vout::from_u32(0),
+        ));
+        let mut proof = PaymentProof {
+            contract_id: ContractId::strict_dumb(),
+            seal,
+            opout: Opout::new(OpId::strict_dumb(), AssignmentType::with(4000), 0),
+            witness_bundle: WitnessBundle::strict_dumb(),
+        };
+        let beneficiary = |seal: &XChain<GraphSeal>| {
+            XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(seal.as_reduced_unsafe().conceal()))
+        };
+        let invoice = RgbInvoiceBuilder::with(ContractId::strict_dumb(), beneficiary(&seal))
+            .set_amount_raw(100u64)
+            .finish();
+
+        let mut other = invoice.clone();
+        other.contract = None;
+        assert_eq!(proof.verify(&other, &DumbResolver), Err(PaymentProofError::NoContract));
+        other.contract = Some(ContractId::from([1u8; 32]));
+        assert_eq!(
+            proof.verify(&other, &DumbResolver),
+            Err(PaymentProofError::ContractMismatch(proof.contract_id))
+        );
+
+        assert_eq!(
+            proof.verify(&invoice, &DumbResolver),
+            Err(PaymentProofError::NoAssignment(proof.opout))
+        );
+        proof.seal = XChain::Bitcoin(GraphSeal::new_random(
+            CloseMethod::OpretFirst,
+            Txid::from([1u8; 32]),
+            Vout::from_u32(0),
+        ));
+        assert_eq!(proof.verify(&invoice, &DumbResolver), Err(PaymentProofError::SealMismatch));
+    }
+}
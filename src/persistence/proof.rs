@@ -0,0 +1,270 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Merkle proofs of contract allocations.
+//!
+//! Allocations of a contract are ordered by their opouts and merklized
+//! according to LNPBP-81 procedure, producing a state root. A server
+//! publishing the state root can provide light clients with compact proofs
+//! of inclusion of a specific allocation into the state.
+//!
+//! Leaves commit to the contract id and to the revealed allocation state
+//! without blinding factors and salts, such that proofs can't be re-used for
+//! other contracts and don't disclose secrets allowing to link the allocation
+//! to its concealed on-chain commitments.
+
+use amplify::confinement::{Confined, U32};
+use amplify::Bytes32;
+use commit_verify::merkle::MerkleHash;
+use commit_verify::{CommitId, DigestExt, Sha256};
+use rgb::{ContractId, Opout, XOutputSeal};
+use strict_encoding::{StrictEncode, StrictWriter};
+
+use crate::interface::AllocatedState;
+use crate::LIB_NAME_RGB_STD;
+
+/// Tag used for hashing allocations into merkle tree leaves.
+pub const ALLOCATION_LEAF_TAG: &str = "urn:lnp-bp:rgb:allocation#2024-10-14";
+
+/// Allocation of a contract state, which is a leaf of the state merkle tree.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct AllocationLeaf {
+    pub opout: Opout,
+    pub seal: XOutputSeal,
+    pub state: AllocatedState,
+}
+
+/// Commitment to an allocation of a specific contract, used as a merkle tree
+/// leaf.
+#[derive(Wrapper, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, From)]
+#[wrapper(Deref, BorrowSlice, Hex)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[derive(CommitEncode)]
+#[commit_encode(strategy = strict, id = MerkleHash)]
+pub struct AllocationCommitment(Bytes32);
+
+impl AllocationLeaf {
+    /// Computes commitment to the allocation of the contract.
+    pub fn commitment(&self, contract_id: ContractId) -> AllocationCommitment {
+        let writer = StrictWriter::in_memory::<{ usize::MAX }>();
+        let writer = contract_id
+            .strict_encode(writer)
+            .and_then(|w| self.opout.strict_encode(w))
+            .and_then(|w| self.seal.strict_encode(w))
+            .and_then(|w| self.state.strict_encode(w))
+            .expect("in-memory encoding");
+        let data = writer.unbox().unconfine();
+
+        let mut hasher = Sha256::from_tag(ALLOCATION_LEAF_TAG);
+        hasher.input_raw(&data);
+        AllocationCommitment(hasher.finish().into())
+    }
+
+    /// Computes hash of the allocation used as a merkle tree leaf.
+    pub fn leaf_hash(&self, contract_id: ContractId) -> MerkleHash {
+        self.commitment(contract_id).commit_id()
+    }
+}
+
+/// Proof of inclusion of an allocation into the contract state with a
+/// specific state root.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct AllocationProof {
+    pub contract_id: ContractId,
+    pub leaf: AllocationLeaf,
+    /// Position of the leaf in the merkle tree.
+    pub index: u32,
+    /// Number of the leaves in the merkle tree.
+    pub width: u32,
+    /// Hashes of the sibling branches, starting from the root.
+    pub path: Vec<MerkleHash>,
+}
+
+impl AllocationProof {
+    /// Computes state root from the proof.
+    ///
+    /// Returns `None` if the proof is malformed.
+    pub fn root(&self) -> Option<MerkleHash> {
+        if self.index >= self.width {
+            return None;
+        }
+        let leaf = self.leaf.leaf_hash(self.contract_id);
+        if self.width == 1 {
+            return self.path.is_empty().then_some(leaf);
+        }
+        let mut path = self.path.iter().copied();
+        let root = replay(leaf, self.index, self.width, 0, self.width, &mut path)?;
+        path.next().is_none().then_some(root)
+    }
+
+    /// Verifies that the allocation is a part of the state with the provided
+    /// state root.
+    pub fn verify(&self, state_root: MerkleHash) -> bool { self.root() == Some(state_root) }
+}
+
+/// Computes state root for the contract allocations ordered by their opouts.
+///
+/// Returns `None` if there are no allocations.
+pub(super) fn state_root(contract_id: ContractId, leaves: &[AllocationLeaf]) -> Option<MerkleHash> {
+    let commitments = leaves
+        .iter()
+        .map(|leaf| leaf.commitment(contract_id))
+        .collect::<Vec<_>>();
+    let commitments = Confined::<_, 1, U32>::try_from(commitments).ok()?;
+    Some(MerkleHash::merklize(&commitments))
+}
+
+/// Constructs path of the sibling hashes for the leaf at the given index.
+pub(super) fn merkle_path(leaves: &[MerkleHash], index: usize) -> Vec<MerkleHash> {
+    let width = u32::try_from(leaves.len()).expect("too many allocations");
+    let mut path = vec![];
+    if width > 1 {
+        collect_path(leaves, index, 0, width, &mut path);
+    }
+    path
+}
+
+fn merklize(leaves: &[MerkleHash], depth: u8, base_width: u32) -> MerkleHash {
+    match leaves {
+        [] => MerkleHash::void(depth, base_width),
+        [leaf] => MerkleHash::single(depth, base_width, *leaf),
+        [leaf1, leaf2] => MerkleHash::branches(depth, base_width, *leaf1, *leaf2),
+        _ => {
+            let (left, right) = leaves.split_at(split(leaves.len()));
+            MerkleHash::branches(
+                depth,
+                base_width,
+                merklize(left, depth + 1, base_width),
+                merklize(right, depth + 1, base_width),
+            )
+        }
+    }
+}
+
+fn collect_path(
+    leaves: &[MerkleHash],
+    index: usize,
+    depth: u8,
+    base_width: u32,
+    path: &mut Vec<MerkleHash>,
+) {
+    match leaves.len() {
+        0 | 1 => {}
+        2 => path.push(leaves[1 - index]),
+        len => {
+            let div = split(len);
+            let (left, right) = leaves.split_at(div);
+            if index < div {
+                path.push(merklize(right, depth + 1, base_width));
+                collect_path(left, index, depth + 1, base_width, path);
+            } else {
+                path.push(merklize(left, depth + 1, base_width));
+                collect_path(right, index - div, depth + 1, base_width, path);
+            }
+        }
+    }
+}
+
+fn replay(
+    leaf: MerkleHash,
+    index: u32,
+    branch_width: u32,
+    depth: u8,
+    base_width: u32,
+    path: &mut impl Iterator<Item = MerkleHash>,
+) -> Option<MerkleHash> {
+    match branch_width {
+        0 => None,
+        1 => Some(MerkleHash::single(depth, base_width, leaf)),
+        2 => {
+            let sibling = path.next()?;
+            Some(if index == 0 {
+                MerkleHash::branches(depth, base_width, leaf, sibling)
+            } else {
+                MerkleHash::branches(depth, base_width, sibling, leaf)
+            })
+        }
+        _ => {
+            let div = split(branch_width as usize) as u32;
+            let sibling = path.next()?;
+            Some(if index < div {
+                let branch = replay(leaf, index, div, depth + 1, base_width, path)?;
+                MerkleHash::branches(depth, base_width, branch, sibling)
+            } else {
+                let branch =
+                    replay(leaf, index - div, branch_width - div, depth + 1, base_width, path)?;
+                MerkleHash::branches(depth, base_width, sibling, branch)
+            })
+        }
+    }
+}
+
+fn split(len: usize) -> usize { len / 2 + len % 2 }
+
+#[cfg(test)]
+mod test {
+    use rgb::{AssignmentType, OpId};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    fn leaf(no: u16) -> AllocationLeaf {
+        AllocationLeaf {
+            opout: Opout::new(OpId::strict_dumb(), AssignmentType::with(1), no),
+            seal: XOutputSeal::strict_dumb(),
+            state: AllocatedState::Void,
+        }
+    }
+
+    #[test]
+    fn allocation_proofs() {
+        for width in 1..=9u16 {
+            let leaves = (0..width).map(leaf).collect::<Vec<_>>();
+            let contract_id = ContractId::strict_dumb();
+            let hashes = leaves
+                .iter()
+                .map(|leaf| leaf.leaf_hash(contract_id))
+                .collect::<Vec<_>>();
+            let root = state_root(contract_id, &leaves).unwrap();
+            if width == 1 {
+                assert_eq!(root, hashes[0]);
+            }
+
+            for (index, leaf) in leaves.into_iter().enumerate() {
+                let mut proof = AllocationProof {
+                    contract_id,
+                    leaf,
+                    index: index as u32,
+                    width: width as u32,
+                    path: merkle_path(&hashes, index),
+                };
+                assert!(proof.verify(root));
+                proof.contract_id = ContractId::from([0xA5u8; 32]);
+                assert!(!proof.verify(root));
+                proof.contract_id = contract_id;
+                proof.leaf.opout.no += 100;
+                assert!(!proof.verify(root));
+            }
+        }
+        assert_eq!(state_root(ContractId::strict_dumb(), &[]), None);
+    }
+}
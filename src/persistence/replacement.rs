@@ -0,0 +1,160 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Replacements of bundle witnesses.
+//!
+//! The same transition bundle may be anchored into different witness
+//! transactions: when the witness transaction is replaced with the one paying
+//! higher fees (RBF), or when the bundle gets anchored twice into independent
+//! transactions. The stock keeps all the witnesses, and the replacements are
+//! reported at the acceptance, such that the wallet may react on a
+//! conflicting double-anchor.
+
+use std::collections::BTreeSet;
+
+use bp::Outpoint;
+use rgb::vm::XWitnessTx;
+use rgb::{BundleId, ContractId, XWitnessId};
+
+/// Kind of the witness replacement.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display(lowercase)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum ReplacementKind {
+    /// Witness transactions spend the same inputs, thus only one of them may
+    /// get mined.
+    Rbf,
+    /// Witness transactions are independent, thus the bundle may get anchored
+    /// twice.
+    DoubleAnchor,
+    /// Witness transactions are not known and the replacement can't be
+    /// classified.
+    Unclassified,
+}
+
+impl ReplacementKind {
+    /// Classifies replacement of the previously recorded witness transaction
+    /// with a new one.
+    pub fn classify(prev: Option<&XWitnessTx>, new: Option<&XWitnessTx>) -> Self {
+        let (Some(prev), Some(new)) = (prev, new) else {
+            return ReplacementKind::Unclassified;
+        };
+        if prev.layer1() != new.layer1() {
+            return ReplacementKind::DoubleAnchor;
+        }
+        let spent = prev
+            .as_reduced_unsafe()
+            .inputs
+            .iter()
+            .map(|input| input.prev_output)
+            .collect::<BTreeSet<Outpoint>>();
+        if new
+            .as_reduced_unsafe()
+            .inputs
+            .iter()
+            .any(|input| spent.contains(&input.prev_output))
+        {
+            ReplacementKind::Rbf
+        } else {
+            ReplacementKind::DoubleAnchor
+        }
+    }
+}
+
+/// Bundle presented with a witness different from the ones previously
+/// recorded for it.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct WitnessReplacement {
+    pub contract_id: ContractId,
+    pub bundle_id: BundleId,
+    /// New witness of the bundle.
+    pub witness_id: XWitnessId,
+    /// Previously recorded witnesses of the bundle, together with the kind of
+    /// their replacement with the new one.
+    pub replaced: Vec<(XWitnessId, ReplacementKind)>,
+}
+
+impl WitnessReplacement {
+    /// Detects whether the new witness is an RBF replacement of all the
+    /// previously recorded witnesses.
+    pub fn is_rbf(&self) -> bool {
+        self.replaced
+            .iter()
+            .all(|(_, kind)| *kind == ReplacementKind::Rbf)
+    }
+
+    /// Detects whether the bundle may get anchored by more than a single
+    /// witness.
+    pub fn is_double_anchor(&self) -> bool {
+        self.replaced
+            .iter()
+            .any(|(_, kind)| *kind == ReplacementKind::DoubleAnchor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::Txid;
+    use rgb::XChain;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::broadcast::test::tx as bp_tx;
+
+    fn tx(inputs: &[u8]) -> XWitnessTx {
+        let inputs = inputs
+            .iter()
+            .map(|no| Outpoint::new(Txid::from([*no; 32]), 0))
+            .collect::<Vec<_>>();
+        XChain::Bitcoin(bp_tx(&inputs, &[1_000]))
+    }
+    #[test]
+    fn classify() {
+        let prev = tx(&[1, 2]);
+        assert_eq!(
+            ReplacementKind::classify(Some(&prev), Some(&tx(&[2, 3]))),
+            ReplacementKind::Rbf
+        );
+        assert_eq!(
+            ReplacementKind::classify(Some(&prev), Some(&tx(&[3]))),
+            ReplacementKind::DoubleAnchor
+        );
+        assert_eq!(ReplacementKind::classify(None, Some(&prev)), ReplacementKind::Unclassified);
+
+        let replacement = WitnessReplacement {
+            contract_id: ContractId::strict_dumb(),
+            bundle_id: BundleId::strict_dumb(),
+            witness_id: XWitnessId::strict_dumb(),
+            replaced: vec![(XWitnessId::strict_dumb(), ReplacementKind::Rbf)],
+        };
+        assert!(replacement.is_rbf());
+        assert!(!replacement.is_double_anchor());
+    }
+}
@@ -0,0 +1,130 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Authorization of allocations spent by composed transfers.
+//!
+//! Policy engines, like spending limits or second-factor confirmations, may be
+//! enforced by the library itself: [`Stock::compose_authorized`] and
+//! [`Stock::compose_batch_authorized`] ask the [`SpendAuthorizer`] for each
+//! allocation which is about to be spent, and fail with
+//! [`ComposeError::SpendVetoed`] once the authorizer vetoes any of them.
+//!
+//! [`Stock::compose_authorized`]: super::Stock::compose_authorized
+//! [`Stock::compose_batch_authorized`]: super::Stock::compose_batch_authorized
+//! [`ComposeError::SpendVetoed`]: super::ComposeError::SpendVetoed
+
+use invoice::{Amount, Beneficiary, XChainNet};
+use rgb::{ContractId, Opout, XOutputSeal};
+
+use super::PersistedState;
+
+/// Allocation which is about to be spent by a composed transfer.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SpendRequest<'a> {
+    pub contract_id: ContractId,
+    pub opout: Opout,
+    pub output: XOutputSeal,
+    pub state: &'a PersistedState,
+    /// Beneficiaries of the invoices paid by the transfer. Empty for the
+    /// allocations of other contracts which are moved to the wallet change
+    /// with blank state transitions.
+    pub destinations: &'a [XChainNet<Beneficiary>],
+}
+
+impl SpendRequest<'_> {
+    /// Returns amount of the allocation, if it contains fungible state.
+    pub fn amount(&self) -> Option<Amount> {
+        match self.state {
+            PersistedState::Amount(amount, _, _) => Some(*amount),
+            _ => None,
+        }
+    }
+
+    /// Detects whether the allocation is moved to the wallet change with a
+    /// blank state transition, not leaving the wallet.
+    pub fn is_blank(&self) -> bool { self.destinations.is_empty() }
+}
+
+/// Hook authorizing spending of allocations during transfer composition.
+pub trait SpendAuthorizer {
+    /// Authorizes spending of the allocation, or returns the reason of the
+    /// veto.
+    fn authorize(&self, request: &SpendRequest) -> Result<(), String>;
+}
+
+impl<F> SpendAuthorizer for F
+where F: Fn(&SpendRequest) -> Result<(), String>
+{
+    fn authorize(&self, request: &SpendRequest) -> Result<(), String> { self(request) }
+}
+
+/// Authorizer allowing spending of any allocation.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct AuthorizeAll;
+
+impl SpendAuthorizer for AuthorizeAll {
+    fn authorize(&self, _: &SpendRequest) -> Result<(), String> { Ok(()) }
+}
+
+#[cfg(test)]
+mod test {
+    use rgb::{AssignmentType, BlindingFactor, OpId};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn spending_limit() {
+        let limit = |request: &SpendRequest| match request.amount() {
+            Some(amount) if !request.is_blank() && amount > Amount::from(1000u64) => {
+                Err(format!("amount {amount} exceeds the limit"))
+            }
+            _ => Ok(()),
+        };
+        let state = PersistedState::Amount(
+            Amount::from(5000u64),
+            BlindingFactor::strict_dumb(),
+            strict_dumb!(),
+        );
+        let destinations = [XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(strict_dumb!()))];
+        let mut request = SpendRequest {
+            contract_id: ContractId::strict_dumb(),
+            opout: Opout::new(OpId::strict_dumb(), AssignmentType::with(1), 0),
+            output: XOutputSeal::strict_dumb(),
+            state: &state,
+            destinations: &destinations,
+        };
+        assert!(limit.authorize(&request).is_err());
+        assert_eq!(AuthorizeAll.authorize(&request), Ok(()));
+
+        request.destinations = &[];
+        assert!(request.is_blank());
+        assert_eq!(limit.authorize(&request), Ok(()));
+        assert_eq!(
+            SpendRequest {
+                state: &PersistedState::Void,
+                ..request
+            }
+            .amount(),
+            None
+        );
+    }
+}
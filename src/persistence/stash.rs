@@ -24,33 +24,37 @@ use std::error::Error;
 use std::fmt::Debug;
 
 use aluvm::library::{Lib, LibId};
-use amplify::confinement::{Confined, MediumBlob, TinyOrdMap};
+use amplify::confinement::{
+    Confined, MediumBlob, MediumOrdSet, SmallOrdSet, SmallString, TinyOrdMap,
+};
 use amplify::{confinement, ByteArray};
 use bp::dbc::anchor::MergeError;
 use bp::dbc::tapret::TapretCommitment;
 use bp::dbc::Anchor;
 use bp::seals::txout::CloseMethod;
+use chrono::Utc;
 use commit_verify::mpc;
 use commit_verify::mpc::MerkleBlock;
 use nonasync::persistence::{CloneNoPersistence, Persisting};
 use rgb::validation::{DbcProof, Scripts};
 use rgb::{
     AttachId, BundleId, ContractId, Extension, Genesis, GraphSeal, Identity, OpId, Operation,
-    Schema, SchemaId, TransitionBundle, XChain, XWitnessId,
+    Opout, Schema, SchemaId, TransitionBundle, XChain, XOutpoint, XWitnessId,
 };
 use strict_encoding::{FieldName, TypeName};
 use strict_types::typesys::UnknownType;
 use strict_types::TypeSystem;
 
 use crate::containers::{
-    AnchorSet, Consignment, ConsignmentExt, ContentId, ContentRef, ContentSigs, Kit, SealWitness,
-    SigBlob, Supplement, TrustLevel, WitnessBundle,
+    AnchorSet, Batch, CloseMethodSet, Consignment, ConsignmentExt, ConsignmentId, ContentId,
+    ContentRef, ContentSigs, Kit, SealWitness, SigBlob, SupplId, Supplement, Transfer, TrustLevel,
+    WitnessBundle,
 };
 use crate::interface::{
     ContractBuilder, Iface, IfaceClass, IfaceId, IfaceImpl, IfaceRef, TransitionBuilder,
 };
-use crate::persistence::{ContractIfaceError, StoreTransaction};
-use crate::{MergeReveal, MergeRevealError, SecretSeal, LIB_NAME_RGB_STD};
+use crate::persistence::{ContractIfaceError, MembershipFilter, StoreTransaction};
+use crate::{MergeReveal, MergeRevealError, SecretSeal, LIB_NAME_RGB_STD, LIB_NAME_RGB_STORAGE};
 
 #[derive(Debug, Display, Error, From)]
 #[display(inner)]
@@ -160,6 +164,9 @@ pub enum StashDataError {
     #[from]
     #[display(inner)]
     NoAbstractIface(ContractIfaceError),
+
+    /// supplement {0} doesn't carry an attestation about a contract.
+    NoAttestation(SupplId),
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -192,15 +199,197 @@ impl SchemaIfaces {
     }
 }
 
+/// Object to which a user-defined label can be attached.
+///
+/// Labels are local wallet data: they are persisted in the stash, and thus
+/// are a part of its backups, but they are not committed to and are never
+/// included into consignments.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display, From)]
+#[display(inner)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STORAGE, tags = order, dumb = LabelRef::Contract(strict_dumb!()))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum LabelRef {
+    #[from]
+    Contract(ContractId),
+    #[from]
+    Transfer(ConsignmentId),
+    #[from]
+    Allocation(Opout),
+}
+
+/// Object which source is tracked in the stash.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Display, From)]
+#[display(inner)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(
+    lib = LIB_NAME_RGB_STORAGE,
+    tags = order,
+    dumb = ProvenanceRef::Contract(strict_dumb!())
+)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum ProvenanceRef {
+    #[from]
+    Schema(SchemaId),
+    #[from]
+    Contract(ContractId),
+    #[from]
+    Transfer(ConsignmentId),
+}
+
+/// Source from which an object was first received.
+///
+/// Provenance is local wallet data persisted in the stash. Only the first
+/// source of an object is kept, such that objects re-sent through other
+/// channels later can be traced back to their origin.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STORAGE)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct Provenance {
+    /// Transport endpoint from which the object was received.
+    pub endpoint: SmallString,
+    /// Identity of the peer which has sent the object, if known.
+    pub peer: Option<Identity>,
+    /// Unix timestamp of the import.
+    pub timestamp: i64,
+}
+
+impl Provenance {
+    /// Constructs provenance of an object imported right now.
+    pub fn new(endpoint: SmallString, peer: Option<Identity>) -> Self {
+        Provenance {
+            endpoint,
+            peer,
+            timestamp: Utc::now().timestamp(),
+        }
+    }
+}
+
+/// Seal close methods used by a stock.
+///
+/// The policy is local wallet data persisted in the stash.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STORAGE)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct CloseMethodPolicy {
+    /// Methods which payers may use for the commitments, embedded into the
+    /// invoices generated by the stock.
+    pub accepted: CloseMethodSet,
+    /// Methods which the stock may use for the outgoing commitments.
+    pub outgoing: CloseMethodSet,
+}
+
+impl Default for CloseMethodPolicy {
+    fn default() -> Self {
+        Self {
+            accepted: CloseMethodSet::Both,
+            outgoing: CloseMethodSet::Both,
+        }
+    }
+}
+
+impl CloseMethodPolicy {
+    /// Policy using a single close method both for incoming and outgoing
+    /// transfers.
+    pub fn with(method: CloseMethod) -> Self {
+        Self {
+            accepted: method.into(),
+            outgoing: method.into(),
+        }
+    }
+}
+
+/// Outputs reserved by a composed batch of state transitions which is not
+/// yet anchored into a witness transaction.
+///
+/// Reservations are local wallet data persisted in the stash. A reservation is
+/// released once the batch gets accepted into the stock, or abandoned after
+/// its deadline, allowing the spent state to be used in another transfer.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STORAGE)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct Reservation {
+    /// Contract of the main state transition of the batch.
+    pub contract_id: ContractId,
+    /// Ids of all state transitions of the batch.
+    pub transitions: SmallOrdSet<OpId>,
+    /// Outputs spent by the batch.
+    pub outputs: MediumOrdSet<XOutpoint>,
+    /// Seals of the state assigned by the main state transition of the batch,
+    /// including invoice beneficiaries.
+    pub seals: SmallOrdSet<XChain<SecretSeal>>,
+    /// Unix timestamp after which the batch is considered abandoned.
+    pub deadline: i64,
+}
+
+impl Reservation {
+    /// Constructs reservation of the outputs spent by the batch until the
+    /// deadline.
+    ///
+    /// Fails if the batch contains more transitions, spent outputs or seals
+    /// than a reservation can hold.
+    pub fn new(batch: &Batch, deadline: i64) -> Result<Self, confinement::Error> {
+        let transitions = batch.clone().into_iter().collect::<Vec<_>>();
+        let seals = batch
+            .main
+            .iter()
+            .flat_map(|info| info.transition.assignments.values())
+            .flat_map(|assigns| assigns.to_confidential_seals());
+        Ok(Reservation {
+            contract_id: batch.main.first.transition.contract_id,
+            transitions: Confined::try_from_iter(transitions.iter().map(|info| info.id))?,
+            outputs: Confined::try_from_iter(
+                transitions
+                    .iter()
+                    .flat_map(|info| info.inputs.iter().copied()),
+            )?,
+            seals: Confined::try_from_iter(seals)?,
+            deadline,
+        })
+    }
+
+    /// Id of the reservation, matching the id of the main state transition of
+    /// the batch.
+    pub fn id(batch: &Batch) -> OpId { batch.main.first.id }
+
+    /// Detects whether the deadline of the reservation has passed.
+    pub fn is_expired(&self, now: i64) -> bool { self.deadline < now }
+}
+
 #[derive(Debug)]
 pub struct Stash<P: StashProvider> {
     provider: P,
+    filter: Option<MembershipFilter>,
 }
 
 impl<P: StashProvider> CloneNoPersistence for Stash<P> {
     fn clone_no_persistence(&self) -> Self {
         Self {
             provider: self.provider.clone_no_persistence(),
+            filter: self.filter.clone(),
         }
     }
 }
@@ -211,12 +400,85 @@ where P: Default
     fn default() -> Self {
         Self {
             provider: default!(),
+            filter: None,
         }
     }
 }
 
 impl<P: StashProvider> Stash<P> {
-    pub(super) fn new(provider: P) -> Self { Self { provider } }
+    pub(super) fn new(provider: P) -> Self {
+        Self {
+            provider,
+            filter: None,
+        }
+    }
+
+    /// Builds membership filters over all operations and bundles known to the
+    /// stash, reserving capacity for the given number of additional ones.
+    pub(super) fn enable_filter(&mut self, reserve: usize) -> Result<(), StashError<P>> {
+        let bundle_ids = self
+            .provider
+            .bundle_ids()
+            .map_err(StashError::ReadProvider)?
+            .collect::<Vec<_>>();
+        let mut opids = self
+            .provider
+            .geneses()
+            .map_err(StashError::ReadProvider)?
+            .map(Genesis::id)
+            .chain(
+                self.provider
+                    .extension_ids()
+                    .map_err(StashError::ReadProvider)?,
+            )
+            .collect::<Vec<_>>();
+        for bundle_id in &bundle_ids {
+            opids.extend(self.provider.bundle(*bundle_id)?.known_transitions.keys());
+        }
+
+        let mut filter =
+            MembershipFilter::with_capacity(opids.len() + reserve, bundle_ids.len() + reserve);
+        for opid in opids {
+            filter.insert_operation(opid);
+        }
+        for bundle_id in bundle_ids {
+            filter.insert_bundle(bundle_id);
+        }
+        self.filter = Some(filter);
+        Ok(())
+    }
+
+    pub(super) fn disable_filter(&mut self) { self.filter = None; }
+
+    pub(super) fn membership_filter(&self) -> Option<&MembershipFilter> { self.filter.as_ref() }
+
+    /// Detects whether the operation may be known to the stash, returning
+    /// `false` only if it is definitely unknown. Always returns `true` if the
+    /// membership filters are not enabled.
+    pub(super) fn may_know_operation(&self, opid: OpId) -> bool {
+        self.filter
+            .as_ref()
+            .map_or(true, |filter| filter.may_contain_operation(opid))
+    }
+
+    /// Detects whether the stash contains genesis or state extension with the
+    /// given id.
+    pub(super) fn has_genesis_or_extension(&self, opid: OpId) -> bool {
+        let contract_id = ContractId::from_byte_array(opid.to_byte_array());
+        self.may_know_operation(opid)
+            && (self.provider.genesis(contract_id).is_ok() || self.provider.extension(opid).is_ok())
+    }
+
+    /// Detects whether the bundle is known to the stash.
+    pub(super) fn has_bundle(&self, bundle_id: BundleId) -> bool {
+        self.may_know_bundle(bundle_id) && self.provider.bundle(bundle_id).is_ok()
+    }
+
+    fn may_know_bundle(&self, bundle_id: BundleId) -> bool {
+        self.filter
+            .as_ref()
+            .map_or(true, |filter| filter.may_contain_bundle(bundle_id))
+    }
 
     #[doc(hidden)]
     pub fn as_provider(&self) -> &P { &self.provider }
@@ -264,6 +526,29 @@ impl<P: StashProvider> Stash<P> {
     pub(super) fn witness(&self, witness_id: XWitnessId) -> Result<&SealWitness, StashError<P>> {
         Ok(self.provider.witness(witness_id)?)
     }
+    pub(super) fn extension(&self, opid: OpId) -> Result<&Extension, StashError<P>> {
+        Ok(self.provider.extension(opid)?)
+    }
+    pub(super) fn witness_ids(
+        &self,
+    ) -> Result<impl Iterator<Item = XWitnessId> + '_, StashError<P>> {
+        self.provider
+            .witness_ids()
+            .map_err(StashError::ReadProvider)
+    }
+    pub(super) fn extensions(
+        &self,
+    ) -> Result<impl Iterator<Item = &Extension> + '_, StashError<P>> {
+        let ids = self
+            .provider
+            .extension_ids()
+            .map_err(StashError::ReadProvider)?;
+        let mut extensions = vec![];
+        for opid in ids {
+            extensions.push(self.provider.extension(opid)?);
+        }
+        Ok(extensions.into_iter())
+    }
 
     pub(super) fn supplements(
         &self,
@@ -498,19 +783,29 @@ impl<P: StashProvider> Stash<P> {
             Ok(g) => g.clone().merge_reveal(consignment.genesis)?,
             Err(_) => consignment.genesis,
         };
+        let opid = genesis.id();
         self.provider
             .replace_genesis(genesis)
             .map_err(StashError::WriteProvider)?;
+        if let Some(filter) = &mut self.filter {
+            filter.insert_operation(opid);
+        }
 
         for extension in consignment.extensions {
             let opid = extension.id();
-            let extension = match self.provider.extension(opid) {
-                Ok(e) => e.clone().merge_reveal(extension)?,
-                Err(_) => extension,
+            let known = self.may_know_operation(opid);
+            let extension = match known.then(|| self.provider.extension(opid)) {
+                Some(Ok(e)) => e.clone().merge_reveal(extension)?,
+                _ => extension,
             };
             self.provider
                 .replace_extension(extension)
                 .map_err(StashError::WriteProvider)?;
+            if !known {
+                if let Some(filter) = &mut self.filter {
+                    filter.insert_operation(opid);
+                }
+            }
         }
 
         for bw in consignment.bundles {
@@ -596,10 +891,18 @@ impl<P: StashProvider> Stash<P> {
         &mut self,
         bundle: TransitionBundle,
     ) -> Result<bool, StashError<P>> {
-        let bundle = match self.provider.bundle(bundle.bundle_id()).cloned() {
-            Ok(b) => b.merge_reveal(bundle)?,
-            Err(_) => bundle,
+        let bundle_id = bundle.bundle_id();
+        let known = self.may_know_bundle(bundle_id);
+        let bundle = match known.then(|| self.provider.bundle(bundle_id).cloned()) {
+            Some(Ok(b)) => b.merge_reveal(bundle)?,
+            _ => bundle,
         };
+        if let Some(filter) = &mut self.filter {
+            filter.insert_bundle(bundle_id);
+            for opid in bundle.known_transitions.keys() {
+                filter.insert_operation(*opid);
+            }
+        }
         self.provider
             .replace_bundle(bundle)
             .map_err(StashError::WriteProvider)
@@ -618,6 +921,262 @@ impl<P: StashProvider> Stash<P> {
         self.commit_transaction()?;
         Ok(seal)
     }
+
+    pub(super) fn seal_secret(
+        &self,
+        secret: XChain<SecretSeal>,
+    ) -> Result<Option<XChain<GraphSeal>>, StashError<P>> {
+        self.provider
+            .seal_secret(secret)
+            .map_err(StashError::ReadProvider)
+    }
+
+    pub(super) fn label(&self, label_ref: LabelRef) -> Result<Option<&str>, StashError<P>> {
+        self.provider
+            .label(label_ref)
+            .map_err(StashError::ReadProvider)
+    }
+
+    pub(super) fn labels(
+        &self,
+    ) -> Result<impl Iterator<Item = (LabelRef, &str)> + '_, StashError<P>> {
+        self.provider.labels().map_err(StashError::ReadProvider)
+    }
+
+    pub(super) fn provenance(
+        &self,
+        obj: ProvenanceRef,
+    ) -> Result<Option<&Provenance>, StashError<P>> {
+        self.provider
+            .provenance(obj)
+            .map_err(StashError::ReadProvider)
+    }
+
+    pub(super) fn provenances(
+        &self,
+    ) -> Result<impl Iterator<Item = (ProvenanceRef, &Provenance)> + '_, StashError<P>> {
+        self.provider
+            .provenances()
+            .map_err(StashError::ReadProvider)
+    }
+
+    pub(super) fn reservations(
+        &self,
+    ) -> Result<impl Iterator<Item = (OpId, &Reservation)> + '_, StashError<P>> {
+        self.provider
+            .reservations()
+            .map_err(StashError::ReadProvider)
+    }
+
+    pub(crate) fn store_reservation(
+        &mut self,
+        id: OpId,
+        reservation: Option<Reservation>,
+    ) -> Result<bool, StashError<P>> {
+        self.begin_transaction()?;
+        let res = match reservation {
+            Some(reservation) => self.provider.replace_reservation(id, reservation),
+            None => self.provider.remove_reservation(id),
+        }
+        .inspect_err(|_| self.rollback_transaction())
+        .map_err(StashError::WriteProvider)?;
+        self.commit_transaction()?;
+        Ok(res)
+    }
+
+    pub(super) fn close_method_policy(&self) -> Result<CloseMethodPolicy, StashError<P>> {
+        self.provider
+            .close_method_policy()
+            .map_err(StashError::ReadProvider)
+    }
+
+    pub(super) fn store_close_method_policy(
+        &mut self,
+        policy: CloseMethodPolicy,
+    ) -> Result<(), StashError<P>> {
+        self.begin_transaction()?;
+        self.provider
+            .set_close_method_policy(policy)
+            .inspect_err(|_| self.rollback_transaction())
+            .map_err(StashError::WriteProvider)?;
+        self.commit_transaction()?;
+        Ok(())
+    }
+
+    pub(super) fn provisional_witnesses(&self) -> Result<BTreeMap<XWitnessId, u32>, StashError<P>> {
+        Ok(self
+            .provider
+            .provisional_witnesses()
+            .map_err(StashError::ReadProvider)?
+            .collect())
+    }
+
+    pub(super) fn store_provisional(
+        &mut self,
+        witness_id: XWitnessId,
+        min_confirmations: Option<u32>,
+    ) -> Result<bool, StashError<P>> {
+        self.begin_transaction()?;
+        let res = match min_confirmations {
+            Some(min) => self.provider.replace_provisional_witness(witness_id, min),
+            None => self.provider.remove_provisional_witness(witness_id),
+        }
+        .inspect_err(|_| self.rollback_transaction())
+        .map_err(StashError::WriteProvider)?;
+        self.commit_transaction()?;
+        Ok(res)
+    }
+
+    pub(super) fn conflict_resolutions(&self) -> Result<BTreeMap<Opout, OpId>, StashError<P>> {
+        Ok(self
+            .provider
+            .conflict_resolutions()
+            .map_err(StashError::ReadProvider)?
+            .collect())
+    }
+
+    pub(super) fn store_conflict_resolution(
+        &mut self,
+        opout: Opout,
+        keep: OpId,
+    ) -> Result<bool, StashError<P>> {
+        self.begin_transaction()?;
+        let res = self
+            .provider
+            .replace_conflict_resolution(opout, keep)
+            .inspect_err(|_| self.rollback_transaction())
+            .map_err(StashError::WriteProvider)?;
+        self.commit_transaction()?;
+        Ok(res)
+    }
+
+    pub(super) fn pending_consignments(
+        &self,
+    ) -> Result<impl Iterator<Item = &Transfer> + '_, StashError<P>> {
+        self.provider
+            .pending_consignments()
+            .map_err(StashError::ReadProvider)
+    }
+
+    pub(super) fn store_pending(
+        &mut self,
+        id: ConsignmentId,
+        consignment: Option<Transfer>,
+    ) -> Result<bool, StashError<P>> {
+        self.begin_transaction()?;
+        let res = match consignment {
+            Some(consignment) => self.provider.add_pending_consignment(consignment),
+            None => self.provider.remove_pending_consignment(id),
+        }
+        .inspect_err(|_| self.rollback_transaction())
+        .map_err(StashError::WriteProvider)?;
+        self.commit_transaction()?;
+        Ok(res)
+    }
+
+    /// Removes consignment from the pending ones as a part of the transaction
+    /// writing its data.
+    pub(super) fn clear_pending(&mut self, id: ConsignmentId) -> Result<bool, StashError<P>> {
+        self.provider
+            .remove_pending_consignment(id)
+            .map_err(StashError::WriteProvider)
+    }
+
+    /// Removes all reservations which deadline has passed, returning them.
+    pub(crate) fn sweep_reservations(
+        &mut self,
+        now: i64,
+    ) -> Result<Vec<(OpId, Reservation)>, StashError<P>> {
+        let expired = self
+            .provider
+            .reservations()
+            .map_err(StashError::ReadProvider)?
+            .filter(|(_, reservation)| reservation.is_expired(now))
+            .map(|(id, reservation)| (id, reservation.clone()))
+            .collect::<Vec<_>>();
+        if expired.is_empty() {
+            return Ok(expired);
+        }
+        self.begin_transaction()?;
+        for (id, _) in &expired {
+            self.provider
+                .remove_reservation(*id)
+                .inspect_err(|_| self.rollback_transaction())
+                .map_err(StashError::WriteProvider)?;
+        }
+        self.commit_transaction()?;
+        Ok(expired)
+    }
+
+    /// Releases reservations of all batches containing any of the provided
+    /// state transitions.
+    pub(crate) fn release_reservations(
+        &mut self,
+        opids: &BTreeSet<OpId>,
+    ) -> Result<(), StashError<P>> {
+        let ids = self
+            .provider
+            .reservations()
+            .map_err(StashError::ReadProvider)?
+            .filter(|(_, reservation)| !reservation.transitions.is_disjoint(opids))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            self.provider
+                .remove_reservation(id)
+                .map_err(StashError::WriteProvider)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn store_attestation(&mut self, suppl: Supplement) -> Result<(), StashError<P>> {
+        if suppl.attestation().is_none() {
+            return Err(StashDataError::NoAttestation(suppl.suppl_id()).into());
+        }
+        self.begin_transaction()?;
+        self.provider
+            .add_supplement(suppl)
+            .inspect_err(|_| self.rollback_transaction())
+            .map_err(StashError::WriteProvider)?;
+        self.commit_transaction()?;
+        Ok(())
+    }
+
+    pub(crate) fn store_label(
+        &mut self,
+        label_ref: LabelRef,
+        label: Option<String>,
+    ) -> Result<bool, StashError<P>> {
+        self.begin_transaction()?;
+        let res = match label {
+            Some(label) => self.provider.replace_label(label_ref, label),
+            None => self.provider.remove_label(label_ref),
+        }
+        .inspect_err(|_| self.rollback_transaction())
+        .map_err(StashError::WriteProvider)?;
+        self.commit_transaction()?;
+        Ok(res)
+    }
+
+    /// Records provenance of the objects, keeping the already known ones.
+    /// Returns whether any provenance was added.
+    pub(crate) fn store_provenance(
+        &mut self,
+        objs: impl IntoIterator<Item = ProvenanceRef>,
+        provenance: &Provenance,
+    ) -> Result<bool, StashError<P>> {
+        self.begin_transaction()?;
+        let mut res = false;
+        for obj in objs {
+            res |= self
+                .provider
+                .add_provenance(obj, provenance.clone())
+                .inspect_err(|_| self.rollback_transaction())
+                .map_err(StashError::WriteProvider)?;
+        }
+        self.commit_transaction()?;
+        Ok(res)
+    }
 }
 
 impl<P: StashProvider> StoreTransaction for Stash<P> {
@@ -694,6 +1253,34 @@ pub trait StashReadProvider {
         secret: XChain<SecretSeal>,
     ) -> Result<Option<XChain<GraphSeal>>, Self::Error>;
     fn secret_seals(&self) -> Result<impl Iterator<Item = XChain<GraphSeal>>, Self::Error>;
+
+    fn label(&self, label_ref: LabelRef) -> Result<Option<&str>, Self::Error>;
+    fn labels(&self) -> Result<impl Iterator<Item = (LabelRef, &str)>, Self::Error>;
+
+    fn provenance(&self, obj: ProvenanceRef) -> Result<Option<&Provenance>, Self::Error>;
+    fn provenances(
+        &self,
+    ) -> Result<impl Iterator<Item = (ProvenanceRef, &Provenance)>, Self::Error>;
+
+    fn reservations(&self) -> Result<impl Iterator<Item = (OpId, &Reservation)>, Self::Error>;
+
+    fn close_method_policy(&self) -> Result<CloseMethodPolicy, Self::Error>;
+
+    /// Iterates over witnesses of the provisionally accepted transfers, which
+    /// state is excluded from the contract state until the witnesses get the
+    /// given number of confirmations.
+    fn provisional_witnesses(&self)
+    -> Result<impl Iterator<Item = (XWitnessId, u32)>, Self::Error>;
+
+    /// Iterates over allocations spent by conflicting state transitions,
+    /// together with the transition which was chosen to be kept. Witnesses of
+    /// other transitions spending the allocation are excluded from the
+    /// contract state.
+    fn conflict_resolutions(&self) -> Result<impl Iterator<Item = (Opout, OpId)>, Self::Error>;
+
+    /// Iterates over consignments which acceptance has failed after some of
+    /// their data were written to the stock.
+    fn pending_consignments(&self) -> Result<impl Iterator<Item = &Transfer>, Self::Error>;
 }
 
 pub trait StashWriteProvider: StoreTransaction<TransactionErr = Self::Error> {
@@ -721,4 +1308,40 @@ pub trait StashWriteProvider: StoreTransaction<TransactionErr = Self::Error> {
     where I: IntoIterator<Item = (Identity, SigBlob)>;
 
     fn add_secret_seal(&mut self, seal: XChain<GraphSeal>) -> Result<bool, Self::Error>;
+
+    fn replace_label(&mut self, label_ref: LabelRef, label: String) -> Result<bool, Self::Error>;
+    fn remove_label(&mut self, label_ref: LabelRef) -> Result<bool, Self::Error>;
+
+    /// Adds provenance of an object unless it is already known, returning
+    /// whether the provenance was added.
+    fn add_provenance(
+        &mut self,
+        obj: ProvenanceRef,
+        provenance: Provenance,
+    ) -> Result<bool, Self::Error>;
+
+    fn replace_reservation(
+        &mut self,
+        id: OpId,
+        reservation: Reservation,
+    ) -> Result<bool, Self::Error>;
+    fn remove_reservation(&mut self, id: OpId) -> Result<bool, Self::Error>;
+
+    fn set_close_method_policy(&mut self, policy: CloseMethodPolicy) -> Result<(), Self::Error>;
+
+    fn replace_provisional_witness(
+        &mut self,
+        witness_id: XWitnessId,
+        min_confirmations: u32,
+    ) -> Result<bool, Self::Error>;
+    fn remove_provisional_witness(&mut self, witness_id: XWitnessId) -> Result<bool, Self::Error>;
+
+    fn replace_conflict_resolution(
+        &mut self,
+        opout: Opout,
+        keep: OpId,
+    ) -> Result<bool, Self::Error>;
+
+    fn add_pending_consignment(&mut self, consignment: Transfer) -> Result<bool, Self::Error>;
+    fn remove_pending_consignment(&mut self, id: ConsignmentId) -> Result<bool, Self::Error>;
 }
@@ -35,9 +35,9 @@ use rgb::{
     VoidState, XWitnessId,
 };
 
-use crate::containers::{ConsignmentExt, ToWitnessId};
+use crate::containers::{ConsignmentExt, ToWitnessId, WitnessId};
 use crate::contract::OutputAssignment;
-use crate::persistence::{StoreTransaction, UpdateRes};
+use crate::persistence::{MemState, StoreTransaction, UpdateRes};
 
 #[derive(Debug, Display, Error, From)]
 #[display(inner)]
@@ -66,6 +66,9 @@ pub enum StateError<P: StateProvider> {
 pub enum StateInconsistency {
     /// contract state {0} is not known.
     UnknownContract(ContractId),
+    /// contract {0} is archived and its state must be restored before being
+    /// accessed.
+    ArchivedContract(ContractId),
     /// valid (non-archived) witness is absent in the list of witnesses for a
     /// state transition bundle.
     AbsentValidWitness,
@@ -179,19 +182,40 @@ impl<P: StateProvider> State<P> {
         consignment: impl ConsignmentExt,
         resolver: R,
     ) -> Result<(), StateError<P>> {
+        self.update_from_parts(
+            consignment.schema(),
+            consignment.genesis(),
+            consignment.extensions(),
+            consignment.bundled_witnesses().map(|witness_bundle| {
+                (witness_bundle.pub_witness.to_witness_id(), &witness_bundle.bundle)
+            }),
+            resolver,
+        )
+    }
+
+    /// Updates contract state from the contract operations, each transition
+    /// bundle being accompanied by the id of its witness.
+    pub(super) fn update_from_parts<'op, R: ResolveWitness>(
+        &mut self,
+        schema: &Schema,
+        genesis: &Genesis,
+        extensions: impl IntoIterator<Item = &'op Extension>,
+        bundles: impl IntoIterator<Item = (XWitnessId, &'op TransitionBundle)>,
+        resolver: R,
+    ) -> Result<(), StateError<P>> {
+        let extensions = extensions.into_iter().collect::<Vec<_>>();
         let mut state = self
             .as_provider_mut()
-            .register_contract(consignment.schema(), consignment.genesis())
+            .register_contract(schema, genesis)
             .map_err(StateError::WriteProvider)?;
-        let mut extension_idx = consignment
-            .extensions()
-            .map(Extension::id)
+        let mut extension_idx = extensions
+            .iter()
+            .map(|extension| extension.id())
             .zip(iter::repeat(false))
             .collect::<BTreeMap<_, _>>();
         let mut ordered_extensions = BTreeMap::new();
-        for witness_bundle in consignment.bundled_witnesses() {
-            for transition in witness_bundle.bundle.known_transitions.values() {
-                let witness_id = witness_bundle.pub_witness.to_witness_id();
+        for (witness_id, bundle) in bundles {
+            for transition in bundle.known_transitions.values() {
                 let witness_ord = resolver
                     .resolve_pub_witness_ord(witness_id)
                     .map_err(|e| StateError::Resolver(witness_id, e))?;
@@ -218,7 +242,7 @@ impl<P: StateProvider> State<P> {
                 }
             }
         }
-        for extension in consignment.extensions() {
+        for extension in extensions {
             if let Some((witness_id, witness_ord)) = ordered_extensions.get(&extension.id()) {
                 state
                     .add_extension(extension, *witness_id, *witness_ord)
@@ -241,6 +265,36 @@ impl<P: StateProvider> State<P> {
             .update_witnesses(resolver, after_height)
             .map_err(StateError::WriteProvider)
     }
+
+    pub fn archived_contracts(
+        &self,
+    ) -> Result<impl Iterator<Item = ContractId> + '_, StateError<P>> {
+        self.provider
+            .archived_contracts()
+            .map_err(StateError::ReadProvider)
+    }
+
+    pub fn archive_contract(&mut self, contract_id: ContractId) -> Result<bool, StateError<P>> {
+        self.provider
+            .archive_contract(contract_id)
+            .map_err(StateError::WriteProvider)
+    }
+
+    pub fn unarchive_contract(&mut self, contract_id: ContractId) -> Result<bool, StateError<P>> {
+        self.provider
+            .unarchive_contract(contract_id)
+            .map_err(StateError::WriteProvider)
+    }
+
+    pub(super) fn replace_contract(
+        &mut self,
+        contract_id: ContractId,
+        rebuilt: &MemState,
+    ) -> Result<(), StateError<P>> {
+        self.provider
+            .replace_contract(contract_id, rebuilt)
+            .map_err(StateError::WriteProvider)
+    }
 }
 
 impl<P: StateProvider> StoreTransaction for State<P> {
@@ -266,8 +320,13 @@ pub trait StateProvider:
 {
 }
 
-pub trait StateReadProvider {
-    type ContractRead<'a>: ContractStateRead
+/// Provider of the contract state.
+///
+/// The provider refers to witness transactions with the [`WitnessId`] of the
+/// layers 1 it supports; the state used by [`Stock`](super::Stock) is
+/// provided for [`XWitnessId`].
+pub trait StateReadProvider<W: WitnessId = XWitnessId> {
+    type ContractRead<'a>: ContractStateRead<W>
     where Self: 'a;
     type Error: Clone + Eq + Error;
 
@@ -276,11 +335,16 @@ pub trait StateReadProvider {
         contract_id: ContractId,
     ) -> Result<Self::ContractRead<'_>, Self::Error>;
 
-    fn is_valid_witness(&self, witness_id: XWitnessId) -> Result<bool, Self::Error>;
+    fn is_valid_witness(&self, witness_id: W) -> Result<bool, Self::Error>;
+
+    /// Lists contracts which state was moved to the archive.
+    fn archived_contracts(&self) -> Result<impl Iterator<Item = ContractId> + '_, Self::Error>;
 }
 
-pub trait StateWriteProvider: StoreTransaction<TransactionErr = Self::Error> {
-    type ContractWrite<'a>: ContractStateWrite<Error = Self::Error>
+pub trait StateWriteProvider<W: WitnessId = XWitnessId>:
+    StoreTransaction<TransactionErr = Self::Error>
+{
+    type ContractWrite<'a>: ContractStateWrite<W, Error = Self::Error>
     where Self: 'a;
     type Error: Error;
 
@@ -300,19 +364,36 @@ pub trait StateWriteProvider: StoreTransaction<TransactionErr = Self::Error> {
         resolver: impl ResolveWitness,
         after_height: u32,
     ) -> Result<UpdateRes, Self::Error>;
+
+    /// Moves contract state to the archive, excluding it from the state
+    /// queries. Returns `false` if the contract is not known or is already
+    /// archived.
+    fn archive_contract(&mut self, contract_id: ContractId) -> Result<bool, Self::Error>;
+
+    /// Restores archived contract state. Returns `false` if the contract is
+    /// not archived.
+    fn unarchive_contract(&mut self, contract_id: ContractId) -> Result<bool, Self::Error>;
+
+    /// Replaces contract state with the one rebuilt from the contract
+    /// operations, updating the status of the contract witnesses.
+    fn replace_contract(
+        &mut self,
+        contract_id: ContractId,
+        rebuilt: &MemState,
+    ) -> Result<(), Self::Error>;
 }
 
-pub trait ContractStateRead: ContractStateAccess {
+pub trait ContractStateRead<W: WitnessId = XWitnessId>: ContractStateAccess {
     fn contract_id(&self) -> ContractId;
     fn schema_id(&self) -> SchemaId;
-    fn witness_ord(&self, witness_id: XWitnessId) -> Option<WitnessOrd>;
+    fn witness_ord(&self, witness_id: W) -> Option<WitnessOrd>;
     fn rights_all(&self) -> impl Iterator<Item = &OutputAssignment<VoidState>>;
     fn fungible_all(&self) -> impl Iterator<Item = &OutputAssignment<RevealedValue>>;
     fn data_all(&self) -> impl Iterator<Item = &OutputAssignment<RevealedData>>;
     fn attach_all(&self) -> impl Iterator<Item = &OutputAssignment<RevealedAttach>>;
 }
 
-pub trait ContractStateWrite {
+pub trait ContractStateWrite<W: WitnessId = XWitnessId> {
     type Error: Error;
 
     fn add_genesis(&mut self, genesis: &Genesis) -> Result<(), Self::Error>;
@@ -320,14 +401,14 @@ pub trait ContractStateWrite {
     fn add_transition(
         &mut self,
         transition: &Transition,
-        witness_id: XWitnessId,
+        witness_id: W,
         witness_ord: WitnessOrd,
     ) -> Result<(), Self::Error>;
 
     fn add_extension(
         &mut self,
         extension: &Extension,
-        witness_id: XWitnessId,
+        witness_id: W,
         witness_ord: WitnessOrd,
     ) -> Result<(), Self::Error>;
 }
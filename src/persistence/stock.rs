@@ -25,39 +25,65 @@ use std::convert::Infallible;
 use std::error::Error;
 use std::fmt::Debug;
 
-use amplify::confinement::{Confined, U24};
+use amplify::confinement::{self, Confined, LargeOrdSet, U24};
+use amplify::hex::ToHex;
 use amplify::Wrapper;
 use bp::dbc::{Anchor, Method};
-use bp::seals::txout::CloseMethod;
-use bp::Vout;
+use bp::seals::txout::{CloseMethod, TxPtr};
+use bp::{ScriptPubkey, Vout};
 use chrono::Utc;
-use invoice::{Amount, Beneficiary, InvoiceState, NonFungible, RgbInvoice};
+use commit_verify::merkle::MerkleHash;
+use commit_verify::{Conceal, DigestExt, Sha256};
+use invoice::{
+    Amount, Beneficiary, ChainNet, InvoiceState, NonFungible, RgbInvoice, RgbInvoiceBuilder,
+    XChainNet,
+};
 use nonasync::persistence::{CloneNoPersistence, PersistenceError, PersistenceProvider};
 use rgb::validation::{DbcProof, ResolveWitness, WitnessResolverError};
+use rgb::vm::WitnessOrd;
 use rgb::{
-    validation, AssignmentType, BlindingFactor, BundleId, ContractId, DataState, GraphSeal,
-    Identity, OpId, Operation, Opout, SchemaId, SecretSeal, Transition, TxoSeal, XChain, XOutpoint,
-    XOutputSeal, XWitnessId,
+    validation, AssignmentType, BlindingFactor, BundleId, ContractId, DataState, Extension,
+    Genesis, GenesisSeal, GraphSeal, Identity, InputMap, OpId, Operation, Opout, Schema, SchemaId,
+    SecretSeal, Transition, TransitionBundle, TxoSeal, Vin, XChain, XOutpoint, XOutputSeal,
+    XWitnessId,
 };
-use strict_encoding::FieldName;
+use strict_encoding::{FieldName, StrictDumb, StrictEncode, StrictWriter};
 
 use super::{
-    ContractStateRead, Index, IndexError, IndexInconsistency, IndexProvider, IndexReadProvider,
-    IndexWriteProvider, MemIndex, MemStash, MemState, PersistedState, SchemaIfaces, Stash,
+    proof, AllocationLeaf, AllocationProof, AuthorizeAll, BatchInvoiceError, BatchSeals,
+    CloseMethodPolicy, Conflict, ConflictBranch, ConflictError, ConflictReport, ConsolidationPolicy,
+    ConsolidationReport, ContractHistory, ContractStateRead, ContractUpgrade, CpfpError,
+    CpfpPackage, CpfpStatus, Index, IndexError, IndexInconsistency, IndexProvider,
+    IndexReadProvider, IndexWriteProvider, InflationReport, InvoiceBatch, InvoiceRecord,
+    InvoiceRequest, KnownOps, LabelRef, MemIndex, MemStash, MemState, MembershipFilter,
+    OnboardReport, PaymentProof, PaymentProofError, PersistedState, Provenance, ProvenanceRef,
+    ReplacementKind, Reservation, SchemaIfaces, SpendAuthorizer, SpendRequest, Stash,
     StashDataError, StashError, StashInconsistency, StashProvider, StashReadProvider,
     StashWriteProvider, State, StateError, StateInconsistency, StateProvider, StateReadProvider,
-    StateWriteProvider, StoreTransaction,
+    StateWriteProvider, StoreTransaction, SyncReport, SyncRequest, SyncResponse, TrustPolicy,
+    UpgradeError, UpgradeStatus, WitnessReplacement, RGB20_ASSIGNMENT_INFLATION,
 };
+#[cfg(feature = "analytics")]
+use super::analytics::{ContractExport, ContractExportBuilder, Value};
+use super::inflation::InflationAudit;
+use crate::broadcast::{Broadcaster, WitnessAnalysis};
 use crate::containers::{
-    AnchorSet, Batch, BuilderSeal, Consignment, ContainerVer, ContentId, ContentRef, Contract,
-    Fascia, Kit, SealWitness, SupplItem, SupplSub, Transfer, TransitionDichotomy, TransitionInfo,
+    AnchorSet, Attestation, AttestationPolicy, Batch, BuilderSeal, Capabilities, CloseMethodSet,
+    Consignment, ConsignmentExt, ConsignmentId, ContainerVer, ContentId, ContentRef, Contract,
+    ContractDependency, DefaultOps, DisplayRules, Fascia, Kit, OpClass, SealWitness, SigValidator,
+    SigningContext, SupplItem, SupplSub, Supplement, Transfer, TransitionDichotomy, TransitionInfo,
     TransitionInfoError, ValidConsignment, ValidContract, ValidKit, ValidTransfer, VelocityHint,
-    WitnessBundle, SUPPL_ANNOT_VELOCITY,
+    WitnessBundle, XPubWitness, SUPPL_ANNOT_VELOCITY,
 };
 use crate::info::{ContractInfo, IfaceInfo, SchemaInfo};
 use crate::interface::{
-    BuilderError, ContractBuilder, ContractIface, Iface, IfaceClass, IfaceId, IfaceRef,
-    IfaceWrapper, TransitionBuilder,
+    AllocatedState, AssignmentsFilter, BuilderError, ContractBuilder, ContractIface,
+    FilterIncludeAll, Iface, IfaceClass, IfaceId, IfaceRef, IfaceWrapper, ImplId,
+    TransitionBuilder, TEMPLATE_GLOBAL_ISSUED_SUPPLY,
+};
+use crate::resolvers::{
+    ArchivingResolver, ConfirmationResolver, PrefetchedResolver, Progress, ProgressResolver,
+    ProgressSink,
 };
 use crate::{BundleExt, MergeRevealError, RevealError};
 
@@ -109,6 +135,13 @@ pub enum StockError<
 
     /// witness {0} can't be resolved: {1}
     WitnessUnresolved(XWitnessId, WitnessResolverError),
+
+    /// unable to recompute state of contract {0}: {1}
+    #[display(doc_comments)]
+    Recompute(ContractId, Box<RecomputeError>),
+
+    #[from]
+    Dependency(DependencyError),
 }
 
 impl<S: StashProvider, H: StateProvider, P: IndexProvider, E: Error> From<StashError<S>>
@@ -173,6 +206,13 @@ pub enum ConsignError {
 
     /// the spent state from transition {1} inside bundle {0} is concealed.
     Concealed(BundleId, OpId),
+
+    /// the invoice contains no contract information.
+    NoContract,
+
+    /// beneficiary output number is not given for the witness vout invoice or
+    /// doesn't match the witness layer.
+    NoBeneficiaryOutput,
 }
 
 impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<ConsignError>
@@ -219,6 +259,19 @@ pub enum ComposeError {
     /// smart contract state.
     InsufficientState,
 
+    /// output {0} is reserved by another transfer which is not yet anchored.
+    ReservedOutput(XOutpoint),
+
+    /// the composed batch can't be reserved: {0}
+    Reservation(confinement::Error),
+
+    /// no invoices are provided for the payment.
+    NoInvoices,
+
+    /// invoices paid within the same state transition must use the same
+    /// contract, interface, operation, assignment and network.
+    InvoiceMismatch,
+
     /// the spent UTXOs contain too many seals which can't fit the state
     /// transition input limit.
     TooManyInputs,
@@ -231,6 +284,34 @@ pub enum ComposeError {
     /// the container requirements.
     TooManyBlanks,
 
+    /// there are no allocations worth consolidating under the given policy.
+    NothingToConsolidate,
+
+    /// close method {0} is not allowed by the stock policy for outgoing
+    /// commitments.
+    CloseMethodPolicy(CloseMethod),
+
+    /// the invoice doesn't accept commitments made with {0} close method.
+    CloseMethodUnsupported(CloseMethod),
+
+    /// none of the close methods accepted by the invoice are allowed by the
+    /// stock policy for outgoing commitments.
+    NoCompatibleCloseMethod,
+
+    /// spending of allocation {0} was vetoed: {1}
+    SpendVetoed(Opout, String),
+
+    /// consignment would exceed the size budget of {budget} bytes; the best
+    /// achievable consignment size is {achievable} bytes.
+    SizeBudgetExceeded { budget: usize, achievable: usize },
+
+    #[display(inner)]
+    Consign(Box<ConsignError>),
+
+    /// the invoice requests state of a type which can't be paid yet; only
+    /// amounts and RGB21 allocations are supported.
+    UnsupportedInvoiceState,
+
     #[from]
     #[display(inner)]
     Builder(BuilderError),
@@ -253,6 +334,17 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<BuilderError>
 pub enum FasciaError {
     /// bundle {1} for contract {0} contains invalid transition input map.
     InvalidBundle(ContractId, BundleId),
+
+    /// fascia doesn't contain witness transaction {0} required for
+    /// broadcasting.
+    NoWitnessTx(XWitnessId),
+
+    /// unable to broadcast witness transaction {0}: {1}
+    Broadcast(XWitnessId, String),
+
+    /// state transition {0} presented to the signer doesn't match the state
+    /// transitions committed to by the signed witness transaction.
+    SigningMismatch(OpId),
 }
 
 impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<FasciaError>
@@ -261,6 +353,39 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<FasciaError>
     fn from(err: FasciaError) -> Self { Self::InvalidInput(err) }
 }
 
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum ConfirmationError {
+    /// terminal witness {witness_id} has {confirmations} confirmations, while
+    /// at least {required} confirmations are required.
+    Unconfirmed {
+        witness_id: XWitnessId,
+        confirmations: u32,
+        required: u32,
+    },
+
+    #[from]
+    #[display(inner)]
+    Resolver(WitnessResolverError),
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<ConfirmationError>
+    for StockError<S, H, P, ConfirmationError>
+{
+    fn from(err: ConfirmationError) -> Self { Self::InvalidInput(err) }
+}
+
+/// Errors rebuilding contract state and indexes in memory by
+/// [`Stock::reindex`].
+#[derive(Debug, Display, Error, From)]
+#[display(inner)]
+pub enum RecomputeError {
+    #[from]
+    State(StateError<MemState>),
+    #[from]
+    Index(IndexError<MemIndex>),
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
 #[display(doc_comments)]
 pub enum ContractIfaceError {
@@ -275,6 +400,58 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<ContractIfaceErr
     fn from(err: ContractIfaceError) -> Self { Self::InvalidInput(err) }
 }
 
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum DependencyError {
+    /// contract {0} depends on contract {1} which is not known.
+    UnknownContract(ContractId, ContractId),
+
+    /// contract {0} depends on allocation {1}, which doesn't exist.
+    AllocationAbsent(ContractId, Box<ContractDependency>),
+
+    /// contract {0} depends on allocation {1}, which is not locked there.
+    NotLocked(ContractId, Box<ContractDependency>),
+
+    /// contract {0} has cyclic dependency on itself.
+    Cycle(ContractId),
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<PaymentProofError>
+    for StockError<S, H, P, PaymentProofError>
+{
+    fn from(err: PaymentProofError) -> Self { Self::InvalidInput(err) }
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<BatchInvoiceError>
+    for StockError<S, H, P, BatchInvoiceError>
+{
+    fn from(err: BatchInvoiceError) -> Self { Self::InvalidInput(err) }
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<UpgradeError>
+    for StockError<S, H, P, UpgradeError>
+{
+    fn from(err: UpgradeError) -> Self { Self::InvalidInput(err) }
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<ConflictError>
+    for StockError<S, H, P, ConflictError>
+{
+    fn from(err: ConflictError) -> Self { Self::InvalidInput(err) }
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<CpfpError>
+    for StockError<S, H, P, CpfpError>
+{
+    fn from(err: CpfpError) -> Self { Self::InvalidInput(err) }
+}
+
+impl<S: StashProvider, H: StateProvider, P: IndexProvider> From<BuilderError>
+    for StockError<S, H, P, CpfpError>
+{
+    fn from(err: BuilderError) -> Self { Self::InvalidInput(err.into()) }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
 #[display(inner)]
 pub enum InputError {
@@ -285,7 +462,19 @@ pub enum InputError {
     #[from]
     Fascia(FasciaError),
     #[from]
+    Confirmation(ConfirmationError),
+    #[from]
     ContractIface(ContractIfaceError),
+    #[from]
+    PaymentProof(PaymentProofError),
+    #[from]
+    Upgrade(UpgradeError),
+    #[from]
+    BatchInvoice(BatchInvoiceError),
+    #[from]
+    Conflict(ConflictError),
+    #[from]
+    Cpfp(CpfpError),
 }
 
 macro_rules! stock_err_conv {
@@ -308,6 +497,8 @@ macro_rules! stock_err_conv {
                     StockError::StateInconsistency(e) => StockError::StateInconsistency(e),
                     StockError::IndexInconsistency(e) => StockError::IndexInconsistency(e),
                     StockError::WitnessUnresolved(id, e) => StockError::WitnessUnresolved(id, e),
+                    StockError::Recompute(id, e) => StockError::Recompute(id, e),
+                    StockError::Dependency(e) => StockError::Dependency(e),
                 }
             }
         }
@@ -317,6 +508,9 @@ macro_rules! stock_err_conv {
 impl From<Infallible> for InputError {
     fn from(_: Infallible) -> Self { unreachable!() }
 }
+impl From<ConsignError> for ComposeError {
+    fn from(err: ConsignError) -> Self { ComposeError::Consign(Box::new(err)) }
+}
 impl From<Infallible> for ComposeError {
     fn from(_: Infallible) -> Self { unreachable!() }
 }
@@ -326,19 +520,55 @@ impl From<Infallible> for ConsignError {
 impl From<Infallible> for FasciaError {
     fn from(_: Infallible) -> Self { unreachable!() }
 }
+impl From<Infallible> for ConfirmationError {
+    fn from(_: Infallible) -> Self { unreachable!() }
+}
 impl From<Infallible> for ContractIfaceError {
     fn from(_: Infallible) -> Self { unreachable!() }
 }
+impl From<Infallible> for PaymentProofError {
+    fn from(_: Infallible) -> Self { unreachable!() }
+}
+impl From<Infallible> for UpgradeError {
+    fn from(_: Infallible) -> Self { unreachable!() }
+}
+impl From<Infallible> for BatchInvoiceError {
+    fn from(_: Infallible) -> Self { unreachable!() }
+}
+impl From<Infallible> for ConflictError {
+    fn from(_: Infallible) -> Self { unreachable!() }
+}
+impl From<Infallible> for CpfpError {
+    fn from(_: Infallible) -> Self { unreachable!() }
+}
 
 stock_err_conv!(Infallible, ComposeError);
+stock_err_conv!(ConsignError, ComposeError);
 stock_err_conv!(Infallible, ConsignError);
 stock_err_conv!(Infallible, FasciaError);
+stock_err_conv!(Infallible, ConfirmationError);
 stock_err_conv!(Infallible, ContractIfaceError);
+stock_err_conv!(Infallible, PaymentProofError);
+stock_err_conv!(Infallible, UpgradeError);
+stock_err_conv!(Infallible, BatchInvoiceError);
+stock_err_conv!(Infallible, ConflictError);
+stock_err_conv!(Infallible, CpfpError);
+stock_err_conv!(FasciaError, CpfpError);
 stock_err_conv!(Infallible, InputError);
 stock_err_conv!(ComposeError, InputError);
 stock_err_conv!(ConsignError, InputError);
 stock_err_conv!(FasciaError, InputError);
+stock_err_conv!(ConfirmationError, InputError);
 stock_err_conv!(ContractIfaceError, InputError);
+stock_err_conv!(PaymentProofError, InputError);
+stock_err_conv!(UpgradeError, InputError);
+stock_err_conv!(BatchInvoiceError, InputError);
+stock_err_conv!(ConflictError, InputError);
+stock_err_conv!(CpfpError, InputError);
+
+/// Default number of seconds for which the outputs spent by a batch composed
+/// with [`Stock::compose_reserved`] are expected to remain reserved.
+pub const RESERVATION_TIMEOUT: i64 = 24 * 60 * 60;
 
 pub type StockErrorMem<E = Infallible> = StockError<MemStash, MemState, MemIndex, E>;
 pub type StockErrorAll<S = MemStash, H = MemState, P = MemIndex> = StockError<S, H, P, InputError>;
@@ -380,6 +610,11 @@ where
 }
 
 impl Stock {
+    /// Constructs an empty stock which keeps all data in memory and is not
+    /// persisted, such that it can be used in unit tests and short-lived
+    /// services without any file system access.
+    ///
+    /// The stock can be persisted later with [`Stock::make_persistent`].
     #[inline]
     pub fn in_memory() -> Self {
         Self::with(MemStash::in_memory(), MemState::in_memory(), MemIndex::in_memory())
@@ -423,6 +658,12 @@ impl<S: StashProvider, H: StateProvider, I: IndexProvider> Stock<S, H, I> {
         Ok(a && b && c)
     }
 
+    /// Creates a copy of the stock which is not persisted. Changes made to the
+    /// fork don't affect the original stock and its persisted data, and vice
+    /// versa.
+    #[inline]
+    pub fn fork(&self) -> Self { self.clone_no_persistence() }
+
     pub fn store(&mut self) -> Result<(), PersistenceError> {
         // TODO: Revert on failure
 
@@ -443,6 +684,137 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         }
     }
 
+    /// Sets seal close methods which the stock accepts for the incoming
+    /// transfers and uses for the outgoing ones. The policy is persisted in
+    /// the stash.
+    pub fn set_close_method_policy(
+        &mut self,
+        policy: CloseMethodPolicy,
+    ) -> Result<(), StockError<S, H, P>> {
+        Ok(self.stash.store_close_method_policy(policy)?)
+    }
+
+    pub fn close_method_policy(&self) -> Result<CloseMethodPolicy, StockError<S, H, P>> {
+        Ok(self.stash.close_method_policy()?)
+    }
+
+    /// Enables in-memory membership filters over operations and bundles known
+    /// to the stock, reserving capacity for the given number of the new ones.
+    ///
+    /// The filters speed up duplicate detection during acceptance of
+    /// consignments for contracts with long histories. Their false positive
+    /// rate grows once the number of the new items exceeds the reserved
+    /// capacity, which may be fixed by enabling the filters again.
+    pub fn enable_membership_filters(&mut self, reserve: usize) -> Result<(), StockError<S, H, P>> {
+        Ok(self.stash.enable_filter(reserve)?)
+    }
+
+    pub fn disable_membership_filters(&mut self) { self.stash.disable_filter() }
+
+    pub fn membership_filter(&self) -> Option<&MembershipFilter> { self.stash.membership_filter() }
+
+    /// Detects whether the operation is known to the stock.
+    pub fn is_known_operation(&self, opid: OpId) -> bool {
+        self.stash.may_know_operation(opid)
+            && (self.index.bundle_id_for_op(opid).is_ok()
+                || self.stash.has_genesis_or_extension(opid))
+    }
+
+    /// Detects whether the bundle is known to the stock.
+    pub fn is_known_bundle(&self, bundle_id: BundleId) -> bool { self.stash.has_bundle(bundle_id) }
+
+    /// Constructs invoice builder embedding the close methods accepted by the
+    /// stock policy, such that payers with incompatible wallets fail fast.
+    pub fn invoice_builder(
+        &self,
+        contract_id: ContractId,
+        beneficiary: impl Into<XChainNet<Beneficiary>>,
+    ) -> Result<RgbInvoiceBuilder, StockError<S, H, P>> {
+        let builder = RgbInvoiceBuilder::with(contract_id, beneficiary);
+        Ok(match self.close_method_policy()?.accepted {
+            CloseMethodSet::Both => builder,
+            methods => builder.add_close_methods(methods.methods()),
+        })
+    }
+
+    /// Generates invoices for a batch of requests, deriving beneficiary seals
+    /// from the provided source.
+    ///
+    /// Blinded seals are stored in the stash, such that the incoming transfers
+    /// paying the invoices can be accepted. The invoices embed close methods
+    /// accepted by the stock policy.
+    pub fn batch_invoices(
+        &mut self,
+        requests: impl IntoIterator<Item = InvoiceRequest>,
+        chain_net: ChainNet,
+        seals: BatchSeals,
+    ) -> Result<InvoiceBatch, StockError<S, H, P, BatchInvoiceError>> {
+        let requests = requests.into_iter().collect::<Vec<_>>();
+        match &seals {
+            BatchSeals::Blinded { utxos, .. } if utxos.is_empty() => {
+                return Err(BatchInvoiceError::NoUtxos.into());
+            }
+            BatchSeals::WitnessVout(addresses) if addresses.len() < requests.len() => {
+                return Err(
+                    BatchInvoiceError::NotEnoughAddresses(addresses.len(), requests.len()).into()
+                );
+            }
+            _ => {}
+        }
+        for request in &requests {
+            self.stash.genesis(request.contract_id)?;
+        }
+
+        let mut beneficiaries = Vec::with_capacity(requests.len());
+        match seals {
+            BatchSeals::Blinded { method, utxos } => {
+                for outpoint in utxos.iter().cycle().take(requests.len()) {
+                    let seal = outpoint.map(|outpoint| {
+                        GraphSeal::new_random(method, outpoint.txid, outpoint.vout)
+                    });
+                    self.store_secret_seal(seal)?;
+                    beneficiaries
+                        .push(Beneficiary::BlindedSeal(seal.as_reduced_unsafe().conceal()));
+                }
+            }
+            BatchSeals::WitnessVout(addresses) => {
+                beneficiaries.extend(addresses.into_iter().map(Beneficiary::WitnessVout));
+            }
+        }
+
+        let mut batch = InvoiceBatch::default();
+        for (request, beneficiary) in requests.into_iter().zip(beneficiaries) {
+            let mut builder = self
+                .invoice_builder(request.contract_id, XChainNet::with(chain_net, beneficiary))?
+                .set_amount_raw(request.amount);
+            if let Some(iface) = request.iface {
+                builder = builder.set_interface(iface);
+            }
+            if let Some(expiry) = request.expiry {
+                builder = builder.set_expiry_timestamp(expiry);
+            }
+            batch
+                .records
+                .push(InvoiceRecord::with(request.label, &builder.finish()));
+        }
+        Ok(batch)
+    }
+
+    /// Selects close method for paying the invoice, which must be both
+    /// accepted by the invoice beneficiary and allowed by the stock policy for
+    /// outgoing commitments.
+    pub fn invoice_close_method(
+        &self,
+        invoice: &RgbInvoice,
+    ) -> Result<CloseMethod, StockError<S, H, P, ComposeError>> {
+        Ok(self
+            .close_method_policy()?
+            .outgoing
+            .methods()
+            .find(|method| invoice.accepts_close_method(*method))
+            .ok_or(ComposeError::NoCompatibleCloseMethod)?)
+    }
+
     #[doc(hidden)]
     pub fn as_stash_provider(&self) -> &S { self.stash.as_provider() }
     #[doc(hidden)]
@@ -485,7 +857,12 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
     pub fn contracts(
         &self,
     ) -> Result<impl Iterator<Item = ContractInfo> + '_, StockError<S, H, P>> {
-        Ok(self.stash.geneses()?.map(ContractInfo::with))
+        let archived = self.state.archived_contracts()?.collect::<BTreeSet<_>>();
+        Ok(self
+            .stash
+            .geneses()?
+            .filter(move |genesis| !archived.contains(&genesis.contract_id()))
+            .map(ContractInfo::with))
     }
 
     #[allow(clippy::multiple_bound_locations)]
@@ -515,7 +892,11 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
             .into_iter()
             .map(|o| o.into())
             .collect::<BTreeSet<_>>();
-        Ok(self.index.contracts_assigning(outputs)?)
+        let archived = self.state.archived_contracts()?.collect::<BTreeSet<_>>();
+        Ok(self
+            .index
+            .contracts_assigning(outputs)?
+            .filter(move |id| !archived.contains(id)))
     }
 
     #[allow(clippy::type_complexity)]
@@ -545,6 +926,43 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
             .map_err(StockError::from)
     }
 
+    /// Moves contract state to the archive, excluding the contract from
+    /// [`Self::contracts`], [`Self::contracts_assigning`] and state queries,
+    /// such that long-dead assets do not slow down wallet balance scans. The
+    /// contract data are kept in the stash; the state is restored either with
+    /// [`Self::unarchive_contract`] or by importing new data for the
+    /// contract.
+    ///
+    /// The archived state is kept apart from the state of active contracts
+    /// (see [`crate::persistence::MemArchive`]) and is loaded only when the
+    /// archive is accessed. If the archival fails, the contract state is left
+    /// unchanged.
+    ///
+    /// Returns `false` if the contract is already archived.
+    pub fn archive_contract(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<bool, StockError<S, H, P>> {
+        self.stash.genesis(contract_id)?;
+        Ok(self.state.archive_contract(contract_id)?)
+    }
+
+    /// Restores archived contract state. Returns `false` if the contract was
+    /// not archived.
+    pub fn unarchive_contract(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<bool, StockError<S, H, P>> {
+        Ok(self.state.unarchive_contract(contract_id)?)
+    }
+
+    /// Iterates over ids of archived contracts.
+    pub fn archived_contracts(
+        &self,
+    ) -> Result<impl Iterator<Item = ContractId> + '_, StockError<S, H, P>> {
+        Ok(self.state.archived_contracts()?)
+    }
+
     #[allow(clippy::multiple_bound_locations, clippy::type_complexity)]
     pub fn contract_iface_class<C: IfaceClass>(
         &self,
@@ -556,13 +974,21 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         let iface = self.stash.iface(iimpl.iface_id)?;
         let (types, _) = self.stash.extract(&schema_ifaces.schema, [iface])?;
 
-        Ok(C::Wrapper::with(ContractIface {
+        let schema_capabilities = self.schema_capabilities(schema_ifaces.schema.schema_id())?;
+        let default_ops = self.default_ops(iimpl.impl_id(), iface)?;
+
+        let mut contract = ContractIface {
             state,
             schema: schema_ifaces.schema.clone(),
             iface: iimpl.clone(),
             types,
             info,
-        }))
+            display: default!(),
+            schema_capabilities,
+            default_ops,
+        };
+        contract.display = self.display_rules(contract_id, iimpl.impl_id(), &contract)?;
+        Ok(C::Wrapper::with(contract))
     }
 
     /// Returns the best matching abstract interface to a contract.
@@ -580,14 +1006,80 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         })?;
 
         let (types, _) = self.stash.extract(&schema_ifaces.schema, [iface])?;
+        let schema_capabilities = self.schema_capabilities(schema_ifaces.schema.schema_id())?;
+        let default_ops = self.default_ops(iimpl.impl_id(), iface)?;
 
-        Ok(ContractIface {
+        let mut contract = ContractIface {
             state,
             schema: schema_ifaces.schema.clone(),
             iface: iimpl.clone(),
             types,
             info,
-        })
+            display: default!(),
+            schema_capabilities,
+            default_ops,
+        };
+        contract.display = self.display_rules(contract_id, iimpl.impl_id(), &contract)?;
+        Ok(contract)
+    }
+
+    /// Returns capabilities of the contracts declared by the schema supplement.
+    fn schema_capabilities(
+        &self,
+        schema_id: SchemaId,
+    ) -> Result<Capabilities, StockError<S, H, P>> {
+        Ok(self
+            .stash
+            .supplement(ContentRef::Schema(schema_id))?
+            .map(Supplement::capabilities)
+            .unwrap_or_default())
+    }
+
+    /// Returns operations implementing wallet actions declared by the interface
+    /// implementation supplement. If the primary transfer is not declared,
+    /// the default operation of the interface is used for it.
+    fn default_ops(
+        &self,
+        impl_id: ImplId,
+        iface: &Iface,
+    ) -> Result<DefaultOps, StockError<S, H, P>> {
+        let mut ops = self
+            .stash
+            .supplement(ContentRef::IfaceImpl(impl_id))?
+            .map(Supplement::default_ops)
+            .unwrap_or_default();
+        if let Some(name) = &iface.default_operation {
+            if !ops.contains_key(&OpClass::Transfer) {
+                ops.insert(OpClass::Transfer, name.clone())
+                    .expect("the number of op classes is small");
+            }
+        }
+        Ok(ops)
+    }
+
+    /// Returns rules for displaying contract amounts, declared by the genesis
+    /// supplement or, if absent, by the interface implementation supplement.
+    /// If neither supplement declares the rules, they follow the precision and
+    /// ticker of the contract asset specification.
+    fn display_rules(
+        &self,
+        contract_id: ContractId,
+        impl_id: ImplId,
+        contract: &ContractIface<H::ContractRead<'_>>,
+    ) -> Result<DisplayRules, StockError<S, H, P>> {
+        if let Some(rules) = self
+            .stash
+            .supplement(ContentRef::Genesis(contract_id))?
+            .and_then(Supplement::display_rules)
+        {
+            return Ok(rules);
+        }
+        Ok(self
+            .stash
+            .supplement(ContentRef::IfaceImpl(impl_id))?
+            .and_then(Supplement::display_rules)
+            .or_else(|| contract.asset_spec().as_ref().map(DisplayRules::with_spec))
+            .unwrap_or_default())
     }
 
     pub fn contract_assignments_for(
@@ -648,75 +1140,762 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         Ok(res)
     }
 
-    pub fn contract_builder(
+    /// Exports list of outpoints which spending affects the state assigned to
+    /// the outputs passing the filter (usually the outputs owned by a wallet).
+    ///
+    /// For each of the outpoints the script pubkey of the output is provided,
+    /// if the output transaction is known to the resolver. Since the script
+    /// pubkey is taken from the transaction itself, it already includes
+    /// tapret commitment tweak, and can be used for subscribing to the
+    /// Electrum or Esplora spend notifications.
+    pub fn watch_list(
         &self,
-        issuer: impl Into<Identity>,
-        schema_id: SchemaId,
-        iface: impl Into<IfaceRef>,
-    ) -> Result<ContractBuilder, StockError<S, H, P>> {
-        Ok(self
-            .stash
-            .contract_builder(issuer.into(), schema_id, iface)?)
+        filter: impl AssignmentsFilter,
+        resolver: impl ResolveWitness,
+    ) -> Result<Vec<WatchItem>, StockError<S, H, P>> {
+        let mut outpoints = BTreeMap::<XOutpoint, BTreeSet<ContractId>>::new();
+        for genesis in self.stash.geneses()? {
+            let contract_id = genesis.contract_id();
+            let state = self.contract_state(contract_id)?;
+            let seals = state
+                .rights_all()
+                .map(|a| (a.seal, a.witness))
+                .chain(state.fungible_all().map(|a| (a.seal, a.witness)))
+                .chain(state.data_all().map(|a| (a.seal, a.witness)))
+                .chain(state.attach_all().map(|a| (a.seal, a.witness)));
+            for (seal, witness_id) in seals {
+                let outpoint = seal.to_outpoint();
+                if filter.should_include(outpoint, witness_id) {
+                    outpoints.entry(outpoint).or_default().insert(contract_id);
+                }
+            }
+        }
+
+        Ok(outpoints
+            .into_iter()
+            .map(|(outpoint, contracts)| {
+                let script_pubkey = resolver
+                    .resolve_pub_witness(outpoint.map_ref(|o| o.txid))
+                    .ok()
+                    .and_then(|tx| {
+                        tx.as_reduced_unsafe()
+                            .outputs
+                            .get(outpoint.as_reduced_unsafe().vout.into_usize())
+                            .map(|out| out.script_pubkey.clone())
+                    });
+                WatchItem {
+                    outpoint,
+                    script_pubkey,
+                    contracts,
+                }
+            })
+            .collect())
     }
 
-    pub fn transition_builder(
+    /// Computes merkle root over the contract allocations passing the filter,
+    /// which can be published by a server to allow light clients verifying
+    /// allocation proofs produced with [`Self::prove_allocation`].
+    ///
+    /// Returns `None` if there are no such allocations.
+    pub fn state_root(
         &self,
         contract_id: ContractId,
-        iface: impl Into<IfaceRef>,
-        transition_name: Option<impl Into<FieldName>>,
-    ) -> Result<TransitionBuilder, StockError<S, H, P>> {
-        Ok(self
-            .stash
-            .transition_builder(contract_id, iface, transition_name)?)
+        filter: impl AssignmentsFilter,
+    ) -> Result<Option<MerkleHash>, StockError<S, H, P>> {
+        let leaves = self.allocation_leaves(contract_id, filter)?;
+        Ok(proof::state_root(contract_id, &leaves))
     }
 
-    pub fn blank_builder(
+    /// Constructs proof of inclusion of the allocation into the state root
+    /// returned by [`Self::state_root`] for the same filter.
+    ///
+    /// Returns `None` if the allocation is not a part of the contract state
+    /// passing the filter.
+    pub fn prove_allocation(
         &self,
         contract_id: ContractId,
-        iface: impl Into<IfaceRef>,
-    ) -> Result<TransitionBuilder, StockError<S, H, P>> {
-        Ok(self.stash.blank_builder(contract_id, iface)?)
-    }
-
-    pub fn export_schema(&self, schema_id: SchemaId) -> Result<ValidKit, StockError<S, H, P>> {
-        let mut kit = Kit::default();
-        let schema_ifaces = self.schema(schema_id)?;
-        kit.schemata
-            .push(schema_ifaces.schema.clone())
-            .expect("single item");
-        for name in schema_ifaces.iimpls.keys() {
-            let iface = self.stash.iface(name.clone())?;
-            kit.ifaces.push(iface.clone()).expect("type guarantees");
-        }
-        kit.iimpls
-            .extend(schema_ifaces.iimpls.values().cloned())
-            .expect("type guarantees");
-        let (types, scripts) = self.stash.extract(&schema_ifaces.schema, &kit.ifaces)?;
-        kit.scripts
-            .extend(scripts.into_values())
-            .expect("type guarantees");
-        kit.types = types;
-        Ok(kit.validate().expect("stock produced invalid kit"))
+        opout: Opout,
+        filter: impl AssignmentsFilter,
+    ) -> Result<Option<AllocationProof>, StockError<S, H, P>> {
+        let leaves = self.allocation_leaves(contract_id, filter)?;
+        let Ok(index) = leaves.binary_search_by_key(&opout, |leaf| leaf.opout) else {
+            return Ok(None);
+        };
+        let hashes = leaves
+            .iter()
+            .map(|leaf| leaf.leaf_hash(contract_id))
+            .collect::<Vec<_>>();
+        let path = proof::merkle_path(&hashes, index);
+        let width = leaves.len() as u32;
+        let leaf = leaves
+            .into_iter()
+            .nth(index)
+            .expect("index is within leaves");
+        Ok(Some(AllocationProof {
+            contract_id,
+            leaf,
+            index: index as u32,
+            width,
+            path,
+        }))
     }
 
-    pub fn export_contract(
+    fn allocation_leaves(
         &self,
         contract_id: ContractId,
-    ) -> Result<Contract, StockError<S, H, P, ConsignError>> {
-        let consignment = self.consign::<false>(contract_id, [], None)?;
-        Ok(consignment)
+        filter: impl AssignmentsFilter,
+    ) -> Result<Vec<AllocationLeaf>, StockError<S, H, P>> {
+        let state = self.contract_state(contract_id)?;
+        let mut leaves = BTreeMap::<Opout, AllocationLeaf>::new();
+        let mut add = |opout: Opout, seal: XOutputSeal, witness_id, state: AllocatedState| {
+            if filter.should_include(seal.to_outpoint(), witness_id) {
+                leaves.insert(opout, AllocationLeaf { opout, seal, state });
+            }
+        };
+        for item in state.rights_all() {
+            add(item.opout, item.seal, item.witness, AllocatedState::Void);
+        }
+        for item in state.fungible_all() {
+            add(item.opout, item.seal, item.witness, item.state.into());
+        }
+        for item in state.data_all() {
+            add(item.opout, item.seal, item.witness, item.state.clone().into());
+        }
+        for item in state.attach_all() {
+            add(item.opout, item.seal, item.witness, item.state.clone().into());
+        }
+        Ok(leaves.into_values().collect())
     }
 
-    pub fn transfer(
+    /// Exports allocations, operations and witnesses of a contract as Arrow
+    /// record batches for loading into analytical stores.
+    #[cfg(feature = "analytics")]
+    pub fn export_analytics(
         &self,
         contract_id: ContractId,
-        outputs: impl AsRef<[XOutputSeal]>,
-        secret_seal: Option<XChain<SecretSeal>>,
-    ) -> Result<Transfer, StockError<S, H, P, ConsignError>> {
+    ) -> Result<ContractExport, StockError<S, H, P>> {
+        let state = self.contract_state(contract_id)?;
+        let mut export = ContractExportBuilder::new(contract_id);
+        let contract = contract_id.to_string();
+
+        let mut add = |opout: Opout,
+                       seal: XOutputSeal,
+                       witness_id: Option<XWitnessId>,
+                       kind: &str,
+                       amount: Option<u64>,
+                       data: Option<String>| {
+            let outpoint = seal.to_outpoint();
+            let layer1 = outpoint.layer1();
+            let outpoint = *outpoint.as_reduced_unsafe();
+            export.allocations.push_row([
+                Value::from(contract.clone()),
+                Value::from(opout.op.to_string()),
+                Value::from(opout.ty.to_inner()),
+                Value::from(opout.no),
+                Value::from(layer1.to_string()),
+                Value::from(outpoint.txid.to_string()),
+                Value::from(outpoint.vout.into_u32()),
+                Value::from(kind.to_owned()),
+                Value::from(amount),
+                Value::from(data),
+                Value::from(witness_id.map(|id| id.to_string())),
+            ]);
+        };
+        for item in state.rights_all() {
+            add(item.opout, item.seal, item.witness, "void", None, None);
+        }
+        for item in state.fungible_all() {
+            let amount = Some(item.state.value.as_u64());
+            add(item.opout, item.seal, item.witness, "amount", amount, None);
+        }
+        for item in state.data_all() {
+            let data = Some(item.state.value.to_string());
+            add(item.opout, item.seal, item.witness, "data", None, data);
+        }
+        for item in state.attach_all() {
+            let data = Some(item.state.file.id.to_string());
+            add(item.opout, item.seal, item.witness, "attachment", None, data);
+        }
+
+        use rgb::OpFullType;
+
+        let mut add = |op: &dyn Operation,
+                       kind: &str,
+                       bundle_id: Option<BundleId>,
+                       witness_id: Option<XWitnessId>| {
+            let op_type = match op.full_type() {
+                OpFullType::Genesis => None,
+                OpFullType::StateTransition(ty) => Some(ty.to_inner()),
+                OpFullType::StateExtension(ty) => Some(ty.to_inner()),
+            };
+            let assignments = op
+                .assignments()
+                .flat()
+                .values()
+                .map(|assigns| assigns.len_u16() as u32)
+                .sum::<u32>();
+            export.operations.push_row([
+                Value::from(contract.clone()),
+                Value::from(op.id().to_string()),
+                Value::from(kind.to_owned()),
+                Value::from(op_type),
+                Value::from(bundle_id.map(|id| id.to_string())),
+                Value::from(op.inputs().len() as u32),
+                Value::from(assignments),
+                Value::from(witness_id.map(|id| id.to_string())),
+            ]);
+        };
+        add(self.stash.genesis(contract_id)?, "genesis", None, None);
+        let mut known = BTreeSet::new();
+        let mut witnesses = vec![];
+        for witness_id in self.stash.witness_ids()? {
+            let witness = self.stash.witness(witness_id)?;
+            let mut bundles = 0u32;
+            for bundle_id in witness.anchors.known_bundle_ids() {
+                let Ok(bundle) = self.stash.bundle(bundle_id) else {
+                    continue;
+                };
+                let mut is_contract = false;
+                for (opid, transition) in &bundle.known_transitions {
+                    if transition.contract_id != contract_id {
+                        continue;
+                    }
+                    is_contract = true;
+                    if known.insert(*opid) {
+                        add(transition, "transition", Some(bundle_id), Some(witness_id));
+                    }
+                }
+                bundles += is_contract as u32;
+            }
+            if bundles > 0 {
+                witnesses.push((witness_id, bundles));
+            }
+        }
+        for extension in self.stash.extensions()? {
+            if extension.contract_id == contract_id {
+                add(extension, "extension", None, None);
+            }
+        }
+
+        for (witness_id, bundles) in witnesses {
+            let ord = state.witness_ord(witness_id);
+            let height = match ord {
+                Some(WitnessOrd::Mined(pos)) => Some(pos.height().get()),
+                _ => None,
+            };
+            export.witnesses.push_row([
+                Value::from(contract.clone()),
+                Value::from(witness_id.to_string()),
+                Value::from(witness_id.layer1().to_string()),
+                Value::from(ord.map(|ord| ord.to_string())),
+                Value::from(height),
+                Value::from(bundles),
+            ]);
+        }
+
+        Ok(export.finish())
+    }
+
+    /// Constructs graph of the contract operations known to the stock, which
+    /// can be exported into DOT or GraphML formats.
+    pub fn contract_history(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<ContractHistory, StockError<S, H, P>> {
+        let mut history = ContractHistory::new(contract_id);
+        history.add_genesis(self.stash.genesis(contract_id)?);
+        for witness_id in self.stash.witness_ids()? {
+            let witness = self.stash.witness(witness_id)?;
+            for bundle_id in witness.anchors.known_bundle_ids() {
+                let Ok(bundle) = self.stash.bundle(bundle_id) else {
+                    continue;
+                };
+                for (opid, transition) in &bundle.known_transitions {
+                    if transition.contract_id == contract_id && !history.nodes.contains_key(opid) {
+                        history.add_transition(transition, witness_id);
+                    }
+                }
+            }
+        }
+        for extension in self.stash.extensions()? {
+            if extension.contract_id == contract_id {
+                history.add_extension(extension);
+            }
+        }
+        Ok(history)
+    }
+
+    /// Reconstructs inflation rights of an RGB20 asset and verifies that the
+    /// known contract operations follow its monetary policy.
+    ///
+    /// Returns `None` if the contract doesn't implement RGB20 inflation, i.e.
+    /// has no interface implementation defining both issued supply and
+    /// inflation allowance.
+    pub fn inflation_report(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<Option<InflationReport>, StockError<S, H, P>> {
+        let (schema_ifaces, state, _) = self.contract_raw(contract_id)?;
+        let types = schema_ifaces.iimpls.values().find_map(|iimpl| {
+            let supply_type = iimpl.global_type(&fname!(TEMPLATE_GLOBAL_ISSUED_SUPPLY))?;
+            let allowance_type = iimpl.assignments_type(&fname!(RGB20_ASSIGNMENT_INFLATION))?;
+            Some((supply_type, allowance_type))
+        });
+        let Some((supply_type, allowance_type)) = types else {
+            return Ok(None);
+        };
+
+        let is_valid = |ord: Option<WitnessOrd>| ord.is_some_and(|ord| ord.is_valid());
+        let mut audit = InflationAudit::new(contract_id, supply_type, allowance_type);
+        audit.add_operation(self.stash.genesis(contract_id)?, None, None);
+        let mut transitions =
+            BTreeMap::<OpId, (&Transition, XWitnessId, Option<WitnessOrd>)>::new();
+        for witness_id in self.stash.witness_ids()? {
+            let ord = state.witness_ord(witness_id);
+            let witness = self.stash.witness(witness_id)?;
+            for bundle_id in witness.anchors.known_bundle_ids() {
+                let Ok(bundle) = self.stash.bundle(bundle_id) else {
+                    continue;
+                };
+                for (opid, transition) in &bundle.known_transitions {
+                    if transition.contract_id != contract_id {
+                        continue;
+                    }
+                    // A transition may be anchored to several witnesses in case of RBF, and we
+                    // keep the one which is not archived
+                    match transitions.entry(*opid) {
+                        Entry::Vacant(entry) => {
+                            entry.insert((transition, witness_id, ord));
+                        }
+                        Entry::Occupied(mut entry) if !is_valid(entry.get().2) && is_valid(ord) => {
+                            entry.insert((transition, witness_id, ord));
+                        }
+                        Entry::Occupied(_) => {}
+                    }
+                }
+            }
+        }
+        for (transition, witness_id, ord) in transitions.into_values() {
+            if !is_valid(ord) {
+                continue;
+            }
+            audit.add_operation(transition, Some(witness_id), ord);
+        }
+        for item in state.fungible_all() {
+            if item.opout.ty == allowance_type {
+                audit.add_holder(item.opout, item.seal);
+            }
+        }
+
+        Ok(Some(audit.finish()))
+    }
+
+    /// Returns allocations of other contracts which the contract depends on,
+    /// as declared in the contract genesis supplement.
+    pub fn contract_dependencies(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<BTreeSet<ContractDependency>, StockError<S, H, P>> {
+        self.stash.genesis(contract_id)?;
+        Ok(self
+            .stash
+            .supplement(ContentRef::Genesis(contract_id))?
+            .map(|suppl| suppl.dependencies().into_inner().release())
+            .unwrap_or_default())
+    }
+
+    /// Returns graph of dependencies between all known contracts, mapping each
+    /// contract to the set of contracts it depends on.
+    pub fn dependency_graph(
+        &self,
+    ) -> Result<BTreeMap<ContractId, BTreeSet<ContractId>>, StockError<S, H, P>> {
+        let mut graph = BTreeMap::new();
+        for genesis in self.stash.geneses()? {
+            let contract_id = genesis.contract_id();
+            let deps = self
+                .contract_dependencies(contract_id)?
+                .into_iter()
+                .map(|dep| dep.contract_id)
+                .collect();
+            graph.insert(contract_id, deps);
+        }
+        Ok(graph)
+    }
+
+    /// Returns contracts which directly depend on the given contract.
+    pub fn contract_dependents(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<BTreeSet<ContractId>, StockError<S, H, P>> {
+        Ok(self
+            .dependency_graph()?
+            .into_iter()
+            .filter(|(_, deps)| deps.contains(&contract_id))
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    /// Checks that all allocations the contract depends on, directly or via
+    /// other contracts, exist and are locked at the declared outpoints.
+    ///
+    /// An allocation is locked if it is not spent by any of the state
+    /// transitions known to the stock and its outpoint passes the filter,
+    /// which may be used to ensure that the outpoint is not spent on-chain.
+    pub fn check_dependencies(
+        &self,
+        contract_id: ContractId,
+        filter: impl AssignmentsFilter,
+    ) -> Result<(), StockError<S, H, P>> {
+        let graph = self.dependency_graph()?;
+        let deps = self.contract_dependencies(contract_id)?;
+        self.check_dependencies_inner(contract_id, deps, &graph, &filter, &mut bset![], &mut vec![])
+    }
+
+    /// Checks dependencies declared by the consignment which is about to be
+    /// accepted, such that contracts backed by allocations which are unknown,
+    /// spent or not locked at the declared outpoints are not imported.
+    fn check_consignment_dependencies<const TRANSFER: bool>(
+        &self,
+        consignment: &Consignment<TRANSFER>,
+    ) -> Result<(), StockError<S, H, P>> {
+        let contract_id = consignment.contract_id();
+        let declared = consignment
+            .supplements
+            .iter()
+            .find(|suppl| suppl.content_id == ContentRef::Genesis(contract_id))
+            .map(|suppl| suppl.dependencies().into_inner().release());
+        let deps = match declared {
+            Some(deps) => deps,
+            None if self.stash.genesis(contract_id).is_ok() => {
+                self.contract_dependencies(contract_id)?
+            }
+            None => none!(),
+        };
+        if deps.is_empty() {
+            return Ok(());
+        }
+        let graph = self.dependency_graph()?;
+        self.check_dependencies_inner(
+            contract_id,
+            deps,
+            &graph,
+            &FilterIncludeAll,
+            &mut bset![],
+            &mut vec![],
+        )
+    }
+
+    fn check_dependencies_inner(
+        &self,
+        contract_id: ContractId,
+        deps: BTreeSet<ContractDependency>,
+        graph: &BTreeMap<ContractId, BTreeSet<ContractId>>,
+        filter: &impl AssignmentsFilter,
+        checked: &mut BTreeSet<ContractId>,
+        path: &mut Vec<ContractId>,
+    ) -> Result<(), StockError<S, H, P>> {
+        if path.contains(&contract_id) {
+            return Err(DependencyError::Cycle(contract_id).into());
+        }
+        if !checked.insert(contract_id) {
+            return Ok(());
+        }
+        path.push(contract_id);
+        for dep in deps {
+            if !graph.contains_key(&dep.contract_id) {
+                return Err(DependencyError::UnknownContract(contract_id, dep.contract_id).into());
+            }
+            let state = self.contract_state(dep.contract_id)?;
+            let allocation = state
+                .rights_all()
+                .map(|a| (a.opout, a.seal, a.witness))
+                .chain(state.fungible_all().map(|a| (a.opout, a.seal, a.witness)))
+                .chain(state.data_all().map(|a| (a.opout, a.seal, a.witness)))
+                .chain(state.attach_all().map(|a| (a.opout, a.seal, a.witness)))
+                .find(|(opout, _, _)| *opout == dep.opout);
+            let Some((_, seal, witness_id)) = allocation else {
+                return Err(DependencyError::AllocationAbsent(contract_id, Box::new(dep)).into());
+            };
+            let outpoint = seal.to_outpoint();
+            if outpoint != dep.lock
+                || !filter.should_include(outpoint, witness_id)
+                || !self.index.spenders(dep.opout)?.is_empty()
+            {
+                return Err(DependencyError::NotLocked(contract_id, Box::new(dep)).into());
+            }
+            let next = self.contract_dependencies(dep.contract_id)?;
+            self.check_dependencies_inner(dep.contract_id, next, graph, filter, checked, path)?;
+        }
+        path.pop();
+        Ok(())
+    }
+
+    pub fn contract_builder(
+        &self,
+        issuer: impl Into<Identity>,
+        schema_id: SchemaId,
+        iface: impl Into<IfaceRef>,
+    ) -> Result<ContractBuilder, StockError<S, H, P>> {
+        Ok(self
+            .stash
+            .contract_builder(issuer.into(), schema_id, iface)?)
+    }
+
+    pub fn transition_builder(
+        &self,
+        contract_id: ContractId,
+        iface: impl Into<IfaceRef>,
+        transition_name: Option<impl Into<FieldName>>,
+    ) -> Result<TransitionBuilder, StockError<S, H, P>> {
+        Ok(self
+            .stash
+            .transition_builder(contract_id, iface, transition_name)?)
+    }
+
+    pub fn blank_builder(
+        &self,
+        contract_id: ContractId,
+        iface: impl Into<IfaceRef>,
+    ) -> Result<TransitionBuilder, StockError<S, H, P>> {
+        Ok(self.stash.blank_builder(contract_id, iface)?)
+    }
+
+    pub fn export_schema(&self, schema_id: SchemaId) -> Result<ValidKit, StockError<S, H, P>> {
+        let mut kit = Kit::default();
+        let schema_ifaces = self.schema(schema_id)?;
+        kit.schemata
+            .push(schema_ifaces.schema.clone())
+            .expect("single item");
+        for name in schema_ifaces.iimpls.keys() {
+            let iface = self.stash.iface(name.clone())?;
+            kit.ifaces.push(iface.clone()).expect("type guarantees");
+        }
+        kit.iimpls
+            .extend(schema_ifaces.iimpls.values().cloned())
+            .expect("type guarantees");
+        let (types, scripts) = self.stash.extract(&schema_ifaces.schema, &kit.ifaces)?;
+        kit.scripts
+            .extend(scripts.into_values())
+            .expect("type guarantees");
+        kit.types = types;
+        Ok(kit.validate().expect("stock produced invalid kit"))
+    }
+
+    pub fn export_contract(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<Contract, StockError<S, H, P, ConsignError>> {
+        let consignment = self.consign::<false>(contract_id, [], None)?;
+        Ok(consignment)
+    }
+
+    pub fn transfer(
+        &self,
+        contract_id: ContractId,
+        outputs: impl AsRef<[XOutputSeal]>,
+        secret_seal: Option<XChain<SecretSeal>>,
+    ) -> Result<Transfer, StockError<S, H, P, ConsignError>> {
         let consignment = self.consign(contract_id, outputs, secret_seal)?;
         Ok(consignment)
     }
 
+    /// Extracts consignments for each of the recipients of the invoices paid by
+    /// the witness transaction with the provided id, composed with
+    /// [`Stock::compose_batch`].
+    ///
+    /// Each invoice must be provided together with the same beneficiary output
+    /// number which was used during the composition. The returned transfers
+    /// follow the order of the provided invoices. Since the recipients share
+    /// the same state transition, use [`Transfer::extract_for_terminal`] to
+    /// conceal the data of the other recipients before sending the transfer.
+    #[allow(clippy::result_large_err)]
+    pub fn transfers<'invoice>(
+        &self,
+        witness_id: XWitnessId,
+        payments: impl IntoIterator<Item = (&'invoice RgbInvoice, Option<Vout>)>,
+    ) -> Result<Vec<Transfer>, StockError<S, H, P, ConsignError>> {
+        let mut transfers = vec![];
+        for (invoice, beneficiary_vout) in payments {
+            let contract_id = invoice.contract.ok_or(ConsignError::NoContract)?;
+            let layer1 = invoice.beneficiary.chain_network().layer1();
+            let transfer = match (invoice.beneficiary.into_inner(), beneficiary_vout) {
+                (Beneficiary::BlindedSeal(seal), _) => {
+                    self.transfer(contract_id, [], Some(XChain::with(layer1, seal)))?
+                }
+                (Beneficiary::WitnessVout(payload), Some(vout)) => {
+                    let seal = GraphSeal::new_random_vout(payload.method, vout);
+                    let seal = XChain::<GraphSeal>::with(layer1, seal)
+                        .try_to_output_seal(witness_id)
+                        .map_err(|_| ConsignError::NoBeneficiaryOutput)?;
+                    self.transfer(contract_id, [seal], None)?
+                }
+                (Beneficiary::WitnessVout(_), None) => {
+                    return Err(ConsignError::NoBeneficiaryOutput.into());
+                }
+            };
+            transfers.push(transfer);
+        }
+        Ok(transfers)
+    }
+
+    /// Constructs request for synchronizing this stock with other one,
+    /// listing bundles and state extensions known for each of the contracts.
+    pub fn sync_request(&self) -> Result<SyncRequest, StockError<S, H, P>> {
+        let mut known = bmap! {};
+        for genesis in self.stash.geneses()? {
+            let contract_id = genesis.contract_id();
+            let ops = KnownOps {
+                bundles: Confined::from_checked(self.index.contract_bundle_ids(contract_id)?),
+                extensions: Confined::from_checked(self.contract_extension_ids(contract_id)?),
+            };
+            known.insert(contract_id, ops);
+        }
+        Ok(SyncRequest {
+            known: Confined::from_checked(known),
+        })
+    }
+
+    /// Answers synchronization request with delta consignments for the
+    /// contracts which the requesting stock doesn't know or misses bundles or
+    /// state extensions for.
+    #[allow(clippy::result_large_err)]
+    pub fn sync_response(
+        &self,
+        request: &SyncRequest,
+    ) -> Result<SyncResponse, StockError<S, H, P, ConsignError>> {
+        let mut deltas = bmap! {};
+        for genesis in self.stash.geneses()? {
+            let contract_id = genesis.contract_id();
+            let missing_bundles = self
+                .index
+                .contract_bundle_ids(contract_id)?
+                .into_iter()
+                .filter(|bundle_id| !request.knows_bundle(contract_id, *bundle_id))
+                .collect::<Vec<_>>();
+            let missing_extensions = self
+                .contract_extension_ids(contract_id)?
+                .into_iter()
+                .filter(|opid| !request.knows_extension(contract_id, *opid))
+                .collect::<Vec<_>>();
+            if request.knows_contract(contract_id)
+                && missing_bundles.is_empty()
+                && missing_extensions.is_empty()
+            {
+                continue;
+            }
+            let mut witness_bundles = vec![];
+            for bundle_id in missing_bundles {
+                witness_bundles.push(self.witness_bundle(bundle_id)?);
+            }
+            let mut extensions = Vec::with_capacity(missing_extensions.len());
+            for opid in missing_extensions {
+                extensions.push(self.stash.extension(opid)?.clone());
+            }
+            let mut delta = self.export_contract(contract_id)?;
+            delta.bundles = merge_witness_bundles(witness_bundles)?;
+            delta.extensions =
+                Confined::try_from_iter(extensions).map_err(|_| ConsignError::TooManyBundles)?;
+            deltas.insert(contract_id, delta);
+        }
+        Ok(SyncResponse {
+            deltas: Confined::from_checked(deltas),
+        })
+    }
+
+    /// Applies synchronization response: completes each of the delta
+    /// consignments with the operations known to this stock which are
+    /// required by the history of the received ones, validates and imports
+    /// them. Delta consignments failing the validation are skipped and
+    /// reported.
+    #[allow(clippy::result_large_err)]
+    pub fn apply_sync(
+        &mut self,
+        response: SyncResponse,
+        resolver: impl ResolveWitness,
+        testnet: bool,
+    ) -> Result<SyncReport, StockError<S, H, P, ConsignError>> {
+        let mut report = SyncReport::default();
+        for (contract_id, mut delta) in response.deltas {
+            if self.stash.genesis(contract_id).is_ok() {
+                self.complete_delta(&mut delta)?;
+            }
+            match delta.validate(&resolver, testnet) {
+                Ok(contract) => {
+                    let status = self.import_contract(contract, &resolver)?;
+                    report.imported.insert(contract_id, status);
+                }
+                Err((status, _)) => {
+                    report.rejected.insert(contract_id, status);
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Adds to the delta consignment local bundles and state extensions from
+    /// the history of its operations which are absent in the delta.
+    #[allow(clippy::result_large_err)]
+    fn complete_delta(
+        &self,
+        delta: &mut Contract,
+    ) -> Result<(), StockError<S, H, P, ConsignError>> {
+        let mut witness_bundles = delta.bundles.iter().cloned().collect::<Vec<_>>();
+        let mut extensions = delta.extensions.iter().cloned().collect::<Vec<_>>();
+        let mut known = bset![delta.genesis.id()];
+        let mut ids = vec![];
+        for transition in witness_bundles
+            .iter()
+            .flat_map(|wb| wb.bundle.known_transitions.values())
+        {
+            known.insert(transition.id());
+            ids.extend(transition.inputs().iter().map(|input| input.prev_out.op));
+        }
+        for extension in &extensions {
+            known.insert(extension.id());
+            ids.extend(extension.redeemed.values().copied());
+        }
+
+        while let Some(id) = ids.pop() {
+            if known.contains(&id) {
+                continue;
+            }
+            if let Ok(extension) = self.stash.extension(id) {
+                known.insert(id);
+                ids.extend(extension.redeemed.values().copied());
+                extensions.push(extension.clone());
+                continue;
+            }
+            // Operations unknown to this stock are reported by the validation
+            let Ok(bundle_id) = self.index.bundle_id_for_op(id) else {
+                continue;
+            };
+            let witness_bundle = self.witness_bundle(bundle_id)?;
+            for transition in witness_bundle.bundle.known_transitions.values() {
+                known.insert(transition.id());
+                ids.extend(transition.inputs().iter().map(|input| input.prev_out.op));
+            }
+            witness_bundles.push(witness_bundle);
+        }
+
+        delta.bundles = merge_witness_bundles(witness_bundles)?;
+        delta.extensions =
+            Confined::try_from_iter(extensions).map_err(|_| ConsignError::TooManyBundles)?;
+        Ok(())
+    }
+
+    /// Returns ids of all state extensions known for the contract.
+    fn contract_extension_ids(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<BTreeSet<OpId>, StockError<S, H, P>> {
+        Ok(self
+            .stash
+            .extensions()?
+            .filter(|extension| extension.contract_id == contract_id)
+            .map(Extension::id)
+            .collect())
+    }
+
     fn consign<const TRANSFER: bool>(
         &self,
         contract_id: ContractId,
@@ -779,6 +1958,32 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
                 entry.insert(bw);
             }
         }
+        // 1.4. Add requested outputs defined by witness transactions (which can't be
+        //      concealed in advance) to the consignment terminals
+        for (bundle_id, witness_bundle) in &witness_bundles {
+            let witness_id = witness_bundle.witness_id();
+            for opid in witness_bundle.bundle.known_transitions.keys() {
+                let Some(transition) = transitions.get(opid) else {
+                    continue;
+                };
+                for typed_assignments in transition.assignments.values() {
+                    for index in 0..typed_assignments.len_u16() {
+                        let Ok(Some(seal)) = typed_assignments.revealed_seal_at(index) else {
+                            continue;
+                        };
+                        if seal.as_reduced_unsafe().txid != TxPtr::WitnessTx {
+                            continue;
+                        }
+                        let output = seal.try_to_output_seal(witness_id);
+                        if matches!(output, Ok(output) if outputs.contains(&output)) {
+                            terminals.entry(*bundle_id).or_insert(
+                                typed_assignments.to_confidential_seals()[index as usize],
+                            );
+                        }
+                    }
+                }
+            }
+        }
 
         // 2. Collect all state transitions between terminals and genesis
         let mut ids = vec![];
@@ -841,20 +2046,7 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         }
         let ifaces = Confined::from_checked(ifaces);
 
-        let mut bundles = BTreeMap::<XWitnessId, WitnessBundle>::new();
-        for witness_bundle in witness_bundles.into_values() {
-            let witness_id = witness_bundle.witness_id();
-            match bundles.get_mut(&witness_id) {
-                Some(prev) => {
-                    *prev = prev.clone().merge_reveal(witness_bundle)?;
-                }
-                None => {
-                    bundles.insert(witness_id, witness_bundle);
-                }
-            }
-        }
-        let bundles = Confined::try_from_iter(bundles.into_values())
-            .map_err(|_| ConsignError::TooManyBundles)?;
+        let bundles = merge_witness_bundles(witness_bundles.into_values())?;
         let terminals =
             Confined::try_from(terminals).map_err(|_| ConsignError::TooManyTerminals)?;
 
@@ -868,7 +2060,7 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         // TODO: Add known sigs to the consignment
 
         Ok(Consignment {
-            version: ContainerVer::V2,
+            version: ContainerVer::V3,
             transfer: TRANSFER,
 
             schema: schema_ifaces.schema,
@@ -883,12 +2075,16 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
             supplements,
             types,
             scripts,
+            records: none!(),
         })
     }
 
     /// Composes a batch of state transitions updating state for the provided
     /// set of previous outputs, satisfying requirements of the invoice, paying
     /// the change back and including the necessary blank state transitions.
+    ///
+    /// Outputs reserved by other batches are not spent; the composed batch
+    /// itself is not reserved, see [`Stock::compose_reserved`].
     #[allow(clippy::result_large_err)]
     pub fn compose(
         &self,
@@ -910,26 +2106,324 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         )
     }
 
-    /// Composes a batch of state transitions updating state for the provided
-    /// set of previous outputs, satisfying requirements of the invoice, paying
-    /// the change back and including the necessary blank state transitions.
+    /// Composes a batch of state transitions like [`Stock::compose`] and
+    /// reserves the outputs spent by it until the deadline, which is a unix
+    /// timestamp, such that they can't be used for composing other transfers
+    /// until the batch is accepted with [`Stock::consume_fascia`] or
+    /// abandoned. See [`Stock::reserve`] for the details.
+    ///
+    /// Expired reservations are dropped before composing.
     #[allow(clippy::too_many_arguments, clippy::result_large_err)]
-    pub fn compose_deterministic(
-        &self,
+    pub fn compose_reserved(
+        &mut self,
         invoice: &RgbInvoice,
         prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
         method: CloseMethod,
         beneficiary_vout: Option<impl Into<Vout>>,
-        priority: u64,
         allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
-        pedersen_blinder: impl Fn(ContractId, AssignmentType) -> BlindingFactor,
+        deadline: i64,
+    ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
+        self.stash.sweep_reservations(Utc::now().timestamp())?;
+        let batch = self.compose(invoice, prev_outputs, method, beneficiary_vout, allocator)?;
+        self.reserve(&batch, deadline)?;
+        Ok(batch)
+    }
+
+    /// Composes a batch of state transitions updating state for the provided
+    /// set of previous outputs, satisfying requirements of the invoice, paying
+    /// the change back and including the necessary blank state transitions.
+    #[allow(clippy::too_many_arguments, clippy::result_large_err)]
+    pub fn compose_deterministic(
+        &self,
+        invoice: &RgbInvoice,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        beneficiary_vout: Option<impl Into<Vout>>,
+        priority: u64,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+        pedersen_blinder: impl Fn(ContractId, AssignmentType) -> BlindingFactor,
+        seal_blinder: impl Fn(ContractId, AssignmentType) -> u64,
+    ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
+        self.compose_batch_deterministic(
+            [(invoice, beneficiary_vout.map(Into::into))],
+            prev_outputs,
+            method,
+            priority,
+            allocator,
+            pedersen_blinder,
+            seal_blinder,
+        )
+    }
+
+    /// Composes a batch of state transitions paying multiple invoices of the
+    /// same contract within a single state transition (and, thus, in a single
+    /// witness transaction), paying the change back and including the
+    /// necessary blank state transitions.
+    ///
+    /// Each invoice is provided together with the number of the witness
+    /// transaction output paying to the beneficiary, which must be given for
+    /// the witness vout invoices only.
+    ///
+    /// All invoices must use the same contract, interface, operation,
+    /// assignment and network. The consignments for each of the recipients can
+    /// be extracted with [`Stock::transfers`] once the batch is accepted into
+    /// the stock.
+    #[allow(clippy::result_large_err)]
+    pub fn compose_batch<'invoice>(
+        &self,
+        payments: impl IntoIterator<Item = (&'invoice RgbInvoice, Option<Vout>)>,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+    ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
+        self.compose_batch_deterministic(
+            payments,
+            prev_outputs,
+            method,
+            u64::MAX,
+            allocator,
+            |_, _| BlindingFactor::random(),
+            |_, _| rand::random(),
+        )
+    }
+
+    /// Composes a batch of state transitions paying multiple invoices of the
+    /// same contract within a single state transition, using the provided
+    /// blinding factors. See [`Stock::compose_batch`] for the details.
+    #[allow(clippy::too_many_arguments, clippy::result_large_err)]
+    pub fn compose_batch_deterministic<'invoice>(
+        &self,
+        payments: impl IntoIterator<Item = (&'invoice RgbInvoice, Option<Vout>)>,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        priority: u64,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+        pedersen_blinder: impl Fn(ContractId, AssignmentType) -> BlindingFactor,
+        seal_blinder: impl Fn(ContractId, AssignmentType) -> u64,
+    ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
+        self.compose_batch_with(
+            payments,
+            prev_outputs,
+            method,
+            priority,
+            allocator,
+            pedersen_blinder,
+            seal_blinder,
+            &AuthorizeAll,
+        )
+    }
+
+    /// Composes a batch of state transitions like [`Stock::compose`], asking
+    /// the authorizer for each allocation which is about to be spent.
+    ///
+    /// Fails with [`ComposeError::SpendVetoed`] if the authorizer vetoes
+    /// spending of any of the allocations, including the ones of other
+    /// contracts moved to the change with blank state transitions.
+    #[allow(clippy::result_large_err)]
+    pub fn compose_authorized(
+        &self,
+        invoice: &RgbInvoice,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        beneficiary_vout: Option<impl Into<Vout>>,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+        authorizer: &impl SpendAuthorizer,
+    ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
+        self.compose_batch_authorized(
+            [(invoice, beneficiary_vout.map(Into::into))],
+            prev_outputs,
+            method,
+            allocator,
+            authorizer,
+        )
+    }
+
+    /// Composes a batch of state transitions paying multiple invoices like
+    /// [`Stock::compose_batch`], asking the authorizer for each allocation
+    /// which is about to be spent. See [`Stock::compose_authorized`] for the
+    /// details.
+    #[allow(clippy::result_large_err)]
+    pub fn compose_batch_authorized<'invoice>(
+        &self,
+        payments: impl IntoIterator<Item = (&'invoice RgbInvoice, Option<Vout>)>,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+        authorizer: &impl SpendAuthorizer,
+    ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
+        self.compose_batch_with(
+            payments,
+            prev_outputs,
+            method,
+            u64::MAX,
+            allocator,
+            |_, _| BlindingFactor::random(),
+            |_, _| rand::random(),
+            authorizer,
+        )
+    }
+
+    /// Composes a batch of state transitions like [`Stock::compose`], spending
+    /// only those of the provided previous outputs which keep the consignment
+    /// for the beneficiary within the size budget (in bytes).
+    ///
+    /// Outputs are selected in the order of increasing size of the history of
+    /// their allocations until the invoiced amount is covered; for invoices
+    /// not requesting an amount all outputs holding the contract state are
+    /// spent. The budget is checked against the strict-serialized size of the
+    /// consignment which the beneficiary will receive for the composed batch,
+    /// where the witness transaction and its anchor, which are not known
+    /// before the transaction is signed, are represented by placeholders.
+    ///
+    /// Fails with [`ComposeError::SizeBudgetExceeded`] reporting the size of
+    /// the best consignment found if the budget can't be met.
+    #[allow(clippy::result_large_err)]
+    pub fn compose_budgeted(
+        &self,
+        invoice: &RgbInvoice,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        beneficiary_vout: Option<impl Into<Vout>>,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+        budget: usize,
+    ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
+        let contract_id = invoice.contract.ok_or(ComposeError::NoContract)?;
+        let iface = invoice.iface.as_ref().ok_or(ComposeError::NoIface)?;
+        let builder =
+            self.transition_builder(contract_id, iface.clone(), invoice.operation.clone())?;
+        let assignment_name = invoice
+            .assignment
+            .as_ref()
+            .or_else(|| builder.default_assignment().ok())
+            .ok_or(BuilderError::NoDefaultAssignment)?
+            .clone();
+        let assignment_id = builder
+            .assignments_type(&assignment_name)
+            .ok_or(BuilderError::InvalidStateField(assignment_name))?;
+        let target = match invoice.owned_state {
+            InvoiceState::Amount(amount) => Some(amount),
+            _ => None,
+        };
+
+        let mut bundle_sizes = BTreeMap::<BundleId, usize>::new();
+        let prev_outputs = prev_outputs.into_iter().map(Into::into).collect::<Vec<_>>();
+        let mut candidates = vec![];
+        for (output, list) in self.contract_assignments_for(contract_id, prev_outputs)? {
+            let mut history = BTreeSet::new();
+            let mut amount = Amount::ZERO;
+            for (opout, state) in list {
+                history.extend(self.history(contract_id, opout.op)?);
+                if let (true, PersistedState::Amount(value, _, _)) =
+                    (opout.ty == assignment_id, state)
+                {
+                    amount += value;
+                }
+            }
+            if target.is_some() && amount == Amount::ZERO {
+                continue;
+            }
+            let size = self.history_size(&history, &mut bundle_sizes)?;
+            candidates.push((output, history, amount, size));
+        }
+        candidates.sort_by_key(|(output, _, _, size)| (*size, *output));
+
+        let mut selected = vec![];
+        let mut covered = Amount::ZERO;
+        for (output, _, amount, _) in candidates {
+            if target.is_some_and(|target| covered >= target) {
+                break;
+            }
+            selected.push(output);
+            covered += amount;
+        }
+
+        let batch =
+            self.compose(invoice, selected.iter().copied(), method, beneficiary_vout, allocator)?;
+        let achievable = self.consignment_size(contract_id, &selected, &batch)?;
+        if achievable > budget {
+            return Err(ComposeError::SizeBudgetExceeded { budget, achievable }.into());
+        }
+        Ok(batch)
+    }
+
+    /// Computes the strict-serialized size of the transfer consignment which
+    /// will be produced for the beneficiary once the composed batch spending
+    /// the provided outputs is accepted into the stock.
+    ///
+    /// Since the witness transaction is not known yet, its anchor and public
+    /// witness are accounted by the size of their placeholders.
+    #[allow(clippy::result_large_err)]
+    fn consignment_size(
+        &self,
+        contract_id: ContractId,
+        spent: &[XOutputSeal],
+        batch: &Batch,
+    ) -> Result<usize, StockError<S, H, P, ComposeError>> {
+        let transfer = self.consign::<true>(contract_id, spent, None)?;
+        let mut size = encoded_size(&transfer);
+        for info in batch.main.clone() {
+            let input_map = info
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(no, _)| (Vin::from_u32(no as u32), info.id))
+                .collect::<BTreeMap<_, _>>();
+            let bundle = TransitionBundle {
+                close_method: info.method,
+                input_map: InputMap::from(
+                    Confined::try_from(input_map).map_err(|_| ComposeError::TooManyInputs)?,
+                ),
+                known_transitions: Confined::from_checked(bmap! { info.id => info.transition }),
+            };
+            let witness_bundle: WitnessBundle = WitnessBundle {
+                pub_witness: strict_dumb!(),
+                anchor: strict_dumb!(),
+                bundle,
+            };
+            size += encoded_size(&witness_bundle);
+        }
+        // Terminal of the beneficiary added to the transfer
+        size += encoded_size(&BundleId::strict_dumb())
+            + encoded_size(&XChain::Bitcoin(SecretSeal::strict_dumb()));
+        Ok(size)
+    }
+
+    #[allow(clippy::too_many_arguments, clippy::result_large_err)]
+    fn compose_batch_with<'invoice>(
+        &self,
+        payments: impl IntoIterator<Item = (&'invoice RgbInvoice, Option<Vout>)>,
+        prev_outputs: impl IntoIterator<Item = impl Into<XOutputSeal>>,
+        method: CloseMethod,
+        priority: u64,
+        allocator: impl Fn(ContractId, AssignmentType, VelocityHint) -> Option<Vout>,
+        pedersen_blinder: impl Fn(ContractId, AssignmentType) -> BlindingFactor,
         seal_blinder: impl Fn(ContractId, AssignmentType) -> u64,
+        authorizer: &impl SpendAuthorizer,
     ) -> Result<Batch, StockError<S, H, P, ComposeError>> {
+        let payments = payments.into_iter().collect::<Vec<_>>();
+        let (invoice, _) = *payments.first().ok_or(ComposeError::NoInvoices)?;
+        if !self.close_method_policy()?.outgoing.contains(method) {
+            return Err(ComposeError::CloseMethodPolicy(method).into());
+        }
+        if payments
+            .iter()
+            .any(|(invoice, _)| !invoice.accepts_close_method(method))
+        {
+            return Err(ComposeError::CloseMethodUnsupported(method).into());
+        }
         let layer1 = invoice.layer1();
         let prev_outputs = prev_outputs
             .into_iter()
             .map(|o| o.into())
             .collect::<HashSet<XOutputSeal>>();
+        let reserved = self.reserved_outputs()?;
+        if let Some(output) = prev_outputs
+            .iter()
+            .map(XOutputSeal::to_outpoint)
+            .find(|outpoint| reserved.contains(outpoint))
+        {
+            return Err(ComposeError::ReservedOutput(output).into());
+        }
 
         #[allow(clippy::type_complexity)]
         let output_for_assignment =
@@ -959,9 +2453,19 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
             };
 
         // 1. Prepare the data
-        if let Some(expiry) = invoice.expiry {
-            if expiry < Utc::now().timestamp() {
-                return Err(ComposeError::InvoiceExpired.into());
+        for (other, _) in &payments {
+            if let Some(expiry) = other.expiry {
+                if expiry < Utc::now().timestamp() {
+                    return Err(ComposeError::InvoiceExpired.into());
+                }
+            }
+            if other.contract != invoice.contract
+                || other.iface != invoice.iface
+                || other.operation != invoice.operation
+                || other.assignment != invoice.assignment
+                || other.beneficiary.chain_network() != invoice.beneficiary.chain_network()
+            {
+                return Err(ComposeError::InvoiceMismatch.into());
             }
         }
         let contract_id = invoice.contract.ok_or(ComposeError::NoContract)?;
@@ -986,21 +2490,45 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         let mut alt_inputs = Vec::<XOutputSeal>::new();
 
         let layer1 = invoice.beneficiary.chain_network().layer1();
-        let beneficiary = match (invoice.beneficiary.into_inner(), beneficiary_vout) {
-            (Beneficiary::BlindedSeal(seal), None) => {
-                BuilderSeal::Concealed(XChain::with(layer1, seal))
-            }
-            (Beneficiary::BlindedSeal(_), Some(_)) => {
-                return Err(ComposeError::BeneficiaryVout.into());
-            }
-            (Beneficiary::WitnessVout(payload), Some(vout)) => {
-                let blinding = seal_blinder(contract_id, assignment_id);
-                let seal = GraphSeal::with_blinded_vout(payload.method, vout, blinding);
-                BuilderSeal::Revealed(XChain::with(layer1, seal))
-            }
-            (Beneficiary::WitnessVout(_), None) => {
-                return Err(ComposeError::NoBeneficiaryOutput.into());
-            }
+        let destinations = payments
+            .iter()
+            .map(|(invoice, _)| invoice.beneficiary)
+            .collect::<Vec<_>>();
+        let mut beneficiaries = Vec::with_capacity(payments.len());
+        for (invoice, beneficiary_vout) in payments {
+            let beneficiary = match (invoice.beneficiary.into_inner(), beneficiary_vout) {
+                (Beneficiary::BlindedSeal(seal), None) => {
+                    BuilderSeal::Concealed(XChain::with(layer1, seal))
+                }
+                (Beneficiary::BlindedSeal(_), Some(_)) => {
+                    return Err(ComposeError::BeneficiaryVout.into());
+                }
+                (Beneficiary::WitnessVout(payload), Some(vout)) => {
+                    let blinding = seal_blinder(contract_id, assignment_id);
+                    let seal = GraphSeal::with_blinded_vout(payload.method, vout, blinding);
+                    BuilderSeal::Revealed(XChain::with(layer1, seal))
+                }
+                (Beneficiary::WitnessVout(_), None) => {
+                    return Err(ComposeError::NoBeneficiaryOutput.into());
+                }
+            };
+            beneficiaries.push((beneficiary, invoice.owned_state.clone()));
+        }
+        let authorize = |contract_id: ContractId,
+                         opout: Opout,
+                         output: XOutputSeal,
+                         state: &PersistedState,
+                         destinations: &[XChainNet<Beneficiary>]| {
+            let request = SpendRequest {
+                contract_id,
+                opout,
+                output,
+                state,
+                destinations,
+            };
+            authorizer
+                .authorize(&request)
+                .map_err(|reason| ComposeError::SpendVetoed(opout, reason))
         };
 
         // 2. Prepare transition
@@ -1008,13 +2536,16 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         let mut sum_inputs = Amount::ZERO;
         let mut sum_alt = Amount::ZERO;
         let mut data_inputs = vec![];
-        let mut data_main = true;
-        let lookup_state =
-            if let InvoiceState::Data(NonFungible::RGB21(allocation)) = &invoice.owned_state {
-                Some(DataState::from(*allocation))
-            } else {
-                None
-            };
+        let mut data_alt = vec![];
+        let lookup_states = beneficiaries
+            .iter()
+            .filter_map(|(_, state)| match state {
+                InvoiceState::Data(NonFungible::RGB21(allocation)) => {
+                    Some(DataState::from(*allocation))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
 
         for (output, list) in
             self.contract_assignments_for(contract_id, prev_outputs.iter().copied())?
@@ -1025,6 +2556,7 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
                 alt_inputs.push(output)
             };
             for (opout, mut state) in list {
+                authorize(contract_id, opout, output, &state, &destinations)?;
                 if output.method() == method {
                     main_builder = main_builder.add_input(opout, state.clone())?;
                 } else {
@@ -1044,94 +2576,102 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
                         sum_alt += value;
                     }
                 } else if let PersistedState::Data(value, _) = state {
-                    if lookup_state.as_ref() == Some(&value) && output.method() != method {
-                        data_main = false;
+                    if lookup_states.contains(&value) && output.method() != method {
+                        data_alt.push(value.clone());
                     }
                     data_inputs.push(value);
                 }
             }
         }
-        // Add payments to beneficiary and change
-        match invoice.owned_state.clone() {
-            InvoiceState::Amount(amt) => {
-                // Pay beneficiary
-                if sum_inputs < amt {
-                    return Err(ComposeError::InsufficientState.into());
-                }
-
-                let sum_main = sum_inputs - sum_alt;
-                let (paid_main, paid_alt) =
-                    if sum_main < amt { (sum_main, amt - sum_main) } else { (amt, Amount::ZERO) };
-                let blinding_beneficiary = pedersen_blinder(contract_id, assignment_id);
-
-                if paid_main > Amount::ZERO {
-                    main_builder = main_builder.add_fungible_state_raw(
-                        assignment_id,
-                        beneficiary,
-                        paid_main,
-                        blinding_beneficiary,
-                    )?;
-                }
-                if paid_alt > Amount::ZERO {
-                    alt_builder = alt_builder.add_fungible_state_raw(
-                        assignment_id,
-                        beneficiary,
-                        paid_alt,
-                        blinding_beneficiary,
-                    )?;
-                }
-
-                let blinding_change = pedersen_blinder(contract_id, assignment_id);
-                let change_seal = output_for_assignment(contract_id, assignment_id)?;
-
-                // Pay change
-                if sum_main > paid_main {
-                    main_builder = main_builder.add_fungible_state_raw(
-                        assignment_id,
-                        change_seal,
-                        sum_main - paid_main,
-                        blinding_change,
-                    )?;
-                }
-                if sum_alt > paid_alt {
-                    alt_builder = alt_builder.add_fungible_state_raw(
-                        assignment_id,
-                        change_seal,
-                        sum_alt - paid_alt,
-                        blinding_change,
-                    )?;
-                }
-            }
-            InvoiceState::Data(data) => match data {
-                NonFungible::RGB21(allocation) => {
-                    let lookup_state = DataState::from(allocation);
-                    if !data_inputs.into_iter().any(|x| x == lookup_state) {
+        // Add payments to beneficiaries and change
+        let sum_main = sum_inputs - sum_alt;
+        let mut left_main = sum_main;
+        let mut left_alt = sum_alt;
+        let mut has_amounts = false;
+        for (beneficiary, state) in beneficiaries {
+            match state {
+                InvoiceState::Amount(amt) => {
+                    // Pay beneficiary
+                    if left_main + left_alt < amt {
                         return Err(ComposeError::InsufficientState.into());
                     }
+                    has_amounts = true;
 
-                    let seal = seal_blinder(contract_id, assignment_id);
-                    if data_main {
-                        main_builder = main_builder.add_data_raw(
+                    let (paid_main, paid_alt) = if left_main < amt {
+                        (left_main, amt - left_main)
+                    } else {
+                        (amt, Amount::ZERO)
+                    };
+                    left_main -= paid_main;
+                    left_alt -= paid_alt;
+                    let blinding_beneficiary = pedersen_blinder(contract_id, assignment_id);
+
+                    if paid_main > Amount::ZERO {
+                        main_builder = main_builder.add_fungible_state_raw(
                             assignment_id,
                             beneficiary,
-                            allocation,
-                            seal,
+                            paid_main,
+                            blinding_beneficiary,
                         )?;
-                    } else {
-                        alt_builder = alt_builder.add_data_raw(
+                    }
+                    if paid_alt > Amount::ZERO {
+                        alt_builder = alt_builder.add_fungible_state_raw(
                             assignment_id,
                             beneficiary,
-                            allocation,
-                            seal,
+                            paid_alt,
+                            blinding_beneficiary,
                         )?;
                     }
                 }
-            },
-            _ => {
-                todo!(
-                    "only PersistedState::Amount and PersistedState::Allocation are currently \
-                     supported"
-                )
+                InvoiceState::Data(data) => match data {
+                    NonFungible::RGB21(allocation) => {
+                        let lookup_state = DataState::from(allocation);
+                        let Some(pos) = data_inputs.iter().position(|x| *x == lookup_state) else {
+                            return Err(ComposeError::InsufficientState.into());
+                        };
+                        data_inputs.remove(pos);
+
+                        let seal = seal_blinder(contract_id, assignment_id);
+                        if !data_alt.contains(&lookup_state) {
+                            main_builder = main_builder.add_data_raw(
+                                assignment_id,
+                                beneficiary,
+                                allocation,
+                                seal,
+                            )?;
+                        } else {
+                            alt_builder = alt_builder.add_data_raw(
+                                assignment_id,
+                                beneficiary,
+                                allocation,
+                                seal,
+                            )?;
+                        }
+                    }
+                },
+                _ => return Err(ComposeError::UnsupportedInvoiceState.into()),
+            }
+        }
+        if has_amounts {
+            let blinding_change = pedersen_blinder(contract_id, assignment_id);
+            let change_seal = output_for_assignment(contract_id, assignment_id)?;
+
+            // Pay change
+            if left_main > Amount::ZERO {
+                main_builder = main_builder.add_fungible_state_raw(
+                    assignment_id,
+                    change_seal,
+                    left_main,
+                    blinding_change,
+                )?;
+            }
+            if left_alt > Amount::ZERO {
+                alt_builder = alt_builder.add_fungible_state_raw(
+                    assignment_id,
+                    change_seal,
+                    left_alt,
+                    blinding_change,
+                )?;
             }
         }
 
@@ -1164,6 +2704,7 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
                     Method::OpretFirst => outputs_opret.push(output),
                 }
                 for (opout, state) in assigns {
+                    authorize(id, opout, output, &state, &[])?;
                     let seal = output_for_assignment(id, opout.ty)?;
                     match output.method() {
                         Method::TapretFirst => {
@@ -1229,6 +2770,183 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         Ok(batch)
     }
 
+    /// Composes a self-transfer merging fragmented allocations of the
+    /// contract owned by the wallet (as defined by the `filter`) into a single
+    /// output, following the consolidation policy.
+    ///
+    /// Allocations are merged starting from the smallest ones, while the
+    /// history of the consolidated allocation doesn't grow beyond the policy
+    /// overhead limit. Outputs which carry other state (of other assignment
+    /// types or other contracts), use a different seal closing method or are
+    /// reserved by pending transfers are not consolidated.
+    ///
+    /// Returns the batch, which must be completed into a witness transaction
+    /// in the same way as batches produced by [`Stock::compose`], together
+    /// with a report on the history economics of the consolidation.
+    #[allow(clippy::result_large_err)]
+    pub fn consolidate(
+        &self,
+        contract_id: ContractId,
+        iface: impl Into<IfaceRef>,
+        filter: impl AssignmentsFilter,
+        policy: &ConsolidationPolicy,
+    ) -> Result<(Batch, ConsolidationReport), StockError<S, H, P, ComposeError>> {
+        let iface = iface.into();
+        let mut builder = self.transition_builder(contract_id, iface, None::<FieldName>)?;
+        let assignment_name = policy
+            .assignment
+            .as_ref()
+            .or_else(|| builder.default_assignment().ok())
+            .ok_or(BuilderError::NoDefaultAssignment)?
+            .clone();
+        let assignment_id = builder
+            .assignments_type(&assignment_name)
+            .ok_or(BuilderError::InvalidStateField(assignment_name))?;
+        let reserved = self.reserved_outputs()?;
+
+        let mut candidates = BTreeMap::<XOutputSeal, Vec<(Opout, Amount)>>::new();
+        for item in self.contract_state(contract_id)?.fungible_all() {
+            if item.opout.ty == assignment_id
+                && item.seal.method() == policy.method
+                && filter.should_include(item.seal.to_outpoint(), item.witness)
+                && !reserved.contains(&item.seal.to_outpoint())
+            {
+                candidates
+                    .entry(item.seal)
+                    .or_default()
+                    .push((item.opout, item.state.value.into()));
+            }
+        }
+        let mut candidates = candidates
+            .into_iter()
+            .map(|(output, list)| {
+                let amount = list.iter().map(|(_, amount)| *amount).sum::<Amount>();
+                (output, list, amount)
+            })
+            .filter(|(_, _, amount)| policy.max_amount.map(|max| *amount <= max).unwrap_or(true))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(_, _, amount)| *amount);
+
+        let mut bundle_sizes = BTreeMap::<BundleId, usize>::new();
+        let mut history = BTreeSet::<BundleId>::new();
+        let mut max_history_size = 0usize;
+        let mut separate_history_size = 0usize;
+        let mut outputs = vec![];
+        let mut merged = vec![];
+        let mut amount = Amount::ZERO;
+        for (output, list, output_amount) in candidates {
+            if merged.len() + list.len() > policy.max_allocations {
+                break;
+            }
+            let assigned = self.contract_assignments_for(contract_id, [output])?;
+            if assigned
+                .values()
+                .flat_map(HashMap::keys)
+                .any(|opout| opout.ty != assignment_id)
+                || self
+                    .contracts_assigning([output])?
+                    .any(|id| id != contract_id)
+            {
+                continue;
+            }
+            let mut output_history = BTreeSet::new();
+            for (opout, _) in &list {
+                output_history.extend(self.history(contract_id, opout.op)?);
+            }
+            let output_size = self.history_size(&output_history, &mut bundle_sizes)?;
+            let union = history
+                .union(&output_history)
+                .copied()
+                .collect::<BTreeSet<_>>();
+            let union_size = self.history_size(&union, &mut bundle_sizes)?;
+            if union_size - max_history_size.max(output_size) > policy.max_overhead {
+                continue;
+            }
+            history = union;
+            max_history_size = max_history_size.max(output_size);
+            separate_history_size += output_size;
+            amount += output_amount;
+            outputs.push(output);
+            for (opout, state) in assigned.into_values().flatten() {
+                builder = builder.add_input(opout, state)?;
+                merged.push(opout);
+            }
+        }
+        if merged.len() < policy.min_allocations.max(1) {
+            return Err(ComposeError::NothingToConsolidate.into());
+        }
+
+        let layer1 = outputs[0].layer1();
+        let seal = GraphSeal::with_blinded_vout(policy.method, policy.vout, rand::random());
+        builder = builder.add_fungible_state_raw(
+            assignment_id,
+            BuilderSeal::Revealed(XChain::with(layer1, seal)),
+            amount,
+            BlindingFactor::random(),
+        )?;
+        let outputs_count = outputs.len();
+        let info = TransitionInfo::new(builder.complete_transition()?, outputs).map_err(|e| {
+            debug_assert!(!matches!(e, TransitionInfoError::CloseMethodDivergence(_)));
+            ComposeError::TooManyInputs
+        })?;
+        let batch = Batch {
+            main: TransitionDichotomy::with(info, None),
+            blanks: none!(),
+        };
+        let history_size = self.history_size(&history, &mut bundle_sizes)?;
+        let report = ConsolidationReport {
+            merged,
+            outputs: outputs_count,
+            amount,
+            history_bundles: history.len(),
+            history_size,
+            max_history_size,
+            separate_history_size,
+        };
+        Ok((batch, report))
+    }
+
+    /// Collects witness bundles from the history of an operation down to the
+    /// genesis. Concealed transitions terminate the history.
+    fn history(
+        &self,
+        contract_id: ContractId,
+        opid: OpId,
+    ) -> Result<BTreeSet<BundleId>, StockError<S, H, P>> {
+        let mut bundles = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        let mut ids = vec![opid];
+        while let Some(id) = ids.pop() {
+            if id == contract_id || !visited.insert(id) {
+                continue;
+            }
+            let bundle_id = self.index.bundle_id_for_op(id)?;
+            bundles.insert(bundle_id);
+            if let Some(transition) = self.stash.bundle(bundle_id)?.known_transitions.get(&id) {
+                ids.extend(transition.inputs().iter().map(|input| input.prev_out.op));
+            }
+        }
+        Ok(bundles)
+    }
+
+    fn history_size(
+        &self,
+        history: &BTreeSet<BundleId>,
+        sizes: &mut BTreeMap<BundleId, usize>,
+    ) -> Result<usize, StockError<S, H, P>> {
+        let mut total = 0;
+        for bundle_id in history {
+            if let Some(size) = sizes.get(bundle_id) {
+                total += size;
+                continue;
+            }
+            let size = encoded_size(&self.witness_bundle(*bundle_id)?);
+            sizes.insert(*bundle_id, size);
+            total += size;
+        }
+        Ok(total)
+    }
+
     fn store_transaction<E: Error>(
         &mut self,
         f: impl FnOnce(
@@ -1245,7 +2963,11 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
             self.state.rollback_transaction();
             self.stash.rollback_transaction();
         })?;
-        f(&mut self.stash, &mut self.state, &mut self.index)?;
+        f(&mut self.stash, &mut self.state, &mut self.index).inspect_err(|_| {
+            self.state.rollback_transaction();
+            self.stash.rollback_transaction();
+            self.index.rollback_transaction();
+        })?;
         self.index
             .commit_transaction()
             .map_err(StockError::from)
@@ -1274,6 +2996,42 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         self.consume_consignment(contract, resolver)
     }
 
+    /// Validates contract and verifies signatures of its issuer against the
+    /// trust policy, importing the contract only if it is valid and the
+    /// policy accepts the verdict on the issuer.
+    ///
+    /// Unlike [`Stock::import_contract`], the contracts which are not
+    /// imported are not reported as errors: the returned report contains the
+    /// validation status, results of checking each of the signatures and the
+    /// verdict on the issuer, allowing wallets to keep a record of their
+    /// onboarding decisions.
+    pub fn import_contract_verified<R: ResolveWitness>(
+        &mut self,
+        contract: Contract,
+        resolver: R,
+        testnet: bool,
+        policy: &TrustPolicy<impl SigValidator>,
+    ) -> Result<OnboardReport, StockError<S, H, P>> {
+        let contract_id = contract.contract_id();
+        let issuer = contract.genesis.issuer.clone();
+        let (signatures, verdict) = policy.check(&contract);
+        let (status, imported) = match contract.validate(&resolver, testnet) {
+            Ok(contract) if policy.accepts(&verdict) => {
+                (self.import_contract(contract, resolver)?, true)
+            }
+            Ok(contract) => (contract.into_validation_status(), false),
+            Err((status, _)) => (status, false),
+        };
+        Ok(OnboardReport {
+            contract_id,
+            issuer,
+            status,
+            signatures,
+            verdict,
+            imported,
+        })
+    }
+
     pub fn accept_transfer<R: ResolveWitness>(
         &mut self,
         contract: ValidTransfer,
@@ -1282,27 +3040,418 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         self.consume_consignment(contract, resolver)
     }
 
-    fn consume_consignment<R: ResolveWitness, const TRANSFER: bool>(
+    /// Imports kit like [`Stock::import_kit`], recording provenance of all
+    /// its schemata unless they are already known.
+    pub fn import_kit_with_provenance(
         &mut self,
-        consignment: ValidConsignment<TRANSFER>,
-        resolver: R,
+        kit: ValidKit,
+        provenance: Provenance,
     ) -> Result<validation::Status, StockError<S, H, P>> {
-        let (mut consignment, status) = consignment.split();
-
-        consignment = self.stash.resolve_secrets(consignment)?;
-        self.store_transaction(move |stash, state, index| {
-            state.update_from_consignment(&consignment, &resolver)?;
-            index.index_consignment(&consignment)?;
-            stash.consume_consignment(consignment)?;
-            Ok(())
-        })?;
-
+        let objs = kit
+            .schemata
+            .iter()
+            .map(|schema| ProvenanceRef::Schema(schema.schema_id()))
+            .collect::<Vec<_>>();
+        let status = self.import_kit(kit)?;
+        self.stash.store_provenance(objs, &provenance)?;
         Ok(status)
     }
 
-    /// Imports fascia into the stash, index and inventory.
-    ///
-    /// Part of the transfer workflow. Called once PSBT is completed and an RGB
+    /// Imports contract like [`Stock::import_contract`], recording provenance
+    /// of the contract and its schema unless they are already known.
+    pub fn import_contract_with_provenance<R: ResolveWitness>(
+        &mut self,
+        contract: ValidContract,
+        resolver: R,
+        provenance: Provenance,
+    ) -> Result<validation::Status, StockError<S, H, P>> {
+        let objs = [
+            ProvenanceRef::Contract(contract.contract_id()),
+            ProvenanceRef::Schema(contract.schema_id()),
+        ];
+        let status = self.import_contract(contract, resolver)?;
+        self.stash.store_provenance(objs, &provenance)?;
+        Ok(status)
+    }
+
+    /// Accepts transfer like [`Stock::accept_transfer`], extended with the
+    /// checks and reports selected by the options.
+    ///
+    /// All the checks happen before any data are written to the stock. If
+    /// the options require the confirmations, transfers which terminal
+    /// witnesses have insufficient number of confirmations are either
+    /// rejected with [`ConfirmationError::Unconfirmed`], or accepted
+    /// provisionally, see [`AcceptOptions::with_confirmations`].
+    pub fn accept_transfer_with<R: ResolveWitness>(
+        &mut self,
+        contract: ValidTransfer,
+        resolver: R,
+        opts: AcceptOptions,
+    ) -> Result<AcceptReport, StockError<S, H, P, ConfirmationError>> {
+        let terminal_witnesses = contract
+            .bundles
+            .iter()
+            .filter(|wb| contract.terminals.contains_key(&wb.bundle.bundle_id()))
+            .map(WitnessBundle::witness_id)
+            .collect::<Vec<_>>();
+        let analysis = match &opts.analyzer {
+            Some(analyze) => terminal_witnesses.iter().map(|id| analyze(*id)).collect(),
+            None => vec![],
+        };
+        let replacements = if opts.replacements {
+            self.witness_replacements(&contract, &resolver)?
+        } else {
+            vec![]
+        };
+        let opids = contract
+            .bundles
+            .iter()
+            .flat_map(|wb| wb.bundle.known_transitions.keys().copied())
+            .collect::<BTreeSet<_>>();
+        let provenance = [
+            ProvenanceRef::Transfer(contract.consignment_id()),
+            ProvenanceRef::Contract(contract.contract_id()),
+            ProvenanceRef::Schema(contract.schema_id()),
+        ];
+
+        // Witness ords are resolved through the confirmation resolver, which
+        // excludes state assigned by the unconfirmed witnesses
+        let min_confirmations = opts.min_confirmations;
+        let resolver = ConfirmationResolver::new(&resolver, min_confirmations, opts.tip_height);
+        let mut pending = BTreeSet::new();
+        for witness_id in terminal_witnesses
+            .into_iter()
+            .filter(|_| min_confirmations > 0)
+        {
+            let confirmations = resolver
+                .confirmations(witness_id)
+                .map_err(ConfirmationError::from)?;
+            if confirmations >= min_confirmations {
+                continue;
+            }
+            if !opts.provisional {
+                return Err(ConfirmationError::Unconfirmed {
+                    witness_id,
+                    confirmations,
+                    required: min_confirmations,
+                }
+                .into());
+            }
+            pending.insert(witness_id);
+        }
+
+        // With the progress reporting, the witnesses of all the transfer
+        // bundles are resolved before any data are written to the stock, such
+        // that cancellation reported by the sink leaves the stock unmodified
+        let mut ords = BTreeMap::new();
+        if let Some(sink) = opts.progress {
+            let witnesses = contract
+                .bundles
+                .iter()
+                .map(WitnessBundle::witness_id)
+                .collect::<Vec<_>>();
+            let progress = ProgressResolver::new(&resolver, sink, witnesses.iter().copied());
+            for witness_id in witnesses {
+                if ords.contains_key(&witness_id) {
+                    continue;
+                }
+                let ord = progress
+                    .resolve_pub_witness_ord(witness_id)
+                    .map_err(|err| {
+                        StockError::<S, H, P, ConfirmationError>::WitnessUnresolved(witness_id, err)
+                    })?;
+                ords.insert(witness_id, ord);
+            }
+            sink.report(Progress::Storing);
+        }
+
+        let id = contract.consignment_id();
+        let mut stored = vec![];
+        for witness_id in &pending {
+            if self
+                .stash
+                .store_provisional(*witness_id, Some(min_confirmations))?
+            {
+                stored.push(*witness_id);
+            }
+        }
+        let status = match self.consume_consignment(contract, PrefetchedResolver {
+            ords,
+            fallback: &resolver,
+        }) {
+            Ok(status) => status,
+            Err(err) => {
+                // Consignments failed midway are re-applied later and must keep
+                // the provisional witnesses excluded
+                if !self
+                    .stash
+                    .pending_consignments()?
+                    .any(|c| c.consignment_id() == id)
+                {
+                    for witness_id in stored {
+                        self.stash.store_provisional(witness_id, None)?;
+                    }
+                }
+                return Err(err.into());
+            }
+        };
+
+        if let Some(provenance_info) = &opts.provenance {
+            self.stash.store_provenance(provenance, provenance_info)?;
+        }
+        let mut conflicts = ConflictReport::default();
+        if opts.conflicts {
+            conflicts = self.conflicts()?;
+            conflicts.conflicts.retain(|conflict| {
+                conflict
+                    .branches
+                    .iter()
+                    .any(|branch| opids.contains(&branch.opid))
+            });
+        }
+
+        Ok(AcceptReport {
+            status,
+            pending,
+            analysis,
+            conflicts,
+            replacements,
+        })
+    }
+
+    /// Returns the source from which a schema, contract or transfer was first
+    /// received.
+    pub fn provenance(
+        &self,
+        obj: impl Into<ProvenanceRef>,
+    ) -> Result<Option<&Provenance>, StockError<S, H, P>> {
+        Ok(self.stash.provenance(obj.into())?)
+    }
+
+    /// Returns sources of all objects with known provenance.
+    pub fn provenances(
+        &self,
+    ) -> Result<impl Iterator<Item = (ProvenanceRef, &Provenance)> + '_, StockError<S, H, P>> {
+        Ok(self.stash.provenances()?)
+    }
+
+    /// Returns witnesses of the provisionally accepted transfers together with
+    /// the number of confirmations they require to be finalized.
+    pub fn provisional_witnesses(&self) -> Result<BTreeMap<XWitnessId, u32>, StockError<S, H, P>> {
+        Ok(self.stash.provisional_witnesses()?)
+    }
+
+    /// Finalizes provisionally accepted transfers which terminal witnesses
+    /// are buried under the chain tip at `tip_height` at least by the number
+    /// of blocks required on their acceptance, including the state assigned
+    /// by them into the contract state.
+    ///
+    /// Returns ids of the finalized witnesses.
+    pub fn update_confirmations(
+        &mut self,
+        resolver: impl ResolveWitness,
+        tip_height: u32,
+    ) -> Result<BTreeSet<XWitnessId>, StockError<S, H, P>> {
+        let mut finalized = BTreeSet::new();
+        for (witness_id, min_confirmations) in self.stash.provisional_witnesses()? {
+            let confirmation = ConfirmationResolver::new(&resolver, min_confirmations, tip_height);
+            if confirmation
+                .is_confirmed(witness_id)
+                .map_err(|err| StockError::WitnessUnresolved(witness_id, err))?
+            {
+                finalized.insert(witness_id);
+            }
+        }
+        if finalized.is_empty() {
+            return Ok(finalized);
+        }
+        for witness_id in &finalized {
+            self.stash.store_provisional(*witness_id, None)?;
+        }
+        // Provisional witnesses are archived, and archived witnesses are
+        // always re-resolved, independently of the height
+        self.update_witnesses(resolver, u32::MAX)?;
+        Ok(finalized)
+    }
+
+    /// Detects bundles of the consignment which are presented with witnesses
+    /// different from the ones previously recorded by the stock.
+    pub fn witness_replacements<const TRANSFER: bool>(
+        &self,
+        consignment: &Consignment<TRANSFER>,
+        resolver: impl ResolveWitness,
+    ) -> Result<Vec<WitnessReplacement>, StockError<S, H, P>> {
+        let witness_tx = |public: &XPubWitness, witness_id: XWitnessId| {
+            public
+                .map_ref(|pub_witness| pub_witness.tx().cloned())
+                .transpose()
+                .or_else(|| resolver.resolve_pub_witness(witness_id).ok())
+        };
+
+        let mut replacements = vec![];
+        for wb in &consignment.bundles {
+            let bundle_id = wb.bundle.bundle_id();
+            let witness_id = wb.witness_id();
+            let (known, contract_id) = match self.index.bundle_info(bundle_id) {
+                Err(IndexError::Inconsistency(_)) => continue,
+                Err(err) => return Err(err.into()),
+                Ok((known, contract_id)) => (known.collect::<Vec<_>>(), contract_id),
+            };
+            if known.contains(&witness_id) {
+                continue;
+            }
+            let new_tx = witness_tx(&wb.pub_witness, witness_id);
+            let mut replaced = Vec::with_capacity(known.len());
+            for id in known {
+                let prev_tx = witness_tx(&self.stash.witness(id)?.public, id);
+                replaced.push((id, ReplacementKind::classify(prev_tx.as_ref(), new_tx.as_ref())));
+            }
+            replacements.push(WitnessReplacement {
+                contract_id,
+                bundle_id,
+                witness_id,
+                replaced,
+            });
+        }
+        Ok(replacements)
+    }
+
+    fn consume_consignment<R: ResolveWitness, const TRANSFER: bool>(
+        &mut self,
+        consignment: ValidConsignment<TRANSFER>,
+        resolver: R,
+    ) -> Result<validation::Status, StockError<S, H, P>> {
+        let (mut consignment, status) = consignment.split();
+
+        self.check_consignment_dependencies(&consignment)?;
+        consignment = self.stash.resolve_secrets(consignment)?;
+        self.apply_consignment(consignment.retype(), resolver)?;
+
+        Ok(status)
+    }
+
+    /// Writes consignment data into the stash, state and index.
+    ///
+    /// Witnesses are resolved before any data are written. The consignment is
+    /// recorded as pending before its data are written and is removed from
+    /// the pending ones by the same transaction which writes the data. Thus,
+    /// if the transaction fails midway or can't be persisted, the consignment
+    /// remains pending and can be re-applied with [`Stock::resume_pending`].
+    fn apply_consignment<R: ResolveWitness>(
+        &mut self,
+        consignment: Transfer,
+        resolver: R,
+    ) -> Result<(), StockError<S, H, P>> {
+        let archived = self.excluded_witnesses()?;
+        let resolver = ArchivingResolver {
+            archived: &archived,
+            fallback: resolver,
+        };
+        let mut ords = BTreeMap::new();
+        for witness_id in consignment.bundles.iter().map(WitnessBundle::witness_id) {
+            let ord = resolver
+                .resolve_pub_witness_ord(witness_id)
+                .map_err(|err| StockError::WitnessUnresolved(witness_id, err))?;
+            ords.insert(witness_id, ord);
+        }
+        let resolver = PrefetchedResolver {
+            ords,
+            fallback: resolver,
+        };
+
+        let id = consignment.consignment_id();
+        let opids = consignment
+            .bundles
+            .iter()
+            .flat_map(|wb| wb.bundle.known_transitions.keys().copied())
+            .collect::<BTreeSet<_>>();
+        self.stash.store_pending(id, Some(consignment.clone()))?;
+        let prefetched = &resolver;
+        let res = self.store_transaction(|stash, state, index| {
+            state.update_from_consignment(&consignment, prefetched)?;
+            index.index_consignment(&consignment)?;
+            stash.consume_consignment(consignment.clone())?;
+            stash.clear_pending(id)?;
+            Ok(())
+        });
+        if let Err(err) = res {
+            // The consignment may be removed from the pending ones kept in memory
+            // before the transaction has failed to persist
+            self.stash.store_pending(id, Some(consignment))?;
+            return Err(err);
+        }
+
+        if self.resolve_new_conflicts(&opids, &resolver)? {
+            self.update_witnesses(&resolver, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Returns ids of consignments which acceptance has failed midway.
+    pub fn pending_consignments(&self) -> Result<Vec<ConsignmentId>, StockError<S, H, P>> {
+        Ok(self
+            .stash
+            .pending_consignments()?
+            .map(Transfer::consignment_id)
+            .collect())
+    }
+
+    /// Re-applies consignments which acceptance has failed midway, making
+    /// stash, state and index consistent again.
+    ///
+    /// Each consignment is validated again against the resolver before being
+    /// applied. Consignments which fail the validation or can't be applied
+    /// remain pending and don't prevent other consignments from being
+    /// applied; the outcome for each of the consignments is reported.
+    pub fn resume_pending(
+        &mut self,
+        resolver: impl ResolveWitness,
+        testnet: bool,
+    ) -> Result<ResumeReport, StockError<S, H, P>> {
+        let pending = self
+            .stash
+            .pending_consignments()?
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut report = ResumeReport::default();
+        for consignment in pending {
+            let id = consignment.consignment_id();
+            let validated = if consignment.transfer {
+                consignment
+                    .validate(&resolver, testnet)
+                    .map(ValidConsignment::split)
+            } else {
+                consignment
+                    .retype::<false>()
+                    .validate(&resolver, testnet)
+                    .map(|valid| {
+                        let (consignment, status) = valid.split();
+                        (consignment.retype(), status)
+                    })
+                    .map_err(|(status, consignment)| (status, consignment.retype()))
+            };
+            let (consignment, status) = match validated {
+                Ok(valid) => valid,
+                Err((status, _)) => {
+                    report.rejected.insert(id, status);
+                    continue;
+                }
+            };
+            match self.apply_consignment(consignment, &resolver) {
+                Ok(()) => {
+                    report.applied.insert(id, status);
+                }
+                Err(err) => {
+                    report.failed.insert(id, err.to_string());
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Imports fascia into the stash, index and inventory.
+    ///
+    /// Part of the transfer workflow. Called once PSBT is completed and an RGB
     /// fascia containing anchor and all state transitions is exported from
     /// it.
     ///
@@ -1318,7 +3467,9 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
             stash
                 .consume_witness(SealWitness::new(fascia.witness.clone(), fascia.anchor.clone()))?;
 
+            let mut opids = BTreeSet::new();
             for (contract_id, bundle) in fascia.into_bundles() {
+                opids.extend(bundle.known_transitions.keys().copied());
                 let ids1 = bundle
                     .known_transitions
                     .keys()
@@ -1333,10 +3484,229 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
                 state.update_from_bundle(contract_id, &bundle, witness_id, &resolver)?;
                 stash.consume_bundle(bundle)?;
             }
+            stash.release_reservations(&opids)?;
             Ok(())
         })
     }
 
+    /// Finalizes the transfer by importing the fascia into the stash, index
+    /// and inventory, optionally broadcasting its witness transaction first.
+    ///
+    /// Part of the transfer workflow, called instead of
+    /// [`Stock::consume_fascia`] once PSBT is completed and signed. When a
+    /// broadcaster is provided, the witness transaction is broadcast before
+    /// the fascia is consumed, such that the resolver reports it as a
+    /// tentative mempool transaction; if the broadcast fails the fascia is
+    /// not consumed.
+    pub fn finalize_transfer<R: ResolveWitness, B: Broadcaster>(
+        &mut self,
+        fascia: Fascia,
+        resolver: R,
+        broadcaster: Option<B>,
+    ) -> Result<XWitnessId, StockError<S, H, P, FasciaError>> {
+        let witness_id = fascia.witness_id();
+        if let Some(broadcaster) = broadcaster {
+            let tx = fascia
+                .witness
+                .maybe_map_ref(|w| w.tx().cloned())
+                .ok_or(FasciaError::NoWitnessTx(witness_id))?;
+            broadcaster
+                .broadcast(&tx)
+                .map_err(|err| FasciaError::Broadcast(witness_id, err.to_string()))?;
+        }
+        self.consume_fascia(fascia, resolver)?;
+        Ok(witness_id)
+    }
+
+    /// Composes a batch of state transitions for a CPFP child transaction
+    /// accelerating stuck witness transaction `witness_id`.
+    ///
+    /// The child spends all outputs of the witness transaction with the state
+    /// owned by the wallet (as defined by the `filter`) and using the `method`
+    /// seal closing method, re-assigning the state to its own output `vout`.
+    /// The returned package specifies the fee the child must pay for the
+    /// package to reach the target fee rate (in sats per vbyte); the wallet
+    /// completes the batch into the child transaction and passes its fascia
+    /// to [`Stock::finalize_cpfp`].
+    #[allow(clippy::too_many_arguments, clippy::result_large_err)]
+    pub fn compose_cpfp(
+        &self,
+        witness_id: XWitnessId,
+        iface: impl Into<IfaceRef>,
+        filter: impl AssignmentsFilter,
+        method: CloseMethod,
+        vout: impl Into<Vout>,
+        fee_rate: f64,
+        broadcaster: &impl Broadcaster,
+    ) -> Result<(Batch, CpfpPackage), StockError<S, H, P, CpfpError>> {
+        let mempool = broadcaster
+            .mempool_info(witness_id)
+            .map_err(|err| CpfpError::Broadcaster(witness_id, err.to_string()))?
+            .ok_or(CpfpError::NotInMempool(witness_id))?;
+        if mempool.ancestor_fee_rate() >= fee_rate {
+            return Err(CpfpError::NotStuck(witness_id).into());
+        }
+
+        let iface = iface.into();
+        let vout = vout.into();
+        let contract_ids = self.contracts()?.map(|info| info.id).collect::<Vec<_>>();
+        let mut outputs = BTreeSet::new();
+        for contract_id in contract_ids {
+            let state = self.contract_state(contract_id)?;
+            let seals = state
+                .rights_all()
+                .map(|a| a.seal)
+                .chain(state.fungible_all().map(|a| a.seal))
+                .chain(state.data_all().map(|a| a.seal))
+                .chain(state.attach_all().map(|a| a.seal));
+            outputs.extend(seals.filter(|seal| {
+                seal.method() == method
+                    && seal.map_ref(|seal| seal.txid) == witness_id
+                    && filter.should_include(seal.to_outpoint(), Some(witness_id))
+            }));
+        }
+        if outputs.is_empty() {
+            return Err(CpfpError::NoOutputs(witness_id).into());
+        }
+
+        let mut transitions = vec![];
+        let contract_ids = self
+            .contracts_assigning(outputs.iter().copied())?
+            .collect::<Vec<_>>();
+        for contract_id in contract_ids {
+            let mut builder = self.blank_builder(contract_id, iface.clone())?;
+            let seal = GraphSeal::with_blinded_vout(method, vout, rand::random());
+            let seal = BuilderSeal::Revealed(XChain::with(witness_id.layer1(), seal));
+            let mut inputs = vec![];
+            for (output, assigns) in
+                self.contract_assignments_for(contract_id, outputs.iter().copied())?
+            {
+                inputs.push(output);
+                for (opout, state) in assigns {
+                    builder = builder
+                        .add_input(opout, state.clone())?
+                        .add_owned_state_raw(opout.ty, seal, state)?;
+                }
+            }
+            let info = TransitionInfo::new(builder.complete_transition()?, inputs)
+                .map_err(CpfpError::from)?;
+            transitions.push(info);
+        }
+        let mut transitions = transitions.into_iter();
+        let main = transitions.next().ok_or(CpfpError::NoOutputs(witness_id))?;
+        let batch = Batch {
+            main: TransitionDichotomy::with(main, None),
+            blanks: Confined::from_iter_checked(
+                transitions.map(|info| TransitionDichotomy::with(info, None)),
+            ),
+        };
+
+        let package = CpfpPackage {
+            parent: witness_id,
+            outputs: outputs.into_iter().collect(),
+            vout,
+            ancestor_fee: mempool.ancestor_fee,
+            ancestor_vsize: mempool.ancestor_vsize,
+            fee_rate,
+        };
+        Ok((batch, package))
+    }
+
+    /// Finalizes CPFP child transaction composed with [`Stock::compose_cpfp`],
+    /// checking that it spends all parent outputs of the package, broadcasting
+    /// it and importing its fascia into the stash, index and inventory.
+    ///
+    /// Returns id of the child transaction.
+    pub fn finalize_cpfp<R: ResolveWitness, B: Broadcaster>(
+        &mut self,
+        package: &CpfpPackage,
+        fascia: Fascia,
+        resolver: R,
+        broadcaster: B,
+    ) -> Result<XWitnessId, StockError<S, H, P, CpfpError>> {
+        let child_id = fascia.witness_id();
+        let tx = fascia
+            .witness
+            .maybe_map_ref(|w| w.tx().cloned())
+            .ok_or(FasciaError::NoWitnessTx(child_id))
+            .map_err(CpfpError::from)?;
+        for output in &package.outputs {
+            let outpoint = output.to_outpoint();
+            let spent = tx.layer1() == outpoint.layer1()
+                && tx
+                    .as_reduced_unsafe()
+                    .inputs
+                    .iter()
+                    .any(|input| input.prev_output == *outpoint.as_reduced_unsafe());
+            if !spent {
+                return Err(CpfpError::NotSpent(child_id, outpoint).into());
+            }
+        }
+        Ok(self.finalize_transfer(fascia, resolver, Some(broadcaster))?)
+    }
+
+    /// Checks status of the CPFP package with the child transaction
+    /// `child_id`. Once both transactions are mined, updates witness status in
+    /// the contract state, confirming the transfers of the package.
+    pub fn cpfp_status(
+        &mut self,
+        package: &CpfpPackage,
+        child_id: XWitnessId,
+        resolver: impl ResolveWitness,
+    ) -> Result<CpfpStatus, StockError<S, H, P>> {
+        let mut heights = vec![];
+        for witness_id in [package.parent, child_id] {
+            match resolver
+                .resolve_pub_witness_ord(witness_id)
+                .map_err(|err| StockError::WitnessUnresolved(witness_id, err))?
+            {
+                WitnessOrd::Mined(pos) => heights.push(pos.height().get()),
+                WitnessOrd::Tentative => return Ok(CpfpStatus::Pending),
+                WitnessOrd::Archived => return Ok(CpfpStatus::Dropped),
+            }
+        }
+        let after_height = heights.into_iter().min().unwrap_or_default();
+        self.update_witnesses(resolver, after_height)?;
+        Ok(CpfpStatus::Confirmed)
+    }
+
+    /// Constructs context describing the state transitions of the batch for
+    /// presenting them to a hardware or air-gapped signer of the witness
+    /// transaction.
+    pub fn signing_context(&self, batch: &Batch) -> SigningContext {
+        SigningContext::with(batch, |contract_id| {
+            let schema_id = self.stash.genesis(contract_id).ok()?.schema_id;
+            self.stash.schema(schema_id).ok()?.iimpls.values().next()
+        })
+    }
+
+    /// Completes transfer with the fascia extracted from the PSBT signed by a
+    /// hardware or air-gapped signer, checking that the witness transaction
+    /// commits exactly to the state transitions from the signing context
+    /// presented to the signer.
+    ///
+    /// Returns id of the witness transaction.
+    pub fn complete_signed<R: ResolveWitness>(
+        &mut self,
+        context: &SigningContext,
+        fascia: Fascia,
+        resolver: R,
+    ) -> Result<XWitnessId, StockError<S, H, P, FasciaError>> {
+        let presented = context.opids().collect::<BTreeSet<_>>();
+        let committed = fascia
+            .bundles
+            .values()
+            .flat_map(|dichotomy| dichotomy.iter())
+            .flat_map(|bundle| bundle.known_transitions.keys().copied())
+            .collect::<BTreeSet<_>>();
+        if let Some(opid) = presented.symmetric_difference(&committed).next() {
+            return Err(FasciaError::SigningMismatch(*opid).into());
+        }
+        let witness_id = fascia.witness_id();
+        self.consume_fascia(fascia, resolver)?;
+        Ok(witness_id)
+    }
+
     fn transition(&self, opid: OpId) -> Result<&Transition, StockError<S, H, P, ConsignError>> {
         let bundle_id = self.index.bundle_id_for_op(opid)?;
         let bundle = self.stash.bundle(bundle_id)?;
@@ -1387,6 +3757,111 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         })
     }
 
+    /// Constructs proof of the invoice payment, binding the invoice blinded
+    /// seal to the state transition of an accepted transfer and its mined
+    /// witness transaction.
+    ///
+    /// Returns `None` if the stock doesn't contain a payment for the invoice
+    /// with a mined witness transaction.
+    pub fn payment_proof(
+        &self,
+        invoice: &RgbInvoice,
+    ) -> Result<Option<PaymentProof>, StockError<S, H, P, PaymentProofError>> {
+        let contract_id = invoice.contract.ok_or(PaymentProofError::NoContract)?;
+        let Beneficiary::BlindedSeal(secret) = invoice.beneficiary.into_inner() else {
+            return Err(PaymentProofError::NotBlinded.into());
+        };
+        let secret = XChain::with(invoice.layer1(), secret);
+        let seal = self
+            .stash
+            .seal_secret(secret)?
+            .ok_or(PaymentProofError::UnknownSeal)?;
+
+        let state = self.contract_state(contract_id)?;
+        for opout in self.index.opouts_by_terminals([secret])? {
+            let bundle_id = self.index.bundle_id_for_op(opout.op)?;
+            let witness_bundle = self.witness_bundle(bundle_id)?;
+            if !matches!(state.witness_ord(witness_bundle.witness_id()), Some(WitnessOrd::Mined(_)))
+            {
+                continue;
+            }
+            return Ok(Some(PaymentProof {
+                contract_id,
+                seal,
+                opout,
+                witness_bundle,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Re-issues the contract as a new one, assigning in the new genesis the
+    /// unspent allocations of the given type which pass the filter to the same
+    /// outputs under the `name` assignment of the new contract.
+    ///
+    /// Returns the new contract, which must be imported into the stock, and
+    /// the mapping of the old allocations to the new ones, which must be
+    /// distributed to the holders together with the contract.
+    pub fn upgrade_contract(
+        &self,
+        contract_id: ContractId,
+        assignment_type: AssignmentType,
+        filter: impl AssignmentsFilter,
+        builder: ContractBuilder,
+        name: impl Into<FieldName>,
+    ) -> Result<(ValidContract, ContractUpgrade), StockError<S, H, P, UpgradeError>> {
+        let name = name.into();
+        let state = self.contract_state(contract_id)?;
+        let allocations = state
+            .fungible_all()
+            .filter(|item| item.opout.ty == assignment_type)
+            .filter(|item| filter.should_include(item.seal.to_outpoint(), item.witness))
+            .map(|item| {
+                let seal = item
+                    .seal
+                    .map(|seal| GenesisSeal::new_random(seal.method, seal.txid, seal.vout));
+                (item.opout, seal, Amount::from(item.state.value))
+            })
+            .collect::<Vec<_>>();
+        if allocations.is_empty() {
+            return Err(UpgradeError::NoAllocations.into());
+        }
+
+        let mut builder = builder;
+        for (_, seal, amount) in &allocations {
+            builder = builder
+                .add_fungible_state(name.clone(), *seal, *amount)
+                .map_err(UpgradeError::from)?;
+        }
+        let contract = builder.issue_contract().map_err(UpgradeError::from)?;
+        let upgrade = ContractUpgrade::with(contract_id, &contract.genesis, allocations)?;
+        Ok((contract, upgrade))
+    }
+
+    /// Reports which of the allocations migrated by the upgrade were already
+    /// claimed by their holders. The new contract must be known to the stock.
+    pub fn upgrade_status(
+        &self,
+        upgrade: &ContractUpgrade,
+    ) -> Result<UpgradeStatus, StockError<S, H, P, UpgradeError>> {
+        let genesis = self.stash.genesis(upgrade.new_contract_id)?;
+        upgrade.verify(genesis)?;
+        let state = self.contract_state(upgrade.new_contract_id)?;
+        let unspent = state
+            .fungible_all()
+            .map(|item| item.opout)
+            .collect::<BTreeSet<_>>();
+        let mut status = UpgradeStatus::default();
+        for entry in &upgrade.entries {
+            if unspent.contains(&entry.new_opout) {
+                status.unclaimed.push(entry.clone());
+            } else {
+                status.claimed.push(entry.clone());
+            }
+        }
+        Ok(status)
+    }
+
     pub fn store_secret_seal(
         &mut self,
         seal: XChain<GraphSeal>,
@@ -1394,48 +3869,2681 @@ impl<S: StashProvider, H: StateProvider, P: IndexProvider> Stock<S, H, P> {
         Ok(self.stash.store_secret_seal(seal)?)
     }
 
-    pub fn update_witnesses(
-        &mut self,
-        resolver: impl ResolveWitness,
-        after_height: u32,
-    ) -> Result<UpdateRes, StockError<S, H, P>> {
-        Ok(self.state.update_witnesses(resolver, after_height)?)
+    /// Imports supplement of a third party carrying an attestation about a
+    /// known contract.
+    pub fn import_attestation(&mut self, suppl: Supplement) -> Result<(), StockError<S, H, P>> {
+        if let ContentRef::Genesis(contract_id) = suppl.content_id {
+            self.stash.genesis(contract_id)?;
+        }
+        Ok(self.stash.store_attestation(suppl)?)
     }
-}
 
-#[derive(Clone, Eq, PartialEq, Debug)]
-pub struct UpdateRes {
-    pub succeeded: usize,
-    pub failed: HashMap<XWitnessId, String>,
-}
+    /// Exports supplements carrying third-party attestations about the
+    /// contract, which can be shared with other wallets.
+    pub fn export_attestations(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<Vec<Supplement>, StockError<S, H, P>> {
+        Ok(self
+            .stash
+            .supplements(ContentRef::Genesis(contract_id))?
+            .filter(|suppl| suppl.attestation().is_some())
+            .collect())
+    }
 
-#[cfg(test)]
-mod test {
-    use std::str::FromStr;
+    /// Returns third-party attestations about the contract together with
+    /// the identities of their attesters.
+    pub fn attestations(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<Vec<(Identity, Attestation)>, StockError<S, H, P>> {
+        Ok(self
+            .stash
+            .supplements(ContentRef::Genesis(contract_id))?
+            .filter_map(|suppl| Some((suppl.creator.clone(), suppl.attestation()?)))
+            .collect())
+    }
 
-    use baid64::FromBaid64Str;
-    use commit_verify::{Conceal, DigestExt, Sha256};
-    use strict_encoding::TypeName;
+    /// Returns negative attestations about the contract made by the curators
+    /// of the policy, which the wallet must warn the user about.
+    pub fn attestation_warnings(
+        &self,
+        contract_id: ContractId,
+        policy: &impl AttestationPolicy,
+    ) -> Result<Vec<(Identity, Attestation)>, StockError<S, H, P>> {
+        let mut attestations = self.attestations(contract_id)?;
+        attestations.retain(|(attester, attestation)| {
+            attestation.verdict.is_negative() && policy.is_curator(attester)
+        });
+        Ok(attestations)
+    }
 
-    use super::*;
-    use crate::containers::ConsignmentExt;
+    /// Returns user-defined label attached to a contract, transfer or
+    /// allocation.
+    pub fn label(
+        &self,
+        label_ref: impl Into<LabelRef>,
+    ) -> Result<Option<&str>, StockError<S, H, P>> {
+        Ok(self.stash.label(label_ref.into())?)
+    }
 
-    #[test]
-    fn test_consign() {
-        let mut stock = Stock::in_memory();
-        let seal = XChain::with(
-            rgbcore::Layer1::Bitcoin,
-            GraphSeal::new_random_vout(bp::dbc::Method::OpretFirst, Vout::from_u32(0)),
-        );
-        let secret_seal = seal.conceal();
+    /// Returns all user-defined labels.
+    pub fn labels(
+        &self,
+    ) -> Result<impl Iterator<Item = (LabelRef, &str)> + '_, StockError<S, H, P>> {
+        Ok(self.stash.labels()?)
+    }
 
-        stock.store_secret_seal(seal).unwrap();
+    /// Attaches a label to a contract, transfer or allocation, replacing the
+    /// existing one. Returns whether there were no label before.
+    ///
+    /// Labels are persisted in the stash, but are not a part of any
+    /// commitment and are never exported with consignments.
+    pub fn set_label(
+        &mut self,
+        label_ref: impl Into<LabelRef>,
+        label: impl Into<String>,
+    ) -> Result<bool, StockError<S, H, P>> {
+        Ok(self
+            .stash
+            .store_label(label_ref.into(), Some(label.into()))?)
+    }
+
+    /// Removes label, returning whether the label was present.
+    pub fn remove_label(
+        &mut self,
+        label_ref: impl Into<LabelRef>,
+    ) -> Result<bool, StockError<S, H, P>> {
+        Ok(self.stash.store_label(label_ref.into(), None)?)
+    }
+
+    /// Reserves outputs spent by the composed batch until the deadline, which
+    /// is a unix timestamp. Until then, the outputs can't be used for composing
+    /// other transfers.
+    ///
+    /// The reservation is released once a witness transaction containing the
+    /// batch is accepted with [`Stock::consume_fascia`], or abandoned with
+    /// [`Stock::abandon`] or [`Stock::abandon_expired`].
+    ///
+    /// Batches composed with [`Stock::compose`] and other `compose_*` methods
+    /// are not reserved, except for [`Stock::compose_reserved`]; the method is
+    /// used to reserve them, or to prolong the reservation. Only the deadline
+    /// is stored with the reservation, so it survives reloading the stock.
+    /// Expired reservations are dropped on each call.
+    ///
+    /// Returns the reservation id, which is the id of the main state transition
+    /// of the batch.
+    #[allow(clippy::result_large_err)]
+    pub fn reserve(
+        &mut self,
+        batch: &Batch,
+        deadline: i64,
+    ) -> Result<OpId, StockError<S, H, P, ComposeError>> {
+        self.stash.sweep_reservations(Utc::now().timestamp())?;
+        let id = Reservation::id(batch);
+        let reservation = Reservation::new(batch, deadline).map_err(ComposeError::Reservation)?;
+        self.stash.store_reservation(id, Some(reservation))?;
+        Ok(id)
+    }
+
+    pub fn reservations(
+        &self,
+    ) -> Result<impl Iterator<Item = (OpId, &Reservation)> + '_, StockError<S, H, P>> {
+        Ok(self.stash.reservations()?)
+    }
+
+    /// Returns outputs reserved by the batches which deadline has not passed
+    /// yet.
+    pub fn reserved_outputs(&self) -> Result<BTreeSet<XOutpoint>, StockError<S, H, P>> {
+        let now = Utc::now().timestamp();
+        Ok(self
+            .stash
+            .reservations()?
+            .filter(|(_, reservation)| !reservation.is_expired(now))
+            .flat_map(|(_, reservation)| reservation.outputs.iter().copied())
+            .collect())
+    }
+
+    /// Abandons the composed batch, releasing the outputs reserved by it.
+    pub fn abandon(&mut self, id: OpId) -> Result<bool, StockError<S, H, P>> {
+        Ok(self.stash.store_reservation(id, None)?)
+    }
+
+    /// Abandons all batches which deadline has passed, releasing the reserved
+    /// outputs.
+    ///
+    /// Returns the abandoned reservations, such that the wallet can invalidate
+    /// invoices matched to the seals of the abandoned batches.
+    pub fn abandon_expired(&mut self) -> Result<Vec<(OpId, Reservation)>, StockError<S, H, P>> {
+        Ok(self.stash.sweep_reservations(Utc::now().timestamp())?)
+    }
+
+    /// Reports all allocations spent by more than one state transition known
+    /// to the stock.
+    pub fn conflicts(&self) -> Result<ConflictReport, StockError<S, H, P>> {
+        let mut conflicts = vec![];
+        for (opout, opids) in self.index.contested_opouts()? {
+            let mut contract_id = None;
+            let mut known = Vec::with_capacity(opids.len());
+            for opid in opids {
+                let bundle_id = self.index.bundle_id_for_op(opid)?;
+                let (witnesses, id) = self.index.bundle_info(bundle_id)?;
+                known.push((opid, bundle_id, witnesses.collect::<Vec<_>>()));
+                contract_id = Some(id);
+            }
+            let contract_id = contract_id.expect("contested allocation has spenders");
+            let state = self.contract_state(contract_id)?;
+            let branches = known
+                .into_iter()
+                .map(|(opid, bundle_id, witnesses)| ConflictBranch {
+                    opid,
+                    bundle_id,
+                    witnesses: witnesses
+                        .into_iter()
+                        .map(|id| (id, state.witness_ord(id)))
+                        .collect(),
+                })
+                .collect();
+            conflicts.push(Conflict {
+                contract_id,
+                opout,
+                branches,
+            });
+        }
+        Ok(ConflictReport { conflicts })
+    }
+
+    /// Resolves conflict by keeping the provided state transition and
+    /// archiving witnesses of all other branches, excluding their allocations
+    /// from the contract state.
+    ///
+    /// The choice is persisted in the stash and is applied by all subsequent
+    /// witness updates, until another branch of the conflict is chosen.
+    pub fn resolve_conflict(
+        &mut self,
+        conflict: &Conflict,
+        keep: OpId,
+        resolver: impl ResolveWitness,
+    ) -> Result<UpdateRes, StockError<S, H, P, ConflictError>> {
+        Self::check_branch(conflict, keep)?;
+        self.stash.store_conflict_resolution(conflict.opout, keep)?;
+        Ok(self.update_witnesses(resolver, 0)?)
+    }
+
+    /// Resolves all conflicts where a single branch has a witness mined
+    /// according to the resolver, returning the conflicts which remain
+    /// unresolved.
+    pub fn resolve_conflicts(
+        &mut self,
+        resolver: impl ResolveWitness,
+    ) -> Result<ConflictReport, StockError<S, H, P, ConflictError>> {
+        let resolutions = self.stash.conflict_resolutions()?;
+        let mut unresolved = vec![];
+        let mut changed = false;
+        for conflict in self.conflicts()?.conflicts {
+            let Some(keep) = Self::mined_branch(&conflict, &resolver) else {
+                unresolved.push(conflict);
+                continue;
+            };
+            if resolutions.get(&conflict.opout) != Some(&keep) {
+                Self::check_branch(&conflict, keep)?;
+                changed |= self.stash.store_conflict_resolution(conflict.opout, keep)?;
+            }
+        }
+        if changed {
+            self.update_witnesses(resolver, 0)?;
+        }
+        Ok(ConflictReport {
+            conflicts: unresolved,
+        })
+    }
+
+    /// Checks that the branch can be kept, i.e. it has witnesses which are not
+    /// shared with other branches of the conflict.
+    fn check_branch(conflict: &Conflict, keep: OpId) -> Result<(), ConflictError> {
+        let winner = conflict
+            .branch(keep)
+            .ok_or(ConflictError::UnknownBranch(conflict.opout, keep))?;
+        let others = conflict
+            .branches
+            .iter()
+            .filter(|branch| branch.opid != keep)
+            .flat_map(|branch| branch.witnesses.iter().map(|(id, _)| *id))
+            .collect::<BTreeSet<_>>();
+        if winner.witnesses.iter().all(|(id, _)| others.contains(id)) {
+            return Err(ConflictError::ArchivedBranch(keep));
+        }
+        Ok(())
+    }
+
+    /// Returns the only branch of the conflict which has a witness mined
+    /// according to the resolver.
+    ///
+    /// Witnesses of the branches which were not chosen are archived in the
+    /// contract state, thus their status is requested from the resolver.
+    fn mined_branch(conflict: &Conflict, resolver: impl ResolveWitness) -> Option<OpId> {
+        let mut mined = conflict.branches.iter().filter(|branch| {
+            branch.witnesses.iter().any(|(id, _)| {
+                matches!(resolver.resolve_pub_witness_ord(*id), Ok(WitnessOrd::Mined(_)))
+            })
+        });
+        match (mined.next(), mined.next()) {
+            (Some(branch), None) => Some(branch.opid),
+            _ => None,
+        }
+    }
+
+    /// Chooses branches for the conflicts introduced by the state
+    /// transitions, which were not resolved before.
+    ///
+    /// The mined branch is kept, if there is a single one; otherwise the
+    /// branch which was known to the stock before is preferred. Returns
+    /// whether any new resolution was recorded.
+    fn resolve_new_conflicts(
+        &mut self,
+        opids: &BTreeSet<OpId>,
+        resolver: impl ResolveWitness,
+    ) -> Result<bool, StockError<S, H, P>> {
+        let resolutions = self.stash.conflict_resolutions()?;
+        let mut changed = false;
+        for conflict in self.conflicts()?.conflicts {
+            if resolutions.contains_key(&conflict.opout)
+                || !conflict
+                    .branches
+                    .iter()
+                    .any(|branch| opids.contains(&branch.opid))
+            {
+                continue;
+            }
+            let Some(keep) = Self::mined_branch(&conflict, &resolver)
+                .or_else(|| {
+                    conflict
+                        .branches
+                        .iter()
+                        .find(|branch| !opids.contains(&branch.opid))
+                        .map(|branch| branch.opid)
+                })
+                .filter(|keep| Self::check_branch(&conflict, *keep).is_ok())
+            else {
+                continue;
+            };
+            changed |= self.stash.store_conflict_resolution(conflict.opout, keep)?;
+        }
+        Ok(changed)
+    }
+
+    /// Re-resolves ordering of the witnesses known to the stock, except
+    /// witnesses mined below `after_height`.
+    ///
+    /// Witnesses of the provisionally accepted transfers remain archived until
+    /// they are finalized with [`Stock::update_confirmations`].
+    pub fn update_witnesses(
+        &mut self,
+        resolver: impl ResolveWitness,
+        after_height: u32,
+    ) -> Result<UpdateRes, StockError<S, H, P>> {
+        let archived = self.excluded_witnesses()?;
+        let resolver = ArchivingResolver {
+            archived: &archived,
+            fallback: resolver,
+        };
+        Ok(self.state.update_witnesses(resolver, after_height)?)
+    }
+
+    /// Returns witnesses which state must be excluded from the contract state
+    /// independently of their status reported by resolvers.
+    fn excluded_witnesses(&self) -> Result<BTreeSet<XWitnessId>, StockError<S, H, P>> {
+        let mut excluded = self
+            .stash
+            .provisional_witnesses()?
+            .into_keys()
+            .collect::<BTreeSet<_>>();
+        let branch_witnesses = |opid: OpId| -> Result<BTreeSet<XWitnessId>, StockError<S, H, P>> {
+            let bundle_id = self.index.bundle_id_for_op(opid)?;
+            Ok(self.index.bundle_info(bundle_id)?.0.collect())
+        };
+        for (opout, keep) in self.stash.conflict_resolutions()? {
+            let kept = branch_witnesses(keep)?;
+            for opid in self.index.spenders(opout)? {
+                if opid != keep {
+                    excluded.extend(branch_witnesses(opid)?.difference(&kept));
+                }
+            }
+        }
+        Ok(excluded)
+    }
+
+    /// Recomputes contract state and indexes from the operations kept in the
+    /// stash, for a single contract or for all known contracts.
+    ///
+    /// The state and indexes of each contract are rebuilt from scratch in
+    /// memory and compared with the present ones. If discrepancies are found,
+    /// the rebuilt data replace the present contract state and index entries,
+    /// repairing both missed and corrupted entries. A contract which can't be
+    /// rebuilt is left untouched.
+    pub fn reindex(
+        &mut self,
+        contract_id: Option<ContractId>,
+        resolver: impl ResolveWitness,
+    ) -> Result<ReindexReport, StockError<S, H, P>> {
+        let contracts = match contract_id {
+            Some(contract_id) => bset![self.stash.genesis(contract_id)?.contract_id()],
+            None => self.stash.geneses()?.map(Genesis::contract_id).collect(),
+        };
+        let archived = self.excluded_witnesses()?;
+        let resolver = ArchivingResolver {
+            archived: &archived,
+            fallback: resolver,
+        };
+
+        let mut found = vec![];
+        let mut unresolved = vec![];
+        for contract_id in &contracts {
+            let (bundles, state, index) = self.rebuild_contract(*contract_id, &resolver)?;
+            let discrepancies = self.verify_contract(*contract_id, &bundles, &state)?;
+            if discrepancies.is_empty() {
+                continue;
+            }
+            self.store_transaction(|_, present, present_index| {
+                present.replace_contract(*contract_id, &state)?;
+                present_index.replace_contract(*contract_id, &index)?;
+                Ok(())
+            })?;
+            found.extend(discrepancies);
+            unresolved.extend(self.verify_contract(*contract_id, &bundles, &state)?);
+        }
+
+        Ok(ReindexReport {
+            contracts,
+            found,
+            unresolved,
+        })
+    }
+
+    /// Rebuilds contract state and indexes from the contract operations kept
+    /// in the stash into new in-memory providers.
+    #[allow(clippy::type_complexity)]
+    fn rebuild_contract(
+        &self,
+        contract_id: ContractId,
+        resolver: impl ResolveWitness,
+    ) -> Result<(Vec<(XWitnessId, TransitionBundle)>, MemState, MemIndex), StockError<S, H, P>>
+    {
+        let (schema, genesis, extensions, bundles) = self.contract_ops(contract_id)?;
+        let mut state = State::new(MemState::in_memory());
+        state
+            .update_from_parts(
+                &schema,
+                &genesis,
+                &extensions,
+                bundles.iter().map(|(id, bundle)| (*id, bundle)),
+                resolver,
+            )
+            .map_err(|err| match err {
+                StateError::Resolver(id, e) => StockError::WitnessUnresolved(id, e),
+                err => StockError::Recompute(contract_id, Box::new(err.into())),
+            })?;
+        let mut index = Index::new(MemIndex::in_memory());
+        index
+            .index_parts(
+                contract_id,
+                &genesis,
+                &extensions,
+                bundles.iter().map(|(id, bundle)| (*id, bundle)),
+            )
+            .map_err(|err| StockError::Recompute(contract_id, Box::new(err.into())))?;
+        let state = state.as_provider().clone_no_persistence();
+        let index = index.as_provider().clone_no_persistence();
+        Ok((bundles, state, index))
+    }
+
+    /// Collects contract operations from the stash without using indexes,
+    /// providing transition bundles together with the ids of their witnesses.
+    #[allow(clippy::type_complexity)]
+    fn contract_ops(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<
+        (Schema, Genesis, Vec<Extension>, Vec<(XWitnessId, TransitionBundle)>),
+        StockError<S, H, P>,
+    > {
+        let genesis = self.stash.genesis(contract_id)?.clone();
+        let schema = self.stash.schema(genesis.schema_id)?.schema.clone();
+        let extensions = self
+            .stash
+            .extensions()?
+            .filter(|extension| extension.contract_id == contract_id)
+            .cloned()
+            .collect();
+        let mut bundles = vec![];
+        for witness_id in self.stash.witness_ids()? {
+            let witness = self.stash.witness(witness_id)?;
+            for bundle_id in witness.anchors.known_bundle_ids() {
+                let Ok(bundle) = self.stash.bundle(bundle_id) else {
+                    continue;
+                };
+                if bundle
+                    .known_transitions
+                    .values()
+                    .any(|transition| transition.contract_id == contract_id)
+                {
+                    bundles.push((witness_id, bundle.clone()));
+                }
+            }
+        }
+        Ok((schema, genesis, extensions, bundles))
+    }
+
+    /// Compares present contract state and indexes with the state rebuilt
+    /// from the contract operations.
+    fn verify_contract(
+        &self,
+        contract_id: ContractId,
+        bundles: &[(XWitnessId, TransitionBundle)],
+        rebuilt: &MemState,
+    ) -> Result<Vec<Discrepancy>, StockError<S, H, P>> {
+        fn opouts(state: &impl ContractStateRead) -> BTreeSet<Opout> {
+            state
+                .rights_all()
+                .map(|a| a.opout)
+                .chain(state.fungible_all().map(|a| a.opout))
+                .chain(state.data_all().map(|a| a.opout))
+                .chain(state.attach_all().map(|a| a.opout))
+                .collect()
+        }
+
+        let mut discrepancies = vec![];
+        let expected = opouts(&rebuilt.contract_state(contract_id).map_err(|err| {
+            StockError::Recompute(contract_id, Box::new(StateError::from(err).into()))
+        })?);
+        match self.state.contract_state(contract_id) {
+            Err(_) => discrepancies.push(Discrepancy::StateAbsent(contract_id)),
+            Ok(state) => {
+                let present = opouts(&state);
+                discrepancies.extend(
+                    expected
+                        .difference(&present)
+                        .map(|opout| Discrepancy::AssignmentAbsent(contract_id, *opout)),
+                );
+                discrepancies.extend(
+                    present
+                        .difference(&expected)
+                        .map(|opout| Discrepancy::AssignmentExcess(contract_id, *opout)),
+                );
+            }
+        }
+
+        for (witness_id, bundle) in bundles {
+            let bundle_id = bundle.bundle_id();
+            match self.index.bundle_info(bundle_id) {
+                Err(IndexError::Inconsistency(_)) => {
+                    discrepancies.push(Discrepancy::BundleAbsent(bundle_id))
+                }
+                Err(err) => return Err(err.into()),
+                Ok((_, id)) if id != contract_id => {
+                    discrepancies.push(Discrepancy::BundleContract(bundle_id, id, contract_id))
+                }
+                Ok((mut witness_ids, _)) => {
+                    if !witness_ids.any(|id| id == *witness_id) {
+                        discrepancies
+                            .push(Discrepancy::BundleWitnessAbsent(bundle_id, *witness_id));
+                    }
+                }
+            }
+            for opid in bundle.known_transitions.keys() {
+                match self.index.bundle_id_for_op(*opid) {
+                    Err(IndexError::Inconsistency(_)) => {
+                        discrepancies.push(Discrepancy::OperationAbsent(*opid, bundle_id))
+                    }
+                    Err(err) => return Err(err.into()),
+                    Ok(id) if id != bundle_id => {
+                        discrepancies.push(Discrepancy::OperationBundle(*opid, id, bundle_id))
+                    }
+                    Ok(_) => {}
+                }
+            }
+        }
+
+        Ok(discrepancies
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect())
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct UpdateRes {
+    pub succeeded: usize,
+    pub failed: HashMap<XWitnessId, String>,
+}
+
+/// Discrepancy between the contract state or indexes and the contract
+/// operations kept in the stash, detected by [`Stock::reindex`].
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(doc_comments)]
+pub enum Discrepancy {
+    /// state of contract {0} is absent.
+    StateAbsent(ContractId),
+
+    /// state of contract {0} misses assignment {1}.
+    AssignmentAbsent(ContractId, Opout),
+
+    /// state of contract {0} contains assignment {1} which is not produced by
+    /// any of the known operations.
+    AssignmentExcess(ContractId, Opout),
+
+    /// bundle {0} is not indexed.
+    BundleAbsent(BundleId),
+
+    /// bundle {0} is indexed as a part of contract {1} instead of {2}.
+    BundleContract(BundleId, ContractId, ContractId),
+
+    /// witness {1} of bundle {0} is not indexed.
+    BundleWitnessAbsent(BundleId, XWitnessId),
+
+    /// operation {0} from bundle {1} is not indexed.
+    OperationAbsent(OpId, BundleId),
+
+    /// operation {0} is indexed as a part of bundle {1} instead of {2}.
+    OperationBundle(OpId, BundleId, BundleId),
+}
+
+/// Report produced by [`Stock::reindex`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ReindexReport {
+    /// Contracts which were reindexed.
+    pub contracts: BTreeSet<ContractId>,
+    /// Discrepancies found before the reindexing.
+    pub found: Vec<Discrepancy>,
+    /// Discrepancies which were not resolved by the reindexing.
+    pub unresolved: Vec<Discrepancy>,
+}
+
+impl ReindexReport {
+    /// Detects whether the state and indexes were consistent with the stash
+    /// before the reindexing.
+    pub fn is_consistent(&self) -> bool { self.found.is_empty() }
+
+    /// Detects whether the state and indexes are consistent with the stash
+    /// after the reindexing.
+    pub fn is_repaired(&self) -> bool { self.unresolved.is_empty() }
+}
+
+/// Options of accepting transfers with [`Stock::accept_transfer_with`].
+///
+/// The default options accept transfer exactly like
+/// [`Stock::accept_transfer`].
+#[derive(Default)]
+pub struct AcceptOptions<'a> {
+    provenance: Option<Provenance>,
+    min_confirmations: u32,
+    tip_height: u32,
+    provisional: bool,
+    analyzer: Option<Box<dyn Fn(XWitnessId) -> WitnessAnalysis + 'a>>,
+    conflicts: bool,
+    progress: Option<&'a dyn ProgressSink>,
+    replacements: bool,
+}
+
+impl<'a> AcceptOptions<'a> {
+    pub fn new() -> Self { Self::default() }
+
+    /// Records the provenance of the transfer, its contract and schema unless
+    /// they are already known.
+    ///
+    /// Since only the first-seen source is kept, the provenance of a contract
+    /// points to the peer which has introduced it to the wallet, even if its
+    /// history was later extended by transfers from other peers.
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    /// Requires the terminal witnesses to have at least `min_confirmations`
+    /// under the chain tip at `tip_height`.
+    ///
+    /// If `provisional` is set, transfers with insufficient number of
+    /// confirmations are accepted provisionally: the consignment is stored,
+    /// but the state assigned by the unconfirmed terminal witnesses is
+    /// excluded from the contract state. The witnesses are persisted in the
+    /// stash together with the required number of confirmations, and remain
+    /// excluded by all later witness updates. The state gets finalized by
+    /// [`Stock::update_confirmations`] for a newer chain tip once the
+    /// witnesses are buried deep enough; [`AcceptReport::pending`] lists the
+    /// terminal witnesses which are pending confirmations.
+    pub fn with_confirmations(
+        mut self,
+        min_confirmations: u32,
+        tip_height: u32,
+        provisional: bool,
+    ) -> Self {
+        self.min_confirmations = min_confirmations;
+        self.tip_height = tip_height;
+        self.provisional = provisional;
+        self
+    }
+
+    /// Analyzes fees and unconfirmed ancestors of the terminal witnesses
+    /// using mempool information from the broadcaster.
+    ///
+    /// The analysis allows receivers to judge the likelihood of the
+    /// unconfirmed witnesses being mined before crediting the deposit.
+    /// Failures to fetch the mempool information don't prevent the transfer
+    /// from being accepted and are reported in the analysis.
+    pub fn with_analysis(mut self, broadcaster: &'a impl Broadcaster) -> Self {
+        self.analyzer =
+            Some(Box::new(|witness_id| WitnessAnalysis::analyze(broadcaster, witness_id)));
+        self
+    }
+
+    /// Reports conflicts between the state transitions of the transfer and
+    /// the ones already known to the stock.
+    ///
+    /// Conflicting transitions appear when the transfer spends allocations
+    /// which were already spent by another device using the same wallet, or
+    /// by a transition from an alternative branch of the contract history.
+    /// All branches are kept in the stash, while the contract state contains
+    /// allocations of a single one of them: the mined branch, if any, or the
+    /// one known to the stock before the transfer. The choice is persisted,
+    /// and another branch can be chosen with [`Stock::resolve_conflict`] or
+    /// [`Stock::resolve_conflicts`].
+    pub fn with_conflict_check(mut self) -> Self {
+        self.conflicts = true;
+        self
+    }
+
+    /// Reports progress of the bundle processing to the sink.
+    ///
+    /// The witnesses of all the transfer bundles are resolved before any data
+    /// are written to the stock, thus cancellation reported by the sink leaves
+    /// the stock unmodified.
+    pub fn with_progress(mut self, sink: &'a dyn ProgressSink) -> Self {
+        self.progress = Some(sink);
+        self
+    }
+
+    /// Reports bundles which were already known to the stock under different
+    /// witnesses.
+    ///
+    /// All the witnesses of a bundle are kept in the stock, and the one which
+    /// gets mined defines the contract state. A replacement which is not an
+    /// RBF may result in the bundle being anchored twice and requires
+    /// attention from the wallet.
+    pub fn with_replacements(mut self) -> Self {
+        self.replacements = true;
+        self
+    }
+}
+
+/// Report produced by [`Stock::accept_transfer_with`].
+#[derive(Clone, Debug)]
+pub struct AcceptReport {
+    /// Validation status of the transfer.
+    pub status: validation::Status,
+    /// Terminal witnesses of a provisionally accepted transfer which are
+    /// pending confirmations.
+    pub pending: BTreeSet<XWitnessId>,
+    /// Fee and ancestry analysis of the terminal witnesses, if requested.
+    pub analysis: Vec<WitnessAnalysis>,
+    /// Conflicts involving the state transitions of the transfer, if
+    /// requested.
+    pub conflicts: ConflictReport,
+    /// Bundles of the transfer known under different witnesses, if requested.
+    pub replacements: Vec<WitnessReplacement>,
+}
+
+/// Report produced by [`Stock::resume_pending`].
+#[derive(Clone, Debug, Default)]
+pub struct ResumeReport {
+    /// Consignments which were applied, with the validation status.
+    pub applied: BTreeMap<ConsignmentId, validation::Status>,
+    /// Consignments which had failed the validation and remain pending.
+    pub rejected: BTreeMap<ConsignmentId, validation::Status>,
+    /// Consignments which can't be applied and remain pending, with the
+    /// failure description.
+    pub failed: BTreeMap<ConsignmentId, String>,
+}
+
+impl ResumeReport {
+    /// Detects whether all the pending consignments were applied.
+    pub fn is_complete(&self) -> bool { self.rejected.is_empty() && self.failed.is_empty() }
+}
+
+/// Outpoint which spending affects RGB state known to the [`Stock`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct WatchItem {
+    pub outpoint: XOutpoint,
+    /// Script pubkey of the output, if the transaction containing the output
+    /// is known.
+    pub script_pubkey: Option<ScriptPubkey>,
+    /// Contracts which assign state to the outpoint.
+    pub contracts: BTreeSet<ContractId>,
+}
+
+impl WatchItem {
+    /// Computes Electrum protocol script hash (reversed SHA256 hash of the
+    /// script pubkey in hex form) used by `blockchain.scripthash.subscribe`
+    /// and Esplora `/scripthash` API.
+    pub fn script_hash(&self) -> Option<String> {
+        let script_pubkey = self.script_pubkey.as_ref()?;
+        let mut hasher = Sha256::default();
+        hasher.input_raw(script_pubkey.as_slice());
+        let mut hash = hasher.finish();
+        hash.reverse();
+        Some(hash.to_hex())
+    }
+}
+
+/// Merges witness bundles sharing the same witness.
+#[allow(clippy::result_large_err)]
+fn merge_witness_bundles(
+    witness_bundles: impl IntoIterator<Item = WitnessBundle>,
+) -> Result<LargeOrdSet<WitnessBundle>, ConsignError> {
+    let mut bundles = BTreeMap::<XWitnessId, WitnessBundle>::new();
+    for witness_bundle in witness_bundles {
+        let witness_id = witness_bundle.witness_id();
+        match bundles.get_mut(&witness_id) {
+            Some(prev) => {
+                *prev = prev.clone().merge_reveal(witness_bundle)?;
+            }
+            None => {
+                bundles.insert(witness_id, witness_bundle);
+            }
+        }
+    }
+    Confined::try_from_iter(bundles.into_values()).map_err(|_| ConsignError::TooManyBundles)
+}
+
+fn encoded_size(data: &impl StrictEncode) -> usize {
+    let writer = StrictWriter::counter::<{ usize::MAX }>();
+    data.strict_encode(writer)
+        .expect("counting writer")
+        .unbox()
+        .unconfine()
+        .count
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::str::FromStr;
+
+    use amplify::confinement::{NonEmptyBlob, SmallString, U8};
+    use amplify::hex::FromHex;
+    use baid64::FromBaid64Str;
+    use bp::seals::txout::ExplicitSeal;
+    use bp::{Outpoint, Txid};
+    use commit_verify::{mpc, CommitId, Conceal, DigestExt, Sha256, TryCommitVerify};
+    use rgb::{
+        FungibleType, GenesisSchema, Occurrences, OwnedStateSchema, TransitionSchema,
+        TransitionType,
+    };
+    use strict_encoding::{StrictDumb, StrictSerialize, TypeName};
+
+    use super::*;
+    use crate::containers::{
+        ContentSigs, ContractDependencies, DumbValidator, SigBlob, TrustLevel,
+    };
+    use crate::interface::resolver::DumbResolver;
+    use crate::interface::{
+        AssignIface, GenesisIface, IfaceImpl, NamedField, OwnedIface, Req,
+        TransitionIface, VerNo,
+    };
+    use crate::persistence::IssuerVerdict;
+
+    pub const FUNGIBLE_IFACE: &str = "TestFungible";
+
+    /// Kit with a minimal schema of a fungible asset, its interface and the
+    /// interface implementation.
+    pub fn fungible_kit() -> ValidKit {
+        let owner = AssignmentType::with(4000);
+        let transfer = TransitionType::with(10000);
+        let transition = TransitionSchema {
+            metadata: none!(),
+            globals: none!(),
+            inputs: tiny_bmap! { owner => Occurrences::OnceOrMore },
+            assignments: tiny_bmap! { owner => Occurrences::OnceOrMore },
+            valencies: none!(),
+            validator: None,
+        };
+        let schema = Schema {
+            ffv: none!(),
+            flags: none!(),
+            name: tn!(FUNGIBLE_IFACE),
+            timestamp: 1_700_000_000,
+            developer: none!(),
+            meta_types: none!(),
+            global_types: none!(),
+            owned_types: tiny_bmap! {
+                owner => OwnedStateSchema::Fungible(FungibleType::Unsigned64Bit),
+            },
+            valency_types: none!(),
+            genesis: GenesisSchema {
+                metadata: none!(),
+                globals: none!(),
+                assignments: tiny_bmap! { owner => Occurrences::NoneOrMore },
+                valencies: none!(),
+                validator: None,
+            },
+            extensions: none!(),
+            transitions: tiny_bmap! { transfer => transition },
+            reserved: none!(),
+        };
+        let iface = Iface {
+            version: VerNo::V1,
+            name: tn!(FUNGIBLE_IFACE),
+            inherits: none!(),
+            timestamp: 1_700_000_000,
+            metadata: none!(),
+            global_state: none!(),
+            assignments: tiny_bmap! {
+                fname!("assetOwner") => AssignIface::public(OwnedIface::Amount, Req::NoneOrMore),
+            },
+            valencies: none!(),
+            genesis: GenesisIface {
+                modifier: none!(),
+                metadata: none!(),
+                globals: none!(),
+                assignments: tiny_bmap! { fname!("assetOwner") => Occurrences::NoneOrMore },
+                valencies: none!(),
+                errors: none!(),
+            },
+            transitions: tiny_bmap! {
+                fname!("transfer") => TransitionIface {
+                    modifier: none!(),
+                    optional: false,
+                    metadata: none!(),
+                    globals: none!(),
+                    inputs: tiny_bmap! { fname!("assetOwner") => Occurrences::OnceOrMore },
+                    assignments: tiny_bmap! { fname!("assetOwner") => Occurrences::OnceOrMore },
+                    valencies: none!(),
+                    errors: none!(),
+                    default_assignment: Some(fname!("assetOwner")),
+                },
+            },
+            extensions: none!(),
+            default_operation: Some(fname!("transfer")),
+            errors: none!(),
+            developer: none!(),
+        };
+        let iimpl = IfaceImpl {
+            version: VerNo::V1,
+            schema_id: schema.schema_id(),
+            iface_id: iface.iface_id(),
+            timestamp: 1_700_000_000,
+            metadata: none!(),
+            global_state: none!(),
+            assignments: tiny_bset! { NamedField::with(owner, fname!("assetOwner")) },
+            valencies: none!(),
+            transitions: tiny_bset! { NamedField::with(transfer, fname!("transfer")) },
+            extensions: none!(),
+            errors: none!(),
+            developer: none!(),
+        };
+        let kit = Kit {
+            version: ContainerVer::V2,
+            ifaces: tiny_bset! { iface },
+            schemata: tiny_bset! { schema },
+            iimpls: tiny_bset! { iimpl },
+            supplements: none!(),
+            types: none!(),
+            scripts: none!(),
+            signatures: none!(),
+        };
+        kit.validate().unwrap()
+    }
+
+    /// Single-use seal output with opret close method.
+    pub fn opret_output(outpoint: Outpoint) -> XOutputSeal {
+        XChain::Bitcoin(ExplicitSeal::new(CloseMethod::OpretFirst, outpoint))
+    }
+
+    /// Resolver reporting all witnesses as unconfirmed mempool transactions.
+    pub struct TentativeResolver;
+    impl ResolveWitness for TentativeResolver {
+        fn resolve_pub_witness(
+            &self,
+            witness_id: XWitnessId,
+        ) -> Result<rgb::vm::XWitnessTx, WitnessResolverError> {
+            Err(WitnessResolverError::Unknown(witness_id))
+        }
+        fn resolve_pub_witness_ord(
+            &self,
+            _: XWitnessId,
+        ) -> Result<WitnessOrd, WitnessResolverError> {
+            Ok(WitnessOrd::Tentative)
+        }
+    }
+
+    /// Constructs fascia for the main state transition of the batch, which is
+    /// witnessed by the transaction spending the transition inputs in the
+    /// order of their outpoints. The fascia anchor commits to the bundle, but
+    /// the transaction doesn't contain the commitment.
+    pub fn fascia_for(contract_id: ContractId, batch: &Batch, tx: bp::Tx) -> Fascia {
+        let info = &batch.main.first;
+        let input_map = info
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(no, _)| (rgb::Vin::from_u32(no as u32), info.id))
+            .collect::<BTreeMap<_, _>>();
+        let bundle = TransitionBundle {
+            close_method: info.method,
+            input_map: rgb::InputMap::from(Confined::from_checked(input_map)),
+            known_transitions: Confined::from_checked(bmap! { info.id => info.transition.clone() }),
+        };
+        let source = mpc::MultiSource {
+            min_depth: mpc::MPC_MINIMAL_DEPTH,
+            messages: Confined::from_checked(bmap! {
+                mpc::ProtocolId::from(contract_id) => mpc::Message::from(bundle.bundle_id())
+            }),
+            static_entropy: Some(1),
+        };
+        let block = mpc::MerkleBlock::from(mpc::MerkleTree::try_commit(&source).unwrap());
+        Fascia {
+            witness: XChain::Bitcoin(crate::containers::PubWitness::Tx(tx)),
+            anchor: AnchorSet::Opret(bp::dbc::Anchor::new(block, default!())),
+            bundles: amplify::confinement::NonEmptyOrdMap::with_key_value(
+                contract_id,
+                crate::containers::Dichotomy::with(bundle, None),
+            ),
+        }
+    }
+
+    /// Constructs fascia like [`fascia_for`], adding the commitment to the
+    /// witness transaction as its last output, such that consignments made
+    /// from the fascia pass validation.
+    pub fn committed_fascia_for(contract_id: ContractId, batch: &Batch, mut tx: bp::Tx) -> Fascia {
+        let fascia = fascia_for(contract_id, batch, tx.clone());
+        let AnchorSet::Opret(anchor) = &fascia.anchor else {
+            unreachable!("fascia is anchored with opret");
+        };
+        let script = bp::ScriptPubkey::op_return(anchor.mpc_proof.commit_id().as_slice());
+        tx.outputs.push(bp::TxOut::new(script, 0u64)).unwrap();
+        Fascia {
+            witness: XChain::Bitcoin(crate::containers::PubWitness::Tx(tx)),
+            ..fascia
+        }
+    }
+
+    /// Issues a contract with [`fungible_kit`] schema, allocating the amounts
+    /// to the outpoints using seals with opret close method.
+    pub fn issue_fungible(stock: &mut Stock, allocations: &[(Outpoint, u64)]) -> ContractId {
+        let contract = fungible_contract(stock, allocations);
+        let contract_id = contract.contract_id();
+        stock.import_contract(contract, DumbResolver).unwrap();
+        contract_id
+    }
+
+    /// Issues a contract like [`issue_fungible`] without importing it into the
+    /// stock.
+    pub fn fungible_contract(stock: &mut Stock, allocations: &[(Outpoint, u64)]) -> ValidContract {
+        let kit = fungible_kit();
+        let schema_id = kit.schemata.first().unwrap().schema_id();
+        stock.import_kit(kit).unwrap();
+        let mut builder = stock
+            .contract_builder(Identity::default(), schema_id, tn!(FUNGIBLE_IFACE))
+            .unwrap();
+        for (no, (outpoint, amount)) in allocations.iter().enumerate() {
+            let seal = GenesisSeal::with_blinding(
+                CloseMethod::OpretFirst,
+                outpoint.txid,
+                outpoint.vout,
+                no as u64,
+            );
+            builder = builder
+                .add_fungible_state("assetOwner", XChain::Bitcoin(seal), *amount)
+                .unwrap();
+        }
+        builder.issue_contract().unwrap()
+    }
+
+    #[test]
+    fn test_consign() {
+        let mut stock = Stock::in_memory();
+        let seal = XChain::with(
+            rgbcore::Layer1::Bitcoin,
+            GraphSeal::new_random_vout(bp::dbc::Method::OpretFirst, Vout::from_u32(0)),
+        );
+        let secret_seal = seal.conceal();
+
+        stock.store_secret_seal(seal).unwrap();
+        let contract_id =
+            ContractId::from_baid64_str("rgb:qFuT6DN8-9AuO95M-7R8R8Mc-AZvs7zG-obum1Va-BRnweKk")
+                .unwrap();
+        if let Ok(transfer) = stock.consign::<true>(contract_id, [], Some(secret_seal)) {
+            println!("{:?}", transfer.supplements)
+        }
+    }
+
+    #[test]
+    fn test_watch_list() {
+        let stock = Stock::in_memory();
+        assert!(
+            stock
+                .watch_list(FilterIncludeAll, DumbResolver)
+                .unwrap()
+                .is_empty()
+        );
+
+        let item = WatchItem {
+            outpoint: XChain::Bitcoin(bp::Outpoint::coinbase()).into(),
+            script_pubkey: Some(ScriptPubkey::from_unsafe(
+                Vec::<u8>::from_hex("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap(),
+            )),
+            contracts: none!(),
+        };
+        assert_eq!(
+            item.script_hash().unwrap(),
+            "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161"
+        );
+    }
+
+    #[test]
+    fn test_reindex() {
+        let mut stock = Stock::in_memory();
+        let report = stock.reindex(None, DumbResolver).unwrap();
+        assert!(report.contracts.is_empty());
+        assert!(report.is_consistent() && report.is_repaired());
+
+        let contract_id =
+            ContractId::from_baid64_str("rgb:qFuT6DN8-9AuO95M-7R8R8Mc-AZvs7zG-obum1Va-BRnweKk")
+                .unwrap();
+        assert!(stock.reindex(Some(contract_id), DumbResolver).is_err());
+    }
+
+    #[test]
+    fn test_reindex_repair() {
+        use crate::persistence::{ContractStateWrite, IndexWriteProvider, StateWriteProvider};
+
+        let mut stock = Stock::in_memory();
+        let funding = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let contract_id = issue_fungible(&mut stock, &[(funding, 1000)]);
+        let mut device = stock.clone_no_persistence();
+        let compose = |stock: &mut Stock, amount: u64| {
+            let secret = XChain::Bitcoin(GraphSeal::new_random_vout(
+                CloseMethod::OpretFirst,
+                Vout::from_u32(0),
+            ))
+            .as_reduced_unsafe()
+            .conceal();
+            let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+            let invoice = RgbInvoiceBuilder::with(contract_id, beneficiary)
+                .set_interface(FUNGIBLE_IFACE)
+                .set_amount_raw(amount)
+                .finish();
+            stock
+                .compose(
+                    &invoice,
+                    [opret_output(funding)],
+                    CloseMethod::OpretFirst,
+                    None::<Vout>,
+                    |_, _, _| Some(Vout::from_u32(1)),
+                )
+                .unwrap()
+        };
+        let batch = compose(&mut stock, 400);
+        let tx = crate::broadcast::test::tx(&[funding], &[1000, 1000]);
+        let witness_id = XChain::Bitcoin(tx.txid());
+        let change = opret_output(Outpoint::new(tx.txid(), 1));
+        stock
+            .consume_fascia(fascia_for(contract_id, &batch, tx), TentativeResolver)
+            .unwrap();
+        let opid = batch.main.first.id;
+        let bundle_id = stock.index.bundle_id_for_op(opid).unwrap();
+        let foreign_change = opret_output(Outpoint::new(Txid::from([2u8; 32]), 1));
+        let assignments = |stock: &Stock| {
+            stock
+                .contract_assignments_for(contract_id, [change, foreign_change])
+                .unwrap()
+        };
+        let expected = assignments(&stock);
+        assert!(
+            stock
+                .reindex(None, TentativeResolver)
+                .unwrap()
+                .is_consistent()
+        );
+
+        // State contains assignments of a transition unknown to the stash, and
+        // index entries point to other contract and bundle
+        let foreign = compose(&mut device, 300).main.first.transition;
+        let foreign_witness = XChain::Bitcoin(Txid::from([2u8; 32]));
+        stock
+            .as_state_provider_mut()
+            .update_contract(contract_id)
+            .unwrap()
+            .unwrap()
+            .add_transition(&foreign, foreign_witness, WitnessOrd::Tentative)
+            .unwrap();
+        let other_contract = ContractId::from([0xA5u8; 32]);
+        let other_bundle = BundleId::from([0xA5u8; 32]);
+        let mut index = MemIndex::in_memory();
+        index
+            .register_bundle(bundle_id, witness_id, other_contract)
+            .unwrap();
+        index.register_operation(opid, other_bundle).unwrap();
+        let mut stock = Stock::with(
+            stock.as_stash_provider().clone_no_persistence(),
+            stock.as_state_provider().clone_no_persistence(),
+            index,
+        );
+        assert_ne!(assignments(&stock), expected);
+
+        let report = stock.reindex(None, TentativeResolver).unwrap();
+        assert!(report.found.iter().any(|discrepancy| matches!(
+            discrepancy,
+            Discrepancy::AssignmentExcess(id, opout) if *id == contract_id && opout.op == foreign.id()
+        )));
+        for discrepancy in [
+            Discrepancy::BundleContract(bundle_id, other_contract, contract_id),
+            Discrepancy::OperationBundle(opid, other_bundle, bundle_id),
+        ] {
+            assert!(report.found.contains(&discrepancy), "{discrepancy} is not detected");
+        }
+        assert!(report.is_repaired());
+        assert_eq!(assignments(&stock), expected);
+        assert_eq!(stock.index.bundle_id_for_op(opid).unwrap(), bundle_id);
+        let spent = batch
+            .main
+            .first
+            .transition
+            .inputs
+            .iter()
+            .next()
+            .unwrap()
+            .prev_out;
+        assert_eq!(stock.index.spenders(spent).unwrap(), bset![opid]);
+        assert!(
+            stock
+                .reindex(None, TentativeResolver)
+                .unwrap()
+                .is_consistent()
+        );
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let stock = Stock::in_memory();
+        assert!(stock.dependency_graph().unwrap().is_empty());
+
+        let contract_id =
+            ContractId::from_baid64_str("rgb:qFuT6DN8-9AuO95M-7R8R8Mc-AZvs7zG-obum1Va-BRnweKk")
+                .unwrap();
+        assert!(stock.contract_dependencies(contract_id).is_err());
+        assert!(stock.contract_dependents(contract_id).unwrap().is_empty());
+        assert!(
+            stock
+                .check_dependencies(contract_id, FilterIncludeAll)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_allocation_proofs() {
+        let mut stock = Stock::in_memory();
+        let first = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let second = Outpoint::new(Txid::from([2u8; 32]), 1);
+        let third = Outpoint::new(Txid::from([3u8; 32]), 2);
+        let contract_id = issue_fungible(&mut stock, &[(first, 100), (second, 200), (third, 300)]);
+
+        let root = stock
+            .state_root(contract_id, FilterIncludeAll)
+            .unwrap()
+            .unwrap();
+        let state = stock.contract_state(contract_id).unwrap();
+        let allocations = state
+            .fungible_all()
+            .map(|a| (a.opout, a.state.value.as_u64()))
+            .collect::<Vec<_>>();
+        assert_eq!(allocations.len(), 3);
+        for (opout, amount) in allocations {
+            let mut proof = stock
+                .prove_allocation(contract_id, opout, FilterIncludeAll)
+                .unwrap()
+                .unwrap();
+            assert_eq!(proof.leaf.state, AllocatedState::Amount(amount.into()));
+            assert!(proof.verify(root));
+            proof.contract_id = ContractId::from([0xA5u8; 32]);
+            assert!(!proof.verify(root));
+        }
+
+        let opout = Opout::new(OpId::from([0xA5u8; 32]), AssignmentType::with(4000), 0);
+        assert_eq!(
+            stock
+                .prove_allocation(contract_id, opout, FilterIncludeAll)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_dependencies_import() {
+        let mut stock = Stock::in_memory();
+        let lock = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let backing_id = issue_fungible(&mut stock, &[(lock, 100)]);
+        let opout = stock
+            .contract_state(backing_id)
+            .unwrap()
+            .fungible_all()
+            .next()
+            .unwrap()
+            .opout;
+
+        let dependent = |stock: &mut Stock, dependency: ContractDependency, amount: u64| {
+            let funding = Outpoint::new(Txid::from([2u8; 32]), 0);
+            let mut contract = fungible_contract(stock, &[(funding, amount)]).into_consignment();
+            let mut suppl = Supplement::new(contract.contract_id(), Identity::default());
+            let deps = ContractDependencies::from(tiny_bset![dependency]);
+            suppl.declare_dependencies(&deps).unwrap();
+            contract.supplements.push(suppl).unwrap();
+            contract.validate(&DumbResolver, true).unwrap()
+        };
+        let dependency = ContractDependency {
+            contract_id: backing_id,
+            opout,
+            lock: XChain::Bitcoin(lock).into(),
+        };
+
+        let contract = dependent(&mut stock, dependency.clone(), 100);
+        let contract_id = contract.contract_id();
+        stock.import_contract(contract, DumbResolver).unwrap();
+        assert_eq!(stock.contract_dependencies(contract_id).unwrap(), bset![dependency.clone()]);
+        assert_eq!(stock.contract_dependents(backing_id).unwrap(), bset![contract_id]);
+        stock
+            .check_dependencies(contract_id, FilterIncludeAll)
+            .unwrap();
+
+        let wrong_lock = ContractDependency {
+            lock: XChain::Bitcoin(Outpoint::new(Txid::from([3u8; 32]), 0)).into(),
+            ..dependency.clone()
+        };
+        let contract = dependent(&mut stock, wrong_lock, 200);
+        let rejected_id = contract.contract_id();
+        assert!(matches!(
+            stock.import_contract(contract, DumbResolver),
+            Err(StockError::Dependency(DependencyError::NotLocked(id, _))) if id == rejected_id
+        ));
+
+        let unknown = ContractDependency {
+            contract_id: ContractId::from([0xA5u8; 32]),
+            ..dependency
+        };
+        let contract = dependent(&mut stock, unknown, 300);
+        assert!(matches!(
+            stock.import_contract(contract, DumbResolver),
+            Err(StockError::Dependency(DependencyError::UnknownContract(_, _)))
+        ));
+        assert_eq!(stock.contracts().unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_labels() {
+        let mut stock = Stock::in_memory();
         let contract_id =
             ContractId::from_baid64_str("rgb:qFuT6DN8-9AuO95M-7R8R8Mc-AZvs7zG-obum1Va-BRnweKk")
                 .unwrap();
-        if let Ok(transfer) = stock.consign::<true>(contract_id, [], Some(secret_seal)) {
-            println!("{:?}", transfer.supplements)
+        assert_eq!(stock.label(contract_id).unwrap(), None);
+        assert!(stock.set_label(contract_id, "invoice #1042").unwrap());
+        assert!(
+            !stock
+                .set_label(contract_id, "invoice #1042 from Alice")
+                .unwrap()
+        );
+        assert_eq!(stock.label(contract_id).unwrap(), Some("invoice #1042 from Alice"));
+        assert_eq!(stock.labels().unwrap().count(), 1);
+        assert!(stock.remove_label(contract_id).unwrap());
+        assert!(!stock.remove_label(contract_id).unwrap());
+        assert_eq!(stock.labels().unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_membership_filters() {
+        let mut stock = Stock::in_memory();
+        assert!(stock.membership_filter().is_none());
+        assert!(!stock.is_known_operation(OpId::strict_dumb()));
+
+        stock.enable_membership_filters(100).unwrap();
+        assert!(stock.membership_filter().unwrap().operations().is_empty());
+        assert!(!stock.is_known_operation(OpId::strict_dumb()));
+        assert!(!stock.is_known_bundle(BundleId::strict_dumb()));
+
+        let bundle = TransitionBundle::strict_dumb();
+        stock.stash.consume_bundle(bundle.clone()).unwrap();
+        assert!(stock.is_known_bundle(bundle.bundle_id()));
+        assert_eq!(stock.membership_filter().unwrap().bundles().len(), 1);
+
+        stock.disable_membership_filters();
+        assert!(stock.is_known_bundle(bundle.bundle_id()));
+    }
+
+    #[test]
+    fn test_provenance() {
+        let mut stock = Stock::in_memory();
+        let contract_id =
+            ContractId::from_baid64_str("rgb:qFuT6DN8-9AuO95M-7R8R8Mc-AZvs7zG-obum1Va-BRnweKk")
+                .unwrap();
+        assert_eq!(stock.provenance(contract_id).unwrap(), None);
+
+        let first = Provenance::new(
+            SmallString::from_checked(s!("rgbhttpjsonrpc:proxy.rgbtools.org")),
+            None,
+        );
+        let second = Provenance::new(
+            SmallString::from_checked(s!("storm:alice")),
+            Some(Identity::from("ssi:alice")),
+        );
+        assert!(
+            stock
+                .stash
+                .store_provenance([ProvenanceRef::Contract(contract_id)], &first)
+                .unwrap()
+        );
+        assert!(
+            !stock
+                .stash
+                .store_provenance([ProvenanceRef::Contract(contract_id)], &second)
+                .unwrap()
+        );
+        assert_eq!(stock.provenance(contract_id).unwrap(), Some(&first));
+        assert_eq!(stock.provenances().unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_import_contract_verified() {
+        let mut stock = Stock::in_memory();
+        let contract = Contract::strict_dumb();
+        let contract_id = contract.contract_id();
+        let policy = TrustPolicy::with(DumbValidator);
+        let report = stock
+            .import_contract_verified(contract, DumbResolver, true, &policy)
+            .unwrap();
+        assert_eq!(report.contract_id, contract_id);
+        assert_eq!(report.verdict, IssuerVerdict::Unsigned);
+        assert!(!report.is_valid());
+        assert!(!report.imported);
+        assert!(stock.contracts().unwrap().next().is_none());
+
+        struct IssuerValidator;
+        impl SigValidator for IssuerValidator {
+            fn validate_sig(&self, _: &Identity, content_id: ContentId, sig: SigBlob) -> bool {
+                sig.as_slice() == content_id.to_strict_serialized::<U8>().unwrap().as_slice()
+            }
+        }
+
+        let funding = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let mut contract = fungible_contract(&mut stock, &[(funding, 100)]).into_consignment();
+        let contract_id = contract.contract_id();
+        let issuer = contract.genesis.issuer.clone();
+        let mut policy = TrustPolicy::with(IssuerValidator);
+        policy
+            .identities
+            .insert(issuer.clone(), TrustLevel::Trusted);
+        policy.min_trust = TrustLevel::Trusted;
+        policy.require_signature = true;
+
+        let report = stock
+            .import_contract_verified(contract.clone(), DumbResolver, true, &policy)
+            .unwrap();
+        assert_eq!(report.verdict, IssuerVerdict::Unsigned);
+        assert!(report.is_valid());
+        assert!(!report.imported);
+
+        let content_id = ContentId::Genesis(contract_id);
+        let sig = content_id.to_strict_serialized::<U8>().unwrap();
+        let sig = SigBlob::from(NonEmptyBlob::from_slice_checked(sig.as_slice()));
+        let sigs = ContentSigs::from(Confined::with((issuer, sig)));
+        contract.signatures.insert(content_id, sigs).unwrap();
+        let report = stock
+            .import_contract_verified(contract, DumbResolver, true, &policy)
+            .unwrap();
+        assert_eq!(report.verdict, IssuerVerdict::Verified);
+        assert!(report.signatures.iter().all(|check| check.valid));
+        assert!(report.is_valid());
+        assert!(report.imported);
+        assert_eq!(
+            stock
+                .contracts()
+                .unwrap()
+                .map(|info| info.id)
+                .collect::<Vec<_>>(),
+            vec![contract_id]
+        );
+    }
+
+    #[test]
+    fn test_attestations() {
+        let mut stock = Stock::in_memory();
+        let genesis = Genesis::strict_dumb();
+        let contract_id = genesis.contract_id();
+        let scam = Attestation::new(crate::containers::Verdict::Scam);
+        let suppl = scam.to_supplement(contract_id, "ssi:curator").unwrap();
+        assert!(stock.import_attestation(suppl.clone()).is_err());
+
+        stock
+            .stash
+            .as_provider_mut()
+            .replace_genesis(genesis)
+            .unwrap();
+        let issuer_suppl = Supplement::new(contract_id, "ssi:issuer");
+        assert!(stock.import_attestation(issuer_suppl).is_err());
+        stock.import_attestation(suppl.clone()).unwrap();
+        assert_eq!(stock.export_attestations(contract_id).unwrap(), vec![suppl]);
+        assert_eq!(stock.attestations(contract_id).unwrap(), vec![(
+            Identity::from("ssi:curator"),
+            scam
+        )]);
+        assert_eq!(stock.attestation_warnings(contract_id, &bset![]).unwrap(), vec![]);
+        let curators = bset![Identity::from("ssi:curator")];
+        assert_eq!(
+            stock
+                .attestation_warnings(contract_id, &curators)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(
+            stock
+                .stash
+                .supplement(ContentRef::Genesis(contract_id))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_compose_batch() {
+        use invoice::{RgbInvoiceBuilder, XChainNet};
+
+        let stock = Stock::in_memory();
+        let contract_id =
+            ContractId::from_baid64_str("rgb:qFuT6DN8-9AuO95M-7R8R8Mc-AZvs7zG-obum1Va-BRnweKk")
+                .unwrap();
+        let secret = XChain::Bitcoin(GraphSeal::strict_dumb())
+            .as_reduced_unsafe()
+            .conceal();
+        let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+        let invoice = RgbInvoiceBuilder::with(contract_id, beneficiary).finish();
+        let mut other = invoice.clone();
+        other.contract = Some(ContractId::from([1u8; 32]));
+
+        let compose = |payments: &[(&RgbInvoice, Option<Vout>)]| {
+            stock.compose_batch(
+                payments.iter().copied(),
+                [XOutputSeal::strict_dumb()],
+                CloseMethod::OpretFirst,
+                |_, _, _| None,
+            )
+        };
+        assert!(matches!(compose(&[]), Err(StockError::InvalidInput(ComposeError::NoInvoices))));
+        assert!(matches!(
+            compose(&[(&invoice, None), (&other, None)]),
+            Err(StockError::InvalidInput(ComposeError::InvoiceMismatch))
+        ));
+        assert!(matches!(
+            compose(&[(&invoice, None), (&invoice, None)]),
+            Err(StockError::InvalidInput(ComposeError::NoIface))
+        ));
+        other.contract = None;
+        assert!(matches!(
+            stock.transfers(XWitnessId::strict_dumb(), [(&other, None)]),
+            Err(StockError::InvalidInput(ConsignError::NoContract))
+        ));
+    }
+
+    #[test]
+    fn test_compose_batch_deterministic() {
+        let mut stock = Stock::in_memory();
+        let funding = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let contract_id = issue_fungible(&mut stock, &[(funding, 1000)]);
+
+        let invoice = |blinding: u64, amount: u64| {
+            let secret = XChain::Bitcoin(GraphSeal::with_blinded_vout(
+                CloseMethod::OpretFirst,
+                Vout::from_u32(0),
+                blinding,
+            ))
+            .as_reduced_unsafe()
+            .conceal();
+            let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+            RgbInvoiceBuilder::with(contract_id, beneficiary)
+                .set_interface(FUNGIBLE_IFACE)
+                .set_amount_raw(amount)
+                .finish()
+        };
+        let first = invoice(1, 300);
+        let second = invoice(2, 200);
+        let compose = || {
+            stock
+                .compose_batch_deterministic(
+                    [(&first, None), (&second, None)],
+                    [opret_output(funding)],
+                    CloseMethod::OpretFirst,
+                    u64::MAX,
+                    |_, _, _| Some(Vout::from_u32(1)),
+                    |_, _| BlindingFactor::try_from([1u8; 32]).unwrap(),
+                    |_, _| 42,
+                )
+                .unwrap()
+        };
+        let batch = compose();
+        assert_eq!(batch, compose());
+        assert!(batch.blanks.is_empty());
+
+        let transition = &batch.main.first.transition;
+        assert_eq!(transition.inputs.len(), 1);
+        let amounts = transition
+            .assignments
+            .values()
+            .flat_map(|assignments| assignments.as_fungible())
+            .filter_map(|assignment| assignment.as_revealed_state())
+            .map(|state| state.value.as_u64())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(amounts, bset![300, 200, 500]);
+
+        let batch = stock
+            .compose_batch(
+                [(&first, None), (&second, None)],
+                [opret_output(funding)],
+                CloseMethod::OpretFirst,
+                |_, _, _| Some(Vout::from_u32(1)),
+            )
+            .unwrap();
+        assert_ne!(batch.main.first.transition.id(), transition.id());
+        let mut expensive = invoice(3, 800);
+        assert!(matches!(
+            stock.compose_batch(
+                [(&first, None), (&expensive, None)],
+                [opret_output(funding)],
+                CloseMethod::OpretFirst,
+                |_, _, _| Some(Vout::from_u32(1)),
+            ),
+            Err(StockError::InvalidInput(ComposeError::InsufficientState))
+        ));
+        expensive.owned_state = InvoiceState::Void;
+        assert!(matches!(
+            stock.compose_batch(
+                [(&expensive, None)],
+                [opret_output(funding)],
+                CloseMethod::OpretFirst,
+                |_, _, _| Some(Vout::from_u32(1)),
+            ),
+            Err(StockError::InvalidInput(ComposeError::UnsupportedInvoiceState))
+        ));
+    }
+
+    #[test]
+    fn test_compose_budgeted() {
+        use invoice::{RgbInvoiceBuilder, XChainNet};
+
+        let stock = Stock::in_memory();
+        let secret = XChain::Bitcoin(GraphSeal::strict_dumb())
+            .as_reduced_unsafe()
+            .conceal();
+        let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+        let mut invoice = RgbInvoiceBuilder::with(ContractId::strict_dumb(), beneficiary).finish();
+        let compose = |invoice: &RgbInvoice| {
+            stock.compose_budgeted(
+                invoice,
+                [XOutputSeal::strict_dumb()],
+                CloseMethod::OpretFirst,
+                None::<Vout>,
+                |_, _, _| None,
+                1024,
+            )
+        };
+        assert!(matches!(compose(&invoice), Err(StockError::InvalidInput(ComposeError::NoIface))));
+        invoice.contract = None;
+        assert!(matches!(
+            compose(&invoice),
+            Err(StockError::InvalidInput(ComposeError::NoContract))
+        ));
+        assert_eq!(
+            ComposeError::SizeBudgetExceeded {
+                budget: 1024,
+                achievable: 2048
+            }
+            .to_string(),
+            "consignment would exceed the size budget of 1024 bytes; the best achievable \
+             consignment size is 2048 bytes."
+        );
+    }
+
+    #[test]
+    fn test_compose_budget_limits() {
+        let mut stock = Stock::in_memory();
+        let first = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let second = Outpoint::new(Txid::from([2u8; 32]), 0);
+        let contract_id = issue_fungible(&mut stock, &[(first, 600), (second, 600)]);
+
+        let secret =
+            XChain::Bitcoin(GraphSeal::new_random_vout(CloseMethod::OpretFirst, Vout::from_u32(0)))
+                .as_reduced_unsafe()
+                .conceal();
+        let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+        let invoice = RgbInvoiceBuilder::with(contract_id, beneficiary)
+            .set_interface(FUNGIBLE_IFACE)
+            .set_amount_raw(500u64)
+            .finish();
+        let compose = |stock: &Stock, budget: usize| {
+            stock.compose_budgeted(
+                &invoice,
+                [opret_output(first), opret_output(second)],
+                CloseMethod::OpretFirst,
+                None::<Vout>,
+                |_, _, _| Some(Vout::from_u32(1)),
+                budget,
+            )
+        };
+
+        let batch = compose(&stock, usize::MAX).unwrap();
+        assert_eq!(batch.main.first.inputs.len(), 1);
+
+        let Err(StockError::InvalidInput(ComposeError::SizeBudgetExceeded { budget, achievable })) =
+            compose(&stock, 100)
+        else {
+            panic!("budget is not enforced")
+        };
+        assert_eq!(budget, 100);
+        let history = stock
+            .transfer(contract_id, [opret_output(first)], None)
+            .unwrap();
+        assert!(achievable > encoded_size(&history));
+
+        let batch = compose(&stock, achievable).unwrap();
+        assert_eq!(batch.main.first.inputs.len(), 1);
+        assert!(matches!(
+            compose(&stock, achievable - 1),
+            Err(StockError::InvalidInput(ComposeError::SizeBudgetExceeded { achievable: size, .. }))
+                if size == achievable
+        ));
+    }
+
+    #[test]
+    fn test_close_method_policy() {
+        use amplify::confinement::U32 as U32MAX;
+        use strict_encoding::StrictDeserialize;
+
+        let mut stock = Stock::in_memory();
+        stock
+            .set_close_method_policy(CloseMethodPolicy::with(CloseMethod::TapretFirst))
+            .unwrap();
+        let secret = XChain::Bitcoin(GraphSeal::strict_dumb())
+            .as_reduced_unsafe()
+            .conceal();
+        let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+        let invoice = stock
+            .invoice_builder(ContractId::strict_dumb(), beneficiary)
+            .unwrap()
+            .finish();
+        assert_eq!(invoice.close_methods, vec![CloseMethod::TapretFirst]);
+        assert_eq!(stock.invoice_close_method(&invoice).unwrap(), CloseMethod::TapretFirst);
+
+        // The policy is persisted with the stash
+        let data = stock
+            .as_stash_provider()
+            .to_strict_serialized::<U32MAX>()
+            .unwrap();
+        let stash = MemStash::from_strict_serialized::<U32MAX>(data).unwrap();
+        assert_eq!(
+            stash.close_method_policy().unwrap(),
+            CloseMethodPolicy::with(CloseMethod::TapretFirst)
+        );
+
+        let compose = |stock: &mut Stock, method| {
+            stock.compose(
+                &invoice,
+                [XOutputSeal::strict_dumb()],
+                method,
+                None::<Vout>,
+                |_, _, _| None,
+            )
+        };
+        assert!(matches!(
+            compose(&mut stock, CloseMethod::OpretFirst),
+            Err(StockError::InvalidInput(ComposeError::CloseMethodPolicy(CloseMethod::OpretFirst)))
+        ));
+        let mut other = Stock::in_memory();
+        other
+            .set_close_method_policy(CloseMethodPolicy::with(CloseMethod::OpretFirst))
+            .unwrap();
+        assert!(matches!(
+            other.invoice_close_method(&invoice),
+            Err(StockError::InvalidInput(ComposeError::NoCompatibleCloseMethod))
+        ));
+        assert!(matches!(
+            compose(&mut Stock::in_memory(), CloseMethod::OpretFirst),
+            Err(StockError::InvalidInput(ComposeError::CloseMethodUnsupported(_)))
+        ));
+    }
+
+    #[test]
+    fn test_batch_invoices() {
+        let mut stock = Stock::in_memory();
+        let contract_id = ContractId::strict_dumb();
+        let requests = || [InvoiceRequest::new("alice", contract_id, 10u64)];
+        assert!(matches!(
+            stock.batch_invoices(requests(), ChainNet::BitcoinTestnet, BatchSeals::Blinded {
+                method: CloseMethod::TapretFirst,
+                utxos: vec![]
+            }),
+            Err(StockError::InvalidInput(BatchInvoiceError::NoUtxos))
+        ));
+        assert!(matches!(
+            stock.batch_invoices(
+                requests(),
+                ChainNet::BitcoinTestnet,
+                BatchSeals::WitnessVout(vec![])
+            ),
+            Err(StockError::InvalidInput(BatchInvoiceError::NotEnoughAddresses(0, 1)))
+        ));
+        assert!(
+            stock
+                .batch_invoices(requests(), ChainNet::BitcoinTestnet, BatchSeals::Blinded {
+                    method: CloseMethod::TapretFirst,
+                    utxos: vec![XOutpoint::strict_dumb()]
+                })
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_archive_contract() {
+        let mut stock = Stock::in_memory();
+        let genesis = Genesis::strict_dumb();
+        let contract_id = genesis.contract_id();
+        assert!(stock.archive_contract(contract_id).is_err());
+
+        stock
+            .state
+            .as_provider_mut()
+            .register_contract(&Schema::strict_dumb(), &genesis)
+            .unwrap();
+        assert!(stock.state.archive_contract(contract_id).unwrap());
+        assert!(!stock.state.archive_contract(contract_id).unwrap());
+        assert_eq!(stock.archived_contracts().unwrap().collect::<Vec<_>>(), vec![contract_id]);
+        assert!(matches!(
+            stock.state.as_provider().contract_state(contract_id),
+            Err(StateInconsistency::ArchivedContract(id)) if id == contract_id
+        ));
+
+        assert!(stock.unarchive_contract(contract_id).unwrap());
+        assert!(!stock.unarchive_contract(contract_id).unwrap());
+        assert_eq!(stock.archived_contracts().unwrap().count(), 0);
+        assert!(stock.contract_state(contract_id).is_ok());
+    }
+
+    #[test]
+    fn test_archive_cold_storage() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use amplify::confinement::U32 as U32MAX;
+        use strict_encoding::{StrictDeserialize, StrictSerialize};
+
+        use crate::persistence::{ArchiveProvider, MemArchive, MemState};
+
+        #[derive(Debug)]
+        struct TestArchive(Option<MemArchive>, Arc<AtomicUsize>);
+        impl ArchiveProvider for TestArchive {
+            fn load_archive(&self) -> Result<MemArchive, PersistenceError> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.clone().ok_or_else(|| {
+                    PersistenceError::with(std::io::Error::from(std::io::ErrorKind::NotFound))
+                })
+            }
+        }
+
+        let genesis = Genesis::strict_dumb();
+        let contract_id = genesis.contract_id();
+        let mut state = MemState::in_memory();
+        state
+            .register_contract(&Schema::strict_dumb(), &genesis)
+            .unwrap();
+        assert!(state.archive_contract(contract_id).unwrap());
+        let archive = state.dirty_archive().cloned().unwrap();
+        let data = state.to_strict_serialized::<U32MAX>().unwrap();
+
+        // Archived state is not a part of the main state and is loaded lazily
+        let loads = Arc::new(AtomicUsize::new(0));
+        let mut state = MemState::from_strict_serialized::<U32MAX>(data.clone())
+            .unwrap()
+            .with_archive_provider(TestArchive(Some(archive), loads.clone()));
+        assert_eq!(state.archived_contracts().unwrap().collect::<Vec<_>>(), vec![contract_id]);
+        assert!(state.contract_state(contract_id).is_err());
+        assert_eq!(loads.load(Ordering::SeqCst), 0);
+        assert!(state.unarchive_contract(contract_id).unwrap());
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert!(state.contract_state(contract_id).is_ok());
+        assert!(state.dirty_archive().unwrap().debug_contracts().is_empty());
+
+        // Failed restoration leaves the contract archived
+        let loads = Arc::new(AtomicUsize::new(0));
+        let mut state = MemState::from_strict_serialized::<U32MAX>(data)
+            .unwrap()
+            .with_archive_provider(TestArchive(None, loads.clone()));
+        assert!(state.unarchive_contract(contract_id).is_err());
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert_eq!(state.archived_contracts().unwrap().collect::<Vec<_>>(), vec![contract_id]);
+        assert!(state.dirty_archive().is_none());
+
+        // Failed archival leaves the contract state intact
+        let mut state =
+            MemState::in_memory().with_archive_provider(TestArchive(None, loads.clone()));
+        state
+            .register_contract(&Schema::strict_dumb(), &genesis)
+            .unwrap();
+        assert!(state.archive_contract(contract_id).is_err());
+        assert_eq!(state.archived_contracts().unwrap().count(), 0);
+        assert!(state.contract_state(contract_id).is_ok());
+    }
+
+    #[test]
+    fn test_compose_cpfp() {
+        use crate::broadcast::MempoolInfo;
+
+        struct MockBroadcaster(Option<MempoolInfo>);
+        impl Broadcaster for MockBroadcaster {
+            type Error = Infallible;
+            fn broadcast(&self, _: &rgb::vm::XWitnessTx) -> Result<XWitnessId, Infallible> {
+                unreachable!()
+            }
+            fn mempool_info(&self, _: XWitnessId) -> Result<Option<MempoolInfo>, Infallible> {
+                Ok(self.0.clone())
+            }
+        }
+
+        let stock = Stock::in_memory();
+        let witness_id = XWitnessId::strict_dumb();
+        let compose = |broadcaster: MockBroadcaster| {
+            stock
+                .compose_cpfp(
+                    witness_id,
+                    "RGB20",
+                    FilterIncludeAll,
+                    CloseMethod::OpretFirst,
+                    1u32,
+                    10.0,
+                    &broadcaster,
+                )
+                .map(|_| ())
+        };
+        let info = |fee: u64| MempoolInfo {
+            fee: bp::Sats::from_sats(fee),
+            vsize: 100,
+            ancestors: none!(),
+            ancestor_fee: bp::Sats::from_sats(fee),
+            ancestor_vsize: 100,
+        };
+        assert!(matches!(
+            compose(MockBroadcaster(None)),
+            Err(StockError::InvalidInput(CpfpError::NotInMempool(id))) if id == witness_id
+        ));
+        assert!(matches!(
+            compose(MockBroadcaster(Some(info(1_000)))),
+            Err(StockError::InvalidInput(CpfpError::NotStuck(_)))
+        ));
+        assert!(matches!(
+            compose(MockBroadcaster(Some(info(100)))),
+            Err(StockError::InvalidInput(CpfpError::NoOutputs(_)))
+        ));
+    }
+
+    #[test]
+    fn test_resume_pending() {
+        use std::io;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        use nonasync::persistence::{PersistenceError, PersistenceProvider};
+
+        /// Storage which fails to persist data while it is offline.
+        #[derive(Clone, Debug, Default)]
+        struct FlakyStore(Arc<AtomicBool>);
+        impl<T> PersistenceProvider<T> for FlakyStore {
+            fn load(&self) -> Result<T, PersistenceError> {
+                Err(PersistenceError::with(io::Error::other("no data")))
+            }
+            fn store(&self, _: &T) -> Result<(), PersistenceError> {
+                if self.0.load(Ordering::SeqCst) {
+                    return Err(PersistenceError::with(io::Error::other("storage is offline")));
+                }
+                Ok(())
+            }
+        }
+
+        let mut sender = Stock::in_memory();
+        let funding = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let contract_id = issue_fungible(&mut sender, &[(funding, 1000)]);
+        let mut receiver = sender.clone_no_persistence();
+        let secret =
+            XChain::Bitcoin(GraphSeal::new_random_vout(CloseMethod::OpretFirst, Vout::from_u32(0)))
+                .as_reduced_unsafe()
+                .conceal();
+        let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+        let invoice = RgbInvoiceBuilder::with(contract_id, beneficiary)
+            .set_interface(FUNGIBLE_IFACE)
+            .set_amount_raw(400u64)
+            .finish();
+        let batch = sender
+            .compose(
+                &invoice,
+                [opret_output(funding)],
+                CloseMethod::OpretFirst,
+                None::<Vout>,
+                |_, _, _| Some(Vout::from_u32(1)),
+            )
+            .unwrap();
+        let uncommitted = crate::broadcast::test::tx(&[funding], &[1000, 1000]);
+        let fascia = committed_fascia_for(contract_id, &batch, uncommitted.clone());
+        let witness_id = fascia.witness_id();
+        let change = opret_output(Outpoint::new(*witness_id.as_reduced_unsafe(), 1));
+        let bundle_id = fascia.bundles.values().next().unwrap().first.bundle_id();
+        sender.consume_fascia(fascia, TentativeResolver).unwrap();
+        let transfer = sender
+            .transfers(witness_id, [(&invoice, None)])
+            .unwrap()
+            .pop()
+            .unwrap();
+        let has_change = |stock: &Stock| {
+            !stock
+                .contract_assignments_for(contract_id, [change])
+                .unwrap()
+                .is_empty()
+        };
+
+        // Consignment which acceptance fails midway remains pending
+        let id = transfer.consignment_id();
+        let mut corrupted = receiver.clone_no_persistence();
+        corrupted
+            .as_index_provider_mut()
+            .register_bundle(bundle_id, witness_id, ContractId::from([0xA5u8; 32]))
+            .unwrap();
+        assert!(
+            corrupted
+                .accept_transfer(ValidTransfer::assume_valid(transfer.clone()), TentativeResolver)
+                .is_err()
+        );
+        assert!(corrupted.as_stash_provider().witness(witness_id).is_err());
+        assert_eq!(corrupted.pending_consignments().unwrap(), vec![id]);
+
+        // Consignment which can't be persisted remains pending
+        let store = FlakyStore::default();
+        receiver.make_persistent(store.clone(), true).unwrap();
+        store.0.store(true, Ordering::SeqCst);
+        assert!(
+            receiver
+                .accept_transfer(ValidTransfer::assume_valid(transfer.clone()), TentativeResolver)
+                .is_err()
+        );
+        assert_eq!(receiver.pending_consignments().unwrap(), vec![id]);
+
+        // Pending consignments are validated before being re-applied, such that
+        // invalid ones remain pending without affecting the others
+        let mut invalid = transfer.clone();
+        invalid.bundles =
+            Confined::from_iter_checked(transfer.bundles.iter().cloned().map(|mut wb| {
+                wb.pub_witness =
+                    XChain::Bitcoin(crate::containers::PubWitness::Tx(uncommitted.clone()));
+                wb
+            }));
+        let invalid_id = invalid.consignment_id();
+        receiver.stash.store_pending(invalid_id, Some(invalid)).ok();
+        store.0.store(false, Ordering::SeqCst);
+        let report = receiver.resume_pending(TentativeResolver, true).unwrap();
+        assert!(!report.is_complete());
+        assert!(report.applied.contains_key(&id));
+        assert!(report.rejected.contains_key(&invalid_id));
+        assert_eq!(receiver.pending_consignments().unwrap(), vec![invalid_id]);
+        assert!(has_change(&receiver));
+    }
+
+    #[test]
+    fn test_in_memory_fork() {
+        use std::io;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use nonasync::persistence::{PersistenceError, PersistenceProvider};
+
+        /// Storage which counts the number of times the data were persisted.
+        #[derive(Clone, Debug, Default)]
+        struct CountingStore(Arc<AtomicUsize>);
+        impl<T> PersistenceProvider<T> for CountingStore {
+            fn load(&self) -> Result<T, PersistenceError> {
+                Err(PersistenceError::with(io::Error::other("no data")))
+            }
+            fn store(&self, _: &T) -> Result<(), PersistenceError> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        let mut stock = Stock::in_memory();
+        let genesis = Genesis::strict_dumb();
+        let contract_id = genesis.contract_id();
+        stock
+            .state
+            .as_provider_mut()
+            .register_contract(&Schema::strict_dumb(), &genesis)
+            .unwrap();
+
+        let mut fork = stock.fork();
+        assert!(fork.state.archive_contract(contract_id).unwrap());
+        assert!(fork.contract_state(contract_id).is_err());
+        assert!(stock.contract_state(contract_id).is_ok());
+        assert_eq!(stock.archived_contracts().unwrap().count(), 0);
+
+        // Forks of persisted stocks are not persisted
+        let store = CountingStore::default();
+        stock.make_persistent(store.clone(), true).unwrap();
+        let stored = store.0.load(Ordering::SeqCst);
+        let mut fork = stock.fork();
+        fork.set_close_method_policy(CloseMethodPolicy::default())
+            .unwrap();
+        assert_eq!(store.0.load(Ordering::SeqCst), stored);
+        stock
+            .set_close_method_policy(CloseMethodPolicy::default())
+            .unwrap();
+        assert!(store.0.load(Ordering::SeqCst) > stored);
+    }
+
+    #[test]
+    #[cfg(feature = "analytics")]
+    fn test_export_analytics() {
+        let stock = Stock::in_memory();
+        assert!(stock.export_analytics(ContractId::strict_dumb()).is_err());
+    }
+
+    #[test]
+    fn test_reservations() {
+        use invoice::{RgbInvoiceBuilder, XChainNet};
+
+        let mut stock = Stock::in_memory();
+        let batch = Batch::strict_dumb();
+        let output = XOutputSeal::strict_dumb();
+        let secret = XChain::Bitcoin(GraphSeal::strict_dumb())
+            .as_reduced_unsafe()
+            .conceal();
+        let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+        let invoice = RgbInvoiceBuilder::with(ContractId::strict_dumb(), beneficiary).finish();
+
+        let id = stock.reserve(&batch, i64::MAX).unwrap();
+        assert_eq!(stock.reservations().unwrap().count(), 1);
+        assert!(
+            stock
+                .reserved_outputs()
+                .unwrap()
+                .contains(&output.to_outpoint())
+        );
+        assert!(matches!(
+            stock.compose(
+                &invoice,
+                [output],
+                CloseMethod::OpretFirst,
+                None::<Vout>,
+                |_, _, _| None,
+            ),
+            Err(StockError::InvalidInput(ComposeError::ReservedOutput(_)))
+        ));
+        assert!(stock.abandon_expired().unwrap().is_empty());
+
+        assert_eq!(stock.reserve(&batch, 0).unwrap(), id);
+        assert!(stock.reserved_outputs().unwrap().is_empty());
+        let abandoned = stock.abandon_expired().unwrap();
+        assert_eq!(abandoned.len(), 1);
+        assert_eq!(abandoned[0].0, id);
+        assert_eq!(stock.reservations().unwrap().count(), 0);
+
+        stock.reserve(&batch, i64::MAX).unwrap();
+        assert!(stock.abandon(id).unwrap());
+        assert!(!stock.abandon(id).unwrap());
+    }
+
+    #[test]
+    fn test_compose_reserves() {
+        let mut stock = Stock::in_memory();
+        let funding = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let contract_id = issue_fungible(&mut stock, &[(funding, 1000)]);
+        let secret =
+            XChain::Bitcoin(GraphSeal::new_random_vout(CloseMethod::OpretFirst, Vout::from_u32(0)))
+                .as_reduced_unsafe()
+                .conceal();
+        let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+        let invoice = RgbInvoiceBuilder::with(contract_id, beneficiary)
+            .set_interface(FUNGIBLE_IFACE)
+            .set_amount_raw(500u64)
+            .finish();
+        let compose = |stock: &Stock| {
+            stock.compose(
+                &invoice,
+                [opret_output(funding)],
+                CloseMethod::OpretFirst,
+                None::<Vout>,
+                |_, _, _| Some(Vout::from_u32(1)),
+            )
+        };
+        let compose_reserved = |stock: &mut Stock, deadline: i64| {
+            stock.compose_reserved(
+                &invoice,
+                [opret_output(funding)],
+                CloseMethod::OpretFirst,
+                None::<Vout>,
+                |_, _, _| Some(Vout::from_u32(1)),
+                deadline,
+            )
+        };
+
+        // Plain composition has no side effects
+        compose(&stock).unwrap();
+        assert_eq!(stock.reservations().unwrap().count(), 0);
+
+        let deadline = Utc::now().timestamp() + RESERVATION_TIMEOUT;
+        let batch = compose_reserved(&mut stock, deadline).unwrap();
+        let id = Reservation::id(&batch);
+        let (reserved, reservation) = stock.reservations().unwrap().next().unwrap();
+        assert_eq!(reserved, id);
+        assert_eq!(reservation.deadline, deadline);
+        assert!(matches!(
+            compose(&stock),
+            Err(StockError::InvalidInput(ComposeError::ReservedOutput(_)))
+        ));
+        assert!(matches!(
+            compose_reserved(&mut stock, deadline),
+            Err(StockError::InvalidInput(ComposeError::ReservedOutput(_)))
+        ));
+
+        // Expired reservations are swept when composing
+        assert!(stock.abandon(id).unwrap());
+        let expired = Reservation::id(&compose_reserved(&mut stock, 0).unwrap());
+        let fresh = Reservation::id(&compose_reserved(&mut stock, deadline).unwrap());
+        assert_ne!(expired, fresh);
+        assert_eq!(
+            stock
+                .reservations()
+                .unwrap()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>(),
+            vec![fresh]
+        );
+    }
+
+    #[test]
+    fn test_min_confirmations() {
+        use amplify::confinement::U32 as U32MAX;
+        use rgb::vm::WitnessPos;
+        use strict_encoding::StrictDeserialize;
+
+        struct MinedResolver(u32);
+        impl ResolveWitness for MinedResolver {
+            fn resolve_pub_witness(
+                &self,
+                witness_id: XWitnessId,
+            ) -> Result<rgb::vm::XWitnessTx, WitnessResolverError> {
+                Err(WitnessResolverError::Unknown(witness_id))
+            }
+            fn resolve_pub_witness_ord(
+                &self,
+                _: XWitnessId,
+            ) -> Result<WitnessOrd, WitnessResolverError> {
+                Ok(WitnessOrd::Mined(WitnessPos::new(self.0, 1231006505).unwrap()))
+            }
+        }
+
+        let mut sender = Stock::in_memory();
+        let funding = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let contract_id = issue_fungible(&mut sender, &[(funding, 1000)]);
+        let mut receiver = sender.clone_no_persistence();
+        let secret =
+            XChain::Bitcoin(GraphSeal::new_random_vout(CloseMethod::OpretFirst, Vout::from_u32(0)))
+                .as_reduced_unsafe()
+                .conceal();
+        let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+        let invoice = RgbInvoiceBuilder::with(contract_id, beneficiary)
+            .set_interface(FUNGIBLE_IFACE)
+            .set_amount_raw(400u64)
+            .finish();
+        let batch = sender
+            .compose(
+                &invoice,
+                [opret_output(funding)],
+                CloseMethod::OpretFirst,
+                None::<Vout>,
+                |_, _, _| Some(Vout::from_u32(1)),
+            )
+            .unwrap();
+        let tx = crate::broadcast::test::tx(&[funding], &[1000, 1000]);
+        let witness_id = XChain::Bitcoin(tx.txid());
+        let change = opret_output(Outpoint::new(tx.txid(), 1));
+        sender
+            .consume_fascia(fascia_for(contract_id, &batch, tx), TentativeResolver)
+            .unwrap();
+        let transfer = sender
+            .transfers(witness_id, [(&invoice, None)])
+            .unwrap()
+            .pop()
+            .unwrap();
+        let accept = |stock: &mut Stock, tip_height: u32, provisional: bool| {
+            let opts = AcceptOptions::new().with_confirmations(6, tip_height, provisional);
+            let transfer = ValidTransfer::assume_valid(transfer.clone());
+            stock.accept_transfer_with(transfer, MinedResolver(100), opts)
+        };
+        let has_change = |stock: &Stock| {
+            !stock
+                .contract_assignments_for(contract_id, [change])
+                .unwrap()
+                .is_empty()
+        };
+
+        assert!(matches!(
+            accept(&mut receiver, 102, false),
+            Err(StockError::InvalidInput(ConfirmationError::Unconfirmed {
+                confirmations: 3,
+                required: 6,
+                ..
+            }))
+        ));
+        assert!(!has_change(&receiver));
+
+        let mut confirmed = receiver.clone_no_persistence();
+        let report = accept(&mut confirmed, 105, false).unwrap();
+        assert!(report.pending.is_empty());
+        assert!(has_change(&confirmed));
+
+        let report = accept(&mut receiver, 102, true).unwrap();
+        assert_eq!(report.pending, bset![witness_id]);
+        assert_eq!(receiver.provisional_witnesses().unwrap(), bmap! { witness_id => 6 });
+        assert!(!has_change(&receiver));
+
+        // Provisional state survives witness updates and persistence
+        receiver.update_witnesses(MinedResolver(100), 0).unwrap();
+        assert!(!has_change(&receiver));
+        let data = receiver
+            .as_stash_provider()
+            .to_strict_serialized::<U32MAX>()
+            .unwrap();
+        let stash = MemStash::from_strict_serialized::<U32MAX>(data).unwrap();
+        assert_eq!(stash.provisional_witnesses().unwrap().collect::<Vec<_>>(), vec![(
+            witness_id, 6
+        )]);
+
+        assert!(
+            receiver
+                .update_confirmations(MinedResolver(100), 104)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(!has_change(&receiver));
+        assert_eq!(
+            receiver
+                .update_confirmations(MinedResolver(100), 105)
+                .unwrap(),
+            bset![witness_id]
+        );
+        assert!(has_change(&receiver));
+        assert!(receiver.provisional_witnesses().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_conflict_resolution() {
+        use rgb::vm::WitnessPos;
+
+        struct MinedResolver(XWitnessId);
+        impl ResolveWitness for MinedResolver {
+            fn resolve_pub_witness(
+                &self,
+                witness_id: XWitnessId,
+            ) -> Result<rgb::vm::XWitnessTx, WitnessResolverError> {
+                Err(WitnessResolverError::Unknown(witness_id))
+            }
+            fn resolve_pub_witness_ord(
+                &self,
+                witness_id: XWitnessId,
+            ) -> Result<WitnessOrd, WitnessResolverError> {
+                if witness_id != self.0 {
+                    return Ok(WitnessOrd::Tentative);
+                }
+                Ok(WitnessOrd::Mined(WitnessPos::new(100, 1231006505).unwrap()))
+            }
+        }
+
+        let mut sender = Stock::in_memory();
+        let funding = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let contract_id = issue_fungible(&mut sender, &[(funding, 1000)]);
+        let mut device = sender.clone_no_persistence();
+        let mut receiver = sender.clone_no_persistence();
+
+        // Two devices of the same wallet spend the same allocation
+        let pay = |stock: &mut Stock, amount: u64| {
+            let secret = XChain::Bitcoin(GraphSeal::new_random_vout(
+                CloseMethod::OpretFirst,
+                Vout::from_u32(0),
+            ))
+            .as_reduced_unsafe()
+            .conceal();
+            let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+            let invoice = RgbInvoiceBuilder::with(contract_id, beneficiary)
+                .set_interface(FUNGIBLE_IFACE)
+                .set_amount_raw(amount)
+                .finish();
+            let batch = stock
+                .compose(
+                    &invoice,
+                    [opret_output(funding)],
+                    CloseMethod::OpretFirst,
+                    None::<Vout>,
+                    |_, _, _| Some(Vout::from_u32(1)),
+                )
+                .unwrap();
+            let tx = crate::broadcast::test::tx(&[funding], &[1000, amount]);
+            let witness_id = XChain::Bitcoin(tx.txid());
+            let change = opret_output(Outpoint::new(tx.txid(), 1));
+            stock
+                .consume_fascia(fascia_for(contract_id, &batch, tx), TentativeResolver)
+                .unwrap();
+            let transfer = stock
+                .transfers(witness_id, [(&invoice, None)])
+                .unwrap()
+                .pop()
+                .unwrap();
+            (batch.main.first.id, witness_id, change, transfer)
+        };
+        let (first, first_witness, first_change, first_transfer) = pay(&mut sender, 400);
+        let (second, _, second_change, second_transfer) = pay(&mut device, 300);
+        let has_change = |stock: &Stock, change: XOutputSeal| {
+            !stock
+                .contract_assignments_for(contract_id, [change])
+                .unwrap()
+                .is_empty()
+        };
+
+        let accept = |stock: &mut Stock, transfer: Transfer, opts: AcceptOptions| {
+            stock
+                .accept_transfer_with(
+                    ValidTransfer::assume_valid(transfer),
+                    TentativeResolver,
+                    opts,
+                )
+                .unwrap()
+        };
+        let provenance = Provenance::new(SmallString::from_checked(s!("storm:sender")), None);
+        let progress = std::cell::RefCell::new(vec![]);
+        let sink = |p: Progress| progress.borrow_mut().push(p);
+        let opts = AcceptOptions::new()
+            .with_conflict_check()
+            .with_provenance(provenance.clone())
+            .with_progress(&sink);
+        let first_id = first_transfer.consignment_id();
+        let report = accept(&mut receiver, first_transfer, opts);
+        assert!(report.conflicts.is_empty());
+        assert!(report.pending.is_empty() && report.replacements.is_empty());
+        assert_eq!(receiver.provenance(first_id).unwrap(), Some(&provenance));
+        assert_eq!(progress.borrow().last(), Some(&Progress::Storing));
+        let opts = AcceptOptions::new().with_conflict_check();
+        let report = accept(&mut receiver, second_transfer, opts).conflicts;
+        let conflict = report.conflicts.first().unwrap().clone();
+        assert_eq!(report.conflicts.len(), 1);
+        assert!(conflict.is_resolved());
+        assert!(!conflict.branch(second).unwrap().is_valid());
+
+        // The branch known first is kept, including after witness updates
+        assert_eq!(receiver.stash.conflict_resolutions().unwrap(), bmap! {
+            conflict.opout => first
+        });
+        receiver.update_witnesses(TentativeResolver, 0).unwrap();
+        assert!(has_change(&receiver, first_change));
+        assert!(!has_change(&receiver, second_change));
+
+        // The chosen branch survives witness updates
+        receiver
+            .resolve_conflict(&conflict, second, TentativeResolver)
+            .unwrap();
+        receiver.update_witnesses(TentativeResolver, 0).unwrap();
+        assert!(!has_change(&receiver, first_change));
+        assert!(has_change(&receiver, second_change));
+        assert!(matches!(
+            receiver.resolve_conflict(&conflict, OpId::from([0u8; 32]), TentativeResolver),
+            Err(StockError::InvalidInput(ConflictError::UnknownBranch(..)))
+        ));
+
+        // Mined branch takes over the one chosen before
+        assert!(
+            !receiver
+                .resolve_conflicts(TentativeResolver)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            receiver
+                .resolve_conflicts(&MinedResolver(first_witness))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(has_change(&receiver, first_change));
+        assert!(!has_change(&receiver, second_change));
+    }
+
+    #[test]
+    fn test_complete_signed() {
+        let mut stock = Stock::in_memory();
+        let context = stock.signing_context(&Batch::strict_dumb());
+        assert!(matches!(
+            stock.complete_signed(&context, Fascia::strict_dumb(), DumbResolver),
+            Err(StockError::InvalidInput(FasciaError::SigningMismatch(_)))
+        ));
+    }
+
+    #[test]
+    fn test_payment_proof() {
+        use bp::seals::txout::BlindSeal;
+        use invoice::{RgbInvoiceBuilder, XChainNet};
+
+        let mut stock = Stock::in_memory();
+        let contract_id =
+            ContractId::from_baid64_str("rgb:qFuT6DN8-9AuO95M-7R8R8Mc-AZvs7zG-obum1Va-BRnweKk")
+                .unwrap();
+        let seal = XChain::Bitcoin(BlindSeal::new_random(
+            CloseMethod::OpretFirst,
+            bp::Txid::from([1u8; 32]),
+            Vout::from_u32(0),
+        ));
+        let beneficiary =
+            XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(seal.as_reduced_unsafe().conceal()));
+        let invoice = RgbInvoiceBuilder::with(contract_id, beneficiary).finish();
+
+        let mut anonymous = invoice.clone();
+        anonymous.contract = None;
+        assert!(matches!(
+            stock.payment_proof(&anonymous),
+            Err(StockError::InvalidInput(PaymentProofError::NoContract))
+        ));
+        assert!(matches!(
+            stock.payment_proof(&invoice),
+            Err(StockError::InvalidInput(PaymentProofError::UnknownSeal))
+        ));
+        stock.store_secret_seal(seal).unwrap();
+        assert!(stock.payment_proof(&invoice).is_err());
+    }
+
+    #[test]
+    fn test_finalize_transfer() {
+        use crate::broadcast::{BroadcastError, MempoolInfo};
+
+        struct Unreachable;
+        impl Broadcaster for Unreachable {
+            type Error = Infallible;
+            fn broadcast(&self, _: &rgb::vm::XWitnessTx) -> Result<XWitnessId, Infallible> {
+                unreachable!()
+            }
+            fn mempool_info(&self, _: XWitnessId) -> Result<Option<MempoolInfo>, Infallible> {
+                unreachable!()
+            }
         }
+
+        struct Node(Result<(), String>);
+        impl Broadcaster for Node {
+            type Error = BroadcastError<Infallible>;
+            fn broadcast(&self, tx: &rgb::vm::XWitnessTx) -> Result<XWitnessId, Self::Error> {
+                self.0.clone().map_err(BroadcastError::Rejected)?;
+                Ok(tx.map_ref(|tx| tx.txid()))
+            }
+            fn mempool_info(&self, _: XWitnessId) -> Result<Option<MempoolInfo>, Self::Error> {
+                unreachable!()
+            }
+        }
+
+        let mut stock = Stock::in_memory();
+        let fascia = Fascia::strict_dumb();
+        let witness_id = fascia.witness_id();
+        assert!(matches!(
+            stock.finalize_transfer(fascia, DumbResolver, Some(Unreachable)),
+            Err(StockError::InvalidInput(FasciaError::NoWitnessTx(id))) if id == witness_id
+        ));
+
+        let funding = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let contract_id = issue_fungible(&mut stock, &[(funding, 1000)]);
+        let secret = XChain::Bitcoin(GraphSeal::strict_dumb())
+            .as_reduced_unsafe()
+            .conceal();
+        let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+        let invoice = RgbInvoiceBuilder::with(contract_id, beneficiary)
+            .set_interface(FUNGIBLE_IFACE)
+            .set_amount_raw(400u64)
+            .finish();
+        let batch = stock
+            .compose(
+                &invoice,
+                [opret_output(funding)],
+                CloseMethod::OpretFirst,
+                None::<Vout>,
+                |_, _, _| Some(Vout::from_u32(1)),
+            )
+            .unwrap();
+        let tx = crate::broadcast::test::tx(&[funding], &[1000, 1000]);
+        let txid = tx.txid();
+        // The contract state keeps spent allocations, thus the import of the
+        // fascia is detected by the presence of the change allocation
+        let change = |stock: &Stock| {
+            stock
+                .contract_assignments_for(contract_id, [opret_output(Outpoint::new(txid, 1))])
+                .unwrap()
+                .len()
+        };
+
+        // Rejected broadcast leaves the stock intact
+        let fascia = fascia_for(contract_id, &batch, tx.clone());
+        let rejecting = Node(Err(s!("fee is too low")));
+        assert!(matches!(
+            stock.finalize_transfer(fascia, TentativeResolver, Some(rejecting)),
+            Err(StockError::InvalidInput(FasciaError::Broadcast(id, _)))
+                if id == XChain::Bitcoin(txid)
+        ));
+        assert_eq!(change(&stock), 0);
+
+        // Without a broadcaster the fascia is consumed right away
+        let mut offline = stock.clone_no_persistence();
+        let fascia = fascia_for(contract_id, &batch, tx.clone());
+        assert_eq!(
+            offline
+                .finalize_transfer(fascia, TentativeResolver, None::<Node>)
+                .unwrap(),
+            XChain::Bitcoin(txid)
+        );
+        assert_eq!(change(&offline), 1);
+
+        let fascia = fascia_for(contract_id, &batch, tx);
+        assert_eq!(
+            stock
+                .finalize_transfer(fascia, TentativeResolver, Some(Node(Ok(()))))
+                .unwrap(),
+            XChain::Bitcoin(txid)
+        );
+        assert_eq!(change(&stock), 1);
+    }
+
+    #[test]
+    fn test_sync() {
+        let mut desktop = Stock::in_memory();
+        let mobile = Stock::in_memory();
+        let request = desktop.sync_request().unwrap();
+        assert!(request.known.is_empty());
+        let response = mobile.sync_response(&request).unwrap();
+        assert!(response.is_empty());
+        let report = desktop.apply_sync(response, DumbResolver, true).unwrap();
+        assert!(report.is_complete());
+        assert!(report.imported.is_empty());
+
+        // Stock which knows the contract receives only the bundles it misses
+        let funding = Outpoint::new(Txid::from([1u8; 32]), 0);
+        let contract_id = issue_fungible(&mut desktop, &[(funding, 1000)]);
+        let mut mobile = desktop.clone_no_persistence();
+        let secret =
+            XChain::Bitcoin(GraphSeal::new_random_vout(CloseMethod::OpretFirst, Vout::from_u32(0)))
+                .as_reduced_unsafe()
+                .conceal();
+        let beneficiary = XChainNet::BitcoinTestnet(Beneficiary::BlindedSeal(secret));
+        let invoice = RgbInvoiceBuilder::with(contract_id, beneficiary)
+            .set_interface(FUNGIBLE_IFACE)
+            .set_amount_raw(400u64)
+            .finish();
+        let batch = desktop
+            .compose(
+                &invoice,
+                [opret_output(funding)],
+                CloseMethod::OpretFirst,
+                None::<Vout>,
+                |_, _, _| Some(Vout::from_u32(1)),
+            )
+            .unwrap();
+        let tx = crate::broadcast::test::tx(&[funding], &[1000, 1000]);
+        let fascia = committed_fascia_for(contract_id, &batch, tx);
+        let bundle_id = fascia.bundles.values().next().unwrap().first.bundle_id();
+        desktop.consume_fascia(fascia, TentativeResolver).unwrap();
+
+        let request = mobile.sync_request().unwrap();
+        assert!(!request.knows_bundle(contract_id, bundle_id));
+        let response = desktop.sync_response(&request).unwrap();
+        let delta = &response.deltas[&contract_id];
+        let bundle_ids = delta.bundles.iter().map(|wb| wb.bundle.bundle_id());
+        assert_eq!(bundle_ids.collect::<Vec<_>>(), vec![bundle_id]);
+        assert!(delta.extensions.is_empty());
+        let report = mobile
+            .apply_sync(response, TentativeResolver, true)
+            .unwrap();
+        assert!(report.is_complete());
+        assert!(report.imported.contains_key(&contract_id));
+        assert!(
+            mobile
+                .index
+                .contract_bundle_ids(contract_id)
+                .unwrap()
+                .contains(&bundle_id)
+        );
+
+        // Up-to-date stocks receive nothing
+        let request = mobile.sync_request().unwrap();
+        assert!(request.knows_bundle(contract_id, bundle_id));
+        assert!(desktop.sync_response(&request).unwrap().is_empty());
+
+        // Empty stock receives the whole contract
+        let mut empty = Stock::in_memory();
+        let response = desktop
+            .sync_response(&empty.sync_request().unwrap())
+            .unwrap();
+        let report = empty.apply_sync(response, TentativeResolver, true).unwrap();
+        assert!(report.imported.contains_key(&contract_id));
+        assert!(
+            empty
+                .sync_response(&mobile.sync_request().unwrap())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_contract_history() {
+        let stock = Stock::in_memory();
+        assert!(stock.contract_history(ContractId::strict_dumb()).is_err());
     }
 
     #[test]
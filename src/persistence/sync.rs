@@ -0,0 +1,186 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Differential synchronization of stocks.
+//!
+//! Two stocks owned by the same user (for instance, desktop and mobile
+//! wallets) converge without exchanging full backups:
+//! 1. The first stock produces [`SyncRequest`] listing ids of the bundles and state extensions it
+//!    knows for each of its contracts with [`Stock::sync_request`].
+//! 2. The second stock answers with [`SyncResponse`] containing a delta consignment for each
+//!    contract, carrying only the bundles and extensions missed by the first stock, using
+//!    [`Stock::sync_response`].
+//! 3. The first stock completes the delta consignments with its own operations from the history of
+//!    the received ones, validates and imports them with [`Stock::apply_sync`].
+//!
+//! Running the procedure in both directions makes both stocks know the same
+//! contract history.
+//!
+//! [`Stock::sync_request`]: super::Stock::sync_request
+//! [`Stock::sync_response`]: super::Stock::sync_response
+//! [`Stock::apply_sync`]: super::Stock::apply_sync
+
+use std::collections::BTreeMap;
+
+use amplify::confinement::{LargeOrdSet, TinyOrdMap};
+use rgb::validation::Status;
+use rgb::{BundleId, ContractId, OpId};
+use strict_encoding::{StrictDeserialize, StrictSerialize};
+
+use crate::containers::Contract;
+use crate::LIB_NAME_RGB_STD;
+
+/// Request for the synchronization, listing bundles known to the requesting
+/// stock.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SyncRequest {
+    /// Operations known for each of the contracts.
+    pub known: TinyOrdMap<ContractId, KnownOps>,
+}
+
+impl StrictSerialize for SyncRequest {}
+impl StrictDeserialize for SyncRequest {}
+
+impl SyncRequest {
+    /// Detects whether the requesting stock knows the contract.
+    pub fn knows_contract(&self, contract_id: ContractId) -> bool {
+        self.known.contains_key(&contract_id)
+    }
+
+    /// Detects whether the requesting stock knows the bundle of the contract.
+    pub fn knows_bundle(&self, contract_id: ContractId, bundle_id: BundleId) -> bool {
+        self.known
+            .get(&contract_id)
+            .map(|known| known.bundles.contains(&bundle_id))
+            .unwrap_or_default()
+    }
+
+    /// Detects whether the requesting stock knows the state extension of the
+    /// contract.
+    pub fn knows_extension(&self, contract_id: ContractId, opid: OpId) -> bool {
+        self.known
+            .get(&contract_id)
+            .map(|known| known.extensions.contains(&opid))
+            .unwrap_or_default()
+    }
+}
+
+/// Operations of a contract known to the stock requesting synchronization.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct KnownOps {
+    /// Ids of the known bundles.
+    pub bundles: LargeOrdSet<BundleId>,
+    /// Ids of the known state extensions.
+    pub extensions: LargeOrdSet<OpId>,
+}
+
+/// Response to [`SyncRequest`], containing data missed by the requesting
+/// stock.
+#[derive(Clone, PartialEq, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct SyncResponse {
+    /// Delta consignments for the contracts which the requesting stock
+    /// doesn't know or misses some operations for. A delta consignment
+    /// contains genesis and contract metadata, but only the bundles and state
+    /// extensions absent in the request.
+    pub deltas: TinyOrdMap<ContractId, Contract>,
+}
+
+impl StrictSerialize for SyncResponse {}
+impl StrictDeserialize for SyncResponse {}
+
+impl SyncResponse {
+    /// Detects whether the requesting stock is already in sync.
+    pub fn is_empty(&self) -> bool { self.deltas.is_empty() }
+}
+
+/// Report on applying [`SyncResponse`] to a stock.
+#[derive(Clone, Debug, Default)]
+pub struct SyncReport {
+    /// Contracts which delta consignments were imported, with the validation
+    /// status.
+    pub imported: BTreeMap<ContractId, Status>,
+    /// Contracts which delta consignments had failed the validation and were
+    /// not imported.
+    pub rejected: BTreeMap<ContractId, Status>,
+}
+
+impl SyncReport {
+    /// Detects whether all the delta consignments were imported.
+    pub fn is_complete(&self) -> bool { self.rejected.is_empty() }
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn sync_request() {
+        let contract_id = ContractId::strict_dumb();
+        let bundle_id = BundleId::strict_dumb();
+        let opid = OpId::strict_dumb();
+        let request = SyncRequest {
+            known: TinyOrdMap::from_checked(bmap! {
+                contract_id => KnownOps {
+                    bundles: LargeOrdSet::from_checked(bset![bundle_id]),
+                    extensions: LargeOrdSet::from_checked(bset![opid]),
+                },
+            }),
+        };
+        assert!(request.knows_contract(contract_id));
+        assert!(request.knows_bundle(contract_id, bundle_id));
+        assert!(!request.knows_bundle(contract_id, BundleId::from([1u8; 32])));
+        assert!(request.knows_extension(contract_id, opid));
+        assert!(!request.knows_extension(contract_id, OpId::from([1u8; 32])));
+        assert!(!request.knows_extension(ContractId::from([1u8; 32]), opid));
+
+        let data = request
+            .to_strict_serialized::<{ u16::MAX as usize }>()
+            .unwrap();
+        assert_eq!(
+            SyncRequest::from_strict_serialized::<{ u16::MAX as usize }>(data).unwrap(),
+            request
+        );
+        assert!(SyncResponse::default().is_empty());
+    }
+}
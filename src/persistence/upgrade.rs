@@ -0,0 +1,268 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contract upgrades.
+//!
+//! When a contract can't be used anymore (for instance, due to a bug in its
+//! schema), the issuer may re-issue it as a new contract, which genesis
+//! assigns the same amounts to the same outputs as the unspent allocations of
+//! the old contract at the moment of the upgrade. The upgrade mapping
+//! published by the issuer together with the new contract links each old
+//! allocation to the corresponding genesis assignment, allowing holders to
+//! verify that their balances were migrated.
+
+use amplify::confinement::MediumVec;
+use bp::seals::txout::ExplicitSeal;
+use invoice::Amount;
+use rgb::{ContractId, Genesis, GenesisSeal, Operation, Opout, TypedAssigns, XChain, XOutputSeal};
+use strict_encoding::{StrictDeserialize, StrictSerialize};
+
+use crate::interface::BuilderError;
+use crate::LIB_NAME_RGB_STD;
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum UpgradeError {
+    /// the old contract has no allocations to migrate.
+    NoAllocations,
+
+    /// too many allocations to migrate.
+    TooManyAllocations,
+
+    /// allocation {0} of the old contract is not assigned by the new contract
+    /// genesis.
+    NotMigrated(Opout),
+
+    /// upgrade is made for contract {0} which doesn't match the provided
+    /// contract.
+    ContractMismatch(ContractId),
+
+    #[from]
+    #[display(inner)]
+    Builder(BuilderError),
+}
+
+/// Allocation of the old contract migrated to the new contract.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct UpgradeEntry {
+    /// Allocation of the old contract.
+    pub old_opout: Opout,
+    /// Genesis assignment of the new contract.
+    pub new_opout: Opout,
+    pub seal: XOutputSeal,
+    pub amount: Amount,
+}
+
+/// Mapping of the old contract allocations to the genesis assignments of the
+/// new contract.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct ContractUpgrade {
+    pub old_contract_id: ContractId,
+    pub new_contract_id: ContractId,
+    pub entries: MediumVec<UpgradeEntry>,
+}
+
+impl StrictSerialize for ContractUpgrade {}
+impl StrictDeserialize for ContractUpgrade {}
+
+impl ContractUpgrade {
+    /// Constructs the mapping by locating the genesis assignments of the new
+    /// contract to each of the migrated allocations.
+    pub fn with(
+        old_contract_id: ContractId,
+        genesis: &Genesis,
+        allocations: impl IntoIterator<Item = (Opout, XChain<GenesisSeal>, Amount)>,
+    ) -> Result<Self, UpgradeError> {
+        let assigned = genesis_allocations(genesis);
+        let mut entries = vec![];
+        for (old_opout, seal, amount) in allocations {
+            let new_opout = assigned
+                .iter()
+                .find(|(_, s, a)| *s == seal && *a == amount)
+                .map(|(opout, _, _)| *opout)
+                .ok_or(UpgradeError::NotMigrated(old_opout))?;
+            entries.push(UpgradeEntry {
+                old_opout,
+                new_opout,
+                seal: output_seal(seal),
+                amount,
+            });
+        }
+        if entries.is_empty() {
+            return Err(UpgradeError::NoAllocations);
+        }
+        Ok(ContractUpgrade {
+            old_contract_id,
+            new_contract_id: genesis.contract_id(),
+            entries: MediumVec::try_from(entries).map_err(|_| UpgradeError::TooManyAllocations)?,
+        })
+    }
+
+    /// Total amount migrated to the new contract.
+    pub fn total(&self) -> Amount { self.entries.iter().map(|entry| entry.amount).sum() }
+
+    /// Returns migration of an allocation of the old contract, if any.
+    pub fn entry(&self, old_opout: Opout) -> Option<&UpgradeEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.old_opout == old_opout)
+    }
+
+    /// Verifies that the genesis of the new contract assigns the migrated
+    /// amounts to the same seals as the mapping declares.
+    pub fn verify(&self, genesis: &Genesis) -> Result<(), UpgradeError> {
+        if genesis.contract_id() != self.new_contract_id {
+            return Err(UpgradeError::ContractMismatch(genesis.contract_id()));
+        }
+        let assigned = genesis_allocations(genesis);
+        for entry in &self.entries {
+            let migrated = assigned.iter().any(|(opout, seal, amount)| {
+                *opout == entry.new_opout
+                    && output_seal(*seal) == entry.seal
+                    && *amount == entry.amount
+            });
+            if !migrated {
+                return Err(UpgradeError::NotMigrated(entry.old_opout));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Status of the allocations migrated by a contract upgrade, as known to the
+/// stock. A migrated allocation is claimed once its holder spends it under
+/// the new contract.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct UpgradeStatus {
+    pub claimed: Vec<UpgradeEntry>,
+    pub unclaimed: Vec<UpgradeEntry>,
+}
+
+impl UpgradeStatus {
+    pub fn claimed_amount(&self) -> Amount { self.claimed.iter().map(|entry| entry.amount).sum() }
+
+    pub fn unclaimed_amount(&self) -> Amount {
+        self.unclaimed.iter().map(|entry| entry.amount).sum()
+    }
+}
+
+fn output_seal(seal: XChain<GenesisSeal>) -> XOutputSeal {
+    seal.map(|seal| ExplicitSeal::with(seal.method, seal.txid, seal.vout))
+}
+
+fn genesis_allocations(genesis: &Genesis) -> Vec<(Opout, XChain<GenesisSeal>, Amount)> {
+    let opid = genesis.id();
+    let mut allocations = vec![];
+    for (ty, assigns) in genesis.assignments.iter() {
+        let TypedAssigns::Fungible(assigns) = assigns else {
+            continue;
+        };
+        for (no, assign) in assigns.iter().enumerate() {
+            if let (Some(seal), Some(state)) = (assign.revealed_seal(), assign.as_revealed_state())
+            {
+                allocations.push((Opout::new(opid, *ty, no as u16), seal, state.value.into()));
+            }
+        }
+    }
+    allocations
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::confinement::Confined;
+    use bp::seals::txout::CloseMethod;
+    use bp::{Txid, Vout};
+    use rgb::{Assign, AssetTag, AssignmentType, Assignments, OpId, RevealedValue};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn upgrade_mapping() {
+        let seal = |no: u32| {
+            XChain::Bitcoin(GenesisSeal::new_random(
+                CloseMethod::OpretFirst,
+                Txid::from([1u8; 32]),
+                Vout::from_u32(no),
+            ))
+        };
+        let (seal1, seal2) = (seal(0), seal(1));
+        let ty = AssignmentType::with(4000);
+        let assign = |seal: XChain<GenesisSeal>, value: u64| {
+            Assign::revealed(
+                seal,
+                RevealedValue::new_random_blinding(value, AssetTag::from([2u8; 32])),
+            )
+        };
+        let mut genesis = Genesis::strict_dumb();
+        genesis.assignments = Assignments::from(Confined::from_checked(bmap! {
+            ty => TypedAssigns::Fungible(
+                Confined::from_checked(vec![assign(seal1, 10), assign(seal2, 20)])
+            )
+        }));
+
+        let old = |no: u16| Opout::new(OpId::strict_dumb(), ty, no);
+        let upgrade = ContractUpgrade::with(ContractId::strict_dumb(), &genesis, [
+            (old(5), seal2, Amount::from(20u64)),
+            (old(6), seal1, Amount::from(10u64)),
+        ])
+        .unwrap();
+        assert_eq!(upgrade.new_contract_id, genesis.contract_id());
+        assert_eq!(upgrade.total(), Amount::from(30u64));
+        assert_eq!(upgrade.entry(old(5)).unwrap().new_opout, Opout::new(genesis.id(), ty, 1));
+        assert_eq!(upgrade.verify(&genesis), Ok(()));
+
+        assert_eq!(
+            ContractUpgrade::with(ContractId::strict_dumb(), &genesis, [(
+                old(7),
+                seal1,
+                Amount::from(11u64)
+            )]),
+            Err(UpgradeError::NotMigrated(old(7)))
+        );
+
+        let mut other = genesis.clone();
+        other.timestamp += 1;
+        assert_eq!(
+            upgrade.verify(&other),
+            Err(UpgradeError::ContractMismatch(other.contract_id()))
+        );
+    }
+}
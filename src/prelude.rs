@@ -0,0 +1,60 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stable public API of the library.
+//!
+//! The module re-exports high-level types which downstream wallets are
+//! expected to build against, and is intended to be glob-imported with
+//! `use rgbstd::prelude::*`. Items re-exported here follow semantic
+//! versioning: they are not removed or changed in a backward-incompatible
+//! way within a release series. Other public items of the crate, including
+//! the ones hidden from the documentation, may change between minor
+//! releases.
+
+pub use bp::seals::txout::CloseMethod;
+pub use invoice::{
+    Allocation, Amount, Beneficiary, ChainNet, CoinAmount, InvoiceState, Pay2Vout, Precision,
+    RgbInvoice, RgbInvoiceBuilder, RgbTransport, XChainNet,
+};
+pub use rgb::validation::{ResolveWitness, Status as ValidationStatus, Validity};
+pub use rgb::vm::WitnessOrd;
+pub use rgb::{
+    ContractId, Identity, OpId, Opout, Schema, SchemaId, XChain, XOutpoint,
+    XOutputSeal, XWitnessId,
+};
+
+pub use crate::broadcast::Broadcaster;
+pub use crate::containers::{
+    Batch, BuilderSeal, Consignment, ConsignmentExt, ConsignmentId, Contract, Fascia, FileContent,
+    Kit, Transfer, UniversalFile, ValidConsignment, ValidContract, ValidKit, ValidTransfer,
+};
+pub use crate::interface::{
+    AllocatedState, ContractBuilder, ContractIface, ContractOp, FungibleAllocation, Iface,
+    IfaceClass, IfaceId, IfaceImpl, IfaceWrapper, ImplId, OwnedAllocation, TransitionBuilder,
+};
+#[cfg(feature = "fs")]
+pub use crate::persistence::fs::FsBinStore;
+pub use crate::persistence::{
+    ComposeError, ConsignError, Index, IndexProvider, MemIndex, MemStash, MemState, Stash,
+    StashProvider, State, StateProvider, Stock, StockError, UpdateRes,
+};
+pub use crate::service::Service;
+pub use crate::{Outpoint, Txid};
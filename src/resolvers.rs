@@ -19,12 +19,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use amplify::confinement::MediumOrdMap;
+use chrono::Utc;
+#[cfg(feature = "fs")]
+use nonasync::persistence::PersistenceError;
 use rgb::validation::{ResolveWitness, WitnessResolverError};
 use rgb::vm::{WitnessOrd, XWitnessId, XWitnessTx};
+use rgb::ContractId;
+use strict_encoding::{StrictDeserialize, StrictSerialize};
 
 use crate::containers::IndexedConsignment;
-
-// TODO: Implement caching witness resolver
+use crate::LIB_NAME_RGB_STORAGE;
 
 pub(crate) struct ConsignmentResolver<'cons, R: ResolveWitness, const TRANSFER: bool> {
     pub consignment: &'cons IndexedConsignment<'cons, TRANSFER>,
@@ -52,3 +62,744 @@ impl<'cons, R: ResolveWitness, const TRANSFER: bool> ResolveWitness
         self.fallback.resolve_pub_witness_ord(witness_id)
     }
 }
+
+/// Resolver reporting the given witnesses as archived, which is used to
+/// exclude losing branches of conflicting state transitions from the contract
+/// state.
+pub(crate) struct ArchivingResolver<'archive, R: ResolveWitness> {
+    pub archived: &'archive BTreeSet<XWitnessId>,
+    pub fallback: R,
+}
+
+impl<'archive, R: ResolveWitness> ResolveWitness for ArchivingResolver<'archive, R> {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.fallback.resolve_pub_witness(witness_id)
+    }
+
+    fn resolve_pub_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        if self.archived.contains(&witness_id) {
+            return Ok(WitnessOrd::Archived);
+        }
+        self.fallback.resolve_pub_witness_ord(witness_id)
+    }
+}
+
+/// Resolver serving witness ordering resolved in advance, which allows to
+/// query slow resolvers before any stock data are modified.
+pub(crate) struct PrefetchedResolver<R: ResolveWitness> {
+    pub ords: BTreeMap<XWitnessId, WitnessOrd>,
+    pub fallback: R,
+}
+
+impl<R: ResolveWitness> ResolveWitness for PrefetchedResolver<R> {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.fallback.resolve_pub_witness(witness_id)
+    }
+
+    fn resolve_pub_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        match self.ords.get(&witness_id) {
+            Some(ord) => Ok(*ord),
+            None => self.fallback.resolve_pub_witness_ord(witness_id),
+        }
+    }
+}
+
+/// Ordering of a mined witness transaction cached at some moment.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STORAGE)]
+pub struct CachedOrd {
+    pub ord: WitnessOrd,
+    /// Unix timestamp at which the ordering was resolved.
+    pub timestamp: i64,
+}
+
+/// Cache of witness transactions and their ordering, which can be shared
+/// between validations and persisted between the program runs.
+///
+/// Witness transactions never change, thus they are kept until removed with
+/// [`ResolverCache::invalidate`]. Ordering of the mined witnesses expires
+/// after TTL and on reorgs, reported by [`ResolverCache::invalidate_above`];
+/// tentative and archived witnesses are never cached.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[derive(StrictType, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STORAGE)]
+pub struct ResolverCache {
+    /// Time in seconds after which the cached witness ordering expires.
+    pub ttl: u32,
+    txs: MediumOrdMap<XWitnessId, XWitnessTx>,
+    ords: MediumOrdMap<XWitnessId, CachedOrd>,
+}
+
+impl StrictSerialize for ResolverCache {}
+impl StrictDeserialize for ResolverCache {}
+
+impl ResolverCache {
+    pub fn with_ttl(ttl: u32) -> Self { Self { ttl, ..default!() } }
+
+    /// Loads the cache from a file.
+    #[cfg(feature = "fs")]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, PersistenceError> {
+        Self::strict_deserialize_from_file::<{ amplify::confinement::U32 }>(path)
+            .map_err(PersistenceError::with)
+    }
+
+    /// Stores the cache to a file.
+    #[cfg(feature = "fs")]
+    pub fn store(&self, path: impl AsRef<std::path::Path>) -> Result<(), PersistenceError> {
+        self.strict_serialize_to_file::<{ amplify::confinement::U32 }>(path)
+            .map_err(PersistenceError::with)
+    }
+
+    pub fn tx(&self, witness_id: XWitnessId) -> Option<&XWitnessTx> { self.txs.get(&witness_id) }
+
+    /// Returns cached ordering of a witness unless it has expired at the
+    /// given time.
+    pub fn ord(&self, witness_id: XWitnessId, now: i64) -> Option<WitnessOrd> {
+        self.ords
+            .get(&witness_id)
+            .filter(|cached| now.saturating_sub(cached.timestamp) <= self.ttl as i64)
+            .map(|cached| cached.ord)
+    }
+
+    /// Caches witness transaction. The cache is not updated once it is full.
+    pub fn insert_tx(&mut self, witness_id: XWitnessId, tx: XWitnessTx) {
+        self.txs.insert(witness_id, tx).ok();
+    }
+
+    /// Caches ordering of a mined witness transaction. The cache is not
+    /// updated once it is full.
+    pub fn insert_ord(&mut self, witness_id: XWitnessId, ord: WitnessOrd, now: i64) {
+        if matches!(ord, WitnessOrd::Mined(_)) {
+            self.ords
+                .insert(witness_id, CachedOrd {
+                    ord,
+                    timestamp: now,
+                })
+                .ok();
+        }
+    }
+
+    /// Removes all cached data for a witness.
+    pub fn invalidate(&mut self, witness_id: XWitnessId) {
+        self.txs.remove(&witness_id).ok();
+        self.ords.remove(&witness_id).ok();
+    }
+
+    /// Removes cached ordering of the witnesses mined above the given height,
+    /// which must be called once a reorg is detected.
+    pub fn invalidate_above(&mut self, height: u32) {
+        let ids = self
+            .ords
+            .iter()
+            .filter(|(_, cached)| {
+                matches!(cached.ord, WitnessOrd::Mined(pos) if pos.height().get() > height)
+            })
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            self.ords.remove(&id).ok();
+        }
+    }
+
+    /// Removes expired witness ordering from the cache.
+    pub fn prune(&mut self, now: i64) {
+        let ttl = self.ttl as i64;
+        let ids = self
+            .ords
+            .iter()
+            .filter(|(_, cached)| now.saturating_sub(cached.timestamp) > ttl)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in ids {
+            self.ords.remove(&id).ok();
+        }
+    }
+}
+
+/// Resolver serving witness transactions and their ordering from the cache,
+/// querying the fallback resolver only for the data which are missed or
+/// expired.
+pub struct CachingResolver<R: ResolveWitness> {
+    cache: RefCell<ResolverCache>,
+    pub fallback: R,
+}
+
+impl<R: ResolveWitness> CachingResolver<R> {
+    pub fn new(fallback: R, cache: ResolverCache) -> Self {
+        Self {
+            cache: RefCell::new(cache),
+            fallback,
+        }
+    }
+
+    pub fn cache(&self) -> Ref<'_, ResolverCache> { self.cache.borrow() }
+
+    pub fn cache_mut(&self) -> RefMut<'_, ResolverCache> { self.cache.borrow_mut() }
+
+    pub fn into_cache(self) -> ResolverCache { self.cache.into_inner() }
+}
+
+impl<R: ResolveWitness> ResolveWitness for CachingResolver<R> {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        if let Some(tx) = self.cache.borrow().tx(witness_id) {
+            return Ok(tx.clone());
+        }
+        let tx = self.fallback.resolve_pub_witness(witness_id)?;
+        self.cache.borrow_mut().insert_tx(witness_id, tx.clone());
+        Ok(tx)
+    }
+
+    fn resolve_pub_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        let now = Utc::now().timestamp();
+        if let Some(ord) = self.cache.borrow().ord(witness_id, now) {
+            return Ok(ord);
+        }
+        let ord = self.fallback.resolve_pub_witness_ord(witness_id)?;
+        self.cache.borrow_mut().insert_ord(witness_id, ord, now);
+        Ok(ord)
+    }
+}
+
+/// Resolver enforcing minimum number of confirmations for the witness
+/// transactions.
+///
+/// Witnesses mined less than the required number of blocks below the
+/// provided chain tip, as well as the ones which are not mined yet, are
+/// reported as archived, such that the state they assign is excluded from the
+/// contract state. Provisionally accepted witnesses are finalized by
+/// `Stock::update_confirmations`, which applies this resolver for a newer
+/// chain tip.
+pub struct ConfirmationResolver<R: ResolveWitness> {
+    pub fallback: R,
+    pub min_confirmations: u32,
+    pub tip_height: u32,
+}
+
+impl<R: ResolveWitness> ConfirmationResolver<R> {
+    pub fn new(fallback: R, min_confirmations: u32, tip_height: u32) -> Self {
+        Self {
+            fallback,
+            min_confirmations,
+            tip_height,
+        }
+    }
+
+    /// Returns number of confirmations for a witness, which is zero for
+    /// witnesses not mined yet.
+    pub fn confirmations(&self, witness_id: XWitnessId) -> Result<u32, WitnessResolverError> {
+        Ok(match self.fallback.resolve_pub_witness_ord(witness_id)? {
+            WitnessOrd::Mined(pos) => self
+                .tip_height
+                .checked_sub(pos.height().get())
+                .map_or(0, |depth| depth.saturating_add(1)),
+            WitnessOrd::Tentative | WitnessOrd::Archived => 0,
+        })
+    }
+
+    /// Detects whether the witness has the required number of confirmations.
+    pub fn is_confirmed(&self, witness_id: XWitnessId) -> Result<bool, WitnessResolverError> {
+        if self.min_confirmations == 0 {
+            return Ok(true);
+        }
+        self.confirmations(witness_id)
+            .map(|confirmations| confirmations >= self.min_confirmations)
+    }
+}
+
+impl<R: ResolveWitness> ResolveWitness for ConfirmationResolver<R> {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.fallback.resolve_pub_witness(witness_id)
+    }
+
+    fn resolve_pub_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        if !self.is_confirmed(witness_id)? {
+            return Ok(WitnessOrd::Archived);
+        }
+        self.fallback.resolve_pub_witness_ord(witness_id)
+    }
+}
+
+/// Discrepancy in the responses of the resolvers combined by
+/// [`QuorumResolver`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Discrepancy {
+    pub witness_id: XWitnessId,
+    /// Indexes of the resolvers which have failed or which responses differ
+    /// from the one agreed by the majority.
+    pub dissenters: Vec<usize>,
+    /// Whether the quorum was reached despite the discrepancy.
+    pub quorum_reached: bool,
+}
+
+/// Resolver combining multiple witness resolvers (for instance, several
+/// Esplora and Electrum servers) and requiring a given number of them to
+/// agree on the witness transaction and its ordering, protecting the
+/// validation against a single lying or stale chain source.
+///
+/// Each response which differs from the agreed one is recorded as a
+/// [`Discrepancy`]; if no quorum is reached the resolution fails.
+pub struct QuorumResolver {
+    resolvers: Vec<Box<dyn ResolveWitness>>,
+    quorum: usize,
+    discrepancies: RefCell<Vec<Discrepancy>>,
+}
+
+impl QuorumResolver {
+    /// Constructs resolver requiring `quorum` of the provided resolvers to
+    /// agree on each response.
+    ///
+    /// # Panics
+    ///
+    /// If the quorum is zero or exceeds the number of resolvers.
+    pub fn new(
+        resolvers: impl IntoIterator<Item = Box<dyn ResolveWitness>>,
+        quorum: usize,
+    ) -> Self {
+        let resolvers = resolvers.into_iter().collect::<Vec<_>>();
+        assert!(
+            quorum > 0 && quorum <= resolvers.len(),
+            "quorum {quorum} is not achievable with {} resolvers",
+            resolvers.len()
+        );
+        Self {
+            resolvers,
+            quorum,
+            discrepancies: empty!(),
+        }
+    }
+
+    /// Constructs resolver requiring the majority of the provided resolvers
+    /// to agree on each response.
+    pub fn majority(resolvers: impl IntoIterator<Item = Box<dyn ResolveWitness>>) -> Self {
+        let resolvers = resolvers.into_iter().collect::<Vec<_>>();
+        let quorum = resolvers.len() / 2 + 1;
+        Self::new(resolvers, quorum)
+    }
+
+    pub fn quorum(&self) -> usize { self.quorum }
+
+    /// Returns discrepancies detected so far.
+    pub fn discrepancies(&self) -> Vec<Discrepancy> { self.discrepancies.borrow().clone() }
+
+    /// Returns discrepancies detected so far and clears them.
+    pub fn take_discrepancies(&self) -> Vec<Discrepancy> { self.discrepancies.take() }
+
+    fn vote<T: Eq>(
+        &self,
+        witness_id: XWitnessId,
+        query: impl Fn(&dyn ResolveWitness) -> Result<T, WitnessResolverError>,
+    ) -> Result<T, WitnessResolverError> {
+        let responses = self
+            .resolvers
+            .iter()
+            .map(|resolver| query(resolver.as_ref()))
+            .collect::<Vec<_>>();
+
+        let mut best = None;
+        let mut best_votes = 0;
+        for (index, response) in responses.iter().enumerate() {
+            let Ok(value) = response else {
+                continue;
+            };
+            let votes = responses
+                .iter()
+                .filter(|other| matches!(other, Ok(other) if other == value))
+                .count();
+            if votes > best_votes {
+                best = Some(index);
+                best_votes = votes;
+            }
+        }
+
+        let quorum_reached = best_votes >= self.quorum;
+        let dissenters = responses
+            .iter()
+            .enumerate()
+            .filter(|(_, response)| match (response, best) {
+                (Ok(value), Some(best)) => Some(value) != responses[best].as_ref().ok(),
+                _ => true,
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let unknown = responses
+            .iter()
+            .all(|response| matches!(response, Err(WitnessResolverError::Unknown(_))));
+        if !dissenters.is_empty() && !unknown {
+            self.discrepancies.borrow_mut().push(Discrepancy {
+                witness_id,
+                dissenters,
+                quorum_reached,
+            });
+        }
+
+        match best {
+            Some(best) if quorum_reached => Ok(responses
+                .into_iter()
+                .nth(best)
+                .and_then(Result::ok)
+                .expect("best response is always a success")),
+            _ if unknown => Err(WitnessResolverError::Unknown(witness_id)),
+            _ => Err(WitnessResolverError::Other(
+                witness_id,
+                format!(
+                    "no quorum among witness resolvers: {best_votes} of {} required responses \
+                     agree",
+                    self.quorum
+                ),
+            )),
+        }
+    }
+}
+
+impl ResolveWitness for QuorumResolver {
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.vote(witness_id, |resolver| resolver.resolve_pub_witness(witness_id))
+    }
+
+    fn resolve_pub_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        self.vote(witness_id, |resolver| resolver.resolve_pub_witness_ord(witness_id))
+    }
+}
+
+/// Progress of a long-running operation, like validation and acceptance of
+/// large consignments.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum Progress {
+    /// Validation of a consignment for the contract has started.
+    Validating(ContractId),
+    /// Number of bundles processed out of their total number.
+    Bundles { processed: usize, total: usize },
+    /// Validated data are being stored.
+    Storing,
+}
+
+/// Receiver of the progress reports of long-running operations, which may
+/// also request the operation to be aborted.
+pub trait ProgressSink {
+    fn report(&self, progress: Progress);
+
+    /// Detects whether the operation must be aborted.
+    fn is_cancelled(&self) -> bool { false }
+}
+
+impl ProgressSink for () {
+    fn report(&self, _: Progress) {}
+}
+
+impl<F: Fn(Progress)> ProgressSink for F {
+    fn report(&self, progress: Progress) { self(progress) }
+}
+
+impl ProgressSink for CancellationToken {
+    fn report(&self, _: Progress) {}
+
+    fn is_cancelled(&self) -> bool { CancellationToken::is_cancelled(self) }
+}
+
+impl<F: Fn(Progress)> ProgressSink for (CancellationToken, F) {
+    fn report(&self, progress: Progress) { (self.1)(progress) }
+
+    fn is_cancelled(&self) -> bool { self.0.is_cancelled() }
+}
+
+/// Token which can be shared between threads for cancelling an operation
+/// reporting its progress to a [`ProgressSink`].
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self { Self::default() }
+
+    /// Requests cancellation of the operation.
+    pub fn cancel(&self) { self.0.store(true, Ordering::Relaxed) }
+
+    pub fn is_cancelled(&self) -> bool { self.0.load(Ordering::Relaxed) }
+}
+
+/// Resolver reporting the number of bundles whose witnesses it was asked
+/// about to the progress sink.
+///
+/// Once the sink reports cancellation, all further requests fail, such that
+/// validation or acceptance stops querying the fallback resolver and results
+/// in an error.
+pub struct ProgressResolver<'sink, R: ResolveWitness, S: ProgressSink + ?Sized> {
+    fallback: R,
+    sink: &'sink S,
+    bundles: BTreeMap<XWitnessId, usize>,
+    total: usize,
+    processed: RefCell<BTreeSet<XWitnessId>>,
+}
+
+impl<'sink, R: ResolveWitness, S: ProgressSink + ?Sized> ProgressResolver<'sink, R, S> {
+    /// Constructs resolver for the operation processing bundles with the
+    /// provided witnesses, one witness id per bundle.
+    pub fn new(
+        fallback: R,
+        sink: &'sink S,
+        bundle_witnesses: impl IntoIterator<Item = XWitnessId>,
+    ) -> Self {
+        let mut bundles = BTreeMap::<_, usize>::new();
+        let mut total = 0;
+        for witness_id in bundle_witnesses {
+            *bundles.entry(witness_id).or_default() += 1;
+            total += 1;
+        }
+        Self {
+            fallback,
+            sink,
+            bundles,
+            total,
+            processed: none!(),
+        }
+    }
+
+    /// Returns the number of bundles processed so far.
+    pub fn processed(&self) -> usize {
+        self.processed
+            .borrow()
+            .iter()
+            .filter_map(|witness_id| self.bundles.get(witness_id))
+            .sum()
+    }
+
+    fn track(&self, witness_id: XWitnessId) -> Result<(), WitnessResolverError> {
+        if self.sink.is_cancelled() {
+            return Err(WitnessResolverError::Other(witness_id, s!("operation is cancelled")));
+        }
+        if self.bundles.contains_key(&witness_id) && self.processed.borrow_mut().insert(witness_id)
+        {
+            self.sink.report(Progress::Bundles {
+                processed: self.processed(),
+                total: self.total,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<'sink, R: ResolveWitness, S: ProgressSink + ?Sized> ResolveWitness
+    for ProgressResolver<'sink, R, S>
+{
+    fn resolve_pub_witness(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<XWitnessTx, WitnessResolverError> {
+        self.track(witness_id)?;
+        self.fallback.resolve_pub_witness(witness_id)
+    }
+
+    fn resolve_pub_witness_ord(
+        &self,
+        witness_id: XWitnessId,
+    ) -> Result<WitnessOrd, WitnessResolverError> {
+        self.track(witness_id)?;
+        self.fallback.resolve_pub_witness_ord(witness_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bp::Txid;
+    use rgb::vm::WitnessPos;
+    use rgb::XChain;
+
+    use super::*;
+
+    struct FixedResolver(WitnessOrd);
+
+    impl ResolveWitness for FixedResolver {
+        fn resolve_pub_witness(&self, id: XWitnessId) -> Result<XWitnessTx, WitnessResolverError> {
+            Err(WitnessResolverError::Unknown(id))
+        }
+
+        fn resolve_pub_witness_ord(
+            &self,
+            _: XWitnessId,
+        ) -> Result<WitnessOrd, WitnessResolverError> {
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn caching() {
+        struct CountingResolver(RefCell<usize>, WitnessOrd);
+        impl ResolveWitness for CountingResolver {
+            fn resolve_pub_witness(
+                &self,
+                id: XWitnessId,
+            ) -> Result<XWitnessTx, WitnessResolverError> {
+                Err(WitnessResolverError::Unknown(id))
+            }
+            fn resolve_pub_witness_ord(
+                &self,
+                _: XWitnessId,
+            ) -> Result<WitnessOrd, WitnessResolverError> {
+                *self.0.borrow_mut() += 1;
+                Ok(self.1)
+            }
+        }
+
+        let id = XChain::Bitcoin(Txid::from([1u8; 32]));
+        let mined = WitnessOrd::Mined(WitnessPos::new(100, 1231006505).unwrap());
+        let resolver = CachingResolver::new(
+            CountingResolver(RefCell::new(0), mined),
+            ResolverCache::with_ttl(3600),
+        );
+        assert_eq!(resolver.resolve_pub_witness_ord(id).unwrap(), mined);
+        assert_eq!(resolver.resolve_pub_witness_ord(id).unwrap(), mined);
+        assert_eq!(*resolver.fallback.0.borrow(), 1);
+        assert!(resolver.resolve_pub_witness(id).is_err());
+
+        resolver.cache_mut().invalidate_above(100);
+        assert!(resolver.cache().ord(id, 0).is_some());
+        resolver.cache_mut().invalidate_above(99);
+        resolver.resolve_pub_witness_ord(id).unwrap();
+        assert_eq!(*resolver.fallback.0.borrow(), 2);
+
+        let mut cache = resolver.into_cache();
+        assert_eq!(cache.ord(id, i64::MAX), None);
+        cache.prune(i64::MAX);
+        assert_eq!(
+            cache,
+            ResolverCache::from_strict_serialized::<{ usize::MAX }>(
+                cache.to_strict_serialized::<{ usize::MAX }>().unwrap()
+            )
+            .unwrap()
+        );
+        assert_eq!(cache, ResolverCache::with_ttl(3600));
+
+        let resolver = CachingResolver::new(
+            CountingResolver(RefCell::new(0), WitnessOrd::Tentative),
+            ResolverCache::with_ttl(3600),
+        );
+        resolver.resolve_pub_witness_ord(id).unwrap();
+        resolver.resolve_pub_witness_ord(id).unwrap();
+        assert_eq!(*resolver.fallback.0.borrow(), 2);
+    }
+
+    #[test]
+    fn min_confirmations() {
+        let id = XChain::Bitcoin(Txid::from([1u8; 32]));
+        let mined = WitnessOrd::Mined(WitnessPos::new(100, 1231006505).unwrap());
+
+        let resolver = ConfirmationResolver::new(FixedResolver(mined), 6, 104);
+        assert_eq!(resolver.confirmations(id).unwrap(), 5);
+        assert_eq!(resolver.resolve_pub_witness_ord(id).unwrap(), WitnessOrd::Archived);
+
+        let resolver = ConfirmationResolver::new(FixedResolver(mined), 6, 105);
+        assert_eq!(resolver.resolve_pub_witness_ord(id).unwrap(), mined);
+
+        let resolver = ConfirmationResolver::new(FixedResolver(mined), 6, 99);
+        assert_eq!(resolver.confirmations(id).unwrap(), 0);
+        let tip = WitnessOrd::Mined(WitnessPos::new(u32::MAX, 1231006505).unwrap());
+        let resolver = ConfirmationResolver::new(FixedResolver(tip), 1, u32::MAX);
+        assert_eq!(resolver.confirmations(id).unwrap(), 1);
+        let resolver = ConfirmationResolver::new(FixedResolver(mined), 6, u32::MAX);
+        assert_eq!(resolver.confirmations(id).unwrap(), u32::MAX - 99);
+
+        let resolver = ConfirmationResolver::new(FixedResolver(WitnessOrd::Tentative), 0, 105);
+        assert_eq!(resolver.resolve_pub_witness_ord(id).unwrap(), WitnessOrd::Tentative);
+    }
+
+    #[test]
+    fn quorum() {
+        let id = XChain::Bitcoin(Txid::from([1u8; 32]));
+        let mined = WitnessOrd::Mined(WitnessPos::new(100, 1231006505).unwrap());
+        let resolvers = |ords: &[WitnessOrd]| {
+            ords.iter()
+                .map(|ord| Box::new(FixedResolver(*ord)) as Box<dyn ResolveWitness>)
+                .collect::<Vec<_>>()
+        };
+
+        let resolver = QuorumResolver::majority(resolvers(&[mined, mined, mined]));
+        assert_eq!(resolver.resolve_pub_witness_ord(id).unwrap(), mined);
+        assert!(resolver.discrepancies().is_empty());
+        assert_eq!(resolver.resolve_pub_witness(id), Err(WitnessResolverError::Unknown(id)));
+        assert!(resolver.discrepancies().is_empty());
+
+        let resolver = QuorumResolver::majority(resolvers(&[mined, WitnessOrd::Tentative, mined]));
+        assert_eq!(resolver.resolve_pub_witness_ord(id).unwrap(), mined);
+        assert_eq!(resolver.take_discrepancies(), vec![Discrepancy {
+            witness_id: id,
+            dissenters: vec![1],
+            quorum_reached: true,
+        }]);
+        assert!(resolver.discrepancies().is_empty());
+
+        let resolver = QuorumResolver::new(resolvers(&[mined, WitnessOrd::Tentative]), 2);
+        assert!(matches!(
+            resolver.resolve_pub_witness_ord(id),
+            Err(WitnessResolverError::Other(..))
+        ));
+        assert!(!resolver.discrepancies()[0].quorum_reached);
+    }
+
+    #[test]
+    fn progress() {
+        let ids = [1u8, 2, 3].map(|no| XChain::Bitcoin(Txid::from([no; 32])));
+        let reports = RefCell::new(vec![]);
+        let cancel = CancellationToken::new();
+        let sink = (cancel.clone(), |progress| reports.borrow_mut().push(progress));
+        let resolver = ProgressResolver::new(FixedResolver(WitnessOrd::Tentative), &sink, [
+            ids[0], ids[1], ids[0],
+        ]);
+        resolver.resolve_pub_witness_ord(ids[0]).unwrap();
+        resolver.resolve_pub_witness(ids[0]).unwrap_err();
+        resolver.resolve_pub_witness_ord(ids[2]).unwrap();
+        resolver.resolve_pub_witness_ord(ids[1]).unwrap();
+        assert_eq!(resolver.processed(), 3);
+        assert_eq!(reports.borrow().as_slice(), &[
+            Progress::Bundles {
+                processed: 2,
+                total: 3
+            },
+            Progress::Bundles {
+                processed: 3,
+                total: 3
+            }
+        ]);
+
+        cancel.cancel();
+        assert!(matches!(
+            resolver.resolve_pub_witness_ord(ids[1]),
+            Err(WitnessResolverError::Other(..))
+        ));
+        assert_eq!(reports.borrow().len(), 2);
+    }
+}
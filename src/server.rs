@@ -0,0 +1,355 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JSON-RPC 2.0 server layer over the [`Service`] facade.
+//!
+//! The server is transport-agnostic: the integrator receives JSON-RPC
+//! requests over HTTP, WebSocket or a UNIX socket, passes them to
+//! [`RpcServer::handle`] together with the credentials presented by the
+//! client, and sends back the returned response.
+//!
+//! Supported methods and their named parameters:
+//! - `listAssets`;
+//! - `balance`: `contractId`, `outpoints`;
+//! - `history`: `contractId`;
+//! - `newInvoice`: `contractId`, `outpoint`, `amount`, `closeMethod`;
+//! - `send`: `contractId`, `outputs`, `secretSeal`, returning ASCII-armored transfer consignment;
+//! - `accept`: `consignment` with ASCII-armored transfer consignment, returning validation report.
+
+use std::str::FromStr;
+
+use rgb::validation::ResolveWitness;
+use rgb::{ContractId, SecretSeal, XChain, XOutpoint, XOutputSeal};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::containers::{Transfer, ValidationReport};
+use crate::persistence::{IndexProvider, MemIndex, MemStash, MemState, StashProvider, StateProvider};
+use crate::service::{Request, Response, Service};
+use crate::Amount;
+
+/// JSON-RPC error code for invalid JSON.
+pub const RPC_PARSE_ERROR: i64 = -32700;
+/// JSON-RPC error code for a malformed request object.
+pub const RPC_INVALID_REQUEST: i64 = -32600;
+/// JSON-RPC error code for an unknown method.
+pub const RPC_METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for invalid method parameters.
+pub const RPC_INVALID_PARAMS: i64 = -32602;
+/// Error code for failed operations.
+pub const RPC_OPERATION_FAILED: i64 = -32000;
+/// Error code for requests rejected by the [`RpcAuth`].
+pub const RPC_UNAUTHORIZED: i64 = -32001;
+/// Error code for consignments or kits which have failed validation; error
+/// data contain the [`ValidationReport`].
+pub const RPC_VALIDATION_FAILED: i64 = -32002;
+/// Error code for cancelled requests.
+pub const RPC_CANCELLED: i64 = -32003;
+
+/// Authentication hook for the [`RpcServer`].
+pub trait RpcAuth {
+    /// Checks whether a client presenting the credentials (like bearer token
+    /// from the HTTP `Authorization` header) is allowed to call the method.
+    fn authorize(&self, credentials: Option<&str>, method: &str) -> bool;
+}
+
+/// Authentication allowing all requests, for the servers which are reachable
+/// only by trusted clients.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct NoAuth;
+
+impl RpcAuth for NoAuth {
+    fn authorize(&self, _: Option<&str>, _: &str) -> bool { true }
+}
+
+/// Authentication requiring clients to present a static token.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TokenAuth(String);
+
+impl TokenAuth {
+    pub fn new(token: impl ToString) -> Self { TokenAuth(token.to_string()) }
+}
+
+impl RpcAuth for TokenAuth {
+    fn authorize(&self, credentials: Option<&str>, _: &str) -> bool {
+        let Some(credentials) = credentials else {
+            return false;
+        };
+        // Constant-time comparison, not leaking the matching prefix length
+        credentials.len() == self.0.len()
+            && credentials
+                .bytes()
+                .zip(self.0.bytes())
+                .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+}
+
+impl<F: Fn(Option<&str>, &str) -> bool> RpcAuth for F {
+    fn authorize(&self, credentials: Option<&str>, method: &str) -> bool {
+        self(credentials, method)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase")]
+struct ContractParams {
+    contract_id: ContractId,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase")]
+struct BalanceParams {
+    contract_id: ContractId,
+    outpoints: Vec<XOutpoint>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase")]
+struct InvoiceParams {
+    contract_id: ContractId,
+    outpoint: XOutpoint,
+    amount: Amount,
+    close_method: bp::seals::txout::CloseMethod,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase")]
+struct SendParams {
+    contract_id: ContractId,
+    outputs: Vec<XOutputSeal>,
+    secret_seal: Option<XChain<SecretSeal>>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "serde_crate", rename_all = "camelCase")]
+struct AcceptParams {
+    consignment: String,
+}
+
+struct RpcFailure {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcFailure {
+    fn new(code: i64, message: impl ToString) -> Self {
+        RpcFailure {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+}
+
+/// JSON-RPC 2.0 server processing requests with a [`Service`].
+#[derive(Debug)]
+pub struct RpcServer<
+    R: ResolveWitness,
+    A: RpcAuth,
+    S: StashProvider = MemStash,
+    H: StateProvider = MemState,
+    P: IndexProvider = MemIndex,
+> {
+    service: Service<R, S, H, P>,
+    auth: A,
+}
+
+impl<R: ResolveWitness, A: RpcAuth, S: StashProvider, H: StateProvider, P: IndexProvider>
+    RpcServer<R, A, S, H, P>
+{
+    pub fn new(service: Service<R, S, H, P>, auth: A) -> Self { RpcServer { service, auth } }
+
+    pub fn service(&self) -> &Service<R, S, H, P> { &self.service }
+
+    pub fn into_service(self) -> Service<R, S, H, P> { self.service }
+
+    /// Processes JSON-RPC request or a batch of requests.
+    ///
+    /// Returns `None` if no response must be sent, which happens for
+    /// notifications (requests without an id).
+    pub fn handle(&mut self, request: &str, credentials: Option<&str>) -> Option<String> {
+        let response = match serde_json::from_str::<Value>(request) {
+            Err(err) => Some(failure(Value::Null, RpcFailure::new(RPC_PARSE_ERROR, err))),
+            Ok(Value::Array(batch)) if batch.is_empty() => {
+                Some(failure(Value::Null, RpcFailure::new(RPC_INVALID_REQUEST, "empty batch")))
+            }
+            Ok(Value::Array(batch)) => {
+                let responses = batch
+                    .into_iter()
+                    .filter_map(|request| self.handle_value(request, credentials))
+                    .collect::<Vec<_>>();
+                (!responses.is_empty()).then_some(Value::Array(responses))
+            }
+            Ok(request) => self.handle_value(request, credentials),
+        };
+        response.map(|response| response.to_string())
+    }
+
+    fn handle_value(&mut self, request: Value, credentials: Option<&str>) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let res = self.dispatch(&request, credentials);
+        let id = id?;
+        Some(match res {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err(err) => failure(id, err),
+        })
+    }
+
+    fn dispatch(
+        &mut self,
+        request: &Value,
+        credentials: Option<&str>,
+    ) -> Result<Value, RpcFailure> {
+        if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            return Err(RpcFailure::new(RPC_INVALID_REQUEST, "invalid JSON-RPC version"));
+        }
+        let method = request
+            .get("method")
+            .and_then(Value::as_str)
+            .ok_or_else(|| RpcFailure::new(RPC_INVALID_REQUEST, "missed method"))?;
+        if !self.auth.authorize(credentials, method) {
+            return Err(RpcFailure::new(RPC_UNAUTHORIZED, "unauthorized"));
+        }
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let request = match method {
+            "listAssets" => Request::Contracts,
+            "balance" => {
+                let params = parse_params::<BalanceParams>(params)?;
+                Request::Balance {
+                    contract_id: params.contract_id,
+                    outpoints: params.outpoints,
+                }
+            }
+            "history" => Request::History(parse_params::<ContractParams>(params)?.contract_id),
+            "newInvoice" => {
+                let params = parse_params::<InvoiceParams>(params)?;
+                Request::NewInvoice {
+                    contract_id: params.contract_id,
+                    outpoint: params.outpoint,
+                    amount: params.amount,
+                    close_method: params.close_method,
+                }
+            }
+            "send" => {
+                let params = parse_params::<SendParams>(params)?;
+                Request::Transfer {
+                    contract_id: params.contract_id,
+                    outputs: params.outputs,
+                    secret_seal: params.secret_seal,
+                }
+            }
+            "accept" => {
+                let params = parse_params::<AcceptParams>(params)?;
+                let transfer = Transfer::from_str(&params.consignment)
+                    .map_err(|err| RpcFailure::new(RPC_INVALID_PARAMS, err))?;
+                Request::AcceptTransfer(transfer)
+            }
+            _ => return Err(RpcFailure::new(RPC_METHOD_NOT_FOUND, "method not found")),
+        };
+
+        match self.service.handle(request) {
+            Response::Contracts(contracts) => to_value(contracts),
+            Response::Balance(amount) => to_value(amount),
+            Response::History(history) => to_value(history),
+            Response::Invoice(invoice) => Ok(Value::String(invoice)),
+            Response::Transfer(transfer) => Ok(Value::String(transfer.to_string())),
+            Response::Imported(status) => to_value(ValidationReport::from(status)),
+            Response::Invalid(status) => Err(RpcFailure {
+                data: Some(to_value(ValidationReport::from(status))?),
+                ..RpcFailure::new(RPC_VALIDATION_FAILED, "validation failed")
+            }),
+            Response::Cancelled => Err(RpcFailure::new(RPC_CANCELLED, "cancelled")),
+            Response::Denied { permission, .. } => Err(RpcFailure::new(
+                RPC_UNAUTHORIZED,
+                format!("{permission} permission is required"),
+            )),
+            Response::Failure(err) => Err(RpcFailure::new(RPC_OPERATION_FAILED, err)),
+            other => unreachable!("unexpected service response {other:?}"),
+        }
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcFailure> {
+    serde_json::from_value(params).map_err(|err| RpcFailure::new(RPC_INVALID_PARAMS, err))
+}
+
+fn to_value(data: impl serde::Serialize) -> Result<Value, RpcFailure> {
+    serde_json::to_value(data).map_err(|err| RpcFailure::new(RPC_OPERATION_FAILED, err))
+}
+
+fn failure(id: Value, err: RpcFailure) -> Value {
+    let mut error = json!({ "code": err.code, "message": err.message });
+    if let Some(data) = err.data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "error": error, "id": id })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interface::resolver::DumbResolver;
+    use crate::persistence::Stock;
+
+    fn call(server: &mut RpcServer<DumbResolver, TokenAuth>, request: &str) -> Value {
+        let response = server.handle(request, Some("secret")).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn json_rpc() {
+        let service = Service::new(Stock::in_memory(), DumbResolver, true);
+        let mut server = RpcServer::new(service, TokenAuth::new("secret"));
+
+        assert_eq!(
+            call(&mut server, r#"{"jsonrpc":"2.0","method":"listAssets","id":1}"#),
+            json!({ "jsonrpc": "2.0", "result": [], "id": 1 })
+        );
+        assert_eq!(
+            call(&mut server, r#"{"jsonrpc":"2.0","method":"mint","id":2}"#)["error"]["code"],
+            RPC_METHOD_NOT_FOUND
+        );
+        assert_eq!(
+            call(&mut server, r#"{"jsonrpc":"2.0","method":"history","params":{},"id":3}"#)
+                ["error"]["code"],
+            RPC_INVALID_PARAMS
+        );
+        assert_eq!(call(&mut server, "{")["error"]["code"], RPC_PARSE_ERROR);
+        assert_eq!(
+            call(
+                &mut server,
+                r#"[{"jsonrpc":"2.0","method":"listAssets","id":4},
+                    {"jsonrpc":"2.0","method":"listAssets"}]"#
+            ),
+            json!([{ "jsonrpc": "2.0", "result": [], "id": 4 }])
+        );
+        assert_eq!(server.handle(r#"{"jsonrpc":"2.0","method":"listAssets"}"#, None), None);
+
+        let response = server
+            .handle(r#"{"jsonrpc":"2.0","method":"listAssets","id":5}"#, Some("secreT"))
+            .unwrap();
+        let response = serde_json::from_str::<Value>(&response).unwrap();
+        assert_eq!(response["error"]["code"], RPC_UNAUTHORIZED);
+    }
+}
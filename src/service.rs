@@ -0,0 +1,655 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Message-driven service facade over [`Stock`].
+//!
+//! Daemons exposing stock over IPC (RPC servers, mobile bindings, rgb-node
+//! like services) receive [`Request`]s, pass them to a [`Service`] and send
+//! back the produced [`Response`]s. Both requests and responses are
+//! serializable with serde, so any IPC encoding can be used.
+//!
+//! Long-running requests – consignment validation – report their progress
+//! to a [`ProgressSink`], which can abort them once cancelled from another
+//! thread with a [`CancellationToken`](crate::resolvers::CancellationToken).
+//!
+//! Custodial deployments sharing a single stock between several internal
+//! services or operators restrict each of them to a limited set of
+//! [`Permission`]s with [`AccessControl`] lists, enforced by
+//! [`Service::process_as`].
+//!
+//! Protocol buffers definition of the facade is provided in
+//! `proto/service.proto` file of the crate; the gRPC server implementing it
+//! is available with `grpc` feature in `grpc` module.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bp::seals::txout::CloseMethod;
+use commit_verify::Conceal;
+use invoice::{Amount, ChainNet, XChainNet};
+use rgb::validation::{self, ResolveWitness};
+use rgb::{
+    ContractId, GraphSeal, Layer1, Opout, SecretSeal, XChain, XOutpoint, XOutputSeal, XWitnessId,
+};
+
+use crate::containers::{ConsignmentExt, Contract, Kit, Transfer, ValidationError};
+use crate::info::{ContractInfo, IfaceInfo, SchemaInfo};
+use crate::persistence::{
+    ContractStateRead, IndexProvider, LabelRef, MemIndex, MemStash, MemState, PersistedState,
+    StashProvider, StateProvider, Stock, StockError,
+};
+use crate::resolvers::{Progress, ProgressSink};
+
+/// Request to a [`Service`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum Request {
+    Ifaces,
+    Schemata,
+    Contracts,
+    ContractInfo(ContractId),
+    /// Total amount of fungible state assigned to the outpoints.
+    Balance {
+        contract_id: ContractId,
+        outpoints: Vec<XOutpoint>,
+    },
+    /// Fungible state allocations of the contract.
+    History(ContractId),
+    /// Creates invoice for receiving the amount to a new blinded seal on the
+    /// outpoint, storing the seal.
+    NewInvoice {
+        contract_id: ContractId,
+        outpoint: XOutpoint,
+        amount: Amount,
+        close_method: CloseMethod,
+    },
+    ImportKit(Kit),
+    /// Validates and imports the contract.
+    ImportContract(Contract),
+    /// Validates and accepts the transfer.
+    AcceptTransfer(Transfer),
+    ExportContract(ContractId),
+    Transfer {
+        contract_id: ContractId,
+        outputs: Vec<XOutputSeal>,
+        secret_seal: Option<XChain<SecretSeal>>,
+    },
+    StoreSecretSeal(XChain<GraphSeal>),
+    Labels,
+    /// Sets label or, if the label is `None`, removes it.
+    SetLabel {
+        label_ref: LabelRef,
+        label: Option<String>,
+    },
+    UpdateWitnesses {
+        after_height: u32,
+    },
+}
+
+impl Request {
+    /// Returns permission required for processing the request, together with
+    /// the contract the request is bound to. Requests which are not bound to
+    /// a contract require the permission to be granted for the whole stock.
+    pub fn required_access(&self) -> (Permission, Option<ContractId>) {
+        match self {
+            Request::Ifaces | Request::Schemata | Request::Contracts | Request::Labels => {
+                (Permission::View, None)
+            }
+            Request::ContractInfo(contract_id)
+            | Request::Balance { contract_id, .. }
+            | Request::History(contract_id)
+            | Request::ExportContract(contract_id) => (Permission::View, Some(*contract_id)),
+            Request::NewInvoice { contract_id, .. } => (Permission::Invoice, Some(*contract_id)),
+            Request::AcceptTransfer(transfer) => {
+                (Permission::Invoice, Some(transfer.contract_id()))
+            }
+            Request::StoreSecretSeal(_) => (Permission::Invoice, None),
+            Request::Transfer { contract_id, .. } => (Permission::Spend, Some(*contract_id)),
+            Request::ImportContract(contract) => (Permission::Issue, Some(contract.contract_id())),
+            Request::ImportKit(_) | Request::SetLabel { .. } | Request::UpdateWitnesses { .. } => {
+                (Permission::Issue, None)
+            }
+        }
+    }
+}
+
+/// Response produced by a [`Service`] for a [`Request`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum Response {
+    Ifaces(Vec<IfaceInfo>),
+    Schemata(Vec<SchemaInfo>),
+    Contracts(Vec<ContractInfo>),
+    ContractInfo(ContractInfo),
+    Balance(Amount),
+    History(Vec<HistoryEntry>),
+    Invoice(String),
+    /// Kit or consignment was validated and imported.
+    Imported(validation::Status),
+    /// Kit or consignment has failed validation and was not imported.
+    Invalid(validation::Status),
+    Contract(Contract),
+    Transfer(Transfer),
+    /// Data were stored; the flag indicates whether the stored data were new.
+    Stored(bool),
+    Labels(Vec<(LabelRef, String)>),
+    Updated {
+        succeeded: usize,
+        failed: Vec<(XWitnessId, String)>,
+    },
+    /// Processing was cancelled by the [`ProgressSink`]; no data were stored.
+    Cancelled,
+    /// Account is not granted the permission required by the request, either
+    /// for the specific contract or for the whole stock.
+    Denied {
+        permission: Permission,
+        contract_id: Option<ContractId>,
+    },
+    Failure(String),
+}
+
+/// Fungible state allocation reported in the contract history.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct HistoryEntry {
+    pub opout: Opout,
+    pub seal: XOutputSeal,
+    pub amount: Amount,
+    /// Witness transaction which has created the allocation, or `None` for
+    /// the genesis allocations.
+    pub witness: Option<XWitnessId>,
+}
+
+/// Capability which may be granted to an account of a shared stock.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(lowercase)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub enum Permission {
+    /// Reading contracts, their state and history.
+    View,
+    /// Creating invoices and accepting incoming transfers.
+    Invoice,
+    /// Constructing outgoing transfers.
+    Spend,
+    /// Importing kits and contracts and managing stock metadata.
+    Issue,
+}
+
+/// Permissions granted to a single account.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct AccountAcl {
+    /// Permissions granted for the whole stock, including all contracts.
+    pub global: BTreeSet<Permission>,
+    /// Permissions granted only for specific contracts.
+    pub contracts: BTreeMap<ContractId, BTreeSet<Permission>>,
+}
+
+impl AccountAcl {
+    /// Checks whether the permission is granted for the contract or, if no
+    /// contract is given, for the whole stock.
+    pub fn allows(&self, permission: Permission, contract_id: Option<ContractId>) -> bool {
+        self.global.contains(&permission)
+            || contract_id
+                .and_then(|id| self.contracts.get(&id))
+                .is_some_and(|set| set.contains(&permission))
+    }
+}
+
+/// Access control lists of the accounts of a shared stock.
+///
+/// Accounts not present in the list are not granted any permissions.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", rename_all = "camelCase")
+)]
+pub struct AccessControl {
+    pub accounts: BTreeMap<String, AccountAcl>,
+}
+
+impl AccessControl {
+    pub fn new() -> Self { default!() }
+
+    pub fn account(&self, account: &str) -> Option<&AccountAcl> { self.accounts.get(account) }
+
+    /// Grants permission to the account for the contract or, if no contract
+    /// is given, for the whole stock. Returns `false` if the permission was
+    /// already granted.
+    pub fn grant(
+        &mut self,
+        account: impl ToString,
+        permission: Permission,
+        contract_id: Option<ContractId>,
+    ) -> bool {
+        let acl = self.accounts.entry(account.to_string()).or_default();
+        match contract_id {
+            None => acl.global.insert(permission),
+            Some(id) => acl.contracts.entry(id).or_default().insert(permission),
+        }
+    }
+
+    /// Revokes permission previously granted with [`AccessControl::grant`].
+    /// Returns `false` if the permission was not granted.
+    pub fn revoke(
+        &mut self,
+        account: &str,
+        permission: Permission,
+        contract_id: Option<ContractId>,
+    ) -> bool {
+        let Some(acl) = self.accounts.get_mut(account) else {
+            return false;
+        };
+        match contract_id {
+            None => acl.global.remove(&permission),
+            Some(id) => acl
+                .contracts
+                .get_mut(&id)
+                .is_some_and(|set| set.remove(&permission)),
+        }
+    }
+
+    /// Checks whether the account is granted the permission for the contract
+    /// or, if no contract is given, for the whole stock.
+    pub fn allows(
+        &self,
+        account: &str,
+        permission: Permission,
+        contract_id: Option<ContractId>,
+    ) -> bool {
+        self.account(account)
+            .is_some_and(|acl| acl.allows(permission, contract_id))
+    }
+}
+
+/// Service processing [`Request`]s over a stock.
+#[derive(Debug)]
+pub struct Service<
+    R: ResolveWitness,
+    S: StashProvider = MemStash,
+    H: StateProvider = MemState,
+    P: IndexProvider = MemIndex,
+> {
+    stock: Stock<S, H, P>,
+    resolver: R,
+    testnet: bool,
+    acl: AccessControl,
+}
+
+impl<R: ResolveWitness, S: StashProvider, H: StateProvider, P: IndexProvider> Service<R, S, H, P> {
+    pub fn new(stock: Stock<S, H, P>, resolver: R, testnet: bool) -> Self {
+        Service {
+            stock,
+            resolver,
+            testnet,
+            acl: none!(),
+        }
+    }
+
+    /// Sets access control lists enforced by [`Service::process_as`].
+    pub fn with_access_control(mut self, acl: AccessControl) -> Self {
+        self.acl = acl;
+        self
+    }
+
+    pub fn access_control(&self) -> &AccessControl { &self.acl }
+
+    pub fn access_control_mut(&mut self) -> &mut AccessControl { &mut self.acl }
+
+    pub fn stock(&self) -> &Stock<S, H, P> { &self.stock }
+
+    pub fn stock_mut(&mut self) -> &mut Stock<S, H, P> { &mut self.stock }
+
+    pub fn into_stock(self) -> Stock<S, H, P> { self.stock }
+
+    /// Processes request without progress reporting and cancellation.
+    pub fn handle(&mut self, request: Request) -> Response { self.process(request, &()) }
+
+    /// Processes request on behalf of the account, without progress reporting
+    /// and cancellation.
+    pub fn handle_as(&mut self, account: &str, request: Request) -> Response {
+        self.process_as(account, request, &())
+    }
+
+    /// Processes request on behalf of the account, checking that the account
+    /// is granted the permission required by the request.
+    ///
+    /// The list of contracts is limited to the contracts the account may
+    /// view.
+    pub fn process_as(
+        &mut self,
+        account: &str,
+        request: Request,
+        sink: &impl ProgressSink,
+    ) -> Response {
+        if matches!(request, Request::Contracts) {
+            return match self.process(request, sink) {
+                Response::Contracts(contracts) => Response::Contracts(
+                    contracts
+                        .into_iter()
+                        .filter(|info| self.acl.allows(account, Permission::View, Some(info.id)))
+                        .collect(),
+                ),
+                other => other,
+            };
+        }
+        let (permission, contract_id) = request.required_access();
+        if !self.acl.allows(account, permission, contract_id) {
+            return Response::Denied {
+                permission,
+                contract_id,
+            };
+        }
+        self.process(request, sink)
+    }
+
+    /// Processes request, reporting progress and checking for cancellation.
+    pub fn process(&mut self, request: Request, sink: &impl ProgressSink) -> Response {
+        if sink.is_cancelled() {
+            return Response::Cancelled;
+        }
+        let stock = &mut self.stock;
+        let res = match request {
+            Request::Ifaces => stock
+                .ifaces()
+                .map(|iter| Response::Ifaces(iter.collect()))
+                .map_err(|err| err.to_string()),
+            Request::Schemata => stock
+                .schemata()
+                .map(|iter| Response::Schemata(iter.collect()))
+                .map_err(|err| err.to_string()),
+            Request::Contracts => stock
+                .contracts()
+                .map(|iter| Response::Contracts(iter.collect()))
+                .map_err(|err| err.to_string()),
+            Request::ContractInfo(contract_id) => stock
+                .contract_info(contract_id)
+                .map(Response::ContractInfo)
+                .map_err(|err| err.to_string()),
+            Request::Balance {
+                contract_id,
+                outpoints,
+            } => stock
+                .contract_assignments_for(contract_id, outpoints)
+                .map(|assignments| {
+                    let sum = assignments
+                        .values()
+                        .flat_map(|states| states.values())
+                        .filter_map(|state| match state {
+                            PersistedState::Amount(amount, _, _) => Some(amount.value()),
+                            _ => None,
+                        })
+                        .sum::<u64>();
+                    Response::Balance(Amount::from(sum))
+                })
+                .map_err(|err| err.to_string()),
+            Request::History(contract_id) => stock
+                .contract_state(contract_id)
+                .map(|state| {
+                    Response::History(
+                        state
+                            .fungible_all()
+                            .map(|item| HistoryEntry {
+                                opout: item.opout,
+                                seal: item.seal,
+                                amount: item.state.value.into(),
+                                witness: item.witness,
+                            })
+                            .collect(),
+                    )
+                })
+                .map_err(|err| err.to_string()),
+            Request::NewInvoice {
+                contract_id,
+                outpoint,
+                amount,
+                close_method,
+            } => self
+                .new_invoice(contract_id, outpoint, amount, close_method)
+                .map(Response::Invoice)
+                .map_err(|err| err.to_string()),
+            Request::ImportKit(kit) => match kit.validate() {
+                Ok(kit) => stock
+                    .import_kit(kit)
+                    .map(Response::Imported)
+                    .map_err(|err| err.to_string()),
+                Err((status, _)) => Ok(Response::Invalid(status)),
+            },
+            Request::ImportContract(contract) => {
+                match contract.validate_with_progress(&self.resolver, self.testnet, sink) {
+                    Ok(contract) => {
+                        sink.report(Progress::Storing);
+                        self.stock
+                            .import_contract(contract, &self.resolver)
+                            .map(Response::Imported)
+                            .map_err(|err| err.to_string())
+                    }
+                    Err((err, _)) => Ok(Self::invalid(err)),
+                }
+            }
+            Request::AcceptTransfer(transfer) => {
+                match transfer.validate_with_progress(&self.resolver, self.testnet, sink) {
+                    Ok(transfer) => {
+                        sink.report(Progress::Storing);
+                        self.stock
+                            .accept_transfer(transfer, &self.resolver)
+                            .map(Response::Imported)
+                            .map_err(|err| err.to_string())
+                    }
+                    Err((err, _)) => Ok(Self::invalid(err)),
+                }
+            }
+            Request::ExportContract(contract_id) => stock
+                .export_contract(contract_id)
+                .map(Response::Contract)
+                .map_err(|err| err.to_string()),
+            Request::Transfer {
+                contract_id,
+                outputs,
+                secret_seal,
+            } => stock
+                .transfer(contract_id, outputs, secret_seal)
+                .map(Response::Transfer)
+                .map_err(|err| err.to_string()),
+            Request::StoreSecretSeal(seal) => stock
+                .store_secret_seal(seal)
+                .map(Response::Stored)
+                .map_err(|err| err.to_string()),
+            Request::Labels => stock
+                .labels()
+                .map(|iter| {
+                    Response::Labels(
+                        iter.map(|(label_ref, label)| (label_ref, label.to_owned()))
+                            .collect(),
+                    )
+                })
+                .map_err(|err| err.to_string()),
+            Request::SetLabel {
+                label_ref,
+                label: Some(label),
+            } => stock
+                .set_label(label_ref, label)
+                .map(Response::Stored)
+                .map_err(|err| err.to_string()),
+            Request::SetLabel {
+                label_ref,
+                label: None,
+            } => stock
+                .remove_label(label_ref)
+                .map(Response::Stored)
+                .map_err(|err| err.to_string()),
+            Request::UpdateWitnesses { after_height } => stock
+                .update_witnesses(&self.resolver, after_height)
+                .map(|res| Response::Updated {
+                    succeeded: res.succeeded,
+                    failed: res.failed.into_iter().collect(),
+                })
+                .map_err(|err| err.to_string()),
+        };
+        res.unwrap_or_else(Response::Failure)
+    }
+
+    fn new_invoice(
+        &mut self,
+        contract_id: ContractId,
+        outpoint: XOutpoint,
+        amount: Amount,
+        close_method: CloseMethod,
+    ) -> Result<String, StockError<S, H, P>> {
+        // Ensure the contract is known
+        self.stock.contract_info(contract_id)?;
+        let seal = outpoint
+            .map(|outpoint| GraphSeal::new_random(close_method, outpoint.txid, outpoint.vout));
+        let chain_net = match (seal.layer1(), self.testnet) {
+            (Layer1::Bitcoin, false) => ChainNet::BitcoinMainnet,
+            (Layer1::Bitcoin, true) => ChainNet::BitcoinTestnet,
+            (Layer1::Liquid, false) => ChainNet::LiquidMainnet,
+            (Layer1::Liquid, true) => ChainNet::LiquidTestnet,
+        };
+        let beneficiary = XChainNet::with(chain_net, seal.as_reduced_unsafe().conceal().into());
+        self.stock.store_secret_seal(seal)?;
+        Ok(self
+            .stock
+            .invoice_builder(contract_id, beneficiary)?
+            .set_amount_raw(amount)
+            .finish()
+            .to_string())
+    }
+
+    fn invalid(err: ValidationError) -> Response {
+        match err {
+            ValidationError::Invalid(status) => Response::Invalid(status),
+            ValidationError::ResourceExhausted(err) => Response::Failure(err.to_string()),
+            ValidationError::Cancelled => Response::Cancelled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use baid64::FromBaid64Str;
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::interface::resolver::DumbResolver;
+    use crate::resolvers::CancellationToken;
+
+    #[test]
+    fn service() {
+        let mut service = Service::new(Stock::in_memory(), DumbResolver, true);
+        assert_eq!(service.handle(Request::Contracts), Response::Contracts(vec![]));
+
+        let contract_id =
+            ContractId::from_baid64_str("rgb:qFuT6DN8-9AuO95M-7R8R8Mc-AZvs7zG-obum1Va-BRnweKk")
+                .unwrap();
+        assert!(matches!(service.handle(Request::ContractInfo(contract_id)), Response::Failure(_)));
+        let set_label = Request::SetLabel {
+            label_ref: contract_id.into(),
+            label: Some(s!("savings")),
+        };
+        assert_eq!(service.handle(set_label.clone()), Response::Stored(true));
+        assert_eq!(
+            service.handle(Request::Labels),
+            Response::Labels(vec![(contract_id.into(), s!("savings"))])
+        );
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        assert_eq!(service.process(set_label, &cancel), Response::Cancelled);
+    }
+
+    #[test]
+    fn access_control() {
+        let contract_id = ContractId::strict_dumb();
+        let mut acl = AccessControl::new();
+        assert!(acl.grant("auditor", Permission::View, None));
+        assert!(acl.grant("cashier", Permission::Invoice, Some(contract_id)));
+        assert!(!acl.grant("cashier", Permission::Invoice, Some(contract_id)));
+        assert!(acl.allows("auditor", Permission::View, Some(contract_id)));
+        assert!(!acl.allows("cashier", Permission::Invoice, None));
+        assert!(!acl.allows("operator", Permission::View, None));
+
+        let mut service =
+            Service::new(Stock::in_memory(), DumbResolver, true).with_access_control(acl);
+        assert_eq!(service.handle_as("auditor", Request::Labels), Response::Labels(vec![]));
+        assert_eq!(service.handle_as("cashier", Request::Contracts), Response::Contracts(vec![]));
+        let transfer = Request::Transfer {
+            contract_id,
+            outputs: vec![],
+            secret_seal: None,
+        };
+        assert_eq!(service.handle_as("cashier", transfer), Response::Denied {
+            permission: Permission::Spend,
+            contract_id: Some(contract_id)
+        });
+        assert!(matches!(
+            service.handle_as("cashier", Request::ContractInfo(contract_id)),
+            Response::Denied {
+                permission: Permission::View,
+                ..
+            }
+        ));
+
+        assert!(
+            service
+                .access_control_mut()
+                .revoke("auditor", Permission::View, None)
+        );
+        assert!(matches!(service.handle_as("auditor", Request::Labels), Response::Denied { .. }));
+    }
+
+    #[test]
+    fn import_progress() {
+        let mut service = Service::new(Stock::in_memory(), DumbResolver, true);
+        let contract = Contract::strict_dumb();
+        let reports = RefCell::new(vec![]);
+        let sink = |progress| reports.borrow_mut().push(progress);
+        assert!(matches!(
+            service.process(Request::ImportContract(contract.clone()), &sink),
+            Response::Invalid(_)
+        ));
+        assert_eq!(*reports.borrow(), vec![Progress::Validating(contract.contract_id())]);
+    }
+}
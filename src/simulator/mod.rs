@@ -0,0 +1,528 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Contract simulation sandbox for schema developers.
+//!
+//! [`Simulator`] instantiates a contract from a schema and genesis fully
+//! in-memory, using a fake blockchain which "mines" a new block for each of
+//! the executed operations. Each state transition or extension is validated
+//! against the schema (including AluVM validation scripts) and the resulting
+//! contract state is updated, without a need to construct witness
+//! transactions, anchors or to run a regtest node.
+//!
+//! If an operation fails script validation, [`Simulator::diagnose`] provides
+//! the error code reported by the script together with the disassembled
+//! script code.
+//!
+//! With `vm-trace` feature, instruction-level trace of the scripts executed
+//! by the consensus validation is printed to stderr, and `Simulator::trace`
+//! re-executes a script with a standalone tracer collecting a structured
+//! `ExecTrace`, optionally halting at a `Breakpoint`. The tracer is a
+//! debugging tool only: nothing in the library depends on it.
+
+#[cfg(feature = "vm-trace")]
+mod trace;
+
+use std::cell::{Ref, RefCell};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::rc::Rc;
+
+use aluvm::isa::{Bytecode, Instr};
+use aluvm::library::{Cursor, Lib, LibSite, Read};
+use amplify::ByteArray;
+use commit_verify::{DigestExt, Sha256};
+use rgb::validation::{
+    CheckedConsignment, ConsignmentApi, EAnchor, Failure, OpRef, Scripts, Status,
+};
+use rgb::vm::{ContractStateEvolve, OrdOpRef, RgbIsa, WitnessOrd, WitnessPos};
+use rgb::{
+    BundleId, Extension, Genesis, OpId, Operation, Schema, Transition, TransitionBundle, XChain,
+    XWitnessId,
+};
+use strict_types::TypeSystem;
+
+use crate::containers::Contract;
+use crate::persistence::MemContract;
+use crate::Txid;
+
+#[cfg(feature = "vm-trace")]
+pub use trace::{Breakpoint, ExecTrace, ScriptTracer, TraceStep, TraceStop};
+
+/// Interval between blocks of the simulated blockchain, in seconds.
+pub const SIMULATOR_BLOCK_INTERVAL: i64 = 600;
+
+/// Timestamp of the bitcoin genesis block, which is the lowest timestamp
+/// allowed for the mined witnesses.
+const BITCOIN_GENESIS_TIMESTAMP: i64 = 1231006505;
+
+/// Report on a single step executed by the [`Simulator`].
+#[derive(Clone, Debug)]
+pub struct SimStep {
+    /// Id of the executed operation.
+    pub opid: OpId,
+    /// Fake witness transaction id assigned to the operation; `None` for the
+    /// genesis.
+    pub witness_id: Option<XWitnessId>,
+    /// Position of the witness in the simulated blockchain.
+    pub witness_ord: Option<WitnessOrd>,
+    /// Entry point of the validation script for the operation, if the schema
+    /// defines one.
+    pub validator: Option<LibSite>,
+    /// Validation status for the operation. If the status is not valid, the
+    /// contract state is left unchanged.
+    pub status: Status,
+}
+
+impl SimStep {
+    pub fn is_valid(&self) -> bool { self.status.failures.is_empty() }
+
+    /// Detects whether the operation has failed script validation, returning
+    /// the error code reported by the script (if any).
+    pub fn script_failure(&self) -> Option<Option<u8>> {
+        self.status
+            .failures
+            .iter()
+            .find_map(|failure| match failure {
+                Failure::ScriptFailure(opid, code, _) if *opid == self.opid => Some(*code),
+                _ => None,
+            })
+    }
+}
+
+/// Diagnostics for an operation which has failed script validation.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ScriptDiagnostics {
+    /// Id of the failed operation.
+    pub opid: OpId,
+    /// Entry point of the validation script.
+    pub entry_point: LibSite,
+    /// Error code reported by the script in `a8[0]` register.
+    pub error_code: Option<u8>,
+    /// Disassembled code of the library containing the entry point, as a
+    /// list of instruction offsets and instructions in assembly notation.
+    pub code: Vec<(u16, String)>,
+}
+
+impl Display for ScriptDiagnostics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "operation {} failed validation script at {}", self.opid, self.entry_point)?;
+        match self.error_code {
+            Some(code) => writeln!(f, " with error code {code}")?,
+            None => writeln!(f, " without an error code")?,
+        }
+        for (pos, instr) in &self.code {
+            let marker = if *pos == self.entry_point.pos { '>' } else { ' ' };
+            writeln!(f, "{marker} @{pos:06}: {instr}")?;
+        }
+        Ok(())
+    }
+}
+
+struct SimConsignment {
+    schema: Schema,
+    types: TypeSystem,
+    scripts: Scripts,
+    genesis: Genesis,
+    transitions: BTreeMap<OpId, Transition>,
+    extensions: BTreeMap<OpId, Extension>,
+    witnesses: BTreeMap<OpId, XWitnessId>,
+}
+
+impl ConsignmentApi for SimConsignment {
+    fn schema(&self) -> &Schema { &self.schema }
+
+    fn types(&self) -> &TypeSystem { &self.types }
+
+    fn scripts(&self) -> &Scripts { &self.scripts }
+
+    fn operation(&self, opid: OpId) -> Option<OpRef<'_>> {
+        if opid == self.genesis.id() {
+            return Some(OpRef::Genesis(&self.genesis));
+        }
+        self.transitions
+            .get(&opid)
+            .map(OpRef::Transition)
+            .or_else(|| self.extensions.get(&opid).map(OpRef::Extension))
+    }
+
+    fn genesis(&self) -> &Genesis { &self.genesis }
+
+    fn bundle_ids<'iter>(&self) -> impl Iterator<Item = BundleId> + 'iter { [].into_iter() }
+
+    fn bundle(&self, _: BundleId) -> Option<&TransitionBundle> { None }
+
+    fn anchor(&self, _: BundleId) -> Option<(XWitnessId, &EAnchor)> { None }
+
+    fn op_witness_id(&self, opid: OpId) -> Option<XWitnessId> { self.witnesses.get(&opid).copied() }
+}
+
+/// Contract simulation sandbox.
+///
+/// Executes contract operations against the schema rules and validation
+/// scripts using in-memory contract state and a fake blockchain.
+pub struct Simulator {
+    consignment: SimConsignment,
+    state: Rc<RefCell<MemContract>>,
+    height: u32,
+    timestamp: i64,
+    steps: Vec<SimStep>,
+}
+
+impl Simulator {
+    /// Constructs simulator for a contract issued with the given schema and
+    /// genesis, validating the genesis.
+    ///
+    /// Check the first step (see [`Self::steps`]) for the genesis validation
+    /// status.
+    pub fn new(schema: Schema, types: TypeSystem, scripts: Scripts, genesis: Genesis) -> Self {
+        let state = MemContract::init((&schema, genesis.contract_id()));
+        let timestamp = genesis.timestamp.max(BITCOIN_GENESIS_TIMESTAMP);
+        let mut me = Simulator {
+            consignment: SimConsignment {
+                schema,
+                types,
+                scripts,
+                genesis,
+                transitions: empty!(),
+                extensions: empty!(),
+                witnesses: empty!(),
+            },
+            state: Rc::new(RefCell::new(state)),
+            height: 0,
+            timestamp,
+            steps: empty!(),
+        };
+        let genesis = me.consignment.genesis.clone();
+        let step = me.execute(OrdOpRef::Genesis(&genesis), None);
+        me.steps.push(step);
+        me
+    }
+
+    /// Constructs simulator from the contract data, for instance produced by
+    /// [`crate::interface::ContractBuilder`].
+    ///
+    /// Only schema, type system, scripts and genesis are taken from the
+    /// contract; the rest of the contract operations are ignored.
+    pub fn from_contract(contract: &Contract) -> Self {
+        let scripts =
+            Scripts::from_iter_checked(contract.scripts.iter().map(|lib| (lib.id(), lib.clone())));
+        Self::new(
+            contract.schema.clone(),
+            contract.types.clone(),
+            scripts,
+            contract.genesis.clone(),
+        )
+    }
+
+    /// Returns current state of the simulated contract.
+    pub fn state(&self) -> Ref<'_, MemContract> { self.state.borrow() }
+
+    /// Returns reports for all steps executed so far, starting from genesis.
+    pub fn steps(&self) -> &[SimStep] { &self.steps }
+
+    /// Returns height of the simulated blockchain.
+    pub fn height(&self) -> u32 { self.height }
+
+    /// Mines a new block in the simulated blockchain and executes state
+    /// transition, assigning it a fake witness transaction mined in that
+    /// block.
+    pub fn execute_transition(&mut self, transition: Transition) -> &SimStep {
+        let ord = self.mine();
+        self.execute_transition_with(transition, ord)
+    }
+
+    /// Executes state transition with a fake witness transaction having a
+    /// specific position (for instance, to simulate unmined transactions).
+    pub fn execute_transition_with(&mut self, transition: Transition, ord: WitnessOrd) -> &SimStep {
+        let opid = transition.id();
+        let witness_id = Self::witness_id(opid);
+        let step = self.execute(OrdOpRef::Transition(&transition, witness_id, ord), Some(ord));
+        if step.is_valid() {
+            self.consignment.transitions.insert(opid, transition);
+            self.consignment.witnesses.insert(opid, witness_id);
+        }
+        self.push(step)
+    }
+
+    /// Mines a new block in the simulated blockchain and executes state
+    /// extension, assigning it a fake witness transaction mined in that
+    /// block.
+    pub fn execute_extension(&mut self, extension: Extension) -> &SimStep {
+        let ord = self.mine();
+        self.execute_extension_with(extension, ord)
+    }
+
+    /// Executes state extension with a fake witness transaction having a
+    /// specific position.
+    pub fn execute_extension_with(&mut self, extension: Extension, ord: WitnessOrd) -> &SimStep {
+        let opid = extension.id();
+        let witness_id = Self::witness_id(opid);
+        let step = self.execute(OrdOpRef::Extension(&extension, witness_id, ord), Some(ord));
+        if step.is_valid() {
+            self.consignment.extensions.insert(opid, extension);
+            self.consignment.witnesses.insert(opid, witness_id);
+        }
+        self.push(step)
+    }
+
+    /// Provides diagnostics for a step which has failed script validation.
+    ///
+    /// Returns `None` if the step hasn't failed script validation.
+    pub fn diagnose(&self, step: &SimStep) -> Option<ScriptDiagnostics> {
+        let error_code = step.script_failure()?;
+        let entry_point = step.validator?;
+        let code = self
+            .consignment
+            .scripts
+            .get(&entry_point.lib)
+            .map(Self::disassemble)
+            .unwrap_or_default();
+        Some(ScriptDiagnostics {
+            opid: step.opid,
+            entry_point,
+            error_code,
+            code,
+        })
+    }
+
+    /// Re-executes validation script for an operation collecting trace of
+    /// the executed instructions. The execution is halted once the
+    /// `breakpoint` is hit.
+    ///
+    /// Global contract state accessed by the script is taken from the
+    /// current state of the simulated contract.
+    ///
+    /// Returns `None` if the schema doesn't define validation script for the
+    /// operation.
+    #[cfg(feature = "vm-trace")]
+    pub fn trace(&self, op: OpRef, breakpoint: &mut impl Breakpoint) -> Option<ExecTrace> {
+        let schema = &self.consignment.schema;
+        let validator = match op {
+            OpRef::Genesis(_) => schema.genesis.validator,
+            OpRef::Transition(transition) => schema
+                .transitions
+                .get(&transition.transition_type)
+                .and_then(|s| s.validator),
+            OpRef::Extension(extension) => schema
+                .extensions
+                .get(&extension.extension_type)
+                .and_then(|s| s.validator),
+        }?;
+        let state = self.state.borrow();
+        let tracer = ScriptTracer::new(&self.consignment, op, &*state);
+        Some(tracer.run(validator, &self.consignment.scripts, breakpoint))
+    }
+
+    fn disassemble(lib: &Lib) -> Vec<(u16, String)> {
+        let mut cursor = Cursor::with(&lib.code, &lib.data, &lib.libs);
+        let mut code = vec![];
+        while !cursor.is_eof() {
+            let pos = cursor.pos();
+            let Ok(instr) = Instr::<RgbIsa<MemContract>>::decode(&mut cursor) else {
+                break;
+            };
+            code.push((pos, instr.to_string()));
+        }
+        code
+    }
+
+    fn mine(&mut self) -> WitnessOrd {
+        self.height += 1;
+        self.timestamp += SIMULATOR_BLOCK_INTERVAL;
+        WitnessOrd::Mined(
+            WitnessPos::new(self.height, self.timestamp).expect("simulated block is always valid"),
+        )
+    }
+
+    fn witness_id(opid: OpId) -> XWitnessId {
+        let mut hasher = Sha256::default();
+        hasher.input_raw(opid.as_slice());
+        XChain::Bitcoin(Txid::from_byte_array(hasher.finish()))
+    }
+
+    fn push(&mut self, step: SimStep) -> &SimStep {
+        self.steps.push(step);
+        self.steps.last().expect("just added")
+    }
+
+    fn execute(&mut self, op: OrdOpRef, witness_ord: Option<WitnessOrd>) -> SimStep {
+        let schema = &self.consignment.schema;
+        let consignment = CheckedConsignment::new(&self.consignment);
+        let mut status = schema.validate_state(&consignment, op, self.state.clone());
+
+        // Validation procedure evolves the state only when the operation has a
+        // validation script, so we have to do it ourselves otherwise.
+        let validator = match op {
+            OrdOpRef::Genesis(_) => schema.genesis.validator,
+            OrdOpRef::Transition(transition, ..) => schema
+                .transitions
+                .get(&transition.transition_type)
+                .and_then(|s| s.validator),
+            OrdOpRef::Extension(extension, ..) => schema
+                .extensions
+                .get(&extension.extension_type)
+                .and_then(|s| s.validator),
+        };
+        if status.failures.is_empty()
+            && validator.is_none()
+            && self.state.borrow_mut().evolve_state(op).is_err()
+        {
+            status.add_failure(Failure::ContractStateFilled(op.id()));
+        }
+
+        SimStep {
+            opid: op.id(),
+            witness_id: op.witness_id(),
+            witness_ord,
+            validator,
+            status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use aluvm::data::{MaybeNumber, Number};
+    use aluvm::isa::{ControlFlowOp, PutOp};
+    use aluvm::reg::{Reg32, RegA};
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::containers::ConsignmentExt;
+    use crate::persistence::ContractStateRead;
+
+    #[test]
+    fn simulate_unknown_transition() {
+        let contract =
+            Contract::from_str(include_str!("../../asset/armored_contract.default")).unwrap();
+        let mut sim = Simulator::from_contract(&contract);
+        assert_eq!(sim.steps().len(), 1);
+        assert!(sim.steps()[0].is_valid(), "{}", sim.steps()[0].status);
+        assert_eq!(sim.height(), 0);
+
+        let transition = Transition::strict_dumb();
+        let opid = transition.id();
+        let step = sim.execute_transition(transition).clone();
+        assert_eq!(step.opid, opid);
+        assert!(step.witness_id.is_some());
+        assert!(!step.is_valid());
+        assert_eq!(sim.height(), 1);
+        assert_eq!(sim.steps().len(), 2);
+        assert_eq!(sim.state().contract_id(), contract.contract_id());
+        assert_eq!(step.script_failure(), None);
+        assert_eq!(sim.diagnose(&step), None);
+    }
+
+    #[test]
+    fn diagnose_script_failure() {
+        let contract =
+            Contract::from_str(include_str!("../../asset/armored_contract.default")).unwrap();
+        let code = [
+            Instr::<RgbIsa<MemContract>>::Put(PutOp::PutA(
+                RegA::A8,
+                Reg32::Reg0,
+                Box::new(MaybeNumber::from(Number::from(7u8))),
+            )),
+            Instr::ControlFlow(ControlFlowOp::Fail),
+        ];
+        let lib = Lib::assemble(&code).unwrap();
+        let entry_point = LibSite::with(0, lib.id());
+        let mut schema = contract.schema.clone();
+        schema.genesis.validator = Some(entry_point);
+        let scripts = Scripts::from_checked(bmap! { lib.id() => lib });
+
+        let sim = Simulator::new(schema, contract.types.clone(), scripts, contract.genesis.clone());
+        let step = &sim.steps()[0];
+        assert!(!step.is_valid());
+        assert_eq!(step.validator, Some(entry_point));
+        assert_eq!(step.script_failure(), Some(Some(7)));
+
+        let diagnostics = sim.diagnose(step).unwrap();
+        assert_eq!(diagnostics.opid, contract.genesis.id());
+        assert_eq!(diagnostics.error_code, Some(7));
+        assert_eq!(diagnostics.code.len(), 2);
+        assert_eq!(diagnostics.code[1].1, "fail");
+    }
+
+    #[test]
+    #[cfg(feature = "vm-trace")]
+    fn trace_script() {
+        use aluvm::reg::CoreRegs;
+        use rgb::vm::ContractOp;
+        use rgb::GlobalStateType;
+
+        let contract =
+            Contract::from_str(include_str!("../../asset/armored_contract.default")).unwrap();
+        let code = [
+            Instr::<RgbIsa<MemContract>>::ExtensionCodes(RgbIsa::Contract(ContractOp::CnG(
+                GlobalStateType::from(0xFFFF),
+                Reg32::Reg1,
+            ))),
+            Instr::Put(PutOp::PutA(
+                RegA::A8,
+                Reg32::Reg0,
+                Box::new(MaybeNumber::from(Number::from(7u8))),
+            )),
+            Instr::ControlFlow(ControlFlowOp::Fail),
+        ];
+        let lib = Lib::assemble(&code).unwrap();
+        let entry_point = LibSite::with(0, lib.id());
+        let mut schema = contract.schema.clone();
+        schema.genesis.validator = Some(entry_point);
+        let scripts = Scripts::from_checked(bmap! { lib.id() => lib });
+        let sim = Simulator::new(schema, contract.types.clone(), scripts, contract.genesis.clone());
+
+        let genesis = OpRef::Genesis(&contract.genesis);
+        let trace = sim.trace(genesis, &mut ()).unwrap();
+        assert_eq!(trace.opid, contract.genesis.id());
+        assert_eq!(trace.stop, TraceStop::Completed);
+        assert!(!trace.is_success());
+        assert_eq!(trace.error_code, Some(7));
+        assert_eq!(trace.steps.len(), 3);
+        assert_eq!(trace.steps[0].instr, "cng     0xFFFF,a8[1]");
+        assert_eq!(trace.steps[0].dst_regs.len(), 1);
+        assert!(trace.steps[1].st0);
+        assert!(!trace.steps[2].st0);
+        assert_eq!(trace.count, 3);
+        assert_eq!(trace.complexity, trace.last_step().unwrap().complexity);
+        assert_eq!(trace.error_code, sim.steps()[0].script_failure().unwrap());
+
+        let second = trace.steps[1].site;
+        let trace = sim.trace(genesis, &mut bset! { second }).unwrap();
+        assert_eq!(trace.stop, TraceStop::Breakpoint(second));
+        assert_eq!(trace.steps.len(), 1);
+        assert!(trace.st0);
+
+        let mut hits = 0;
+        let trace = sim
+            .trace(genesis, &mut |_, regs: &CoreRegs| {
+                hits += 1;
+                regs.get_n(RegA::A8, Reg32::Reg0).is_some()
+            })
+            .unwrap();
+        assert_eq!(hits, 3);
+        assert!(matches!(trace.stop, TraceStop::Breakpoint(_)));
+        assert_eq!(trace.steps.len(), 2);
+    }
+}
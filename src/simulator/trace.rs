@@ -0,0 +1,576 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Step-by-step execution of validation scripts with collection of a
+//! structured trace.
+//!
+//! RGB consensus validation runs AluVM scripts inside rgb-core using a
+//! crate-private VM context, which doesn't allow to observe the execution.
+//! [`ScriptTracer`] re-executes a validation script for debugging: core AluVM
+//! instructions are executed by AluVM itself, while RGB contract instructions
+//! are evaluated by the tracer against the operation data, mirroring rgb-core
+//! VM. Before each instruction a [`Breakpoint`] is consulted, which may halt
+//! the execution.
+//!
+//! The tracer is a development tool available with `vm-trace` feature. It is
+//! not a part of the validation and nothing in the library depends on it, so
+//! its results are not authoritative: the consensus validation is always
+//! performed by rgb-core.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+use aluvm::data::RegValue;
+use aluvm::isa::{Bytecode, ControlFlowOp, ExecStep, Instr, InstructionSet};
+use aluvm::library::{Cursor, LibId, LibSite, Read};
+use aluvm::reg::{CoreRegs, Reg, Reg32, RegA};
+use amplify::num::u24;
+use amplify::Wrapper;
+use commit_verify::CommitVerify;
+use rgb::validation::{ConsignmentApi, OpRef, Scripts};
+use rgb::vm::{ContractOp, ContractStateAccess, RgbIsa};
+use rgb::{
+    Assign, AssetTags, Assignments, BlindingFactor, GlobalState, GraphSeal, Metadata, OpId,
+    Operation, Opout, PedersenCommitment, RevealedValue, TypedAssigns,
+};
+
+/// Breakpoint consulted by [`ScriptTracer`] before the execution of each
+/// instruction.
+pub trait Breakpoint {
+    /// Returns `true` if the execution must be halted before the instruction
+    /// located at `site`.
+    fn is_hit(&mut self, site: LibSite, regs: &CoreRegs) -> bool;
+}
+
+/// No breakpoints: the script is executed until it stops.
+impl Breakpoint for () {
+    fn is_hit(&mut self, _: LibSite, _: &CoreRegs) -> bool { false }
+}
+
+/// Breakpoints at specific code locations.
+impl Breakpoint for BTreeSet<LibSite> {
+    fn is_hit(&mut self, site: LibSite, _: &CoreRegs) -> bool { self.contains(&site) }
+}
+
+/// Conditional breakpoint checking code location and register values.
+impl<F> Breakpoint for F
+where F: FnMut(LibSite, &CoreRegs) -> bool
+{
+    fn is_hit(&mut self, site: LibSite, regs: &CoreRegs) -> bool { self(site, regs) }
+}
+
+/// Single instruction executed by a validation script.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TraceStep {
+    /// Location of the instruction.
+    pub site: LibSite,
+    /// Instruction in assembly notation.
+    pub instr: String,
+    /// Source registers of the instruction with their values before the
+    /// execution.
+    pub src_regs: Vec<(Reg, RegValue)>,
+    /// Destination registers of the instruction with their values after the
+    /// execution.
+    pub dst_regs: Vec<(Reg, RegValue)>,
+    /// Value of `st0` register after the execution.
+    pub st0: bool,
+    /// Complexity accumulated by the script after the execution of the
+    /// instruction.
+    pub complexity: u64,
+}
+
+impl Display for TraceStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "@{:06}: {: <32};", self.site.pos, self.instr)?;
+        for (reg, val) in &self.src_regs {
+            write!(f, " {reg}={val}")?;
+        }
+        f.write_str(" ->")?;
+        for (reg, val) in &self.dst_regs {
+            write!(f, " {reg}={val}")?;
+        }
+        write!(f, " st0={}", self.st0)
+    }
+}
+
+/// Reason for which traced script execution has stopped.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Display)]
+#[display(doc_comments)]
+pub enum TraceStop {
+    /// script execution has completed.
+    Completed,
+
+    /// execution was halted by a breakpoint before instruction at {0}.
+    Breakpoint(LibSite),
+
+    /// script has reached complexity limit, accumulating complexity {0}.
+    ComplexityLimit(u64),
+
+    /// script has reached limit of {0} executed instructions.
+    StepLimit(usize),
+
+    /// script has called library {0} which is absent.
+    LibAbsent(LibId),
+
+    /// script contains invalid instruction at {0}.
+    InvalidInstruction(LibSite),
+}
+
+/// Structured trace of a validation script execution.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ExecTrace {
+    /// Id of the operation validated by the script.
+    pub opid: OpId,
+    /// Entry point of the validation script.
+    pub entry_point: LibSite,
+    /// Executed instructions, in the order of their execution.
+    pub steps: Vec<TraceStep>,
+    /// Reason for which the execution has stopped.
+    pub stop: TraceStop,
+    /// Value of `st0` register at the end of the execution.
+    pub st0: bool,
+    /// Error code in `a8[0]` register at the end of the execution.
+    pub error_code: Option<u8>,
+    /// Number of the executed instructions.
+    pub count: usize,
+    /// Total complexity of the executed instructions.
+    pub complexity: u64,
+}
+
+impl ExecTrace {
+    /// Detects whether the script has completed successfully and the
+    /// operation is considered valid by it.
+    pub fn is_success(&self) -> bool { self.st0 && self.stop == TraceStop::Completed }
+
+    /// Returns last executed instruction.
+    pub fn last_step(&self) -> Option<&TraceStep> { self.steps.last() }
+}
+
+impl Display for ExecTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "trace of operation {} validation script at {}", self.opid, self.entry_point)?;
+        for step in &self.steps {
+            writeln!(f, "{step}")?;
+        }
+        write!(f, "{}; st0={}", self.stop, self.st0)?;
+        if let Some(code) = self.error_code {
+            write!(f, ", error code {code}")?;
+        }
+        writeln!(f, ", complexity {}", self.complexity)
+    }
+}
+
+/// Executes validation script for a single operation collecting
+/// [`ExecTrace`].
+pub struct ScriptTracer<'op, S: ContractStateAccess> {
+    opid: OpId,
+    ty: Option<u16>,
+    asset_tags: &'op AssetTags,
+    prev_state: Assignments<GraphSeal>,
+    owned_state: Assignments<GraphSeal>,
+    global: &'op GlobalState,
+    metadata: &'op Metadata,
+    contract_state: &'op S,
+    complexity_limit: Option<u64>,
+    step_limit: Option<usize>,
+}
+
+impl<'op, S: ContractStateAccess> ScriptTracer<'op, S> {
+    /// Prepares tracer for the operation from the consignment, using given
+    /// contract state for the global state access.
+    pub fn new(
+        consignment: &'op impl ConsignmentApi,
+        op: OpRef<'op>,
+        contract_state: &'op S,
+    ) -> Self {
+        let (ty, prev_state, global, metadata) = match op {
+            OpRef::Genesis(genesis) => (None, none!(), &genesis.globals, &genesis.metadata),
+            OpRef::Transition(transition) => (
+                Some(transition.transition_type.into_inner()),
+                prev_state(consignment, transition.inputs.iter().map(|input| input.prev_out)),
+                &transition.globals,
+                &transition.metadata,
+            ),
+            OpRef::Extension(extension) => (
+                Some(extension.extension_type.into_inner()),
+                none!(),
+                &extension.globals,
+                &extension.metadata,
+            ),
+        };
+        ScriptTracer {
+            opid: op.id(),
+            ty,
+            asset_tags: &consignment.genesis().asset_tags,
+            prev_state,
+            owned_state: op.assignments().flat(),
+            global,
+            metadata,
+            contract_state,
+            complexity_limit: None,
+            step_limit: None,
+        }
+    }
+
+    /// Limits total complexity of the executed instructions.
+    pub fn with_complexity_limit(mut self, limit: u64) -> Self {
+        self.complexity_limit = Some(limit);
+        self
+    }
+
+    /// Limits number of the executed instructions.
+    pub fn with_step_limit(mut self, limit: usize) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    /// Executes script starting from the entry point, checking breakpoint
+    /// before each of the instructions.
+    pub fn run(
+        &self,
+        entry_point: LibSite,
+        scripts: &Scripts,
+        breakpoint: &mut impl Breakpoint,
+    ) -> ExecTrace {
+        let mut regs = CoreRegs::default();
+        if let Some(ty) = self.ty {
+            regs.set_n(RegA::A16, Reg32::Reg0, ty);
+        }
+        let mut steps = vec![];
+        let mut count = 0usize;
+        let mut complexity = 0u64;
+        let mut call = Some(entry_point);
+
+        let stop = 'exec: loop {
+            let Some(site) = call else {
+                break TraceStop::Completed;
+            };
+            let Some(lib) = scripts.get(&site.lib) else {
+                break TraceStop::LibAbsent(site.lib);
+            };
+            let mut cursor = Cursor::with(&lib.code, &lib.data, &lib.libs);
+            if cursor.seek(site.pos).is_err() {
+                break TraceStop::InvalidInstruction(site);
+            }
+            call = None;
+            while !cursor.is_eof() {
+                let site = LibSite::with(cursor.pos(), site.lib);
+                let Ok(instr) = Instr::<RgbIsa<S>>::decode(&mut cursor) else {
+                    break 'exec TraceStop::InvalidInstruction(site);
+                };
+                if breakpoint.is_hit(site, &regs) {
+                    break 'exec TraceStop::Breakpoint(site);
+                }
+                if self.step_limit.is_some_and(|limit| count >= limit) {
+                    regs.set_failure();
+                    break 'exec TraceStop::StepLimit(count);
+                }
+                let src_regs = Self::reg_values(&regs, instr.src_regs());
+                let next = self.exec(&instr, &mut regs, site);
+                count += 1;
+                complexity = complexity.saturating_add(instr.complexity());
+                steps.push(TraceStep {
+                    site,
+                    instr: instr.to_string(),
+                    src_regs,
+                    dst_regs: Self::reg_values(&regs, instr.dst_regs()),
+                    st0: regs.status(),
+                    complexity,
+                });
+                // Instructions stopping the execution (like `fail`) may have maximal
+                // complexity, which must not be reported as exhaustion of the limit.
+                if !matches!(next, ExecStep::Stop)
+                    && self
+                        .complexity_limit
+                        .is_some_and(|limit| complexity >= limit)
+                {
+                    regs.set_failure();
+                    break 'exec TraceStop::ComplexityLimit(complexity);
+                }
+                match next {
+                    ExecStep::Stop => break 'exec TraceStop::Completed,
+                    ExecStep::Next => continue,
+                    ExecStep::Jump(pos) => {
+                        if cursor.seek(pos).is_err() {
+                            break 'exec TraceStop::InvalidInstruction(site);
+                        }
+                    }
+                    ExecStep::Call(site) => {
+                        call = Some(site);
+                        break;
+                    }
+                }
+            }
+        };
+
+        let error_code = regs.get_n(RegA::A8, Reg32::Reg0).map(u8::from);
+        ExecTrace {
+            opid: self.opid,
+            entry_point,
+            steps,
+            stop,
+            st0: regs.status(),
+            error_code,
+            count,
+            complexity,
+        }
+    }
+
+    fn reg_values(regs: &CoreRegs, list: BTreeSet<Reg>) -> Vec<(Reg, RegValue)> {
+        list.into_iter().map(|reg| (reg, regs.get(reg))).collect()
+    }
+
+    fn exec(&self, instr: &Instr<RgbIsa<S>>, regs: &mut CoreRegs, site: LibSite) -> ExecStep {
+        match instr {
+            Instr::ControlFlow(instr) => instr.exec(regs, site, &()),
+            Instr::Put(instr) => instr.exec(regs, site, &()),
+            Instr::Move(instr) => instr.exec(regs, site, &()),
+            Instr::Cmp(instr) => instr.exec(regs, site, &()),
+            Instr::Arithmetic(instr) => instr.exec(regs, site, &()),
+            Instr::Bitwise(instr) => instr.exec(regs, site, &()),
+            Instr::Bytes(instr) => instr.exec(regs, site, &()),
+            Instr::Digest(instr) => instr.exec(regs, site, &()),
+            Instr::ExtensionCodes(RgbIsa::Contract(op)) => self.exec_contract(op, regs),
+            Instr::ExtensionCodes(RgbIsa::Timechain(op)) => op.exec(regs, site, &()),
+            Instr::Nop => ExecStep::Next,
+            // Reserved and unsupported instructions, which must set `st0` to `false`.
+            _ => ControlFlowOp::Fail.exec(regs, site, &()),
+        }
+    }
+
+    /// Evaluates RGB contract instruction with the same semantics as rgb-core
+    /// VM.
+    fn exec_contract(&self, op: &ContractOp<S>, regs: &mut CoreRegs) -> ExecStep {
+        macro_rules! fail {
+            () => {{
+                regs.set_failure();
+                return ExecStep::Stop;
+            }};
+        }
+        macro_rules! commitments {
+            ($assignments:expr, $state_type:expr) => {{
+                let Some(TypedAssigns::Fungible(state)) = $assignments.get($state_type) else {
+                    fail!()
+                };
+                state
+                    .iter()
+                    .map(Assign::to_confidential_state)
+                    .map(|s| s.commitment.into_inner())
+                    .collect::<Vec<_>>()
+            }};
+        }
+        macro_rules! check_sum {
+            ($inputs:expr, $outputs:expr) => {
+                if !secp256k1_zkp::verify_commitments_sum_to_equal(
+                    secp256k1_zkp::SECP256K1,
+                    &$inputs,
+                    &$outputs,
+                ) {
+                    fail!()
+                }
+            };
+        }
+        macro_rules! declared_sum {
+            ($state_type:expr) => {{
+                let Some(sum) = *regs.get_n(RegA::A64, Reg32::Reg0) else {
+                    fail!()
+                };
+                let Some(tag) = self.asset_tags.get($state_type) else {
+                    fail!()
+                };
+                let sum = RevealedValue::with_blinding(u64::from(sum), BlindingFactor::EMPTY, *tag);
+                [PedersenCommitment::commit(&sum).into_inner()]
+            }};
+        }
+
+        match op {
+            ContractOp::CnP(state_type, reg) => {
+                let count = self.prev_state.get(state_type).map(TypedAssigns::len_u16);
+                regs.set_n(RegA::A16, *reg, count);
+            }
+            ContractOp::CnS(state_type, reg) => {
+                let count = self.owned_state.get(state_type).map(TypedAssigns::len_u16);
+                regs.set_n(RegA::A16, *reg, count);
+            }
+            ContractOp::CnG(state_type, reg) => {
+                regs.set_n(RegA::A8, *reg, self.global.get(state_type).map(|a| a.len_u16()));
+            }
+            ContractOp::CnC(state_type, reg) => {
+                let count = match self.contract_state.global(*state_type) {
+                    Ok(mut global) => Some(global.size().to_u32()),
+                    Err(_) => None,
+                };
+                regs.set_n(RegA::A32, *reg, count);
+            }
+            ContractOp::LdP(state_type, reg_16, reg) => {
+                let Some(index) = *regs.get_n(RegA::A16, *reg_16) else {
+                    fail!()
+                };
+                let Some(Ok(state)) = self
+                    .prev_state
+                    .get(state_type)
+                    .map(|a| a.as_structured_state_at(index.into()))
+                else {
+                    fail!()
+                };
+                let state = state.map(|s| s.value.as_inner().clone());
+                regs.set_s(*reg, state);
+            }
+            ContractOp::LdS(state_type, reg_16, reg) => {
+                let Some(index) = *regs.get_n(RegA::A16, *reg_16) else {
+                    fail!()
+                };
+                let Some(Ok(state)) = self
+                    .owned_state
+                    .get(state_type)
+                    .map(|a| a.as_structured_state_at(index.into()))
+                else {
+                    fail!()
+                };
+                let state = state.map(|s| s.value.as_inner().clone());
+                regs.set_s(*reg, state);
+            }
+            ContractOp::LdF(state_type, reg_16, reg) => {
+                let Some(index) = *regs.get_n(RegA::A16, *reg_16) else {
+                    fail!()
+                };
+                let Some(Ok(state)) = self
+                    .owned_state
+                    .get(state_type)
+                    .map(|a| a.as_fungible_state_at(index.into()))
+                else {
+                    fail!()
+                };
+                regs.set_n(RegA::A64, *reg, state.map(|s| s.value.as_u64()));
+            }
+            ContractOp::LdG(state_type, reg_8, reg_s) => {
+                let Some(index) = *regs.get_n(RegA::A8, *reg_8) else {
+                    fail!()
+                };
+                let index: u8 = index.into();
+                let Some(state) = self
+                    .global
+                    .get(state_type)
+                    .and_then(|a| a.get(index as usize))
+                else {
+                    fail!()
+                };
+                regs.set_s(*reg_s, Some(state.as_inner()));
+            }
+            ContractOp::LdC(state_type, reg_32, reg_s) => {
+                let Ok(mut global) = self.contract_state.global(*state_type) else {
+                    fail!()
+                };
+                let Some(index) = *regs.get_n(RegA::A32, *reg_32) else {
+                    fail!()
+                };
+                let Ok(index) = u24::try_from(u32::from(index)) else {
+                    fail!()
+                };
+                let Some(state) = global.nth(index) else {
+                    fail!()
+                };
+                regs.set_s(*reg_s, Some(state.borrow().as_inner()));
+            }
+            ContractOp::LdM(type_id, reg) => {
+                let Some(meta) = self.metadata.get(type_id) else {
+                    fail!()
+                };
+                regs.set_s(*reg, Some(meta.to_inner()));
+            }
+            ContractOp::Pcvs(state_type) => {
+                let inputs = commitments!(self.prev_state, state_type);
+                let outputs = commitments!(self.owned_state, state_type);
+                check_sum!(inputs, outputs);
+            }
+            ContractOp::Pcas(state_type) => {
+                let inputs = declared_sum!(state_type);
+                let outputs = commitments!(self.owned_state, state_type);
+                check_sum!(inputs, outputs);
+            }
+            ContractOp::Pcps(state_type) => {
+                let inputs = declared_sum!(state_type);
+                let outputs = commitments!(self.prev_state, state_type);
+                check_sum!(inputs, outputs);
+            }
+            ContractOp::Fail(..) => fail!(),
+        }
+        ExecStep::Next
+    }
+}
+
+/// Collects state assigned to the operation inputs, in the same way as
+/// rgb-core validation does.
+fn prev_state(
+    consignment: &impl ConsignmentApi,
+    inputs: impl IntoIterator<Item = Opout>,
+) -> Assignments<GraphSeal> {
+    let mut assignments = BTreeMap::<_, TypedAssigns<GraphSeal>>::new();
+    for Opout { op, ty, no } in inputs {
+        let Some(prev) = consignment
+            .operation(op)
+            .and_then(|op| op.assignments_by_type(ty))
+        else {
+            continue;
+        };
+        let no = no as usize;
+        let entry = assignments.entry(ty);
+        match prev {
+            TypedAssigns::Declarative(prev) => {
+                if let Some(assign) = prev.get(no) {
+                    let typed = entry.or_insert_with(|| TypedAssigns::Declarative(none!()));
+                    if let Some(typed) = typed.as_declarative_mut() {
+                        typed.push(assign.clone()).expect("same size");
+                    }
+                }
+            }
+            TypedAssigns::Fungible(prev) => {
+                if let Some(assign) = prev.get(no) {
+                    let typed = entry.or_insert_with(|| TypedAssigns::Fungible(none!()));
+                    if let Some(typed) = typed.as_fungible_mut() {
+                        typed.push(assign.clone()).expect("same size");
+                    }
+                }
+            }
+            TypedAssigns::Structured(prev) => {
+                if let Some(assign) = prev.get(no) {
+                    let typed = entry.or_insert_with(|| TypedAssigns::Structured(none!()));
+                    if let Some(typed) = typed.as_structured_mut() {
+                        typed.push(assign.clone()).expect("same size");
+                    }
+                }
+            }
+            TypedAssigns::Attachment(prev) => {
+                if let Some(assign) = prev.get(no) {
+                    let typed = entry.or_insert_with(|| TypedAssigns::Attachment(none!()));
+                    if let Some(typed) = typed.as_attachment_mut() {
+                        typed.push(assign.clone()).expect("same size");
+                    }
+                }
+            }
+        }
+    }
+    Assignments::from(
+        amplify::confinement::Confined::try_from(assignments)
+            .expect("collection is assembled from another collection with the same size limit"),
+    )
+}
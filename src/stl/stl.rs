@@ -34,14 +34,14 @@ use super::{
     LIB_NAME_RGB_CONTRACT, LIB_NAME_RGB_STORAGE,
 };
 use crate::containers::{Contract, Kit, Transfer};
-use crate::persistence::{MemIndex, MemStash, MemState};
+use crate::persistence::{MemArchive, MemIndex, MemStash, MemState};
 use crate::stl::ProofOfReserves;
 use crate::LIB_NAME_RGB_STD;
 
 /// Strict types id for the library providing standard data types which may be
 /// used in RGB smart contracts.
 pub const LIB_ID_RGB_STORAGE: &str =
-    "stl:YBgLtVmT-FuzNBPe-1fxIqay-JW4Evd5-Za7fm9w-GLF6JAg#transit-xray-giraffe";
+    "stl:s3j2nSQD-z$BKY8I-eeStDzR-QMUmQbb-1VVZltF-1yCAYns#stock-survive-package";
 
 /// Strict types id for the library providing standard data types which may be
 /// used in RGB smart contracts.
@@ -50,7 +50,7 @@ pub const LIB_ID_RGB_CONTRACT: &str =
 
 /// Strict types id for the library representing of RGB StdLib data types.
 pub const LIB_ID_RGB_STD: &str =
-    "stl:H1HLPfyC-5YLlAk!-KWhcUo1-0vtex!9-ODxSmIA-zj1J$qc#western-craft-bogart";
+    "stl:R9Zj0$vn-HP7VIGR-EzTDVzy-9tfxgIL-9p!JXVo-rwp$Sd8#samba-memo-charm";
 
 fn _rgb_std_stl() -> Result<TypeLib, CompileError> {
     LibBuilder::new(libname!(LIB_NAME_RGB_STD), tiny_bset! {
@@ -100,6 +100,7 @@ fn _rgb_storage_stl() -> Result<TypeLib, CompileError> {
     })
     .transpile::<MemIndex>()
     .transpile::<MemState>()
+    .transpile::<MemArchive>()
     .transpile::<MemStash>()
     .compile()
 }
@@ -119,6 +120,42 @@ pub fn rgb_storage_stl() -> TypeLib {
     _rgb_storage_stl().expect("invalid strict type RGBStorage library")
 }
 
+/// Strict type names of the containers defined by the library, which may be
+/// used for generating codecs from [`rgb_containers_sys`].
+pub const CONTAINER_TYPES: [(&str, &str); 3] = [
+    ("Transfer", "RGBStd.Consignmenttrue"),
+    ("Contract", "RGBStd.Consignmentfalse"),
+    ("Kit", "RGBStd.Kit"),
+];
+
+/// Generates complete strict type system describing RGB container formats,
+/// including all the libraries they depend on.
+///
+/// Third-party implementations may use the system (in its textual or armored
+/// form) to generate compatible container codecs; the root types are listed
+/// in [`CONTAINER_TYPES`].
+pub fn rgb_containers_sys() -> SymbolicSys {
+    let mut builder = SystemBuilder::new();
+    for lib in [
+        std_stl(),
+        strict_types_stl(),
+        commit_verify_stl(),
+        bp_tx_stl(),
+        bp_core_stl(),
+        aluvm_stl(),
+        rgb_commit_stl(),
+        rgb_logic_stl(),
+        rgb_std_stl(),
+    ] {
+        builder = builder
+            .import(lib)
+            .expect("duplicated library in RGB containers type system");
+    }
+    builder
+        .finalize()
+        .expect("not all libraries present in RGB containers type system")
+}
+
 #[derive(Debug)]
 pub struct StandardTypes(SymbolicSys);
 
@@ -172,6 +209,14 @@ mod test {
         assert_eq!(lib.id().to_string(), LIB_ID_RGB_STD);
     }
 
+    #[test]
+    fn containers_sys() {
+        let sys = rgb_containers_sys();
+        for (_, name) in CONTAINER_TYPES {
+            assert!(sys.type_tree(name).is_some(), "{name} is absent in the type system");
+        }
+    }
+
     #[test]
     fn storage_lib_id() {
         let lib = rgb_storage_stl();
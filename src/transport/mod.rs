@@ -0,0 +1,136 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transports for exchanging consignments and receipts between wallets.
+//!
+//! The library doesn't perform network I/O itself: transport adapters
+//! implement message framing on top of a client provided by the wallet, which
+//! owns network connections and keys.
+
+mod network;
+mod uri;
+#[cfg(feature = "nostr")]
+pub mod nostr;
+
+use std::error::Error;
+
+use amplify::confinement::{self, Confined, U32 as U32MAX};
+use strict_encoding::{DeserializeError, StrictDeserialize, StrictSerialize};
+
+pub use network::{
+    network_config, set_network_config, NetworkAware, NetworkConfig, NetworkError, Socks5Proxy,
+    TOR_DEFAULT_SOCKS5,
+};
+pub use uri::{ConsignmentLocation, RgbUri, UriParseError};
+
+use crate::containers::{SignedReceipt, Transfer};
+use crate::LIB_NAME_RGB_STD;
+
+/// Errors decoding [`TransportMsg`] received from the transport.
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum TransportMsgError {
+    /// message exceeds the maximal allowed size: {0}
+    #[from]
+    TooLarge(confinement::Error),
+
+    /// invalid message data: {0}
+    #[from]
+    Decode(DeserializeError),
+}
+
+/// Message exchanged between wallets over a consignment transport.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, PartialEq, Debug, From)]
+#[derive(StrictType, StrictDumb, StrictEncode, StrictDecode)]
+#[strict_type(lib = LIB_NAME_RGB_STD, tags = custom, dumb = Self::Receipt(strict_dumb!()))]
+pub enum TransportMsg {
+    #[from]
+    #[strict_type(tag = 0)]
+    Transfer(Transfer),
+
+    #[from]
+    #[strict_type(tag = 1)]
+    Receipt(SignedReceipt),
+}
+
+impl StrictSerialize for TransportMsg {}
+impl StrictDeserialize for TransportMsg {}
+
+impl TransportMsg {
+    /// Serializes message into bytes sent over the transport.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_strict_serialized::<U32MAX>()
+            .expect("message exceeds 4GB")
+            .release()
+    }
+
+    /// Deserializes message received from the transport.
+    pub fn from_bytes(data: impl AsRef<[u8]>) -> Result<Self, TransportMsgError> {
+        let data = Confined::try_from(data.as_ref().to_vec())?;
+        Self::from_strict_serialized::<U32MAX>(data).map_err(TransportMsgError::from)
+    }
+}
+
+/// Transport for exchanging transfers and receipts between wallets.
+pub trait ConsignmentTransport {
+    /// Address of a remote party.
+    type Addr;
+    type Error: Error;
+
+    /// Sends a message to the remote party.
+    fn send(&mut self, to: &Self::Addr, msg: &TransportMsg) -> Result<(), Self::Error>;
+
+    /// Receives all messages which were fully delivered since the last call,
+    /// together with the address of their senders.
+    fn receive(&mut self) -> Result<Vec<(Self::Addr, TransportMsg)>, Self::Error>;
+
+    #[inline]
+    fn send_transfer(&mut self, to: &Self::Addr, transfer: Transfer) -> Result<(), Self::Error> {
+        self.send(to, &TransportMsg::Transfer(transfer))
+    }
+
+    #[inline]
+    fn send_receipt(
+        &mut self,
+        to: &Self::Addr,
+        receipt: SignedReceipt,
+    ) -> Result<(), Self::Error> {
+        self.send(to, &TransportMsg::Receipt(receipt))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+
+    #[test]
+    fn msg_from_bytes() {
+        let msg = TransportMsg::Transfer(Transfer::strict_dumb());
+        assert_eq!(TransportMsg::from_bytes(msg.to_bytes()).unwrap(), msg);
+        assert!(matches!(TransportMsg::from_bytes([0xFFu8; 4]), Err(TransportMsgError::Decode(_))));
+        let mut data = msg.to_bytes();
+        data.push(0);
+        assert!(matches!(TransportMsg::from_bytes(data), Err(TransportMsgError::Decode(_))));
+    }
+}
@@ -0,0 +1,201 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Network configuration shared by transports and resolvers.
+//!
+//! Clients performing network I/O on behalf of the library (relay clients,
+//! witness resolvers, broadcasters) implement [`NetworkAware`] and are
+//! configured with a [`NetworkConfig`], which is either given explicitly or
+//! taken from the process-wide configuration set with [`set_network_config`].
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Default address of the Tor SOCKS5 proxy.
+pub const TOR_DEFAULT_SOCKS5: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::LOCALHOST), 9050);
+
+static NETWORK_CONFIG: RwLock<Option<NetworkConfig>> = RwLock::new(None);
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum NetworkError {
+    /// connection to the clearnet endpoint '{0}' is forbidden by the network
+    /// configuration allowing only Tor onion services.
+    ClearnetForbidden(String),
+
+    /// endpoint '{0}' has invalid format.
+    InvalidEndpoint(String),
+}
+
+/// SOCKS5 proxy configuration.
+///
+/// Host names are resolved by the proxy (`socks5h`), ensuring that no DNS
+/// requests leak outside of Tor.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Socks5Proxy {
+    pub addr: SocketAddr,
+    /// Username and password for the proxy authentication. With Tor, distinct
+    /// credentials result in distinct circuits (stream isolation).
+    pub auth: Option<(String, String)>,
+}
+
+impl Socks5Proxy {
+    pub fn new(addr: SocketAddr) -> Self { Socks5Proxy { addr, auth: None } }
+
+    pub fn with_auth(addr: SocketAddr, user: impl ToString, password: impl ToString) -> Self {
+        Socks5Proxy {
+            addr,
+            auth: Some((user.to_string(), password.to_string())),
+        }
+    }
+}
+
+/// Configuration for all network connections made by transports and
+/// resolvers.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct NetworkConfig {
+    /// Proxy which all connections must be routed through; if absent, direct
+    /// connections are used.
+    pub proxy: Option<Socks5Proxy>,
+    /// Allow connections only to Tor onion services.
+    pub onion_only: bool,
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig {
+            proxy: None,
+            onion_only: false,
+            connect_timeout: Duration::from_secs(30),
+            request_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Configuration routing all connections via Tor SOCKS5 proxy at the
+    /// provided address.
+    pub fn tor(proxy: SocketAddr) -> Self {
+        NetworkConfig {
+            proxy: Some(Socks5Proxy::new(proxy)),
+            // Tor connections are slow to establish
+            connect_timeout: Duration::from_secs(120),
+            ..default!()
+        }
+    }
+
+    /// Configuration allowing connections only to Tor onion services via Tor
+    /// SOCKS5 proxy at the provided address.
+    pub fn tor_onion_only(proxy: SocketAddr) -> Self {
+        NetworkConfig {
+            onion_only: true,
+            ..Self::tor(proxy)
+        }
+    }
+
+    /// Checks whether connection to the endpoint (an URL or `host:port`
+    /// string) is allowed by the configuration.
+    pub fn check_endpoint(&self, endpoint: &str) -> Result<(), NetworkError> {
+        let host = endpoint_host(endpoint)
+            .ok_or_else(|| NetworkError::InvalidEndpoint(endpoint.to_owned()))?;
+        if self.onion_only && !host.ends_with(".onion") {
+            return Err(NetworkError::ClearnetForbidden(endpoint.to_owned()));
+        }
+        Ok(())
+    }
+}
+
+/// Network clients which can be configured with [`NetworkConfig`].
+pub trait NetworkAware {
+    /// Applies the configuration to all subsequent connections.
+    fn set_network_config(&mut self, config: &NetworkConfig);
+}
+
+/// Sets process-wide network configuration used by transports and resolvers
+/// which are not given an explicit configuration.
+pub fn set_network_config(config: NetworkConfig) {
+    *NETWORK_CONFIG
+        .write()
+        .expect("poisoned network config lock") = Some(config);
+}
+
+/// Returns process-wide network configuration.
+pub fn network_config() -> NetworkConfig {
+    NETWORK_CONFIG
+        .read()
+        .expect("poisoned network config lock")
+        .clone()
+        .unwrap_or_default()
+}
+
+fn endpoint_host(endpoint: &str) -> Option<&str> {
+    let rest = endpoint
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(endpoint);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let authority = authority
+        .rsplit_once('@')
+        .map(|(_, host)| host)
+        .unwrap_or(authority);
+    let host = match authority.strip_prefix('[') {
+        Some(ipv6) => ipv6.split_once(']')?.0,
+        None => authority
+            .rsplit_once(':')
+            .map(|(host, _)| host)
+            .unwrap_or(authority),
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn endpoints() {
+        assert_eq!(endpoint_host("wss://relay.example.org/path"), Some("relay.example.org"));
+        assert_eq!(endpoint_host("ssl://user@electrum.local:50002"), Some("electrum.local"));
+        assert_eq!(endpoint_host("[::1]:8332"), Some("::1"));
+        assert_eq!(endpoint_host("https://:443"), None);
+
+        let config = NetworkConfig::tor_onion_only(TOR_DEFAULT_SOCKS5);
+        assert_eq!(config.proxy, Some(Socks5Proxy::new(TOR_DEFAULT_SOCKS5)));
+        assert!(
+            config
+                .check_endpoint("http://abcdef.onion:3000/api")
+                .is_ok()
+        );
+        assert_eq!(
+            config.check_endpoint("https://mempool.space/api"),
+            Err(NetworkError::ClearnetForbidden(s!("https://mempool.space/api")))
+        );
+        assert!(
+            NetworkConfig::default()
+                .check_endpoint("https://mempool.space/api")
+                .is_ok()
+        );
+    }
+}
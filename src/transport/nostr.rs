@@ -0,0 +1,482 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consignment transport over Nostr encrypted direct messages.
+//!
+//! Messages are split into chunks fitting relay event size limits. Each chunk
+//! is sent as a separate direct message with the content of the form
+//! `rgb1:<msg_id>:<index>:<total>:<data>`, where `msg_id` is a hex-encoded
+//! tagged hash of the whole message, `index` and `total` are decimal numbers
+//! and `data` is Base85 (RFC 1924) encoded chunk payload.
+//!
+//! Partially received messages are kept in memory until all their chunks
+//! arrive, within the bounds defined by [`NostrLimits`]: messages exceeding
+//! the size limits are dropped, the oldest messages are evicted once the total
+//! limits are reached and messages which are not completed within the timeout
+//! are expired.
+//!
+//! Encryption (NIP-04 or NIP-44), event signing and relay connectivity are
+//! provided by the wallet via [`NostrClient`] implementation.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::str::FromStr;
+
+use amplify::hex::{self, FromHex};
+use amplify::Bytes32;
+use chrono::Utc;
+use commit_verify::{DigestExt, Sha256};
+
+use super::{network_config, ConsignmentTransport, NetworkAware, NetworkConfig, TransportMsg};
+
+/// Nostr event kind for encrypted direct messages (NIP-04).
+pub const NOSTR_KIND_ENCRYPTED_DM: u16 = 4;
+/// Prefix of the direct messages carrying RGB message chunks.
+pub const NOSTR_CHUNK_PREFIX: &str = "rgb1";
+/// Default size of the chunk payload, which keeps encrypted and encoded events
+/// under 64kB limit used by the most of the relays.
+pub const NOSTR_DEFAULT_CHUNK_SIZE: usize = 32 * 1024;
+
+/// Limits on the messages which are partially received by the
+/// [`NostrTransport`], protecting it from memory exhaustion by the remote
+/// parties.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct NostrLimits {
+    /// Maximum size of a single message, in bytes.
+    pub max_msg_size: usize,
+    /// Maximum number of partially received messages.
+    pub max_pending: usize,
+    /// Maximum size of all partially received messages, in bytes.
+    pub max_pending_size: usize,
+    /// Time, in seconds, after which partially received message is dropped.
+    pub pending_timeout: i64,
+}
+
+impl Default for NostrLimits {
+    fn default() -> Self { Self::STANDARD }
+}
+
+impl NostrLimits {
+    /// Default limits, sufficient for the most of consignments.
+    pub const STANDARD: Self = NostrLimits {
+        max_msg_size: 64 * 1024 * 1024,
+        max_pending: 64,
+        max_pending_size: 256 * 1024 * 1024,
+        pending_timeout: 3600,
+    };
+}
+
+const NOSTR_MSG_TAG: &str = "urn:lnp-bp:rgb:nostr-msg#2024-10-14";
+
+/// Nostr public key (x-only secp256k1 key).
+#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
+#[wrapper(Deref, BorrowSlice, Hex, Index, RangeOps)]
+#[display(LowerHex)]
+pub struct NostrPubkey(
+    #[from]
+    #[from([u8; 32])]
+    Bytes32,
+);
+
+impl FromStr for NostrPubkey {
+    type Err = hex::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Self::from_hex(s) }
+}
+
+/// Client providing access to Nostr relays for the [`NostrTransport`].
+///
+/// The client must route all relay connections according to the network
+/// configuration it is given and check relay endpoints with
+/// [`NetworkConfig::check_endpoint`].
+pub trait NostrClient: NetworkAware {
+    type Error: Error;
+
+    /// Encrypts the content for the recipient, signs and publishes encrypted
+    /// direct message event to the relays.
+    fn publish_dm(&mut self, to: NostrPubkey, content: String) -> Result<(), Self::Error>;
+
+    /// Returns decrypted content of all direct messages received since the
+    /// last call.
+    fn fetch_dms(&mut self) -> Result<Vec<(NostrPubkey, String)>, Self::Error>;
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error)]
+#[display(doc_comments)]
+pub enum NostrError<E: Error> {
+    /// nostr client error: {0}
+    Client(E),
+
+    /// message of {0} bytes requires more than 65535 chunks.
+    TooLarge(usize),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct Pending {
+    total: u16,
+    started: i64,
+    size: usize,
+    chunks: BTreeMap<u16, Vec<u8>>,
+}
+
+/// Consignment transport over Nostr encrypted direct messages.
+///
+/// Direct messages which are not RGB message chunks are ignored. Malformed
+/// chunks, messages violating [`NostrLimits`] and messages which reassembled
+/// data don't match their id are dropped.
+#[derive(Debug)]
+pub struct NostrTransport<C: NostrClient> {
+    client: C,
+    chunk_size: usize,
+    limits: NostrLimits,
+    pending: BTreeMap<(NostrPubkey, Bytes32), Pending>,
+}
+
+impl<C: NostrClient> NostrTransport<C> {
+    /// Constructs transport configuring the client with the process-wide
+    /// network configuration.
+    pub fn new(client: C) -> Self { Self::with_chunk_size(client, NOSTR_DEFAULT_CHUNK_SIZE) }
+
+    /// Constructs transport configuring the client with the provided network
+    /// configuration.
+    pub fn with_network_config(mut client: C, config: &NetworkConfig) -> Self {
+        client.set_network_config(config);
+        Self::with_raw_client(client, NOSTR_DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Constructs transport with a custom chunk size, configuring the client
+    /// with the process-wide network configuration.
+    ///
+    /// # Panics
+    ///
+    /// If the chunk size is zero.
+    pub fn with_chunk_size(mut client: C, chunk_size: usize) -> Self {
+        client.set_network_config(&network_config());
+        Self::with_raw_client(client, chunk_size)
+    }
+
+    fn with_raw_client(client: C, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "zero chunk size");
+        NostrTransport {
+            client,
+            chunk_size,
+            limits: default!(),
+            pending: none!(),
+        }
+    }
+
+    /// Sets limits on the partially received messages.
+    pub fn with_limits(mut self, limits: NostrLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn limits(&self) -> NostrLimits { self.limits }
+
+    pub fn client(&self) -> &C { &self.client }
+
+    pub fn client_mut(&mut self) -> &mut C { &mut self.client }
+
+    pub fn into_client(self) -> C { self.client }
+
+    /// Number of messages which are only partially received.
+    pub fn pending_count(&self) -> usize { self.pending.len() }
+
+    /// Total size of the data of the partially received messages, in bytes.
+    pub fn pending_size(&self) -> usize { self.pending.values().map(|p| p.size).sum() }
+
+    fn msg_id(data: &[u8]) -> Bytes32 {
+        let mut hasher = Sha256::from_tag(NOSTR_MSG_TAG);
+        hasher.input_raw(data);
+        hasher.finish().into()
+    }
+
+    fn expire_pending(&mut self, now: i64) {
+        let timeout = self.limits.pending_timeout;
+        self.pending
+            .retain(|_, pending| now.saturating_sub(pending.started) < timeout);
+    }
+
+    /// Evicts the oldest partially received messages until the total limits
+    /// are satisfied, evicting the `current` message only if it doesn't fit
+    /// the limits alone.
+    fn enforce_limits(&mut self, current: (NostrPubkey, Bytes32)) {
+        while self.pending.len() > self.limits.max_pending
+            || self.pending_size() > self.limits.max_pending_size
+        {
+            let oldest = self
+                .pending
+                .iter()
+                .filter(|(key, _)| **key != current)
+                .min_by_key(|(_, pending)| pending.started)
+                .map(|(key, _)| *key)
+                .unwrap_or(current);
+            self.pending.remove(&oldest);
+            if oldest == current {
+                break;
+            }
+        }
+    }
+
+    fn accept_chunk(&mut self, from: NostrPubkey, content: &str, now: i64) -> Option<TransportMsg> {
+        let mut parts = content.splitn(5, ':');
+        if parts.next() != Some(NOSTR_CHUNK_PREFIX) {
+            return None;
+        }
+        let msg_id = Bytes32::from_hex(parts.next()?).ok()?;
+        let index = u16::from_str(parts.next()?).ok()?;
+        let total = u16::from_str(parts.next()?).ok()?;
+        let data = base85::decode(parts.next()?).ok()?;
+        if index >= total || data.is_empty() {
+            return None;
+        }
+
+        let key = (from, msg_id);
+        let pending = self.pending.entry(key).or_insert_with(|| Pending {
+            total,
+            started: now,
+            size: 0,
+            chunks: none!(),
+        });
+        if pending.total != total || pending.chunks.contains_key(&index) {
+            return None;
+        }
+        if pending.size + data.len() > self.limits.max_msg_size {
+            self.pending.remove(&key);
+            return None;
+        }
+        pending.size += data.len();
+        pending.chunks.insert(index, data);
+        if pending.chunks.len() < total as usize {
+            self.enforce_limits(key);
+            return None;
+        }
+
+        let pending = self.pending.remove(&key)?;
+        let data = pending.chunks.into_values().flatten().collect::<Vec<_>>();
+        if Self::msg_id(&data) != msg_id {
+            return None;
+        }
+        TransportMsg::from_bytes(data).ok()
+    }
+}
+
+impl<C: NostrClient> ConsignmentTransport for NostrTransport<C> {
+    type Addr = NostrPubkey;
+    type Error = NostrError<C::Error>;
+
+    fn send(&mut self, to: &NostrPubkey, msg: &TransportMsg) -> Result<(), Self::Error> {
+        let data = msg.to_bytes();
+        let msg_id = Self::msg_id(&data);
+        let chunks = data.chunks(self.chunk_size).collect::<Vec<_>>();
+        let total = u16::try_from(chunks.len()).map_err(|_| NostrError::TooLarge(data.len()))?;
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let content = format!(
+                "{NOSTR_CHUNK_PREFIX}:{msg_id:x}:{index}:{total}:{}",
+                base85::encode(chunk)
+            );
+            self.client
+                .publish_dm(*to, content)
+                .map_err(NostrError::Client)?;
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Vec<(NostrPubkey, TransportMsg)>, Self::Error> {
+        let dms = self.client.fetch_dms().map_err(NostrError::Client)?;
+        let now = Utc::now().timestamp();
+        self.expire_pending(now);
+        Ok(dms
+            .into_iter()
+            .filter_map(|(from, content)| Some((from, self.accept_chunk(from, &content, now)?)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+
+    use strict_encoding::StrictDumb;
+
+    use super::*;
+    use crate::containers::Transfer;
+
+    #[derive(Debug, Default)]
+    struct Relay {
+        outbox: Vec<(NostrPubkey, String)>,
+        inbox: Vec<(NostrPubkey, String)>,
+        config: Option<NetworkConfig>,
+    }
+
+    impl NetworkAware for Relay {
+        fn set_network_config(&mut self, config: &NetworkConfig) {
+            self.config = Some(config.clone());
+        }
+    }
+
+    impl NostrClient for Relay {
+        type Error = Infallible;
+
+        fn publish_dm(&mut self, to: NostrPubkey, content: String) -> Result<(), Self::Error> {
+            self.outbox.push((to, content));
+            Ok(())
+        }
+
+        fn fetch_dms(&mut self) -> Result<Vec<(NostrPubkey, String)>, Self::Error> {
+            Ok(std::mem::take(&mut self.inbox))
+        }
+    }
+
+    #[test]
+    fn chunked_transfer() {
+        let alice = NostrPubkey::from([1u8; 32]);
+        let bob = NostrPubkey::from([2u8; 32]);
+        assert_eq!(NostrPubkey::from_str(&bob.to_string()).unwrap(), bob);
+
+        let mut sender = NostrTransport::with_chunk_size(Relay::default(), 16);
+        let transfer = Transfer::strict_dumb();
+        sender.send_transfer(&bob, transfer.clone()).unwrap();
+        let mut outbox = std::mem::take(&mut sender.client_mut().outbox);
+        assert!(outbox.len() > 1);
+        assert!(outbox.iter().all(|(to, _)| *to == bob));
+
+        let mut receiver = NostrTransport::new(Relay::default());
+        let last = outbox.remove(0);
+        outbox.reverse();
+        receiver.client_mut().inbox = outbox
+            .into_iter()
+            .map(|(_, content)| (alice, content))
+            .chain([(alice, s!("hello bob"))])
+            .collect();
+        assert!(receiver.receive().unwrap().is_empty());
+        assert_eq!(receiver.pending_count(), 1);
+
+        assert_eq!(receiver.client().config, Some(NetworkConfig::default()));
+        receiver.client_mut().inbox = vec![(alice, last.1)];
+        assert_eq!(receiver.receive().unwrap(), vec![(alice, TransportMsg::Transfer(transfer))]);
+        assert_eq!(receiver.pending_count(), 0);
+    }
+
+    fn chunks() -> Vec<String> {
+        let mut sender = NostrTransport::with_chunk_size(Relay::default(), 16);
+        sender
+            .send_transfer(&NostrPubkey::from([2u8; 32]), Transfer::strict_dumb())
+            .unwrap();
+        let outbox = std::mem::take(&mut sender.client_mut().outbox);
+        assert!(outbox.len() > 3);
+        outbox.into_iter().map(|(_, content)| content).collect()
+    }
+
+    fn chunks_id(chunk: &str) -> Bytes32 {
+        Bytes32::from_hex(chunk.split(':').nth(1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn message_size_limit() {
+        let alice = NostrPubkey::from([1u8; 32]);
+        let chunks = chunks();
+        let limits = NostrLimits {
+            max_msg_size: 32,
+            ..default!()
+        };
+        let mut receiver = NostrTransport::new(Relay::default()).with_limits(limits);
+        assert!(receiver.accept_chunk(alice, &chunks[0], 0).is_none());
+        assert!(receiver.accept_chunk(alice, &chunks[0], 0).is_none());
+        assert!(receiver.accept_chunk(alice, &chunks[1], 0).is_none());
+        assert_eq!(receiver.pending_size(), 32);
+        assert!(receiver.accept_chunk(alice, &chunks[2], 0).is_none());
+        assert_eq!(receiver.pending_count(), 0);
+        assert_eq!(receiver.pending_size(), 0);
+
+        let empty = format!("{}:{}", chunks[0].rsplit_once(':').unwrap().0, "");
+        assert!(receiver.accept_chunk(alice, &empty, 0).is_none());
+        assert_eq!(receiver.pending_count(), 0);
+    }
+
+    #[test]
+    fn pending_limits() {
+        let senders = [1u8, 2, 3].map(|n| NostrPubkey::from([n; 32]));
+        let chunks = chunks();
+
+        let limits = NostrLimits {
+            max_pending: 2,
+            ..default!()
+        };
+        let mut receiver = NostrTransport::new(Relay::default()).with_limits(limits);
+        for (now, from) in senders.iter().enumerate() {
+            assert!(
+                receiver
+                    .accept_chunk(*from, &chunks[0], now as i64)
+                    .is_none()
+            );
+        }
+        assert_eq!(receiver.pending_count(), 2);
+        assert!(
+            !receiver
+                .pending
+                .contains_key(&(senders[0], chunks_id(&chunks[0])))
+        );
+
+        let limits = NostrLimits {
+            max_pending_size: 40,
+            ..default!()
+        };
+        let mut receiver = NostrTransport::new(Relay::default()).with_limits(limits);
+        assert!(receiver.accept_chunk(senders[0], &chunks[0], 0).is_none());
+        assert!(receiver.accept_chunk(senders[0], &chunks[1], 0).is_none());
+        assert!(receiver.accept_chunk(senders[1], &chunks[0], 1).is_none());
+        assert_eq!(receiver.pending_count(), 1);
+        assert_eq!(receiver.pending_size(), 16);
+        assert!(
+            receiver
+                .pending
+                .contains_key(&(senders[1], chunks_id(&chunks[0])))
+        );
+        assert!(receiver.accept_chunk(senders[1], &chunks[1], 1).is_none());
+        assert!(receiver.accept_chunk(senders[1], &chunks[2], 1).is_none());
+        assert_eq!(receiver.pending_count(), 0);
+    }
+
+    #[test]
+    fn pending_expiry() {
+        let alice = NostrPubkey::from([1u8; 32]);
+        let chunks = chunks();
+
+        let mut receiver = NostrTransport::new(Relay::default());
+        let timeout = receiver.limits().pending_timeout;
+        assert!(receiver.accept_chunk(alice, &chunks[0], 0).is_none());
+        receiver.expire_pending(timeout - 1);
+        assert_eq!(receiver.pending_count(), 1);
+        receiver.expire_pending(timeout);
+        assert_eq!(receiver.pending_count(), 0);
+
+        let limits = NostrLimits {
+            pending_timeout: 0,
+            ..default!()
+        };
+        let mut receiver = NostrTransport::new(Relay::default()).with_limits(limits);
+        receiver.client_mut().inbox = vec![(alice, chunks[0].clone())];
+        assert!(receiver.receive().unwrap().is_empty());
+        assert_eq!(receiver.pending_count(), 1);
+        assert!(receiver.receive().unwrap().is_empty());
+        assert_eq!(receiver.pending_count(), 0);
+    }
+}
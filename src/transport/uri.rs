@@ -0,0 +1,235 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `rgb:` URIs used by wallets and web pages for interoperating via clickable
+//! links. A URI may contain:
+//! - an invoice, in the format defined by [`RgbInvoice`];
+//! - a contract id, for "add asset" links: `rgb:<contract_id>`;
+//! - a location of a consignment to fetch: `rgb:csg:<consignment_id>?endpoints=<transport>,...`.
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use baid64::Baid64ParseError;
+use invoice::{InvoiceParseError, RgbInvoice, RgbTransport, TransportParseError};
+use rgb::ContractId;
+
+use crate::containers::ConsignmentId;
+
+const URI_SCHEME: &str = "rgb:";
+const CONSIGNMENT_PREFIX: &str = "rgb:csg:";
+const ENDPOINTS: &str = "endpoints";
+const ENDPOINT_SEP: char = ',';
+
+#[derive(Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum UriParseError {
+    /// URI doesn't use `rgb:` scheme.
+    InvalidScheme,
+
+    /// URI must not contain a fragment.
+    Fragment,
+
+    #[from]
+    #[display(inner)]
+    Invoice(InvoiceParseError),
+
+    #[from]
+    #[display(inner)]
+    Id(Baid64ParseError),
+
+    /// consignment location doesn't provide any endpoints.
+    NoEndpoints,
+
+    #[from]
+    #[display(inner)]
+    Endpoint(TransportParseError),
+
+    /// unknown URI query parameter '{0}'.
+    UnknownParam(String),
+
+    /// URI query parameter '{0}' is repeated.
+    RepeatedParam(String),
+}
+
+/// Location of a consignment which can be fetched by a wallet.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ConsignmentLocation {
+    pub consignment_id: ConsignmentId,
+    /// Endpoints providing the consignment, which must not be empty.
+    pub endpoints: Vec<RgbTransport>,
+}
+
+/// Data referenced by an `rgb:` URI.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Eq, PartialEq, Debug, From)]
+pub enum RgbUri {
+    #[from]
+    Invoice(RgbInvoice),
+
+    #[from]
+    Contract(ContractId),
+
+    #[from]
+    Consignment(ConsignmentLocation),
+}
+
+impl Display for RgbUri {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RgbUri::Invoice(invoice) => Display::fmt(invoice, f),
+            RgbUri::Contract(contract_id) => write!(f, "{contract_id}"),
+            RgbUri::Consignment(location) => {
+                // Mnemonic suffix is omitted since `#` starts URI fragment
+                write!(f, "{:#}?{ENDPOINTS}=", location.consignment_id)?;
+                for (index, endpoint) in location.endpoints.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "{ENDPOINT_SEP}")?;
+                    }
+                    write!(f, "{endpoint}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for RgbUri {
+    type Err = UriParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some(rest) = s.strip_prefix(URI_SCHEME) else {
+            return Err(UriParseError::InvalidScheme);
+        };
+        if s.starts_with(CONSIGNMENT_PREFIX) {
+            if s.contains('#') {
+                return Err(UriParseError::Fragment);
+            }
+            let (id, query) = s.split_once('?').ok_or(UriParseError::NoEndpoints)?;
+            let consignment_id = ConsignmentId::from_str(id)?;
+            let mut endpoints = None;
+            for param in query.split('&') {
+                match param.split_once('=') {
+                    Some((ENDPOINTS, _)) if endpoints.is_some() => {
+                        return Err(UriParseError::RepeatedParam(ENDPOINTS.to_owned()));
+                    }
+                    Some((ENDPOINTS, value)) => {
+                        endpoints = Some(
+                            value
+                                .split(ENDPOINT_SEP)
+                                .filter(|endpoint| !endpoint.is_empty())
+                                .map(RgbTransport::from_str)
+                                .collect::<Result<Vec<_>, _>>()?,
+                        );
+                    }
+                    Some((key, _)) => return Err(UriParseError::UnknownParam(key.to_owned())),
+                    None => return Err(UriParseError::UnknownParam(param.to_owned())),
+                }
+            }
+            let endpoints = endpoints.unwrap_or_default();
+            if endpoints.is_empty() {
+                return Err(UriParseError::NoEndpoints);
+            }
+            return Ok(RgbUri::Consignment(ConsignmentLocation {
+                consignment_id,
+                endpoints,
+            }));
+        }
+        if rest.contains('/') {
+            return RgbInvoice::from_str(s)
+                .map(RgbUri::Invoice)
+                .map_err(From::from);
+        }
+        if s.contains('#') {
+            return Err(UriParseError::Fragment);
+        }
+        Ok(RgbUri::Contract(ContractId::from_str(s)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let contract_id =
+            ContractId::from_str("rgb:11Fa!$Dk-rUWXhy8-7H35qXm-pLGGLOo-txBWUgj-tbOaSbI").unwrap();
+        let uri = RgbUri::from_str(&contract_id.to_string()).unwrap();
+        assert_eq!(uri, RgbUri::Contract(contract_id));
+        assert_eq!(uri.to_string(), contract_id.to_string());
+
+        let s = "rgb:11Fa!$Dk-rUWXhy8-7H35qXm-pLGGLOo-txBWUgj-tbOaSbI/RGB20/BF+bc:utxob:\
+                 zlVS28Rb-amM5lih-ONXGACC-IUWD0Y$-0JXcnWZ-MQn8VEI-B39!F";
+        let uri = RgbUri::from_str(s).unwrap();
+        assert!(
+            matches!(uri, RgbUri::Invoice(ref invoice) if invoice.contract == Some(contract_id))
+        );
+        assert_eq!(uri.to_string(), s);
+
+        let location = ConsignmentLocation {
+            consignment_id: ConsignmentId::from_array([7u8; 32]),
+            endpoints: vec![
+                RgbTransport::RestHttp {
+                    tls: true,
+                    host: s!("proxy.example.com/json-rpc"),
+                },
+                RgbTransport::WebSockets {
+                    tls: false,
+                    host: s!("127.0.0.1:8080"),
+                },
+            ],
+        };
+        let uri = RgbUri::Consignment(location.clone());
+        let s = uri.to_string();
+        assert!(s.starts_with(CONSIGNMENT_PREFIX));
+        assert!(!s.contains('#'));
+        assert_eq!(RgbUri::from_str(&s).unwrap(), uri);
+    }
+
+    #[test]
+    fn invalid_uris() {
+        let id = format!("{:#}", ConsignmentId::from_array([7u8; 32]));
+        assert!(matches!(RgbUri::from_str("bitcoin:bc1q"), Err(UriParseError::InvalidScheme)));
+        assert!(matches!(RgbUri::from_str(&id), Err(UriParseError::NoEndpoints)));
+        assert!(matches!(
+            RgbUri::from_str(&format!("{id}?endpoints=")),
+            Err(UriParseError::NoEndpoints)
+        ));
+        assert!(matches!(
+            RgbUri::from_str(&format!("{id}?endpoints=ftp://host")),
+            Err(UriParseError::Endpoint(_))
+        ));
+        assert!(matches!(
+            RgbUri::from_str(&format!("{id}?endpoints=https://a&expiry=1")),
+            Err(UriParseError::UnknownParam(param)) if param == "expiry"
+        ));
+        assert!(matches!(
+            RgbUri::from_str(&format!("{id}?endpoints=https://a&endpoints=https://b")),
+            Err(UriParseError::RepeatedParam(_))
+        ));
+        assert!(matches!(
+            RgbUri::from_str("rgb:11Fa!$Dk-rUWXhy8-7H35qXm-pLGGLOo-txBWUgj-tbOaSbI#x"),
+            Err(UriParseError::Fragment)
+        ));
+        assert!(matches!(RgbUri::from_str("rgb:invalid"), Err(UriParseError::Id(_))));
+    }
+}
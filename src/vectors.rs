@@ -0,0 +1,153 @@
+// RGB standard library for working with smart contracts on Bitcoin & Lightning
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2019-2024 by
+//     Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+//
+// Copyright (C) 2019-2024 LNP/BP Standards Association. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic test vectors for the commitments used by RGB containers.
+//!
+//! Each vector provides strict-serialized input data and the identifier
+//! produced by committing to it. Alternative implementations may use the
+//! vectors to verify compatibility of their commitment procedures
+//! byte-for-byte; the same vectors are kept in `asset/test_vectors.txt` to
+//! detect accidental changes to the commitments in this crate.
+
+use std::fmt::{self, Display, Formatter};
+
+use amplify::hex::ToHex;
+use commit_verify::mpc::MerkleProof;
+use commit_verify::CommitId;
+use rgb::{Genesis, Identity, Operation, TransitionBundle};
+use strict_encoding::{StrictDumb, StrictEncode, StrictWriter};
+
+use crate::containers::{Contract, Transfer, WitnessBundle};
+
+/// Test vector for a commitment.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TestVector {
+    /// Name of the test vector.
+    pub name: &'static str,
+    /// Name of the commitment type.
+    pub commitment: &'static str,
+    /// Hex-encoded strict serialization of the committed data.
+    pub input: String,
+    /// Resulting commitment, in its standard string representation.
+    pub id: String,
+}
+
+impl Display for TestVector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.name, self.commitment, self.id, self.input)
+    }
+}
+
+impl TestVector {
+    fn with(
+        name: &'static str,
+        commitment: &'static str,
+        input: &impl StrictEncode,
+        id: impl Display,
+    ) -> Self {
+        let writer = StrictWriter::in_memory::<{ usize::MAX }>();
+        let input = input
+            .strict_encode(writer)
+            .expect("in-memory encoding")
+            .unbox()
+            .unconfine()
+            .to_hex();
+        TestVector {
+            name,
+            commitment,
+            input,
+            id: id.to_string(),
+        }
+    }
+}
+
+/// Produces test vectors for contract ids, bundle ids, disclose hashes of
+/// transition bundles and witness bundles and for consignment (contract and
+/// transfer) ids from fixed inputs.
+pub fn test_vectors() -> Vec<TestVector> {
+    let mut vectors = vec![];
+
+    let genesis = Genesis::strict_dumb();
+    vectors.push(TestVector::with("genesis-dumb", "ContractId", &genesis, genesis.contract_id()));
+
+    let mut genesis = Genesis::strict_dumb();
+    genesis.timestamp = 1231006505;
+    genesis.testnet = true;
+    genesis.issuer = Identity::from("ssi:anonymous");
+    vectors.push(TestVector::with(
+        "genesis-testnet",
+        "ContractId",
+        &genesis,
+        genesis.contract_id(),
+    ));
+
+    let bundle = TransitionBundle::strict_dumb();
+    vectors.push(TestVector::with("bundle-dumb", "BundleId", &bundle, bundle.bundle_id()));
+    vectors.push(TestVector::with(
+        "bundle-dumb",
+        "DiscloseHash",
+        &bundle.disclose(),
+        bundle.disclose_hash(),
+    ));
+
+    let witness_bundle = WitnessBundle::<MerkleProof>::strict_dumb();
+    vectors.push(TestVector::with(
+        "witness-bundle-dumb",
+        "DiscloseHash",
+        &witness_bundle,
+        witness_bundle.commit_id(),
+    ));
+
+    let contract = Contract::strict_dumb();
+    vectors.push(TestVector::with(
+        "contract-dumb",
+        "ConsignmentId",
+        &contract,
+        contract.consignment_id(),
+    ));
+
+    let mut transfer = Transfer::strict_dumb();
+    transfer.transfer = true;
+    transfer.genesis = genesis;
+    vectors.push(TestVector::with(
+        "transfer-dumb",
+        "ConsignmentId",
+        &transfer,
+        transfer.consignment_id(),
+    ));
+
+    vectors
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_vectors_fixture() {
+        let fixture = include_str!("../asset/test_vectors.txt").replace('\r', "");
+        let vectors = test_vectors()
+            .iter()
+            .map(TestVector::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(fixture.lines().collect::<Vec<_>>(), vectors);
+    }
+}
@@ -26,11 +26,9 @@ use std::io::Write;
 use commit_verify::CommitmentLayout;
 use rgbstd::containers::Transfer;
 use rgbstd::stl::{
-    aluvm_stl, bp_core_stl, bp_tx_stl, commit_verify_stl, rgb_commit_stl, rgb_contract_stl,
-    rgb_logic_stl, rgb_std_stl, rgb_storage_stl,
+    rgb_containers_sys, rgb_contract_stl, rgb_std_stl, rgb_storage_stl, CONTAINER_TYPES,
 };
-use strict_types::stl::{std_stl, strict_types_stl};
-use strict_types::{parse_args, StlFormat, SystemBuilder};
+use strict_types::{parse_args, StlFormat};
 
 fn main() {
     let (_, dir) = parse_args();
@@ -102,37 +100,24 @@ fn main() {
         )
         .expect("unable to write to the file");
 
-    let std = std_stl();
-    let rgb = rgb_std_stl();
-    let rgb_commit = rgb_commit_stl();
-    let rgb_logic = rgb_logic_stl();
-    let tx = bp_tx_stl();
-    let bp = bp_core_stl();
-    let cv = commit_verify_stl();
-    let st = strict_types_stl();
-    let vm = aluvm_stl();
+    let sys = rgb_containers_sys();
 
-    let sys = SystemBuilder::new()
-        .import(rgb)
-        .unwrap()
-        .import(rgb_logic)
-        .unwrap()
-        .import(rgb_commit)
-        .unwrap()
-        .import(vm)
-        .unwrap()
-        .import(bp)
-        .unwrap()
-        .import(tx)
-        .unwrap()
-        .import(cv)
-        .unwrap()
-        .import(st)
-        .unwrap()
-        .import(std)
-        .unwrap()
-        .finalize()
-        .expect("not all libraries present");
+    let mut file = fs::File::create(format!("{dir}/Containers.sts")).unwrap();
+    writeln!(
+        file,
+        "{{-
+  Description: RGB container formats
+  Author: Dr Maxim Orlovsky <orlovsky@lnp-bp.org>
+  Copyright (C) 2024 LNP/BP Standards Association. All rights reserved.
+  License: Apache-2.0
+-}}
+"
+    )
+    .unwrap();
+    for (container, name) in CONTAINER_TYPES {
+        writeln!(file, "-- {container}: {name}").unwrap();
+    }
+    writeln!(file, "{sys}").unwrap();
 
     let mut file = fs::File::create(format!("{dir}/Transfer.vesper")).unwrap();
     writeln!(
@@ -148,7 +133,7 @@ Seals vesper lexicon=types+commitments
 "
     )
     .unwrap();
-    let layout = Transfer::commitment_layout();
+    let layout = <Transfer as CommitmentLayout>::commitment_layout();
     writeln!(file, "{layout}").unwrap();
     let tt = sys.type_tree("RGBStd.Consignmenttrue").unwrap();
     writeln!(file, "{tt}").unwrap();